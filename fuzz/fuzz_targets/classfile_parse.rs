@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libjava::classfile::ClassFile;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ClassFile::from_bytes(data);
+    let _ = ClassFile::parse_bytes(data);
+});
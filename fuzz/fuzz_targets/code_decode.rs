@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libjvm::vm::code::Code;
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte selects an additional entry point, like an exception
+    // handler would
+    if let Some((entry_point, code)) = data.split_first() {
+        let _ = Code::decode(code, &[*entry_point as usize]);
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libjava::bytecode::Op;
+
+fuzz_target!(|data: &[u8]| {
+    let mut source = data;
+    let mut offset = 0;
+    while !source.is_empty() {
+        let remaining = source.len();
+        if Op::parse_at(&mut source, offset).is_err() {
+            break;
+        }
+        offset += remaining - source.len();
+    }
+});
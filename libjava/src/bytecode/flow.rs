@@ -0,0 +1,126 @@
+use crate::bytecode::Op;
+use crate::prelude::*;
+
+impl Op {
+    /// The offsets that this op, at the given offset, may jump to, except for
+    /// the next instruction. Targets before the start of the code are left
+    /// out.
+    pub fn branch_targets(&self, offset: usize) -> Vec<usize> {
+        let relative: Vec<isize> = match self {
+            Op::TableSwitch {
+                default, offsets, ..
+            } => core::iter::once(default)
+                .chain(offsets)
+                .map(|o| *o as isize)
+                .collect(),
+            Op::LookupSwitch { default, npairs } => core::iter::once(*default as i32)
+                .chain(npairs.iter().map(|(_, o)| *o))
+                .map(|o| o as isize)
+                .collect(),
+            op => op.branch_offset().into_iter().collect(),
+        };
+        relative
+            .into_iter()
+            .filter_map(|r| offset.checked_add_signed(r))
+            .collect()
+    }
+
+    /// Whether this op jumps or may jump, i.e. whether it is a branch, a
+    /// `goto`, a `jsr` or a switch.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Op::TableSwitch { .. } | Op::LookupSwitch { .. })
+            || self.branch_offset().is_some()
+    }
+
+    /// Whether execution may continue with the next instruction after this
+    /// op. `jsr` falls through, since its subroutine returns to the next
+    /// instruction with `ret`.
+    pub fn falls_through(&self) -> bool {
+        !matches!(
+            self,
+            Op::Goto(_)
+                | Op::GotoW(_)
+                | Op::TableSwitch { .. }
+                | Op::LookupSwitch { .. }
+                | Op::Ret(_)
+                | Op::Return
+                | Op::AReturn
+                | Op::IReturn
+                | Op::LReturn
+                | Op::FReturn
+                | Op::DReturn
+                | Op::AThrow
+        )
+    }
+
+    /// Whether this op is the last one of a basic block, because it may
+    /// continue anywhere but the next instruction.
+    pub fn ends_block(&self) -> bool {
+        self.is_branch() || !self.falls_through()
+    }
+
+    /// The relative offset of a branch, `goto` or `jsr`.
+    fn branch_offset(&self) -> Option<isize> {
+        let relative = match self {
+            Op::Goto(o)
+            | Op::Jsr(o)
+            | Op::IfACmpEq(o)
+            | Op::IfACmpNe(o)
+            | Op::IfICmpEq(o)
+            | Op::IfICmpNe(o)
+            | Op::IfICmpLt(o)
+            | Op::IfICmpGe(o)
+            | Op::IfICmpGt(o)
+            | Op::IfICmpLe(o)
+            | Op::IfEq(o)
+            | Op::IfNe(o)
+            | Op::IfLt(o)
+            | Op::IfGe(o)
+            | Op::IfGt(o)
+            | Op::IfLe(o)
+            | Op::IfNonNull(o)
+            | Op::IfNull(o) => *o as i16 as isize,
+            Op::GotoW(o) | Op::JsrW(o) => *o as i32 as isize,
+            _ => return None,
+        };
+        Some(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch_targets() {
+        // -4 as u16
+        assert_eq!(vec![6], Op::Goto(0xFFFC).branch_targets(10));
+        assert_eq!(Vec::<usize>::new(), Op::Goto(0xFFFC).branch_targets(2));
+        assert_eq!(vec![13], Op::IfNull(3).branch_targets(10));
+        assert_eq!(
+            vec![30, 14, 18],
+            Op::LookupSwitch {
+                default: 20,
+                npairs: vec![(1, 4), (5, 8)],
+            }
+            .branch_targets(10)
+        );
+        assert_eq!(Vec::<usize>::new(), Op::IAdd.branch_targets(10));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        for (op, is_branch, falls_through) in [
+            (Op::IAdd, false, true),
+            (Op::IfEq(3), true, true),
+            (Op::Jsr(3), true, true),
+            (Op::Goto(3), true, false),
+            (Op::Ret(1), false, false),
+            (Op::AThrow, false, false),
+        ] {
+            assert_eq!(is_branch, op.is_branch(), "{:?}", op);
+            assert_eq!(falls_through, op.falls_through(), "{:?}", op);
+            assert_eq!(is_branch || !falls_through, op.ends_block(), "{:?}", op);
+        }
+    }
+}
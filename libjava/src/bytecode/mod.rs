@@ -1,11 +1,26 @@
-use std::io::Read;
+use crate::io::Read;
+use crate::prelude::*;
 
+mod flow;
+mod write;
+
+pub use write::assemble;
+
+#[derive(Debug, Eq, PartialEq)]
 pub enum OpParseError {
     UnexpectedEOF,
     InvalidByteCode,
+    InvalidArrayType,
+    InvalidSwitch,
 }
 
+/// The maximum number of switch entries that are allocated up front, no matter
+/// how many entries the instruction claims to have.
+const MAX_INITIAL_CAPACITY: usize = 256;
+
 #[repr(u8)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Op {
     AALoad,
     AAStore,
@@ -181,10 +196,16 @@ pub enum Op {
         offsets: Vec<i32>,
     },
     Wide,
+    /// Reserved for debuggers, and must not appear in a class file
+    /// ([`$6.2`]), so it is never parsed.
+    ///
+    /// [`$6.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.2
     Breakpoint,
 }
 
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AType {
     TBoolean = 4,
     TChar = 5,
@@ -237,11 +258,373 @@ macro_rules! read_i32 {
 }
 
 impl Op {
+    /// The mnemonic of the op as used by the specification ([`$6.5`]) and
+    /// `javap`, e.g. `iconst_m1` or `invokevirtual`. Ops with a short form,
+    /// like `aload_0`, are parsed into their general form, so this is the
+    /// mnemonic of the general form for them.
+    ///
+    /// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Op::AALoad => "aaload",
+            Op::AAStore => "aastore",
+            Op::AConstNull => "aconstnull",
+            Op::ALoad(..) => "aload",
+            Op::ANewArray(..) => "anewarray",
+            Op::AReturn => "areturn",
+            Op::ArrayLength => "arraylength",
+            Op::AStore(..) => "astore",
+            Op::AThrow => "athrow",
+            Op::BALoad => "baload",
+            Op::BAStore => "bastore",
+            Op::BIPush(..) => "bipush",
+            Op::CALoad => "caload",
+            Op::CAStore => "castore",
+            Op::CheckCast(..) => "checkcast",
+            Op::D2F => "d2f",
+            Op::D2I => "d2i",
+            Op::D2L => "d2l",
+            Op::DAdd => "dadd",
+            Op::DALoad => "daload",
+            Op::DAStore => "dastore",
+            Op::DCmpG => "dcmpg",
+            Op::DCmpL => "dcmpl",
+            Op::DConst0 => "dconst_0",
+            Op::DConst1 => "dconst_1",
+            Op::DDiv => "ddiv",
+            Op::DLoad(..) => "dload",
+            Op::DLoad0 => "dload_0",
+            Op::DLoad1 => "dload_1",
+            Op::DLoad2 => "dload_2",
+            Op::DLoad3 => "dload_3",
+            Op::DMul => "dmul",
+            Op::DNeg => "dneg",
+            Op::DRem => "drem",
+            Op::DReturn => "dreturn",
+            Op::DStore(..) => "dstore",
+            Op::DSub => "dsub",
+            Op::Dup => "dup",
+            Op::DupX1 => "dup_x1",
+            Op::DupX2 => "dup_x2",
+            Op::Dup2 => "dup2",
+            Op::Dup2X1 => "dup2_x1",
+            Op::Dup2X2 => "dup2_x2",
+            Op::F2D => "f2d",
+            Op::F2I => "f2i",
+            Op::F2L => "f2l",
+            Op::FAdd => "fadd",
+            Op::FALoad => "faload",
+            Op::FAStore => "fastore",
+            Op::FCmpG => "fcmpg",
+            Op::FCmpL => "fcmpl",
+            Op::FConst0 => "fconst_0",
+            Op::FConst1 => "fconst_1",
+            Op::FConst2 => "fconst_2",
+            Op::FDiv => "fdiv",
+            Op::FLoad(..) => "fload",
+            Op::FMul => "fmul",
+            Op::FNeg => "fneg",
+            Op::FRem => "frem",
+            Op::FReturn => "freturn",
+            Op::FStore(..) => "fstore",
+            Op::FSub => "fsub",
+            Op::GetField(..) => "getfield",
+            Op::GetStatic(..) => "getstatic",
+            Op::Goto(..) => "goto",
+            Op::GotoW(..) => "goto_w",
+            Op::I2B => "i2b",
+            Op::I2C => "i2c",
+            Op::I2D => "i2d",
+            Op::I2F => "i2f",
+            Op::I2L => "i2l",
+            Op::I2S => "i2s",
+            Op::IAdd => "iadd",
+            Op::IALoad => "iaload",
+            Op::IAnd => "iand",
+            Op::IAStore => "iastore",
+            Op::IConstM1 => "iconst_m1",
+            Op::IConst0 => "iconst_0",
+            Op::IConst1 => "iconst_1",
+            Op::IConst2 => "iconst_2",
+            Op::IConst3 => "iconst_3",
+            Op::IConst4 => "iconst_4",
+            Op::IConst5 => "iconst_5",
+            Op::IDiv => "idiv",
+            Op::IfACmpEq(..) => "if_acmpeq",
+            Op::IfACmpNe(..) => "if_acmpne",
+            Op::IfICmpEq(..) => "if_icmpeq",
+            Op::IfICmpNe(..) => "if_icmpne",
+            Op::IfICmpLt(..) => "if_icmplt",
+            Op::IfICmpGe(..) => "if_icmpge",
+            Op::IfICmpGt(..) => "if_icmpgt",
+            Op::IfICmpLe(..) => "if_icmple",
+            Op::IfEq(..) => "ifeq",
+            Op::IfNe(..) => "ifne",
+            Op::IfLt(..) => "iflt",
+            Op::IfGe(..) => "ifge",
+            Op::IfGt(..) => "ifgt",
+            Op::IfLe(..) => "ifle",
+            Op::IfNonNull(..) => "ifnonnull",
+            Op::IfNull(..) => "ifnull",
+            Op::IInc(..) => "iinc",
+            Op::ILoad(..) => "iload",
+            Op::IMul => "imul",
+            Op::INeg => "ineg",
+            Op::InstanceOf(..) => "instanceof",
+            Op::InvokeDynamic(..) => "invokedynamic",
+            Op::InvokeInterface(..) => "invokeinterface",
+            Op::InvokeSpecial(..) => "invokespecial",
+            Op::InvokeStatic(..) => "invokestatic",
+            Op::InvokeVirtual(..) => "invokevirtual",
+            Op::IOr => "ior",
+            Op::IRem => "irem",
+            Op::IReturn => "ireturn",
+            Op::IShl => "ishl",
+            Op::IShr => "ishr",
+            Op::IStore(..) => "istore",
+            Op::ISub => "isub",
+            Op::IUShr => "iushr",
+            Op::IXor => "ixor",
+            Op::Jsr(..) => "jsr",
+            Op::JsrW(..) => "jsr_w",
+            Op::L2D => "l2d",
+            Op::L2F => "l2f",
+            Op::L2I => "l2i",
+            Op::LAdd => "ladd",
+            Op::LALoad => "laload",
+            Op::LAnd => "land",
+            Op::LAStore => "lastore",
+            Op::LCmp => "lcmp",
+            Op::LConst0 => "lconst_0",
+            Op::LConst1 => "lconst_1",
+            Op::LDC(..) => "ldc",
+            Op::LDCW(..) => "ldc_w",
+            Op::LDC2W(..) => "ldc2_w",
+            Op::LDiv => "ldiv",
+            Op::LLoad(..) => "lload",
+            Op::LMul => "lmul",
+            Op::LNeg => "lneg",
+            Op::LookupSwitch { .. } => "lookupswitch",
+            Op::LOr => "lor",
+            Op::LRem => "lrem",
+            Op::LReturn => "lreturn",
+            Op::LShl => "lshl",
+            Op::LShr => "lshr",
+            Op::LStore(..) => "lstore",
+            Op::LSub => "lsub",
+            Op::LUShr => "lushr",
+            Op::LXor => "lxor",
+            Op::MonitorEnter => "monitorenter",
+            Op::MonitorExit => "monitorexit",
+            Op::MultiANewArray(..) => "multianewarray",
+            Op::New(..) => "new",
+            Op::NewArray(..) => "newarray",
+            Op::Nop => "nop",
+            Op::Pop => "pop",
+            Op::Pop2 => "pop2",
+            Op::PutField(..) => "putfield",
+            Op::PutStatic(..) => "putstatic",
+            Op::Ret(..) => "ret",
+            Op::Return => "return",
+            Op::SALoad => "saload",
+            Op::SAStore => "sastore",
+            Op::SIPush(..) => "sipush",
+            Op::Swap => "swap",
+            Op::TableSwitch { .. } => "tableswitch",
+            Op::Wide => "wide",
+            Op::Breakpoint => "breakpoint",
+        }
+    }
+
+    /// The opcode of the op ([`$6.5`]). Ops with a short form, like
+    /// `aload_0`, are parsed into their general form, so this is the opcode of
+    /// the general form for them, see [`Op::write`] for the short forms.
+    ///
+    /// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Op::AALoad => 0x32,
+            Op::AAStore => 0x53,
+            Op::AConstNull => 0x01,
+            Op::ALoad(..) => 0x19,
+            Op::ANewArray(..) => 0xBD,
+            Op::AReturn => 0xB0,
+            Op::ArrayLength => 0xBE,
+            Op::AStore(..) => 0x3A,
+            Op::AThrow => 0xBF,
+            Op::BALoad => 0x33,
+            Op::BAStore => 0x54,
+            Op::BIPush(..) => 0x10,
+            Op::CALoad => 0x34,
+            Op::CAStore => 0x55,
+            Op::CheckCast(..) => 0xC0,
+            Op::D2F => 0x90,
+            Op::D2I => 0x8E,
+            Op::D2L => 0x8F,
+            Op::DAdd => 0x63,
+            Op::DALoad => 0x31,
+            Op::DAStore => 0x52,
+            Op::DCmpG => 0x98,
+            Op::DCmpL => 0x97,
+            Op::DConst0 => 0x0E,
+            Op::DConst1 => 0x0F,
+            Op::DDiv => 0x6F,
+            Op::DLoad(..) => 0x18,
+            Op::DLoad0 => 0x26,
+            Op::DLoad1 => 0x27,
+            Op::DLoad2 => 0x28,
+            Op::DLoad3 => 0x29,
+            Op::DMul => 0x6B,
+            Op::DNeg => 0x77,
+            Op::DRem => 0x73,
+            Op::DReturn => 0xAF,
+            Op::DStore(..) => 0x39,
+            Op::DSub => 0x67,
+            Op::Dup => 0x59,
+            Op::DupX1 => 0x5A,
+            Op::DupX2 => 0x5B,
+            Op::Dup2 => 0x5C,
+            Op::Dup2X1 => 0x5D,
+            Op::Dup2X2 => 0x5E,
+            Op::F2D => 0x8D,
+            Op::F2I => 0x8B,
+            Op::F2L => 0x8C,
+            Op::FAdd => 0x62,
+            Op::FALoad => 0x30,
+            Op::FAStore => 0x51,
+            Op::FCmpG => 0x96,
+            Op::FCmpL => 0x95,
+            Op::FConst0 => 0x0B,
+            Op::FConst1 => 0x0C,
+            Op::FConst2 => 0x0D,
+            Op::FDiv => 0x6E,
+            Op::FLoad(..) => 0x17,
+            Op::FMul => 0x6A,
+            Op::FNeg => 0x76,
+            Op::FRem => 0x72,
+            Op::FReturn => 0xAE,
+            Op::FStore(..) => 0x38,
+            Op::FSub => 0x66,
+            Op::GetField(..) => 0xB4,
+            Op::GetStatic(..) => 0xB2,
+            Op::Goto(..) => 0xA7,
+            Op::GotoW(..) => 0xC8,
+            Op::I2B => 0x91,
+            Op::I2C => 0x92,
+            Op::I2D => 0x87,
+            Op::I2F => 0x86,
+            Op::I2L => 0x85,
+            Op::I2S => 0x93,
+            Op::IAdd => 0x60,
+            Op::IALoad => 0x2E,
+            Op::IAnd => 0x7E,
+            Op::IAStore => 0x4F,
+            Op::IConstM1 => 0x02,
+            Op::IConst0 => 0x03,
+            Op::IConst1 => 0x04,
+            Op::IConst2 => 0x05,
+            Op::IConst3 => 0x06,
+            Op::IConst4 => 0x07,
+            Op::IConst5 => 0x08,
+            Op::IDiv => 0x6C,
+            Op::IfACmpEq(..) => 0xA5,
+            Op::IfACmpNe(..) => 0xA6,
+            Op::IfICmpEq(..) => 0x9F,
+            Op::IfICmpNe(..) => 0xA0,
+            Op::IfICmpLt(..) => 0xA1,
+            Op::IfICmpGe(..) => 0xA2,
+            Op::IfICmpGt(..) => 0xA3,
+            Op::IfICmpLe(..) => 0xA4,
+            Op::IfEq(..) => 0x99,
+            Op::IfNe(..) => 0x9A,
+            Op::IfLt(..) => 0x9B,
+            Op::IfGe(..) => 0x9C,
+            Op::IfGt(..) => 0x9D,
+            Op::IfLe(..) => 0x9E,
+            Op::IfNonNull(..) => 0xC7,
+            Op::IfNull(..) => 0xC6,
+            Op::IInc(..) => 0x84,
+            Op::ILoad(..) => 0x15,
+            Op::IMul => 0x68,
+            Op::INeg => 0x74,
+            Op::InstanceOf(..) => 0xC1,
+            Op::InvokeDynamic(..) => 0xBA,
+            Op::InvokeInterface(..) => 0xB9,
+            Op::InvokeSpecial(..) => 0xB7,
+            Op::InvokeStatic(..) => 0xB8,
+            Op::InvokeVirtual(..) => 0xB6,
+            Op::IOr => 0x80,
+            Op::IRem => 0x70,
+            Op::IReturn => 0xAC,
+            Op::IShl => 0x78,
+            Op::IShr => 0x7A,
+            Op::IStore(..) => 0x36,
+            Op::ISub => 0x64,
+            Op::IUShr => 0x7C,
+            Op::IXor => 0x82,
+            Op::Jsr(..) => 0xA8,
+            Op::JsrW(..) => 0xC9,
+            Op::L2D => 0x8A,
+            Op::L2F => 0x89,
+            Op::L2I => 0x88,
+            Op::LAdd => 0x61,
+            Op::LALoad => 0x2F,
+            Op::LAnd => 0x7F,
+            Op::LAStore => 0x50,
+            Op::LCmp => 0x94,
+            Op::LConst0 => 0x09,
+            Op::LConst1 => 0x0A,
+            Op::LDC(..) => 0x12,
+            Op::LDCW(..) => 0x13,
+            Op::LDC2W(..) => 0x14,
+            Op::LDiv => 0x6D,
+            Op::LLoad(..) => 0x16,
+            Op::LMul => 0x69,
+            Op::LNeg => 0x75,
+            Op::LookupSwitch { .. } => 0xAB,
+            Op::LOr => 0x81,
+            Op::LRem => 0x71,
+            Op::LReturn => 0xAD,
+            Op::LShl => 0x79,
+            Op::LShr => 0x7B,
+            Op::LStore(..) => 0x37,
+            Op::LSub => 0x65,
+            Op::LUShr => 0x7D,
+            Op::LXor => 0x83,
+            Op::MonitorEnter => 0xC2,
+            Op::MonitorExit => 0xC3,
+            Op::MultiANewArray(..) => 0xC5,
+            Op::New(..) => 0xBB,
+            Op::NewArray(..) => 0xBC,
+            Op::Nop => 0x00,
+            Op::Pop => 0x57,
+            Op::Pop2 => 0x58,
+            Op::PutField(..) => 0xB5,
+            Op::PutStatic(..) => 0xB3,
+            Op::Ret(..) => 0xA9,
+            Op::Return => 0xB1,
+            Op::SALoad => 0x35,
+            Op::SAStore => 0x56,
+            Op::SIPush(..) => 0x11,
+            Op::Swap => 0x5F,
+            Op::TableSwitch { .. } => 0xAA,
+            Op::Wide => 0xC4,
+            Op::Breakpoint => 0xCA,
+        }
+    }
+
+    /// Parses an op that starts at a 4-byte aligned offset in the code. Use
+    /// [`Op::parse_at`] if the offset is known, since `tableswitch` and
+    /// `lookupswitch` are padded relative to it.
     pub fn parse(source: &mut impl Read) -> Result<Op, OpParseError> {
-        let mut opcode = [0_u8; 1];
-        source.read_exact(&mut opcode).unwrap();
+        Self::parse_at(source, 0)
+    }
+
+    /// Parses an op that starts at the given offset in the code of its method.
+    pub fn parse_at(source: &mut impl Read, offset: usize) -> Result<Op, OpParseError> {
+        let opcode = read_u8!(source);
 
-        Ok(match opcode[0] {
+        Ok(match opcode {
             0x32 => Op::AALoad,
             0x53 => Op::AAStore,
             0x01 => Op::AConstNull,
@@ -371,8 +754,19 @@ impl Op {
             0x68 => Op::IMul,
             0x74 => Op::INeg,
             0xC1 => Op::InstanceOf(read_u16!(source)),
-            0xBA => Op::InvokeDynamic(read_u16!(source)),
-            0xB9 => Op::InvokeInterface(read_u16!(source), read_u8!(source)),
+            0xBA => {
+                let index = read_u16!(source);
+                // two bytes that are always zero
+                let _ = read_u16!(source);
+                Op::InvokeDynamic(index)
+            }
+            0xB9 => {
+                let index = read_u16!(source);
+                let count = read_u8!(source);
+                // a byte that is always zero
+                let _ = read_u8!(source);
+                Op::InvokeInterface(index, count)
+            }
             0xB7 => Op::InvokeSpecial(read_u16!(source)),
             0xB8 => Op::InvokeStatic(read_u16!(source)),
             0xB6 => Op::InvokeVirtual(read_u16!(source)),
@@ -413,10 +807,14 @@ impl Op {
             0x69 => Op::LMul,
             0x75 => Op::LNeg,
             0xAB => {
-                todo!("padding");
+                skip_padding(source, offset)?;
                 let default = read_u32!(source);
-                let npairs_count = read_u32!(source);
-                let mut npairs = Vec::with_capacity(npairs_count as usize);
+                let npairs_count = read_i32!(source);
+                if npairs_count < 0 {
+                    return Err(OpParseError::InvalidSwitch);
+                }
+                let mut npairs =
+                    Vec::with_capacity((npairs_count as usize).min(MAX_INITIAL_CAPACITY));
                 for _ in 0..npairs_count {
                     npairs.push((read_i32!(source), read_i32!(source)));
                 }
@@ -448,7 +846,7 @@ impl Op {
                 9 => AType::TShort,
                 10 => AType::TInt,
                 11 => AType::TLong,
-                _ => panic!("invalid atype"),
+                _ => return Err(OpParseError::InvalidArrayType),
             }),
             0x00 => Op::Nop,
             0x57 => Op::Pop,
@@ -462,12 +860,16 @@ impl Op {
             0x11 => Op::SIPush(read_u16!(source)),
             0x5F => Op::Swap,
             0xAA => {
-                todo!("padding");
+                skip_padding(source, offset)?;
                 let default = read_i32!(source);
                 let low = read_i32!(source);
                 let high = read_i32!(source);
-                let mut offsets = Vec::with_capacity((high - low + 1) as usize);
-                for _ in 0..high - low + 1 {
+                if low > high {
+                    return Err(OpParseError::InvalidSwitch);
+                }
+                let count = high as i64 - low as i64 + 1;
+                let mut offsets = Vec::with_capacity((count as usize).min(MAX_INITIAL_CAPACITY));
+                for _ in 0..count {
                     offsets.push(read_i32!(source));
                 }
                 Op::TableSwitch {
@@ -478,8 +880,373 @@ impl Op {
                 }
             }
             0xC4 => Op::Wide,
-            0xCA => Op::Breakpoint,
             _ => return Err(OpParseError::InvalidByteCode),
         })
     }
 }
+
+/// Parses all ops of the code of a method, the inverse of [`assemble`].
+pub fn decode(code: &[u8]) -> Result<Vec<Op>, OpParseError> {
+    let mut source = code;
+    let mut ops = vec![];
+    while !source.is_empty() {
+        let offset = code.len() - source.len();
+        ops.push(Op::parse_at(&mut source, offset)?);
+    }
+    Ok(ops)
+}
+
+/// Skips the 0-3 bytes after the opcode of a switch at the given offset, so
+/// that its operands start at a multiple of 4 from the start of the code.
+fn skip_padding(source: &mut impl Read, offset: usize) -> Result<(), OpParseError> {
+    let padding = (4 - (offset + 1) % 4) % 4;
+    for _ in 0..padding {
+        let _ = read_u8!(source);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// The ops without operands. `wide` is left out of the generated ops: it
+    /// is parsed as an op of its own, followed by the op that it modifies with
+    /// narrow operands, so code with it doesn't round-trip yet.
+    const SIMPLE_OPS: &[Op] = &[
+        Op::AALoad,
+        Op::AAStore,
+        Op::AConstNull,
+        Op::AReturn,
+        Op::ArrayLength,
+        Op::AThrow,
+        Op::BALoad,
+        Op::BAStore,
+        Op::CALoad,
+        Op::CAStore,
+        Op::D2F,
+        Op::D2I,
+        Op::D2L,
+        Op::DAdd,
+        Op::DALoad,
+        Op::DAStore,
+        Op::DCmpG,
+        Op::DCmpL,
+        Op::DConst0,
+        Op::DConst1,
+        Op::DDiv,
+        Op::DMul,
+        Op::DNeg,
+        Op::DRem,
+        Op::DReturn,
+        Op::DSub,
+        Op::Dup,
+        Op::DupX1,
+        Op::DupX2,
+        Op::Dup2,
+        Op::Dup2X1,
+        Op::Dup2X2,
+        Op::F2D,
+        Op::F2I,
+        Op::F2L,
+        Op::FAdd,
+        Op::FALoad,
+        Op::FAStore,
+        Op::FCmpG,
+        Op::FCmpL,
+        Op::FConst0,
+        Op::FConst1,
+        Op::FConst2,
+        Op::FDiv,
+        Op::FMul,
+        Op::FNeg,
+        Op::FRem,
+        Op::FReturn,
+        Op::FSub,
+        Op::I2B,
+        Op::I2C,
+        Op::I2D,
+        Op::I2F,
+        Op::I2L,
+        Op::I2S,
+        Op::IAdd,
+        Op::IALoad,
+        Op::IAnd,
+        Op::IAStore,
+        Op::IConstM1,
+        Op::IConst0,
+        Op::IConst1,
+        Op::IConst2,
+        Op::IConst3,
+        Op::IConst4,
+        Op::IConst5,
+        Op::IDiv,
+        Op::IMul,
+        Op::INeg,
+        Op::IOr,
+        Op::IRem,
+        Op::IReturn,
+        Op::IShl,
+        Op::IShr,
+        Op::ISub,
+        Op::IUShr,
+        Op::IXor,
+        Op::L2D,
+        Op::L2F,
+        Op::L2I,
+        Op::LAdd,
+        Op::LALoad,
+        Op::LAnd,
+        Op::LAStore,
+        Op::LCmp,
+        Op::LConst0,
+        Op::LConst1,
+        Op::LDiv,
+        Op::LMul,
+        Op::LNeg,
+        Op::LOr,
+        Op::LRem,
+        Op::LReturn,
+        Op::LShl,
+        Op::LShr,
+        Op::LSub,
+        Op::LUShr,
+        Op::LXor,
+        Op::MonitorEnter,
+        Op::MonitorExit,
+        Op::Nop,
+        Op::Pop,
+        Op::Pop2,
+        Op::Return,
+        Op::SALoad,
+        Op::SAStore,
+        Op::Swap,
+    ];
+
+    const U8_OPS: &[fn(u8) -> Op] = &[
+        Op::ALoad,
+        Op::AStore,
+        Op::DLoad,
+        Op::DStore,
+        Op::FLoad,
+        Op::FStore,
+        Op::ILoad,
+        Op::IStore,
+        Op::LDC,
+        Op::LLoad,
+        Op::LStore,
+        Op::Ret,
+    ];
+
+    const U16_OPS: &[fn(u16) -> Op] = &[
+        Op::ANewArray,
+        Op::CheckCast,
+        Op::GetField,
+        Op::GetStatic,
+        Op::Goto,
+        Op::IfACmpEq,
+        Op::IfACmpNe,
+        Op::IfICmpEq,
+        Op::IfICmpNe,
+        Op::IfICmpLt,
+        Op::IfICmpGe,
+        Op::IfICmpGt,
+        Op::IfICmpLe,
+        Op::IfEq,
+        Op::IfNe,
+        Op::IfLt,
+        Op::IfGe,
+        Op::IfGt,
+        Op::IfLe,
+        Op::IfNonNull,
+        Op::IfNull,
+        Op::InstanceOf,
+        Op::InvokeDynamic,
+        Op::InvokeSpecial,
+        Op::InvokeStatic,
+        Op::InvokeVirtual,
+        Op::Jsr,
+        Op::LDCW,
+        Op::LDC2W,
+        Op::New,
+        Op::PutField,
+        Op::PutStatic,
+        Op::SIPush,
+    ];
+
+    const ATYPES: &[AType] = &[
+        AType::TBoolean,
+        AType::TChar,
+        AType::TFloat,
+        AType::TDouble,
+        AType::TByte,
+        AType::TShort,
+        AType::TInt,
+        AType::TLong,
+    ];
+
+    /// Any op that [`Op::parse`] can return, with arbitrary operands.
+    fn op() -> impl Strategy<Value = Op> {
+        let table_switch = (
+            any::<i32>(),
+            any::<i32>(),
+            prop::collection::vec(any::<i32>(), 1..8),
+        )
+            .prop_map(|(default, low, offsets)| {
+                let low = low.min(i32::MAX - offsets.len() as i32);
+                Op::TableSwitch {
+                    default,
+                    low,
+                    high: low + offsets.len() as i32 - 1,
+                    offsets,
+                }
+            });
+        let lookup_switch = (
+            any::<u32>(),
+            prop::collection::vec(any::<(i32, i32)>(), 0..8),
+        )
+            .prop_map(|(default, npairs)| Op::LookupSwitch { default, npairs });
+        prop_oneof![
+            prop::sample::select(SIMPLE_OPS),
+            (prop::sample::select(U8_OPS), any::<u8>()).prop_map(|(op, operand)| op(operand)),
+            (prop::sample::select(U16_OPS), any::<u16>()).prop_map(|(op, operand)| op(operand)),
+            any::<i8>().prop_map(Op::BIPush),
+            any::<(u8, i8)>().prop_map(|(index, value)| Op::IInc(index, value)),
+            any::<u32>().prop_map(Op::GotoW),
+            any::<u32>().prop_map(Op::JsrW),
+            any::<(u16, u8)>().prop_map(|(index, count)| Op::InvokeInterface(index, count)),
+            any::<(u16, u8)>().prop_map(|(index, dims)| Op::MultiANewArray(index, dims)),
+            prop::sample::select(ATYPES).prop_map(Op::NewArray),
+            table_switch,
+            lookup_switch,
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(ops in prop::collection::vec(op(), 0..64)) {
+            let code = assemble(&ops);
+            let decoded = decode(&code).unwrap();
+            prop_assert_eq!(&ops, &decoded);
+            prop_assert_eq!(code, assemble(&decoded));
+        }
+
+        #[test]
+        fn test_decode_arbitrary_bytes(code in prop::collection::vec(any::<u8>(), 0..64)) {
+            // anything that decodes is normalized by writing it once, e.g.
+            // `aload 0` becomes `aload_0`
+            if let Ok(ops) = decode(&code) {
+                prop_assert_eq!(Ok(ops.clone()), decode(&assemble(&ops)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_opcodes() {
+        let mut defined = 0;
+        for opcode in 0..=u8::MAX {
+            // the operands are valid for every op: the array type int, and
+            // switches with a single offset or no pairs
+            let code = [&[opcode, 10][..], &[0; 32]].concat();
+            match Op::parse(&mut code.as_slice()) {
+                Ok(op) => {
+                    defined += 1;
+                    assert_eq!(opcode, assemble(std::slice::from_ref(&op))[0], "{:?}", op);
+                }
+                // 0xCA, 0xFE and 0xFF are reserved, the rest is undefined
+                Err(e) => {
+                    assert_eq!(OpParseError::InvalidByteCode, e);
+                    assert!(opcode >= 0xCA, "{:#04x}", opcode);
+                }
+            }
+        }
+        assert_eq!(202, defined);
+    }
+
+    #[test]
+    fn test_table_switch_padding() {
+        let switch = [
+            0xAA, 0x00, 0x00, // tableswitch at offset 1, padded to offset 4
+            0x00, 0x00, 0x00, 0x10, // default
+            0x00, 0x00, 0x00, 0x01, // low
+            0x00, 0x00, 0x00, 0x02, // high
+            0x00, 0x00, 0x00, 0x20, // offsets
+            0x00, 0x00, 0x00, 0x30,
+        ];
+        assert_eq!(
+            Ok(Op::TableSwitch {
+                default: 0x10,
+                low: 1,
+                high: 2,
+                offsets: vec![0x20, 0x30],
+            }),
+            Op::parse_at(&mut switch.as_slice(), 1)
+        );
+    }
+
+    #[test]
+    fn test_lookup_switch() {
+        let switch = [
+            0xAB, // lookupswitch at offset 3, so no padding
+            0x00, 0x00, 0x00, 0x10, // default
+            0x00, 0x00, 0x00, 0x01, // npairs
+            0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x20, // -1 => 0x20
+        ];
+        assert_eq!(
+            Ok(Op::LookupSwitch {
+                default: 0x10,
+                npairs: vec![(-1, 0x20)],
+            }),
+            Op::parse_at(&mut switch.as_slice(), 3)
+        );
+    }
+
+    #[test]
+    fn test_invoke_operands() {
+        let code = [
+            0xBA, 0x00, 0x07, 0x00, 0x00, // invokedynamic #7
+            0xB9, 0x00, 0x0B, 0x01, 0x00, // invokeinterface #11, 1
+            0xB0, // areturn
+        ];
+        let mut source = code.as_slice();
+        assert_eq!(Ok(Op::InvokeDynamic(7)), Op::parse(&mut source));
+        assert_eq!(Ok(Op::InvokeInterface(11, 1)), Op::parse(&mut source));
+        assert_eq!(Ok(Op::AReturn), Op::parse(&mut source));
+    }
+
+    #[test]
+    fn test_malformed_ops() {
+        let padding = [0xAA, 0x00, 0x00, 0x00];
+        let table_switch = |low: i32, high: i32| {
+            [
+                &padding[..],
+                &[0; 4],
+                &low.to_be_bytes(),
+                &high.to_be_bytes(),
+            ]
+            .concat()
+        };
+        // a lookupswitch with a negative number of pairs
+        let lookup_switch = [
+            &[0xAB, 0x00, 0x00, 0x00][..],
+            &[0; 4],
+            &(-1_i32).to_be_bytes(),
+        ]
+        .concat();
+
+        for (code, err) in [
+            (vec![], OpParseError::UnexpectedEOF),
+            (vec![0xBC, 0x03], OpParseError::InvalidArrayType),
+            (table_switch(1, 0), OpParseError::InvalidSwitch),
+            (lookup_switch, OpParseError::InvalidSwitch),
+            // 2^32 entries, but no offsets
+            (
+                table_switch(i32::MIN, i32::MAX),
+                OpParseError::UnexpectedEOF,
+            ),
+        ] {
+            assert_eq!(Err(err), Op::parse(&mut code.as_slice()));
+        }
+    }
+}
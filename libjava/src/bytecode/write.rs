@@ -0,0 +1,157 @@
+use crate::bytecode::Op;
+use crate::io::{self, Write};
+use crate::prelude::*;
+
+fn write_u8(sink: &mut impl Write, value: u8) -> io::Result<()> {
+    sink.write_all(&[value])
+}
+
+fn write_u16(sink: &mut impl Write, value: u16) -> io::Result<()> {
+    sink.write_all(&value.to_be_bytes())
+}
+
+fn write_u32(sink: &mut impl Write, value: u32) -> io::Result<()> {
+    sink.write_all(&value.to_be_bytes())
+}
+
+fn write_i32(sink: &mut impl Write, value: i32) -> io::Result<()> {
+    sink.write_all(&value.to_be_bytes())
+}
+
+impl Op {
+    /// Writes the op at the given offset in the code of its method, so that
+    /// [`Op::parse_at`] reads it back as an equal op. Local variable ops with
+    /// an index of 0 to 3 are written in their short form, like `aload_0`, as
+    /// javac does.
+    pub fn write(&self, sink: &mut impl Write, offset: usize) -> io::Result<()> {
+        if let Some(short_form) = self.short_form() {
+            return write_u8(sink, short_form);
+        }
+        write_u8(sink, self.opcode())?;
+        match self {
+            Op::ALoad(index)
+            | Op::AStore(index)
+            | Op::DLoad(index)
+            | Op::DStore(index)
+            | Op::FLoad(index)
+            | Op::FStore(index)
+            | Op::ILoad(index)
+            | Op::IStore(index)
+            | Op::LLoad(index)
+            | Op::LStore(index)
+            | Op::LDC(index)
+            | Op::Ret(index) => write_u8(sink, *index),
+            Op::BIPush(value) => sink.write_all(&value.to_be_bytes()),
+            Op::IInc(index, value) => {
+                write_u8(sink, *index)?;
+                sink.write_all(&value.to_be_bytes())
+            }
+            Op::ANewArray(index)
+            | Op::CheckCast(index)
+            | Op::GetField(index)
+            | Op::GetStatic(index)
+            | Op::InstanceOf(index)
+            | Op::InvokeSpecial(index)
+            | Op::InvokeStatic(index)
+            | Op::InvokeVirtual(index)
+            | Op::LDCW(index)
+            | Op::LDC2W(index)
+            | Op::New(index)
+            | Op::PutField(index)
+            | Op::PutStatic(index) => write_u16(sink, *index),
+            Op::SIPush(value)
+            | Op::Goto(value)
+            | Op::Jsr(value)
+            | Op::IfACmpEq(value)
+            | Op::IfACmpNe(value)
+            | Op::IfICmpEq(value)
+            | Op::IfICmpNe(value)
+            | Op::IfICmpLt(value)
+            | Op::IfICmpGe(value)
+            | Op::IfICmpGt(value)
+            | Op::IfICmpLe(value)
+            | Op::IfEq(value)
+            | Op::IfNe(value)
+            | Op::IfLt(value)
+            | Op::IfGe(value)
+            | Op::IfGt(value)
+            | Op::IfLe(value)
+            | Op::IfNonNull(value)
+            | Op::IfNull(value) => write_u16(sink, *value),
+            Op::GotoW(branch) | Op::JsrW(branch) => write_u32(sink, *branch),
+            Op::InvokeInterface(index, count) => {
+                write_u16(sink, *index)?;
+                write_u8(sink, *count)?;
+                write_u8(sink, 0)
+            }
+            Op::InvokeDynamic(index) => {
+                write_u16(sink, *index)?;
+                write_u16(sink, 0)
+            }
+            Op::MultiANewArray(index, dimensions) => {
+                write_u16(sink, *index)?;
+                write_u8(sink, *dimensions)
+            }
+            Op::NewArray(atype) => write_u8(sink, *atype as u8),
+            Op::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                write_padding(sink, offset)?;
+                write_i32(sink, *default)?;
+                write_i32(sink, *low)?;
+                write_i32(sink, *high)?;
+                offsets.iter().try_for_each(|o| write_i32(sink, *o))
+            }
+            Op::LookupSwitch { default, npairs } => {
+                write_padding(sink, offset)?;
+                write_u32(sink, *default)?;
+                write_i32(sink, npairs.len() as i32)?;
+                npairs.iter().try_for_each(|(key, offset)| {
+                    write_i32(sink, *key)?;
+                    write_i32(sink, *offset)
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The opcode of the short form of a local variable op, like `aload_0`,
+    /// or [`None`] if there is none for the index.
+    fn short_form(&self) -> Option<u8> {
+        let (first, index) = match self {
+            Op::ALoad(index) => (0x2A, index),
+            Op::AStore(index) => (0x4B, index),
+            Op::DLoad(index) => (0x26, index),
+            Op::DStore(index) => (0x47, index),
+            Op::FLoad(index) => (0x22, index),
+            Op::FStore(index) => (0x43, index),
+            Op::ILoad(index) => (0x1A, index),
+            Op::IStore(index) => (0x3B, index),
+            Op::LLoad(index) => (0x1E, index),
+            Op::LStore(index) => (0x3F, index),
+            _ => return None,
+        };
+        (*index <= 3).then(|| first + index)
+    }
+}
+
+/// Writes the 0-3 zero bytes after the opcode of a switch at the given offset,
+/// which [`Op::parse_at`] skips.
+fn write_padding(sink: &mut impl Write, offset: usize) -> io::Result<()> {
+    let padding = (4 - (offset + 1) % 4) % 4;
+    sink.write_all(&[0; 3][..padding])
+}
+
+/// Writes the ops one after another, the inverse of [`decode`](super::decode).
+pub fn assemble(ops: &[Op]) -> Vec<u8> {
+    let mut code = vec![];
+    for op in ops {
+        let offset = code.len();
+        op.write(&mut code, offset)
+            .expect("writing to a Vec doesn't fail");
+    }
+    code
+}
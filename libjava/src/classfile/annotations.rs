@@ -0,0 +1,463 @@
+use crate::classfile::{
+    Annotation, AttributeInfo, ClassFile, ClassFileParseError, ConstantPool, ConstantPoolError,
+    ElementValue, FieldInfo, MethodInfo, RecordComponentInfo,
+};
+use crate::prelude::*;
+
+/// An [`Annotation`] with its type and element values resolved from the
+/// constant pool, see [`ClassFile::runtime_visible_annotations`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAnnotation {
+    /// The field descriptor of the annotation interface, e.g.
+    /// `Ljava/lang/Deprecated;`.
+    pub type_descriptor: String,
+    /// The names and values of the elements, in the order of the class file.
+    /// Elements that have their default value are not included.
+    pub elements: Vec<(String, DecodedValue)>,
+}
+
+/// The value of an element of a [`DecodedAnnotation`] ([`$4.7.16.1`]).
+///
+/// [`$4.7.16.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16.1
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedValue {
+    Byte(i8),
+    /// A UTF-16 code unit, like a Java `char`.
+    Char(u16),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    Long(i64),
+    Short(i16),
+    Boolean(bool),
+    String(String),
+    Enum {
+        /// The field descriptor of the enum class, e.g.
+        /// `Ljava/lang/annotation/RetentionPolicy;`.
+        type_descriptor: String,
+        name: String,
+    },
+    /// The return descriptor of a class literal, e.g. `Ljava/lang/String;`,
+    /// `I` or `V` for `void.class`.
+    Class(String),
+    Annotation(DecodedAnnotation),
+    Array(Vec<DecodedValue>),
+}
+
+/// An error of decoding annotations.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AnnotationError {
+    /// An annotation attribute that was kept unparsed (see
+    /// [`ParseMode::Lazy`](crate::classfile::ParseMode::Lazy)) is malformed.
+    Malformed(ClassFileParseError),
+    /// An annotation refers to a missing or unexpected constant.
+    ConstantPool(ConstantPoolError),
+}
+
+impl From<ClassFileParseError> for AnnotationError {
+    fn from(error: ClassFileParseError) -> Self {
+        AnnotationError::Malformed(error)
+    }
+}
+
+impl From<ConstantPoolError> for AnnotationError {
+    fn from(error: ConstantPoolError) -> Self {
+        AnnotationError::ConstantPool(error)
+    }
+}
+
+impl DecodedAnnotation {
+    /// Returns the value of the element with the given name, or [`None`] if
+    /// the annotation doesn't specify it.
+    pub fn element(&self, name: &str) -> Option<&DecodedValue> {
+        self.elements
+            .iter()
+            .find(|(element, _)| element == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl ClassFile {
+    /// The annotations of the class from its `RuntimeVisibleAnnotations`
+    /// attribute.
+    pub fn runtime_visible_annotations(&self) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(&self.cp_info, &self.attributes, true)
+    }
+
+    /// The annotations of the class from its `RuntimeInvisibleAnnotations`
+    /// attribute, i.e. those with a retention policy of `CLASS`.
+    pub fn runtime_invisible_annotations(&self) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(&self.cp_info, &self.attributes, false)
+    }
+
+    /// Finds a visible or invisible annotation of the class by the descriptor
+    /// of its type, e.g. `find_annotation("Ljava/lang/Deprecated;")`.
+    pub fn find_annotation(
+        &self,
+        type_descriptor: &str,
+    ) -> Result<Option<DecodedAnnotation>, AnnotationError> {
+        find_annotation(&self.cp_info, &self.attributes, type_descriptor)
+    }
+}
+
+impl FieldInfo {
+    /// See [`ClassFile::runtime_visible_annotations`]. The constant pool
+    /// must be the one of the class file that the field is declared in.
+    pub fn runtime_visible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, true)
+    }
+
+    /// See [`ClassFile::runtime_invisible_annotations`].
+    pub fn runtime_invisible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, false)
+    }
+
+    /// See [`ClassFile::find_annotation`].
+    pub fn find_annotation(
+        &self,
+        cp: &ConstantPool,
+        type_descriptor: &str,
+    ) -> Result<Option<DecodedAnnotation>, AnnotationError> {
+        find_annotation(cp, &self.attributes, type_descriptor)
+    }
+}
+
+impl MethodInfo {
+    /// See [`ClassFile::runtime_visible_annotations`]. The constant pool
+    /// must be the one of the class file that the method is declared in.
+    pub fn runtime_visible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, true)
+    }
+
+    /// See [`ClassFile::runtime_invisible_annotations`].
+    pub fn runtime_invisible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, false)
+    }
+
+    /// See [`ClassFile::find_annotation`].
+    pub fn find_annotation(
+        &self,
+        cp: &ConstantPool,
+        type_descriptor: &str,
+    ) -> Result<Option<DecodedAnnotation>, AnnotationError> {
+        find_annotation(cp, &self.attributes, type_descriptor)
+    }
+
+    /// The annotations of each parameter from the
+    /// `RuntimeVisibleParameterAnnotations` attribute. This is empty if the
+    /// method has no such attribute, and may have fewer entries than the
+    /// method has parameters ([`$4.7.18`]).
+    ///
+    /// [`$4.7.18`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.18
+    pub fn runtime_visible_parameter_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<Vec<DecodedAnnotation>>, AnnotationError> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::RuntimeVisibleParameterAnnotations {
+                parameter_annotations,
+                ..
+            } = attribute.materialize(cp)?
+            {
+                return parameter_annotations
+                    .iter()
+                    .map(|annotations| annotations.iter().map(|a| decode(cp, a)).collect())
+                    .collect();
+            }
+        }
+        Ok(vec![])
+    }
+
+    /// The default value of the element of an annotation interface that
+    /// this method declares, from its `AnnotationDefault` attribute.
+    pub fn annotation_default(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Option<DecodedValue>, AnnotationError> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::AnnotationDefault { default_value, .. } =
+                attribute.materialize(cp)?
+            {
+                return decode_value(cp, default_value).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl RecordComponentInfo {
+    /// See [`ClassFile::runtime_visible_annotations`]. The constant pool
+    /// must be the one of the record class that declares the component.
+    pub fn runtime_visible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, true)
+    }
+
+    /// See [`ClassFile::runtime_invisible_annotations`].
+    pub fn runtime_invisible_annotations(
+        &self,
+        cp: &ConstantPool,
+    ) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+        annotations(cp, &self.attributes, false)
+    }
+
+    /// See [`ClassFile::find_annotation`].
+    pub fn find_annotation(
+        &self,
+        cp: &ConstantPool,
+        type_descriptor: &str,
+    ) -> Result<Option<DecodedAnnotation>, AnnotationError> {
+        find_annotation(cp, &self.attributes, type_descriptor)
+    }
+}
+
+fn annotations(
+    cp: &ConstantPool,
+    attributes: &[AttributeInfo],
+    visible: bool,
+) -> Result<Vec<DecodedAnnotation>, AnnotationError> {
+    let mut decoded = vec![];
+    for attribute in attributes {
+        match (attribute.materialize(cp)?, visible) {
+            (AttributeInfo::RuntimeVisibleAnnotations { annotations, .. }, true)
+            | (AttributeInfo::RuntimeInvisibleAnnotations { annotations, .. }, false) => {
+                for annotation in annotations {
+                    decoded.push(decode(cp, annotation)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(decoded)
+}
+
+fn find_annotation(
+    cp: &ConstantPool,
+    attributes: &[AttributeInfo],
+    type_descriptor: &str,
+) -> Result<Option<DecodedAnnotation>, AnnotationError> {
+    for attribute in attributes {
+        let annotations = match attribute.materialize(cp)? {
+            AttributeInfo::RuntimeVisibleAnnotations { annotations, .. }
+            | AttributeInfo::RuntimeInvisibleAnnotations { annotations, .. } => annotations,
+            _ => continue,
+        };
+        // only the matching annotation is decoded
+        for annotation in annotations {
+            if cp.get_utf8(annotation.type_index)? == type_descriptor {
+                return decode(cp, annotation).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn decode(
+    cp: &ConstantPool,
+    annotation: &Annotation,
+) -> Result<DecodedAnnotation, AnnotationError> {
+    let elements = annotation
+        .element_value_pairs
+        .iter()
+        .map(|pair| {
+            let name = cp.get_utf8(pair.element_name_index)?.into_owned();
+            Ok((name, decode_value(cp, &pair.value)?))
+        })
+        .collect::<Result<_, AnnotationError>>()?;
+    Ok(DecodedAnnotation {
+        type_descriptor: cp.get_utf8(annotation.type_index)?.into_owned(),
+        elements,
+    })
+}
+
+fn decode_value(cp: &ConstantPool, value: &ElementValue) -> Result<DecodedValue, AnnotationError> {
+    Ok(match value {
+        ElementValue::ConstValueIndex {
+            tag,
+            const_value_index: index,
+        } => {
+            let index = *index;
+            // byte, char, short and boolean values are CONSTANT_Integer
+            // entries, which are truncated like by the corresponding casts
+            match tag {
+                b'B' => DecodedValue::Byte(cp.get_integer(index)? as i8),
+                b'C' => DecodedValue::Char(cp.get_integer(index)? as u16),
+                b'D' => DecodedValue::Double(cp.get_double(index)?),
+                b'F' => DecodedValue::Float(cp.get_float(index)?),
+                b'I' => DecodedValue::Int(cp.get_integer(index)?),
+                b'J' => DecodedValue::Long(cp.get_long(index)?),
+                b'S' => DecodedValue::Short(cp.get_integer(index)? as i16),
+                b'Z' => DecodedValue::Boolean(cp.get_integer(index)? != 0),
+                // unlike an ldc, a string value refers to the CONSTANT_Utf8
+                // entry directly
+                _ => DecodedValue::String(cp.get_utf8(index)?.into_owned()),
+            }
+        }
+        ElementValue::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => DecodedValue::Enum {
+            type_descriptor: cp.get_utf8(*type_name_index)?.into_owned(),
+            name: cp.get_utf8(*const_name_index)?.into_owned(),
+        },
+        ElementValue::ClassInfoIndex(index) => {
+            DecodedValue::Class(cp.get_utf8(*index)?.into_owned())
+        }
+        ElementValue::AnnotationValue(annotation) => {
+            DecodedValue::Annotation(decode(cp, annotation)?)
+        }
+        ElementValue::ArrayValue { values } => DecodedValue::Array(
+            values
+                .iter()
+                .map(|value| decode_value(cp, value))
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::{parse, MODES};
+    use crate::classfile::ParseMode;
+
+    fn tag(value: &str) -> DecodedAnnotation {
+        DecodedAnnotation {
+            type_descriptor: "LAnnotations$Tag;".into(),
+            elements: vec![("value".into(), DecodedValue::String(value.into()))],
+        }
+    }
+
+    #[test]
+    fn test_class_annotations() {
+        for mode in MODES {
+            let class_file = parse("Annotations", mode);
+            let annotations = class_file.runtime_visible_annotations().unwrap();
+            assert_eq!(2, annotations.len());
+            assert_eq!(tag("class"), annotations[1]);
+
+            let info = &annotations[0];
+            assert_eq!("LAnnotations$Info;", info.type_descriptor);
+            assert_eq!(Some(&DecodedValue::Int(3)), info.element("count"));
+            assert_eq!(
+                Some(&DecodedValue::String("annotations".into())),
+                info.element("name")
+            );
+            assert_eq!(
+                Some(&DecodedValue::Enum {
+                    type_descriptor: "Ljava/lang/annotation/RetentionPolicy;".into(),
+                    name: "RUNTIME".into()
+                }),
+                info.element("retention")
+            );
+            assert_eq!(
+                Some(&DecodedValue::Class("Ljava/lang/String;".into())),
+                info.element("type")
+            );
+            assert_eq!(
+                Some(&DecodedValue::Array(vec![
+                    DecodedValue::String("a".into()),
+                    DecodedValue::String("b".into())
+                ])),
+                info.element("tags")
+            );
+            assert_eq!(
+                Some(&DecodedValue::Annotation(tag("nested"))),
+                info.element("nested")
+            );
+            // defaults are not part of the annotation
+            assert_eq!(None, info.element("target"));
+
+            assert_eq!(
+                Some(tag("class")),
+                class_file.find_annotation("LAnnotations$Tag;").unwrap()
+            );
+            assert_eq!(
+                None,
+                class_file
+                    .find_annotation("Ljava/lang/Deprecated;")
+                    .unwrap()
+            );
+            assert!(class_file
+                .runtime_invisible_annotations()
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_member_annotations() {
+        let class_file = parse("Annotations", ParseMode::Lazy);
+        let cp = class_file.constant_pool();
+
+        let field = class_file.find_field("big", "J").unwrap();
+        assert!(field
+            .find_annotation(cp, "Ljava/lang/Deprecated;")
+            .unwrap()
+            .is_some());
+        let invisible = field.runtime_invisible_annotations(cp).unwrap();
+        assert_eq!(
+            vec!["LAnnotations$Invisible;"],
+            invisible
+                .iter()
+                .map(|a| &a.type_descriptor)
+                .collect::<Vec<_>>()
+        );
+
+        let method = class_file.find_method("run", "(IC)V").unwrap();
+        assert_eq!(
+            vec![tag("method")],
+            method.runtime_visible_annotations(cp).unwrap()
+        );
+        assert_eq!(
+            vec![vec![tag("parameter")], vec![]],
+            method.runtime_visible_parameter_annotations(cp).unwrap()
+        );
+        assert_eq!(None, method.annotation_default(cp).unwrap());
+    }
+
+    #[test]
+    fn test_annotation_defaults() {
+        let class_file = parse("Annotations$Info", ParseMode::Eager);
+        let cp = class_file.constant_pool();
+        let default = |name: &str, descriptor: &str| {
+            class_file
+                .find_method(name, descriptor)
+                .unwrap()
+                .annotation_default(cp)
+                .unwrap()
+        };
+        assert_eq!(None, default("count", "()I"));
+        assert_eq!(
+            Some(DecodedValue::Enum {
+                type_descriptor: "Ljava/lang/annotation/ElementType;".into(),
+                name: "FIELD".into()
+            }),
+            default("target", "()Ljava/lang/annotation/ElementType;")
+        );
+        assert_eq!(Some(DecodedValue::Long(1 << 40)), default("big", "()J"));
+        assert_eq!(Some(DecodedValue::Double(0.5)), default("ratio", "()D"));
+        assert_eq!(Some(DecodedValue::Float(1.5)), default("scale", "()F"));
+        assert_eq!(Some(DecodedValue::Boolean(true)), default("enabled", "()Z"));
+        assert_eq!(
+            Some(DecodedValue::Char('x' as u16)),
+            default("letter", "()C")
+        );
+        assert_eq!(Some(DecodedValue::Byte(-1)), default("small", "()B"));
+        assert_eq!(Some(DecodedValue::Short(300)), default("medium", "()S"));
+    }
+}
@@ -0,0 +1,1033 @@
+use crate::bytecode::{assemble, Op};
+use crate::classfile::flags::{
+    ClassAccessFlags, FieldAccessFlags, InnerClassAccessFlags, MethodAccessFlags,
+};
+use crate::classfile::{
+    AttributeInfo, BootstrapMethod, ClassFile, ConstantPool, ConstantPoolInfo, ExceptionTableEntry,
+    FieldInfo, InnerClass, LineNumberTableEntry, MethodInfo, ReferenceKind, Version,
+};
+use crate::prelude::*;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+const PRIMITIVES: &[&str] = &["B", "C", "D", "F", "I", "J", "S", "Z"];
+
+const REFERENCE_KINDS: &[ReferenceKind] = &[
+    ReferenceKind::GetField,
+    ReferenceKind::GetStatic,
+    ReferenceKind::PutField,
+    ReferenceKind::PutStatic,
+    ReferenceKind::InvokeVirtual,
+    ReferenceKind::InvokeStatic,
+    ReferenceKind::InvokeSpecial,
+    ReferenceKind::NewInvokeSpecial,
+    ReferenceKind::InvokeInterface,
+];
+
+/// The ops of generated code. They don't refer to the constant pool or to
+/// other instructions, so any sequence of them decodes.
+const SIMPLE_OPS: &[Op] = &[
+    Op::Nop,
+    Op::AConstNull,
+    Op::IConst0,
+    Op::IConst1,
+    Op::IAdd,
+    Op::Pop,
+    Op::Dup,
+    Op::Swap,
+];
+
+/// The bootstrap method of every generated `CONSTANT_InvokeDynamic` entry.
+const METAFACTORY: (&str, &str, &str) = (
+    "java/lang/invoke/LambdaMetafactory",
+    "metafactory",
+    "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;\
+     Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;",
+);
+
+fn class_name() -> impl Strategy<Value = String> {
+    "[a-z]{1,5}(/[a-z]{1,5}){0,2}/[A-Z][A-Za-z0-9_$]{0,8}"
+}
+
+fn unqualified_name() -> impl Strategy<Value = String> {
+    "[a-z_][A-Za-z0-9_$]{0,10}"
+}
+
+fn field_descriptor() -> impl Strategy<Value = String> {
+    let element = prop_oneof![
+        select(PRIMITIVES).prop_map(String::from),
+        class_name().prop_map(|name| format!("L{};", name)),
+    ];
+    (0..3_usize, element).prop_map(|(dimensions, element)| "[".repeat(dimensions) + &element)
+}
+
+fn method_descriptor() -> impl Strategy<Value = String> {
+    (
+        vec(field_descriptor(), 0..4),
+        option::of(field_descriptor()),
+    )
+        .prop_map(|(parameters, return_type)| {
+            format!(
+                "({}){}",
+                parameters.concat(),
+                return_type.as_deref().unwrap_or("V")
+            )
+        })
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        select(SIMPLE_OPS),
+        any::<i8>().prop_map(Op::BIPush),
+        any::<u8>().prop_map(Op::ILoad),
+        any::<(u8, i8)>().prop_map(|(index, value)| Op::IInc(index, value)),
+    ]
+}
+
+/// An index into the constant pool, which is never 0.
+fn index() -> impl Strategy<Value = u16> {
+    1..=u16::MAX
+}
+
+/// Builds an attribute whose `attribute_length` matches its contents, from a
+/// function that builds the attribute with the given length.
+fn sized(build: impl Fn(u32) -> AttributeInfo) -> AttributeInfo {
+    let mut bytes = vec![];
+    build(0)
+        .write(&mut bytes)
+        .expect("writing to a Vec doesn't fail");
+    // the name index and the length aren't part of the length
+    build((bytes.len() - 6) as u32)
+}
+
+/// Generates a single constant pool entry. References to other entries are
+/// arbitrary indices, since there is no pool that they could be consistent
+/// with. Long and double constants aren't followed by
+/// [`ConstantPoolInfo::Unusable`], which only [`ConstantPool`] inserts.
+impl Arbitrary for ConstantPoolInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<String>().prop_map(|s| ConstantPoolInfo::utf8(&s)),
+            any::<(bool, u32)>().prop_map(|(float, bytes)| if float {
+                ConstantPoolInfo::FloatInfo { bytes }
+            } else {
+                ConstantPoolInfo::IntegerInfo { bytes }
+            }),
+            any::<(bool, u32, u32)>().prop_map(|(double, high_bytes, low_bytes)| if double {
+                ConstantPoolInfo::DoubleInfo {
+                    high_bytes,
+                    low_bytes,
+                }
+            } else {
+                ConstantPoolInfo::LongInfo {
+                    high_bytes,
+                    low_bytes,
+                }
+            }),
+            (0..5, index()).prop_map(|(kind, index)| match kind {
+                0 => ConstantPoolInfo::ClassInfo { name_index: index },
+                1 => ConstantPoolInfo::StringInfo {
+                    string_index: index,
+                },
+                2 => ConstantPoolInfo::MethodTypeInfo {
+                    descriptor_index: index,
+                },
+                3 => ConstantPoolInfo::ModuleInfo { name_index: index },
+                _ => ConstantPoolInfo::PackageInfo { name_index: index },
+            }),
+            (0..3, index(), index()).prop_map(|(kind, class_index, name_and_type_index)| {
+                match kind {
+                    0 => ConstantPoolInfo::FieldrefInfo {
+                        class_index,
+                        name_and_type_index,
+                    },
+                    1 => ConstantPoolInfo::MethodrefInfo {
+                        class_index,
+                        name_and_type_index,
+                    },
+                    _ => ConstantPoolInfo::InterfaceMethodrefInfo {
+                        class_index,
+                        name_and_type_index,
+                    },
+                }
+            }),
+            (index(), index()).prop_map(|(name_index, descriptor_index)| {
+                ConstantPoolInfo::NameAndTypeInfo {
+                    name_index,
+                    descriptor_index,
+                }
+            }),
+            (select(REFERENCE_KINDS), index()).prop_map(|(reference_kind, reference_index)| {
+                ConstantPoolInfo::MethodHandleInfo {
+                    reference_kind,
+                    reference_index,
+                }
+            }),
+            (any::<bool>(), any::<u16>(), index()).prop_map(
+                |(invoke, bootstrap_method_attr_index, name_and_type_index)| if invoke {
+                    ConstantPoolInfo::InvokeDynamicInfo {
+                        bootstrap_method_attr_index,
+                        name_and_type_index,
+                    }
+                } else {
+                    ConstantPoolInfo::DynamicInfo {
+                        bootstrap_method_attr_index,
+                        name_and_type_index,
+                    }
+                }
+            ),
+        ]
+        .boxed()
+    }
+}
+
+/// Generates a single attribute whose length matches its contents. Like for
+/// [`ConstantPoolInfo`], its name and the entries that it refers to are
+/// arbitrary indices, so it can be written but not parsed on its own. The
+/// attributes of a generated [`ClassFile`] can be.
+impl Arbitrary for AttributeInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            (0..5, index(), index()).prop_map(|(kind, name, index)| sized(|length| match kind {
+                0 => AttributeInfo::ConstantValue {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    constantvalue_index: index,
+                },
+                1 => AttributeInfo::Signature {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    signature_index: index,
+                },
+                2 => AttributeInfo::SourceFile {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    sourcefile_index: index,
+                },
+                3 => AttributeInfo::NestHost {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    host_class_index: index,
+                },
+                _ => AttributeInfo::ModuleMainClass {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    main_class_index: index,
+                },
+            })),
+            (any::<bool>(), index()).prop_map(|(synthetic, name)| sized(|length| if synthetic {
+                AttributeInfo::Synthetic {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                }
+            } else {
+                AttributeInfo::Deprecated {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                }
+            })),
+            (0..3, index(), vec(index(), 0..8)).prop_map(|(kind, name, classes)| {
+                sized(|length| match kind {
+                    0 => AttributeInfo::Exceptions {
+                        attribute_name_index: name,
+                        attribute_length: length,
+                        exception_index_table: classes.clone(),
+                    },
+                    1 => AttributeInfo::NestMembers {
+                        attribute_name_index: name,
+                        attribute_length: length,
+                        classes: classes.clone(),
+                    },
+                    _ => AttributeInfo::PermittedSubclasses {
+                        attribute_name_index: name,
+                        attribute_length: length,
+                        classes: classes.clone(),
+                    },
+                })
+            }),
+            (index(), vec(any::<(u16, u16)>(), 0..8)).prop_map(|(name, lines)| {
+                sized(|length| line_number_table(name, length, &lines))
+            }),
+            (index(), vec(any::<u8>(), 0..32)).prop_map(|(name, raw_bytes)| {
+                sized(|length| AttributeInfo::Unknown {
+                    name_index: name,
+                    length,
+                    raw_bytes: raw_bytes.clone(),
+                })
+            }),
+            (index(), any::<(u16, u16)>(), vec(op(), 0..16)).prop_map(
+                |(name, (max_stack, max_locals), ops)| {
+                    let code = assemble(&ops);
+                    sized(|length| AttributeInfo::Code {
+                        attribute_name_index: name,
+                        attribute_length: length,
+                        max_stack,
+                        max_locals,
+                        code_length: code.len() as u32,
+                        code: code.clone(),
+                        ops: None,
+                        exception_table: vec![],
+                        attributes: vec![],
+                    })
+                }
+            ),
+        ]
+        .boxed()
+    }
+}
+
+/// Generates a class file that passes [`ClassFile::validate`], with a
+/// constant pool that contains exactly the entries that the rest of the
+/// class file refers to, and some constants that nothing refers to. Writing
+/// it and parsing it again results in an equal class file.
+impl Arbitrary for ClassFile {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            (45_u16..=65, any::<u16>()),
+            (
+                class_name(),
+                option::of(class_name()),
+                vec(class_name(), 0..3),
+            ),
+            vec(constant(), 0..16),
+            vec(field(), 0..4),
+            vec(method(), 0..4),
+            vec(class_attribute(), 0..4),
+        )
+            .prop_map(
+                |(
+                    (major, flags),
+                    (name, super_name, interfaces),
+                    constants,
+                    fields,
+                    methods,
+                    attributes,
+                )| {
+                    Class {
+                        major,
+                        flags,
+                        name,
+                        super_name,
+                        interfaces,
+                        constants,
+                        fields,
+                        methods,
+                        attributes,
+                    }
+                    .build()
+                },
+            )
+            .boxed()
+    }
+}
+
+/// The entries of a constant pool that is being built, by their indices.
+/// Equal entries are only added once, like compilers do.
+struct PoolBuilder {
+    entries: Vec<(u16, ConstantPoolInfo)>,
+    next_index: u16,
+    /// Whether an entry refers to the bootstrap method.
+    uses_bootstrap_method: bool,
+}
+
+impl PoolBuilder {
+    fn new() -> Self {
+        Self {
+            entries: vec![],
+            next_index: 1,
+            uses_bootstrap_method: false,
+        }
+    }
+
+    fn add(&mut self, info: ConstantPoolInfo) -> u16 {
+        if let Some((index, _)) = self.entries.iter().find(|(_, entry)| *entry == info) {
+            return *index;
+        }
+        let index = self.next_index;
+        self.next_index += info.slots() as u16;
+        self.entries.push((index, info));
+        index
+    }
+
+    fn utf8(&mut self, s: &str) -> u16 {
+        self.add(ConstantPoolInfo::utf8(s))
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.add(ConstantPoolInfo::ClassInfo { name_index })
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.add(ConstantPoolInfo::NameAndTypeInfo {
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    /// Adds a member reference of the kind that the given kind of method
+    /// handle refers to.
+    fn member(&mut self, kind: ReferenceKind, class: &str, name: &str, descriptor: &str) -> u16 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.add(match kind {
+            ReferenceKind::GetField
+            | ReferenceKind::GetStatic
+            | ReferenceKind::PutField
+            | ReferenceKind::PutStatic => ConstantPoolInfo::FieldrefInfo {
+                class_index,
+                name_and_type_index,
+            },
+            ReferenceKind::InvokeInterface => ConstantPoolInfo::InterfaceMethodrefInfo {
+                class_index,
+                name_and_type_index,
+            },
+            _ => ConstantPoolInfo::MethodrefInfo {
+                class_index,
+                name_and_type_index,
+            },
+        })
+    }
+
+    fn build(self) -> ConstantPool {
+        ConstantPool::from(self.entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+}
+
+/// A constant of a generated class file that nothing else refers to, with
+/// the entries that it refers to by their values.
+#[derive(Debug, Clone)]
+enum Constant {
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    String(String),
+    Class(String),
+    MethodType(String),
+    /// A field or method reference, and a method handle to it if `handle` is
+    /// set. The kind determines whether the reference is a field, method or
+    /// interface method, and so which descriptor it has.
+    Member {
+        kind: ReferenceKind,
+        handle: bool,
+        class: String,
+        name: String,
+        field_descriptor: String,
+        method_descriptor: String,
+    },
+    InvokeDynamic(String, String),
+}
+
+fn constant() -> impl Strategy<Value = Constant> {
+    prop_oneof![
+        any::<i32>().prop_map(Constant::Integer),
+        any::<u32>().prop_map(Constant::Float),
+        any::<i64>().prop_map(Constant::Long),
+        any::<u64>().prop_map(Constant::Double),
+        any::<String>().prop_map(Constant::String),
+        class_name().prop_map(Constant::Class),
+        method_descriptor().prop_map(Constant::MethodType),
+        (
+            select(REFERENCE_KINDS),
+            any::<bool>(),
+            class_name(),
+            unqualified_name(),
+            field_descriptor(),
+            method_descriptor(),
+        )
+            .prop_map(
+                |(kind, handle, class, name, field_descriptor, method_descriptor)| {
+                    Constant::Member {
+                        kind,
+                        handle,
+                        class,
+                        name,
+                        field_descriptor,
+                        method_descriptor,
+                    }
+                }
+            ),
+        (unqualified_name(), method_descriptor())
+            .prop_map(|(name, descriptor)| Constant::InvokeDynamic(name, descriptor)),
+    ]
+}
+
+impl Constant {
+    /// Adds the constant and the entries it refers to, and returns its index.
+    fn build(&self, cp: &mut PoolBuilder) -> u16 {
+        match self {
+            Constant::Integer(value) => cp.add(ConstantPoolInfo::IntegerInfo {
+                bytes: *value as u32,
+            }),
+            Constant::Float(bytes) => cp.add(ConstantPoolInfo::FloatInfo { bytes: *bytes }),
+            Constant::Long(value) => cp.add(ConstantPoolInfo::LongInfo {
+                high_bytes: (*value as u64 >> 32) as u32,
+                low_bytes: *value as u32,
+            }),
+            Constant::Double(bits) => cp.add(ConstantPoolInfo::DoubleInfo {
+                high_bytes: (bits >> 32) as u32,
+                low_bytes: *bits as u32,
+            }),
+            Constant::String(s) => {
+                let string_index = cp.utf8(s);
+                cp.add(ConstantPoolInfo::StringInfo { string_index })
+            }
+            Constant::Class(name) => cp.class(name),
+            Constant::MethodType(descriptor) => {
+                let descriptor_index = cp.utf8(descriptor);
+                cp.add(ConstantPoolInfo::MethodTypeInfo { descriptor_index })
+            }
+            Constant::Member {
+                kind,
+                handle,
+                class,
+                name,
+                field_descriptor,
+                method_descriptor,
+            } => {
+                let descriptor = match kind {
+                    ReferenceKind::GetField
+                    | ReferenceKind::GetStatic
+                    | ReferenceKind::PutField
+                    | ReferenceKind::PutStatic => field_descriptor,
+                    _ => method_descriptor,
+                };
+                let reference_index = cp.member(*kind, class, name, descriptor);
+                if !handle {
+                    return reference_index;
+                }
+                cp.add(ConstantPoolInfo::MethodHandleInfo {
+                    reference_kind: *kind,
+                    reference_index,
+                })
+            }
+            Constant::InvokeDynamic(name, descriptor) => {
+                let name_and_type_index = cp.name_and_type(name, descriptor);
+                cp.uses_bootstrap_method = true;
+                cp.add(ConstantPoolInfo::InvokeDynamicInfo {
+                    bootstrap_method_attr_index: 0,
+                    name_and_type_index,
+                })
+            }
+        }
+    }
+}
+
+/// An attribute of a field, a method or a class.
+#[derive(Debug, Clone)]
+enum MemberAttribute {
+    Synthetic,
+    Deprecated,
+    Signature(String),
+    /// An attribute with a name that the specification doesn't define, which
+    /// starts with `Custom`.
+    Unknown(String, Vec<u8>),
+}
+
+fn member_attribute() -> impl Strategy<Value = MemberAttribute> {
+    prop_oneof![
+        Just(MemberAttribute::Synthetic),
+        Just(MemberAttribute::Deprecated),
+        any::<String>().prop_map(MemberAttribute::Signature),
+        ("Custom[A-Za-z]{0,8}", vec(any::<u8>(), 0..16))
+            .prop_map(|(name, bytes)| MemberAttribute::Unknown(name, bytes)),
+    ]
+}
+
+impl MemberAttribute {
+    fn build(&self, cp: &mut PoolBuilder) -> AttributeInfo {
+        match self {
+            MemberAttribute::Synthetic => {
+                let name = cp.utf8("Synthetic");
+                sized(|length| AttributeInfo::Synthetic {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                })
+            }
+            MemberAttribute::Deprecated => {
+                let name = cp.utf8("Deprecated");
+                sized(|length| AttributeInfo::Deprecated {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                })
+            }
+            MemberAttribute::Signature(signature) => {
+                let name = cp.utf8("Signature");
+                let signature_index = cp.utf8(signature);
+                sized(|length| AttributeInfo::Signature {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    signature_index,
+                })
+            }
+            MemberAttribute::Unknown(name, bytes) => {
+                let name_index = cp.utf8(name);
+                sized(|length| AttributeInfo::Unknown {
+                    name_index,
+                    length,
+                    raw_bytes: bytes.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    flags: u16,
+    name: String,
+    descriptor: String,
+    /// The bits of the `ConstantValue` of a static field of a type that can
+    /// have one.
+    value: Option<u64>,
+    attributes: Vec<MemberAttribute>,
+}
+
+fn field() -> impl Strategy<Value = Field> {
+    (
+        any::<u16>(),
+        unqualified_name(),
+        prop_oneof![field_descriptor(), Just("Ljava/lang/String;".to_string())],
+        option::of(any::<u64>()),
+        vec(member_attribute(), 0..3),
+    )
+        .prop_map(|(flags, name, descriptor, value, attributes)| Field {
+            flags,
+            name,
+            descriptor,
+            value,
+            attributes,
+        })
+}
+
+impl Field {
+    fn build(&self, cp: &mut PoolBuilder) -> FieldInfo {
+        let access_flags = FieldAccessFlags::from_bits_truncate(self.flags);
+        let name_index = cp.utf8(&self.name);
+        let descriptor_index = cp.utf8(&self.descriptor);
+        let mut attributes: Vec<_> = self.attributes.iter().map(|a| a.build(cp)).collect();
+        if let (Some(bits), true) = (self.value, access_flags.contains(FieldAccessFlags::STATIC)) {
+            let constant = match self.descriptor.as_str() {
+                "I" | "S" | "C" | "B" | "Z" => Some(Constant::Integer(bits as i32)),
+                "F" => Some(Constant::Float(bits as u32)),
+                "J" => Some(Constant::Long(bits as i64)),
+                "D" => Some(Constant::Double(bits)),
+                "Ljava/lang/String;" => Some(Constant::String(bits.to_string())),
+                _ => None,
+            };
+            if let Some(constant) = constant {
+                let name = cp.utf8("ConstantValue");
+                let constantvalue_index = constant.build(cp);
+                attributes.push(sized(|length| AttributeInfo::ConstantValue {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    constantvalue_index,
+                }));
+            }
+        }
+        FieldInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Code {
+    max_stack: u16,
+    max_locals: u16,
+    /// The code without the final `return`.
+    ops: Vec<Op>,
+    /// The ranges and handlers of the exception table, which are reduced
+    /// into the code, and their catch types.
+    handlers: Vec<(u16, u16, u16, Option<String>)>,
+    /// The pcs, which are reduced into the code, and their lines.
+    lines: Vec<(u16, u16)>,
+}
+
+fn code() -> impl Strategy<Value = Code> {
+    (
+        any::<(u16, u16)>(),
+        vec(op(), 0..16),
+        vec((any::<(u16, u16, u16)>(), option::of(class_name())), 0..3),
+        vec(any::<(u16, u16)>(), 0..4),
+    )
+        .prop_map(|((max_stack, max_locals), ops, handlers, lines)| Code {
+            max_stack,
+            max_locals,
+            ops,
+            handlers: handlers
+                .into_iter()
+                .map(|((start, end, handler), catch_type)| (start, end, handler, catch_type))
+                .collect(),
+            lines,
+        })
+}
+
+impl Code {
+    fn build(&self, cp: &mut PoolBuilder) -> AttributeInfo {
+        let name = cp.utf8("Code");
+        let code = assemble(&[&self.ops[..], &[Op::Return]].concat());
+        let code_length = code.len() as u16;
+        let handlers: Vec<_> = self
+            .handlers
+            .iter()
+            .map(|(start, end, handler, catch_type)| {
+                let start_pc = start % code_length;
+                (
+                    start_pc,
+                    start_pc + 1 + end % (code_length - start_pc),
+                    handler % code_length,
+                    catch_type.as_ref().map_or(0, |name| cp.class(name)),
+                )
+            })
+            .collect();
+        let lines: Vec<_> = self
+            .lines
+            .iter()
+            .map(|(pc, line)| (pc % code_length, *line))
+            .collect();
+        let line_number_table_name = (!lines.is_empty()).then(|| cp.utf8("LineNumberTable"));
+        sized(|length| AttributeInfo::Code {
+            attribute_name_index: name,
+            attribute_length: length,
+            max_stack: self.max_stack,
+            max_locals: self.max_locals,
+            code_length: code.len() as u32,
+            code: code.clone(),
+            ops: None,
+            exception_table: handlers
+                .iter()
+                .map(
+                    |(start_pc, end_pc, handler_pc, catch_type)| ExceptionTableEntry {
+                        start_pc: *start_pc,
+                        end_pc: *end_pc,
+                        handler_pc: *handler_pc,
+                        catch_type: *catch_type,
+                    },
+                )
+                .collect(),
+            attributes: line_number_table_name
+                .map(|name| sized(|length| line_number_table(name, length, &lines)))
+                .into_iter()
+                .collect(),
+        })
+    }
+}
+
+fn line_number_table(name: u16, length: u32, lines: &[(u16, u16)]) -> AttributeInfo {
+    AttributeInfo::LineNumberTable {
+        attribute_name_index: name,
+        attribute_length: length,
+        line_number_table: lines
+            .iter()
+            .map(|(start_pc, line_number)| LineNumberTableEntry {
+                start_pc: *start_pc,
+                line_number: *line_number,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Method {
+    flags: u16,
+    name: String,
+    descriptor: String,
+    /// The code, unless the method is abstract or native.
+    code: Code,
+    exceptions: Vec<String>,
+    attributes: Vec<MemberAttribute>,
+}
+
+fn method() -> impl Strategy<Value = Method> {
+    (
+        any::<u16>(),
+        prop_oneof![
+            unqualified_name(),
+            Just("<init>".to_string()),
+            Just("<clinit>".to_string()),
+        ],
+        method_descriptor(),
+        code(),
+        vec(class_name(), 0..3),
+        vec(member_attribute(), 0..3),
+    )
+        .prop_map(
+            |(flags, name, descriptor, code, exceptions, attributes)| Method {
+                flags,
+                name,
+                descriptor,
+                code,
+                exceptions,
+                attributes,
+            },
+        )
+}
+
+impl Method {
+    fn build(&self, cp: &mut PoolBuilder) -> MethodInfo {
+        let access_flags = MethodAccessFlags::from_bits_truncate(self.flags);
+        let name_index = cp.utf8(&self.name);
+        let descriptor_index = cp.utf8(&self.descriptor);
+        let mut attributes = vec![];
+        if !access_flags.intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::NATIVE) {
+            attributes.push(self.code.build(cp));
+        }
+        if !self.exceptions.is_empty() {
+            let name = cp.utf8("Exceptions");
+            let exception_index_table: Vec<_> = self
+                .exceptions
+                .iter()
+                .map(|class| cp.class(class))
+                .collect();
+            attributes.push(sized(|length| AttributeInfo::Exceptions {
+                attribute_name_index: name,
+                attribute_length: length,
+                exception_index_table: exception_index_table.clone(),
+            }));
+        }
+        attributes.extend(self.attributes.iter().map(|a| a.build(cp)));
+        MethodInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ClassAttribute {
+    Member(MemberAttribute),
+    SourceFile(String),
+    /// The inner classes, with their outer classes, simple names and flags.
+    InnerClasses(Vec<(String, Option<String>, Option<String>, u16)>),
+    NestHost(String),
+    NestMembers(Vec<String>),
+    PermittedSubclasses(Vec<String>),
+}
+
+fn class_attribute() -> impl Strategy<Value = ClassAttribute> {
+    prop_oneof![
+        member_attribute().prop_map(ClassAttribute::Member),
+        any::<String>().prop_map(ClassAttribute::SourceFile),
+        vec(
+            (
+                class_name(),
+                option::of(class_name()),
+                option::of(unqualified_name()),
+                any::<u16>(),
+            ),
+            0..4,
+        )
+        .prop_map(ClassAttribute::InnerClasses),
+        class_name().prop_map(ClassAttribute::NestHost),
+        vec(class_name(), 0..4).prop_map(ClassAttribute::NestMembers),
+        vec(class_name(), 0..4).prop_map(ClassAttribute::PermittedSubclasses),
+    ]
+}
+
+impl ClassAttribute {
+    fn build(&self, cp: &mut PoolBuilder) -> AttributeInfo {
+        let classes = |cp: &mut PoolBuilder, names: &[String]| -> Vec<u16> {
+            names.iter().map(|name| cp.class(name)).collect()
+        };
+        match self {
+            ClassAttribute::Member(attribute) => attribute.build(cp),
+            ClassAttribute::SourceFile(file) => {
+                let name = cp.utf8("SourceFile");
+                let sourcefile_index = cp.utf8(file);
+                sized(|length| AttributeInfo::SourceFile {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    sourcefile_index,
+                })
+            }
+            ClassAttribute::InnerClasses(inner_classes) => {
+                let name = cp.utf8("InnerClasses");
+                let entries: Vec<_> = inner_classes
+                    .iter()
+                    .map(|(inner, outer, simple_name, flags)| {
+                        (
+                            cp.class(inner),
+                            outer.as_ref().map_or(0, |outer| cp.class(outer)),
+                            simple_name.as_ref().map_or(0, |name| cp.utf8(name)),
+                            InnerClassAccessFlags::from_bits_truncate(*flags),
+                        )
+                    })
+                    .collect();
+                sized(|length| AttributeInfo::InnerClasses {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    classes: entries
+                        .iter()
+                        .map(|(inner, outer, simple_name, flags)| InnerClass {
+                            inner_class_info_index: *inner,
+                            outer_class_info_index: *outer,
+                            inner_name_index: *simple_name,
+                            inner_class_access_flags: *flags,
+                        })
+                        .collect(),
+                })
+            }
+            ClassAttribute::NestHost(host) => {
+                let name = cp.utf8("NestHost");
+                let host_class_index = cp.class(host);
+                sized(|length| AttributeInfo::NestHost {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    host_class_index,
+                })
+            }
+            ClassAttribute::NestMembers(members) => {
+                let name = cp.utf8("NestMembers");
+                let members = classes(cp, members);
+                sized(|length| AttributeInfo::NestMembers {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    classes: members.clone(),
+                })
+            }
+            ClassAttribute::PermittedSubclasses(subclasses) => {
+                let name = cp.utf8("PermittedSubclasses");
+                let subclasses = classes(cp, subclasses);
+                sized(|length| AttributeInfo::PermittedSubclasses {
+                    attribute_name_index: name,
+                    attribute_length: length,
+                    classes: subclasses.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    major: u16,
+    flags: u16,
+    name: String,
+    super_name: Option<String>,
+    interfaces: Vec<String>,
+    constants: Vec<Constant>,
+    fields: Vec<Field>,
+    methods: Vec<Method>,
+    attributes: Vec<ClassAttribute>,
+}
+
+impl Class {
+    fn build(&self) -> ClassFile {
+        let mut cp = PoolBuilder::new();
+        let mut access_flags = ClassAccessFlags::from_bits_truncate(self.flags);
+        if access_flags.contains(ClassAccessFlags::INTERFACE) {
+            access_flags |= ClassAccessFlags::ABSTRACT;
+        }
+        let this_class = cp.class(&self.name);
+        let super_class = self.super_name.as_ref().map_or(0, |name| cp.class(name));
+        let interfaces = self.interfaces.iter().map(|name| cp.class(name)).collect();
+        for constant in &self.constants {
+            constant.build(&mut cp);
+        }
+        let fields = self.fields.iter().map(|f| f.build(&mut cp)).collect();
+        let methods = self.methods.iter().map(|m| m.build(&mut cp)).collect();
+        let mut attributes: Vec<_> = self.attributes.iter().map(|a| a.build(&mut cp)).collect();
+        if cp.uses_bootstrap_method {
+            let name = cp.utf8("BootstrapMethods");
+            let (class, method, descriptor) = METAFACTORY;
+            let reference_index = cp.member(ReferenceKind::InvokeStatic, class, method, descriptor);
+            let bootstrap_method_ref = cp.add(ConstantPoolInfo::MethodHandleInfo {
+                reference_kind: ReferenceKind::InvokeStatic,
+                reference_index,
+            });
+            attributes.push(sized(|length| AttributeInfo::BootstrapMethods {
+                attribute_name_index: name,
+                attribute_length: length,
+                bootstrap_methods: vec![BootstrapMethod {
+                    bootstrap_method_ref,
+                    bootstrap_arguments: vec![],
+                }],
+            }));
+        }
+        ClassFile {
+            magic: 0xCAFEBABE,
+            version: Version::new(self.major, 0),
+            cp_info: cp.build(),
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::ParseMode;
+
+    proptest! {
+        #[test]
+        fn test_class_file_round_trip(class_file in any::<ClassFile>()) {
+            let mut bytes = vec![];
+            class_file.write(&mut bytes).unwrap();
+            prop_assert_eq!(&class_file, &ClassFile::from_bytes(&bytes).unwrap());
+
+            let mut rewritten = vec![];
+            ClassFile::parse_with(&mut bytes.as_slice(), ParseMode::Lazy)
+                .unwrap()
+                .write(&mut rewritten)
+                .unwrap();
+            prop_assert_eq!(bytes, rewritten);
+        }
+
+        #[test]
+        fn test_class_file_is_valid(class_file in any::<ClassFile>()) {
+            prop_assert_eq!(Vec::<String>::new(), class_file
+                .validate()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn test_code_decodes(class_file in any::<ClassFile>()) {
+            let mut bytes = vec![];
+            class_file.write(&mut bytes).unwrap();
+            prop_assert!(ClassFile::parse_decoded(&mut bytes.as_slice(), ParseMode::Eager).is_ok());
+        }
+
+        #[test]
+        fn test_constant_round_trip(info in any::<ConstantPoolInfo>()) {
+            let mut bytes = vec![];
+            info.write(&mut bytes).unwrap();
+            prop_assert_eq!(info, ConstantPoolInfo::parse(&mut bytes.as_slice()).unwrap());
+        }
+
+        #[test]
+        fn test_attribute_length(attribute in any::<AttributeInfo>()) {
+            let mut bytes = vec![];
+            attribute.write(&mut bytes).unwrap();
+            let (_, length) = attribute.header();
+            prop_assert_eq!(bytes.len(), length as usize + 6);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use crate::classfile::{
+    AttributeInfo, BootstrapMethod, ClassFile, ClassFileParseError, ConstantPool,
+    ConstantPoolError, ConstantPoolInfo, MemberRef, ReferenceKind,
+};
+use crate::prelude::*;
+
+/// A `CONSTANT_InvokeDynamic` or `CONSTANT_Dynamic` entry joined with its
+/// bootstrap method from the `BootstrapMethods` attribute ([`$4.7.23`]), with
+/// the method handle and the static arguments resolved from the constant pool,
+/// see [`ClassFile::dynamic_reference`].
+///
+/// [`$4.7.23`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.23
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicReference {
+    /// The index of the bootstrap method in the `BootstrapMethods` attribute,
+    /// which call sites and constants with the same bootstrap method share.
+    pub bootstrap_method_attr_index: u16,
+    /// The name of the call site or constant, e.g. `get` for a lambda that
+    /// implements a `Supplier`.
+    pub name: String,
+    /// The method descriptor of the call site, or the field descriptor of the
+    /// constant.
+    pub descriptor: String,
+    pub bootstrap_method: MethodHandle,
+    /// The static arguments, in the order of the class file.
+    pub arguments: Vec<BootstrapArgument>,
+}
+
+/// A resolved `CONSTANT_MethodHandle` entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MethodHandle {
+    pub reference_kind: ReferenceKind,
+    /// The name of the class of the member, e.g.
+    /// `java/lang/invoke/LambdaMetafactory`.
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// A static argument of a bootstrap method, which is a loadable constant
+/// ([`$4.4`]).
+///
+/// [`$4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4
+#[derive(Debug, Clone, PartialEq)]
+pub enum BootstrapArgument {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    /// The name of a class, e.g. `java/lang/String`.
+    Class(String),
+    String(String),
+    MethodHandle(MethodHandle),
+    /// A method descriptor, e.g. `()Ljava/lang/Object;`.
+    MethodType(String),
+    /// Another dynamically-computed constant, by its index in the constant
+    /// pool. It isn't resolved here, since it can have arguments of its own,
+    /// see [`ClassFile::dynamic_reference`].
+    Dynamic(u16),
+}
+
+/// An error of [`ClassFile::dynamic_reference`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BootstrapError {
+    /// The `BootstrapMethods` attribute was kept unparsed (see
+    /// [`ParseMode::Lazy`](crate::classfile::ParseMode::Lazy)) and is
+    /// malformed.
+    Malformed(ClassFileParseError),
+    /// The entry refers to a missing or unexpected constant.
+    ConstantPool(ConstantPoolError),
+    /// There is no bootstrap method with the given index, or no
+    /// `BootstrapMethods` attribute at all.
+    NoBootstrapMethod(u16),
+}
+
+impl From<ClassFileParseError> for BootstrapError {
+    fn from(error: ClassFileParseError) -> Self {
+        BootstrapError::Malformed(error)
+    }
+}
+
+impl From<ConstantPoolError> for BootstrapError {
+    fn from(error: ConstantPoolError) -> Self {
+        BootstrapError::ConstantPool(error)
+    }
+}
+
+impl BootstrapMethod {
+    /// The index of the `CONSTANT_MethodHandle` entry of the bootstrap method.
+    pub fn bootstrap_method_ref(&self) -> u16 {
+        self.bootstrap_method_ref
+    }
+
+    /// The indices of the static arguments in the constant pool.
+    pub fn bootstrap_arguments(&self) -> &[u16] {
+        &self.bootstrap_arguments
+    }
+}
+
+impl ClassFile {
+    /// The bootstrap methods from the `BootstrapMethods` attribute, which is
+    /// empty if there is no such attribute.
+    pub fn bootstrap_methods(&self) -> Result<&[BootstrapMethod], ClassFileParseError> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::BootstrapMethods {
+                bootstrap_methods, ..
+            } = attribute.materialize(&self.cp_info)?
+            {
+                return Ok(bootstrap_methods);
+            }
+        }
+        Ok(&[])
+    }
+
+    /// Joins the `CONSTANT_InvokeDynamic` or `CONSTANT_Dynamic` entry at the
+    /// given index with its bootstrap method, and resolves the method handle
+    /// and the static arguments of the bootstrap method, which is what the
+    /// resolution of a call site or dynamically-computed constant starts with
+    /// ([`$5.4.3.6`]).
+    ///
+    /// [`$5.4.3.6`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.6
+    pub fn dynamic_reference(&self, index: u16) -> Result<DynamicReference, BootstrapError> {
+        let cp = &self.cp_info;
+        let (bootstrap_method_attr_index, name_and_type_index) = match cp.get_entry(index)? {
+            ConstantPoolInfo::InvokeDynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::DynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => (*bootstrap_method_attr_index, *name_and_type_index),
+            _ => return Err(ConstantPoolError::unexpected(index, "InvokeDynamic").into()),
+        };
+        let name_and_type = cp.get_name_and_type(name_and_type_index)?;
+        let bootstrap_method = self
+            .bootstrap_methods()?
+            .get(bootstrap_method_attr_index as usize)
+            .ok_or(BootstrapError::NoBootstrapMethod(
+                bootstrap_method_attr_index,
+            ))?;
+        Ok(DynamicReference {
+            bootstrap_method_attr_index,
+            name: name_and_type.name.into_owned(),
+            descriptor: name_and_type.descriptor.into_owned(),
+            bootstrap_method: MethodHandle::resolve(cp, bootstrap_method.bootstrap_method_ref)?,
+            arguments: bootstrap_method
+                .bootstrap_arguments
+                .iter()
+                .map(|argument| BootstrapArgument::resolve(cp, *argument))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl MethodHandle {
+    fn resolve(cp: &ConstantPool, index: u16) -> Result<MethodHandle, ConstantPoolError> {
+        let ConstantPoolInfo::MethodHandleInfo {
+            reference_kind,
+            reference_index,
+        } = cp.get_entry(index)?
+        else {
+            return Err(ConstantPoolError::unexpected(index, "MethodHandle"));
+        };
+        let MemberRef {
+            class_name,
+            name,
+            descriptor,
+        } = match cp.get_entry(*reference_index)? {
+            ConstantPoolInfo::FieldrefInfo {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::MethodrefInfo {
+                class_index,
+                name_and_type_index,
+            }
+            | ConstantPoolInfo::InterfaceMethodrefInfo {
+                class_index,
+                name_and_type_index,
+            } => cp.member_ref(*class_index, *name_and_type_index)?,
+            _ => return Err(ConstantPoolError::unexpected(*reference_index, "Methodref")),
+        };
+        Ok(MethodHandle {
+            reference_kind: *reference_kind,
+            class_name: class_name.into_owned(),
+            name: name.into_owned(),
+            descriptor: descriptor.into_owned(),
+        })
+    }
+}
+
+impl BootstrapArgument {
+    fn resolve(cp: &ConstantPool, index: u16) -> Result<BootstrapArgument, ConstantPoolError> {
+        Ok(match cp.get_entry(index)? {
+            ConstantPoolInfo::IntegerInfo { .. } => {
+                BootstrapArgument::Integer(cp.get_integer(index)?)
+            }
+            ConstantPoolInfo::FloatInfo { .. } => BootstrapArgument::Float(cp.get_float(index)?),
+            ConstantPoolInfo::LongInfo { .. } => BootstrapArgument::Long(cp.get_long(index)?),
+            ConstantPoolInfo::DoubleInfo { .. } => BootstrapArgument::Double(cp.get_double(index)?),
+            ConstantPoolInfo::ClassInfo { .. } => {
+                BootstrapArgument::Class(cp.get_class_name(index)?.into_owned())
+            }
+            ConstantPoolInfo::StringInfo { .. } => {
+                BootstrapArgument::String(cp.get_string(index)?.into_owned())
+            }
+            ConstantPoolInfo::MethodHandleInfo { .. } => {
+                BootstrapArgument::MethodHandle(MethodHandle::resolve(cp, index)?)
+            }
+            ConstantPoolInfo::MethodTypeInfo { descriptor_index } => {
+                BootstrapArgument::MethodType(cp.get_utf8(*descriptor_index)?.into_owned())
+            }
+            ConstantPoolInfo::DynamicInfo { .. } => BootstrapArgument::Dynamic(index),
+            _ => return Err(ConstantPoolError::unexpected(index, "loadable constant")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::{parse, MODES};
+    use crate::classfile::ConstantPoolInfo::{DynamicInfo, InvokeDynamicInfo};
+    use crate::classfile::ParseMode;
+
+    /// The indices of the `CONSTANT_InvokeDynamic` entries.
+    fn call_sites(class_file: &ClassFile) -> Vec<u16> {
+        (1..=class_file.constant_pool().len() as u16)
+            .filter(|i| {
+                matches!(
+                    class_file.constant_pool().get(*i),
+                    Some(InvokeDynamicInfo { .. } | DynamicInfo { .. })
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lambda() {
+        for mode in MODES {
+            let class_file = parse("Formats", mode);
+            let sites = call_sites(&class_file);
+            assert_eq!(1, sites.len());
+            let reference = class_file.dynamic_reference(sites[0]).unwrap();
+            assert_eq!("get", reference.name);
+            assert!(reference
+                .descriptor
+                .ends_with(")Ljava/util/function/Supplier;"));
+            assert_eq!(
+                MethodHandle {
+                    reference_kind: ReferenceKind::InvokeStatic,
+                    class_name: "java/lang/invoke/LambdaMetafactory".to_string(),
+                    name: "metafactory".to_string(),
+                    descriptor: "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;".to_string(),
+                },
+                reference.bootstrap_method
+            );
+            let [BootstrapArgument::MethodType(erased), BootstrapArgument::MethodHandle(implementation), BootstrapArgument::MethodType(instantiated)] =
+                &reference.arguments[..]
+            else {
+                panic!("unexpected arguments {:?}", reference.arguments);
+            };
+            assert_eq!("()Ljava/lang/Object;", erased);
+            assert_eq!("Formats", implementation.class_name);
+            assert!(implementation.name.starts_with("lambda$"));
+            assert!(instantiated.starts_with("()"));
+        }
+    }
+
+    #[test]
+    fn test_record_methods() {
+        let class_file = parse("Records", ParseMode::Eager);
+        let references: Vec<DynamicReference> = call_sites(&class_file)
+            .into_iter()
+            .map(|i| class_file.dynamic_reference(i).unwrap())
+            .collect();
+        let mut names: Vec<&str> = references.iter().map(|r| r.name.as_str()).collect();
+        names.sort();
+        assert_eq!(vec!["equals", "hashCode", "toString"], names);
+        for reference in &references {
+            assert_eq!(
+                "java/lang/runtime/ObjectMethods",
+                reference.bootstrap_method.class_name
+            );
+            assert_eq!("bootstrap", reference.bootstrap_method.name);
+            // the record class, the names of the components and their getters
+            assert_eq!(
+                Some(&BootstrapArgument::Class("Records".to_string())),
+                reference.arguments.first()
+            );
+            assert!(matches!(
+                reference.arguments[1],
+                BootstrapArgument::String(_)
+            ));
+            assert!(reference.arguments[2..].iter().all(|a| matches!(
+                a,
+                BootstrapArgument::MethodHandle(MethodHandle {
+                    reference_kind: ReferenceKind::GetField,
+                    ..
+                })
+            )));
+        }
+        assert_eq!(1, class_file.bootstrap_methods().unwrap().len());
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        let class_file = parse("Foo", ParseMode::Eager);
+        assert!(class_file.bootstrap_methods().unwrap().is_empty());
+        assert_eq!(
+            Err(BootstrapError::ConstantPool(
+                ConstantPoolError::UnexpectedEntry {
+                    index: 1,
+                    expected: "InvokeDynamic"
+                }
+            )),
+            class_file.dynamic_reference(1)
+        );
+        assert_eq!(
+            Err(BootstrapError::ConstantPool(
+                ConstantPoolError::IndexOutOfBounds(0)
+            )),
+            class_file.dynamic_reference(0)
+        );
+    }
+}
@@ -0,0 +1,757 @@
+use crate::bytecode::Op;
+use crate::classfile::{
+    Annotation, AttributeInfo, ClassFile, ClassFileParseError, ConstantPool, ConstantPoolInfo,
+    ElementValue, ElementValuePair, StackMapFrame, TypeAnnotation, VerificationTypeInfo,
+};
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+
+impl ClassFile {
+    /// Merges identical constant pool entries, so that a class file that was
+    /// generated or modified after parsing isn't bloated with duplicates when
+    /// it is written with [`ClassFile::write`].
+    ///
+    /// Entries are identical if they have the same contents after their own
+    /// references were merged, e.g. two `CONSTANT_Class` entries whose names
+    /// are equal `CONSTANT_Utf8` entries. The first of them is kept, and
+    /// every index in the class file is rewritten, including the operands of
+    /// instructions. Entries that nothing refers to are kept as well.
+    ///
+    /// Lazy attributes are parsed first, which fails if any of them is
+    /// malformed; the class file is unchanged then. The contents of
+    /// [`AttributeInfo::Unknown`] attributes are kept as they are, so like any
+    /// other change of the constant pool, this breaks them if they refer to it.
+    pub fn deduplicate_constants(&mut self) -> Result<(), ClassFileParseError> {
+        let cp = &self.cp_info;
+        for attributes in self
+            .fields
+            .iter_mut()
+            .map(|f| &mut f.attributes)
+            .chain(self.methods.iter_mut().map(|m| &mut m.attributes))
+            .chain([&mut self.attributes])
+        {
+            materialize_all(cp, attributes)?;
+        }
+
+        let (cp, remap) = deduplicate(&self.cp_info);
+        self.cp_info = cp;
+        remap.class_file(self);
+        Ok(())
+    }
+}
+
+/// Replaces all lazy attributes, also nested ones, with their parsed
+/// contents, since their raw bytes can't be remapped.
+fn materialize_all(
+    cp: &ConstantPool,
+    attributes: &mut [AttributeInfo],
+) -> Result<(), ClassFileParseError> {
+    for attribute in attributes {
+        if let AttributeInfo::Lazy(lazy) = attribute {
+            *attribute = lazy.parse(cp)?;
+        }
+        match attribute {
+            AttributeInfo::Code { attributes, .. } => materialize_all(cp, attributes)?,
+            AttributeInfo::Record { components, .. } => {
+                for component in components {
+                    materialize_all(cp, &mut component.attributes)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the constant pool without duplicates, and the mapping from the
+/// indices of the old pool to the new one.
+fn deduplicate(cp: &ConstantPool) -> (ConstantPool, Remap<impl Fn(u16) -> u16>) {
+    // the first of the identical entries for every index, which is repeated
+    // until merging references doesn't make any more entries identical
+    let mut canonical: Vec<u16> = (0..=cp.items.len() as u16).collect();
+    loop {
+        let mut first: BTreeMap<Vec<u8>, u16> = BTreeMap::new();
+        let mut changed = false;
+        for (i, info) in cp.items.iter().enumerate() {
+            if let ConstantPoolInfo::Unusable = info {
+                continue;
+            }
+            let index = i as u16 + 1;
+            let first = *first
+                .entry(key(&with_indices(info, |i| canonical[i as usize])))
+                .or_insert(index);
+            if canonical[index as usize] != first {
+                canonical[index as usize] = first;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // entries only move towards the start of the pool, so the index of an
+    // `ldc` still fits into a byte
+    let mut indices = vec![0; cp.items.len() + 1];
+    let mut next = 1;
+    for (i, info) in cp.items.iter().enumerate() {
+        let index = i + 1;
+        indices[index] = match info {
+            ConstantPoolInfo::Unusable => indices[index - 1] + 1,
+            _ if canonical[index] as usize == index => {
+                next += info.slots() as u16;
+                next - info.slots() as u16
+            }
+            _ => indices[canonical[index] as usize],
+        };
+    }
+    let remap = table(indices);
+
+    let entries = cp
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(i, info)| {
+            !matches!(info, ConstantPoolInfo::Unusable) && canonical[i + 1] as usize == i + 1
+        })
+        .map(|(_, info)| with_indices(info, |i| remap.get(i)))
+        .collect();
+    (ConstantPool::from(entries), remap)
+}
+
+/// The bytes of the entry in the class file, which are equal exactly if the
+/// entries are.
+pub(super) fn key(info: &ConstantPoolInfo) -> Vec<u8> {
+    let mut bytes = vec![];
+    info.write(&mut bytes).expect("writing to a Vec can't fail");
+    bytes
+}
+
+/// Returns a copy of the entry, with the constant pool indices that it
+/// contains mapped with the given function.
+pub(super) fn with_indices(info: &ConstantPoolInfo, f: impl Fn(u16) -> u16) -> ConstantPoolInfo {
+    use ConstantPoolInfo::*;
+    match info {
+        ClassInfo { name_index } => ClassInfo {
+            name_index: f(*name_index),
+        },
+        FieldrefInfo {
+            class_index,
+            name_and_type_index,
+        } => FieldrefInfo {
+            class_index: f(*class_index),
+            name_and_type_index: f(*name_and_type_index),
+        },
+        MethodrefInfo {
+            class_index,
+            name_and_type_index,
+        } => MethodrefInfo {
+            class_index: f(*class_index),
+            name_and_type_index: f(*name_and_type_index),
+        },
+        InterfaceMethodrefInfo {
+            class_index,
+            name_and_type_index,
+        } => InterfaceMethodrefInfo {
+            class_index: f(*class_index),
+            name_and_type_index: f(*name_and_type_index),
+        },
+        StringInfo { string_index } => StringInfo {
+            string_index: f(*string_index),
+        },
+        IntegerInfo { bytes } => IntegerInfo { bytes: *bytes },
+        FloatInfo { bytes } => FloatInfo { bytes: *bytes },
+        LongInfo {
+            high_bytes,
+            low_bytes,
+        } => LongInfo {
+            high_bytes: *high_bytes,
+            low_bytes: *low_bytes,
+        },
+        DoubleInfo {
+            high_bytes,
+            low_bytes,
+        } => DoubleInfo {
+            high_bytes: *high_bytes,
+            low_bytes: *low_bytes,
+        },
+        NameAndTypeInfo {
+            name_index,
+            descriptor_index,
+        } => NameAndTypeInfo {
+            name_index: f(*name_index),
+            descriptor_index: f(*descriptor_index),
+        },
+        Utf8Info { length, bytes } => Utf8Info {
+            length: *length,
+            bytes: bytes.clone(),
+        },
+        MethodHandleInfo {
+            reference_kind,
+            reference_index,
+        } => MethodHandleInfo {
+            reference_kind: *reference_kind,
+            reference_index: f(*reference_index),
+        },
+        MethodTypeInfo { descriptor_index } => MethodTypeInfo {
+            descriptor_index: f(*descriptor_index),
+        },
+        // the bootstrap method index refers to the BootstrapMethods attribute
+        DynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => DynamicInfo {
+            bootstrap_method_attr_index: *bootstrap_method_attr_index,
+            name_and_type_index: f(*name_and_type_index),
+        },
+        InvokeDynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => InvokeDynamicInfo {
+            bootstrap_method_attr_index: *bootstrap_method_attr_index,
+            name_and_type_index: f(*name_and_type_index),
+        },
+        ModuleInfo { name_index } => ModuleInfo {
+            name_index: f(*name_index),
+        },
+        PackageInfo { name_index } => PackageInfo {
+            name_index: f(*name_index),
+        },
+        Unusable => Unusable,
+    }
+}
+
+/// The mapping from the indices of the old constant pool to the new one, by
+/// old index. Index 0, which stands for "none" in many places, stays 0, and
+/// indices that are out of bounds are kept, so that they are still reported
+/// as invalid.
+fn table(indices: Vec<u16>) -> Remap<impl Fn(u16) -> u16> {
+    Remap::new(move |index| indices.get(index as usize).copied().unwrap_or(index))
+}
+
+/// Rewrites the constant pool indices of a class file with a function, from
+/// the indices of the old constant pool to the new one.
+pub(super) struct Remap<F> {
+    map: F,
+}
+
+impl<F: Fn(u16) -> u16> Remap<F> {
+    pub(super) fn new(map: F) -> Self {
+        Self { map }
+    }
+
+    fn get(&self, index: u16) -> u16 {
+        (self.map)(index)
+    }
+
+    fn index(&self, index: &mut u16) {
+        *index = self.get(*index);
+    }
+
+    fn indices(&self, indices: &mut [u16]) {
+        indices.iter_mut().for_each(|index| self.index(index));
+    }
+
+    /// Rewrites every index outside of the constant pool itself.
+    fn class_file(&self, class_file: &mut ClassFile) {
+        self.index(&mut class_file.this_class);
+        self.index(&mut class_file.super_class);
+        self.indices(&mut class_file.interfaces);
+        for field in &mut class_file.fields {
+            self.index(&mut field.name_index);
+            self.index(&mut field.descriptor_index);
+            self.attributes(&mut field.attributes);
+        }
+        for method in &mut class_file.methods {
+            self.index(&mut method.name_index);
+            self.index(&mut method.descriptor_index);
+            self.attributes(&mut method.attributes);
+        }
+        self.attributes(&mut class_file.attributes);
+    }
+
+    fn attributes(&self, attributes: &mut [AttributeInfo]) {
+        attributes.iter_mut().for_each(|a| self.attribute(a));
+    }
+
+    pub(super) fn attribute(&self, attribute: &mut AttributeInfo) {
+        self.index(name_index_mut(attribute));
+        match attribute {
+            AttributeInfo::ConstantValue {
+                constantvalue_index,
+                ..
+            } => self.index(constantvalue_index),
+            AttributeInfo::Code {
+                code,
+                ops,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                self.code(code);
+                if ops.is_some() {
+                    *ops = decode(code);
+                }
+                for entry in exception_table {
+                    self.index(&mut entry.catch_type);
+                }
+                self.attributes(attributes);
+            }
+            AttributeInfo::StackMapTable { entries, .. } => {
+                for frame in entries {
+                    match frame {
+                        StackMapFrame::SameLocals1StackItem { stack, .. }
+                        | StackMapFrame::SameLocals1StackItemExtended { stack, .. } => {
+                            self.verification_type(stack)
+                        }
+                        StackMapFrame::Append { locals, .. } => {
+                            locals.iter_mut().for_each(|t| self.verification_type(t))
+                        }
+                        StackMapFrame::Full { locals, stack, .. } => locals
+                            .iter_mut()
+                            .chain(stack)
+                            .for_each(|t| self.verification_type(t)),
+                        StackMapFrame::Same { .. }
+                        | StackMapFrame::Chop { .. }
+                        | StackMapFrame::SameExtended { .. } => {}
+                    }
+                }
+            }
+            AttributeInfo::Exceptions {
+                exception_index_table,
+                ..
+            } => self.indices(exception_index_table),
+            AttributeInfo::InnerClasses { classes, .. } => {
+                for class in classes {
+                    self.index(&mut class.inner_class_info_index);
+                    self.index(&mut class.outer_class_info_index);
+                    self.index(&mut class.inner_name_index);
+                }
+            }
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+                ..
+            } => {
+                self.index(class_index);
+                self.index(method_index);
+            }
+            AttributeInfo::Signature {
+                signature_index, ..
+            } => self.index(signature_index),
+            AttributeInfo::SourceFile {
+                sourcefile_index, ..
+            } => self.index(sourcefile_index),
+            AttributeInfo::LocalVariableTable {
+                local_variable_table,
+                ..
+            } => {
+                for entry in local_variable_table {
+                    self.index(&mut entry.name_index);
+                    self.index(&mut entry.descriptor_index);
+                }
+            }
+            AttributeInfo::LocalVariableTypeTable {
+                local_variable_type_table,
+                ..
+            } => {
+                for entry in local_variable_type_table {
+                    self.index(&mut entry.name_index);
+                    self.index(&mut entry.signature_index);
+                }
+            }
+            AttributeInfo::RuntimeVisibleAnnotations { annotations, .. }
+            | AttributeInfo::RuntimeInvisibleAnnotations { annotations, .. } => {
+                annotations.iter_mut().for_each(|a| self.annotation(a))
+            }
+            AttributeInfo::RuntimeVisibleParameterAnnotations {
+                parameter_annotations,
+                ..
+            }
+            | AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                parameter_annotations,
+                ..
+            } => parameter_annotations
+                .iter_mut()
+                .flatten()
+                .for_each(|a| self.annotation(a)),
+            AttributeInfo::RuntimeVisibleTypeAnnotations { annotations, .. }
+            | AttributeInfo::RuntimeInvisibleTypeAnnotations { annotations, .. } => {
+                annotations.iter_mut().for_each(|a| self.type_annotation(a))
+            }
+            AttributeInfo::AnnotationDefault { default_value, .. } => {
+                self.element_value(default_value)
+            }
+            AttributeInfo::BootstrapMethods {
+                bootstrap_methods, ..
+            } => {
+                for method in bootstrap_methods {
+                    self.index(&mut method.bootstrap_method_ref);
+                    self.indices(&mut method.bootstrap_arguments);
+                }
+            }
+            AttributeInfo::MethodParameters { parameters, .. } => {
+                for parameter in parameters {
+                    self.index(&mut parameter.name_index);
+                }
+            }
+            AttributeInfo::Module {
+                module_name_index,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses_index,
+                provides,
+                ..
+            } => {
+                self.index(module_name_index);
+                self.index(module_version_index);
+                for r in requires {
+                    self.index(&mut r.requires_index);
+                    self.index(&mut r.requires_version_index);
+                }
+                for e in exports {
+                    self.index(&mut e.exports_index);
+                    self.indices(&mut e.exports_to_index);
+                }
+                for o in opens {
+                    self.index(&mut o.opens_index);
+                    self.indices(&mut o.opens_to_index);
+                }
+                self.indices(uses_index);
+                for p in provides {
+                    self.index(&mut p.provides_index);
+                    self.indices(&mut p.provides_with_index);
+                }
+            }
+            AttributeInfo::ModulePackages { package_index, .. } => self.indices(package_index),
+            AttributeInfo::ModuleMainClass {
+                main_class_index, ..
+            } => self.index(main_class_index),
+            AttributeInfo::NestHost {
+                host_class_index, ..
+            } => self.index(host_class_index),
+            AttributeInfo::NestMembers { classes, .. }
+            | AttributeInfo::PermittedSubclasses { classes, .. } => self.indices(classes),
+            AttributeInfo::Record { components, .. } => {
+                for component in components {
+                    self.index(&mut component.name_index);
+                    self.index(&mut component.descriptor_index);
+                    self.attributes(&mut component.attributes);
+                }
+            }
+            AttributeInfo::Synthetic { .. }
+            | AttributeInfo::SourceDebugExtension { .. }
+            | AttributeInfo::LineNumberTable { .. }
+            | AttributeInfo::Deprecated { .. }
+            | AttributeInfo::Unknown { .. }
+            | AttributeInfo::Lazy(_) => {}
+        }
+    }
+
+    /// Rewrites the constant pool indices in the operands of the
+    /// instructions. Instructions don't change their size, so branch offsets
+    /// stay valid. Code that can't be decoded is left as it is from the
+    /// first invalid instruction on.
+    fn code(&self, code: &mut [u8]) {
+        let mut offset = 0;
+        while offset < code.len() {
+            let mut rest = &code[offset..];
+            let Ok(op) = Op::parse_at(&mut rest, offset) else {
+                return;
+            };
+            let length = match op {
+                // the modified instruction is parsed with narrow operands
+                Op::Wide if code.get(offset + 1) == Some(&0x84) => 6,
+                Op::Wide => 4,
+                _ => code.len() - offset - rest.len(),
+            };
+            match code[offset] {
+                // ldc
+                0x12 => code[offset + 1] = self.get(code[offset + 1] as u16) as u8,
+                // ldc_w, ldc2_w, the field and invoke instructions, new,
+                // anewarray, checkcast, instanceof and multianewarray
+                0x13 | 0x14 | 0xB2..=0xBB | 0xBD | 0xC0 | 0xC1 | 0xC5 => {
+                    let index = u16::from_be_bytes([code[offset + 1], code[offset + 2]]);
+                    code[offset + 1..offset + 3].copy_from_slice(&self.get(index).to_be_bytes());
+                }
+                _ => {}
+            }
+            offset += length;
+        }
+    }
+
+    fn verification_type(&self, info: &mut VerificationTypeInfo) {
+        if let VerificationTypeInfo::ObjectVariable { cpool_index, .. } = info {
+            self.index(cpool_index);
+        }
+    }
+
+    fn annotation(&self, annotation: &mut Annotation) {
+        self.index(&mut annotation.type_index);
+        self.element_value_pairs(&mut annotation.element_value_pairs);
+    }
+
+    fn type_annotation(&self, annotation: &mut TypeAnnotation) {
+        self.index(&mut annotation.type_index);
+        self.element_value_pairs(&mut annotation.element_value_pairs);
+    }
+
+    fn element_value_pairs(&self, pairs: &mut [ElementValuePair]) {
+        for pair in pairs {
+            self.index(&mut pair.element_name_index);
+            self.element_value(&mut pair.value);
+        }
+    }
+
+    fn element_value(&self, value: &mut ElementValue) {
+        match value {
+            ElementValue::ConstValueIndex {
+                const_value_index, ..
+            } => self.index(const_value_index),
+            ElementValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                self.index(type_name_index);
+                self.index(const_name_index);
+            }
+            ElementValue::ClassInfoIndex(index) => self.index(index),
+            ElementValue::AnnotationValue(annotation) => self.annotation(annotation),
+            ElementValue::ArrayValue { values } => {
+                values.iter_mut().for_each(|v| self.element_value(v))
+            }
+        }
+    }
+}
+
+fn name_index_mut(attribute: &mut AttributeInfo) -> &mut u16 {
+    match attribute {
+        AttributeInfo::ConstantValue {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Code {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::StackMapTable {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Exceptions {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::InnerClasses {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::EnclosingMethod {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Synthetic {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Signature {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::SourceFile {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::SourceDebugExtension {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::LineNumberTable {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::LocalVariableTable {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::LocalVariableTypeTable {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Deprecated {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeVisibleAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeInvisibleAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeVisibleParameterAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeInvisibleParameterAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeVisibleTypeAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::RuntimeInvisibleTypeAnnotations {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::AnnotationDefault {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::BootstrapMethods {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::MethodParameters {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Module {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::ModulePackages {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::ModuleMainClass {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::NestHost {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::NestMembers {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::Record {
+            attribute_name_index,
+            ..
+        }
+        | AttributeInfo::PermittedSubclasses {
+            attribute_name_index,
+            ..
+        } => attribute_name_index,
+        AttributeInfo::Unknown { name_index, .. } => name_index,
+        AttributeInfo::Lazy(lazy) => &mut lazy.name_index,
+    }
+}
+
+/// Decodes the remapped code again, see [`ClassFile::parse_decoded`].
+fn decode(code: &[u8]) -> Option<Vec<(u32, Op)>> {
+    let mut ops = vec![];
+    let mut rest = code;
+    while !rest.is_empty() {
+        let offset = code.len() - rest.len();
+        ops.push((offset as u32, Op::parse_at(&mut rest, offset).ok()?));
+    }
+    Some(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::ParseMode;
+
+    fn write(class_file: &ClassFile) -> Vec<u8> {
+        let mut bytes = vec![];
+        class_file.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_deduplicate_constants() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        let mut class_file = ClassFile::parse_with(&mut bytes.as_slice(), ParseMode::Lazy).unwrap();
+        let original = ClassFile::from_bytes(&bytes).unwrap();
+
+        // javac emits no duplicates, so this only parses the lazy attributes
+        class_file.deduplicate_constants().unwrap();
+        assert_eq!(bytes, write(&class_file));
+
+        // refer to the class through duplicates of its name and its
+        // `CONSTANT_Class` entry, which only becomes identical to the
+        // original once the names are merged
+        let this_class = class_file.this_class;
+        let ConstantPoolInfo::ClassInfo { name_index } =
+            *class_file.cp_info.get(this_class).unwrap()
+        else {
+            panic!("this_class is not a class");
+        };
+        let duplicate_name = class_file.cp_info.len() as u16 + 1;
+        let mut items = entries(&class_file.cp_info);
+        items.push(with_indices(
+            class_file.cp_info.get(name_index).unwrap(),
+            |i| i,
+        ));
+        items.push(ConstantPoolInfo::ClassInfo {
+            name_index: duplicate_name,
+        });
+        class_file.cp_info = ConstantPool::from(items);
+        class_file.this_class = duplicate_name + 1;
+        assert_ne!(bytes, write(&class_file));
+
+        class_file.deduplicate_constants().unwrap();
+        assert_eq!(original.constant_pool(), class_file.constant_pool());
+        assert_eq!(this_class, class_file.this_class);
+        assert_eq!(bytes, write(&class_file));
+    }
+
+    #[test]
+    fn test_deduplicate_rewrites_code() {
+        let bytes = std::fs::read("tests/resources/Constants.class").unwrap();
+        let parse = || ClassFile::parse_decoded(&mut bytes.as_slice(), ParseMode::Eager).unwrap();
+        let mut class_file = parse();
+
+        // insert a duplicate in front of the first `CONSTANT_Utf8` entry, so
+        // that the indices of all entries after it change
+        let first_utf8 = class_file
+            .cp_info
+            .iter()
+            .position(|info| matches!(info, ConstantPoolInfo::Utf8Info { .. }))
+            .unwrap() as u16
+            + 1;
+        let shift = table(
+            (0..=class_file.cp_info.len() as u16)
+                .map(|i| if i < first_utf8 { i } else { i + 1 })
+                .collect(),
+        );
+        let mut items: Vec<_> = entries(&class_file.cp_info)
+            .iter()
+            .map(|info| with_indices(info, |i| shift.get(i)))
+            .collect();
+        let duplicate = with_indices(class_file.cp_info.get(first_utf8).unwrap(), |i| i);
+        items.insert(first_utf8 as usize - 1, duplicate);
+        class_file.cp_info = ConstantPool::from(items);
+        shift.class_file(&mut class_file);
+        assert_ne!(bytes, write(&class_file));
+
+        class_file.deduplicate_constants().unwrap();
+        assert_eq!(bytes, write(&class_file));
+        assert_eq!(parse(), class_file);
+    }
+
+    /// The entries of the constant pool as they are in the class file.
+    fn entries(cp: &ConstantPool) -> Vec<ConstantPoolInfo> {
+        cp.iter()
+            .filter(|info| !matches!(info, ConstantPoolInfo::Unusable))
+            .map(|info| with_indices(info, |i| i))
+            .collect()
+    }
+}
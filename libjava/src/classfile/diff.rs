@@ -0,0 +1,365 @@
+use crate::classfile::dedup::{key, with_indices, Remap};
+use crate::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::classfile::{AttributeInfo, ClassFile, ConstantPool, FieldInfo, MethodInfo, Version};
+use crate::prelude::*;
+use core::cell::RefCell;
+
+/// The differences between two class files, see [`ClassFile::diff`]. Every
+/// pair is the value in the old class file and the value in the new one.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClassDiff {
+    pub version: Option<(Version, Version)>,
+    pub access_flags: Option<(ClassAccessFlags, ClassAccessFlags)>,
+    pub this_class: Option<(String, String)>,
+    pub super_class: Option<(Option<String>, Option<String>)>,
+    pub interfaces: Option<(Vec<String>, Vec<String>)>,
+    pub fields: Vec<MemberDiff<FieldAccessFlags>>,
+    pub methods: Vec<MemberDiff<MethodAccessFlags>>,
+    /// The changes of the attributes of the class itself.
+    pub attributes: Vec<AttributeDiff>,
+}
+
+impl ClassDiff {
+    /// Whether the class files are equal, apart from the layout of their
+    /// constant pools.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A field or method that was added, removed or changed, by its name and
+/// descriptor.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MemberDiff<F> {
+    Added {
+        name: String,
+        descriptor: String,
+    },
+    Removed {
+        name: String,
+        descriptor: String,
+    },
+    Changed {
+        name: String,
+        descriptor: String,
+        access_flags: Option<(F, F)>,
+        attributes: Vec<AttributeDiff>,
+    },
+}
+
+/// An attribute that was added, removed or changed, by its name. A `Code`
+/// attribute changes with any of the attributes nested in it, like its
+/// `LineNumberTable`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AttributeDiff {
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+impl ClassFile {
+    /// Compares this class file with a newer one, e.g. the class file that
+    /// the same source compiles to after a change of the source or the
+    /// compiler.
+    ///
+    /// Members are matched by their name and descriptor, and attributes by
+    /// their name. Constant pool entries are compared by their contents, not
+    /// by their index, so two class files whose constant pools are laid out
+    /// differently are equal if everything that refers to the pools is.
+    /// Removed and changed members and attributes are in the order of this
+    /// class file, followed by the added ones in the order of the other.
+    pub fn diff(&self, other: &ClassFile) -> ClassDiff {
+        let (old, new) = (&self.cp_info, &other.cp_info);
+        ClassDiff {
+            version: changed(self.version, other.version),
+            access_flags: changed(self.access_flags, other.access_flags),
+            this_class: changed(self.this_class(), other.this_class()),
+            super_class: changed(self.super_class(), other.super_class()),
+            interfaces: changed(self.interfaces(), other.interfaces()),
+            fields: diff_members(
+                self.fields.iter().map(field),
+                other.fields.iter().map(field),
+                old,
+                new,
+            ),
+            methods: diff_members(
+                self.methods.iter().map(method),
+                other.methods.iter().map(method),
+                old,
+                new,
+            ),
+            attributes: diff_attributes(&self.attributes, &other.attributes, old, new),
+        }
+    }
+}
+
+fn changed<T: PartialEq>(old: T, new: T) -> Option<(T, T)> {
+    (old != new).then_some((old, new))
+}
+
+/// A field or method by its name index, descriptor index, access flags and
+/// attributes.
+type Member<'a, F> = (u16, u16, F, &'a Vec<AttributeInfo>);
+
+fn field(f: &FieldInfo) -> Member<'_, FieldAccessFlags> {
+    (
+        f.name_index,
+        f.descriptor_index,
+        f.access_flags,
+        &f.attributes,
+    )
+}
+
+fn method(m: &MethodInfo) -> Member<'_, MethodAccessFlags> {
+    (
+        m.name_index,
+        m.descriptor_index,
+        m.access_flags,
+        &m.attributes,
+    )
+}
+
+fn diff_members<'a, F: Copy + PartialEq>(
+    old_members: impl Iterator<Item = Member<'a, F>>,
+    new_members: impl Iterator<Item = Member<'a, F>>,
+    old: &ConstantPool,
+    new: &ConstantPool,
+) -> Vec<MemberDiff<F>> {
+    let resolve = |cp: &ConstantPool, (name, descriptor, flags, attributes): Member<'a, F>| {
+        ((utf8(cp, name), utf8(cp, descriptor)), (flags, attributes))
+    };
+    let old_members: Vec<_> = old_members.map(|m| resolve(old, m)).collect();
+    let new_members: Vec<_> = new_members.map(|m| resolve(new, m)).collect();
+
+    let mut diffs = vec![];
+    for ((name, descriptor), (old_flags, old_attributes)) in &old_members {
+        let Some((_, (new_flags, new_attributes))) = new_members
+            .iter()
+            .find(|(key, _)| key.0 == *name && key.1 == *descriptor)
+        else {
+            diffs.push(MemberDiff::Removed {
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+            });
+            continue;
+        };
+        let access_flags = changed(*old_flags, *new_flags);
+        let attributes = diff_attributes(old_attributes, new_attributes, old, new);
+        if access_flags.is_some() || !attributes.is_empty() {
+            diffs.push(MemberDiff::Changed {
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                access_flags,
+                attributes,
+            });
+        }
+    }
+    for ((name, descriptor), _) in &new_members {
+        if !old_members
+            .iter()
+            .any(|(key, _)| key.0 == *name && key.1 == *descriptor)
+        {
+            diffs.push(MemberDiff::Added {
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+            });
+        }
+    }
+    diffs
+}
+
+/// Compares the attributes with the same name as a whole, so that e.g. a
+/// `RuntimeVisibleAnnotations` attribute that occurs twice changes if either
+/// does.
+fn diff_attributes(
+    old_attributes: &[AttributeInfo],
+    new_attributes: &[AttributeInfo],
+    old: &ConstantPool,
+    new: &ConstantPool,
+) -> Vec<AttributeDiff> {
+    let old_attributes = by_name(old_attributes, old);
+    let new_attributes = by_name(new_attributes, new);
+    let mut diffs = vec![];
+    for (name, old_attributes) in &old_attributes {
+        match new_attributes.iter().find(|(n, _)| n == name) {
+            None => diffs.push(AttributeDiff::Removed(name.clone())),
+            Some((_, new_attributes)) => {
+                let canonical = |attributes: &Vec<&AttributeInfo>, cp| {
+                    attributes
+                        .iter()
+                        .map(|a| canonical(a, cp))
+                        .collect::<Vec<_>>()
+                };
+                if canonical(old_attributes, old) != canonical(new_attributes, new) {
+                    diffs.push(AttributeDiff::Changed(name.clone()));
+                }
+            }
+        }
+    }
+    for (name, _) in &new_attributes {
+        if !old_attributes.iter().any(|(n, _)| n == name) {
+            diffs.push(AttributeDiff::Added(name.clone()));
+        }
+    }
+    diffs
+}
+
+/// The attributes grouped by their name, in the order of the first attribute
+/// with every name.
+fn by_name<'a>(
+    attributes: &'a [AttributeInfo],
+    cp: &ConstantPool,
+) -> Vec<(String, Vec<&'a AttributeInfo>)> {
+    let mut groups: Vec<(String, Vec<&AttributeInfo>)> = vec![];
+    for attribute in attributes {
+        let name = utf8(cp, attribute.header().0);
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, group)) => group.push(attribute),
+            None => groups.push((name, vec![attribute])),
+        }
+    }
+    groups
+}
+
+/// A form of the attribute that doesn't depend on the layout of the constant
+/// pool: its bytes with every constant pool index replaced by 1 (or 0 for
+/// "none"), and the contents of the entries at the replaced indices, in the
+/// order they occur. Attributes that can't be parsed, like unknown ones, are
+/// only compared by their bytes.
+fn canonical(attribute: &AttributeInfo, cp: &ConstantPool) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let mut bytes = vec![];
+    attribute
+        .write(&mut bytes)
+        .expect("writing to a Vec can't fail");
+    // parses lazy attributes, also nested ones, and makes a copy to remap
+    let Ok(mut attribute) = AttributeInfo::parse(cp, &mut bytes.as_slice()) else {
+        return (bytes, vec![]);
+    };
+    let indices = RefCell::new(vec![]);
+    Remap::new(|index| {
+        indices.borrow_mut().push(index);
+        index.min(1)
+    })
+    .attribute(&mut attribute);
+    bytes.clear();
+    attribute
+        .write(&mut bytes)
+        .expect("writing to a Vec can't fail");
+    let entries = indices
+        .into_inner()
+        .into_iter()
+        .map(|index| entry(cp, index, 0))
+        .collect();
+    (bytes, entries)
+}
+
+/// The contents of the constant pool entry at the given index, including the
+/// contents of the entries that it refers to.
+fn entry(cp: &ConstantPool, index: u16, depth: usize) -> Vec<u8> {
+    // the deepest valid entries, like a `CONSTANT_MethodHandle`, are 4 deep
+    const MAX_DEPTH: usize = 8;
+    let info = match cp.get(index) {
+        Some(info) if depth < MAX_DEPTH => info,
+        _ => return index.to_be_bytes().to_vec(),
+    };
+    let references = RefCell::new(vec![]);
+    let mut bytes = key(&with_indices(info, |index| {
+        references.borrow_mut().push(index);
+        0
+    }));
+    for index in references.into_inner() {
+        let entry = entry(cp, index, depth + 1);
+        bytes.extend((entry.len() as u32).to_be_bytes());
+        bytes.extend(entry);
+    }
+    bytes
+}
+
+fn utf8(cp: &ConstantPool, index: u16) -> String {
+    cp.get_utf8(index)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| format!("#{}", index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::{parse, MODES};
+
+    #[test]
+    fn test_diff() {
+        for mode in MODES {
+            let (v1, v2) = (parse("diff/v1/Shape", mode), parse("diff/v2/Shape", mode));
+            assert!(v1.diff(&v1).is_empty());
+            let member = |name: &str, descriptor: &str| (name.to_string(), descriptor.to_string());
+            let ((sides, i), (name, string), (id, long)) = (
+                member("SIDES", "I"),
+                member("name", "Ljava/lang/String;"),
+                member("id", "J"),
+            );
+            assert_eq!(
+                ClassDiff {
+                    version: None,
+                    access_flags: Some((
+                        ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER,
+                        ClassAccessFlags::PUBLIC
+                            | ClassAccessFlags::FINAL
+                            | ClassAccessFlags::SUPER
+                    )),
+                    this_class: None,
+                    super_class: None,
+                    interfaces: Some((vec![], vec!["java/lang/Comparable".into()])),
+                    fields: vec![
+                        MemberDiff::Changed {
+                            name: sides,
+                            descriptor: i,
+                            access_flags: None,
+                            attributes: vec![AttributeDiff::Changed("ConstantValue".into())],
+                        },
+                        MemberDiff::Removed {
+                            name,
+                            descriptor: string,
+                        },
+                        MemberDiff::Added {
+                            name: id,
+                            descriptor: long,
+                        },
+                    ],
+                    // area() is unchanged, although the field that it reads
+                    // has another index, since the constructor initializes id
+                    methods: vec![
+                        MemberDiff::Changed {
+                            name: "<init>".into(),
+                            descriptor: "()V".into(),
+                            access_flags: None,
+                            attributes: vec![AttributeDiff::Changed("Code".into())],
+                        },
+                        MemberDiff::Changed {
+                            name: "resize".into(),
+                            descriptor: "(I)V".into(),
+                            access_flags: Some((
+                                MethodAccessFlags::empty(),
+                                MethodAccessFlags::PUBLIC | MethodAccessFlags::SYNCHRONIZED
+                            )),
+                            attributes: vec![],
+                        },
+                        MemberDiff::Removed {
+                            name: "name".into(),
+                            descriptor: "()Ljava/lang/String;".into(),
+                        },
+                        MemberDiff::Added {
+                            name: "compareTo".into(),
+                            descriptor: "(LShape;)I".into(),
+                        },
+                        MemberDiff::Added {
+                            name: "compareTo".into(),
+                            descriptor: "(Ljava/lang/Object;)I".into(),
+                        },
+                    ],
+                    attributes: vec![AttributeDiff::Added("Signature".into())],
+                },
+                v1.diff(&v2)
+            );
+        }
+    }
+}
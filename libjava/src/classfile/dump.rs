@@ -0,0 +1,996 @@
+use crate::bytecode::{AType, Op};
+use crate::classfile::flags::{
+    ClassAccessFlags, FieldAccessFlags, InnerClassAccessFlags, MethodAccessFlags,
+};
+use crate::classfile::{
+    AttributeInfo, ClassFile, ConstantPool, ConstantPoolInfo, FieldInfo, MethodInfo, ReferenceKind,
+};
+use crate::prelude::*;
+use core::fmt::{Display, Formatter, Result};
+
+/// The column that `//` comments start at in the header and the constant pool.
+const COMMENT_COLUMN: usize = 42;
+/// The column that `//` comments start at in disassembled code.
+const CODE_COMMENT_COLUMN: usize = 46;
+
+const CLASS_FLAGS: &[(u16, &str)] = &[
+    (ClassAccessFlags::PUBLIC.bits(), "ACC_PUBLIC"),
+    (ClassAccessFlags::FINAL.bits(), "ACC_FINAL"),
+    (ClassAccessFlags::SUPER.bits(), "ACC_SUPER"),
+    (ClassAccessFlags::INTERFACE.bits(), "ACC_INTERFACE"),
+    (ClassAccessFlags::ABSTRACT.bits(), "ACC_ABSTRACT"),
+    (ClassAccessFlags::SYNTHETIC.bits(), "ACC_SYNTHETIC"),
+    (ClassAccessFlags::ANNOTATION.bits(), "ACC_ANNOTATION"),
+    (ClassAccessFlags::ENUM.bits(), "ACC_ENUM"),
+    (ClassAccessFlags::MODULE.bits(), "ACC_MODULE"),
+];
+
+const FIELD_FLAGS: &[(u16, &str)] = &[
+    (FieldAccessFlags::PUBLIC.bits(), "ACC_PUBLIC"),
+    (FieldAccessFlags::PRIVATE.bits(), "ACC_PRIVATE"),
+    (FieldAccessFlags::PROTECTED.bits(), "ACC_PROTECTED"),
+    (FieldAccessFlags::STATIC.bits(), "ACC_STATIC"),
+    (FieldAccessFlags::FINAL.bits(), "ACC_FINAL"),
+    (FieldAccessFlags::VOLATILE.bits(), "ACC_VOLATILE"),
+    (FieldAccessFlags::TRANSIENT.bits(), "ACC_TRANSIENT"),
+    (FieldAccessFlags::SYNTHETIC.bits(), "ACC_SYNTHETIC"),
+    (FieldAccessFlags::ENUM.bits(), "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: &[(u16, &str)] = &[
+    (MethodAccessFlags::PUBLIC.bits(), "ACC_PUBLIC"),
+    (MethodAccessFlags::PRIVATE.bits(), "ACC_PRIVATE"),
+    (MethodAccessFlags::PROTECTED.bits(), "ACC_PROTECTED"),
+    (MethodAccessFlags::STATIC.bits(), "ACC_STATIC"),
+    (MethodAccessFlags::FINAL.bits(), "ACC_FINAL"),
+    (MethodAccessFlags::SYNCHRONIZED.bits(), "ACC_SYNCHRONIZED"),
+    (MethodAccessFlags::BRIDGE.bits(), "ACC_BRIDGE"),
+    (MethodAccessFlags::VARARGS.bits(), "ACC_VARARGS"),
+    (MethodAccessFlags::NATIVE.bits(), "ACC_NATIVE"),
+    (MethodAccessFlags::ABSTRACT.bits(), "ACC_ABSTRACT"),
+    (MethodAccessFlags::STRICT.bits(), "ACC_STRICT"),
+    (MethodAccessFlags::SYNTHETIC.bits(), "ACC_SYNTHETIC"),
+];
+
+impl ClassFile {
+    /// Returns a textual dump of the class file in the layout of `javap -v`:
+    /// the header, the constant pool, the fields and the methods with their
+    /// disassembled code, and the attributes of the class. See the [`Display`]
+    /// implementation.
+    ///
+    /// The dump is meant for debugging and golden-file tests. It follows
+    /// `javap` closely, but not to the character: every `Class` constant is
+    /// shown with its name, short forms like `aload_0` are shown for every
+    /// local variable index that has one, and attributes that are rarely
+    /// needed while debugging, like annotations, are only shown with their
+    /// length.
+    pub fn dump(&self) -> String {
+        self.to_string()
+    }
+
+    fn source_file(&self) -> Option<u16> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::SourceFile {
+                    sourcefile_index, ..
+                } => Some(*sourcefile_index),
+                _ => None,
+            })
+    }
+
+    /// The declaration of the class in Java syntax, e.g.
+    /// `public class Foo extends Bar implements Baz`.
+    fn declaration(&self) -> String {
+        let flags = self.access_flags;
+        let is_interface = flags.contains(ClassAccessFlags::INTERFACE);
+        let mut declaration = String::new();
+        if flags.contains(ClassAccessFlags::PUBLIC) {
+            declaration.push_str("public ");
+        }
+        if flags.contains(ClassAccessFlags::ABSTRACT) && !is_interface {
+            declaration.push_str("abstract ");
+        }
+        if flags.contains(ClassAccessFlags::FINAL) {
+            declaration.push_str("final ");
+        }
+        declaration.push_str(if is_interface { "interface " } else { "class " });
+        declaration.push_str(&java_name(&self.this_class()));
+
+        let interfaces: Vec<String> = self.interfaces().iter().map(|i| java_name(i)).collect();
+        let super_class = self
+            .super_class()
+            .filter(|name| name != "java/lang/Object" && !is_interface);
+        if let Some(super_class) = super_class {
+            declaration.push_str(" extends ");
+            declaration.push_str(&java_name(&super_class));
+        }
+        if !interfaces.is_empty() {
+            declaration.push_str(if is_interface {
+                " extends "
+            } else {
+                " implements "
+            });
+            declaration.push_str(&interfaces.join(", "));
+        }
+        declaration
+    }
+}
+
+impl Display for ClassFile {
+    /// Writes the dump of [`ClassFile::dump`].
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let cp = &self.cp_info;
+        if let Some(source_file) = self.source_file() {
+            writeln!(f, "Compiled from \"{}\"", utf8(cp, source_file))?;
+        }
+        writeln!(f, "{}", self.declaration())?;
+        writeln!(f, "  minor version: {}", self.version.minor)?;
+        writeln!(f, "  major version: {}", self.version.major)?;
+        writeln!(
+            f,
+            "  flags: {}",
+            flags(self.access_flags.bits(), CLASS_FLAGS)
+        )?;
+        writeln!(
+            f,
+            "{}",
+            commented(
+                format!("  this_class: #{}", self.this_class),
+                &entry(cp, self.this_class),
+                COMMENT_COLUMN
+            )
+        )?;
+        let super_class = format!("  super_class: #{}", self.super_class);
+        if self.super_class == 0 {
+            writeln!(f, "{}", super_class)?;
+        } else {
+            let comment = entry(cp, self.super_class);
+            writeln!(f, "{}", commented(super_class, &comment, COMMENT_COLUMN))?;
+        }
+        writeln!(
+            f,
+            "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            self.interfaces.len(),
+            self.fields.len(),
+            self.methods.len(),
+            self.attributes.len()
+        )?;
+
+        writeln!(f, "Constant pool:")?;
+        for (i, info) in cp.iter().enumerate() {
+            if !matches!(info, ConstantPoolInfo::Unusable) {
+                writeln!(f, "{}", constant_pool_entry(cp, i as u16 + 1, info))?;
+            }
+        }
+
+        writeln!(f, "{{")?;
+        let mut members = self
+            .fields
+            .iter()
+            .map(|field| dump_field(cp, field))
+            .chain(
+                self.methods
+                    .iter()
+                    .map(|method| dump_method(cp, &self.this_class(), method)),
+            )
+            .peekable();
+        while let Some(member) = members.next() {
+            f.write_str(&member)?;
+            if members.peek().is_some() {
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "}}")?;
+        for attribute in &self.attributes {
+            f.write_str(&dump_attribute(cp, attribute, ""))?;
+        }
+        Ok(())
+    }
+}
+
+fn dump_field(cp: &ConstantPool, field: &FieldInfo) -> String {
+    let flags_bits = field.access_flags.bits();
+    let descriptor = utf8(cp, field.descriptor_index);
+    let mut out = format!(
+        "  {}{} {};\n",
+        modifiers(field.access_flags.bits(), FIELD_MODIFIERS),
+        field_type(&descriptor)
+            .map(|(java, _)| java)
+            .unwrap_or_else(|| descriptor.clone()),
+        utf8(cp, field.name_index)
+    );
+    out.push_str(&format!("    descriptor: {}\n", descriptor));
+    out.push_str(&format!("    flags: {}\n", flags(flags_bits, FIELD_FLAGS)));
+    for attribute in &field.attributes {
+        out.push_str(&dump_attribute(cp, attribute, "    "));
+    }
+    out
+}
+
+fn dump_method(cp: &ConstantPool, this_class: &str, method: &MethodInfo) -> String {
+    let name = utf8(cp, method.name_index);
+    let descriptor = utf8(cp, method.descriptor_index);
+    let flags_bits = method.access_flags.bits();
+    let throws: Vec<String> = method
+        .attributes
+        .iter()
+        .filter_map(|attribute| match attribute {
+            AttributeInfo::Exceptions {
+                exception_index_table,
+                ..
+            } => Some(exception_index_table),
+            _ => None,
+        })
+        .flatten()
+        .map(|index| class_name(cp, *index))
+        .collect();
+
+    let mut declaration = modifiers(flags_bits, METHOD_MODIFIERS);
+    match (name.as_str(), method_type(&descriptor)) {
+        ("<clinit>", _) => declaration.push_str("{}"),
+        (name, Some((parameters, return_type))) => {
+            if name == "<init>" {
+                declaration.push_str(&java_name(this_class));
+            } else {
+                declaration.push_str(&format!("{} {}", return_type, name));
+            }
+            declaration.push_str(&format!("({})", parameters.join(", ")));
+        }
+        (name, None) => declaration.push_str(name),
+    }
+    if !throws.is_empty() {
+        declaration.push_str(" throws ");
+        declaration.push_str(&throws.join(", "));
+    }
+
+    let mut out = format!("  {};\n", declaration);
+    out.push_str(&format!("    descriptor: {}\n", descriptor));
+    out.push_str(&format!("    flags: {}\n", flags(flags_bits, METHOD_FLAGS)));
+    let is_static = method.access_flags.contains(MethodAccessFlags::STATIC);
+    for attribute in &method.attributes {
+        match attribute {
+            AttributeInfo::Code { .. } => {
+                out.push_str(&dump_code(cp, attribute, &descriptor, is_static))
+            }
+            attribute => out.push_str(&dump_attribute(cp, attribute, "    ")),
+        }
+    }
+    out
+}
+
+fn dump_code(cp: &ConstantPool, code: &AttributeInfo, descriptor: &str, is_static: bool) -> String {
+    let AttributeInfo::Code {
+        max_stack,
+        max_locals,
+        code,
+        exception_table,
+        attributes,
+        ..
+    } = code
+    else {
+        return String::new();
+    };
+    let args_size = parameter_slots(descriptor) + usize::from(!is_static);
+    let mut out = String::from("    Code:\n");
+    out.push_str(&format!(
+        "      stack={}, locals={}, args_size={}\n",
+        max_stack, max_locals, args_size
+    ));
+    out.push_str(&disassemble(cp, code));
+    if !exception_table.is_empty() {
+        out.push_str("      Exception table:\n");
+        out.push_str("         from    to  target type\n");
+        for entry in exception_table {
+            let catch_type = if entry.catch_type == 0 {
+                "any".to_string()
+            } else {
+                format!("Class {}", class_name(cp, entry.catch_type))
+            };
+            out.push_str(&format!(
+                "        {:>5} {:>5} {:>5}   {}\n",
+                entry.start_pc, entry.end_pc, entry.handler_pc, catch_type
+            ));
+        }
+    }
+    for attribute in attributes {
+        out.push_str(&dump_attribute(cp, attribute, "      "));
+    }
+    out
+}
+
+/// Disassembles the code of a method, one instruction per line, with the
+/// constants that instructions refer to and the targets of branches.
+fn disassemble(cp: &ConstantPool, code: &[u8]) -> String {
+    let mut out = String::new();
+    let mut source = code;
+    while !source.is_empty() {
+        let pc = code.len() - source.len();
+        let Ok(op) = Op::parse_at(&mut source, pc) else {
+            out.push_str(&format!("      {:>4}: <invalid>\n", pc));
+            break;
+        };
+        let target = |relative: isize| pc.wrapping_add_signed(relative).to_string();
+        let mnemonic = op.mnemonic();
+        let (operands, comment) = match &op {
+            Op::ALoad(index)
+            | Op::AStore(index)
+            | Op::DLoad(index)
+            | Op::DStore(index)
+            | Op::FLoad(index)
+            | Op::FStore(index)
+            | Op::ILoad(index)
+            | Op::IStore(index)
+            | Op::LLoad(index)
+            | Op::LStore(index) => {
+                if *index <= 3 {
+                    out.push_str(&format!("      {:>4}: {}_{}\n", pc, mnemonic, index));
+                    continue;
+                }
+                (index.to_string(), None)
+            }
+            Op::Ret(index) => (index.to_string(), None),
+            Op::BIPush(value) => (value.to_string(), None),
+            Op::SIPush(value) => ((*value as i16).to_string(), None),
+            Op::IInc(index, value) => (format!("{}, {}", index, value), None),
+            Op::LDC(index) => constant_operand(cp, *index as u16),
+            Op::LDCW(index)
+            | Op::LDC2W(index)
+            | Op::GetField(index)
+            | Op::GetStatic(index)
+            | Op::PutField(index)
+            | Op::PutStatic(index)
+            | Op::InvokeVirtual(index)
+            | Op::InvokeSpecial(index)
+            | Op::InvokeStatic(index)
+            | Op::New(index)
+            | Op::ANewArray(index)
+            | Op::CheckCast(index)
+            | Op::InstanceOf(index) => constant_operand(cp, *index),
+            Op::InvokeDynamic(index) => {
+                let (_, comment) = constant_operand(cp, *index);
+                (format!("#{},  0", index), comment)
+            }
+            Op::InvokeInterface(index, count) => {
+                let (_, comment) = constant_operand(cp, *index);
+                (format!("#{},  {}", index, count), comment)
+            }
+            Op::MultiANewArray(index, dimensions) => {
+                let (_, comment) = constant_operand(cp, *index);
+                (format!("#{},  {}", index, dimensions), comment)
+            }
+            Op::NewArray(atype) => (array_type(*atype).to_string(), None),
+            Op::Goto(relative)
+            | Op::Jsr(relative)
+            | Op::IfACmpEq(relative)
+            | Op::IfACmpNe(relative)
+            | Op::IfICmpEq(relative)
+            | Op::IfICmpNe(relative)
+            | Op::IfICmpLt(relative)
+            | Op::IfICmpGe(relative)
+            | Op::IfICmpGt(relative)
+            | Op::IfICmpLe(relative)
+            | Op::IfEq(relative)
+            | Op::IfNe(relative)
+            | Op::IfLt(relative)
+            | Op::IfGe(relative)
+            | Op::IfGt(relative)
+            | Op::IfLe(relative)
+            | Op::IfNonNull(relative)
+            | Op::IfNull(relative) => (target(*relative as i16 as isize), None),
+            Op::GotoW(relative) | Op::JsrW(relative) => (target(*relative as i32 as isize), None),
+            Op::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                out.push_str(&format!(
+                    "      {:>4}: {:<13} {{ // {} to {}\n",
+                    pc, mnemonic, low, high
+                ));
+                for (key, offset) in (*low..=*high).zip(offsets) {
+                    out.push_str(&format!("{:>24}: {}\n", key, target(*offset as isize)));
+                }
+                out.push_str(&format!(
+                    "{:>24}: {}\n",
+                    "default",
+                    target(*default as isize)
+                ));
+                out.push_str("            }\n");
+                continue;
+            }
+            Op::LookupSwitch { default, npairs } => {
+                out.push_str(&format!(
+                    "      {:>4}: {:<13} {{ // {}\n",
+                    pc,
+                    mnemonic,
+                    npairs.len()
+                ));
+                for (key, offset) in npairs {
+                    out.push_str(&format!("{:>24}: {}\n", key, target(*offset as isize)));
+                }
+                out.push_str(&format!(
+                    "{:>24}: {}\n",
+                    "default",
+                    target(*default as i32 as isize)
+                ));
+                out.push_str("            }\n");
+                continue;
+            }
+            _ => {
+                out.push_str(&format!("      {:>4}: {}\n", pc, mnemonic));
+                continue;
+            }
+        };
+        let line = format!("      {:>4}: {:<13} {}", pc, mnemonic, operands);
+        match comment {
+            Some(comment) => out.push_str(&commented(line, &comment, CODE_COMMENT_COLUMN)),
+            None => out.push_str(&line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The operand of an instruction that refers to the constant pool, and the
+/// comment that describes the constant, e.g. `Method Foo.bar:()V`.
+fn constant_operand(cp: &ConstantPool, index: u16) -> (String, Option<String>) {
+    let comment = cp
+        .get(index)
+        .map(|info| format!("{} {}", constant_kind(info), entry(cp, index)));
+    (format!("#{}", index), comment)
+}
+
+fn dump_attribute(cp: &ConstantPool, attribute: &AttributeInfo, indent: &str) -> String {
+    // malformed lazy attributes are shown with their length like unknown ones
+    let attribute = attribute.materialize(cp).unwrap_or(attribute);
+    match attribute {
+        AttributeInfo::ConstantValue {
+            constantvalue_index,
+            ..
+        } => {
+            let (_, comment) = constant_operand(cp, *constantvalue_index);
+            format!(
+                "{}ConstantValue: {}\n",
+                indent,
+                comment.unwrap_or_else(|| format!("#{}", constantvalue_index))
+            )
+        }
+        AttributeInfo::Exceptions {
+            exception_index_table,
+            ..
+        } => {
+            let mut out = format!("{}Exceptions:\n", indent);
+            for index in exception_index_table {
+                out.push_str(&format!("{}  throws {}\n", indent, class_name(cp, *index)));
+            }
+            out
+        }
+        AttributeInfo::Signature {
+            signature_index, ..
+        } => {
+            let line = format!("{}Signature: #{}", indent, signature_index);
+            let comment = utf8(cp, *signature_index);
+            format!("{}\n", commented(line, &comment, COMMENT_COLUMN))
+        }
+        AttributeInfo::SourceFile {
+            sourcefile_index, ..
+        } => format!(
+            "{}SourceFile: \"{}\"\n",
+            indent,
+            utf8(cp, *sourcefile_index)
+        ),
+        AttributeInfo::LineNumberTable {
+            line_number_table, ..
+        } => {
+            let mut out = format!("{}LineNumberTable:\n", indent);
+            for entry in line_number_table {
+                out.push_str(&format!(
+                    "{}  line {}: {}\n",
+                    indent, entry.line_number, entry.start_pc
+                ));
+            }
+            out
+        }
+        AttributeInfo::LocalVariableTable {
+            local_variable_table,
+            ..
+        } => {
+            let mut out = format!("{}LocalVariableTable:\n", indent);
+            out.push_str(&format!(
+                "{}  Start  Length  Slot  Name   Signature\n",
+                indent
+            ));
+            for entry in local_variable_table {
+                out.push_str(&format!(
+                    "{}  {:>5}  {:>6}  {:>4}  {:<6} {}\n",
+                    indent,
+                    entry.start_pc,
+                    entry.length,
+                    entry.index,
+                    utf8(cp, entry.name_index),
+                    utf8(cp, entry.descriptor_index)
+                ));
+            }
+            out
+        }
+        AttributeInfo::StackMapTable { entries, .. } => format!(
+            "{}StackMapTable: number_of_entries = {}\n",
+            indent,
+            entries.len()
+        ),
+        AttributeInfo::InnerClasses { classes, .. } => {
+            let mut out = format!("{}InnerClasses:\n", indent);
+            for class in classes {
+                let inner = class_name(cp, class.inner_class_info_index);
+                let flags = class.inner_class_access_flags.bits();
+                let modifiers = modifiers(flags, INNER_CLASS_MODIFIERS);
+                let line = match class.inner_name_index {
+                    0 => format!(
+                        "{}  {}#{};",
+                        indent, modifiers, class.inner_class_info_index
+                    ),
+                    name => format!(
+                        "{}  {}#{}= #{} of #{};",
+                        indent,
+                        modifiers,
+                        name,
+                        class.inner_class_info_index,
+                        class.outer_class_info_index
+                    ),
+                };
+                out.push_str(&commented(line, &inner, COMMENT_COLUMN + 2));
+                out.push('\n');
+            }
+            out
+        }
+        AttributeInfo::NestHost {
+            host_class_index, ..
+        } => format!(
+            "{}NestHost: class {}\n",
+            indent,
+            class_name(cp, *host_class_index)
+        ),
+        AttributeInfo::NestMembers { classes, .. } => {
+            let mut out = format!("{}NestMembers:\n", indent);
+            for class in classes {
+                out.push_str(&format!("{}  {}\n", indent, class_name(cp, *class)));
+            }
+            out
+        }
+        AttributeInfo::BootstrapMethods {
+            bootstrap_methods, ..
+        } => {
+            let mut out = format!("{}BootstrapMethods:\n", indent);
+            for (i, method) in bootstrap_methods.iter().enumerate() {
+                out.push_str(&format!(
+                    "{}  {}: #{} {}\n",
+                    indent,
+                    i,
+                    method.bootstrap_method_ref,
+                    entry(cp, method.bootstrap_method_ref)
+                ));
+                out.push_str(&format!("{}    Method arguments:\n", indent));
+                for argument in &method.bootstrap_arguments {
+                    out.push_str(&format!(
+                        "{}      #{} {}\n",
+                        indent,
+                        argument,
+                        entry(cp, *argument)
+                    ));
+                }
+            }
+            out
+        }
+        attribute => {
+            let (name_index, length) = attribute.header();
+            format!(
+                "{}{}: length = {:#x}\n",
+                indent,
+                utf8(cp, name_index),
+                length
+            )
+        }
+    }
+}
+
+/// A line of the constant pool, e.g.
+/// `   #1 = Methodref          #2.#3          // java/lang/Object."<init>":()V`.
+fn constant_pool_entry(cp: &ConstantPool, index: u16, info: &ConstantPoolInfo) -> String {
+    let references = match info {
+        ConstantPoolInfo::ClassInfo { name_index: i }
+        | ConstantPoolInfo::StringInfo { string_index: i }
+        | ConstantPoolInfo::MethodTypeInfo {
+            descriptor_index: i,
+        }
+        | ConstantPoolInfo::ModuleInfo { name_index: i }
+        | ConstantPoolInfo::PackageInfo { name_index: i } => Some(format!("#{}", i)),
+        ConstantPoolInfo::FieldrefInfo {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::MethodrefInfo {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InterfaceMethodrefInfo {
+            class_index,
+            name_and_type_index,
+        } => Some(format!("#{}.#{}", class_index, name_and_type_index)),
+        ConstantPoolInfo::NameAndTypeInfo {
+            name_index,
+            descriptor_index,
+        } => Some(format!("#{}:#{}", name_index, descriptor_index)),
+        ConstantPoolInfo::MethodHandleInfo {
+            reference_kind,
+            reference_index,
+        } => Some(format!("{}:#{}", *reference_kind as u8, reference_index)),
+        ConstantPoolInfo::DynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InvokeDynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => Some(format!(
+            "#{}:#{}",
+            bootstrap_method_attr_index, name_and_type_index
+        )),
+        _ => None,
+    };
+    let line = format!("{:>5} = {:<18} ", format!("#{}", index), tag(info));
+    match references {
+        Some(references) => {
+            let line = format!("{}{}", line, references);
+            commented(line, &entry(cp, index), COMMENT_COLUMN)
+        }
+        None => format!("{}{}", line, entry(cp, index)),
+    }
+}
+
+fn tag(info: &ConstantPoolInfo) -> &'static str {
+    match info {
+        ConstantPoolInfo::ClassInfo { .. } => "Class",
+        ConstantPoolInfo::FieldrefInfo { .. } => "Fieldref",
+        ConstantPoolInfo::MethodrefInfo { .. } => "Methodref",
+        ConstantPoolInfo::InterfaceMethodrefInfo { .. } => "InterfaceMethodref",
+        ConstantPoolInfo::StringInfo { .. } => "String",
+        ConstantPoolInfo::IntegerInfo { .. } => "Integer",
+        ConstantPoolInfo::FloatInfo { .. } => "Float",
+        ConstantPoolInfo::LongInfo { .. } => "Long",
+        ConstantPoolInfo::DoubleInfo { .. } => "Double",
+        ConstantPoolInfo::NameAndTypeInfo { .. } => "NameAndType",
+        ConstantPoolInfo::Utf8Info { .. } => "Utf8",
+        ConstantPoolInfo::MethodHandleInfo { .. } => "MethodHandle",
+        ConstantPoolInfo::MethodTypeInfo { .. } => "MethodType",
+        ConstantPoolInfo::DynamicInfo { .. } => "Dynamic",
+        ConstantPoolInfo::InvokeDynamicInfo { .. } => "InvokeDynamic",
+        ConstantPoolInfo::ModuleInfo { .. } => "Module",
+        ConstantPoolInfo::PackageInfo { .. } => "Package",
+        ConstantPoolInfo::Unusable => "Unusable",
+    }
+}
+
+/// How the comment of an instruction calls a constant, e.g. `Method` or `int`.
+fn constant_kind(info: &ConstantPoolInfo) -> &'static str {
+    match info {
+        ConstantPoolInfo::ClassInfo { .. } => "class",
+        ConstantPoolInfo::FieldrefInfo { .. } => "Field",
+        ConstantPoolInfo::MethodrefInfo { .. } => "Method",
+        ConstantPoolInfo::InterfaceMethodrefInfo { .. } => "InterfaceMethod",
+        ConstantPoolInfo::IntegerInfo { .. } => "int",
+        ConstantPoolInfo::FloatInfo { .. } => "float",
+        ConstantPoolInfo::LongInfo { .. } => "long",
+        ConstantPoolInfo::DoubleInfo { .. } => "double",
+        info => tag(info),
+    }
+}
+
+/// The value of a constant pool entry with the entries it refers to resolved,
+/// e.g. `java/lang/Object."<init>":()V` for a `Methodref`. Indices that don't
+/// refer to a valid entry are shown as they are, so that broken class files
+/// can be dumped too.
+fn entry(cp: &ConstantPool, index: u16) -> String {
+    let Some(info) = cp.get(index) else {
+        return format!("#{}", index);
+    };
+    match info {
+        ConstantPoolInfo::Utf8Info { .. } => utf8(cp, index),
+        ConstantPoolInfo::IntegerInfo { bytes } => (*bytes as i32).to_string(),
+        ConstantPoolInfo::FloatInfo { bytes } => format!("{:?}f", f32::from_bits(*bytes)),
+        ConstantPoolInfo::LongInfo { .. } => format!("{}l", info.as_i64().unwrap_or_default()),
+        ConstantPoolInfo::DoubleInfo { .. } => {
+            format!("{:?}d", info.as_f64().unwrap_or_default())
+        }
+        ConstantPoolInfo::ClassInfo { .. } => {
+            let name = class_name(cp, index);
+            if name.starts_with('[') {
+                format!("\"{}\"", name)
+            } else {
+                name
+            }
+        }
+        ConstantPoolInfo::StringInfo { string_index } => utf8(cp, *string_index),
+        ConstantPoolInfo::MethodTypeInfo { descriptor_index } => utf8(cp, *descriptor_index),
+        ConstantPoolInfo::ModuleInfo { name_index }
+        | ConstantPoolInfo::PackageInfo { name_index } => utf8(cp, *name_index),
+        ConstantPoolInfo::FieldrefInfo {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::MethodrefInfo {
+            class_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InterfaceMethodrefInfo {
+            class_index,
+            name_and_type_index,
+        } => format!(
+            "{}.{}",
+            entry(cp, *class_index),
+            entry(cp, *name_and_type_index)
+        ),
+        ConstantPoolInfo::NameAndTypeInfo {
+            name_index,
+            descriptor_index,
+        } => {
+            let name = utf8(cp, *name_index);
+            let name = if name.starts_with('<') {
+                format!("\"{}\"", name)
+            } else {
+                name
+            };
+            format!("{}:{}", name, utf8(cp, *descriptor_index))
+        }
+        ConstantPoolInfo::MethodHandleInfo {
+            reference_kind,
+            reference_index,
+        } => format!(
+            "{} {}",
+            reference_kind_name(*reference_kind),
+            entry(cp, *reference_index)
+        ),
+        ConstantPoolInfo::DynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }
+        | ConstantPoolInfo::InvokeDynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => format!(
+            "#{}:{}",
+            bootstrap_method_attr_index,
+            entry(cp, *name_and_type_index)
+        ),
+        ConstantPoolInfo::Unusable => format!("#{}", index),
+    }
+}
+
+fn reference_kind_name(kind: ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::GetField => "REF_getField",
+        ReferenceKind::GetStatic => "REF_getStatic",
+        ReferenceKind::PutField => "REF_putField",
+        ReferenceKind::PutStatic => "REF_putStatic",
+        ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+        ReferenceKind::InvokeStatic => "REF_invokeStatic",
+        ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+        ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+        ReferenceKind::InvokeInterface => "REF_invokeInterface",
+    }
+}
+
+fn array_type(atype: AType) -> &'static str {
+    match atype {
+        AType::TBoolean => "boolean",
+        AType::TChar => "char",
+        AType::TFloat => "float",
+        AType::TDouble => "double",
+        AType::TByte => "byte",
+        AType::TShort => "short",
+        AType::TInt => "int",
+        AType::TLong => "long",
+    }
+}
+
+fn utf8(cp: &ConstantPool, index: u16) -> String {
+    cp.get_utf8(index)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| format!("#{}", index))
+}
+
+fn class_name(cp: &ConstantPool, index: u16) -> String {
+    cp.get_class_name(index)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| format!("#{}", index))
+}
+
+/// Appends a `//` comment to the line, starting at the given column or one
+/// space after the line if it is longer.
+fn commented(line: String, comment: &str, column: usize) -> String {
+    format!(
+        "{:<width$}// {}",
+        line,
+        comment,
+        width = column.max(line.len() + 1)
+    )
+}
+
+/// The flags in the layout of `javap`, e.g. `(0x0021) ACC_PUBLIC, ACC_SUPER`.
+fn flags(bits: u16, names: &[(u16, &str)]) -> String {
+    let names: Vec<&str> = names
+        .iter()
+        .filter(|(flag, _)| bits & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if names.is_empty() {
+        format!("({:#06x})", bits)
+    } else {
+        format!("({:#06x}) {}", bits, names.join(", "))
+    }
+}
+
+const FIELD_MODIFIERS: &[(u16, &str)] = &[
+    (FieldAccessFlags::PUBLIC.bits(), "public"),
+    (FieldAccessFlags::PRIVATE.bits(), "private"),
+    (FieldAccessFlags::PROTECTED.bits(), "protected"),
+    (FieldAccessFlags::STATIC.bits(), "static"),
+    (FieldAccessFlags::FINAL.bits(), "final"),
+    (FieldAccessFlags::VOLATILE.bits(), "volatile"),
+    (FieldAccessFlags::TRANSIENT.bits(), "transient"),
+];
+
+const METHOD_MODIFIERS: &[(u16, &str)] = &[
+    (MethodAccessFlags::PUBLIC.bits(), "public"),
+    (MethodAccessFlags::PRIVATE.bits(), "private"),
+    (MethodAccessFlags::PROTECTED.bits(), "protected"),
+    (MethodAccessFlags::STATIC.bits(), "static"),
+    (MethodAccessFlags::FINAL.bits(), "final"),
+    (MethodAccessFlags::SYNCHRONIZED.bits(), "synchronized"),
+    (MethodAccessFlags::NATIVE.bits(), "native"),
+    (MethodAccessFlags::ABSTRACT.bits(), "abstract"),
+];
+
+const INNER_CLASS_MODIFIERS: &[(u16, &str)] = &[
+    (InnerClassAccessFlags::PUBLIC.bits(), "public"),
+    (InnerClassAccessFlags::PRIVATE.bits(), "private"),
+    (InnerClassAccessFlags::PROTECTED.bits(), "protected"),
+    (InnerClassAccessFlags::STATIC.bits(), "static"),
+    (InnerClassAccessFlags::FINAL.bits(), "final"),
+    (InnerClassAccessFlags::ABSTRACT.bits(), "abstract"),
+];
+
+/// The Java modifiers of the flags, each followed by a space.
+fn modifiers(bits: u16, names: &[(u16, &str)]) -> String {
+    names
+        .iter()
+        .filter(|(flag, _)| bits & flag != 0)
+        .map(|(_, name)| format!("{} ", name))
+        .collect()
+}
+
+/// The binary name of a class in Java syntax, e.g. `java.lang.Object`.
+fn java_name(internal: &str) -> String {
+    internal.replace('/', ".")
+}
+
+/// Parses the field type at the start of the descriptor into its Java syntax,
+/// e.g. `int[]` for `[I`, and returns it with the rest of the descriptor.
+fn field_type(descriptor: &str) -> Option<(String, &str)> {
+    let dimensions = descriptor.len() - descriptor.trim_start_matches('[').len();
+    let element = &descriptor[dimensions..];
+    let (name, rest) = match element.chars().next()? {
+        'B' => ("byte".to_string(), &element[1..]),
+        'C' => ("char".to_string(), &element[1..]),
+        'D' => ("double".to_string(), &element[1..]),
+        'F' => ("float".to_string(), &element[1..]),
+        'I' => ("int".to_string(), &element[1..]),
+        'J' => ("long".to_string(), &element[1..]),
+        'S' => ("short".to_string(), &element[1..]),
+        'Z' => ("boolean".to_string(), &element[1..]),
+        'V' if dimensions == 0 => ("void".to_string(), &element[1..]),
+        'L' => {
+            let end = element.find(';')?;
+            (java_name(&element[1..end]), &element[end + 1..])
+        }
+        _ => return None,
+    };
+    Some((format!("{}{}", name, "[]".repeat(dimensions)), rest))
+}
+
+/// Parses a method descriptor into the Java syntax of its parameter types and
+/// its return type.
+fn method_type(descriptor: &str) -> Option<(Vec<String>, String)> {
+    let mut rest = descriptor.strip_prefix('(')?;
+    let mut parameters = vec![];
+    while !rest.starts_with(')') {
+        if rest.starts_with('V') {
+            return None;
+        }
+        let (parameter, next) = field_type(rest)?;
+        parameters.push(parameter);
+        rest = next;
+    }
+    let (return_type, rest) = field_type(&rest[1..])?;
+    rest.is_empty().then_some((parameters, return_type))
+}
+
+/// The number of local variable slots that the parameters of the method
+/// descriptor take up, where `long` and `double` take up two.
+fn parameter_slots(descriptor: &str) -> usize {
+    let Some(mut rest) = descriptor.strip_prefix('(') else {
+        return 0;
+    };
+    let mut slots = 0;
+    while let Some((_, next)) = field_type(rest) {
+        slots += if rest.starts_with(['J', 'D']) { 2 } else { 1 };
+        rest = next;
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(path: &str) -> String {
+        ClassFile::parse(&mut std::fs::read(path).unwrap().as_slice())
+            .unwrap()
+            .dump()
+    }
+
+    #[test]
+    fn test_dump_matches_golden_file() {
+        let expected = std::fs::read_to_string("tests/resources/Foo.javap").unwrap();
+        assert_eq!(expected, dump("tests/resources/Foo.class"));
+    }
+
+    #[test]
+    fn test_dump() {
+        let dump = dump("tests/resources/Formats.class");
+        for line in [
+            "public abstract class Formats",
+            "  flags: (0x0421) ACC_PUBLIC, ACC_SUPER, ACC_ABSTRACT",
+            "  static final int ANSWER;",
+            "    ConstantValue: int 42",
+            "    ConstantValue: long 1099511627776l",
+            "    ConstantValue: float 1.5f",
+            "  abstract void missing();",
+            "  native int fromNative(long);",
+            "    flags: (0x0100) ACC_NATIVE",
+            "  java.util.function.Supplier supplier();",
+            "      stack=1, locals=3, args_size=2",
+            "         from    to  target type",
+            "SourceFile: \"Formats.java\"",
+        ] {
+            assert!(dump.contains(line), "missing {:?} in\n{}", line, dump);
+        }
+    }
+
+    #[test]
+    fn test_dump_wide_constants() {
+        let dump = dump("tests/resources/Constants.class");
+        for comment in [
+            "// long 123456789012l",
+            "// double 0.25d",
+            "// String after",
+        ] {
+            assert!(dump.contains(comment), "missing {:?} in\n{}", comment, dump);
+        }
+        assert!(dump.contains(": ldc2_w "));
+    }
+
+    #[test]
+    fn test_method_type() {
+        assert_eq!(
+            Some((
+                vec!["int[][]".to_string(), "java.lang.String".to_string()],
+                "void".to_string()
+            )),
+            method_type("([[ILjava/lang/String;)V")
+        );
+        assert_eq!(None, method_type("(V)V"));
+        assert_eq!(3, parameter_slots("(JI)V"));
+    }
+}
@@ -0,0 +1,312 @@
+use crate::classfile::{ClassFileParseError, ConstantPool, ParseLimit, ParseOptions};
+use crate::io::{self, Read};
+use crate::prelude::*;
+use core::fmt::{Display, Formatter};
+
+/// An error of [`ClassFile::parse`](crate::classfile::ClassFile::parse), with
+/// where in the class file it occurred.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClassFileError {
+    kind: ClassFileParseError,
+    offset: u64,
+    context: Vec<String>,
+}
+
+impl ClassFileError {
+    pub fn kind(&self) -> ClassFileParseError {
+        self.kind
+    }
+
+    /// The number of bytes of the class file that were read when parsing
+    /// failed.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The structures that were being parsed, from the outermost to the
+    /// innermost, e.g. `method #3`, `Code attribute`, `StackMapTable
+    /// attribute` and `entry 7`. Empty if parsing failed at the top level of
+    /// the class file.
+    pub fn context(&self) -> &[String] {
+        &self.context
+    }
+}
+
+impl Display for ClassFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?} at offset {}", self.kind, self.offset)?;
+        if !self.context.is_empty() {
+            write!(f, " in {}", self.context.join(" → "))?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ClassFileError {}
+
+/// A structure of a class file that is being parsed, see
+/// [`ClassFileError::context`].
+#[derive(Debug, Copy, Clone)]
+pub(super) enum Frame {
+    /// The constant pool entry with the given (1-based) index.
+    ConstantPoolEntry(u16),
+    Field(u16),
+    Method(u16),
+    /// The attribute with the given name index.
+    Attribute(u16),
+    /// An entry of the table of an attribute, e.g. a frame of a
+    /// `StackMapTable`.
+    Entry(u16),
+    /// The instruction at the given offset in the code of a method.
+    Instruction(u32),
+}
+
+impl Frame {
+    /// Describes the frame, with the name of attributes looked up in the
+    /// constant pool if there is one already.
+    fn describe(self, cp: Option<&ConstantPool>) -> String {
+        match self {
+            Frame::ConstantPoolEntry(index) => format!("constant pool entry #{}", index),
+            Frame::Field(index) => format!("field #{}", index),
+            Frame::Method(index) => format!("method #{}", index),
+            Frame::Attribute(name_index) => match cp.and_then(|cp| cp.get_utf8(name_index).ok()) {
+                Some(name) => format!("{} attribute", name),
+                None => format!("attribute with name index {}", name_index),
+            },
+            Frame::Entry(index) => format!("entry {}", index),
+            Frame::Instruction(offset) => format!("instruction at pc {}", offset),
+        }
+    }
+}
+
+/// The source of a class file, which keeps track of how many bytes were read
+/// and what is being parsed, so that errors can tell where they occurred.
+pub(super) struct Source<R> {
+    inner: R,
+    offset: u64,
+    frames: Vec<Frame>,
+    /// Whether the code of methods is decoded, see
+    /// [`ClassFile::parse_decoded`](crate::classfile::ClassFile::parse_decoded).
+    decode_code: bool,
+    options: ParseOptions,
+    /// The offset at which the innermost attribute that is being parsed ends,
+    /// see [`Source::bounded`]. Reads never go past it.
+    end: Option<u64>,
+}
+
+impl<R: Read> Source<R> {
+    pub(super) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            frames: vec![],
+            decode_code: false,
+            options: ParseOptions::default(),
+            end: None,
+        }
+    }
+
+    pub(super) fn with_options(self, options: ParseOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    pub(super) fn decoding_code(self) -> Self {
+        Self {
+            decode_code: true,
+            ..self
+        }
+    }
+
+    pub(super) fn decodes_code(&self) -> bool {
+        self.decode_code
+    }
+
+    /// Fails with [`ClassFileParseError::LimitExceeded`] if the value exceeds
+    /// the given limit of the options.
+    pub(super) fn check(&self, limit: ParseLimit, value: usize) -> Result<(), ClassFileParseError> {
+        self.options.check(limit, value)
+    }
+
+    /// Parses the structure of the given frame. The frame is only left when
+    /// parsing succeeds, so after an error, the frames are where it occurred.
+    pub(super) fn within<T>(
+        &mut self,
+        frame: Frame,
+        parse: impl FnOnce(&mut Self) -> Result<T, ClassFileParseError>,
+    ) -> Result<T, ClassFileParseError> {
+        self.frames.push(frame);
+        let parsed = parse(self)?;
+        self.frames.pop();
+        Ok(parsed)
+    }
+
+    /// Parses a structure of the given length, like the contents of an
+    /// attribute, from no more than the given number of bytes. Fails with
+    /// [`ClassFileParseError::InvalidAttributeLength`] if the structure needs
+    /// more bytes, doesn't use all of them, or is longer than the structure
+    /// that it is nested in. Without these checks, a corrupted length would
+    /// make the parser continue with the bytes of the next structure.
+    pub(super) fn bounded<T>(
+        &mut self,
+        length: u32,
+        parse: impl FnOnce(&mut Self) -> Result<T, ClassFileParseError>,
+    ) -> Result<T, ClassFileParseError> {
+        let end = self.offset + length as u64;
+        let outer = self.end;
+        if outer.is_some_and(|outer| end > outer) {
+            return Err(ClassFileParseError::InvalidAttributeLength);
+        }
+        self.end = Some(end);
+        let parsed = parse(self);
+        self.end = outer;
+        match parsed {
+            // the structure read past its end, rather than past the end of the
+            // class file
+            Err(ClassFileParseError::UnexpectedEOF) if self.offset == end => {
+                Err(ClassFileParseError::InvalidAttributeLength)
+            }
+            Ok(_) if self.offset != end => Err(ClassFileParseError::InvalidAttributeLength),
+            parsed => parsed,
+        }
+    }
+
+    pub(super) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the error of the given kind at the current offset and frames.
+    pub(super) fn error(
+        &self,
+        kind: ClassFileParseError,
+        cp: Option<&ConstantPool>,
+    ) -> ClassFileError {
+        ClassFileError {
+            kind,
+            offset: self.offset,
+            context: self.frames.iter().map(|frame| frame.describe(cp)).collect(),
+        }
+    }
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let buf = match self.end {
+            Some(end) => {
+                let remaining = end.saturating_sub(self.offset);
+                let len = buf
+                    .len()
+                    .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let read = self.inner.read(buf)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::classfile::{ClassFile, ClassFileParseError};
+
+    #[test]
+    fn test_error_context() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        // in the first frame of the StackMapTable of the method `guarded`
+        let error = ClassFile::from_bytes(&bytes[..1356]).unwrap_err();
+        assert_eq!(ClassFileParseError::UnexpectedEOF, error.kind());
+        assert_eq!(1356, error.offset());
+        assert_eq!(
+            vec![
+                "method #4",
+                "Code attribute",
+                "StackMapTable attribute",
+                "entry 0"
+            ],
+            error.context()
+        );
+        assert_eq!(
+            "UnexpectedEOF at offset 1356 in method #4 \u{2192} Code attribute \u{2192} \
+             StackMapTable attribute \u{2192} entry 0",
+            error.to_string()
+        );
+
+        let trailing = ClassFile::from_bytes(&[&bytes[..], &[0]].concat()).unwrap_err();
+        assert_eq!(ClassFileParseError::TrailingBytes, trailing.kind());
+        assert_eq!(bytes.len() as u64, trailing.offset());
+        assert!(trailing.context().is_empty());
+    }
+
+    #[test]
+    fn test_error_before_constant_pool() {
+        // the tag of the first constant is invalid, so there are no names yet
+        let bytes = [
+            0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D, 0x00, 0x02, 0xFF,
+        ];
+        let error = ClassFile::from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            ClassFileParseError::InvalidConstantPoolInfoTag,
+            error.kind()
+        );
+        assert_eq!(11, error.offset());
+        assert_eq!(vec!["constant pool entry #1"], error.context());
+    }
+
+    #[test]
+    fn test_attribute_length_mismatch() {
+        // a class with an Exceptions attribute that names one exception
+        let class_file = |length: u8, contents: &[u8]| {
+            let bytes = [
+                &[0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D][..],
+                &[0x00, 0x03, 0x01, 0x00, 0x0A],
+                b"Exceptions",
+                &[0x07, 0x00, 0x01],                   // constant pool
+                &[0x00, 0x00, 0x00, 0x02, 0x00, 0x00], // flags, this and super class
+                &[0x00; 6],                            // interfaces, fields, methods
+                &[0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, length],
+                contents,
+            ]
+            .concat();
+            ClassFile::from_bytes(&bytes).map_err(|e| (e.kind(), e.offset()))
+        };
+        let exceptions = [0x00, 0x01, 0x00, 0x05];
+        assert_eq!(Ok(()), class_file(4, &exceptions).map(|_| ()));
+        // the attribute ends in the middle of the exception table
+        assert_eq!(
+            Err((ClassFileParseError::InvalidAttributeLength, 49)),
+            class_file(3, &exceptions)
+        );
+        // trailing bytes in the attribute are not taken for the next structure
+        assert_eq!(
+            Err((ClassFileParseError::InvalidAttributeLength, 50)),
+            class_file(6, &[&exceptions[..], &[0x00, 0x00]].concat())
+        );
+        // the attribute claims more bytes than the class file has
+        assert_eq!(
+            Err((ClassFileParseError::UnexpectedEOF, 49)),
+            class_file(6, &exceptions[..3])
+        );
+    }
+
+    #[test]
+    fn test_nested_attribute_length() {
+        // a Code attribute of 26 bytes whose nested attribute claims 16 bytes,
+        // of which the Code attribute only has 8 left
+        let bytes = [
+            &[0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D][..],
+            &[0x00, 0x02, 0x01, 0x00, 0x04],
+            b"Code",
+            &[0x00; 12], // flags, this and super class, counts
+            &[0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1A],
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // stack, locals, length
+            &[0x00, 0x00, 0x00, 0x01],                         // exception table, attributes
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x10],
+            &[0x00; 16],
+        ]
+        .concat();
+        let error = ClassFile::from_bytes(&bytes).unwrap_err();
+        assert_eq!(ClassFileParseError::InvalidAttributeLength, error.kind());
+        assert_eq!(vec!["Code attribute", "Code attribute"], error.context());
+    }
+}
@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ModuleFlags: u16 {
         const OPEN = 0x0020;
         const SYNTHETIC = 0x1000;
@@ -9,6 +10,7 @@ bitflags! {
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct RequiresFlags: u16 {
         const TRANSITIVE = 0x0020;
         const STATIC_PHASE = 0x0040;
@@ -18,6 +20,7 @@ bitflags! {
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ExportsFlags: u16 {
         const SYNTHETIC = 0x1000;
         const MANDATED = 0x8000;
@@ -25,6 +28,7 @@ bitflags! {
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct OpensFlags: u16 {
         const SYNTHETIC = 0x1000;
         const MANDATED = 0x8000;
@@ -35,6 +39,7 @@ bitflags! {
     /// Specified by [`$4.1-B`]
     ///
     /// [`$4.1-B`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1-200-E.1
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ClassAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const FINAL = 0x0010;
@@ -52,6 +57,7 @@ bitflags! {
     /// Specified by [`$4.5-A`]
     ///
     /// [`$4.5-A`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5-200-A.1
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct FieldAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
@@ -69,6 +75,7 @@ bitflags! {
     /// Specified by [`$4.6-A`]
     ///
     /// [`$4.6-A`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6-200-A.1
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct MethodAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
@@ -89,6 +96,7 @@ bitflags! {
     /// Specified by [`$4.7.6-A`]
     ///
     /// [`$4.7.6-A`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6-300-D.1-D.1
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct InnerClassAccessFlags: u16 {
         const PUBLIC = 0x0001;
         const PRIVATE = 0x0002;
@@ -104,6 +112,7 @@ bitflags! {
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct MethodParameterAccessFlags: u16 {
         const FINAL = 0x0010;
         const SYNTHETIC = 0x1000;
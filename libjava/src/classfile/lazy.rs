@@ -0,0 +1,225 @@
+use crate::classfile::{AttributeInfo, ClassFileParseError, ConstantPool};
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+/// How [`ClassFile::parse_with`](crate::classfile::ClassFile::parse_with)
+/// parses attributes.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseMode {
+    /// Every attribute is parsed while the class file is parsed.
+    #[default]
+    Eager,
+    /// The attributes that loading and linking don't need are kept as their
+    /// raw bytes and only parsed on first access, see [`LazyAttribute`]. These
+    /// are the annotations, the debugging tables (`LineNumberTable`,
+    /// `LocalVariableTable`, `LocalVariableTypeTable` and
+    /// `SourceDebugExtension`), `StackMapTable` and `MethodParameters`.
+    ///
+    /// Malformed contents of these attributes are only reported when they are
+    /// accessed, not when the class file is parsed.
+    Lazy,
+}
+
+impl ParseMode {
+    /// Whether the attribute with the given name is kept unparsed in this mode.
+    pub(super) fn defers(self, name: &[u8]) -> bool {
+        self == ParseMode::Lazy
+            && matches!(
+                name,
+                b"StackMapTable"
+                    | b"LineNumberTable"
+                    | b"LocalVariableTable"
+                    | b"LocalVariableTypeTable"
+                    | b"SourceDebugExtension"
+                    | b"RuntimeVisibleAnnotations"
+                    | b"RuntimeInvisibleAnnotations"
+                    | b"RuntimeVisibleParameterAnnotations"
+                    | b"RuntimeInvisibleParameterAnnotations"
+                    | b"RuntimeVisibleTypeAnnotations"
+                    | b"RuntimeInvisibleTypeAnnotations"
+                    | b"AnnotationDefault"
+                    | b"MethodParameters"
+            )
+    }
+}
+
+/// An attribute that was parsed in [`ParseMode::Lazy`]: its contents are
+/// kept as they are in the class file, and are parsed the first time they are
+/// accessed with [`LazyAttribute::get`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LazyAttribute {
+    pub(super) name_index: u16,
+    length: u32,
+    pub(super) raw_bytes: Vec<u8>,
+    /// Not serialized, like the parsed contents aren't written to a class
+    /// file.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parsed: OnceLock<Box<AttributeInfo>>,
+}
+
+impl LazyAttribute {
+    pub(super) fn new(name_index: u16, length: u32, raw_bytes: Vec<u8>) -> Self {
+        Self {
+            name_index,
+            length,
+            raw_bytes,
+            parsed: OnceLock::new(),
+        }
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    /// The declared length of the attribute.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// The contents of the attribute as they are in the class file, without
+    /// the name index and length.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.raw_bytes
+    }
+
+    /// Whether the contents were parsed already.
+    pub fn is_materialized(&self) -> bool {
+        self.parsed.get().is_some()
+    }
+
+    /// Returns the parsed attribute, parsing it on the first call. The
+    /// constant pool must be the one of the class file that the attribute is
+    /// from. An error is returned again on every call, since nothing is kept
+    /// of a failed parse.
+    pub fn get(&self, cp: &ConstantPool) -> Result<&AttributeInfo, ClassFileParseError> {
+        if let Some(parsed) = self.parsed.get() {
+            return Ok(parsed);
+        }
+        let parsed = self.parse(cp)?;
+        Ok(self.parsed.get_or_init(|| Box::new(parsed)))
+    }
+
+    /// Parses the contents, without keeping them, see [`LazyAttribute::get`].
+    pub(super) fn parse(&self, cp: &ConstantPool) -> Result<AttributeInfo, ClassFileParseError> {
+        let source = [
+            &self.name_index.to_be_bytes()[..],
+            &self.length.to_be_bytes(),
+            &self.raw_bytes,
+        ]
+        .concat();
+        AttributeInfo::parse(cp, &mut source.as_slice())
+    }
+}
+
+/// Lazy attributes are equal if their contents are, whether or not they were
+/// materialized.
+impl PartialEq for LazyAttribute {
+    fn eq(&self, other: &Self) -> bool {
+        self.name_index == other.name_index
+            && self.length == other.length
+            && self.raw_bytes == other.raw_bytes
+    }
+}
+
+impl Eq for LazyAttribute {}
+
+impl AttributeInfo {
+    /// Returns the parsed attribute: the attribute itself, or the contents of
+    /// a [`AttributeInfo::Lazy`] attribute, which are parsed on the first
+    /// access. See [`LazyAttribute::get`].
+    pub fn materialize<'a>(
+        &'a self,
+        cp: &ConstantPool,
+    ) -> Result<&'a AttributeInfo, ClassFileParseError> {
+        match self {
+            AttributeInfo::Lazy(lazy) => lazy.get(cp),
+            attribute => Ok(attribute),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::ClassFile;
+
+    fn parse(mode: ParseMode) -> (Vec<u8>, ClassFile) {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        let class_file = ClassFile::parse_with(&mut bytes.as_slice(), mode).unwrap();
+        (bytes, class_file)
+    }
+
+    /// The attributes of the `Code` attribute of the `guarded` method, which
+    /// has a `LineNumberTable` and a `StackMapTable`.
+    fn code_attributes(class_file: &ClassFile) -> &[AttributeInfo] {
+        let method = class_file
+            .find_method("guarded", "(LFormats$Inner;)I")
+            .unwrap();
+        match &method.attributes()[0] {
+            AttributeInfo::Code { attributes, .. } => attributes,
+            attribute => panic!("expected Code, got {:?}", attribute),
+        }
+    }
+
+    #[test]
+    fn test_lazy_attributes() {
+        let (_, eager) = parse(ParseMode::Eager);
+        let (_, lazy) = parse(ParseMode::Lazy);
+        let cp = lazy.constant_pool();
+        let attributes = code_attributes(&lazy);
+        assert_eq!(2, attributes.len());
+        for (lazy, eager) in attributes.iter().zip(code_attributes(&eager)) {
+            let AttributeInfo::Lazy(attribute) = lazy else {
+                panic!("expected a lazy attribute, got {:?}", lazy);
+            };
+            assert!(!attribute.is_materialized());
+            assert_eq!(eager, lazy.materialize(cp).unwrap());
+            assert!(attribute.is_materialized());
+            // the second access returns the same parsed attribute
+            assert!(std::ptr::eq(
+                lazy.materialize(cp).unwrap(),
+                attribute.get(cp).unwrap()
+            ));
+        }
+
+        // attributes that are needed for loading are parsed right away
+        assert!(matches!(
+            lazy.fields()[0].attributes()[0],
+            AttributeInfo::ConstantValue { .. }
+        ));
+        assert!(lazy.validate().is_empty());
+    }
+
+    #[test]
+    fn test_lazy_round_trip() {
+        let (bytes, lazy) = parse(ParseMode::Lazy);
+        let mut written = vec![];
+        lazy.write(&mut written).unwrap();
+        assert_eq!(bytes, written);
+        assert_eq!(lazy.dump(), parse(ParseMode::Eager).1.dump());
+    }
+
+    #[test]
+    fn test_malformed_lazy_attribute() {
+        let (_, lazy) = parse(ParseMode::Lazy);
+        let AttributeInfo::Lazy(table) = &code_attributes(&lazy)[0] else {
+            panic!("expected a lazy attribute");
+        };
+        let truncated = LazyAttribute::new(
+            table.name_index(),
+            table.length(),
+            table.raw_bytes()[..3].to_vec(),
+        );
+        let cp = lazy.constant_pool();
+        assert_eq!(
+            Err(ClassFileParseError::UnexpectedEOF),
+            truncated.get(cp).map(|_| ())
+        );
+        assert!(!truncated.is_materialized());
+    }
+}
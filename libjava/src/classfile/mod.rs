@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use crate::bytecode::Op;
 use crate::classfile::ConstantPoolInfo::{
     ClassInfo, DoubleInfo, DynamicInfo, FieldrefInfo, FloatInfo, IntegerInfo,
     InterfaceMethodrefInfo, InvokeDynamicInfo, LongInfo, MethodHandleInfo, MethodTypeInfo,
@@ -9,21 +10,84 @@ use crate::classfile::VerificationTypeInfo::{
     DoubleVariable, FloatVariable, IntegerVariable, LongVariable, NullVariable, ObjectVariable,
     TopVariable, UninitializedThisVariable, UninitializedVariable,
 };
+use crate::io::Read;
+use crate::prelude::*;
+use alloc::borrow::Cow;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter};
+use core::ops::Index;
 use num_enum::TryFromPrimitive;
-use std::cmp::Ordering;
-use std::convert::TryFrom;
-use std::io::Read;
-use std::ops::Index;
 
+mod annotations;
+/// [`Arbitrary`](proptest::arbitrary::Arbitrary) implementations for property
+/// tests of class files, e.g. of round trips through writing and parsing.
+#[cfg(any(test, feature = "proptest"))]
+mod arbitrary;
+mod bootstrap;
+mod dedup;
+mod diff;
+mod dump;
+mod error;
 pub mod flags;
+mod lazy;
+mod module;
+/// The Modified UTF-8 encoding of `CONSTANT_Utf8` entries.
+pub mod mutf8;
+mod nesting;
+mod options;
+mod record;
+mod resolved;
+/// The fixtures that the tests of the modules of class files share.
+#[cfg(test)]
+mod test_util;
+mod validate;
+mod view;
+/// A visitor API for class files, like the one of ASM, to transform class
+/// files without rebuilding their structures by hand.
+pub mod visitor;
+mod write;
+
+pub use annotations::{AnnotationError, DecodedAnnotation, DecodedValue};
+pub use bootstrap::{BootstrapArgument, BootstrapError, DynamicReference, MethodHandle};
+pub use diff::{AttributeDiff, ClassDiff, MemberDiff};
+pub use error::ClassFileError;
+use error::{Frame, Source};
+pub use lazy::{LazyAttribute, ParseMode};
+pub use module::{Exports, ModuleDescriptor, Opens, Provides, Requires};
+pub use nesting::{EnclosingMethod, NestedClass, NestingKind};
+pub use options::{ParseLimit, ParseOptions};
+pub use record::RecordComponent;
+pub use resolved::{ResolvedConstant, ResolvedConstantPool, ResolvedMember};
+pub use validate::FormatViolation;
+pub use view::{
+    AttributeView, ClassFileView, ConstantPoolView, ConstantView, FieldView, MethodView,
+};
 
-#[derive(Eq, PartialEq, Debug)]
+/// The constant pool of a class file, with one item per index. Long and
+/// double constants take up two indices ([`$4.4.5`]), so each of them is
+/// followed by a [`ConstantPoolInfo::Unusable`] item, and the position of
+/// every item matches its index in the class file.
+///
+/// [`$4.4.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.5
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstantPool {
     items: Vec<ConstantPoolInfo>,
 }
 
 impl ConstantPool {
-    pub fn from(items: Vec<ConstantPoolInfo>) -> Self {
+    /// Creates a constant pool from the entries as they appear in the class
+    /// file, inserting the unusable item after every long and double constant.
+    pub fn from(entries: Vec<ConstantPoolInfo>) -> Self {
+        let mut items = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let slots = entry.slots();
+            items.push(entry);
+            if slots == 2 {
+                items.push(ConstantPoolInfo::Unusable);
+            }
+        }
         Self { items }
     }
 
@@ -34,42 +98,262 @@ impl ConstantPool {
     pub fn len(&self) -> usize {
         self.items.len()
     }
-}
 
-impl Index<usize> for ConstantPool {
-    type Output = ConstantPoolInfo;
+    pub fn iter(&self) -> impl Iterator<Item = &ConstantPoolInfo> {
+        self.items.iter()
+    }
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.items[index]
+    /// Returns the entry at the given index, which is 1-based like all
+    /// indices into the constant pool in a class file, or `None` if there is
+    /// no entry at the index.
+    pub fn get(&self, index: u16) -> Option<&ConstantPoolInfo> {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.items.get(i as usize))
     }
-}
 
-impl Index<u16> for ConstantPool {
-    type Output = ConstantPoolInfo;
+    /// Returns the entry at the given index like [`get`](Self::get), but fails
+    /// with [`IndexOutOfBounds`](ConstantPoolError::IndexOutOfBounds) if there
+    /// is none, for the getters of the typed entries below.
+    pub(crate) fn get_entry(&self, index: u16) -> Result<&ConstantPoolInfo, ConstantPoolError> {
+        self.get(index)
+            .ok_or(ConstantPoolError::IndexOutOfBounds(index))
+    }
+
+    /// Returns the string of the `CONSTANT_Utf8` entry at the given index,
+    /// decoded from Modified UTF-8. The string is only copied if it contains
+    /// null or supplementary characters.
+    pub fn get_utf8(&self, index: u16) -> Result<Cow<'_, str>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            Utf8Info { bytes, .. } => {
+                mutf8::decode(bytes).or(Err(ConstantPoolError::InvalidUtf8(index)))
+            }
+            _ => Err(ConstantPoolError::unexpected(index, "Utf8")),
+        }
+    }
+
+    /// Returns the name of the `CONSTANT_Class` entry at the given index, e.g.
+    /// `java/lang/Object`.
+    pub fn get_class_name(&self, index: u16) -> Result<Cow<'_, str>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            ClassInfo { name_index } => self.get_utf8(*name_index),
+            _ => Err(ConstantPoolError::unexpected(index, "Class")),
+        }
+    }
+
+    /// Returns the name of the `CONSTANT_Module` entry at the given index, e.g.
+    /// `java.base`.
+    pub fn get_module_name(&self, index: u16) -> Result<Cow<'_, str>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            ModuleInfo { name_index } => self.get_utf8(*name_index),
+            _ => Err(ConstantPoolError::unexpected(index, "Module")),
+        }
+    }
+
+    /// Returns the name of the `CONSTANT_Package` entry at the given index in
+    /// internal form, e.g. `java/lang`.
+    pub fn get_package_name(&self, index: u16) -> Result<Cow<'_, str>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            PackageInfo { name_index } => self.get_utf8(*name_index),
+            _ => Err(ConstantPoolError::unexpected(index, "Package")),
+        }
+    }
+
+    /// Returns the string of the `CONSTANT_String` entry at the given index.
+    pub fn get_string(&self, index: u16) -> Result<Cow<'_, str>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            StringInfo { string_index } => self.get_utf8(*string_index),
+            _ => Err(ConstantPoolError::unexpected(index, "String")),
+        }
+    }
+
+    pub fn get_integer(&self, index: u16) -> Result<i32, ConstantPoolError> {
+        self.get_entry(index)?
+            .as_i32()
+            .ok_or(ConstantPoolError::unexpected(index, "Integer"))
+    }
+
+    pub fn get_float(&self, index: u16) -> Result<f32, ConstantPoolError> {
+        self.get_entry(index)?
+            .as_f32()
+            .ok_or(ConstantPoolError::unexpected(index, "Float"))
+    }
+
+    pub fn get_long(&self, index: u16) -> Result<i64, ConstantPoolError> {
+        self.get_entry(index)?
+            .as_i64()
+            .ok_or(ConstantPoolError::unexpected(index, "Long"))
+    }
+
+    pub fn get_double(&self, index: u16) -> Result<f64, ConstantPoolError> {
+        self.get_entry(index)?
+            .as_f64()
+            .ok_or(ConstantPoolError::unexpected(index, "Double"))
+    }
+
+    pub fn get_name_and_type(&self, index: u16) -> Result<NameAndType<'_>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            NameAndTypeInfo {
+                name_index,
+                descriptor_index,
+            } => Ok(NameAndType {
+                name: self.get_utf8(*name_index)?,
+                descriptor: self.get_utf8(*descriptor_index)?,
+            }),
+            _ => Err(ConstantPoolError::unexpected(index, "NameAndType")),
+        }
+    }
+
+    /// Returns the `CONSTANT_Fieldref` entry at the given index.
+    pub fn get_field_ref(&self, index: u16) -> Result<MemberRef<'_>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            FieldrefInfo {
+                class_index,
+                name_and_type_index,
+            } => self.member_ref(*class_index, *name_and_type_index),
+            _ => Err(ConstantPoolError::unexpected(index, "Fieldref")),
+        }
+    }
+
+    /// Returns the `CONSTANT_Methodref` entry at the given index.
+    pub fn get_method_ref(&self, index: u16) -> Result<MemberRef<'_>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            MethodrefInfo {
+                class_index,
+                name_and_type_index,
+            } => self.member_ref(*class_index, *name_and_type_index),
+            _ => Err(ConstantPoolError::unexpected(index, "Methodref")),
+        }
+    }
+
+    /// Returns the `CONSTANT_InterfaceMethodref` entry at the given index.
+    pub fn get_interface_method_ref(&self, index: u16) -> Result<MemberRef<'_>, ConstantPoolError> {
+        match self.get_entry(index)? {
+            InterfaceMethodrefInfo {
+                class_index,
+                name_and_type_index,
+            } => self.member_ref(*class_index, *name_and_type_index),
+            _ => Err(ConstantPoolError::unexpected(index, "InterfaceMethodref")),
+        }
+    }
+
+    fn member_ref(
+        &self,
+        class_index: u16,
+        name_and_type_index: u16,
+    ) -> Result<MemberRef<'_>, ConstantPoolError> {
+        let NameAndType { name, descriptor } = self.get_name_and_type(name_and_type_index)?;
+        Ok(MemberRef {
+            class_name: self.get_class_name(class_index)?,
+            name,
+            descriptor,
+        })
+    }
+}
 
-    fn index(&self, index: u16) -> &Self::Output {
-        &self[index as usize]
+/// A resolved `CONSTANT_NameAndType` entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NameAndType<'a> {
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
+/// A resolved `CONSTANT_Fieldref`, `CONSTANT_Methodref` or
+/// `CONSTANT_InterfaceMethodref` entry.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MemberRef<'a> {
+    /// The name of the class that the member is referenced in, e.g.
+    /// `java/lang/Object`.
+    pub class_name: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
+/// A local variable of a method, from the `LocalVariableTable` and
+/// `LocalVariableTypeTable` attributes of its code, see
+/// [`MethodInfo::local_variable`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocalVariable<'a> {
+    pub name: Cow<'a, str>,
+    /// The field descriptor of the type of the variable, e.g. `Ljava/util/List;`.
+    pub descriptor: Cow<'a, str>,
+    /// The generic signature of the type of the variable, e.g.
+    /// `Ljava/util/List<Ljava/lang/String;>;`, if it has a generic type.
+    pub signature: Option<Cow<'a, str>>,
+}
+
+/// An error of the typed accessors of [`ConstantPool`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ConstantPoolError {
+    /// The index is 0 or past the end of the constant pool.
+    IndexOutOfBounds(u16),
+    /// The entry at the index is not of the expected kind, e.g. `Class`.
+    UnexpectedEntry { index: u16, expected: &'static str },
+    /// The `CONSTANT_Utf8` entry at the index is not valid Modified UTF-8.
+    InvalidUtf8(u16),
+    /// An entry can't be added, because the constant pool has as many entries
+    /// as a class file can have.
+    TooManyEntries,
+}
+
+impl ConstantPoolError {
+    fn unexpected(index: u16, expected: &'static str) -> Self {
+        ConstantPoolError::UnexpectedEntry { index, expected }
     }
 }
 
-impl Index<i32> for ConstantPool {
+/// Indexes the entries by their 0-based position, like [`ConstantPool::iter`],
+/// and panics if there is no such entry. Indices from a class file are 1-based
+/// and have to be looked up with [`ConstantPool::get`] instead, which is why
+/// there is no indexing by `u16`.
+impl Index<usize> for ConstantPool {
     type Output = ConstantPoolInfo;
 
-    fn index(&self, index: i32) -> &Self::Output {
-        &self[index as usize]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.items[index]
     }
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Version {
     major: u16,
     minor: u16,
 }
 
 impl Version {
+    /// The minor version of class files that depend on the preview features
+    /// of the Java SE release of their major version ([`$4.1`]).
+    ///
+    /// [`$4.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+    pub const PREVIEW_MINOR: u16 = 0xFFFF;
+
     pub fn new(major: u16, minor: u16) -> Self {
         Self { major, minor }
     }
+
+    pub fn major(&self) -> u16 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.minor
+    }
+
+    /// Whether this is the version of a class file that was compiled with
+    /// `--enable-preview`. Only major versions since Java SE 12 (56) have
+    /// preview features, so a minor version of 0xFFFF is an ordinary minor
+    /// version before that.
+    pub fn is_preview(&self) -> bool {
+        self.major >= 56 && self.minor == Self::PREVIEW_MINOR
+    }
+}
+
+/// Writes the version like `61.0`, like the JDK does in its messages.
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
 }
 
 impl PartialOrd<Self> for Version {
@@ -92,6 +376,7 @@ impl Ord for Version {
 ///
 /// [`$4.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassFile {
     /// Always 0xCAFEBABE
     magic: u32,
@@ -134,8 +419,9 @@ pub enum ConstantPoolInfoTag {
 /// Specified by [`$5.4.3.5-A`]
 ///
 /// [`$5.4.3.5-A`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.5-220
-#[derive(TryFromPrimitive, Eq, PartialEq, Debug)]
+#[derive(TryFromPrimitive, Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReferenceKind {
     GetField = 1,
     GetStatic = 2,
@@ -151,7 +437,8 @@ pub enum ReferenceKind {
 /// Specified by [`$4.4`]
 ///
 /// [`$4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConstantPoolInfo {
     ClassInfo {
         name_index: u16,
@@ -214,12 +501,18 @@ pub enum ConstantPoolInfo {
     PackageInfo {
         name_index: u16,
     },
+    /// The index after a long or double constant, which is valid but must
+    /// not be used ([`$4.4.5`]).
+    ///
+    /// [`$4.4.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.5
+    Unusable,
 }
 
 /// Specified by [`$4.5`]
 ///
 /// [`$4.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FieldInfo {
     access_flags: flags::FieldAccessFlags,
     name_index: u16,
@@ -231,6 +524,7 @@ pub struct FieldInfo {
 ///
 /// [`$4.6`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodInfo {
     access_flags: flags::MethodAccessFlags,
     name_index: u16,
@@ -239,6 +533,7 @@ pub struct MethodInfo {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExceptionTableEntry {
     start_pc: u16,
     end_pc: u16,
@@ -250,6 +545,7 @@ pub struct ExceptionTableEntry {
 ///
 /// [`$4.7.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StackMapFrame {
     Same {
         frame_type: u8,
@@ -285,6 +581,7 @@ pub enum StackMapFrame {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VerificationTypeInfo {
     TopVariable { tag: u8 },
     IntegerVariable { tag: u8 },
@@ -298,6 +595,7 @@ pub enum VerificationTypeInfo {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InnerClass {
     inner_class_info_index: u16,
     outer_class_info_index: u16,
@@ -306,12 +604,14 @@ pub struct InnerClass {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineNumberTableEntry {
     start_pc: u16,
     line_number: u16,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableTableEntry {
     start_pc: u16,
     length: u16,
@@ -321,6 +621,7 @@ pub struct LocalVariableTableEntry {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableTypeTableEntry {
     start_pc: u16,
     length: u16,
@@ -330,20 +631,28 @@ pub struct LocalVariableTypeTableEntry {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Annotation {
     type_index: u16,
     element_value_pairs: Vec<ElementValuePair>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementValuePair {
     element_name_index: u16,
     value: ElementValue,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ElementValue {
-    ConstValueIndex(u16),
+    /// A constant of the type given by the tag, e.g. `I` for an int or `s` for
+    /// a string.
+    ConstValueIndex {
+        tag: u8,
+        const_value_index: u16,
+    },
     EnumConstValue {
         type_name_index: u16,
         const_name_index: u16,
@@ -356,7 +665,11 @@ pub enum ElementValue {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeAnnotation {
+    /// The kind of target, which several kinds of [`TargetInfo`] share, e.g.
+    /// 0x13 to 0x15 for [`TargetInfo::Empty`].
+    target_type: u8,
     target_path: TypePath,
     target_info: TargetInfo,
     type_index: u16,
@@ -364,6 +677,7 @@ pub struct TypeAnnotation {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetInfo {
     TypeParameter {
         type_parameter_index: u8,
@@ -398,6 +712,7 @@ pub enum TargetInfo {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVarTargetTableEntry {
     start_pc: u16,
     length: u16,
@@ -406,7 +721,8 @@ pub struct LocalVarTargetTableEntry {
 
 #[derive(TryFromPrimitive)]
 #[repr(u8)]
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypePathKind {
     DeepArray,
     DeepNested,
@@ -415,29 +731,34 @@ pub enum TypePathKind {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Path {
     type_path_kind: TypePathKind,
     type_path_argument_index: u8,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypePath {
     path: Vec<Path>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootstrapMethod {
     bootstrap_method_ref: u16,
     bootstrap_arguments: Vec<u16>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MethodParameter {
     name_index: u16,
     access_flags: flags::MethodParameterAccessFlags,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleRequires {
     requires_index: u16,
     requires_flags: flags::RequiresFlags,
@@ -445,6 +766,7 @@ pub struct ModuleRequires {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleExports {
     exports_index: u16,
     exports_flags: flags::ExportsFlags,
@@ -452,6 +774,7 @@ pub struct ModuleExports {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleOpens {
     opens_index: u16,
     opens_flags: flags::OpensFlags,
@@ -459,12 +782,14 @@ pub struct ModuleOpens {
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleProvides {
     provides_index: u16,
     provides_with_index: Vec<u16>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecordComponentInfo {
     name_index: u16,
     descriptor_index: u16,
@@ -475,6 +800,7 @@ pub struct RecordComponentInfo {
 ///
 /// [`$4.7`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7
 #[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttributeInfo {
     ConstantValue {
         attribute_name_index: u16,
@@ -488,6 +814,9 @@ pub enum AttributeInfo {
         max_locals: u16,
         code_length: u32,
         code: Vec<u8>,
+        /// The instructions of `code` with their offsets, if the class file
+        /// was parsed with [`ClassFile::parse_decoded`].
+        ops: Option<Vec<(u32, Op)>>,
         exception_table: Vec<ExceptionTableEntry>,
         attributes: Vec<AttributeInfo>,
     },
@@ -637,9 +966,23 @@ pub enum AttributeInfo {
         attribute_length: u32,
         classes: Vec<u16>,
     },
+    /// An attribute that is not defined by the specification, e.g. one that
+    /// is emitted by the Kotlin or Scala compiler. The JVM must silently
+    /// ignore these ([`$4.7.1`]), but they are kept so that the class file can
+    /// be written back unchanged.
+    ///
+    /// [`$4.7.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.1
+    Unknown {
+        name_index: u16,
+        length: u32,
+        raw_bytes: Vec<u8>,
+    },
+    /// An attribute whose contents are only parsed when they are first
+    /// accessed, see [`ParseMode::Lazy`] and [`AttributeInfo::materialize`].
+    Lazy(LazyAttribute),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ClassFileParseError {
     InvalidTypePathKind,
     InvalidTypeAnnotationTargetType,
@@ -649,9 +992,93 @@ pub enum ClassFileParseError {
     InvalidConstantPoolInfoTag,
     InvalidVerificationTypeTag,
     InvalidReferenceKind,
+    InvalidConstantPoolCount,
+    /// `this_class`, `super_class` or one of the interfaces doesn't refer to a
+    /// `CONSTANT_Class` entry with a valid name.
+    InvalidClassIndex,
+    /// The `attribute_length` of an attribute doesn't match its contents, or
+    /// exceeds the attribute that it is nested in.
+    InvalidAttributeLength,
+    InvalidStackMapFrameType,
+    NestingTooDeep,
+    /// The code of a method contains an invalid instruction, or doesn't end
+    /// on an instruction boundary. Only checked by
+    /// [`ClassFile::parse_decoded`].
+    InvalidCode,
+    /// The class file exceeds a limit of the [`ParseOptions`] it was parsed
+    /// with.
+    LimitExceeded(ParseLimit),
+    TrailingBytes,
     UnexpectedEOF,
 }
 
+/// The maximum number of items that are allocated up front for a table, no
+/// matter how many items the class file claims it contains. Larger tables
+/// grow as their items are actually read, so that a malformed count can't
+/// allocate more memory than the class file is large.
+const MAX_INITIAL_CAPACITY: usize = 256;
+
+/// The number of bytes that [`read_byte_vec`] reads at once.
+const READ_CHUNK_SIZE: usize = 8192;
+
+/// How deep attributes and annotation element values may be nested, e.g.
+/// attributes of a `Code` attribute, or arrays of annotations within an
+/// annotation. Valid class files stay far below this, but without a limit,
+/// malformed ones could overflow the stack.
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Whether the given (1-based) index refers to a `CONSTANT_Class` entry, whose
+/// name is a `CONSTANT_Utf8` entry with valid UTF-8.
+fn is_class_info(cp: &ConstantPool, index: u16) -> bool {
+    cp.get_class_name(index).is_ok()
+}
+
+fn initial_capacity(count: usize) -> usize {
+    count.min(MAX_INITIAL_CAPACITY)
+}
+
+/// Decodes the instructions of the code of a method, with their offsets.
+fn decode_code(
+    source: &mut Source<impl Read>,
+    code: &[u8],
+) -> Result<Vec<(u32, Op)>, ClassFileParseError> {
+    let mut ops = vec![];
+    let mut remaining = code;
+    while !remaining.is_empty() {
+        let offset = code.len() - remaining.len();
+        let op = source.within(Frame::Instruction(offset as u32), |_| {
+            Op::parse_at(&mut remaining, offset).map_err(|_| ClassFileParseError::InvalidCode)
+        })?;
+        ops.push((offset as u32, op));
+    }
+    Ok(ops)
+}
+
+/// Combines the `high_bytes` and `low_bytes` of a long or double constant
+/// ([`$4.4.5`]).
+///
+/// [`$4.4.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.5
+fn combine(high_bytes: u32, low_bytes: u32) -> u64 {
+    ((high_bytes as u64) << 32) | low_bytes as u64
+}
+
+/// Reads exactly `length` bytes, without allocating them before they have
+/// actually been read.
+fn read_byte_vec(source: &mut impl Read, length: u32) -> Result<Vec<u8>, ClassFileParseError> {
+    let length = length as usize;
+    let mut bytes = Vec::with_capacity(initial_capacity(length));
+    // the bytes are read in chunks, so that the vector only grows by what
+    // the source actually contains
+    while bytes.len() < length {
+        let start = bytes.len();
+        bytes.resize(start + (length - start).min(READ_CHUNK_SIZE), 0);
+        source
+            .read_exact(&mut bytes[start..])
+            .or(Err(ClassFileParseError::UnexpectedEOF))?;
+    }
+    Ok(bytes)
+}
+
 macro_rules! read_bytes {
     ($source:expr, $count:expr) => {{
         let mut buf = [0_u8; $count];
@@ -681,7 +1108,52 @@ macro_rules! read_u32 {
 }
 
 impl ClassFile {
-    pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+    pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileError> {
+        Self::parse_with(source, ParseMode::Eager)
+    }
+
+    /// Parses a class file, parsing its attributes as given by the mode.
+    pub fn parse_with(source: &mut impl Read, mode: ParseMode) -> Result<Self, ClassFileError> {
+        let mut source = Source::new(source);
+        let mut cp = None;
+        Self::parse_source(&mut source, mode, &mut cp)
+            .map_err(|kind| source.error(kind, cp.as_ref()))
+    }
+
+    /// Parses a class file with the given options, e.g. with lower limits for
+    /// class files from untrusted sources. Fails with
+    /// [`ClassFileParseError::LimitExceeded`] as soon as the class file
+    /// exceeds one of the limits.
+    pub fn parse_with_options(
+        source: &mut impl Read,
+        options: &ParseOptions,
+    ) -> Result<Self, ClassFileError> {
+        let mut source = Source::new(source).with_options(*options);
+        let mut cp = None;
+        Self::parse_source(&mut source, options.mode, &mut cp)
+            .map_err(|kind| source.error(kind, cp.as_ref()))
+    }
+
+    /// Parses a class file like [`ClassFile::parse_with`], and also decodes
+    /// the code of every method into its instructions, so that consumers
+    /// don't have to decode it again. Fails with
+    /// [`ClassFileParseError::InvalidCode`] if any code contains an invalid
+    /// instruction or doesn't end on an instruction boundary.
+    pub fn parse_decoded(source: &mut impl Read, mode: ParseMode) -> Result<Self, ClassFileError> {
+        let mut source = Source::new(source).decoding_code();
+        let mut cp = None;
+        Self::parse_source(&mut source, mode, &mut cp)
+            .map_err(|kind| source.error(kind, cp.as_ref()))
+    }
+
+    /// Parses a class file from the source. The constant pool is stored in
+    /// `cp_slot` as soon as it is parsed, so that errors after it can refer to
+    /// attributes by their names.
+    fn parse_source(
+        source: &mut Source<impl Read>,
+        mode: ParseMode,
+        cp_slot: &mut Option<ConstantPool>,
+    ) -> Result<Self, ClassFileParseError> {
         let magic = read_u32!(source);
         if magic != 0xCAFEBABE {
             return Err(ClassFileParseError::InvalidMagicValue);
@@ -691,45 +1163,76 @@ impl ClassFile {
         let major_version = read_u16!(source);
 
         let constant_pool_count = read_u16!(source);
+        if constant_pool_count == 0 {
+            return Err(ClassFileParseError::InvalidConstantPoolCount);
+        }
+        source.check(ParseLimit::ConstantPoolCount, constant_pool_count as usize)?;
         let mut cp_info: Vec<ConstantPoolInfo> =
-            Vec::with_capacity((constant_pool_count - 1) as usize);
-        for _ in 0..constant_pool_count - 1 {
-            cp_info.push(ConstantPoolInfo::parse(source)?);
+            Vec::with_capacity(initial_capacity(constant_pool_count as usize - 1));
+        let mut slots = 0;
+        while slots < constant_pool_count as usize - 1 {
+            let index = slots as u16 + 1;
+            let info = source.within(Frame::ConstantPoolEntry(index), ConstantPoolInfo::parse)?;
+            slots += info.slots();
+            cp_info.push(info);
         }
-        let cp = ConstantPool::from(cp_info);
+        // the last entry can't be a long or double constant that takes up an
+        // index past the end of the pool
+        if slots != constant_pool_count as usize - 1 {
+            return Err(ClassFileParseError::InvalidConstantPoolCount);
+        }
+        let cp = &*cp_slot.insert(ConstantPool::from(cp_info));
 
         let access_flags = flags::ClassAccessFlags::from_bits_truncate(read_u16!(source));
         let this_class = read_u16!(source);
         let super_class = read_u16!(source);
 
         let interfaces_count = read_u16!(source);
-        let mut interfaces: Vec<u16> = Vec::with_capacity(interfaces_count as usize);
+        let mut interfaces: Vec<u16> =
+            Vec::with_capacity(initial_capacity(interfaces_count as usize));
         for _ in 0..interfaces_count {
             interfaces.push(read_u16!(source));
         }
 
         let fields_count = read_u16!(source);
-        let mut fields: Vec<FieldInfo> = Vec::with_capacity(fields_count as usize);
-        for _ in 0..fields_count {
-            fields.push(FieldInfo::parse(&cp, source)?);
+        let mut fields: Vec<FieldInfo> =
+            Vec::with_capacity(initial_capacity(fields_count as usize));
+        for i in 0..fields_count {
+            fields.push(source.within(Frame::Field(i), |source| {
+                FieldInfo::parse_with(cp, source, mode)
+            })?);
         }
 
         let methods_count = read_u16!(source);
-        let mut methods: Vec<MethodInfo> = Vec::with_capacity(methods_count as usize);
-        for _ in 0..methods_count {
-            methods.push(MethodInfo::parse(&cp, source)?);
+        let mut methods: Vec<MethodInfo> =
+            Vec::with_capacity(initial_capacity(methods_count as usize));
+        for i in 0..methods_count {
+            methods.push(source.within(Frame::Method(i), |source| {
+                MethodInfo::parse_with(cp, source, mode)
+            })?);
         }
 
         let attributes_count = read_u16!(source);
-        let mut attributes: Vec<AttributeInfo> = Vec::with_capacity(attributes_count as usize);
+        source.check(ParseLimit::Attributes, attributes_count as usize)?;
+        let mut attributes: Vec<AttributeInfo> =
+            Vec::with_capacity(initial_capacity(attributes_count as usize));
         for _ in 0..attributes_count {
-            attributes.push(AttributeInfo::parse(&cp, source)?);
+            attributes.push(AttributeInfo::parse_nested(cp, source, 0, mode)?);
+        }
+
+        // the class and its supertypes are only referred to by index, so check
+        // the indices once here rather than on every access
+        if !is_class_info(cp, this_class)
+            || (super_class != 0 && !is_class_info(cp, super_class))
+            || !interfaces.iter().all(|index| is_class_info(cp, *index))
+        {
+            return Err(ClassFileParseError::InvalidClassIndex);
         }
 
         Ok(Self {
             magic,
             version: Version::new(major_version, minor_version),
-            cp_info: cp,
+            cp_info: cp_slot.take().unwrap(),
             access_flags,
             this_class,
             super_class,
@@ -740,12 +1243,152 @@ impl ClassFile {
         })
     }
 
+    /// Parses a class file that is completely contained in the given bytes.
+    /// Unlike [`ClassFile::parse`], this fails if there are any bytes after the
+    /// end of the class file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ClassFileError> {
+        let mut source = Source::new(bytes);
+        let mut cp = None;
+        let class_file = Self::parse_source(&mut source, ParseMode::Eager, &mut cp)
+            .map_err(|kind| source.error(kind, cp.as_ref()))?;
+        if source.offset() != bytes.len() as u64 {
+            return Err(source.error(ClassFileParseError::TrailingBytes, None));
+        }
+        Ok(class_file)
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Whether the class file depends on preview features, see
+    /// [`Version::is_preview`].
+    pub fn is_preview(&self) -> bool {
+        self.version.is_preview()
+    }
+
     pub fn constant_pool(&self) -> &ConstantPool {
         &self.cp_info
     }
 
+    /// Returns the name of the class defined by this class file.
     pub fn this_class(&self) -> String {
-        self.constant_pool()[self.this_class].unwrap_utf8()
+        self.this_class_name()
+            .expect("this_class is not a class info")
+            .into_owned()
+    }
+
+    /// Returns the name of the direct superclass, or [`None`] if this is
+    /// `java/lang/Object` (or a module-info), which has no superclass.
+    pub fn super_class(&self) -> Option<String> {
+        self.super_class_name().ok().flatten().map(Cow::into_owned)
+    }
+
+    /// Returns the names of the direct superinterfaces, in the order of the
+    /// class file. Entries that are not class constants are skipped.
+    pub fn interfaces(&self) -> Vec<String> {
+        self.interfaces
+            .iter()
+            .filter_map(|index| self.cp_info.get_class_name(*index).ok())
+            .map(Cow::into_owned)
+            .collect()
+    }
+
+    /// Resolves the name of the class defined by this class file through the
+    /// constant pool, without copying it unless it has to be decoded from
+    /// modified UTF-8. Unlike [`ClassFile::this_class`], this fails instead of
+    /// panicking if `this_class` is not a `CONSTANT_Class` entry.
+    pub fn this_class_name(&self) -> Result<Cow<'_, str>, ConstantPoolError> {
+        self.cp_info.get_class_name(self.this_class)
+    }
+
+    /// Resolves the name of the direct superclass, which is [`None`] for
+    /// `java/lang/Object` and module-infos. Fails if `super_class` is neither
+    /// 0 nor a `CONSTANT_Class` entry.
+    pub fn super_class_name(&self) -> Result<Option<Cow<'_, str>>, ConstantPoolError> {
+        match self.super_class {
+            0 => Ok(None),
+            index => self.cp_info.get_class_name(index).map(Some),
+        }
+    }
+
+    /// Resolves the names of the direct superinterfaces, in the order of the
+    /// class file. Fails on the first entry that is not a `CONSTANT_Class`.
+    pub fn interface_names(&self) -> Result<Vec<Cow<'_, str>>, ConstantPoolError> {
+        self.interfaces
+            .iter()
+            .map(|index| self.cp_info.get_class_name(*index))
+            .collect()
+    }
+
+    /// Returns the names of the classes that may directly extend or implement
+    /// this class, from its `PermittedSubclasses` attribute ([`$4.7.31`]), in
+    /// the order of the class file. This is empty unless the class is sealed.
+    /// Entries that are not class constants are skipped.
+    ///
+    /// [`$4.7.31`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.31
+    pub fn permitted_subclasses(&self) -> Vec<String> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeInfo::PermittedSubclasses { classes, .. } => Some(classes),
+                _ => None,
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|index| self.cp_info.get_class_name(*index).ok())
+            .map(Cow::into_owned)
+            .collect()
+    }
+
+    /// Whether this class is sealed, i.e. has a `PermittedSubclasses`
+    /// attribute, so that only the classes in it may directly extend or
+    /// implement it.
+    pub fn is_sealed(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| matches!(attribute, AttributeInfo::PermittedSubclasses { .. }))
+    }
+
+    pub fn access_flags(&self) -> flags::ClassAccessFlags {
+        self.access_flags
+    }
+
+    pub fn fields(&self) -> &[FieldInfo] {
+        &self.fields
+    }
+
+    pub fn methods(&self) -> &[MethodInfo] {
+        &self.methods
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    /// Finds a field declared in this class file by its name and descriptor,
+    /// e.g. `find_field("count", "I")`.
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&FieldInfo> {
+        let (name, descriptor) = (mutf8::encode(name), mutf8::encode(descriptor));
+        self.fields.iter().find(|f| {
+            self.is_utf8(f.name_index, &name) && self.is_utf8(f.descriptor_index, &descriptor)
+        })
+    }
+
+    /// Finds a method declared in this class file by its name and descriptor,
+    /// e.g. `find_method("main", "([Ljava/lang/String;)V")`.
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo> {
+        let (name, descriptor) = (mutf8::encode(name), mutf8::encode(descriptor));
+        self.methods.iter().find(|m| {
+            self.is_utf8(m.name_index, &name) && self.is_utf8(m.descriptor_index, &descriptor)
+        })
+    }
+
+    /// Whether the constant at the given index is a `CONSTANT_Utf8` entry
+    /// with the given bytes, which compares the encoded strings rather than
+    /// decoding the entry.
+    fn is_utf8(&self, index: u16, expected: &[u8]) -> bool {
+        matches!(self.cp_info.get(index), Some(Utf8Info { bytes, .. }) if bytes == expected)
     }
 }
 
@@ -778,11 +1421,69 @@ impl ConstantPoolInfo {
         }
     }
 
-    /// Copies the bytes from the utf8 info into a string.
+    /// The number of indices in the constant pool that this entry takes up.
+    pub fn slots(&self) -> usize {
+        match self {
+            LongInfo { .. } | DoubleInfo { .. } => 2,
+            _ => 1,
+        }
+    }
+
+    /// The value of a `CONSTANT_Integer` entry, or [`None`] for other entries.
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            IntegerInfo { bytes } => Some(*bytes as i32),
+            _ => None,
+        }
+    }
+
+    /// The value of a `CONSTANT_Float` entry, or [`None`] for other entries.
+    /// The bits are kept as they are, including those of NaN values.
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            FloatInfo { bytes } => Some(f32::from_bits(*bytes)),
+            _ => None,
+        }
+    }
+
+    /// The value of a `CONSTANT_Long` entry, or [`None`] for other entries.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            LongInfo {
+                high_bytes,
+                low_bytes,
+            } => Some(combine(*high_bytes, *low_bytes) as i64),
+            _ => None,
+        }
+    }
+
+    /// The value of a `CONSTANT_Double` entry, or [`None`] for other entries.
+    /// The bits are kept as they are, including those of NaN values.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DoubleInfo {
+                high_bytes,
+                low_bytes,
+            } => Some(f64::from_bits(combine(*high_bytes, *low_bytes))),
+            _ => None,
+        }
+    }
+
+    /// Creates a `CONSTANT_Utf8` entry for the given string, encoded as
+    /// Modified UTF-8. The writer fails for strings longer than 65535 bytes.
+    pub fn utf8(s: &str) -> Self {
+        let bytes = mutf8::encode(s).into_owned();
+        Utf8Info {
+            length: u16::try_from(bytes.len()).unwrap_or(u16::MAX),
+            bytes,
+        }
+    }
+
+    /// Decodes the bytes from the utf8 info into a string.
     pub fn unwrap_utf8(&self) -> String {
         match self {
-            ConstantPoolInfo::Utf8Info { length, bytes } => {
-                String::from_utf8(bytes.clone()).unwrap()
+            ConstantPoolInfo::Utf8Info { length: _, bytes } => {
+                mutf8::decode(bytes).unwrap().into_owned()
             }
             _ => panic!("not utf8 info"),
         }
@@ -790,10 +1491,7 @@ impl ConstantPoolInfo {
 
     fn parse_utf8(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let length = read_u16!(source);
-        let mut bytes = vec![0_u8; length as usize];
-        source
-            .read_exact(&mut bytes)
-            .or(Err(ClassFileParseError::UnexpectedEOF))?;
+        let bytes = read_byte_vec(source, length as u32)?;
 
         Ok(Utf8Info { length, bytes })
     }
@@ -925,14 +1623,39 @@ impl ConstantPoolInfo {
 }
 
 impl FieldInfo {
+    pub fn access_flags(&self) -> flags::FieldAccessFlags {
+        self.access_flags
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
     pub fn parse(cp: &ConstantPool, source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_with(cp, &mut Source::new(source), ParseMode::Eager)
+    }
+
+    fn parse_with(
+        cp: &ConstantPool,
+        source: &mut Source<impl Read>,
+        mode: ParseMode,
+    ) -> Result<Self, ClassFileParseError> {
         let access_flags = flags::FieldAccessFlags::from_bits_truncate(read_u16!(source));
         let name_index = read_u16!(source);
         let descriptor_index = read_u16!(source);
         let attributes_count = read_u16!(source);
-        let mut attributes = Vec::with_capacity(attributes_count as usize);
+        source.check(ParseLimit::Attributes, attributes_count as usize)?;
+        let mut attributes = Vec::with_capacity(initial_capacity(attributes_count as usize));
         for _ in 0..attributes_count {
-            attributes.push(AttributeInfo::parse(cp, source)?);
+            attributes.push(AttributeInfo::parse_nested(cp, source, 0, mode)?);
         }
         Ok(FieldInfo {
             access_flags,
@@ -944,15 +1667,142 @@ impl FieldInfo {
 }
 
 impl MethodInfo {
+    pub fn access_flags(&self) -> flags::MethodAccessFlags {
+        self.access_flags
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    /// The source line of the instruction at the given offset in the code,
+    /// from the `LineNumberTable` attributes of the `Code` attribute
+    /// ([`$4.7.12`]). That is the line of the entry with the greatest start
+    /// that is not after the offset, since an entry covers the instructions up
+    /// to the next one. The entries may be spread over several tables, in any
+    /// order.
+    ///
+    /// Returns [`None`] if the method has no code, the offset is not within
+    /// the code, or no entry starts before it. Malformed tables are ignored
+    /// like missing ones. The constant pool must be the one of the class file
+    /// that the method is declared in.
+    ///
+    /// [`$4.7.12`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.12
+    pub fn line_for_pc(&self, cp: &ConstantPool, pc: u32) -> Option<u16> {
+        let (code, attributes) = self.attributes.iter().find_map(|a| match a {
+            AttributeInfo::Code {
+                code, attributes, ..
+            } => Some((code, attributes)),
+            _ => None,
+        })?;
+        if pc as usize >= code.len() {
+            return None;
+        }
+        attributes
+            .iter()
+            .filter_map(|a| match a.materialize(cp) {
+                Ok(AttributeInfo::LineNumberTable {
+                    line_number_table, ..
+                }) => Some(line_number_table),
+                _ => None,
+            })
+            .flatten()
+            .filter(|e| e.start_pc as u32 <= pc)
+            .max_by_key(|e| e.start_pc)
+            .map(|e| e.line_number)
+    }
+
+    /// Returns the local variable in the given slot at the instruction at the
+    /// given offset of the code of this method, from the `LocalVariableTable`
+    /// ([`$4.7.13`]) and `LocalVariableTypeTable` ([`$4.7.14`]) attributes of
+    /// the `Code` attribute. An entry covers the instructions from its start
+    /// up to its start plus its length, exclusive. A long or a double is found
+    /// in its first slot only.
+    ///
+    /// Returns [`None`] if the method has no code, or no entry covers the
+    /// slot at the offset, e.g. because the class was compiled without
+    /// `javac -g`. The signature is [`None`] if no entry of a
+    /// `LocalVariableTypeTable` covers it. Malformed tables and entries are
+    /// ignored like missing ones. The constant pool must be the one of the
+    /// class file that the method is declared in.
+    ///
+    /// [`$4.7.13`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.13
+    /// [`$4.7.14`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.14
+    pub fn local_variable<'a>(
+        &'a self,
+        cp: &'a ConstantPool,
+        pc: u32,
+        slot: u16,
+    ) -> Option<LocalVariable<'a>> {
+        let attributes = self.attributes.iter().find_map(|a| match a {
+            AttributeInfo::Code { attributes, .. } => Some(attributes),
+            _ => None,
+        })?;
+        let covers = |start_pc: u16, length: u16, index: u16| {
+            index == slot && (start_pc as u32..start_pc as u32 + length as u32).contains(&pc)
+        };
+        let tables = || attributes.iter().filter_map(|a| a.materialize(cp).ok());
+
+        let (name, descriptor) = tables()
+            .filter_map(|a| match a {
+                AttributeInfo::LocalVariableTable {
+                    local_variable_table,
+                    ..
+                } => Some(local_variable_table),
+                _ => None,
+            })
+            .flatten()
+            .filter(|e| covers(e.start_pc, e.length, e.index))
+            .find_map(|e| {
+                Some((
+                    cp.get_utf8(e.name_index).ok()?,
+                    cp.get_utf8(e.descriptor_index).ok()?,
+                ))
+            })?;
+        let signature = tables()
+            .filter_map(|a| match a {
+                AttributeInfo::LocalVariableTypeTable {
+                    local_variable_type_table,
+                    ..
+                } => Some(local_variable_type_table),
+                _ => None,
+            })
+            .flatten()
+            .filter(|e| covers(e.start_pc, e.length, e.index))
+            .find_map(|e| cp.get_utf8(e.signature_index).ok());
+        Some(LocalVariable {
+            name,
+            descriptor,
+            signature,
+        })
+    }
+
     pub fn parse(cp: &ConstantPool, source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_with(cp, &mut Source::new(source), ParseMode::Eager)
+    }
+
+    fn parse_with(
+        cp: &ConstantPool,
+        source: &mut Source<impl Read>,
+        mode: ParseMode,
+    ) -> Result<Self, ClassFileParseError> {
         let access_flags_bytes = read_u16!(source);
         let access_flags = flags::MethodAccessFlags::from_bits_truncate(access_flags_bytes);
         let name_index = read_u16!(source);
         let descriptor_index = read_u16!(source);
         let attributes_count = read_u16!(source);
-        let mut attributes = Vec::with_capacity(attributes_count as usize);
+        source.check(ParseLimit::Attributes, attributes_count as usize)?;
+        let mut attributes = Vec::with_capacity(initial_capacity(attributes_count as usize));
         for _ in 0..attributes_count {
-            attributes.push(AttributeInfo::parse(cp, source)?);
+            attributes.push(AttributeInfo::parse_nested(cp, source, 0, mode)?);
         }
         Ok(MethodInfo {
             access_flags,
@@ -965,13 +1815,63 @@ impl MethodInfo {
 
 impl AttributeInfo {
     pub fn parse(cp: &ConstantPool, source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_nested(cp, &mut Source::new(source), 0, ParseMode::Eager)
+    }
+
+    /// Parses an attribute that is nested in `depth` other attributes.
+    fn parse_nested(
+        cp: &ConstantPool,
+        source: &mut Source<impl Read>,
+        depth: usize,
+        mode: ParseMode,
+    ) -> Result<Self, ClassFileParseError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ClassFileParseError::NestingTooDeep);
+        }
+        source.check(ParseLimit::NestingDepth, depth)?;
+
         let attribute_name_index = read_u16!(source);
         let attribute_length = read_u32!(source);
+        source.within(Frame::Attribute(attribute_name_index), |source| {
+            source.bounded(attribute_length, |source| {
+                Self::parse_contents(
+                    cp,
+                    source,
+                    depth,
+                    mode,
+                    attribute_name_index,
+                    attribute_length,
+                )
+            })
+        })
+    }
 
-        match &cp[(attribute_name_index - 1) as usize] {
-            ConstantPoolInfo::Utf8Info { length: _, bytes } => Ok(match bytes.as_slice() {
+    /// Parses the contents of an attribute after its name index and length.
+    fn parse_contents(
+        cp: &ConstantPool,
+        source: &mut Source<impl Read>,
+        depth: usize,
+        mode: ParseMode,
+        attribute_name_index: u16,
+        attribute_length: u32,
+    ) -> Result<Self, ClassFileParseError> {
+        // the index is 1-based, so 0 wraps around and is out of bounds as well
+        match cp
+            .items
+            .get((attribute_name_index as usize).wrapping_sub(1))
+        {
+            Some(ConstantPoolInfo::Utf8Info { length: _, bytes }) if mode.defers(bytes) => {
+                Ok(Self::Lazy(LazyAttribute::new(
+                    attribute_name_index,
+                    attribute_length,
+                    read_byte_vec(source, attribute_length)?,
+                )))
+            }
+            Some(ConstantPoolInfo::Utf8Info { length: _, bytes }) => Ok(match bytes.as_slice() {
                 b"ConstantValue" => {
-                    assert_eq!(2, attribute_length);
+                    if attribute_length != 2 {
+                        return Err(ClassFileParseError::InvalidAttributeLength);
+                    }
                     Self::ConstantValue {
                         attribute_name_index,
                         attribute_length,
@@ -982,20 +1882,25 @@ impl AttributeInfo {
                     let max_stack = read_u16!(source);
                     let max_locals = read_u16!(source);
                     let code_length = read_u32!(source);
-                    let mut code = vec![0_u8; code_length as usize];
-                    source
-                        .read_exact(&mut code)
-                        .or(Err(ClassFileParseError::UnexpectedEOF))?;
+                    source.check(ParseLimit::CodeLength, code_length as usize)?;
+                    let code = read_byte_vec(source, code_length)?;
+                    let ops = if source.decodes_code() {
+                        Some(decode_code(source, &code)?)
+                    } else {
+                        None
+                    };
                     let exception_table_length = read_u16!(source);
                     let mut exception_table: Vec<ExceptionTableEntry> =
-                        Vec::with_capacity(exception_table_length as usize);
+                        Vec::with_capacity(initial_capacity(exception_table_length as usize));
                     for _ in 0..exception_table_length {
                         exception_table.push(ExceptionTableEntry::parse(source)?);
                     }
                     let attributes_count = read_u16!(source);
-                    let mut attributes = Vec::with_capacity(attributes_count as usize);
+                    source.check(ParseLimit::Attributes, attributes_count as usize)?;
+                    let mut attributes =
+                        Vec::with_capacity(initial_capacity(attributes_count as usize));
                     for _ in 0..attributes_count {
-                        attributes.push(AttributeInfo::parse(cp, source)?);
+                        attributes.push(AttributeInfo::parse_nested(cp, source, depth + 1, mode)?);
                     }
 
                     Self::Code {
@@ -1005,15 +1910,17 @@ impl AttributeInfo {
                         max_locals,
                         code_length,
                         code,
+                        ops,
                         exception_table,
                         attributes,
                     }
                 }
                 b"StackMapTable" => {
                     let number_of_entries = read_u16!(source);
-                    let mut entries = Vec::with_capacity(number_of_entries as usize);
-                    for _ in 0..number_of_entries {
-                        entries.push(StackMapFrame::parse(source)?);
+                    let mut entries =
+                        Vec::with_capacity(initial_capacity(number_of_entries as usize));
+                    for i in 0..number_of_entries {
+                        entries.push(source.within(Frame::Entry(i), StackMapFrame::parse)?);
                     }
                     Self::StackMapTable {
                         attribute_name_index,
@@ -1024,7 +1931,7 @@ impl AttributeInfo {
                 b"Exceptions" => {
                     let number_of_exceptions = read_u16!(source);
                     let mut exception_index_table =
-                        Vec::with_capacity(number_of_exceptions as usize);
+                        Vec::with_capacity(initial_capacity(number_of_exceptions as usize));
                     for _ in 0..number_of_exceptions {
                         exception_index_table.push(read_u16!(source));
                     }
@@ -1036,7 +1943,8 @@ impl AttributeInfo {
                 }
                 b"InnerClasses" => {
                     let number_of_classes = read_u16!(source);
-                    let mut classes = Vec::with_capacity(number_of_classes as usize);
+                    let mut classes =
+                        Vec::with_capacity(initial_capacity(number_of_classes as usize));
                     for _ in 0..number_of_classes {
                         classes.push(InnerClass::parse(source)?);
                     }
@@ -1072,10 +1980,7 @@ impl AttributeInfo {
                     sourcefile_index: read_u16!(source),
                 },
                 b"SourceDebugExtension" => {
-                    let mut debug_extension = vec![0_u8; attribute_length as usize];
-                    source
-                        .read_exact(&mut debug_extension)
-                        .or(Err(ClassFileParseError::UnexpectedEOF))?;
+                    let debug_extension = read_byte_vec(source, attribute_length)?;
                     Self::SourceDebugExtension {
                         attribute_name_index,
                         attribute_length,
@@ -1085,7 +1990,7 @@ impl AttributeInfo {
                 b"LineNumberTable" => {
                     let line_number_table_length = read_u16!(source);
                     let mut line_number_table =
-                        Vec::with_capacity(line_number_table_length as usize);
+                        Vec::with_capacity(initial_capacity(line_number_table_length as usize));
                     for _ in 0..line_number_table_length {
                         line_number_table.push(LineNumberTableEntry::parse(source)?);
                     }
@@ -1098,7 +2003,7 @@ impl AttributeInfo {
                 b"LocalVariableTable" => {
                     let local_variable_table_length = read_u16!(source);
                     let mut local_variable_table =
-                        Vec::with_capacity(local_variable_table_length as usize);
+                        Vec::with_capacity(initial_capacity(local_variable_table_length as usize));
                     for _ in 0..local_variable_table_length {
                         local_variable_table.push(LocalVariableTableEntry::parse(source)?);
                     }
@@ -1111,7 +2016,7 @@ impl AttributeInfo {
                 b"LocalVariableTypeTable" => {
                     let local_variable_table_length = read_u16!(source);
                     let mut local_variable_type_table =
-                        Vec::with_capacity(local_variable_table_length as usize);
+                        Vec::with_capacity(initial_capacity(local_variable_table_length as usize));
                     for _ in 0..local_variable_table_length {
                         local_variable_type_table.push(LocalVariableTypeTableEntry::parse(source)?);
                     }
@@ -1127,9 +2032,10 @@ impl AttributeInfo {
                 },
                 b"RuntimeVisibleAnnotations" => {
                     let num_annotations = read_u16!(source);
-                    let mut annotations = Vec::with_capacity(num_annotations as usize);
-                    for _ in 0..num_annotations {
-                        annotations.push(Annotation::parse(source)?);
+                    let mut annotations =
+                        Vec::with_capacity(initial_capacity(num_annotations as usize));
+                    for i in 0..num_annotations {
+                        annotations.push(source.within(Frame::Entry(i), Annotation::parse)?);
                     }
                     Self::RuntimeVisibleAnnotations {
                         attribute_name_index,
@@ -1139,9 +2045,10 @@ impl AttributeInfo {
                 }
                 b"RuntimeInvisibleAnnotations" => {
                     let num_annotations = read_u16!(source);
-                    let mut annotations = Vec::with_capacity(num_annotations as usize);
-                    for _ in 0..num_annotations {
-                        annotations.push(Annotation::parse(source)?);
+                    let mut annotations =
+                        Vec::with_capacity(initial_capacity(num_annotations as usize));
+                    for i in 0..num_annotations {
+                        annotations.push(source.within(Frame::Entry(i), Annotation::parse)?);
                     }
                     Self::RuntimeInvisibleAnnotations {
                         attribute_name_index,
@@ -1151,10 +2058,12 @@ impl AttributeInfo {
                 }
                 b"RuntimeVisibleParameterAnnotations" => {
                     let num_parameters = read_u8!(source);
-                    let mut parameter_annotations = Vec::with_capacity(num_parameters as usize);
+                    let mut parameter_annotations =
+                        Vec::with_capacity(initial_capacity(num_parameters as usize));
                     for _ in 0..num_parameters {
                         let num_annotations = read_u16!(source);
-                        let mut annotations = Vec::with_capacity(num_annotations as usize);
+                        let mut annotations =
+                            Vec::with_capacity(initial_capacity(num_annotations as usize));
                         for _ in 0..num_annotations {
                             annotations.push(Annotation::parse(source)?);
                         }
@@ -1168,10 +2077,12 @@ impl AttributeInfo {
                 }
                 b"RuntimeInvisibleParameterAnnotations" => {
                     let num_parameters = read_u8!(source);
-                    let mut parameter_annotations = Vec::with_capacity(num_parameters as usize);
+                    let mut parameter_annotations =
+                        Vec::with_capacity(initial_capacity(num_parameters as usize));
                     for _ in 0..num_parameters {
                         let num_annotations = read_u16!(source);
-                        let mut annotations = Vec::with_capacity(num_annotations as usize);
+                        let mut annotations =
+                            Vec::with_capacity(initial_capacity(num_annotations as usize));
                         for _ in 0..num_annotations {
                             annotations.push(Annotation::parse(source)?);
                         }
@@ -1185,9 +2096,10 @@ impl AttributeInfo {
                 }
                 b"RuntimeVisibleTypeAnnotations" => {
                     let num_annotations = read_u16!(source);
-                    let mut annotations = Vec::with_capacity(num_annotations as usize);
-                    for _ in 0..num_annotations {
-                        annotations.push(TypeAnnotation::parse(source)?);
+                    let mut annotations =
+                        Vec::with_capacity(initial_capacity(num_annotations as usize));
+                    for i in 0..num_annotations {
+                        annotations.push(source.within(Frame::Entry(i), TypeAnnotation::parse)?);
                     }
 
                     Self::RuntimeVisibleTypeAnnotations {
@@ -1198,9 +2110,10 @@ impl AttributeInfo {
                 }
                 b"RuntimeInvisibleTypeAnnotations" => {
                     let num_annotations = read_u16!(source);
-                    let mut annotations = Vec::with_capacity(num_annotations as usize);
-                    for _ in 0..num_annotations {
-                        annotations.push(TypeAnnotation::parse(source)?);
+                    let mut annotations =
+                        Vec::with_capacity(initial_capacity(num_annotations as usize));
+                    for i in 0..num_annotations {
+                        annotations.push(source.within(Frame::Entry(i), TypeAnnotation::parse)?);
                     }
 
                     Self::RuntimeInvisibleTypeAnnotations {
@@ -1216,9 +2129,11 @@ impl AttributeInfo {
                 },
                 b"BootstrapMethods" => {
                     let num_bootstrap_methods = read_u16!(source);
-                    let mut bootstrap_methods = Vec::with_capacity(num_bootstrap_methods as usize);
-                    for _ in 0..num_bootstrap_methods {
-                        bootstrap_methods.push(BootstrapMethod::parse(source)?);
+                    let mut bootstrap_methods =
+                        Vec::with_capacity(initial_capacity(num_bootstrap_methods as usize));
+                    for i in 0..num_bootstrap_methods {
+                        bootstrap_methods
+                            .push(source.within(Frame::Entry(i), BootstrapMethod::parse)?);
                     }
 
                     Self::BootstrapMethods {
@@ -1228,10 +2143,9 @@ impl AttributeInfo {
                     }
                 }
                 b"MethodParameters" => {
-                    let attribute_name_index = read_u16!(source);
-                    let attribute_length = read_u32!(source);
                     let parameters_count = read_u8!(source);
-                    let mut parameters = Vec::with_capacity(parameters_count as usize);
+                    let mut parameters =
+                        Vec::with_capacity(initial_capacity(parameters_count as usize));
                     for _ in 0..parameters_count {
                         parameters.push(MethodParameter::parse(source)?);
                     }
@@ -1242,39 +2156,38 @@ impl AttributeInfo {
                     }
                 }
                 b"Module" => {
-                    let attribute_name_index = read_u16!(source);
-                    let attribute_length = read_u32!(source);
-
                     let module_name_index = read_u16!(source);
                     let module_flags = flags::ModuleFlags::from_bits_truncate(read_u16!(source));
                     let module_version_index = read_u16!(source);
 
                     let requires_count = read_u16!(source);
-                    let mut requires = Vec::with_capacity(requires_count as usize);
+                    let mut requires =
+                        Vec::with_capacity(initial_capacity(requires_count as usize));
                     for _ in 0..requires_count {
                         requires.push(ModuleRequires::parse(source)?);
                     }
 
                     let exports_count = read_u16!(source);
-                    let mut exports = Vec::with_capacity(requires_count as usize);
+                    let mut exports = Vec::with_capacity(initial_capacity(exports_count as usize));
                     for _ in 0..exports_count {
                         exports.push(ModuleExports::parse(source)?);
                     }
 
                     let opens_count = read_u16!(source);
-                    let mut opens = Vec::with_capacity(requires_count as usize);
+                    let mut opens = Vec::with_capacity(initial_capacity(opens_count as usize));
                     for _ in 0..opens_count {
                         opens.push(ModuleOpens::parse(source)?);
                     }
 
                     let uses_count = read_u16!(source);
-                    let mut uses_index = Vec::with_capacity(requires_count as usize);
+                    let mut uses_index = Vec::with_capacity(initial_capacity(uses_count as usize));
                     for _ in 0..uses_count {
                         uses_index.push(read_u16!(source));
                     }
 
                     let provides_count = read_u16!(source);
-                    let mut provides = Vec::with_capacity(requires_count as usize);
+                    let mut provides =
+                        Vec::with_capacity(initial_capacity(provides_count as usize));
                     for _ in 0..provides_count {
                         provides.push(ModuleProvides::parse(source)?);
                     }
@@ -1294,7 +2207,8 @@ impl AttributeInfo {
                 }
                 b"ModulePackages" => {
                     let package_count = read_u16!(source);
-                    let mut package_index = Vec::with_capacity(package_count as usize);
+                    let mut package_index =
+                        Vec::with_capacity(initial_capacity(package_count as usize));
                     for _ in 0..package_count {
                         package_index.push(read_u16!(source));
                     }
@@ -1317,7 +2231,8 @@ impl AttributeInfo {
                 },
                 b"NestMembers" => {
                     let number_of_classes = read_u16!(source);
-                    let mut classes = Vec::with_capacity(number_of_classes as usize);
+                    let mut classes =
+                        Vec::with_capacity(initial_capacity(number_of_classes as usize));
                     for _ in 0..number_of_classes {
                         classes.push(read_u16!(source));
                     }
@@ -1329,9 +2244,12 @@ impl AttributeInfo {
                 }
                 b"Record" => {
                     let components_count = read_u16!(source);
-                    let mut components = Vec::with_capacity(components_count as usize);
-                    for _ in 0..components_count {
-                        components.push(RecordComponentInfo::parse(cp, source)?);
+                    let mut components =
+                        Vec::with_capacity(initial_capacity(components_count as usize));
+                    for i in 0..components_count {
+                        components.push(source.within(Frame::Entry(i), |source| {
+                            RecordComponentInfo::parse_nested(cp, source, depth + 1)
+                        })?);
                     }
 
                     Self::Record {
@@ -1342,7 +2260,8 @@ impl AttributeInfo {
                 }
                 b"PermittedSubclasses" => {
                     let number_of_classes = read_u16!(source);
-                    let mut classes = Vec::with_capacity(number_of_classes as usize);
+                    let mut classes =
+                        Vec::with_capacity(initial_capacity(number_of_classes as usize));
                     for _ in 0..number_of_classes {
                         classes.push(read_u16!(source));
                     }
@@ -1352,14 +2271,11 @@ impl AttributeInfo {
                         classes,
                     }
                 }
-                _ => {
-                    let mut info = vec![0_u8; attribute_length as usize];
-                    source
-                        .read_exact(&mut info)
-                        .or(Err(ClassFileParseError::UnexpectedEOF))?;
-
-                    todo!("must be ignored silently")
-                }
+                _ => Self::Unknown {
+                    name_index: attribute_name_index,
+                    length: attribute_length,
+                    raw_bytes: read_byte_vec(source, attribute_length)?,
+                },
             }),
             _ => Err(ClassFileParseError::InvalidAttributeNameIndex),
         }
@@ -1367,13 +2283,39 @@ impl AttributeInfo {
 }
 
 impl RecordComponentInfo {
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
     pub fn parse(cp: &ConstantPool, source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_nested(cp, &mut Source::new(source), 0)
+    }
+
+    fn parse_nested(
+        cp: &ConstantPool,
+        source: &mut Source<impl Read>,
+        depth: usize,
+    ) -> Result<Self, ClassFileParseError> {
         let name_index = read_u16!(source);
         let descriptor_index = read_u16!(source);
         let attributes_count = read_u16!(source);
-        let mut attributes = Vec::with_capacity(attributes_count as usize);
+        source.check(ParseLimit::Attributes, attributes_count as usize)?;
+        let mut attributes = Vec::with_capacity(initial_capacity(attributes_count as usize));
         for _ in 0..attributes_count {
-            attributes.push(AttributeInfo::parse(cp, source)?);
+            attributes.push(AttributeInfo::parse_nested(
+                cp,
+                source,
+                depth,
+                ParseMode::Eager,
+            )?);
         }
         Ok(Self {
             name_index,
@@ -1384,6 +2326,21 @@ impl RecordComponentInfo {
 }
 
 impl ModuleRequires {
+    /// The index of the `CONSTANT_Module` entry of the required module.
+    pub fn requires_index(&self) -> u16 {
+        self.requires_index
+    }
+
+    pub fn requires_flags(&self) -> flags::RequiresFlags {
+        self.requires_flags
+    }
+
+    /// The index of the `CONSTANT_Utf8` entry of the version of the required
+    /// module at compile time, or 0 if there is none.
+    pub fn requires_version_index(&self) -> u16 {
+        self.requires_version_index
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let requires_index = read_u16!(source);
         let requires_flags = flags::RequiresFlags::from_bits_truncate(read_u16!(source));
@@ -1398,11 +2355,27 @@ impl ModuleRequires {
 }
 
 impl ModuleExports {
+    /// The index of the `CONSTANT_Package` entry of the exported package.
+    pub fn exports_index(&self) -> u16 {
+        self.exports_index
+    }
+
+    pub fn exports_flags(&self) -> flags::ExportsFlags {
+        self.exports_flags
+    }
+
+    /// The indices of the `CONSTANT_Module` entries of the modules that the
+    /// package is exported to, which is empty if it is exported to all
+    /// modules.
+    pub fn exports_to_index(&self) -> &[u16] {
+        &self.exports_to_index
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let exports_index = read_u16!(source);
         let exports_flags = flags::ExportsFlags::from_bits_truncate(read_u16!(source));
         let exports_to_count = read_u16!(source);
-        let mut exports_to_index = Vec::with_capacity(exports_to_count as usize);
+        let mut exports_to_index = Vec::with_capacity(initial_capacity(exports_to_count as usize));
         for _ in 0..exports_to_count {
             exports_to_index.push(read_u16!(source));
         }
@@ -1416,11 +2389,26 @@ impl ModuleExports {
 }
 
 impl ModuleOpens {
+    /// The index of the `CONSTANT_Package` entry of the opened package.
+    pub fn opens_index(&self) -> u16 {
+        self.opens_index
+    }
+
+    pub fn opens_flags(&self) -> flags::OpensFlags {
+        self.opens_flags
+    }
+
+    /// The indices of the `CONSTANT_Module` entries of the modules that the
+    /// package is opened to, which is empty if it is opened to all modules.
+    pub fn opens_to_index(&self) -> &[u16] {
+        &self.opens_to_index
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let opens_index = read_u16!(source);
         let opens_flags = flags::OpensFlags::from_bits_truncate(read_u16!(source));
         let opens_to_count = read_u16!(source);
-        let mut opens_to_index = Vec::with_capacity(opens_to_count as usize);
+        let mut opens_to_index = Vec::with_capacity(initial_capacity(opens_to_count as usize));
         for _ in 0..opens_to_count {
             opens_to_index.push(read_u16!(source));
         }
@@ -1434,10 +2422,22 @@ impl ModuleOpens {
 }
 
 impl ModuleProvides {
+    /// The index of the `CONSTANT_Class` entry of the service interface.
+    pub fn provides_index(&self) -> u16 {
+        self.provides_index
+    }
+
+    /// The indices of the `CONSTANT_Class` entries of the implementations of
+    /// the service.
+    pub fn provides_with_index(&self) -> &[u16] {
+        &self.provides_with_index
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let provides_index = read_u16!(source);
         let provides_with_count = read_u16!(source);
-        let mut provides_with_index = Vec::with_capacity(provides_with_count as usize);
+        let mut provides_with_index =
+            Vec::with_capacity(initial_capacity(provides_with_count as usize));
         for _ in 0..provides_with_count {
             provides_with_index.push(read_u16!(source));
         }
@@ -1464,7 +2464,8 @@ impl BootstrapMethod {
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let bootstrap_method_ref = read_u16!(source);
         let num_bootstrap_arguments = read_u16!(source);
-        let mut bootstrap_arguments = Vec::with_capacity(num_bootstrap_arguments as usize);
+        let mut bootstrap_arguments =
+            Vec::with_capacity(initial_capacity(num_bootstrap_arguments as usize));
         for _ in 0..num_bootstrap_arguments {
             bootstrap_arguments.push(read_u16!(source));
         }
@@ -1503,7 +2504,7 @@ impl TypeAnnotation {
             // ===
             0x40 | 0x41 => {
                 let table_length = read_u16!(source);
-                let mut table = Vec::with_capacity(table_length as usize);
+                let mut table = Vec::with_capacity(initial_capacity(table_length as usize));
                 for _ in 0..table_length {
                     table.push(LocalVarTargetTableEntry::parse(source)?);
                 }
@@ -1528,11 +2529,13 @@ impl TypeAnnotation {
         let target_path = TypePath::parse(source)?;
         let type_index = read_u16!(source);
         let num_element_value_pairs = read_u16!(source);
-        let mut element_value_pairs = Vec::with_capacity(num_element_value_pairs as usize);
+        let mut element_value_pairs =
+            Vec::with_capacity(initial_capacity(num_element_value_pairs as usize));
         for _ in 0..num_element_value_pairs {
             element_value_pairs.push(ElementValuePair::parse(source)?);
         }
         Ok(Self {
+            target_type,
             target_path,
             target_info,
             type_index,
@@ -1544,7 +2547,7 @@ impl TypeAnnotation {
 impl TypePath {
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let path_length = read_u8!(source);
-        let mut path = Vec::with_capacity(path_length as usize);
+        let mut path = Vec::with_capacity(initial_capacity(path_length as usize));
         for _ in 0..path_length {
             path.push(Path::parse(source)?);
         }
@@ -1581,12 +2584,23 @@ impl LocalVarTargetTableEntry {
 }
 
 impl Annotation {
+    /// The constant pool index of the field descriptor of the annotation
+    /// interface, e.g. `Ljava/lang/Deprecated;`.
+    pub fn type_index(&self) -> u16 {
+        self.type_index
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_nested(source, 0)
+    }
+
+    fn parse_nested(source: &mut impl Read, depth: usize) -> Result<Self, ClassFileParseError> {
         let type_index = read_u16!(source);
         let num_element_value_pairs = read_u16!(source);
-        let mut element_value_pairs = Vec::with_capacity(num_element_value_pairs as usize);
+        let mut element_value_pairs =
+            Vec::with_capacity(initial_capacity(num_element_value_pairs as usize));
         for _ in 0..num_element_value_pairs {
-            element_value_pairs.push(ElementValuePair::parse(source)?);
+            element_value_pairs.push(ElementValuePair::parse_nested(source, depth)?);
         }
         Ok(Self {
             type_index,
@@ -1597,8 +2611,12 @@ impl Annotation {
 
 impl ElementValuePair {
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_nested(source, 0)
+    }
+
+    fn parse_nested(source: &mut impl Read, depth: usize) -> Result<Self, ClassFileParseError> {
         let element_name_index = read_u16!(source);
-        let value = ElementValue::parse(source)?;
+        let value = ElementValue::parse_nested(source, depth)?;
         Ok(Self {
             element_name_index,
             value,
@@ -1608,11 +2626,22 @@ impl ElementValuePair {
 
 impl ElementValue {
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
+        Self::parse_nested(source, 0)
+    }
+
+    /// Parses an element value that is nested in `depth` other annotations or
+    /// arrays.
+    fn parse_nested(source: &mut impl Read, depth: usize) -> Result<Self, ClassFileParseError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ClassFileParseError::NestingTooDeep);
+        }
+
         let tag = read_u8!(source) as char;
         match tag {
-            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
-                Ok(Self::ConstValueIndex(read_u16!(source)))
-            }
+            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => Ok(Self::ConstValueIndex {
+                tag: tag as u8,
+                const_value_index: read_u16!(source),
+            }),
             'e' => {
                 let type_name_index = read_u16!(source);
                 let const_name_index = read_u16!(source);
@@ -1622,12 +2651,15 @@ impl ElementValue {
                 })
             }
             'c' => Ok(Self::ClassInfoIndex(read_u16!(source))),
-            '@' => Ok(Self::AnnotationValue(Annotation::parse(source)?)),
+            '@' => Ok(Self::AnnotationValue(Annotation::parse_nested(
+                source,
+                depth + 1,
+            )?)),
             '[' => {
                 let num_values = read_u16!(source);
-                let mut values = Vec::with_capacity(num_values as usize);
+                let mut values = Vec::with_capacity(initial_capacity(num_values as usize));
                 for _ in 0..num_values {
-                    values.push(ElementValue::parse(source)?);
+                    values.push(ElementValue::parse_nested(source, depth + 1)?);
                 }
                 Ok(Self::ArrayValue { values })
             }
@@ -1671,6 +2703,14 @@ impl LocalVariableTypeTableEntry {
 }
 
 impl LineNumberTableEntry {
+    pub fn start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn line_number(&self) -> u16 {
+        self.line_number
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let start_pc = read_u16!(source);
         let line_number = read_u16!(source);
@@ -1708,7 +2748,7 @@ impl StackMapFrame {
             251 => StackMapFrame::parse_same_frame_extended(source, frame_type),
             252..=254 => StackMapFrame::parse_append_frame(source, frame_type),
             255 => StackMapFrame::parse_full_frame(source, frame_type),
-            _ => panic!(),
+            _ => Err(ClassFileParseError::InvalidStackMapFrameType),
         }
     }
 
@@ -1777,12 +2817,12 @@ impl StackMapFrame {
     ) -> Result<Self, ClassFileParseError> {
         let offset_delta = read_u16!(source);
         let number_of_locals = read_u16!(source);
-        let mut locals = Vec::with_capacity(number_of_locals as usize);
+        let mut locals = Vec::with_capacity(initial_capacity(number_of_locals as usize));
         for _ in 0..number_of_locals {
             locals.push(VerificationTypeInfo::parse(source)?);
         }
         let number_of_stack_items = read_u16!(source);
-        let mut stack = Vec::with_capacity(number_of_stack_items as usize);
+        let mut stack = Vec::with_capacity(initial_capacity(number_of_stack_items as usize));
         for _ in 0..number_of_stack_items {
             stack.push(VerificationTypeInfo::parse(source)?);
         }
@@ -1821,6 +2861,25 @@ impl VerificationTypeInfo {
 }
 
 impl ExceptionTableEntry {
+    pub fn start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    /// The end of the range that the handler covers, exclusive.
+    pub fn end_pc(&self) -> u16 {
+        self.end_pc
+    }
+
+    pub fn handler_pc(&self) -> u16 {
+        self.handler_pc
+    }
+
+    /// The constant pool index of the class of the caught exceptions, or 0 if
+    /// the handler catches all exceptions.
+    pub fn catch_type(&self) -> u16 {
+        self.catch_type
+    }
+
     pub fn parse(source: &mut impl Read) -> Result<Self, ClassFileParseError> {
         let start_pc = read_u16!(source);
         let end_pc = read_u16!(source);
@@ -1838,9 +2897,16 @@ impl ExceptionTableEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::classfile::flags::{ClassAccessFlags, MethodAccessFlags};
-    use crate::classfile::AttributeInfo::{Code, LineNumberTable, SourceFile};
+    use crate::classfile::AttributeInfo::Code;
+    #[cfg(feature = "std")]
+    use crate::classfile::{
+        flags::{ClassAccessFlags, MethodAccessFlags},
+        AttributeInfo::{LineNumberTable, SourceFile},
+    };
+    use crate::io::ErrorKind;
+    #[cfg(feature = "std")]
     use std::fs::File;
+    #[cfg(feature = "std")]
     use std::io::BufReader;
 
     #[test]
@@ -1856,6 +2922,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_simple_class_file() {
         let f = File::open("tests/resources/Foo.class").unwrap();
         let mut rd = BufReader::new(f);
@@ -1931,6 +2998,7 @@ mod tests {
                             max_locals: 1,
                             code_length: 5,
                             code: vec![0x2A, 0xB7, 0x00, 0x01, 0xB1],
+                            ops: None,
                             exception_table: vec![],
                             attributes: vec![LineNumberTable {
                                 attribute_name_index: 10,
@@ -1953,6 +3021,7 @@ mod tests {
                             max_locals: 1,
                             code_length: 1,
                             code: vec![0xB1],
+                            ops: None,
                             exception_table: vec![],
                             attributes: vec![LineNumberTable {
                                 attribute_name_index: 10,
@@ -1974,4 +3043,555 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn test_super_class() {
+        let bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            Some("java/lang/Object".to_string()),
+            class_file.super_class()
+        );
+    }
+
+    #[test]
+    fn test_class_names() {
+        let square_bytes = std::fs::read("tests/resources/Shapes$Square.class").unwrap();
+        let square = ClassFile::from_bytes(&square_bytes).unwrap();
+        assert_eq!(Ok("Shapes$Square".into()), square.this_class_name());
+        assert_eq!(
+            Ok(Some("java/lang/Object".into())),
+            square.super_class_name()
+        );
+        assert_eq!(Ok(vec!["Shapes".into()]), square.interface_names());
+
+        let bytes = std::fs::read("tests/resources/modules/app/module-info.class").unwrap();
+        let module_info = ClassFile::from_bytes(&bytes).unwrap();
+        assert_eq!(Ok(None), module_info.super_class_name());
+        assert_eq!(Ok(vec![]), module_info.interface_names());
+
+        // this_class pointing at the Utf8 name instead of the Class entry
+        let mut broken = ClassFile::from_bytes(&square_bytes).unwrap();
+        let Some(ConstantPoolInfo::ClassInfo { name_index }) =
+            square.constant_pool().get(square.this_class)
+        else {
+            panic!("this_class is not a class info");
+        };
+        broken.this_class = *name_index;
+        broken.interfaces = vec![*name_index];
+        assert!(broken.this_class_name().is_err());
+        assert!(broken.interface_names().is_err());
+        assert!(broken.interfaces().is_empty());
+    }
+
+    #[test]
+    fn test_permitted_subclasses() {
+        let bytes = std::fs::read("tests/resources/Shapes.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        assert!(class_file.is_sealed());
+        assert_eq!(
+            vec!["Shapes$Circle".to_string(), "Shapes$Square".to_string()],
+            class_file.permitted_subclasses()
+        );
+
+        let bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        assert!(!class_file.is_sealed());
+        assert!(class_file.permitted_subclasses().is_empty());
+    }
+
+    #[test]
+    fn test_parse_decoded() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        for mode in [ParseMode::Eager, ParseMode::Lazy] {
+            let class_file = ClassFile::parse_decoded(&mut bytes.as_slice(), mode).unwrap();
+            for method in class_file.methods() {
+                let Some(Code { code, ops, .. }) = method.attributes().first() else {
+                    continue;
+                };
+                let ops = ops.as_ref().unwrap();
+                assert_eq!(
+                    crate::bytecode::decode(code).unwrap(),
+                    ops.iter().map(|(_, op)| op.clone()).collect::<Vec<_>>()
+                );
+                assert_eq!(0, ops[0].0);
+            }
+        }
+        let plain = ClassFile::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            plain.methods()[0].attributes()[0],
+            Code { ops: None, .. }
+        ));
+
+        // the code of `bar` is a single `return`, make it a `bipush` without
+        // its operand
+        let mut bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let code = bytes
+            .windows(5)
+            .position(|w| w == [0x00, 0x00, 0x00, 0x01, 0xB1])
+            .unwrap();
+        bytes[code + 4] = 0x10;
+        assert!(ClassFile::from_bytes(&bytes).is_ok());
+        let error = ClassFile::parse_decoded(&mut bytes.as_slice(), ParseMode::Eager).unwrap_err();
+        assert_eq!(ClassFileParseError::InvalidCode, error.kind());
+        assert_eq!(
+            vec!["method #1", "Code attribute", "instruction at pc 0"],
+            error.context()
+        );
+    }
+
+    #[test]
+    fn test_find_member() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        let cp = class_file.constant_pool();
+
+        let field = class_file.find_field("ANSWER", "I").unwrap();
+        assert_eq!(Ok("ANSWER".into()), cp.get_utf8(field.name_index()));
+        assert!(class_file.find_field("ANSWER", "J").is_none());
+
+        let method = class_file.find_method("fromNative", "(J)I").unwrap();
+        assert_eq!(Ok("fromNative".into()), cp.get_utf8(method.name_index()));
+        assert!(class_file.find_method("fromNative", "(I)I").is_none());
+        assert!(class_file.find_method("ANSWER", "I").is_none());
+        assert!(class_file.find_field("fromNative", "(J)I").is_none());
+    }
+
+    #[test]
+    fn test_line_for_pc() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        for mode in [ParseMode::Eager, ParseMode::Lazy] {
+            let class_file = ClassFile::parse_with(&mut bytes.as_slice(), mode).unwrap();
+            let cp = class_file.constant_pool();
+            let method = class_file
+                .find_method("guarded", "(LFormats$Inner;)I")
+                .unwrap();
+            let lines = (0..12)
+                .map(|pc| method.line_for_pc(cp, pc))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    Some(26),
+                    Some(26),
+                    Some(26),
+                    Some(26),
+                    Some(26),
+                    Some(26),
+                    Some(27),
+                    Some(27),
+                    Some(28),
+                    Some(29),
+                    Some(29),
+                    None,
+                ],
+                lines
+            );
+
+            let method = class_file.find_method("missing", "()V").unwrap();
+            assert_eq!(None, method.line_for_pc(cp, 0));
+        }
+
+        // entries in several tables and out of order, and none for the start
+        let table = |entries: &[(u16, u16)]| AttributeInfo::LineNumberTable {
+            attribute_name_index: 1,
+            attribute_length: 2 + 4 * entries.len() as u32,
+            line_number_table: entries
+                .iter()
+                .map(|(start_pc, line_number)| LineNumberTableEntry {
+                    start_pc: *start_pc,
+                    line_number: *line_number,
+                })
+                .collect(),
+        };
+        let method = MethodInfo {
+            access_flags: flags::MethodAccessFlags::empty(),
+            name_index: 1,
+            descriptor_index: 1,
+            attributes: vec![Code {
+                attribute_name_index: 1,
+                attribute_length: 0,
+                max_stack: 0,
+                max_locals: 0,
+                code_length: 20,
+                code: vec![0; 20],
+                ops: None,
+                exception_table: vec![],
+                attributes: vec![table(&[(12, 7), (4, 5)]), table(&[(8, 6)])],
+            }],
+        };
+        let cp = ConstantPool::default();
+        assert_eq!(None, method.line_for_pc(&cp, 3));
+        assert_eq!(Some(5), method.line_for_pc(&cp, 4));
+        assert_eq!(Some(6), method.line_for_pc(&cp, 11));
+        assert_eq!(Some(7), method.line_for_pc(&cp, 19));
+        assert_eq!(None, method.line_for_pc(&cp, 20));
+    }
+
+    #[test]
+    fn test_preview_version() {
+        let mut bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        assert!(!class_file.is_preview());
+        assert_eq!("61.0", class_file.version().to_string());
+
+        // the minor version follows the magic
+        bytes[4..6].copy_from_slice(&Version::PREVIEW_MINOR.to_be_bytes());
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        assert!(class_file.is_preview());
+        assert_eq!("61.65535", class_file.version().to_string());
+
+        assert!(!Version::new(55, Version::PREVIEW_MINOR).is_preview());
+        assert!(Version::new(56, Version::PREVIEW_MINOR).is_preview());
+    }
+
+    #[test]
+    fn test_local_variable() {
+        let bytes = std::fs::read("tests/resources/Locals.class").unwrap();
+        for mode in [ParseMode::Eager, ParseMode::Lazy] {
+            let class_file = ClassFile::parse_with(&mut bytes.as_slice(), mode).unwrap();
+            let cp = class_file.constant_pool();
+            let method = class_file
+                .find_method("sum", "(Ljava/util/List;)I")
+                .unwrap();
+            let variable = |pc, slot| {
+                method.local_variable(cp, pc, slot).map(|v| {
+                    (
+                        v.name.into_owned(),
+                        v.descriptor.into_owned(),
+                        v.signature.map(|s| s.into_owned()),
+                    )
+                })
+            };
+            let values = Some((
+                "values".to_string(),
+                "Ljava/util/List;".to_string(),
+                Some("Ljava/util/List<Ljava/lang/Integer;>;".to_string()),
+            ));
+            assert_eq!(values, variable(0, 0));
+            assert_eq!(values, variable(43, 0));
+            assert_eq!(None, variable(44, 0));
+
+            // total is stored at 1
+            assert_eq!(None, variable(1, 1));
+            assert_eq!(
+                Some(("total".to_string(), "I".to_string(), None)),
+                variable(2, 1)
+            );
+
+            // the iterator has no name, and wide reuses its slot
+            assert_eq!(None, variable(9, 2));
+            assert_eq!(
+                Some(("wide".to_string(), "J".to_string(), None)),
+                variable(41, 2)
+            );
+            assert_eq!(None, variable(41, 3));
+            assert_eq!(
+                Some(("value".to_string(), "I".to_string(), None)),
+                variable(31, 3)
+            );
+        }
+
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        let method = class_file.find_method("missing", "()V").unwrap();
+        assert_eq!(
+            None,
+            method.local_variable(class_file.constant_pool(), 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        assert_eq!(2, class_file.methods().len());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Err(ClassFileParseError::TrailingBytes),
+            ClassFile::from_bytes(&trailing).map_err(|e| e.kind())
+        );
+        assert_eq!(
+            Err(ClassFileParseError::UnexpectedEOF),
+            ClassFile::from_bytes(&bytes[..bytes.len() - 1]).map_err(|e| e.kind())
+        );
+    }
+
+    #[test]
+    fn test_typed_constant_pool_accessors() {
+        let bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        let cp = class_file.constant_pool();
+
+        assert_eq!(
+            Ok(MemberRef {
+                class_name: "java/lang/Object".into(),
+                name: "<init>".into(),
+                descriptor: "()V".into(),
+            }),
+            cp.get_method_ref(1)
+        );
+        assert_eq!(Ok("Foo".into()), cp.get_class_name(7));
+        assert_eq!(Ok("Foo.java".into()), cp.get_utf8(13));
+        assert_eq!(
+            Ok(NameAndType {
+                name: "<init>".into(),
+                descriptor: "()V".into(),
+            }),
+            cp.get_name_and_type(3)
+        );
+
+        assert_eq!(Err(ConstantPoolError::IndexOutOfBounds(0)), cp.get_entry(0));
+        assert_eq!(
+            Err(ConstantPoolError::IndexOutOfBounds(14)),
+            cp.get_entry(14)
+        );
+        assert_eq!(
+            Err(ConstantPoolError::UnexpectedEntry {
+                index: 1,
+                expected: "Fieldref"
+            }),
+            cp.get_field_ref(1)
+        );
+        assert_eq!(
+            Err(ConstantPoolError::UnexpectedEntry {
+                index: 4,
+                expected: "Class"
+            }),
+            cp.get_class_name(4)
+        );
+        let invalid = ConstantPool::from(vec![Utf8Info {
+            length: 1,
+            bytes: vec![0xFF],
+        }]);
+        assert_eq!(Err(ConstantPoolError::InvalidUtf8(1)), invalid.get_utf8(1));
+    }
+
+    #[test]
+    fn test_numeric_constants() {
+        assert_eq!(Some(-2), IntegerInfo { bytes: 0xFFFF_FFFE }.as_i32());
+        assert_eq!(Some(1.5), FloatInfo { bytes: 0x3FC0_0000 }.as_f32());
+        let long = LongInfo {
+            high_bytes: 0xFFFF_FFFF,
+            low_bytes: 0xFFFF_FFFE,
+        };
+        assert_eq!(Some(-2), long.as_i64());
+        assert_eq!(None, long.as_f64());
+        let double = DoubleInfo {
+            high_bytes: 0x7FF8_0000,
+            low_bytes: 1,
+        };
+        assert_eq!(
+            Some(0x7FF8_0000_0000_0001),
+            double.as_f64().map(f64::to_bits)
+        );
+        assert_eq!(None, IntegerInfo { bytes: 0 }.as_f32());
+    }
+
+    #[test]
+    fn test_modified_utf8_constants() {
+        let cp = ConstantPool::from(vec![
+            ConstantPoolInfo::utf8("smile \u{1F600}"),
+            StringInfo { string_index: 1 },
+            ConstantPoolInfo::utf8("nul\0"),
+        ]);
+        assert_eq!(
+            &Utf8Info {
+                length: 12,
+                bytes: b"smile \xED\xA0\xBD\xED\xB8\x80".to_vec()
+            },
+            cp.get(1).unwrap()
+        );
+        assert_eq!(Ok("smile \u{1F600}".into()), cp.get_string(2));
+        assert_eq!(Ok("nul\0".into()), cp.get_utf8(3));
+        assert_eq!("nul\0", cp.get(3).unwrap().unwrap_utf8());
+        assert_eq!(None, cp.get(0));
+        assert_eq!(None, cp.get(4));
+    }
+
+    #[test]
+    fn test_wide_constants() {
+        let bytes = std::fs::read("tests/resources/Constants.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        let cp = class_file.constant_pool();
+
+        // indices match javac's, the long and double each take up two
+        assert_eq!(24, cp.len());
+        assert_eq!(Ok(123456789012), cp.get_long(7));
+        assert_eq!(Some(&ConstantPoolInfo::Unusable), cp.get(8));
+        assert_eq!(Ok(0.25), cp.get_double(9));
+        assert_eq!(Some(&ConstantPoolInfo::Unusable), cp.get(10));
+        assert_eq!(Ok("after the wide constants".into()), cp.get_string(11));
+        assert_eq!("Constants", class_file.this_class());
+        assert_round_trip(&bytes);
+
+        // a long constant as the last entry would take up an index past the end
+        let header = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D];
+        assert_eq!(
+            Err(ClassFileParseError::InvalidConstantPoolCount),
+            ClassFile::from_bytes(
+                &[
+                    &header[..],
+                    &[0x00, 0x02, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01],
+                ]
+                .concat()
+            )
+            .map_err(|e| e.kind())
+        );
+    }
+
+    #[test]
+    fn test_malformed_class_files() {
+        let header = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D];
+        let class_file = |rest: &[u8]| {
+            ClassFile::from_bytes(&[&header[..], rest].concat()).map_err(|e| e.kind())
+        };
+
+        assert_eq!(
+            Err(ClassFileParseError::InvalidConstantPoolCount),
+            class_file(&[0x00, 0x00])
+        );
+        // an attribute whose name index is 0
+        assert_eq!(
+            Err(ClassFileParseError::InvalidAttributeNameIndex),
+            class_file(&[
+                0x00, 0x01, // empty constant pool
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // flags, this and super class
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // interfaces, fields, methods
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ])
+        );
+        // this_class must refer to a class constant
+        assert_eq!(
+            Err(ClassFileParseError::InvalidClassIndex),
+            class_file(&[
+                0x00, 0x02, 0x01, 0x00, 0x01, b'A', // constant pool
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // flags, this and super class
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // counts
+            ])
+        );
+        // so must interfaces, and no index may be out of bounds
+        assert_eq!(
+            Err(ClassFileParseError::InvalidClassIndex),
+            class_file(&[
+                0x00, 0x03, 0x01, 0x00, 0x01, b'A', 0x07, 0x00, 0x01, // constant pool
+                0x00, 0x00, 0x00, 0x02, 0x00, 0x00, // flags, this and super class
+                0x00, 0x01, 0x00, 0x09, // interfaces
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // fields, methods, attributes
+            ])
+        );
+        assert!(class_file(&[
+            0x00, 0x03, 0x01, 0x00, 0x01, b'A', 0x07, 0x00, 0x01, // constant pool
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x00, // flags, this and super class
+            0x00, 0x01, 0x00, 0x02, // interfaces
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // fields, methods, attributes
+        ])
+        .is_ok());
+        // a Code attribute claiming to contain 4GiB of code
+        assert_eq!(
+            Err(ClassFileParseError::UnexpectedEOF),
+            class_file(&[
+                0x00, 0x02, 0x01, 0x00, 0x04, b'C', b'o', b'd', b'e', // constant pool
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // flags, this and super class
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // interfaces, fields, methods
+                0x00, 0x01, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, // attribute header
+                0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // stack, locals, length
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_attribute() {
+        let header = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D];
+        let bytes = [
+            &header[..],
+            &[
+                0x00, 0x04, 0x01, 0x00, 0x01, b'A', 0x07, 0x00, 0x01, // constant pool
+                0x01, 0x00, 0x06, b'K', b'o', b't', b'l', b'i', b'n', // constant pool
+                0x00, 0x00, 0x00, 0x02, 0x00, 0x00, // flags, this and super class
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // interfaces, fields, methods
+                0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x03, 0x01, 0x02, 0x03,
+            ],
+        ]
+        .concat();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            &[AttributeInfo::Unknown {
+                name_index: 3,
+                length: 3,
+                raw_bytes: vec![0x01, 0x02, 0x03],
+            }],
+            class_file.attributes()
+        );
+        assert_round_trip(&bytes);
+    }
+
+    #[test]
+    fn test_nesting_too_deep() {
+        // an array, nested in an array, nested in an array, ...
+        let nested = [b'[', 0x00, 0x01].repeat(MAX_NESTING_DEPTH + 1);
+        assert_eq!(
+            Err(ClassFileParseError::NestingTooDeep),
+            ElementValue::parse(&mut nested.as_slice())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        for mode in [ParseMode::Eager, ParseMode::Lazy] {
+            let class_file = ClassFile::parse_with(&mut bytes.as_slice(), mode).unwrap();
+            let json = serde_json::to_string(&class_file).unwrap();
+            assert!(json.starts_with(r#"{"magic":3405691582,"version":{"major":"#));
+            assert_eq!(class_file, serde_json::from_str(&json).unwrap());
+        }
+    }
+
+    fn assert_round_trip(original: &[u8]) {
+        let class_file = ClassFile::parse(&mut &original[..]).unwrap();
+        let mut written = vec![];
+        class_file.write(&mut written).unwrap();
+        assert_eq!(original, written.as_slice());
+        assert_eq!(
+            class_file,
+            ClassFile::parse(&mut written.as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        assert_round_trip(&std::fs::read("tests/resources/Foo.class").unwrap());
+
+        let mut dirs = vec![std::path::PathBuf::from("../libjvm/tests/resources/vm")];
+        let mut count = 0;
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "class") {
+                    let bytes = std::fs::read(&path).unwrap();
+                    // some fixtures are malformed on purpose
+                    if ClassFile::parse(&mut bytes.as_slice()).is_ok() {
+                        assert_round_trip(&bytes);
+                        count += 1;
+                    }
+                }
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_write_table_too_large() {
+        let bytes = std::fs::read("tests/resources/Foo.class").unwrap();
+        let mut too_many = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        too_many.interfaces = vec![1; u16::MAX as usize + 1];
+        assert_eq!(
+            ErrorKind::InvalidData,
+            too_many.write(&mut vec![]).unwrap_err().kind()
+        );
+    }
 }
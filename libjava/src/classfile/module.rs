@@ -0,0 +1,229 @@
+use crate::classfile::flags::{ExportsFlags, ModuleFlags, OpensFlags, RequiresFlags};
+use crate::classfile::{AttributeInfo, ClassFile, ConstantPool, ConstantPoolError};
+use crate::prelude::*;
+
+/// The `Module` attribute ([`$4.7.25`]) of a `module-info` class file with
+/// its names resolved from the constant pool, see
+/// [`ClassFile::module_descriptor`]. Module names are in dotted form like
+/// `java.base`, package and class names in internal form like `java/lang`.
+///
+/// [`$4.7.25`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub flags: ModuleFlags,
+    pub version: Option<String>,
+    pub requires: Vec<Requires>,
+    pub exports: Vec<Exports>,
+    pub opens: Vec<Opens>,
+    /// The binary names of the service interfaces that the module uses.
+    pub uses: Vec<String>,
+    pub provides: Vec<Provides>,
+    /// The packages of the `ModulePackages` attribute, which is empty if the
+    /// class file has none, like the class files compiled by javac.
+    pub packages: Vec<String>,
+}
+
+/// A dependence of a [`ModuleDescriptor`] on another module.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Requires {
+    pub name: String,
+    pub flags: RequiresFlags,
+    /// The version of the required module at compile time, if it was
+    /// recorded.
+    pub version: Option<String>,
+}
+
+/// A package that a [`ModuleDescriptor`] exports.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Exports {
+    pub package: String,
+    pub flags: ExportsFlags,
+    /// The modules that the package is exported to, which is empty if it is
+    /// exported to all modules.
+    pub to: Vec<String>,
+}
+
+/// A package that a [`ModuleDescriptor`] opens.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Opens {
+    pub package: String,
+    pub flags: OpensFlags,
+    /// The modules that the package is opened to, which is empty if it is
+    /// opened to all modules.
+    pub to: Vec<String>,
+}
+
+/// A service that a [`ModuleDescriptor`] provides.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Provides {
+    /// The binary name of the service interface.
+    pub service: String,
+    /// The binary names of the implementations of the service.
+    pub with: Vec<String>,
+}
+
+impl ClassFile {
+    /// The module that this `module-info` class file declares in its
+    /// `Module` attribute, or [`None`] if the class file has none.
+    pub fn module_descriptor(&self) -> Result<Option<ModuleDescriptor>, ConstantPoolError> {
+        let cp = &self.cp_info;
+        for attribute in &self.attributes {
+            if let AttributeInfo::Module {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses_index,
+                provides,
+                ..
+            } = attribute
+            {
+                let mut packages = vec![];
+                for attribute in &self.attributes {
+                    if let AttributeInfo::ModulePackages { package_index, .. } = attribute {
+                        for index in package_index {
+                            packages.push(cp.get_package_name(*index)?.into_owned());
+                        }
+                    }
+                }
+                return Ok(Some(ModuleDescriptor {
+                    name: cp.get_module_name(*module_name_index)?.into_owned(),
+                    flags: *module_flags,
+                    version: optional_utf8(cp, *module_version_index)?,
+                    requires: requires
+                        .iter()
+                        .map(|r| {
+                            Ok(Requires {
+                                name: cp.get_module_name(r.requires_index())?.into_owned(),
+                                flags: r.requires_flags(),
+                                version: optional_utf8(cp, r.requires_version_index())?,
+                            })
+                        })
+                        .collect::<Result<_, ConstantPoolError>>()?,
+                    exports: exports
+                        .iter()
+                        .map(|e| {
+                            Ok(Exports {
+                                package: cp.get_package_name(e.exports_index())?.into_owned(),
+                                flags: e.exports_flags(),
+                                to: module_names(cp, e.exports_to_index())?,
+                            })
+                        })
+                        .collect::<Result<_, ConstantPoolError>>()?,
+                    opens: opens
+                        .iter()
+                        .map(|o| {
+                            Ok(Opens {
+                                package: cp.get_package_name(o.opens_index())?.into_owned(),
+                                flags: o.opens_flags(),
+                                to: module_names(cp, o.opens_to_index())?,
+                            })
+                        })
+                        .collect::<Result<_, ConstantPoolError>>()?,
+                    uses: class_names(cp, uses_index)?,
+                    provides: provides
+                        .iter()
+                        .map(|p| {
+                            Ok(Provides {
+                                service: cp.get_class_name(p.provides_index())?.into_owned(),
+                                with: class_names(cp, p.provides_with_index())?,
+                            })
+                        })
+                        .collect::<Result<_, ConstantPoolError>>()?,
+                    packages,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The `CONSTANT_Utf8` entry at the given index, or [`None`] for index 0.
+fn optional_utf8(cp: &ConstantPool, index: u16) -> Result<Option<String>, ConstantPoolError> {
+    match index {
+        0 => Ok(None),
+        index => Ok(Some(cp.get_utf8(index)?.into_owned())),
+    }
+}
+
+fn module_names(cp: &ConstantPool, indices: &[u16]) -> Result<Vec<String>, ConstantPoolError> {
+    indices
+        .iter()
+        .map(|index| Ok(cp.get_module_name(*index)?.into_owned()))
+        .collect()
+}
+
+fn class_names(cp: &ConstantPool, indices: &[u16]) -> Result<Vec<String>, ConstantPoolError> {
+    indices
+        .iter()
+        .map(|index| Ok(cp.get_class_name(*index)?.into_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::{parse, MODES};
+    use crate::classfile::ParseMode;
+
+    #[test]
+    fn test_module_descriptor() {
+        for mode in MODES {
+            let module = parse("modules/app/module-info", mode)
+                .module_descriptor()
+                .unwrap()
+                .unwrap();
+            let requires = |name: &str, flags| Requires {
+                name: name.into(),
+                flags,
+                version: Some("17.0.15".into()),
+            };
+            assert_eq!(
+                ModuleDescriptor {
+                    name: "app".into(),
+                    flags: ModuleFlags::empty(),
+                    version: Some("1.2".into()),
+                    requires: vec![
+                        requires("java.base", RequiresFlags::MANDATED),
+                        requires("java.logging", RequiresFlags::empty()),
+                        requires("java.sql", RequiresFlags::STATIC_PHASE),
+                        requires("java.xml", RequiresFlags::TRANSITIVE),
+                    ],
+                    exports: vec![
+                        Exports {
+                            package: "app/api".into(),
+                            flags: ExportsFlags::empty(),
+                            to: vec![],
+                        },
+                        Exports {
+                            package: "app/spi".into(),
+                            flags: ExportsFlags::empty(),
+                            to: vec!["java.logging".into(), "java.xml".into()],
+                        },
+                    ],
+                    opens: vec![Opens {
+                        package: "app/internal".into(),
+                        flags: OpensFlags::empty(),
+                        to: vec![],
+                    }],
+                    uses: vec!["app/spi/Plugin".into()],
+                    provides: vec![Provides {
+                        service: "app/spi/Plugin".into(),
+                        with: vec!["app/internal/DefaultPlugin".into()],
+                    }],
+                    packages: vec![],
+                },
+                module
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_module() {
+        let class_file = parse("Foo", ParseMode::Eager);
+        assert_eq!(None, class_file.module_descriptor().unwrap());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// The bytes are not valid Modified UTF-8, see [`decode`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DecodeError {
+    /// The offset of the first byte of the invalid sequence.
+    pub offset: usize,
+}
+
+/// Decodes the bytes of a `CONSTANT_Utf8` entry ([`$4.4.7`]).
+///
+/// Modified UTF-8 differs from standard UTF-8 in two ways: the null character
+/// is encoded in two bytes (`0xC0 0x80`), so that no byte is ever 0, and
+/// supplementary characters are encoded as their surrogate pair, each
+/// surrogate in three bytes, instead of in four bytes. Like
+/// `DataInput.readUTF`, overlong two and three byte sequences are accepted.
+///
+/// The bytes are borrowed if they are standard UTF-8 as well, which is the
+/// case for all strings without null and supplementary characters. A
+/// surrogate that is not part of a pair can't be represented in a [`str`], so
+/// it is an error, see [`decode_lossy`].
+///
+/// [`$4.4.7`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.7
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, DecodeError> {
+    decode_with(bytes, |offset| Err(DecodeError { offset }))
+}
+
+/// Decodes the bytes of a `CONSTANT_Utf8` entry like [`decode`], but replaces
+/// every invalid sequence and unpaired surrogate with
+/// [`char::REPLACEMENT_CHARACTER`], like [`String::from_utf8_lossy`].
+pub fn decode_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    match decode_with(bytes, |_| Ok(char::REPLACEMENT_CHARACTER)) {
+        Ok(s) => s,
+        Err(e) => unreachable!("lossy decoding failed at {}", e.offset),
+    }
+}
+
+/// Encodes the string as Modified UTF-8, the inverse of [`decode`]. The bytes
+/// of the string are borrowed if it has no null and supplementary characters.
+pub fn encode(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(|c| c == '\0' || c.len_utf16() == 2) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+    let mut bytes = Vec::with_capacity(s.len() + 2);
+    for unit in s.encode_utf16() {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            // including the null character, which has an overlong encoding
+            0x00 | 0x80..=0x7FF => {
+                bytes.extend([0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8])
+            }
+            _ => bytes.extend([
+                0xE0 | (unit >> 12) as u8,
+                0x80 | ((unit >> 6) & 0x3F) as u8,
+                0x80 | (unit & 0x3F) as u8,
+            ]),
+        }
+    }
+    Cow::Owned(bytes)
+}
+
+/// Decodes the bytes, calling `invalid` with the offset of every invalid
+/// sequence for the character that replaces it.
+fn decode_with(
+    bytes: &[u8],
+    mut invalid: impl FnMut(usize) -> Result<char, DecodeError>,
+) -> Result<Cow<'_, str>, DecodeError> {
+    // standard UTF-8 without null bytes and four byte sequences means the same
+    // in Modified UTF-8, since neither allows surrogates or overlong sequences
+    if let Ok(s) = core::str::from_utf8(bytes) {
+        if bytes.iter().all(|b| *b != 0 && *b < 0xF0) {
+            return Ok(Cow::Borrowed(s));
+        }
+    }
+
+    // the UTF-16 code units and the offsets that they start at
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let continuation = |i: usize| {
+            bytes
+                .get(offset + i)
+                .filter(|b| *b & 0xC0 == 0x80)
+                .map(|b| (*b & 0x3F) as u16)
+        };
+        let (unit, len) = match bytes[offset] {
+            b @ 0x01..=0x7F => (Some(b as u16), 1),
+            b @ 0xC0..=0xDF => (continuation(1).map(|c| ((b & 0x1F) as u16) << 6 | c), 2),
+            b @ 0xE0..=0xEF => (
+                continuation(1)
+                    .zip(continuation(2))
+                    .map(|(c1, c2)| ((b & 0x0F) as u16) << 12 | c1 << 6 | c2),
+                3,
+            ),
+            _ => (None, 1),
+        };
+        match unit {
+            Some(unit) => {
+                units.push((offset, Ok(unit)));
+                offset += len;
+            }
+            None => {
+                units.push((offset, Err(())));
+                offset += 1;
+            }
+        }
+    }
+
+    let mut s = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < units.len() {
+        let (offset, unit) = units[i];
+        i += 1;
+        let c = match unit {
+            Ok(high @ 0xD800..=0xDBFF) => match units.get(i) {
+                Some((_, Ok(low @ 0xDC00..=0xDFFF))) => {
+                    i += 1;
+                    char::decode_utf16([high, *low]).next().unwrap().ok()
+                }
+                _ => None,
+            },
+            Ok(unit) => char::from_u32(unit as u32),
+            Err(()) => None,
+        };
+        s.push(match c {
+            Some(c) => c,
+            None => invalid(offset)?,
+        });
+    }
+    Ok(Cow::Owned(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Ok(Cow::Borrowed("java/lang/Object")),
+            decode(b"java/lang/Object")
+        );
+        assert_eq!(
+            Ok(Cow::Borrowed("caf\u{e9} \u{20ac}")),
+            decode("caf\u{e9} \u{20ac}".as_bytes())
+        );
+
+        // the null character and a supplementary character
+        let bytes = b"a\xC0\x80b\xED\xA0\xBD\xED\xB8\x80";
+        assert_eq!(Ok("a\0b\u{1F600}".into()), decode(bytes));
+        // overlong encodings like DataInput.readUTF
+        assert_eq!(Ok("A".into()), decode(b"\xC1\x81"));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        // raw null bytes and four byte sequences are not allowed
+        assert_eq!(Err(DecodeError { offset: 1 }), decode(b"a\0"));
+        assert_eq!(
+            Err(DecodeError { offset: 0 }),
+            decode("\u{1F600}".as_bytes())
+        );
+        // truncated sequence, unpaired surrogates
+        assert_eq!(Err(DecodeError { offset: 2 }), decode(b"ab\xE2\x82"));
+        assert_eq!(Err(DecodeError { offset: 1 }), decode(b"a\xED\xA0\xBDb"));
+        assert_eq!(Err(DecodeError { offset: 0 }), decode(b"\xED\xB8\x80"));
+
+        assert_eq!("a\u{FFFD}b", decode_lossy(b"a\xED\xA0\xBDb"));
+        assert_eq!("\u{FFFD}\u{FFFD}", decode_lossy(b"\xE2\x82"));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert!(matches!(encode("java/lang/Object"), Cow::Borrowed(_)));
+        assert_eq!(
+            &b"a\xC0\x80b\xED\xA0\xBD\xED\xB8\x80"[..],
+            &*encode("a\0b\u{1F600}")
+        );
+        for s in ["", "\0", "\u{7FF}\u{800}\u{FFFF}", "x\u{10000}\u{10FFFF}y"] {
+            assert_eq!(Ok(s.into()), decode(&encode(s)).map(Cow::into_owned));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::classfile::flags::InnerClassAccessFlags;
+use crate::classfile::{AttributeInfo, ClassFile, ConstantPool, ConstantPoolError};
+use crate::prelude::*;
+
+/// An entry of the `InnerClasses` attribute ([`$4.7.6`]) with its names
+/// resolved from the constant pool, see [`ClassFile::inner_classes`].
+///
+/// [`$4.7.6`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NestedClass {
+    /// The binary name of the nested class, e.g. `Outer$Inner`.
+    pub name: String,
+    /// The binary name of the class that declares the nested class as a
+    /// member, which is [`None`] for local and anonymous classes.
+    pub outer: Option<String>,
+    /// The simple name of the nested class as in the source, e.g. `Inner`,
+    /// which is [`None`] for anonymous classes.
+    pub simple_name: Option<String>,
+    pub flags: InnerClassAccessFlags,
+}
+
+/// The `EnclosingMethod` attribute ([`$4.7.7`]) of a local or anonymous class
+/// with its names resolved, see [`ClassFile::enclosing_method`].
+///
+/// [`$4.7.7`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.7
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EnclosingMethod {
+    /// The binary name of the innermost class that encloses the declaration.
+    pub class: String,
+    /// The name and descriptor of the method that immediately encloses the
+    /// declaration, which is [`None`] if the class is declared in an
+    /// initializer of a field or in an initializer block.
+    pub method: Option<(String, String)>,
+}
+
+/// Where a class is declared, like `Class.isMemberClass`,
+/// `Class.isLocalClass` and `Class.isAnonymousClass`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NestingKind {
+    TopLevel,
+    /// A class declared in the body of another class.
+    Member,
+    /// A named class declared in a block, e.g. in a method.
+    Local,
+    Anonymous,
+}
+
+impl ClassFile {
+    /// The entries of the `InnerClasses` attribute, in the order of the class
+    /// file. Besides the classes nested in this one, these are all nested
+    /// classes that this class refers to, and this class itself if it is
+    /// nested.
+    pub fn inner_classes(&self) -> Result<Vec<NestedClass>, ConstantPoolError> {
+        let cp = &self.cp_info;
+        let mut nested = vec![];
+        for attribute in &self.attributes {
+            if let AttributeInfo::InnerClasses { classes, .. } = attribute {
+                for class in classes {
+                    nested.push(NestedClass {
+                        name: cp
+                            .get_class_name(class.inner_class_info_index)?
+                            .into_owned(),
+                        outer: optional_class_name(cp, class.outer_class_info_index)?,
+                        simple_name: match class.inner_name_index {
+                            0 => None,
+                            index => Some(cp.get_utf8(index)?.into_owned()),
+                        },
+                        flags: class.inner_class_access_flags,
+                    });
+                }
+            }
+        }
+        Ok(nested)
+    }
+
+    /// The method that this local or anonymous class is declared in, or
+    /// [`None`] if the class has no `EnclosingMethod` attribute.
+    pub fn enclosing_method(&self) -> Result<Option<EnclosingMethod>, ConstantPoolError> {
+        let cp = &self.cp_info;
+        for attribute in &self.attributes {
+            if let AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+                ..
+            } = attribute
+            {
+                let method = match method_index {
+                    0 => None,
+                    index => {
+                        let name_and_type = cp.get_name_and_type(*index)?;
+                        Some((
+                            name_and_type.name.into_owned(),
+                            name_and_type.descriptor.into_owned(),
+                        ))
+                    }
+                };
+                return Ok(Some(EnclosingMethod {
+                    class: cp.get_class_name(*class_index)?.into_owned(),
+                    method,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether this is a top level, member, local or anonymous class, by the
+    /// entry for this class in the `InnerClasses` attribute.
+    pub fn nesting_kind(&self) -> Result<NestingKind, ConstantPoolError> {
+        Ok(match self.own_inner_class()? {
+            None => NestingKind::TopLevel,
+            Some(NestedClass {
+                simple_name: None, ..
+            }) => NestingKind::Anonymous,
+            Some(NestedClass { outer: None, .. }) => NestingKind::Local,
+            Some(_) => NestingKind::Member,
+        })
+    }
+
+    /// The class that declares this class as a member, like
+    /// `Class.getDeclaringClass`, or [`None`] unless this is a member class.
+    pub fn declaring_class(&self) -> Result<Option<String>, ConstantPoolError> {
+        Ok(self.own_inner_class()?.and_then(|class| class.outer))
+    }
+
+    /// The host of the nest that this class belongs to ([`$5.4.4`]) from its
+    /// `NestHost` attribute, which is this class itself if it has none.
+    ///
+    /// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+    pub fn nest_host(&self) -> Result<String, ConstantPoolError> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::NestHost {
+                host_class_index, ..
+            } = attribute
+            {
+                return Ok(self.cp_info.get_class_name(*host_class_index)?.into_owned());
+            }
+        }
+        Ok(self.this_class_name()?.into_owned())
+    }
+
+    /// The classes that this nest host lists in its `NestMembers` attribute,
+    /// in the order of the class file. This is empty for classes that aren't
+    /// the host of a nest.
+    pub fn nest_members(&self) -> Result<Vec<String>, ConstantPoolError> {
+        let mut members = vec![];
+        for attribute in &self.attributes {
+            if let AttributeInfo::NestMembers { classes, .. } = attribute {
+                for index in classes {
+                    members.push(self.cp_info.get_class_name(*index)?.into_owned());
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    /// Whether this class and the other one claim to be in the same nest, by
+    /// their class files alone: both name the same host, and if one of them is
+    /// the host, it lists the other one as a member. A VM also has to load
+    /// the host to check that it lists both classes.
+    pub fn is_nestmate_of(&self, other: &ClassFile) -> Result<bool, ConstantPoolError> {
+        let host = self.nest_host()?;
+        if host != other.nest_host()? {
+            return Ok(false);
+        }
+        let (this, other_name) = (self.this_class_name()?, other.this_class_name()?);
+        if this == other_name {
+            return Ok(true);
+        }
+        if host == this {
+            return Ok(self.nest_members()?.contains(&other_name.into_owned()));
+        }
+        if host == other_name {
+            return Ok(other.nest_members()?.contains(&this.into_owned()));
+        }
+        Ok(true)
+    }
+
+    /// The entry for this class in the `InnerClasses` attribute, if it is a
+    /// nested class.
+    fn own_inner_class(&self) -> Result<Option<NestedClass>, ConstantPoolError> {
+        let name = self.this_class_name()?;
+        Ok(self
+            .inner_classes()?
+            .into_iter()
+            .find(|class| class.name == name))
+    }
+}
+
+/// The name of the `CONSTANT_Class` entry at the given index, or [`None`] for
+/// index 0.
+fn optional_class_name(cp: &ConstantPool, index: u16) -> Result<Option<String>, ConstantPoolError> {
+    match index {
+        0 => Ok(None),
+        index => Ok(Some(cp.get_class_name(index)?.into_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::parse;
+    use crate::classfile::ParseMode;
+
+    #[test]
+    fn test_nesting_kind() {
+        for (name, kind, declaring_class) in [
+            ("Nesting", NestingKind::TopLevel, None),
+            ("Nesting$Member", NestingKind::Member, Some("Nesting")),
+            (
+                "Nesting$Member$Deep",
+                NestingKind::Member,
+                Some("Nesting$Member"),
+            ),
+            ("Nesting$1Local", NestingKind::Local, None),
+            ("Nesting$1", NestingKind::Anonymous, None),
+        ] {
+            let class_file = parse(name, ParseMode::Eager);
+            assert_eq!(kind, class_file.nesting_kind().unwrap(), "{}", name);
+            assert_eq!(
+                declaring_class.map(String::from),
+                class_file.declaring_class().unwrap(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_inner_classes() {
+        let inner_classes = parse("Nesting$Member", ParseMode::Eager)
+            .inner_classes()
+            .unwrap();
+        assert_eq!(
+            vec![
+                NestedClass {
+                    name: "Nesting$Member".into(),
+                    outer: Some("Nesting".into()),
+                    simple_name: Some("Member".into()),
+                    flags: InnerClassAccessFlags::empty(),
+                },
+                NestedClass {
+                    name: "Nesting$Member$Deep".into(),
+                    outer: Some("Nesting$Member".into()),
+                    simple_name: Some("Deep".into()),
+                    flags: InnerClassAccessFlags::STATIC,
+                },
+            ],
+            inner_classes
+        );
+    }
+
+    #[test]
+    fn test_enclosing_method() {
+        assert_eq!(
+            Some(EnclosingMethod {
+                class: "Nesting".into(),
+                method: Some(("anonymous".into(), "()Ljava/lang/Runnable;".into())),
+            }),
+            parse("Nesting$1", ParseMode::Eager)
+                .enclosing_method()
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            parse("Nesting$Member", ParseMode::Eager)
+                .enclosing_method()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nest() {
+        let host = parse("Nesting", ParseMode::Eager);
+        let (member, anonymous) = (
+            parse("Nesting$Member$Deep", ParseMode::Eager),
+            parse("Nesting$1", ParseMode::Eager),
+        );
+        assert_eq!("Nesting", host.nest_host().unwrap());
+        assert_eq!("Nesting", member.nest_host().unwrap());
+        assert_eq!(
+            vec![
+                "Nesting$Member",
+                "Nesting$Member$Deep",
+                "Nesting$1Local",
+                "Nesting$1"
+            ],
+            host.nest_members().unwrap()
+        );
+        assert!(member.nest_members().unwrap().is_empty());
+
+        assert!(host.is_nestmate_of(&member).unwrap());
+        assert!(member.is_nestmate_of(&anonymous).unwrap());
+        assert!(anonymous.is_nestmate_of(&host).unwrap());
+        assert!(!host
+            .is_nestmate_of(&parse("Shapes", ParseMode::Eager))
+            .unwrap());
+        // Shapes$Square names Shapes as its host, which doesn't list Nesting
+        assert!(!parse("Shapes$Square", ParseMode::Eager)
+            .is_nestmate_of(&host)
+            .unwrap());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::classfile::{ClassFileParseError, ParseMode, MAX_NESTING_DEPTH};
+
+/// How [`ClassFile::parse_with_options`](crate::classfile::ClassFile::parse_with_options)
+/// parses a class file, and limits on its structures for class files from
+/// untrusted sources. The parser never allocates more than the class file
+/// contains, but a malformed class file that claims a constant pool of 65535
+/// entries or code of 4 GiB still makes the parser read and keep all of it
+/// before it fails. A limit fails parsing as soon as such a count is read, with
+/// [`ClassFileParseError::LimitExceeded`].
+///
+/// By default, there are no limits beyond the ranges of the counts in the
+/// class file format, like for [`ClassFile::parse`](crate::classfile::ClassFile::parse).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    /// The largest `constant_pool_count`, which is one more than the number
+    /// of constant pool indices.
+    pub max_constant_pool_count: u16,
+    /// The largest `code_length` of a `Code` attribute. The JVMS requires it
+    /// to be less than 65536 ([`$4.7.3`]), so 65535 rejects no valid class
+    /// file.
+    ///
+    /// [`$4.7.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.3
+    pub max_code_length: u32,
+    /// The largest `attributes_count` of the class, a field, a method, a
+    /// `Code` attribute or a record component.
+    pub max_attributes: u16,
+    /// How deep attributes may be nested in other attributes, e.g. 1 for the
+    /// attributes of a `Code` attribute. This can't be more than the parser
+    /// supports anyway, beyond which it fails with
+    /// [`ClassFileParseError::NestingTooDeep`].
+    pub max_nesting_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            mode: ParseMode::default(),
+            max_constant_pool_count: u16::MAX,
+            max_code_length: u32::MAX,
+            max_attributes: u16::MAX,
+            max_nesting_depth: MAX_NESTING_DEPTH,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Checks the value of the given limit, e.g. the `code_length` of a
+    /// `Code` attribute against [`ParseOptions::max_code_length`].
+    pub(super) fn check(&self, limit: ParseLimit, value: usize) -> Result<(), ClassFileParseError> {
+        let max = match limit {
+            ParseLimit::ConstantPoolCount => self.max_constant_pool_count as usize,
+            ParseLimit::CodeLength => self.max_code_length as usize,
+            ParseLimit::Attributes => self.max_attributes as usize,
+            ParseLimit::NestingDepth => self.max_nesting_depth,
+        };
+        if value > max {
+            return Err(ClassFileParseError::LimitExceeded(limit));
+        }
+        Ok(())
+    }
+}
+
+/// A limit of [`ParseOptions`] that a class file exceeded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseLimit {
+    ConstantPoolCount,
+    CodeLength,
+    Attributes,
+    NestingDepth,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::classfile::{ClassFile, ClassFileParseError, ParseLimit, ParseOptions};
+
+    fn parse(bytes: &[u8], options: ParseOptions) -> Result<ClassFile, ClassFileParseError> {
+        ClassFile::parse_with_options(&mut &bytes[..], &options).map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        assert!(parse(&bytes, ParseOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_limits() {
+        let bytes = std::fs::read("tests/resources/Formats.class").unwrap();
+        let limited = |options: ParseOptions| parse(&bytes, options).err();
+        assert_eq!(
+            Some(ClassFileParseError::LimitExceeded(
+                ParseLimit::ConstantPoolCount
+            )),
+            limited(ParseOptions {
+                max_constant_pool_count: 10,
+                ..ParseOptions::default()
+            })
+        );
+        assert_eq!(
+            Some(ClassFileParseError::LimitExceeded(ParseLimit::CodeLength)),
+            limited(ParseOptions {
+                max_code_length: 4,
+                ..ParseOptions::default()
+            })
+        );
+        assert_eq!(
+            Some(ClassFileParseError::LimitExceeded(ParseLimit::Attributes)),
+            limited(ParseOptions {
+                max_attributes: 0,
+                ..ParseOptions::default()
+            })
+        );
+        // the attributes of Code attributes are nested
+        assert_eq!(
+            Some(ClassFileParseError::LimitExceeded(ParseLimit::NestingDepth)),
+            limited(ParseOptions {
+                max_nesting_depth: 0,
+                ..ParseOptions::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_limit_before_reading() {
+        // a constant pool of 65535 entries, of which there are none
+        let bytes = [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x3D, 0xFF, 0xFF];
+        let options = ParseOptions {
+            max_constant_pool_count: 1024,
+            ..ParseOptions::default()
+        };
+        let error = ClassFile::parse_with_options(&mut &bytes[..], &options).unwrap_err();
+        assert_eq!(
+            ClassFileParseError::LimitExceeded(ParseLimit::ConstantPoolCount),
+            error.kind()
+        );
+        assert_eq!(10, error.offset());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::classfile::{
+    AnnotationError, AttributeInfo, ClassFile, ConstantPool, DecodedAnnotation, RecordComponentInfo,
+};
+use crate::prelude::*;
+
+/// A [`RecordComponentInfo`] with its name, descriptor and attributes
+/// resolved from the constant pool, see [`ClassFile::record_components`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponent {
+    pub name: String,
+    /// The field descriptor of the component, e.g. `Ljava/util/List;`.
+    pub descriptor: String,
+    /// The generic signature from the `Signature` attribute, e.g.
+    /// `Ljava/util/List<Ljava/lang/String;>;`, if the type of the component
+    /// uses type variables or parameterized types.
+    pub signature: Option<String>,
+    pub runtime_visible_annotations: Vec<DecodedAnnotation>,
+    pub runtime_invisible_annotations: Vec<DecodedAnnotation>,
+}
+
+impl ClassFile {
+    /// The components of the record class from its `Record` attribute
+    /// ([`$4.7.30`]), in the order of their declaration, or [`None`] if this
+    /// class isn't a record.
+    ///
+    /// [`$4.7.30`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.30
+    pub fn record_components(&self) -> Result<Option<Vec<RecordComponent>>, AnnotationError> {
+        for attribute in &self.attributes {
+            if let AttributeInfo::Record { components, .. } =
+                attribute.materialize(&self.cp_info)?
+            {
+                return components
+                    .iter()
+                    .map(|component| RecordComponent::decode(&self.cp_info, component))
+                    .collect::<Result<_, _>>()
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl RecordComponent {
+    fn decode(
+        cp: &ConstantPool,
+        component: &RecordComponentInfo,
+    ) -> Result<RecordComponent, AnnotationError> {
+        let mut signature = None;
+        for attribute in &component.attributes {
+            if let AttributeInfo::Signature {
+                signature_index, ..
+            } = attribute.materialize(cp)?
+            {
+                signature = Some(cp.get_utf8(*signature_index)?.into_owned());
+            }
+        }
+        Ok(RecordComponent {
+            name: cp.get_utf8(component.name_index)?.into_owned(),
+            descriptor: cp.get_utf8(component.descriptor_index)?.into_owned(),
+            signature,
+            runtime_visible_annotations: component.runtime_visible_annotations(cp)?,
+            runtime_invisible_annotations: component.runtime_invisible_annotations(cp)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::{parse, MODES};
+    use crate::classfile::{DecodedValue, ParseMode};
+
+    #[test]
+    fn test_record_components() {
+        for mode in MODES {
+            let components = parse("Records", mode).record_components().unwrap().unwrap();
+            assert_eq!(
+                vec![
+                    RecordComponent {
+                        name: "count".into(),
+                        descriptor: "I".into(),
+                        signature: None,
+                        runtime_visible_annotations: vec![],
+                        runtime_invisible_annotations: vec![],
+                    },
+                    RecordComponent {
+                        name: "name".into(),
+                        descriptor: "Ljava/lang/String;".into(),
+                        signature: None,
+                        runtime_visible_annotations: vec![DecodedAnnotation {
+                            type_descriptor: "LRecords$Tag;".into(),
+                            elements: vec![("value".into(), DecodedValue::String("name".into()))],
+                        }],
+                        runtime_invisible_annotations: vec![],
+                    },
+                    RecordComponent {
+                        name: "tags".into(),
+                        descriptor: "Ljava/util/List;".into(),
+                        signature: Some("Ljava/util/List<Ljava/lang/String;>;".into()),
+                        runtime_visible_annotations: vec![],
+                        runtime_invisible_annotations: vec![],
+                    },
+                ],
+                components
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_record() {
+        let class_file = parse("Foo", ParseMode::Eager);
+        assert_eq!(None, class_file.record_components().unwrap());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::classfile::{
+    ConstantPool, ConstantPoolError, ConstantPoolInfo, MemberRef, NameAndType, ReferenceKind,
+};
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// A [`ConstantPool`] whose entries are resolved once up front, so that the
+/// typed accessors don't have to follow indices and decode strings on every
+/// call: `CONSTANT_Utf8` entries are decoded to [`String`]s, numeric
+/// constants to their values, and the symbolic references of the other
+/// entries are replaced with the names they refer to.
+///
+/// An entry that can't be resolved, e.g. because it refers to an entry of the
+/// wrong kind, is kept as [`ResolvedConstant::Invalid`], and accessing it fails
+/// like accessing it in the [`ConstantPool`] does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResolvedConstantPool {
+    items: Vec<ResolvedConstant>,
+}
+
+/// An entry of a [`ResolvedConstantPool`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedConstant {
+    Utf8(String),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    /// The name of a class, e.g. `java/lang/Object`.
+    Class(String),
+    String(String),
+    Fieldref(ResolvedMember),
+    Methodref(ResolvedMember),
+    InterfaceMethodref(ResolvedMember),
+    NameAndType {
+        name: String,
+        descriptor: String,
+    },
+    MethodHandle {
+        kind: ReferenceKind,
+        /// The field or method that the handle refers to.
+        member: ResolvedMember,
+    },
+    /// The method descriptor of a method type, e.g. `(I)V`.
+    MethodType(String),
+    /// A dynamically-computed constant, whose bootstrap method is the one at
+    /// the given index in the `BootstrapMethods` attribute.
+    Dynamic {
+        bootstrap_method_attr_index: u16,
+        name: String,
+        descriptor: String,
+    },
+    InvokeDynamic {
+        bootstrap_method_attr_index: u16,
+        name: String,
+        descriptor: String,
+    },
+    /// The name of a module, e.g. `java.base`.
+    Module(String),
+    /// The name of a package in internal form, e.g. `java/lang`.
+    Package(String),
+    /// The index after a long or double constant, see
+    /// [`ConstantPoolInfo::Unusable`].
+    Unusable,
+    /// An entry that can't be resolved, with the error of resolving it.
+    Invalid(ConstantPoolError),
+}
+
+/// A resolved `CONSTANT_Fieldref`, `CONSTANT_Methodref` or
+/// `CONSTANT_InterfaceMethodref` entry, like [`MemberRef`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResolvedMember {
+    /// The name of the class that the member is referenced in, e.g.
+    /// `java/lang/Object`.
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl From<MemberRef<'_>> for ResolvedMember {
+    fn from(member: MemberRef<'_>) -> Self {
+        Self {
+            class_name: member.class_name.into_owned(),
+            name: member.name.into_owned(),
+            descriptor: member.descriptor.into_owned(),
+        }
+    }
+}
+
+impl ResolvedConstantPool {
+    /// Resolves all entries of the given constant pool.
+    pub fn new(cp: &ConstantPool) -> Self {
+        let items = (1..=cp.len() as u16)
+            .map(|index| resolve(cp, index).unwrap_or_else(ResolvedConstant::Invalid))
+            .collect();
+        Self { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ResolvedConstant> {
+        self.items.iter()
+    }
+
+    /// Returns the entry at the given 1-based index, like
+    /// [`ConstantPool::get`], or the error of resolving it.
+    pub fn get(&self, index: u16) -> Result<&ResolvedConstant, ConstantPoolError> {
+        match index
+            .checked_sub(1)
+            .and_then(|i| self.items.get(i as usize))
+        {
+            Some(ResolvedConstant::Invalid(e)) => Err(*e),
+            Some(constant) => Ok(constant),
+            None => Err(ConstantPoolError::IndexOutOfBounds(index)),
+        }
+    }
+
+    pub fn get_utf8(&self, index: u16) -> Result<&str, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Utf8(s) => Ok(s),
+            _ => Err(ConstantPoolError::unexpected(index, "Utf8")),
+        }
+    }
+
+    pub fn get_class_name(&self, index: u16) -> Result<&str, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Class(name) => Ok(name),
+            _ => Err(ConstantPoolError::unexpected(index, "Class")),
+        }
+    }
+
+    pub fn get_string(&self, index: u16) -> Result<&str, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::String(s) => Ok(s),
+            _ => Err(ConstantPoolError::unexpected(index, "String")),
+        }
+    }
+
+    pub fn get_integer(&self, index: u16) -> Result<i32, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Integer(value) => Ok(*value),
+            _ => Err(ConstantPoolError::unexpected(index, "Integer")),
+        }
+    }
+
+    pub fn get_float(&self, index: u16) -> Result<f32, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Float(value) => Ok(*value),
+            _ => Err(ConstantPoolError::unexpected(index, "Float")),
+        }
+    }
+
+    pub fn get_long(&self, index: u16) -> Result<i64, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Long(value) => Ok(*value),
+            _ => Err(ConstantPoolError::unexpected(index, "Long")),
+        }
+    }
+
+    pub fn get_double(&self, index: u16) -> Result<f64, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Double(value) => Ok(*value),
+            _ => Err(ConstantPoolError::unexpected(index, "Double")),
+        }
+    }
+
+    pub fn get_name_and_type(&self, index: u16) -> Result<NameAndType<'_>, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::NameAndType { name, descriptor } => Ok(NameAndType {
+                name: Cow::Borrowed(name),
+                descriptor: Cow::Borrowed(descriptor),
+            }),
+            _ => Err(ConstantPoolError::unexpected(index, "NameAndType")),
+        }
+    }
+
+    pub fn get_field_ref(&self, index: u16) -> Result<&ResolvedMember, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Fieldref(member) => Ok(member),
+            _ => Err(ConstantPoolError::unexpected(index, "Fieldref")),
+        }
+    }
+
+    pub fn get_method_ref(&self, index: u16) -> Result<&ResolvedMember, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::Methodref(member) => Ok(member),
+            _ => Err(ConstantPoolError::unexpected(index, "Methodref")),
+        }
+    }
+
+    pub fn get_interface_method_ref(
+        &self,
+        index: u16,
+    ) -> Result<&ResolvedMember, ConstantPoolError> {
+        match self.get(index)? {
+            ResolvedConstant::InterfaceMethodref(member) => Ok(member),
+            _ => Err(ConstantPoolError::unexpected(index, "InterfaceMethodref")),
+        }
+    }
+}
+
+fn resolve(cp: &ConstantPool, index: u16) -> Result<ResolvedConstant, ConstantPoolError> {
+    use ConstantPoolInfo::*;
+    let name_and_type = |index| {
+        cp.get_name_and_type(index)
+            .map(|nat| (nat.name.into_owned(), nat.descriptor.into_owned()))
+    };
+    Ok(match cp.get_entry(index)? {
+        Utf8Info { .. } => ResolvedConstant::Utf8(cp.get_utf8(index)?.into_owned()),
+        IntegerInfo { .. } => ResolvedConstant::Integer(cp.get_integer(index)?),
+        FloatInfo { .. } => ResolvedConstant::Float(cp.get_float(index)?),
+        LongInfo { .. } => ResolvedConstant::Long(cp.get_long(index)?),
+        DoubleInfo { .. } => ResolvedConstant::Double(cp.get_double(index)?),
+        ClassInfo { .. } => ResolvedConstant::Class(cp.get_class_name(index)?.into_owned()),
+        StringInfo { .. } => ResolvedConstant::String(cp.get_string(index)?.into_owned()),
+        FieldrefInfo { .. } => ResolvedConstant::Fieldref(cp.get_field_ref(index)?.into()),
+        MethodrefInfo { .. } => ResolvedConstant::Methodref(cp.get_method_ref(index)?.into()),
+        InterfaceMethodrefInfo { .. } => {
+            ResolvedConstant::InterfaceMethodref(cp.get_interface_method_ref(index)?.into())
+        }
+        NameAndTypeInfo { .. } => {
+            let (name, descriptor) = name_and_type(index)?;
+            ResolvedConstant::NameAndType { name, descriptor }
+        }
+        MethodHandleInfo {
+            reference_kind,
+            reference_index,
+        } => {
+            let member = match cp.get_entry(*reference_index)? {
+                FieldrefInfo { .. } => cp.get_field_ref(*reference_index)?,
+                MethodrefInfo { .. } => cp.get_method_ref(*reference_index)?,
+                InterfaceMethodrefInfo { .. } => cp.get_interface_method_ref(*reference_index)?,
+                _ => return Err(ConstantPoolError::unexpected(*reference_index, "Methodref")),
+            };
+            ResolvedConstant::MethodHandle {
+                kind: *reference_kind,
+                member: member.into(),
+            }
+        }
+        MethodTypeInfo { descriptor_index } => {
+            ResolvedConstant::MethodType(cp.get_utf8(*descriptor_index)?.into_owned())
+        }
+        DynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => {
+            let (name, descriptor) = name_and_type(*name_and_type_index)?;
+            ResolvedConstant::Dynamic {
+                bootstrap_method_attr_index: *bootstrap_method_attr_index,
+                name,
+                descriptor,
+            }
+        }
+        InvokeDynamicInfo {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        } => {
+            let (name, descriptor) = name_and_type(*name_and_type_index)?;
+            ResolvedConstant::InvokeDynamic {
+                bootstrap_method_attr_index: *bootstrap_method_attr_index,
+                name,
+                descriptor,
+            }
+        }
+        ModuleInfo { .. } => ResolvedConstant::Module(cp.get_module_name(index)?.into_owned()),
+        PackageInfo { .. } => ResolvedConstant::Package(cp.get_package_name(index)?.into_owned()),
+        Unusable => ResolvedConstant::Unusable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::ClassFile;
+
+    #[test]
+    fn test_resolve() {
+        let bytes = std::fs::read("tests/resources/Constants.class").unwrap();
+        let class_file = ClassFile::parse(&mut bytes.as_slice()).unwrap();
+        let cp = class_file.constant_pool();
+        let resolved = ResolvedConstantPool::new(cp);
+        assert_eq!(cp.len(), resolved.len());
+
+        // every accessor returns what it returns for the constant pool
+        for index in 0..=cp.len() as u16 + 1 {
+            assert_eq!(
+                cp.get_utf8(index).as_deref().map_err(|e| *e),
+                resolved.get_utf8(index)
+            );
+            assert_eq!(
+                cp.get_class_name(index).as_deref().map_err(|e| *e),
+                resolved.get_class_name(index)
+            );
+            assert_eq!(
+                cp.get_string(index).as_deref().map_err(|e| *e),
+                resolved.get_string(index)
+            );
+            assert_eq!(cp.get_integer(index), resolved.get_integer(index));
+            assert_eq!(cp.get_long(index), resolved.get_long(index));
+            assert_eq!(
+                cp.get_float(index).map(f32::to_bits),
+                resolved.get_float(index).map(f32::to_bits)
+            );
+            assert_eq!(
+                cp.get_double(index).map(f64::to_bits),
+                resolved.get_double(index).map(f64::to_bits)
+            );
+            assert_eq!(
+                cp.get_name_and_type(index),
+                resolved.get_name_and_type(index)
+            );
+            assert_eq!(
+                cp.get_field_ref(index).map(ResolvedMember::from),
+                resolved.get_field_ref(index).cloned()
+            );
+            assert_eq!(
+                cp.get_method_ref(index).map(ResolvedMember::from),
+                resolved.get_method_ref(index).cloned()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        let cp = ConstantPool::from(vec![
+            ConstantPoolInfo::ClassInfo { name_index: 3 },
+            ConstantPoolInfo::StringInfo { string_index: 1 },
+            ConstantPoolInfo::utf8("java/lang/Object"),
+        ]);
+        let resolved = ResolvedConstantPool::new(&cp);
+        assert_eq!(Ok("java/lang/Object"), resolved.get_class_name(1));
+        assert_eq!(
+            Err(ConstantPoolError::UnexpectedEntry {
+                index: 1,
+                expected: "Utf8"
+            }),
+            resolved.get(2)
+        );
+        assert_eq!(Err(ConstantPoolError::IndexOutOfBounds(4)), resolved.get(4));
+    }
+}
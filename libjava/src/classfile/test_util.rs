@@ -0,0 +1,16 @@
+use crate::classfile::{ClassFile, ParseMode};
+
+/// The parse modes, which the tests of the accessors run for, so that the
+/// attributes that a lazily parsed class file decodes on demand are checked
+/// against the eagerly parsed ones.
+pub const MODES: [ParseMode; 2] = [ParseMode::Eager, ParseMode::Lazy];
+
+/// Reads the bytes of `tests/resources/<path>.class`.
+pub fn read(path: &str) -> Vec<u8> {
+    std::fs::read(format!("tests/resources/{}.class", path)).unwrap()
+}
+
+/// Parses `tests/resources/<path>.class` in the given mode.
+pub fn parse(path: &str, mode: ParseMode) -> ClassFile {
+    ClassFile::parse_with(&mut read(path).as_slice(), mode).unwrap()
+}
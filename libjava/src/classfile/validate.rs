@@ -0,0 +1,972 @@
+use crate::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::classfile::{
+    AttributeInfo, BootstrapMethod, ClassFile, ConstantPool, ConstantPoolError, ConstantPoolInfo,
+    FieldInfo, MethodInfo, ReferenceKind,
+};
+use crate::prelude::*;
+use alloc::borrow::Cow;
+use core::fmt::{Display, Formatter};
+
+/// The maximum number of array dimensions of a descriptor ([`$4.3.2`]).
+///
+/// [`$4.3.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+/// The maximum number of parameter slots of a method descriptor, including
+/// `this` ([`$4.3.3`]).
+///
+/// [`$4.3.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+const MAX_PARAMETER_SLOTS: usize = 255;
+
+/// A violation of the format checks of [`ClassFile::validate`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FormatViolation {
+    /// Where the violation is, e.g. `constant pool #7` or `method foo()V`.
+    pub location: String,
+    /// What is wrong, in the words of the `ClassFormatError`s of HotSpot
+    /// where there is one.
+    pub message: String,
+}
+
+impl Display for FormatViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl ClassFile {
+    /// Performs the format checks of [`$4.8`] that parsing doesn't, and
+    /// returns every violation that was found, or nothing for a well-formed
+    /// class file:
+    ///
+    /// - every index into the constant pool, in the constant pool itself and
+    ///   in the rest of the class file, refers to an entry of the expected
+    ///   kind, and `CONSTANT_Utf8` entries are valid Modified UTF-8
+    /// - names and descriptors of classes, fields and methods are valid
+    ///   ([`$4.2`], [`$4.3`])
+    /// - a method has exactly one `Code` attribute unless it is abstract or
+    ///   native, in which case it has none, and the code and exception table
+    ///   are in range
+    /// - the declared length of every attribute matches its contents
+    ///
+    /// Checks that need other classes, like whether the superclass is final,
+    /// are left to linking.
+    ///
+    /// [`$4.8`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.8
+    /// [`$4.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2
+    /// [`$4.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3
+    pub fn validate(&self) -> Vec<FormatViolation> {
+        let mut checker = Checker {
+            cp: &self.cp_info,
+            bootstrap_methods: self
+                .attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    AttributeInfo::BootstrapMethods {
+                        bootstrap_methods, ..
+                    } => Some(bootstrap_methods.len()),
+                    _ => None,
+                })
+                .unwrap_or(0),
+            major_version: self.version.major,
+            violations: vec![],
+        };
+        checker.check_constant_pool();
+        checker.check_class(self);
+        for field in &self.fields {
+            checker.check_field(field);
+        }
+        for method in &self.methods {
+            checker.check_method(method);
+        }
+        for attribute in &self.attributes {
+            checker.check_attribute("class", attribute);
+        }
+        checker.violations
+    }
+}
+
+struct Checker<'a> {
+    cp: &'a ConstantPool,
+    /// The number of entries of the `BootstrapMethods` attribute.
+    bootstrap_methods: usize,
+    major_version: u16,
+    violations: Vec<FormatViolation>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, location: &str, message: String) {
+        self.violations.push(FormatViolation {
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    /// Reports the error of a typed constant pool accessor.
+    fn report_cp(&mut self, location: &str, error: ConstantPoolError) {
+        let message = match error {
+            ConstantPoolError::IndexOutOfBounds(index) => {
+                format!("Invalid constant pool index {}", index)
+            }
+            ConstantPoolError::UnexpectedEntry { index, expected } => {
+                format!("Constant pool index {} is not a {} entry", index, expected)
+            }
+            ConstantPoolError::InvalidUtf8(index) => {
+                format!("Illegal UTF8 string in constant pool at index {}", index)
+            }
+            ConstantPoolError::TooManyEntries => "Constant pool is too large".to_string(),
+        };
+        self.report(location, message);
+    }
+
+    fn utf8(&mut self, location: &str, index: u16) -> Option<Cow<'a, str>> {
+        self.cp
+            .get_utf8(index)
+            .map_err(|e| self.report_cp(location, e))
+            .ok()
+    }
+
+    /// Checks that the index refers to a `CONSTANT_Class` entry.
+    fn class(&mut self, location: &str, index: u16) {
+        if let Err(e) = self.cp.get_class_name(index) {
+            self.report_cp(location, e);
+        }
+    }
+
+    /// Checks that the index is 0 or refers to a `CONSTANT_Class` entry.
+    fn optional_class(&mut self, location: &str, index: u16) {
+        if index != 0 {
+            self.class(location, index);
+        }
+    }
+
+    /// Checks that the index refers to an entry that matches `is_expected`,
+    /// and returns it.
+    fn entry(
+        &mut self,
+        location: &str,
+        index: u16,
+        expected: &'static str,
+        is_expected: impl Fn(&ConstantPoolInfo) -> bool,
+    ) -> Option<&'a ConstantPoolInfo> {
+        let result = match self.cp.get(index) {
+            Some(info) if is_expected(info) => return Some(info),
+            Some(_) => ConstantPoolError::UnexpectedEntry { index, expected },
+            None => ConstantPoolError::IndexOutOfBounds(index),
+        };
+        self.report_cp(location, result);
+        None
+    }
+
+    /// Checks the `CONSTANT_NameAndType` entry at the index, and that its
+    /// descriptor is a field descriptor or a method descriptor.
+    fn name_and_type(&mut self, location: &str, index: u16, method: bool) {
+        let name_and_type = match self.cp.get_name_and_type(index) {
+            Ok(name_and_type) => name_and_type,
+            Err(e) => return self.report_cp(location, e),
+        };
+        if method {
+            self.method_name(location, &name_and_type.name);
+            self.method_descriptor(location, &name_and_type.descriptor);
+        } else {
+            self.field_name(location, &name_and_type.name);
+            self.field_descriptor(location, &name_and_type.descriptor);
+        }
+    }
+
+    fn field_name(&mut self, location: &str, name: &str) {
+        if !is_unqualified_name(name) {
+            self.report(location, format!("Illegal field name \"{}\"", name));
+        }
+    }
+
+    fn method_name(&mut self, location: &str, name: &str) {
+        if !is_method_name(name) {
+            self.report(location, format!("Illegal method name \"{}\"", name));
+        }
+    }
+
+    fn field_descriptor(&mut self, location: &str, descriptor: &str) {
+        if !is_field_descriptor(descriptor) {
+            self.report(
+                location,
+                format!("Illegal field signature \"{}\"", descriptor),
+            );
+        }
+    }
+
+    fn method_descriptor(&mut self, location: &str, descriptor: &str) {
+        if !is_method_descriptor(descriptor) {
+            self.report(
+                location,
+                format!("Illegal method signature \"{}\"", descriptor),
+            );
+        }
+    }
+
+    fn bootstrap_method(&mut self, location: &str, index: u16) {
+        if index as usize >= self.bootstrap_methods {
+            self.report(
+                location,
+                format!("Invalid bootstrap method index {}", index),
+            );
+        }
+    }
+
+    /// Checks the references of every entry of the constant pool ([`$4.4`]).
+    ///
+    /// [`$4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4
+    fn check_constant_pool(&mut self) {
+        for (i, info) in self.cp.iter().enumerate() {
+            let location = &format!("constant pool #{}", i + 1);
+            match info {
+                ConstantPoolInfo::Utf8Info { length, bytes } => {
+                    if *length as usize != bytes.len() {
+                        self.report(
+                            location,
+                            format!("Utf8 length {} of {} bytes", length, bytes.len()),
+                        );
+                    }
+                    self.utf8(location, i as u16 + 1);
+                }
+                ConstantPoolInfo::ClassInfo { name_index } => {
+                    if let Some(name) = self.utf8(location, *name_index) {
+                        let valid = if name.starts_with('[') {
+                            is_field_descriptor(&name)
+                        } else {
+                            is_binary_name(&name)
+                        };
+                        if !valid {
+                            self.report(location, format!("Illegal class name \"{}\"", name));
+                        }
+                    }
+                }
+                ConstantPoolInfo::StringInfo {
+                    string_index: index,
+                }
+                | ConstantPoolInfo::ModuleInfo { name_index: index }
+                | ConstantPoolInfo::PackageInfo { name_index: index } => {
+                    self.utf8(location, *index);
+                }
+                ConstantPoolInfo::FieldrefInfo {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.class(location, *class_index);
+                    self.name_and_type(location, *name_and_type_index, false);
+                }
+                ConstantPoolInfo::MethodrefInfo {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantPoolInfo::InterfaceMethodrefInfo {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.class(location, *class_index);
+                    self.name_and_type(location, *name_and_type_index, true);
+                }
+                ConstantPoolInfo::NameAndTypeInfo {
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.utf8(location, *name_index);
+                    self.utf8(location, *descriptor_index);
+                }
+                ConstantPoolInfo::MethodHandleInfo {
+                    reference_kind,
+                    reference_index,
+                } => self.check_method_handle(location, *reference_kind, *reference_index),
+                ConstantPoolInfo::MethodTypeInfo { descriptor_index } => {
+                    if let Some(descriptor) = self.utf8(location, *descriptor_index) {
+                        self.method_descriptor(location, &descriptor);
+                    }
+                }
+                ConstantPoolInfo::DynamicInfo {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.bootstrap_method(location, *bootstrap_method_attr_index);
+                    self.name_and_type(location, *name_and_type_index, false);
+                }
+                ConstantPoolInfo::InvokeDynamicInfo {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.bootstrap_method(location, *bootstrap_method_attr_index);
+                    self.name_and_type(location, *name_and_type_index, true);
+                }
+                ConstantPoolInfo::IntegerInfo { .. }
+                | ConstantPoolInfo::FloatInfo { .. }
+                | ConstantPoolInfo::LongInfo { .. }
+                | ConstantPoolInfo::DoubleInfo { .. }
+                | ConstantPoolInfo::Unusable => {}
+            }
+        }
+    }
+
+    /// The kind of a method handle determines the kind of the entry that it
+    /// refers to ([`$4.4.8`]).
+    ///
+    /// [`$4.4.8`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.8
+    fn check_method_handle(&mut self, location: &str, kind: ReferenceKind, index: u16) {
+        // interface methods can be invoked statically and specially since
+        // version 52
+        let interface_methods = self.major_version >= 52;
+        let (expected, is_expected): (_, fn(&ConstantPoolInfo, bool) -> bool) = match kind {
+            ReferenceKind::GetField
+            | ReferenceKind::GetStatic
+            | ReferenceKind::PutField
+            | ReferenceKind::PutStatic => ("Fieldref", |info, _| {
+                matches!(info, ConstantPoolInfo::FieldrefInfo { .. })
+            }),
+            ReferenceKind::InvokeVirtual | ReferenceKind::NewInvokeSpecial => {
+                ("Methodref", |info, _| {
+                    matches!(info, ConstantPoolInfo::MethodrefInfo { .. })
+                })
+            }
+            ReferenceKind::InvokeStatic | ReferenceKind::InvokeSpecial => {
+                ("Methodref", |info, interface_methods| {
+                    matches!(info, ConstantPoolInfo::MethodrefInfo { .. })
+                        || (interface_methods
+                            && matches!(info, ConstantPoolInfo::InterfaceMethodrefInfo { .. }))
+                })
+            }
+            ReferenceKind::InvokeInterface => ("InterfaceMethodref", |info, _| {
+                matches!(info, ConstantPoolInfo::InterfaceMethodrefInfo { .. })
+            }),
+        };
+        self.entry(location, index, expected, |info| {
+            is_expected(info, interface_methods)
+        });
+    }
+
+    fn check_class(&mut self, class: &ClassFile) {
+        self.class("this_class", class.this_class);
+        // only java/lang/Object and module descriptors have no superclass,
+        // which linking checks since it needs the name
+        self.optional_class("super_class", class.super_class);
+        for index in &class.interfaces {
+            self.class("interfaces", *index);
+        }
+        if class.access_flags.contains(ClassAccessFlags::INTERFACE)
+            && !class.access_flags.contains(ClassAccessFlags::ABSTRACT)
+        {
+            self.report(
+                "class",
+                "Illegal class modifiers: interfaces must be abstract".to_string(),
+            );
+        }
+    }
+
+    fn check_field(&mut self, field: &FieldInfo) {
+        let name = self.utf8("field", field.name_index);
+        let descriptor = self.utf8("field", field.descriptor_index);
+        let location = &match (&name, &descriptor) {
+            (Some(name), Some(descriptor)) => format!("field {} {}", name, descriptor),
+            _ => "field".to_string(),
+        };
+        if let Some(name) = &name {
+            self.field_name(location, name);
+        }
+        if let Some(descriptor) = &descriptor {
+            self.field_descriptor(location, descriptor);
+        }
+
+        for attribute in &field.attributes {
+            self.check_attribute(location, attribute);
+            // only static fields are initialized with a ConstantValue, it is
+            // ignored for others
+            if let (
+                AttributeInfo::ConstantValue {
+                    constantvalue_index,
+                    ..
+                },
+                Some(descriptor),
+                true,
+            ) = (
+                attribute,
+                &descriptor,
+                field.access_flags.contains(FieldAccessFlags::STATIC),
+            ) {
+                self.check_constant_value(location, descriptor, *constantvalue_index);
+            }
+        }
+    }
+
+    /// The constant must have the type of the field ([`$4.7.2`]).
+    ///
+    /// [`$4.7.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.2
+    fn check_constant_value(&mut self, location: &str, descriptor: &str, index: u16) {
+        let (expected, is_expected): (_, fn(&ConstantPoolInfo) -> bool) = match descriptor {
+            "I" | "S" | "C" | "B" | "Z" => ("Integer", |info| info.as_i32().is_some()),
+            "F" => ("Float", |info| info.as_f32().is_some()),
+            "J" => ("Long", |info| info.as_i64().is_some()),
+            "D" => ("Double", |info| info.as_f64().is_some()),
+            "Ljava/lang/String;" => ("String", |info| {
+                matches!(info, ConstantPoolInfo::StringInfo { .. })
+            }),
+            _ => {
+                return self.report(
+                    location,
+                    format!(
+                        "Inconsistent constant value type for field of type {}",
+                        descriptor
+                    ),
+                )
+            }
+        };
+        self.entry(location, index, expected, is_expected);
+    }
+
+    fn check_method(&mut self, method: &MethodInfo) {
+        let name = self.utf8("method", method.name_index);
+        let descriptor = self.utf8("method", method.descriptor_index);
+        let location = &match (&name, &descriptor) {
+            (Some(name), Some(descriptor)) => format!("method {}{}", name, descriptor),
+            _ => "method".to_string(),
+        };
+        if let Some(name) = &name {
+            self.method_name(location, name);
+        }
+        if let Some(descriptor) = &descriptor {
+            self.method_descriptor(location, descriptor);
+        }
+
+        let codes = method
+            .attributes
+            .iter()
+            .filter(|a| matches!(a, AttributeInfo::Code { .. }))
+            .count();
+        let has_code = !method
+            .access_flags
+            .intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::NATIVE);
+        match (has_code, codes) {
+            (true, 0) => self.report(
+                location,
+                "Absent Code attribute in method that is not native or abstract".to_string(),
+            ),
+            (false, 1..) => self.report(
+                location,
+                "Code attribute in native or abstract methods".to_string(),
+            ),
+            (_, 2..) => self.report(location, "Multiple Code attributes in method".to_string()),
+            _ => {}
+        }
+
+        for attribute in &method.attributes {
+            self.check_attribute(location, attribute);
+        }
+    }
+
+    /// Checks the name and length of the attribute, and the indices and
+    /// offsets in the attributes that the JVM uses.
+    fn check_attribute(&mut self, location: &str, attribute: &AttributeInfo) {
+        let (name_index, declared_length) = attribute.header();
+        let name = match self.utf8(location, name_index) {
+            Some(name) => name,
+            None => return,
+        };
+        let location = &format!("{} attribute of {}", name, location);
+        let attribute = match attribute.materialize(self.cp) {
+            Ok(attribute) => attribute,
+            Err(e) => return self.report(location, format!("Malformed contents: {:?}", e)),
+        };
+
+        // the writer computes the length from the contents, and the attribute
+        // starts with its name index and length
+        let mut bytes = vec![];
+        match attribute.write(&mut bytes) {
+            Ok(()) if bytes.len() - 6 != declared_length as usize => self.report(
+                location,
+                format!(
+                    "Wrong attribute length {}, the contents are {} bytes",
+                    declared_length,
+                    bytes.len() - 6
+                ),
+            ),
+            Ok(()) => {}
+            Err(e) => self.report(location, e.to_string()),
+        }
+
+        match attribute {
+            AttributeInfo::Code {
+                code_length,
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                if *code_length == 0 || *code_length > u16::MAX as u32 {
+                    self.report(
+                        location,
+                        format!("Invalid method Code length {}", code_length),
+                    );
+                } else if *code_length as usize != code.len() {
+                    self.report(
+                        location,
+                        format!("Code length {} of {} bytes", code_length, code.len()),
+                    );
+                }
+                for entry in exception_table {
+                    let code_length = code.len();
+                    if entry.start_pc >= entry.end_pc
+                        || entry.end_pc as usize > code_length
+                        || entry.handler_pc as usize >= code_length
+                    {
+                        self.report(
+                            location,
+                            format!(
+                                "Illegal exception table range [{}, {}) with handler {}",
+                                entry.start_pc, entry.end_pc, entry.handler_pc
+                            ),
+                        );
+                    }
+                    self.optional_class(location, entry.catch_type);
+                }
+                for attribute in attributes {
+                    self.check_attribute(location, attribute);
+                }
+            }
+            AttributeInfo::Exceptions {
+                exception_index_table: classes,
+                ..
+            }
+            | AttributeInfo::NestMembers { classes, .. }
+            | AttributeInfo::PermittedSubclasses { classes, .. } => {
+                for index in classes {
+                    self.class(location, *index);
+                }
+            }
+            AttributeInfo::NestHost {
+                host_class_index, ..
+            } => self.class(location, *host_class_index),
+            AttributeInfo::InnerClasses { classes, .. } => {
+                for class in classes {
+                    self.class(location, class.inner_class_info_index);
+                    self.optional_class(location, class.outer_class_info_index);
+                    if class.inner_name_index != 0 {
+                        self.utf8(location, class.inner_name_index);
+                    }
+                }
+            }
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+                ..
+            } => {
+                self.class(location, *class_index);
+                if *method_index != 0 {
+                    self.name_and_type(location, *method_index, true);
+                }
+            }
+            AttributeInfo::Signature {
+                signature_index: index,
+                ..
+            }
+            | AttributeInfo::SourceFile {
+                sourcefile_index: index,
+                ..
+            } => {
+                self.utf8(location, *index);
+            }
+            AttributeInfo::BootstrapMethods {
+                bootstrap_methods, ..
+            } => {
+                for method in bootstrap_methods {
+                    self.check_bootstrap_method(location, method);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_bootstrap_method(&mut self, location: &str, method: &BootstrapMethod) {
+        self.entry(
+            location,
+            method.bootstrap_method_ref,
+            "MethodHandle",
+            |info| matches!(info, ConstantPoolInfo::MethodHandleInfo { .. }),
+        );
+        for argument in &method.bootstrap_arguments {
+            // only loadable constants can be arguments ($4.4-C)
+            self.entry(location, *argument, "loadable", |info| {
+                !matches!(
+                    info,
+                    ConstantPoolInfo::Utf8Info { .. }
+                        | ConstantPoolInfo::NameAndTypeInfo { .. }
+                        | ConstantPoolInfo::FieldrefInfo { .. }
+                        | ConstantPoolInfo::MethodrefInfo { .. }
+                        | ConstantPoolInfo::InterfaceMethodrefInfo { .. }
+                        | ConstantPoolInfo::InvokeDynamicInfo { .. }
+                        | ConstantPoolInfo::ModuleInfo { .. }
+                        | ConstantPoolInfo::PackageInfo { .. }
+                        | ConstantPoolInfo::Unusable
+                )
+            });
+        }
+    }
+}
+
+impl AttributeInfo {
+    /// The name index and the declared length of this attribute.
+    pub(super) fn header(&self) -> (u16, u32) {
+        match self {
+            Self::ConstantValue {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Code {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::StackMapTable {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Exceptions {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::InnerClasses {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::EnclosingMethod {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Synthetic {
+                attribute_name_index,
+                attribute_length,
+            }
+            | Self::Signature {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::SourceFile {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::SourceDebugExtension {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::LineNumberTable {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::LocalVariableTable {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::LocalVariableTypeTable {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Deprecated {
+                attribute_name_index,
+                attribute_length,
+            }
+            | Self::RuntimeVisibleAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::RuntimeInvisibleAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::RuntimeVisibleParameterAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::RuntimeInvisibleParameterAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::RuntimeVisibleTypeAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::RuntimeInvisibleTypeAnnotations {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::AnnotationDefault {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::BootstrapMethods {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::MethodParameters {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Module {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::ModulePackages {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::ModuleMainClass {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::NestHost {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::NestMembers {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Record {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::PermittedSubclasses {
+                attribute_name_index,
+                attribute_length,
+                ..
+            }
+            | Self::Unknown {
+                name_index: attribute_name_index,
+                length: attribute_length,
+                ..
+            } => (*attribute_name_index, *attribute_length),
+            Self::Lazy(lazy) => (lazy.name_index(), lazy.length()),
+        }
+    }
+}
+
+/// Whether the name is an unqualified name ([`$4.2.2`]), like the names of
+/// fields.
+///
+/// [`$4.2.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.2
+fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+/// Whether the name is a valid method name, which is an unqualified name
+/// without `<` and `>` unless it is the name of an initializer.
+fn is_method_name(name: &str) -> bool {
+    name == "<init>"
+        || name == "<clinit>"
+        || (is_unqualified_name(name) && !name.contains(['<', '>']))
+}
+
+/// Whether the name is a binary name in its internal form ([`$4.2.1`]), e.g.
+/// `java/lang/Object`.
+///
+/// [`$4.2.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.1
+fn is_binary_name(name: &str) -> bool {
+    name.split('/').all(is_unqualified_name)
+}
+
+fn is_field_descriptor(descriptor: &str) -> bool {
+    skip_field_type(descriptor) == Some("")
+}
+
+fn is_method_descriptor(descriptor: &str) -> bool {
+    let mut rest = match descriptor.strip_prefix('(') {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let mut slots = 0;
+    while !rest.starts_with(')') {
+        slots += if rest.starts_with(['J', 'D']) { 2 } else { 1 };
+        rest = match skip_field_type(rest) {
+            Some(rest) => rest,
+            None => return false,
+        };
+    }
+    let return_type = &rest[1..];
+    slots <= MAX_PARAMETER_SLOTS && (return_type == "V" || is_field_descriptor(return_type))
+}
+
+/// Returns the rest of the string after the field descriptor at its start
+/// ([`$4.3.2`]), or [`None`] if it doesn't start with one.
+///
+/// [`$4.3.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+fn skip_field_type(s: &str) -> Option<&str> {
+    let dimensions = s.bytes().take_while(|b| *b == b'[').count();
+    if dimensions > MAX_ARRAY_DIMENSIONS {
+        return None;
+    }
+    let s = &s[dimensions..];
+    match s.as_bytes().first()? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => Some(&s[1..]),
+        b'L' => {
+            let end = s.find(';')?;
+            is_binary_name(&s[1..end]).then(|| &s[end + 1..])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::parse;
+    use crate::classfile::ExceptionTableEntry;
+    use crate::classfile::ParseMode;
+
+    fn messages(class_file: &ClassFile) -> Vec<String> {
+        class_file
+            .validate()
+            .iter()
+            .map(FormatViolation::to_string)
+            .collect()
+    }
+
+    fn method_mut<'a>(class_file: &'a mut ClassFile, name: &str) -> &'a mut MethodInfo {
+        let cp = &class_file.cp_info;
+        class_file
+            .methods
+            .iter_mut()
+            .find(|m| cp.get_utf8(m.name_index).unwrap() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_valid() {
+        for name in ["Foo", "Constants", "Formats", "Formats$Inner"] {
+            assert_eq!(
+                Vec::<String>::new(),
+                messages(&parse(name, ParseMode::Eager)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_constant_pool() {
+        let mut class_file = parse("Foo", ParseMode::Eager);
+        // the Methodref #1 refers to the class #2 and the NameAndType #3
+        class_file.cp_info.items[0] = ConstantPoolInfo::MethodrefInfo {
+            class_index: 4,
+            name_and_type_index: 300,
+        };
+        // the class #7 is named by #8
+        class_file.cp_info.items[7] = ConstantPoolInfo::utf8("not a.class");
+        // #6 is the descriptor ()V of both methods
+        class_file.cp_info.items[5] = ConstantPoolInfo::utf8("(I");
+        assert_eq!(
+            vec![
+                "constant pool #1: Constant pool index 4 is not a Class entry",
+                "constant pool #1: Invalid constant pool index 300",
+                "constant pool #7: Illegal class name \"not a.class\"",
+                "method <init>(I: Illegal method signature \"(I\"",
+                "method bar(I: Illegal method signature \"(I\"",
+            ],
+            messages(&class_file)
+        );
+
+        let mut class_file = parse("Foo", ParseMode::Eager);
+        class_file.this_class = 4;
+        class_file.super_class = 0;
+        assert_eq!(
+            vec!["this_class: Constant pool index 4 is not a Class entry"],
+            messages(&class_file)
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let mut class_file = parse("Formats", ParseMode::Eager);
+        // a method without code, and an abstract one with it
+        let code = method_mut(&mut class_file, "guarded").attributes.remove(0);
+        method_mut(&mut class_file, "missing").attributes.push(code);
+        assert_eq!(
+            vec![
+                "method missing()V: Code attribute in native or abstract methods",
+                "method guarded(LFormats$Inner;)I: Absent Code attribute in method that is not native or abstract",
+            ],
+            messages(&class_file)
+        );
+
+        let mut class_file = parse("Formats", ParseMode::Eager);
+        let method = method_mut(&mut class_file, "guarded");
+        match &mut method.attributes[0] {
+            AttributeInfo::Code {
+                attribute_length,
+                exception_table,
+                ..
+            } => {
+                *attribute_length += 1;
+                exception_table[0].end_pc = exception_table[0].start_pc;
+                exception_table.push(ExceptionTableEntry {
+                    start_pc: 0,
+                    end_pc: 1,
+                    handler_pc: 1000,
+                    catch_type: 1,
+                });
+            }
+            _ => panic!("not a code attribute"),
+        }
+        let violations = messages(&class_file);
+        assert_eq!(4, violations.len(), "{:?}", violations);
+        assert!(violations[0].starts_with(
+            "Code attribute of method guarded(LFormats$Inner;)I: Wrong attribute length"
+        ));
+        assert!(violations[1].ends_with("Illegal exception table range [0, 0) with handler 8"));
+        assert!(violations[2].ends_with("Illegal exception table range [0, 1) with handler 1000"));
+        assert!(violations[3].ends_with("Constant pool index 1 is not a Class entry"));
+    }
+
+    #[test]
+    fn test_descriptors() {
+        for valid in ["I", "[[J", "Ljava/lang/Object;", "[Ljava/util/Map$Entry;"] {
+            assert!(is_field_descriptor(valid), "{}", valid);
+        }
+        for invalid in ["", "V", "L;", "Ljava/lang/Object", "La//b;", "II", "[V"] {
+            assert!(!is_field_descriptor(invalid), "{}", invalid);
+        }
+        for valid in ["()V", "(IJ[Ljava/lang/String;)Ljava/lang/Object;"] {
+            assert!(is_method_descriptor(valid), "{}", valid);
+        }
+        for invalid in ["V", "()", "(V)V", "(I)VV", "()Q"] {
+            assert!(!is_method_descriptor(invalid), "{}", invalid);
+        }
+        // 127 longs and an int are 255 slots
+        let max = format!("({}I)V", "J".repeat(127));
+        assert!(is_method_descriptor(&max));
+        assert!(!is_method_descriptor(&max.replace("I)", "II)")));
+
+        assert!(is_method_name("<init>"));
+        assert!(!is_method_name("<main>"));
+        assert!(!is_unqualified_name("a.b"));
+    }
+}
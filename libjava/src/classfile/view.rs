@@ -0,0 +1,520 @@
+use crate::classfile::{
+    flags, initial_capacity, mutf8, ClassFile, ClassFileError, ClassFileParseError,
+    ConstantPoolError, ConstantPoolInfo, ExceptionTableEntry, Version, MAX_NESTING_DEPTH,
+};
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// A class file that borrows the contents of its `CONSTANT_Utf8` entries and
+/// attributes from the bytes that it was parsed from, see
+/// [`ClassFile::parse_bytes`].
+#[derive(Eq, PartialEq, Debug)]
+pub struct ClassFileView<'a> {
+    bytes: &'a [u8],
+    version: Version,
+    cp_info: ConstantPoolView<'a>,
+    access_flags: flags::ClassAccessFlags,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldView<'a>>,
+    methods: Vec<MethodView<'a>>,
+    attributes: Vec<AttributeView<'a>>,
+}
+
+/// The constant pool of a [`ClassFileView`], laid out like a
+/// [`ConstantPool`](crate::classfile::ConstantPool).
+#[derive(Eq, PartialEq, Debug)]
+pub struct ConstantPoolView<'a> {
+    items: Vec<ConstantView<'a>>,
+}
+
+/// An entry of a [`ConstantPoolView`].
+#[derive(Eq, PartialEq, Debug)]
+pub enum ConstantView<'a> {
+    /// The Modified UTF-8 bytes of a `CONSTANT_Utf8` entry.
+    Utf8(&'a [u8]),
+    /// Any other entry, which only consists of numbers and indices.
+    Info(ConstantPoolInfo),
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub struct FieldView<'a> {
+    access_flags: flags::FieldAccessFlags,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<AttributeView<'a>>,
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub struct MethodView<'a> {
+    access_flags: flags::MethodAccessFlags,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<AttributeView<'a>>,
+}
+
+/// An attribute of a [`ClassFileView`]. Only `Code` is parsed, every other
+/// attribute is kept as its raw contents.
+#[derive(Eq, PartialEq, Debug)]
+pub enum AttributeView<'a> {
+    Code {
+        attribute_name_index: u16,
+        max_stack: u16,
+        max_locals: u16,
+        code: &'a [u8],
+        exception_table: Vec<ExceptionTableEntry>,
+        attributes: Vec<AttributeView<'a>>,
+    },
+    Raw {
+        attribute_name_index: u16,
+        /// The contents of the attribute, without the name index and length.
+        info: &'a [u8],
+    },
+}
+
+impl ClassFile {
+    /// Parses a class file that is completely contained in the given bytes,
+    /// like [`ClassFile::from_bytes`], but without copying the strings of the
+    /// constant pool and the code of the methods: the returned view borrows
+    /// them from the bytes. Attributes other than `Code` aren't parsed.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<ClassFileView<'_>, ClassFileParseError> {
+        let mut source = bytes;
+        if read_u32(&mut source)? != 0xCAFEBABE {
+            return Err(ClassFileParseError::InvalidMagicValue);
+        }
+        let minor_version = read_u16(&mut source)?;
+        let major_version = read_u16(&mut source)?;
+        let cp = ConstantPoolView::parse(&mut source)?;
+
+        let access_flags = flags::ClassAccessFlags::from_bits_truncate(read_u16(&mut source)?);
+        let this_class = read_u16(&mut source)?;
+        let super_class = read_u16(&mut source)?;
+
+        let interfaces_count = read_u16(&mut source)?;
+        let mut interfaces = Vec::with_capacity(initial_capacity(interfaces_count as usize));
+        for _ in 0..interfaces_count {
+            interfaces.push(read_u16(&mut source)?);
+        }
+
+        let fields_count = read_u16(&mut source)?;
+        let mut fields = Vec::with_capacity(initial_capacity(fields_count as usize));
+        for _ in 0..fields_count {
+            let (access_flags, name_index, descriptor_index, attributes) =
+                parse_member(&cp, &mut source)?;
+            fields.push(FieldView {
+                access_flags: flags::FieldAccessFlags::from_bits_truncate(access_flags),
+                name_index,
+                descriptor_index,
+                attributes,
+            });
+        }
+
+        let methods_count = read_u16(&mut source)?;
+        let mut methods = Vec::with_capacity(initial_capacity(methods_count as usize));
+        for _ in 0..methods_count {
+            let (access_flags, name_index, descriptor_index, attributes) =
+                parse_member(&cp, &mut source)?;
+            methods.push(MethodView {
+                access_flags: flags::MethodAccessFlags::from_bits_truncate(access_flags),
+                name_index,
+                descriptor_index,
+                attributes,
+            });
+        }
+
+        let attributes = parse_attributes(&cp, &mut source, 0)?;
+        if !source.is_empty() {
+            return Err(ClassFileParseError::TrailingBytes);
+        }
+
+        let is_class_info = |index: u16| cp.get_class_name(index).is_ok();
+        if !is_class_info(this_class)
+            || (super_class != 0 && !is_class_info(super_class))
+            || !interfaces.iter().all(|index| is_class_info(*index))
+        {
+            return Err(ClassFileParseError::InvalidClassIndex);
+        }
+
+        Ok(ClassFileView {
+            bytes,
+            version: Version::new(major_version, minor_version),
+            cp_info: cp,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+}
+
+impl<'a> ClassFileView<'a> {
+    /// The bytes that the class file was parsed from.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn constant_pool(&self) -> &ConstantPoolView<'a> {
+        &self.cp_info
+    }
+
+    pub fn access_flags(&self) -> flags::ClassAccessFlags {
+        self.access_flags
+    }
+
+    /// Returns the name of the class defined by this class file.
+    pub fn this_class(&self) -> Cow<'a, str> {
+        self.cp_info
+            .get_class_name(self.this_class)
+            .expect("this_class is not a class info")
+    }
+
+    /// Returns the name of the direct superclass, or [`None`] if there is
+    /// none, see [`ClassFile::super_class`].
+    pub fn super_class(&self) -> Option<Cow<'a, str>> {
+        if self.super_class == 0 {
+            return None;
+        }
+        self.cp_info.get_class_name(self.super_class).ok()
+    }
+
+    /// Returns the names of the direct superinterfaces, in the order of the
+    /// class file.
+    pub fn interfaces(&self) -> Vec<Cow<'a, str>> {
+        self.interfaces
+            .iter()
+            .filter_map(|index| self.cp_info.get_class_name(*index).ok())
+            .collect()
+    }
+
+    pub fn fields(&self) -> &[FieldView<'a>] {
+        &self.fields
+    }
+
+    pub fn methods(&self) -> &[MethodView<'a>] {
+        &self.methods
+    }
+
+    pub fn attributes(&self) -> &[AttributeView<'a>] {
+        &self.attributes
+    }
+
+    /// Parses the bytes into an owned class file, with all of its attributes.
+    pub fn to_class_file(&self) -> Result<ClassFile, ClassFileError> {
+        ClassFile::from_bytes(self.bytes)
+    }
+}
+
+impl<'a> ConstantPoolView<'a> {
+    fn parse(source: &mut &'a [u8]) -> Result<Self, ClassFileParseError> {
+        let constant_pool_count = read_u16(source)?;
+        if constant_pool_count == 0 {
+            return Err(ClassFileParseError::InvalidConstantPoolCount);
+        }
+        let mut items = Vec::with_capacity(initial_capacity(constant_pool_count as usize - 1));
+        while items.len() < constant_pool_count as usize - 1 {
+            if source.first() == Some(&1) {
+                *source = &source[1..];
+                let length = read_u16(source)?;
+                items.push(ConstantView::Utf8(take(source, length as usize)?));
+                continue;
+            }
+            let info = ConstantPoolInfo::parse(source)?;
+            let slots = info.slots();
+            items.push(ConstantView::Info(info));
+            if slots == 2 {
+                items.push(ConstantView::Info(ConstantPoolInfo::Unusable));
+            }
+        }
+        // the last entry can't be a long or double constant that takes up an
+        // index past the end of the pool
+        if items.len() != constant_pool_count as usize - 1 {
+            return Err(ClassFileParseError::InvalidConstantPoolCount);
+        }
+        Ok(Self { items })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ConstantView<'a>> {
+        self.items.iter()
+    }
+
+    /// Returns the entry at the given index, which is 1-based like all
+    /// indices into the constant pool in a class file.
+    pub fn get(&self, index: u16) -> Result<&ConstantView<'a>, ConstantPoolError> {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.items.get(i as usize))
+            .ok_or(ConstantPoolError::IndexOutOfBounds(index))
+    }
+
+    /// Returns the string of the `CONSTANT_Utf8` entry at the given index,
+    /// which borrows from the bytes of the class file unless it contains null
+    /// or supplementary characters.
+    pub fn get_utf8(&self, index: u16) -> Result<Cow<'a, str>, ConstantPoolError> {
+        match self.get(index)? {
+            ConstantView::Utf8(bytes) => {
+                mutf8::decode(bytes).or(Err(ConstantPoolError::InvalidUtf8(index)))
+            }
+            _ => Err(ConstantPoolError::unexpected(index, "Utf8")),
+        }
+    }
+
+    /// Returns the name of the `CONSTANT_Class` entry at the given index.
+    pub fn get_class_name(&self, index: u16) -> Result<Cow<'a, str>, ConstantPoolError> {
+        match self.get(index)? {
+            ConstantView::Info(ConstantPoolInfo::ClassInfo { name_index }) => {
+                self.get_utf8(*name_index)
+            }
+            _ => Err(ConstantPoolError::unexpected(index, "Class")),
+        }
+    }
+
+    /// Returns the string of the `CONSTANT_String` entry at the given index.
+    pub fn get_string(&self, index: u16) -> Result<Cow<'a, str>, ConstantPoolError> {
+        match self.get(index)? {
+            ConstantView::Info(ConstantPoolInfo::StringInfo { string_index }) => {
+                self.get_utf8(*string_index)
+            }
+            _ => Err(ConstantPoolError::unexpected(index, "String")),
+        }
+    }
+}
+
+impl<'a> FieldView<'a> {
+    pub fn access_flags(&self) -> flags::FieldAccessFlags {
+        self.access_flags
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeView<'a>] {
+        &self.attributes
+    }
+}
+
+impl<'a> MethodView<'a> {
+    pub fn access_flags(&self) -> flags::MethodAccessFlags {
+        self.access_flags
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeView<'a>] {
+        &self.attributes
+    }
+
+    /// Returns the bytecode of the method, or [`None`] if it is abstract or
+    /// native.
+    pub fn code(&self) -> Option<&'a [u8]> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                AttributeView::Code { code, .. } => Some(*code),
+                _ => None,
+            })
+    }
+}
+
+impl<'a> AttributeView<'a> {
+    pub fn attribute_name_index(&self) -> u16 {
+        match self {
+            AttributeView::Code {
+                attribute_name_index,
+                ..
+            }
+            | AttributeView::Raw {
+                attribute_name_index,
+                ..
+            } => *attribute_name_index,
+        }
+    }
+
+    /// Parses an attribute that is nested in `depth` other attributes.
+    fn parse(
+        cp: &ConstantPoolView<'a>,
+        source: &mut &'a [u8],
+        depth: usize,
+    ) -> Result<Self, ClassFileParseError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ClassFileParseError::NestingTooDeep);
+        }
+        let attribute_name_index = read_u16(source)?;
+        let attribute_length = read_u32(source)?;
+        let mut info = take(source, attribute_length as usize)?;
+        match cp.get(attribute_name_index) {
+            Ok(ConstantView::Utf8(b"Code")) => {
+                let max_stack = read_u16(&mut info)?;
+                let max_locals = read_u16(&mut info)?;
+                let code_length = read_u32(&mut info)?;
+                let code = take(&mut info, code_length as usize)?;
+                let exception_table_length = read_u16(&mut info)?;
+                let mut exception_table =
+                    Vec::with_capacity(initial_capacity(exception_table_length as usize));
+                for _ in 0..exception_table_length {
+                    exception_table.push(ExceptionTableEntry::parse(&mut info)?);
+                }
+                let attributes = parse_attributes(cp, &mut info, depth + 1)?;
+                // the contents are read from the attribute length's worth of
+                // bytes, so they can't end after it, only before
+                if !info.is_empty() {
+                    return Err(ClassFileParseError::InvalidAttributeLength);
+                }
+                Ok(AttributeView::Code {
+                    attribute_name_index,
+                    max_stack,
+                    max_locals,
+                    code,
+                    exception_table,
+                    attributes,
+                })
+            }
+            Ok(ConstantView::Utf8(_)) => Ok(AttributeView::Raw {
+                attribute_name_index,
+                info,
+            }),
+            _ => Err(ClassFileParseError::InvalidAttributeNameIndex),
+        }
+    }
+}
+
+/// Parses the access flags, name and descriptor indices and attributes of a
+/// field or method.
+fn parse_member<'a>(
+    cp: &ConstantPoolView<'a>,
+    source: &mut &'a [u8],
+) -> Result<(u16, u16, u16, Vec<AttributeView<'a>>), ClassFileParseError> {
+    let access_flags = read_u16(source)?;
+    let name_index = read_u16(source)?;
+    let descriptor_index = read_u16(source)?;
+    let attributes = parse_attributes(cp, source, 0)?;
+    Ok((access_flags, name_index, descriptor_index, attributes))
+}
+
+/// Parses an attribute count followed by that many attributes.
+fn parse_attributes<'a>(
+    cp: &ConstantPoolView<'a>,
+    source: &mut &'a [u8],
+    depth: usize,
+) -> Result<Vec<AttributeView<'a>>, ClassFileParseError> {
+    let attributes_count = read_u16(source)?;
+    let mut attributes = Vec::with_capacity(initial_capacity(attributes_count as usize));
+    for _ in 0..attributes_count {
+        attributes.push(AttributeView::parse(cp, source, depth)?);
+    }
+    Ok(attributes)
+}
+
+/// Splits off the next `length` bytes of the source.
+fn take<'a>(source: &mut &'a [u8], length: usize) -> Result<&'a [u8], ClassFileParseError> {
+    if source.len() < length {
+        return Err(ClassFileParseError::UnexpectedEOF);
+    }
+    let (bytes, rest) = source.split_at(length);
+    *source = rest;
+    Ok(bytes)
+}
+
+fn read_u16(source: &mut &[u8]) -> Result<u16, ClassFileParseError> {
+    Ok(u16::from_be_bytes(take(source, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(source: &mut &[u8]) -> Result<u32, ClassFileParseError> {
+    Ok(u32::from_be_bytes(take(source, 4)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::read;
+    use crate::classfile::AttributeInfo;
+
+    #[test]
+    fn test_parse_bytes() {
+        for name in ["Foo", "Formats", "Constants"] {
+            let bytes = read(name);
+            let view = ClassFile::parse_bytes(&bytes).unwrap();
+            let class_file = view.to_class_file().unwrap();
+            assert_eq!(class_file.this_class(), view.this_class());
+            assert_eq!(
+                class_file.super_class().as_deref(),
+                view.super_class().as_deref()
+            );
+            assert_eq!(class_file.constant_pool().len(), view.constant_pool().len());
+            for index in 1..=view.constant_pool().len() as u16 {
+                assert_eq!(
+                    class_file.constant_pool().get_utf8(index).ok(),
+                    view.constant_pool().get_utf8(index).ok()
+                );
+            }
+            assert_eq!(class_file.methods().len(), view.methods().len());
+            for (method, view) in class_file.methods().iter().zip(view.methods()) {
+                assert_eq!(method.name_index(), view.name_index());
+                assert_eq!(method.access_flags(), view.access_flags());
+                let code = method
+                    .attributes()
+                    .iter()
+                    .find_map(|attribute| match attribute {
+                        AttributeInfo::Code { code, .. } => Some(code.as_slice()),
+                        _ => None,
+                    });
+                assert_eq!(code, view.code());
+            }
+        }
+    }
+
+    #[test]
+    fn test_borrowed_strings() {
+        let bytes = read("Foo");
+        let view = ClassFile::parse_bytes(&bytes).unwrap();
+        let Cow::Borrowed(name) = view.this_class() else {
+            panic!("expected the name to be borrowed");
+        };
+        assert!(bytes.as_ptr_range().contains(&name.as_ptr()));
+        let code = view.methods()[0].code().unwrap();
+        assert!(bytes.as_ptr_range().contains(&code.as_ptr()));
+    }
+
+    #[test]
+    fn test_malformed_bytes() {
+        let bytes = read("Foo");
+        assert_eq!(
+            Err(ClassFileParseError::TrailingBytes),
+            ClassFile::parse_bytes(&[&bytes[..], &[0]].concat())
+        );
+        for length in 0..bytes.len() {
+            assert_eq!(
+                Err(ClassFileParseError::UnexpectedEOF),
+                ClassFile::parse_bytes(&bytes[..length]),
+                "truncated to {} bytes",
+                length
+            );
+        }
+    }
+}
@@ -0,0 +1,783 @@
+//! A class file is walked with [`ClassFile::accept`], which reports its parts
+//! as events to a [`ClassVisitor`]: the constant pool, the header, every field
+//! and method with a [`FieldVisitor`] or [`MethodVisitor`], the attributes,
+//! and the end. A [`ClassWriter`] builds a class file from the events again.
+//!
+//! Transformations are visitors between the two that forward the events they
+//! don't change to the next visitor, which is what the default implementations
+//! of the traits do, e.g. to strip the debugging information:
+//!
+//! ```
+//! # use libjava::classfile::visitor::*;
+//! # use libjava::classfile::AttributeInfo;
+//! # use libjava::classfile::flags::MethodAccessFlags;
+//! struct StripDebug<'a>(&'a mut dyn ClassVisitor);
+//!
+//! impl ClassVisitor for StripDebug<'_> {
+//!     fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+//!         Some(self.0)
+//!     }
+//!
+//!     fn visit_method(
+//!         &mut self,
+//!         access_flags: MethodAccessFlags,
+//!         name: &str,
+//!         descriptor: &str,
+//!     ) -> Option<Box<dyn MethodVisitor + '_>> {
+//!         let next = self.0.visit_method(access_flags, name, descriptor)?;
+//!         Some(Box::new(StripDebugMethod(next)))
+//!     }
+//! }
+//!
+//! struct StripDebugMethod<'a>(Box<dyn MethodVisitor + 'a>);
+//!
+//! impl MethodVisitor for StripDebugMethod<'_> {
+//!     fn next(&mut self) -> Option<&mut dyn MethodVisitor> {
+//!         Some(self.0.as_mut())
+//!     }
+//!
+//!     fn visit_code_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+//!         if name != "LineNumberTable" && name != "LocalVariableTable" {
+//!             self.0.visit_code_attribute(name, attribute);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Attributes are visited as they are, so their indices refer to the constant
+//! pool that was visited first. A [`ClassWriter`] starts with that pool and
+//! only adds the entries that the names of the visited class, fields and
+//! methods need, so the indices remain valid.
+
+use crate::bytecode::Op;
+use crate::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use crate::classfile::{
+    AttributeInfo, ClassFile, ConstantPool, ConstantPoolError, ConstantPoolInfo,
+    ExceptionTableEntry, FieldInfo, MethodInfo, Version,
+};
+use crate::prelude::*;
+
+/// The contents of a `Code` attribute ([`$4.7.3`]) without its attributes,
+/// which are visited with [`MethodVisitor::visit_code_attribute`].
+///
+/// [`$4.7.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.3
+#[derive(Debug, Eq, PartialEq)]
+pub struct Code {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: Vec<u8>,
+    /// The decoded instructions of `code`, if the class file was parsed with
+    /// [`ClassFile::parse_decoded`]. A visitor that changes `code` has to
+    /// update or drop them.
+    pub ops: Option<Vec<(u32, Op)>>,
+    pub exception_table: Vec<ExceptionTableEntry>,
+}
+
+/// Receives the events of a class file, see the [module](self) documentation.
+/// The events are visited in this order: [`visit_constant_pool`], [`visit`],
+/// the fields, the methods, the attributes of the class and [`visit_end`].
+///
+/// Every method forwards the event to [`ClassVisitor::next`] by default.
+///
+/// [`visit_constant_pool`]: ClassVisitor::visit_constant_pool
+/// [`visit`]: ClassVisitor::visit
+/// [`visit_end`]: ClassVisitor::visit_end
+pub trait ClassVisitor {
+    /// The visitor that the default implementations forward the events to, or
+    /// [`None`] (the default) to drop them.
+    fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+        None
+    }
+
+    /// Visits the constant pool that the indices in the attributes refer to.
+    fn visit_constant_pool(&mut self, cp: &ConstantPool) {
+        if let Some(next) = self.next() {
+            next.visit_constant_pool(cp);
+        }
+    }
+
+    /// Visits the header of the class, with the binary names of the class,
+    /// its superclass and its superinterfaces, e.g. `java/lang/Object`.
+    fn visit(
+        &mut self,
+        version: Version,
+        access_flags: ClassAccessFlags,
+        name: &str,
+        super_name: Option<&str>,
+        interfaces: &[String],
+    ) {
+        if let Some(next) = self.next() {
+            next.visit(version, access_flags, name, super_name, interfaces);
+        }
+    }
+
+    /// Visits a field, and returns the visitor of its attributes, or [`None`]
+    /// to drop the field.
+    fn visit_field(
+        &mut self,
+        access_flags: FieldAccessFlags,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<Box<dyn FieldVisitor + '_>> {
+        self.next()?.visit_field(access_flags, name, descriptor)
+    }
+
+    /// Visits a method, and returns the visitor of its code and attributes,
+    /// or [`None`] to drop the method.
+    fn visit_method(
+        &mut self,
+        access_flags: MethodAccessFlags,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<Box<dyn MethodVisitor + '_>> {
+        self.next()?.visit_method(access_flags, name, descriptor)
+    }
+
+    /// Visits an attribute of the class with the given name, e.g.
+    /// `SourceFile`.
+    fn visit_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+        if let Some(next) = self.next() {
+            next.visit_attribute(name, attribute);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(next) = self.next() {
+            next.visit_end();
+        }
+    }
+}
+
+/// Receives the attributes of a field, see [`ClassVisitor::visit_field`].
+/// [`FieldVisitor::visit_end`] is visited last.
+pub trait FieldVisitor {
+    /// The visitor that the default implementations forward the events to, or
+    /// [`None`] (the default) to drop them.
+    fn next(&mut self) -> Option<&mut dyn FieldVisitor> {
+        None
+    }
+
+    fn visit_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+        if let Some(next) = self.next() {
+            next.visit_attribute(name, attribute);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(next) = self.next() {
+            next.visit_end();
+        }
+    }
+}
+
+/// Receives the code and the attributes of a method, see
+/// [`ClassVisitor::visit_method`]. The attributes are visited in the order of
+/// the class file, and the attributes of the `Code` attribute right after
+/// [`MethodVisitor::visit_code`]. [`MethodVisitor::visit_end`] is visited last.
+pub trait MethodVisitor {
+    /// The visitor that the default implementations forward the events to, or
+    /// [`None`] (the default) to drop them.
+    fn next(&mut self) -> Option<&mut dyn MethodVisitor> {
+        None
+    }
+
+    /// Visits the `Code` attribute, which abstract and native methods don't
+    /// have.
+    fn visit_code(&mut self, code: Code) {
+        if let Some(next) = self.next() {
+            next.visit_code(code);
+        }
+    }
+
+    /// Visits an attribute of the `Code` attribute, e.g. `LineNumberTable`.
+    fn visit_code_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+        if let Some(next) = self.next() {
+            next.visit_code_attribute(name, attribute);
+        }
+    }
+
+    /// Visits an attribute of the method other than `Code`, e.g. `Exceptions`.
+    fn visit_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+        if let Some(next) = self.next() {
+            next.visit_attribute(name, attribute);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(next) = self.next() {
+            next.visit_end();
+        }
+    }
+}
+
+/// An attribute with its name, or a `Code` attribute with the names of its
+/// attributes.
+enum Named {
+    Code(Code, Vec<(String, AttributeInfo)>),
+    Attribute(String, AttributeInfo),
+}
+
+impl ClassFile {
+    /// Walks this class file and visits its parts with the given visitor, see
+    /// the [module](self) documentation. Fails before anything is visited if
+    /// the name of the class, a field, a method or an attribute can't be
+    /// resolved from the constant pool.
+    pub fn accept(self, visitor: &mut dyn ClassVisitor) -> Result<(), ConstantPoolError> {
+        let cp = &self.cp_info;
+        let name = cp.get_class_name(self.this_class)?;
+        let super_name = match self.super_class {
+            0 => None,
+            index => Some(cp.get_class_name(index)?),
+        };
+        let interfaces = self
+            .interfaces
+            .iter()
+            .map(|index| Ok(cp.get_class_name(*index)?.into_owned()))
+            .collect::<Result<Vec<_>, ConstantPoolError>>()?;
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|f| {
+                Ok((
+                    f.access_flags,
+                    cp.get_utf8(f.name_index)?,
+                    cp.get_utf8(f.descriptor_index)?,
+                    named(cp, f.attributes)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, ConstantPoolError>>()?;
+        let methods = self
+            .methods
+            .into_iter()
+            .map(|m| {
+                Ok((
+                    m.access_flags,
+                    cp.get_utf8(m.name_index)?,
+                    cp.get_utf8(m.descriptor_index)?,
+                    named(cp, m.attributes)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, ConstantPoolError>>()?;
+        let attributes = named(cp, self.attributes)?;
+
+        visitor.visit_constant_pool(cp);
+        visitor.visit(
+            self.version,
+            self.access_flags,
+            &name,
+            super_name.as_deref(),
+            &interfaces,
+        );
+        for (access_flags, name, descriptor, attributes) in fields {
+            if let Some(mut field) = visitor.visit_field(access_flags, &name, &descriptor) {
+                for attribute in attributes {
+                    if let Named::Attribute(name, attribute) = attribute {
+                        field.visit_attribute(&name, attribute);
+                    }
+                }
+                field.visit_end();
+            }
+        }
+        for (access_flags, name, descriptor, attributes) in methods {
+            if let Some(mut method) = visitor.visit_method(access_flags, &name, &descriptor) {
+                for attribute in attributes {
+                    match attribute {
+                        Named::Code(code, attributes) => {
+                            method.visit_code(code);
+                            for (name, attribute) in attributes {
+                                method.visit_code_attribute(&name, attribute);
+                            }
+                        }
+                        Named::Attribute(name, attribute) => {
+                            method.visit_attribute(&name, attribute)
+                        }
+                    }
+                }
+                method.visit_end();
+            }
+        }
+        for attribute in attributes {
+            if let Named::Attribute(name, attribute) = attribute {
+                visitor.visit_attribute(&name, attribute);
+            }
+        }
+        visitor.visit_end();
+        Ok(())
+    }
+}
+
+/// Resolves the names of the attributes, and separates the `Code` attribute
+/// from its attributes.
+fn named(
+    cp: &ConstantPool,
+    attributes: Vec<AttributeInfo>,
+) -> Result<Vec<Named>, ConstantPoolError> {
+    attributes
+        .into_iter()
+        .map(|attribute| {
+            let name = cp.get_utf8(attribute.header().0)?.into_owned();
+            Ok(match attribute {
+                AttributeInfo::Code {
+                    max_stack,
+                    max_locals,
+                    code,
+                    ops,
+                    exception_table,
+                    attributes,
+                    ..
+                } => Named::Code(
+                    Code {
+                        max_stack,
+                        max_locals,
+                        code,
+                        ops,
+                        exception_table,
+                    },
+                    attributes
+                        .into_iter()
+                        .map(|a| Ok((cp.get_utf8(a.header().0)?.into_owned(), a)))
+                        .collect::<Result<_, ConstantPoolError>>()?,
+                ),
+                attribute => Named::Attribute(name, attribute),
+            })
+        })
+        .collect()
+}
+
+/// A [`ClassVisitor`] that builds a class file from the events, see
+/// [`ClassWriter::into_class_file`].
+///
+/// It starts with the visited constant pool, or an empty one, and adds the
+/// `CONSTANT_Utf8` and `CONSTANT_Class` entries of the names of the class, its
+/// fields and methods that aren't in it yet. Lengths of attributes are
+/// computed when the class file is written, except for the `Code` attributes
+/// that it builds itself, so writing a class file that was visited without
+/// changes gives the same bytes.
+#[derive(Debug)]
+pub struct ClassWriter {
+    cp: ConstantPool,
+    version: Version,
+    access_flags: ClassAccessFlags,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeInfo>,
+    /// The first entry that couldn't be added to the constant pool.
+    error: Option<ConstantPoolError>,
+}
+
+impl Default for ClassWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClassWriter {
+    pub fn new() -> Self {
+        Self {
+            cp: ConstantPool::default(),
+            version: Version::new(0, 0),
+            access_flags: ClassAccessFlags::empty(),
+            this_class: 0,
+            super_class: 0,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![],
+            attributes: vec![],
+            error: None,
+        }
+    }
+
+    /// Returns the class file that was built from the events. Fails if the
+    /// constant pool outgrew the 65535 entries that a class file can have.
+    pub fn into_class_file(self) -> Result<ClassFile, ConstantPoolError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        Ok(ClassFile {
+            magic: 0xCAFE_BABE,
+            version: self.version,
+            cp_info: self.cp,
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attributes: self.attributes,
+        })
+    }
+
+    /// The index of the given entry, which is added if the constant pool
+    /// doesn't contain it yet. Failures are kept for
+    /// [`ClassWriter::into_class_file`].
+    fn constant(&mut self, info: ConstantPoolInfo) -> u16 {
+        if let Some(i) = self.cp.items.iter().position(|item| *item == info) {
+            return i as u16 + 1;
+        }
+        let slots = info.slots();
+        if self.cp.items.len() + slots > u16::MAX as usize - 1 {
+            self.error.get_or_insert(ConstantPoolError::TooManyEntries);
+            return 0;
+        }
+        self.cp.items.push(info);
+        let index = self.cp.items.len() as u16;
+        if slots == 2 {
+            self.cp.items.push(ConstantPoolInfo::Unusable);
+        }
+        index
+    }
+
+    fn utf8(&mut self, s: &str) -> u16 {
+        self.constant(ConstantPoolInfo::utf8(s))
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.constant(ConstantPoolInfo::ClassInfo { name_index })
+    }
+}
+
+impl ClassVisitor for ClassWriter {
+    fn visit_constant_pool(&mut self, cp: &ConstantPool) {
+        self.cp = cp.clone();
+    }
+
+    fn visit(
+        &mut self,
+        version: Version,
+        access_flags: ClassAccessFlags,
+        name: &str,
+        super_name: Option<&str>,
+        interfaces: &[String],
+    ) {
+        self.version = version;
+        self.access_flags = access_flags;
+        self.this_class = self.class(name);
+        self.super_class = super_name.map_or(0, |s| self.class(s));
+        self.interfaces = interfaces.iter().map(|i| self.class(i)).collect();
+    }
+
+    fn visit_field(
+        &mut self,
+        access_flags: FieldAccessFlags,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<Box<dyn FieldVisitor + '_>> {
+        let field = FieldInfo {
+            access_flags,
+            name_index: self.utf8(name),
+            descriptor_index: self.utf8(descriptor),
+            attributes: vec![],
+        };
+        Some(Box::new(FieldWriter { class: self, field }))
+    }
+
+    fn visit_method(
+        &mut self,
+        access_flags: MethodAccessFlags,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<Box<dyn MethodVisitor + '_>> {
+        let method = MethodInfo {
+            access_flags,
+            name_index: self.utf8(name),
+            descriptor_index: self.utf8(descriptor),
+            attributes: vec![],
+        };
+        Some(Box::new(MethodWriter {
+            class: self,
+            method,
+            code: None,
+        }))
+    }
+
+    fn visit_attribute(&mut self, _name: &str, attribute: AttributeInfo) {
+        self.attributes.push(attribute);
+    }
+}
+
+struct FieldWriter<'a> {
+    class: &'a mut ClassWriter,
+    field: FieldInfo,
+}
+
+impl FieldVisitor for FieldWriter<'_> {
+    fn visit_attribute(&mut self, _name: &str, attribute: AttributeInfo) {
+        self.field.attributes.push(attribute);
+    }
+
+    fn visit_end(&mut self) {
+        let field = core::mem::replace(
+            &mut self.field,
+            FieldInfo {
+                access_flags: FieldAccessFlags::empty(),
+                name_index: 0,
+                descriptor_index: 0,
+                attributes: vec![],
+            },
+        );
+        self.class.fields.push(field);
+    }
+}
+
+struct MethodWriter<'a> {
+    class: &'a mut ClassWriter,
+    method: MethodInfo,
+    /// The index of the `Code` attribute among the attributes of the method,
+    /// the code and the attributes of the code, which are put together at the
+    /// end.
+    code: Option<(usize, Code, Vec<AttributeInfo>)>,
+}
+
+impl MethodVisitor for MethodWriter<'_> {
+    fn visit_code(&mut self, code: Code) {
+        self.code = Some((self.method.attributes.len(), code, vec![]));
+    }
+
+    fn visit_code_attribute(&mut self, _name: &str, attribute: AttributeInfo) {
+        if let Some((_, _, attributes)) = &mut self.code {
+            attributes.push(attribute);
+        }
+    }
+
+    fn visit_attribute(&mut self, _name: &str, attribute: AttributeInfo) {
+        self.method.attributes.push(attribute);
+    }
+
+    fn visit_end(&mut self) {
+        if let Some((position, code, attributes)) = self.code.take() {
+            let mut attribute = AttributeInfo::Code {
+                attribute_name_index: self.class.utf8("Code"),
+                attribute_length: 0,
+                max_stack: code.max_stack,
+                max_locals: code.max_locals,
+                code_length: code.code.len() as u32,
+                code: code.code,
+                ops: code.ops,
+                exception_table: code.exception_table,
+                attributes,
+            };
+            // writing fails for code that doesn't fit into a class file, which
+            // writing the class file reports again
+            let mut bytes = vec![];
+            if attribute.write(&mut bytes).is_ok() {
+                if let AttributeInfo::Code {
+                    attribute_length, ..
+                } = &mut attribute
+                {
+                    *attribute_length = (bytes.len() - 6) as u32;
+                }
+            }
+            self.method.attributes.insert(position, attribute);
+        }
+        let method = core::mem::replace(
+            &mut self.method,
+            MethodInfo {
+                access_flags: MethodAccessFlags::empty(),
+                name_index: 0,
+                descriptor_index: 0,
+                attributes: vec![],
+            },
+        );
+        self.class.methods.push(method);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classfile::test_util::read;
+    use crate::classfile::ParseMode;
+
+    fn transform(
+        bytes: &[u8],
+        mode: ParseMode,
+        visitor: impl FnOnce(&mut ClassWriter) -> Box<dyn ClassVisitor + '_>,
+    ) -> Vec<u8> {
+        let class_file = ClassFile::parse_with(&mut &bytes[..], mode).unwrap();
+        let mut writer = ClassWriter::new();
+        class_file.accept(visitor(&mut writer).as_mut()).unwrap();
+        let mut written = vec![];
+        writer
+            .into_class_file()
+            .unwrap()
+            .write(&mut written)
+            .unwrap();
+        written
+    }
+
+    /// Forwards everything.
+    struct Identity<'a>(&'a mut dyn ClassVisitor);
+
+    impl ClassVisitor for Identity<'_> {
+        fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for name in [
+            "Foo",
+            "Formats",
+            "Constants",
+            "Annotations",
+            "Records",
+            "Shapes",
+        ] {
+            let bytes = read(name);
+            for mode in [ParseMode::Eager, ParseMode::Lazy] {
+                let mut writer = ClassWriter::new();
+                ClassFile::parse_with(&mut bytes.as_slice(), mode)
+                    .unwrap()
+                    .accept(&mut writer)
+                    .unwrap();
+                let class_file = writer.into_class_file().unwrap();
+                assert_eq!(
+                    ClassFile::parse_with(&mut bytes.as_slice(), mode).unwrap(),
+                    class_file
+                );
+
+                // through a visitor that changes nothing
+                assert_eq!(
+                    bytes,
+                    transform(&bytes, mode, |w| Box::new(Identity(w))),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    /// Drops the debugging information.
+    struct Strip<'a>(&'a mut dyn ClassVisitor);
+
+    impl ClassVisitor for Strip<'_> {
+        fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.0)
+        }
+
+        fn visit_method(
+            &mut self,
+            access_flags: MethodAccessFlags,
+            name: &str,
+            descriptor: &str,
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            let next = self.0.visit_method(access_flags, name, descriptor)?;
+            Some(Box::new(StripMethod(next)))
+        }
+
+        fn visit_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+            if name != "SourceFile" {
+                self.0.visit_attribute(name, attribute);
+            }
+        }
+    }
+
+    struct StripMethod<'a>(Box<dyn MethodVisitor + 'a>);
+
+    impl MethodVisitor for StripMethod<'_> {
+        fn next(&mut self) -> Option<&mut dyn MethodVisitor> {
+            Some(self.0.as_mut())
+        }
+
+        fn visit_code_attribute(&mut self, name: &str, attribute: AttributeInfo) {
+            if !matches!(name, "LineNumberTable" | "LocalVariableTable") {
+                self.0.visit_code_attribute(name, attribute);
+            }
+        }
+    }
+
+    /// Collects the names of the attributes of the class and of the code.
+    #[derive(Default)]
+    struct Names {
+        class: Vec<String>,
+        code: Vec<String>,
+    }
+
+    impl ClassVisitor for Names {
+        fn visit_method(
+            &mut self,
+            _access_flags: MethodAccessFlags,
+            _name: &str,
+            _descriptor: &str,
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            Some(Box::new(CodeNames(&mut self.code)))
+        }
+
+        fn visit_attribute(&mut self, name: &str, _attribute: AttributeInfo) {
+            self.class.push(name.to_string());
+        }
+    }
+
+    struct CodeNames<'a>(&'a mut Vec<String>);
+
+    impl MethodVisitor for CodeNames<'_> {
+        fn visit_code_attribute(&mut self, name: &str, _attribute: AttributeInfo) {
+            self.0.push(name.to_string());
+        }
+    }
+
+    #[test]
+    fn test_strip_debug_info() {
+        let bytes = read("Formats");
+        for mode in [ParseMode::Eager, ParseMode::Lazy] {
+            let stripped = transform(&bytes, mode, |w| Box::new(Strip(w)));
+            assert!(stripped.len() < bytes.len());
+            let mut names = Names::default();
+            ClassFile::parse(&mut stripped.as_slice())
+                .unwrap()
+                .accept(&mut names)
+                .unwrap();
+            assert_eq!(
+                vec!["NestMembers", "BootstrapMethods", "InnerClasses"],
+                names.class
+            );
+            assert!(names.code.iter().all(|name| name == "StackMapTable"));
+            assert!(names.code.iter().any(|name| name == "StackMapTable"));
+        }
+    }
+
+    /// Renames the methods named `bar` and adds a field.
+    struct Rename<'a>(&'a mut dyn ClassVisitor);
+
+    impl ClassVisitor for Rename<'_> {
+        fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.0)
+        }
+
+        fn visit_method(
+            &mut self,
+            access_flags: MethodAccessFlags,
+            name: &str,
+            descriptor: &str,
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            let name = if name == "bar" { "baz" } else { name };
+            self.0.visit_method(access_flags, name, descriptor)
+        }
+
+        fn visit_end(&mut self) {
+            if let Some(mut field) = self.0.visit_field(FieldAccessFlags::PRIVATE, "count", "J") {
+                field.visit_end();
+            }
+            self.0.visit_end();
+        }
+    }
+
+    #[test]
+    fn test_rename() {
+        let renamed = transform(&read("Foo"), ParseMode::Eager, |w| Box::new(Rename(w)));
+        let class_file = ClassFile::parse(&mut renamed.as_slice()).unwrap();
+        assert!(class_file.find_method("bar", "()V").is_none());
+        assert!(class_file.find_method("baz", "()V").is_some());
+        assert!(class_file.find_method("<init>", "()V").is_some());
+        assert_eq!(
+            FieldAccessFlags::PRIVATE,
+            class_file.find_field("count", "J").unwrap().access_flags()
+        );
+        assert!(class_file.validate().is_empty());
+    }
+}
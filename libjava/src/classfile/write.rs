@@ -0,0 +1,830 @@
+use crate::classfile::{
+    Annotation, AttributeInfo, BootstrapMethod, ClassFile, ConstantPoolInfo, ElementValue,
+    ElementValuePair, ExceptionTableEntry, FieldInfo, InnerClass, LineNumberTableEntry,
+    LocalVarTargetTableEntry, LocalVariableTableEntry, LocalVariableTypeTableEntry, MethodInfo,
+    MethodParameter, ModuleExports, ModuleOpens, ModuleProvides, ModuleRequires,
+    RecordComponentInfo, StackMapFrame, TargetInfo, TypeAnnotation, TypePath, VerificationTypeInfo,
+};
+use crate::io::{self, Error, ErrorKind, Write};
+use crate::prelude::*;
+
+fn write_u8(sink: &mut impl Write, value: u8) -> io::Result<()> {
+    sink.write_all(&[value])
+}
+
+fn write_u16(sink: &mut impl Write, value: u16) -> io::Result<()> {
+    sink.write_all(&value.to_be_bytes())
+}
+
+fn write_u32(sink: &mut impl Write, value: u32) -> io::Result<()> {
+    sink.write_all(&value.to_be_bytes())
+}
+
+/// Writes the number of items of a table as a u2, failing if the table has
+/// more items than a class file can express.
+fn write_count(sink: &mut impl Write, count: usize) -> io::Result<()> {
+    write_u16(sink, narrow(count)?)
+}
+
+/// Writes the number of items of a table as a u1, see [`write_count`].
+fn write_count_u8(sink: &mut impl Write, count: usize) -> io::Result<()> {
+    write_u8(sink, narrow(count)?)
+}
+
+fn narrow<T: TryFrom<usize>>(count: usize) -> io::Result<T> {
+    T::try_from(count).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} items don't fit into a class file table", count),
+        )
+    })
+}
+
+fn write_u16s(sink: &mut impl Write, values: &[u16]) -> io::Result<()> {
+    write_count(sink, values.len())?;
+    values.iter().try_for_each(|v| write_u16(sink, *v))
+}
+
+fn write_attributes(sink: &mut impl Write, attributes: &[AttributeInfo]) -> io::Result<()> {
+    write_count(sink, attributes.len())?;
+    attributes.iter().try_for_each(|a| a.write(sink))
+}
+
+impl ClassFile {
+    /// Writes this class file in the format specified by [`$4.1`], so that
+    /// [`ClassFile::parse`] reads it back as an equal class file.
+    ///
+    /// Lengths and counts are computed from the contents rather than taken from
+    /// the parsed `*_length` fields, so the output is well-formed even if the
+    /// structure was modified after parsing.
+    ///
+    /// [`$4.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u32(sink, self.magic)?;
+        write_u16(sink, self.version.minor)?;
+        write_u16(sink, self.version.major)?;
+
+        // the unusable items after long and double constants are counted, but
+        // not written ($4.4.5)
+        write_count(sink, self.cp_info.items.len() + 1)?;
+        for info in &self.cp_info.items {
+            info.write(sink)?;
+        }
+
+        write_u16(sink, self.access_flags.bits())?;
+        write_u16(sink, self.this_class)?;
+        write_u16(sink, self.super_class)?;
+        write_u16s(sink, &self.interfaces)?;
+
+        write_count(sink, self.fields.len())?;
+        for field in &self.fields {
+            field.write(sink)?;
+        }
+        write_count(sink, self.methods.len())?;
+        for method in &self.methods {
+            method.write(sink)?;
+        }
+        write_attributes(sink, &self.attributes)
+    }
+}
+
+impl ConstantPoolInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        match self {
+            ConstantPoolInfo::Utf8Info { bytes, .. } => {
+                write_u8(sink, 1)?;
+                write_count(sink, bytes.len())?;
+                sink.write_all(bytes)
+            }
+            ConstantPoolInfo::IntegerInfo { bytes } => {
+                write_u8(sink, 3)?;
+                write_u32(sink, *bytes)
+            }
+            ConstantPoolInfo::FloatInfo { bytes } => {
+                write_u8(sink, 4)?;
+                write_u32(sink, *bytes)
+            }
+            ConstantPoolInfo::LongInfo {
+                high_bytes,
+                low_bytes,
+            } => {
+                write_u8(sink, 5)?;
+                write_u32(sink, *high_bytes)?;
+                write_u32(sink, *low_bytes)
+            }
+            ConstantPoolInfo::DoubleInfo {
+                high_bytes,
+                low_bytes,
+            } => {
+                write_u8(sink, 6)?;
+                write_u32(sink, *high_bytes)?;
+                write_u32(sink, *low_bytes)
+            }
+            ConstantPoolInfo::ClassInfo { name_index } => {
+                write_u8(sink, 7)?;
+                write_u16(sink, *name_index)
+            }
+            ConstantPoolInfo::StringInfo { string_index } => {
+                write_u8(sink, 8)?;
+                write_u16(sink, *string_index)
+            }
+            ConstantPoolInfo::FieldrefInfo {
+                class_index,
+                name_and_type_index,
+            } => {
+                write_u8(sink, 9)?;
+                write_u16(sink, *class_index)?;
+                write_u16(sink, *name_and_type_index)
+            }
+            ConstantPoolInfo::MethodrefInfo {
+                class_index,
+                name_and_type_index,
+            } => {
+                write_u8(sink, 10)?;
+                write_u16(sink, *class_index)?;
+                write_u16(sink, *name_and_type_index)
+            }
+            ConstantPoolInfo::InterfaceMethodrefInfo {
+                class_index,
+                name_and_type_index,
+            } => {
+                write_u8(sink, 11)?;
+                write_u16(sink, *class_index)?;
+                write_u16(sink, *name_and_type_index)
+            }
+            ConstantPoolInfo::NameAndTypeInfo {
+                name_index,
+                descriptor_index,
+            } => {
+                write_u8(sink, 12)?;
+                write_u16(sink, *name_index)?;
+                write_u16(sink, *descriptor_index)
+            }
+            ConstantPoolInfo::MethodHandleInfo {
+                reference_kind,
+                reference_index,
+            } => {
+                write_u8(sink, 15)?;
+                write_u8(sink, *reference_kind as u8)?;
+                write_u16(sink, *reference_index)
+            }
+            ConstantPoolInfo::MethodTypeInfo { descriptor_index } => {
+                write_u8(sink, 16)?;
+                write_u16(sink, *descriptor_index)
+            }
+            ConstantPoolInfo::DynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                write_u8(sink, 17)?;
+                write_u16(sink, *bootstrap_method_attr_index)?;
+                write_u16(sink, *name_and_type_index)
+            }
+            ConstantPoolInfo::InvokeDynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                write_u8(sink, 18)?;
+                write_u16(sink, *bootstrap_method_attr_index)?;
+                write_u16(sink, *name_and_type_index)
+            }
+            ConstantPoolInfo::ModuleInfo { name_index } => {
+                write_u8(sink, 19)?;
+                write_u16(sink, *name_index)
+            }
+            ConstantPoolInfo::PackageInfo { name_index } => {
+                write_u8(sink, 20)?;
+                write_u16(sink, *name_index)
+            }
+            ConstantPoolInfo::Unusable => Ok(()),
+        }
+    }
+}
+
+impl FieldInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.access_flags.bits())?;
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.descriptor_index)?;
+        write_attributes(sink, &self.attributes)
+    }
+}
+
+impl MethodInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.access_flags.bits())?;
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.descriptor_index)?;
+        write_attributes(sink, &self.attributes)
+    }
+}
+
+impl AttributeInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        // the length precedes the contents, so write them to a buffer first
+        let mut info = vec![];
+        let out = &mut info;
+        let attribute_name_index = match self {
+            Self::ConstantValue {
+                attribute_name_index,
+                constantvalue_index,
+                ..
+            } => {
+                write_u16(out, *constantvalue_index)?;
+                attribute_name_index
+            }
+            Self::Code {
+                attribute_name_index,
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                write_u16(out, *max_stack)?;
+                write_u16(out, *max_locals)?;
+                write_u32(out, narrow(code.len())?)?;
+                out.write_all(code)?;
+                write_count(out, exception_table.len())?;
+                for entry in exception_table {
+                    entry.write(out)?;
+                }
+                write_attributes(out, attributes)?;
+                attribute_name_index
+            }
+            Self::StackMapTable {
+                attribute_name_index,
+                entries,
+                ..
+            } => {
+                write_count(out, entries.len())?;
+                for frame in entries {
+                    frame.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::Exceptions {
+                attribute_name_index,
+                exception_index_table,
+                ..
+            } => {
+                write_u16s(out, exception_index_table)?;
+                attribute_name_index
+            }
+            Self::InnerClasses {
+                attribute_name_index,
+                classes,
+                ..
+            } => {
+                write_count(out, classes.len())?;
+                for class in classes {
+                    class.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::EnclosingMethod {
+                attribute_name_index,
+                class_index,
+                method_index,
+                ..
+            } => {
+                write_u16(out, *class_index)?;
+                write_u16(out, *method_index)?;
+                attribute_name_index
+            }
+            Self::Synthetic {
+                attribute_name_index,
+                ..
+            }
+            | Self::Deprecated {
+                attribute_name_index,
+                ..
+            } => attribute_name_index,
+            Self::Signature {
+                attribute_name_index,
+                signature_index: index,
+                ..
+            }
+            | Self::SourceFile {
+                attribute_name_index,
+                sourcefile_index: index,
+                ..
+            }
+            | Self::ModuleMainClass {
+                attribute_name_index,
+                main_class_index: index,
+                ..
+            }
+            | Self::NestHost {
+                attribute_name_index,
+                host_class_index: index,
+                ..
+            } => {
+                write_u16(out, *index)?;
+                attribute_name_index
+            }
+            Self::SourceDebugExtension {
+                attribute_name_index,
+                debug_extension,
+                ..
+            } => {
+                out.write_all(debug_extension)?;
+                attribute_name_index
+            }
+            Self::LineNumberTable {
+                attribute_name_index,
+                line_number_table,
+                ..
+            } => {
+                write_count(out, line_number_table.len())?;
+                for entry in line_number_table {
+                    entry.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::LocalVariableTable {
+                attribute_name_index,
+                local_variable_table,
+                ..
+            } => {
+                write_count(out, local_variable_table.len())?;
+                for entry in local_variable_table {
+                    entry.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::LocalVariableTypeTable {
+                attribute_name_index,
+                local_variable_type_table,
+                ..
+            } => {
+                write_count(out, local_variable_type_table.len())?;
+                for entry in local_variable_type_table {
+                    entry.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::RuntimeVisibleAnnotations {
+                attribute_name_index,
+                annotations,
+                ..
+            }
+            | Self::RuntimeInvisibleAnnotations {
+                attribute_name_index,
+                annotations,
+                ..
+            } => {
+                write_count(out, annotations.len())?;
+                for annotation in annotations {
+                    annotation.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::RuntimeVisibleParameterAnnotations {
+                attribute_name_index,
+                parameter_annotations,
+                ..
+            }
+            | Self::RuntimeInvisibleParameterAnnotations {
+                attribute_name_index,
+                parameter_annotations,
+                ..
+            } => {
+                write_count_u8(out, parameter_annotations.len())?;
+                for annotations in parameter_annotations {
+                    write_count(out, annotations.len())?;
+                    for annotation in annotations {
+                        annotation.write(out)?;
+                    }
+                }
+                attribute_name_index
+            }
+            Self::RuntimeVisibleTypeAnnotations {
+                attribute_name_index,
+                annotations,
+                ..
+            }
+            | Self::RuntimeInvisibleTypeAnnotations {
+                attribute_name_index,
+                annotations,
+                ..
+            } => {
+                write_count(out, annotations.len())?;
+                for annotation in annotations {
+                    annotation.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::AnnotationDefault {
+                attribute_name_index,
+                default_value,
+                ..
+            } => {
+                default_value.write(out)?;
+                attribute_name_index
+            }
+            Self::BootstrapMethods {
+                attribute_name_index,
+                bootstrap_methods,
+                ..
+            } => {
+                write_count(out, bootstrap_methods.len())?;
+                for method in bootstrap_methods {
+                    method.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::MethodParameters {
+                attribute_name_index,
+                parameters,
+                ..
+            } => {
+                write_count_u8(out, parameters.len())?;
+                for parameter in parameters {
+                    parameter.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::Module {
+                attribute_name_index,
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses_index,
+                provides,
+                ..
+            } => {
+                write_u16(out, *module_name_index)?;
+                write_u16(out, module_flags.bits())?;
+                write_u16(out, *module_version_index)?;
+                write_count(out, requires.len())?;
+                for r in requires {
+                    r.write(out)?;
+                }
+                write_count(out, exports.len())?;
+                for e in exports {
+                    e.write(out)?;
+                }
+                write_count(out, opens.len())?;
+                for o in opens {
+                    o.write(out)?;
+                }
+                write_u16s(out, uses_index)?;
+                write_count(out, provides.len())?;
+                for p in provides {
+                    p.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::ModulePackages {
+                attribute_name_index,
+                package_index: indices,
+                ..
+            }
+            | Self::NestMembers {
+                attribute_name_index,
+                classes: indices,
+                ..
+            }
+            | Self::PermittedSubclasses {
+                attribute_name_index,
+                classes: indices,
+                ..
+            } => {
+                write_u16s(out, indices)?;
+                attribute_name_index
+            }
+            Self::Record {
+                attribute_name_index,
+                components,
+                ..
+            } => {
+                write_count(out, components.len())?;
+                for component in components {
+                    component.write(out)?;
+                }
+                attribute_name_index
+            }
+            Self::Unknown {
+                name_index,
+                raw_bytes,
+                ..
+            } => {
+                out.write_all(raw_bytes)?;
+                name_index
+            }
+            Self::Lazy(lazy) => {
+                out.write_all(&lazy.raw_bytes)?;
+                &lazy.name_index
+            }
+        };
+        write_u16(sink, *attribute_name_index)?;
+        write_u32(sink, narrow(info.len())?)?;
+        sink.write_all(&info)
+    }
+}
+
+impl ExceptionTableEntry {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.start_pc)?;
+        write_u16(sink, self.end_pc)?;
+        write_u16(sink, self.handler_pc)?;
+        write_u16(sink, self.catch_type)
+    }
+}
+
+impl StackMapFrame {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        match self {
+            StackMapFrame::Same { frame_type } => write_u8(sink, *frame_type),
+            StackMapFrame::SameLocals1StackItem { frame_type, stack } => {
+                write_u8(sink, *frame_type)?;
+                stack.write(sink)
+            }
+            StackMapFrame::SameLocals1StackItemExtended {
+                frame_type,
+                offset_delta,
+                stack,
+            } => {
+                write_u8(sink, *frame_type)?;
+                write_u16(sink, *offset_delta)?;
+                stack.write(sink)
+            }
+            StackMapFrame::Chop {
+                frame_type,
+                offset_delta,
+            }
+            | StackMapFrame::SameExtended {
+                frame_type,
+                offset_delta,
+            } => {
+                write_u8(sink, *frame_type)?;
+                write_u16(sink, *offset_delta)
+            }
+            StackMapFrame::Append {
+                frame_type,
+                offset_delta,
+                locals,
+            } => {
+                // the number of locals is implied by the frame type
+                write_u8(sink, *frame_type)?;
+                write_u16(sink, *offset_delta)?;
+                locals.iter().try_for_each(|l| l.write(sink))
+            }
+            StackMapFrame::Full {
+                frame_type,
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                write_u8(sink, *frame_type)?;
+                write_u16(sink, *offset_delta)?;
+                write_count(sink, locals.len())?;
+                locals.iter().try_for_each(|l| l.write(sink))?;
+                write_count(sink, stack.len())?;
+                stack.iter().try_for_each(|s| s.write(sink))
+            }
+        }
+    }
+}
+
+impl VerificationTypeInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        match self {
+            VerificationTypeInfo::TopVariable { tag }
+            | VerificationTypeInfo::IntegerVariable { tag }
+            | VerificationTypeInfo::FloatVariable { tag }
+            | VerificationTypeInfo::NullVariable { tag }
+            | VerificationTypeInfo::UninitializedThisVariable { tag }
+            | VerificationTypeInfo::LongVariable { tag }
+            | VerificationTypeInfo::DoubleVariable { tag } => write_u8(sink, *tag),
+            VerificationTypeInfo::ObjectVariable {
+                tag,
+                cpool_index: index,
+            }
+            | VerificationTypeInfo::UninitializedVariable { tag, offset: index } => {
+                write_u8(sink, *tag)?;
+                write_u16(sink, *index)
+            }
+        }
+    }
+}
+
+impl InnerClass {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.inner_class_info_index)?;
+        write_u16(sink, self.outer_class_info_index)?;
+        write_u16(sink, self.inner_name_index)?;
+        write_u16(sink, self.inner_class_access_flags.bits())
+    }
+}
+
+impl LineNumberTableEntry {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.start_pc)?;
+        write_u16(sink, self.line_number)
+    }
+}
+
+impl LocalVariableTableEntry {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.start_pc)?;
+        write_u16(sink, self.length)?;
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.descriptor_index)?;
+        write_u16(sink, self.index)
+    }
+}
+
+impl LocalVariableTypeTableEntry {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.start_pc)?;
+        write_u16(sink, self.length)?;
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.signature_index)?;
+        write_u16(sink, self.index)
+    }
+}
+
+impl Annotation {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.type_index)?;
+        write_count(sink, self.element_value_pairs.len())?;
+        self.element_value_pairs
+            .iter()
+            .try_for_each(|p| p.write(sink))
+    }
+}
+
+impl ElementValuePair {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.element_name_index)?;
+        self.value.write(sink)
+    }
+}
+
+impl ElementValue {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        match self {
+            ElementValue::ConstValueIndex {
+                tag,
+                const_value_index,
+            } => {
+                write_u8(sink, *tag)?;
+                write_u16(sink, *const_value_index)
+            }
+            ElementValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                write_u8(sink, b'e')?;
+                write_u16(sink, *type_name_index)?;
+                write_u16(sink, *const_name_index)
+            }
+            ElementValue::ClassInfoIndex(index) => {
+                write_u8(sink, b'c')?;
+                write_u16(sink, *index)
+            }
+            ElementValue::AnnotationValue(annotation) => {
+                write_u8(sink, b'@')?;
+                annotation.write(sink)
+            }
+            ElementValue::ArrayValue { values } => {
+                write_u8(sink, b'[')?;
+                write_count(sink, values.len())?;
+                values.iter().try_for_each(|v| v.write(sink))
+            }
+        }
+    }
+}
+
+impl TypeAnnotation {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u8(sink, self.target_type)?;
+        self.target_info.write(sink)?;
+        self.target_path.write(sink)?;
+        write_u16(sink, self.type_index)?;
+        write_count(sink, self.element_value_pairs.len())?;
+        self.element_value_pairs
+            .iter()
+            .try_for_each(|p| p.write(sink))
+    }
+}
+
+impl TargetInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        match self {
+            TargetInfo::TypeParameter {
+                type_parameter_index: index,
+            }
+            | TargetInfo::FormalParameter {
+                formal_parameter_index: index,
+            } => write_u8(sink, *index),
+            TargetInfo::Supertype {
+                supertype_index: index,
+            }
+            | TargetInfo::Throws {
+                throws_type_index: index,
+            }
+            | TargetInfo::Catch {
+                exception_table_index: index,
+            }
+            | TargetInfo::Offset { offset: index } => write_u16(sink, *index),
+            TargetInfo::TypeParameterBound {
+                type_parameter_index,
+                bound_index,
+            } => {
+                write_u8(sink, *type_parameter_index)?;
+                write_u8(sink, *bound_index)
+            }
+            TargetInfo::Empty => Ok(()),
+            TargetInfo::Localvar { table } => {
+                write_count(sink, table.len())?;
+                table.iter().try_for_each(|e| e.write(sink))
+            }
+            TargetInfo::TypeArgument {
+                offset,
+                type_argument_index,
+            } => {
+                write_u16(sink, *offset)?;
+                write_u8(sink, *type_argument_index)
+            }
+        }
+    }
+}
+
+impl LocalVarTargetTableEntry {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.start_pc)?;
+        write_u16(sink, self.length)?;
+        write_u16(sink, self.index)
+    }
+}
+
+impl TypePath {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_count_u8(sink, self.path.len())?;
+        for path in &self.path {
+            write_u8(sink, path.type_path_kind as u8)?;
+            write_u8(sink, path.type_path_argument_index)?;
+        }
+        Ok(())
+    }
+}
+
+impl BootstrapMethod {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.bootstrap_method_ref)?;
+        write_u16s(sink, &self.bootstrap_arguments)
+    }
+}
+
+impl MethodParameter {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.access_flags.bits())
+    }
+}
+
+impl ModuleRequires {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.requires_index)?;
+        write_u16(sink, self.requires_flags.bits())?;
+        write_u16(sink, self.requires_version_index)
+    }
+}
+
+impl ModuleExports {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.exports_index)?;
+        write_u16(sink, self.exports_flags.bits())?;
+        write_u16s(sink, &self.exports_to_index)
+    }
+}
+
+impl ModuleOpens {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.opens_index)?;
+        write_u16(sink, self.opens_flags.bits())?;
+        write_u16s(sink, &self.opens_to_index)
+    }
+}
+
+impl ModuleProvides {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.provides_index)?;
+        write_u16s(sink, &self.provides_with_index)
+    }
+}
+
+impl RecordComponentInfo {
+    pub fn write(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_u16(sink, self.name_index)?;
+        write_u16(sink, self.descriptor_index)?;
+        write_attributes(sink, &self.attributes)
+    }
+}
@@ -0,0 +1,139 @@
+//! The byte sources that class files and bytecode are parsed from, and the
+//! sinks that they are written to.
+//!
+//! With the `std` feature, these are the traits of [`std::io`], so any reader
+//! or writer of the standard library can be used. Without it, the crate only
+//! needs `alloc`, and this module provides minimal versions of the traits
+//! instead, with implementations for byte slices and vectors. Embedders can
+//! implement them for their own sources, e.g. flash memory on a
+//! microcontroller, or a buffer handed over by a WebAssembly host.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use no_std::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use crate::prelude::*;
+    use core::fmt::{Display, Formatter};
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// The kinds of [`Error`] that parsing and writing class files produce,
+    /// a subset of those of `std::io::ErrorKind`.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub enum ErrorKind {
+        /// The source ended before all requested bytes were read.
+        UnexpectedEof,
+        /// The sink didn't accept all bytes that were written.
+        WriteZero,
+        /// The data can't be represented, e.g. a table with too many items
+        /// for a class file.
+        InvalidData,
+        Other,
+    }
+
+    /// An error of a [`Read`] or [`Write`], like `std::io::Error`.
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: String,
+    }
+
+    impl Error {
+        pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+            Self {
+                kind,
+                message: message.into(),
+            }
+        }
+
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl Display for Error {
+        fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{:?}: {}", self.kind, self.message)
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// A source of bytes, like `std::io::Read`.
+    pub trait Read {
+        /// Reads some bytes into the given buffer, and returns how many were
+        /// read. Returning 0 for a non-empty buffer means the source ended.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        /// Reads exactly enough bytes to fill the given buffer.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => {
+                        return Err(Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "failed to fill whole buffer",
+                        ))
+                    }
+                    read => buf = &mut buf[read..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A sink for bytes, like `std::io::Write`.
+    pub trait Write {
+        /// Writes some of the given bytes, and returns how many were written.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        /// Writes all of the given bytes.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => {
+                        return Err(Error::new(
+                            ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        ))
+                    }
+                    written => buf = &buf[written..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let read = buf.len().min(self.len());
+            let (head, tail) = self.split_at(read);
+            buf[..read].copy_from_slice(head);
+            *self = tail;
+            Ok(read)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+    }
+}
@@ -1,5 +1,32 @@
+//! Class files and bytecode of the JVM.
+//!
+//! Without the default `std` feature, this crate only depends on `alloc`, so
+//! that class files can be parsed and written on targets without an operating
+//! system, see [`io`]. This is only the class file layer: the interpreter in
+//! `libjvm` still needs `std` for its threads, locks and file systems, so
+//! hosts without an operating system can inspect and transform classes, but
+//! not run them.
+//!
+//! The tests link `std` for their fixtures in any case, but only use the
+//! `std` readers and writers with the `std` feature, so that
+//! `--no-default-features` tests the `alloc`-only code.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod bytecode;
 pub mod classfile;
+pub mod io;
+
+/// The items of `alloc` that the standard prelude contains, so that modules
+/// build the same with and without `std`.
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
 
 #[cfg(test)]
 mod tests {
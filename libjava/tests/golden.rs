@@ -0,0 +1,96 @@
+// Golden-file tests of the class file parser. Every class file in
+// `tests/golden` is parsed, and its JSON dump is compared to the `.json` file
+// next to it, so that changes of the parser are reviewed as diffs of the golden
+// files. The class files are compiled from the sources next to them, with the
+// javac of several releases (`--release 8` and so on).
+//
+// After an intended change of the parser, run the tests with `UPDATE_GOLDEN=1`
+// to rewrite the golden files, and review the diff. The dump needs the serde
+// feature, which the dev-dependency on this crate enables for the tests.
+
+use libjava::classfile::ClassFile;
+use std::path::{Path, PathBuf};
+
+const CORPUS: &str = "tests/golden";
+
+/// The class files in the directory and its subdirectories, sorted by path.
+fn class_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(class_files(&path));
+        } else if path.extension().is_some_and(|e| e == "class") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+fn dump(class_file: &Path) -> String {
+    let bytes = std::fs::read(class_file).unwrap();
+    let parsed = ClassFile::parse(&mut bytes.as_slice())
+        .unwrap_or_else(|e| panic!("unable to parse {}: {}", class_file.display(), e));
+    let json = serde_json::to_string_pretty(&parsed).unwrap();
+    // the dump has to describe the class file completely
+    assert_eq!(
+        parsed,
+        serde_json::from_str(&json).unwrap(),
+        "{}",
+        class_file.display()
+    );
+    json + "\n"
+}
+
+#[test]
+fn test_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let class_files = class_files(Path::new(CORPUS));
+    assert!(!class_files.is_empty(), "no class files in {}", CORPUS);
+
+    let mut mismatches = vec![];
+    for class_file in &class_files {
+        let actual = dump(class_file);
+        let golden = class_file.with_extension("json");
+        if update {
+            std::fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+        match std::fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            Ok(_) => mismatches.push(format!("{} differs", golden.display())),
+            Err(_) => mismatches.push(format!("{} is missing", golden.display())),
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "the dumps of {} class files don't match their golden files, rerun with \
+         UPDATE_GOLDEN=1 and review the diff:\n{}",
+        mismatches.len(),
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn test_no_stale_golden_files() {
+    let mut stale = vec![];
+    for class_file in class_files(Path::new(CORPUS)) {
+        let dir = class_file.parent().unwrap();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "json")
+                && !path.with_extension("class").exists()
+            {
+                stale.push(path.display().to_string());
+            }
+        }
+    }
+    stale.sort();
+    stale.dedup();
+    assert!(
+        stale.is_empty(),
+        "golden files without a class file: {:?}",
+        stale
+    );
+}
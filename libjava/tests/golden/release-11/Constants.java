@@ -0,0 +1,13 @@
+public class Constants {
+    long big() {
+        return 123456789012L;
+    }
+
+    double half() {
+        return 0.25;
+    }
+
+    String after() {
+        return "after the wide constants";
+    }
+}
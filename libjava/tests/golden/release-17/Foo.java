@@ -0,0 +1,4 @@
+public class Foo {
+    public void bar() {
+    }
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+public sealed interface Shapes permits Shapes.Circle, Shapes.Square {
+    record Circle(double radius) implements Shapes {
+    }
+
+    final class Square implements Shapes {
+    }
+}
@@ -0,0 +1,59 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+@Annotations.Info(
+        count = 3,
+        name = "annotations",
+        retention = RetentionPolicy.RUNTIME,
+        type = String.class,
+        tags = {"a", "b"},
+        nested = @Annotations.Tag("nested"))
+@Annotations.Tag("class")
+public class Annotations {
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Info {
+        int count();
+
+        String name();
+
+        RetentionPolicy retention();
+
+        Class<?> type();
+
+        String[] tags();
+
+        Tag nested();
+
+        ElementType target() default ElementType.FIELD;
+
+        long big() default 1L << 40;
+
+        double ratio() default 0.5;
+
+        float scale() default 1.5f;
+
+        boolean enabled() default true;
+
+        char letter() default 'x';
+
+        byte small() default -1;
+
+        short medium() default 300;
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Tag {
+        String value();
+    }
+
+    @Retention(RetentionPolicy.CLASS)
+    @interface Invisible {}
+
+    @Deprecated
+    @Invisible
+    long big = 1L << 40;
+
+    @Tag("method")
+    void run(@Tag("parameter") int count, char c) {}
+}
@@ -0,0 +1,32 @@
+import java.io.IOException;
+import java.util.function.Supplier;
+
+public abstract class Formats {
+    static final int ANSWER = 42;
+    static final long BIG = 1L << 40;
+    static final float RATIO = 1.5f;
+    static final double HALF = 0.5;
+    static final String NAME = "formats";
+    static final boolean ENABLED = true;
+
+    interface Inner {
+        void run() throws IOException;
+    }
+
+    abstract void missing();
+
+    native int fromNative(long handle);
+
+    Supplier<String> supplier() {
+        return () -> NAME + ANSWER;
+    }
+
+    int guarded(Inner inner) {
+        try {
+            inner.run();
+            return 0;
+        } catch (IOException e) {
+            return 1;
+        }
+    }
+}
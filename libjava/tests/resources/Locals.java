@@ -0,0 +1,14 @@
+// compiled with javac -g, for the LocalVariableTable and
+// LocalVariableTypeTable attributes
+import java.util.List;
+
+public class Locals {
+    static int sum(List<Integer> values) {
+        int total = 0;
+        for (int value : values) {
+            total += value;
+        }
+        long wide = total;
+        return (int) wide;
+    }
+}
@@ -0,0 +1,19 @@
+public class Nesting {
+    class Member {
+        static class Deep {
+        }
+    }
+
+    Runnable anonymous() {
+        return new Runnable() {
+            public void run() {
+            }
+        };
+    }
+
+    Object local() {
+        class Local {
+        }
+        return new Local();
+    }
+}
@@ -0,0 +1,17 @@
+public class Shape {
+    public static final int SIDES = 4;
+    private int width;
+    protected String name;
+
+    public int area() {
+        return width * width;
+    }
+
+    void resize(int width) {
+        this.width = width;
+    }
+
+    public String name() {
+        return name;
+    }
+}
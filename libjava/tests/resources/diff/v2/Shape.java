@@ -0,0 +1,17 @@
+public final class Shape implements Comparable<Shape> {
+    public static final int SIDES = 5;
+    private int width;
+    private long id = 7;
+
+    public int area() {
+        return width * width;
+    }
+
+    public synchronized void resize(int width) {
+        this.width = width;
+    }
+
+    public int compareTo(Shape other) {
+        return Integer.compare(width, other.width);
+    }
+}
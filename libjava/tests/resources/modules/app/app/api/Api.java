@@ -0,0 +1,4 @@
+package app.api;
+
+public class Api {
+}
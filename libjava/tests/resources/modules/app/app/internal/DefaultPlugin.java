@@ -0,0 +1,6 @@
+package app.internal;
+
+import app.spi.Plugin;
+
+public class DefaultPlugin implements Plugin {
+}
@@ -0,0 +1,4 @@
+package app.spi;
+
+public interface Plugin {
+}
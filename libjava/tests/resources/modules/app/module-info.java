@@ -0,0 +1,13 @@
+// compiled with javac --module-version 1.2, for the version of the module
+module app {
+    requires java.logging;
+    requires static java.sql;
+    requires transitive java.xml;
+
+    exports app.api;
+    exports app.spi to java.logging, java.xml;
+    opens app.internal;
+
+    uses app.spi.Plugin;
+    provides app.spi.Plugin with app.internal.DefaultPlugin;
+}
@@ -0,0 +1,288 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Error, Expr, ExprLit, ItemFn, Lit, LitStr, MetaNameValue, Token};
+
+/// Binds a Rust function to the Java method that it implements, e.g.
+///
+/// ```ignore
+/// #[jvm_native(class = "java/lang/System", name = "nanoTime", sig = "()J")]
+/// pub fn system_nano_time(thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+///     ...
+/// }
+/// ```
+///
+/// The function is kept as it is, and a constant with the upper case name of
+/// the function (`SYSTEM_NANO_TIME`) and the same visibility is generated next
+/// to it, which holds the `NativeBinding` of libjvm that `find_native` looks
+/// the method up in. `class` is the binary name of the class with slashes,
+/// and `sig` the method descriptor. Both are checked at compile time, as is
+/// the signature of the function, which has to be a `NativeMethod`.
+#[proc_macro_attribute]
+pub fn jvm_native(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand(attr.into(), item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The Java method that a native is bound to.
+#[derive(Debug, Eq, PartialEq)]
+struct Binding {
+    class: String,
+    name: String,
+    descriptor: String,
+}
+
+fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let binding = parse_binding(attr)?;
+    let function: ItemFn = syn::parse2(item)?;
+    check_signature(&function)?;
+
+    let ident = &function.sig.ident;
+    let vis = &function.vis;
+    let constant = format_ident!("{}", ident.to_string().to_uppercase(), span = ident.span());
+    let doc = format!(
+        "The binding of [`{}`] to `{}.{}{}`.",
+        ident, binding.class, binding.name, binding.descriptor
+    );
+    let Binding {
+        class,
+        name,
+        descriptor,
+    } = binding;
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #vis const #constant: crate::vm::native::NativeBinding = crate::vm::native::NativeBinding {
+            class: #class,
+            name: #name,
+            descriptor: #descriptor,
+            method: #ident,
+        };
+    })
+}
+
+/// Parses the `class`, `name` and `sig` arguments of the attribute, which are
+/// all required.
+fn parse_binding(attr: TokenStream) -> syn::Result<Binding> {
+    let args = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(attr)?;
+    let (mut class, mut name, mut descriptor) = (None, None, None);
+    for arg in &args {
+        let value = match &arg.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(s), ..
+            }) => s,
+            value => return Err(Error::new_spanned(value, "expected a string literal")),
+        };
+        let key = arg.path.get_ident().map(|i| i.to_string());
+        let slot = match key.as_deref() {
+            Some("class") => &mut class,
+            Some("name") => &mut name,
+            Some("sig") => &mut descriptor,
+            _ => {
+                return Err(Error::new_spanned(
+                    &arg.path,
+                    "expected `class`, `name` or `sig`",
+                ))
+            }
+        };
+        if slot.replace(value.clone()).is_some() {
+            return Err(Error::new_spanned(&arg.path, "duplicate argument"));
+        }
+    }
+
+    let required = |value: Option<LitStr>, key: &str| {
+        value.ok_or_else(|| Error::new(Span::call_site(), format!("missing `{}`", key)))
+    };
+    let (class, name, descriptor) = (
+        required(class, "class")?,
+        required(name, "name")?,
+        required(descriptor, "sig")?,
+    );
+    if class.value().is_empty() || class.value().contains('.') {
+        return Err(Error::new(
+            class.span(),
+            "expected a binary name with slashes, like `java/lang/System`",
+        ));
+    }
+    if name.value().is_empty() || name.value().contains(['.', ';', '[', '/', '<', '>']) {
+        return Err(Error::new(name.span(), "invalid method name"));
+    }
+    if !is_method_descriptor(&descriptor.value()) {
+        return Err(Error::new(
+            descriptor.span(),
+            "invalid method descriptor, expected e.g. `(IJ)V`",
+        ));
+    }
+    Ok(Binding {
+        class: class.value(),
+        name: name.value(),
+        descriptor: descriptor.value(),
+    })
+}
+
+/// Fails unless the function takes two arguments and returns something, like
+/// `fn(&mut Thread, &[NativeValue]) -> NativeResult`. The exact types are
+/// checked by the compiler when the function is assigned to the binding.
+fn check_signature(function: &ItemFn) -> syn::Result<()> {
+    let sig = &function.sig;
+    if sig.inputs.len() != 2
+        || matches!(sig.output, syn::ReturnType::Default)
+        || sig.asyncness.is_some()
+        || !sig.generics.params.is_empty()
+    {
+        return Err(Error::new_spanned(
+            sig,
+            "a native has to be a `fn(&mut Thread, &[NativeValue]) -> NativeResult`",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the string is a method descriptor ([`$4.3.3`]).
+///
+/// [`$4.3.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+fn is_method_descriptor(descriptor: &str) -> bool {
+    let Some(rest) = descriptor.strip_prefix('(') else {
+        return false;
+    };
+    let Some((parameters, ret)) = rest.split_once(')') else {
+        return false;
+    };
+    let mut parameters = parameters;
+    while !parameters.is_empty() {
+        match field_type_len(parameters) {
+            Some(len) => parameters = &parameters[len..],
+            None => return false,
+        }
+    }
+    ret == "V" || field_type_len(ret) == Some(ret.len())
+}
+
+/// The length of the field descriptor at the start of the string, if there is
+/// one.
+fn field_type_len(s: &str) -> Option<usize> {
+    let dimensions = s.bytes().take_while(|b| *b == b'[').count();
+    let rest = &s[dimensions..];
+    let len = match rest.bytes().next()? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => 1,
+        b'L' => {
+            let end = rest.find(';')?;
+            if end == 1 || rest[1..end].contains(['.', '[']) {
+                return None;
+            }
+            end + 1
+        }
+        _ => return None,
+    };
+    (dimensions <= 255).then_some(dimensions + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let attr = quote!(class = "java/lang/System", name = "nanoTime", sig = "()J");
+        let item = quote! {
+            pub(crate) fn system_nano_time(thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+                Ok(None)
+            }
+        };
+        let expanded = expand(attr, item).unwrap().to_string();
+        assert!(expanded.starts_with("pub (crate) fn system_nano_time"));
+        assert!(expanded.contains(
+            &quote! {
+                pub(crate) const SYSTEM_NANO_TIME: crate::vm::native::NativeBinding = crate::vm::native::NativeBinding {
+                    class: "java/lang/System",
+                    name: "nanoTime",
+                    descriptor: "()J",
+                    method: system_nano_time,
+                };
+            }
+            .to_string()
+        ));
+        assert!(expanded
+            .contains("The binding of [`system_nano_time`] to `java/lang/System.nanoTime()J`."));
+    }
+
+    #[test]
+    fn test_invalid_attributes() {
+        let item = quote!(
+            fn f(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {}
+        );
+        for (attr, message) in [
+            (quote!(class = "A", name = "f"), "missing `sig`"),
+            (quote!(name = "f", sig = "()V"), "missing `class`"),
+            (
+                quote!(class = "A", name = "f", sig = "()V", name = "g"),
+                "duplicate argument",
+            ),
+            (
+                quote!(class = "A", method = "f", sig = "()V"),
+                "expected `class`, `name` or `sig`",
+            ),
+            (
+                quote!(class = "A", name = f, sig = "()V"),
+                "expected a string literal",
+            ),
+            (
+                quote!(class = "java.lang.System", name = "f", sig = "()V"),
+                "expected a binary name with slashes, like `java/lang/System`",
+            ),
+            (
+                quote!(class = "A", name = "<init>", sig = "()V"),
+                "invalid method name",
+            ),
+            (
+                quote!(class = "A", name = "f", sig = "(Ljava/lang/String)V"),
+                "invalid method descriptor, expected e.g. `(IJ)V`",
+            ),
+        ] {
+            let error = expand(attr, item.clone()).unwrap_err();
+            assert_eq!(message, error.to_string());
+        }
+
+        let error = expand(
+            quote!(class = "A", name = "f", sig = "()V"),
+            quote!(
+                fn f(thread: &mut Thread) -> NativeResult {}
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(
+            "a native has to be a `fn(&mut Thread, &[NativeValue]) -> NativeResult`",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_method_descriptors() {
+        for valid in [
+            "()V",
+            "(IJ)J",
+            "([[Ljava/lang/String;Z)[B",
+            "(Ljava/lang/Object;JJ)V",
+        ] {
+            assert!(is_method_descriptor(valid), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "V",
+            "()",
+            "(V)V",
+            "(L;)V",
+            "(Ljava.lang.String;)V",
+            "()II",
+            "(I",
+        ] {
+            assert!(!is_method_descriptor(invalid), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use libjava::classfile::{ConstantPool, ResolvedConstantPool};
+use libjvm::vm::stack::{Frame, Stack};
+use std::sync::Arc;
+
+/// The depth of the simulated call chain.
+const DEPTH: usize = 64;
+
+fn invoke_fresh(stack: &mut Stack, cp: &Arc<ResolvedConstantPool>, depth: usize) {
+    if depth == 0 {
+        return;
+    }
+    stack.push_frame(Frame::allocate(4 + depth % 3, 8, cp.clone()));
+    stack
+        .current_frame_mut()
+        .operand_stack
+        .push_int(depth as i32);
+    invoke_fresh(stack, cp, depth - 1);
+    black_box(stack.pop_frame());
+}
+
+fn invoke_pooled(stack: &mut Stack, cp: &Arc<ResolvedConstantPool>, depth: usize) {
+    if depth == 0 {
+        return;
+    }
+    stack.push_new_frame(4 + depth % 3, 8, cp.clone());
+    stack
+        .current_frame_mut()
+        .operand_stack
+        .push_int(depth as i32);
+    invoke_pooled(stack, cp, depth - 1);
+    stack.drop_frame();
+}
+
+fn frame_pool(c: &mut Criterion) {
+    let cp = Arc::new(ResolvedConstantPool::new(&ConstantPool::from(vec![])));
+    let mut group = c.benchmark_group("invocation");
+    group.bench_function("fresh frames", |b| {
+        let mut stack = Stack::allocate(DEPTH);
+        b.iter(|| invoke_fresh(&mut stack, &cp, DEPTH))
+    });
+    group.bench_function("pooled frames", |b| {
+        let mut stack = Stack::allocate(DEPTH);
+        b.iter(|| invoke_pooled(&mut stack, &cp, DEPTH))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frame_pool);
+criterion_main!(benches);
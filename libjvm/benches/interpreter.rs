@@ -0,0 +1,168 @@
+//! Representative workloads for the interpreter, run against each of its
+//! configurations, see [`VARIANTS`]. The sources of the workloads are in
+//! `benches/resources/workloads`; compile them with `javac --release 17`.
+//!
+//! The interpreter doesn't execute methods yet, so for now the workloads
+//! measure the work that happens before execution: loading and linking their
+//! classes, and preparing the code of their methods for dispatch.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use libjava::bytecode::Op;
+use libjava::classfile::{AttributeInfo, ClassFile};
+use libjvm::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use libjvm::vm::code::Code;
+use libjvm::vm::VM;
+use libvfs::FileSystem;
+use std::fs::File;
+use std::io::Cursor;
+
+const WORKLOAD_DIR: &str = "benches/resources/workloads";
+
+struct Workload {
+    name: &'static str,
+    /// The classes of the workload, the one with the `run(int)` entry point
+    /// first.
+    classes: &'static [&'static str],
+}
+
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "arithmetic loop",
+        classes: &["Arith"],
+    },
+    Workload {
+        name: "virtual call chain",
+        classes: &["Calls", "Calls$Node", "Calls$Add", "Calls$Leaf"],
+    },
+    Workload {
+        name: "allocation storm",
+        classes: &["Alloc", "Alloc$Point"],
+    },
+    Workload {
+        name: "string building",
+        classes: &["Strings"],
+    },
+    Workload {
+        name: "recursion",
+        classes: &["Recursion"],
+    },
+];
+
+/// A configuration of the interpreter, given by how it prepares the code of a
+/// method for dispatch. Returns the number of instructions to dispatch.
+struct Variant {
+    name: &'static str,
+    prepare: fn(&[u8], &[usize]) -> usize,
+}
+
+const VARIANTS: &[Variant] = &[
+    Variant {
+        name: "ops",
+        prepare: parse_ops,
+    },
+    Variant {
+        name: "superinstructions",
+        prepare: decode,
+    },
+];
+
+/// Dispatches every op on its own, straight from the bytecode.
+fn parse_ops(code: &[u8], _entry_points: &[usize]) -> usize {
+    let mut cursor = Cursor::new(code);
+    let mut count = 0;
+    while (cursor.position() as usize) < code.len() {
+        let offset = cursor.position() as usize;
+        black_box(Op::parse_at(&mut cursor, offset).unwrap());
+        count += 1;
+    }
+    count
+}
+
+/// Dispatches the instructions of [`Code`], with common sequences fused.
+fn decode(code: &[u8], entry_points: &[usize]) -> usize {
+    Code::decode(code, entry_points)
+        .unwrap()
+        .instructions()
+        .len()
+}
+
+/// The code of every method of the workload, with the offsets of its
+/// exception handlers.
+fn methods(workload: &Workload) -> Vec<(Vec<u8>, Vec<usize>)> {
+    let mut methods = vec![];
+    for class in workload.classes {
+        let path = format!("{}/{}.class", WORKLOAD_DIR, class);
+        let class_file = ClassFile::parse(&mut File::open(path).unwrap()).unwrap();
+        for method in class_file.methods() {
+            for attribute in method.attributes() {
+                if let AttributeInfo::Code {
+                    code,
+                    exception_table,
+                    ..
+                } = attribute
+                {
+                    let handlers = exception_table
+                        .iter()
+                        .map(|entry| entry.handler_pc() as usize)
+                        .collect();
+                    methods.push((code.clone(), handlers));
+                }
+            }
+        }
+    }
+    methods
+}
+
+fn vm() -> VM {
+    VM::new(
+        FileSystem::new_os_fs(),
+        ClassPath::from(vec![
+            ClassPathEntry::Dir(WORKLOAD_DIR.into()),
+            ClassPathEntry::Dir("tests/resources/vm/classloader".into()),
+        ]),
+    )
+}
+
+fn load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+    for workload in WORKLOADS {
+        group.bench_function(workload.name, |b| {
+            b.iter_batched(
+                vm,
+                |mut vm| {
+                    for class in workload.classes {
+                        black_box(vm.for_name(class).unwrap());
+                    }
+                    vm
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn prepare(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prepare");
+    for workload in WORKLOADS {
+        let methods = methods(workload);
+        for variant in VARIANTS {
+            group.bench_with_input(
+                BenchmarkId::new(variant.name, workload.name),
+                &methods,
+                |b, methods| {
+                    b.iter(|| {
+                        methods
+                            .iter()
+                            .map(|(code, handlers)| (variant.prepare)(code, handlers))
+                            .sum::<usize>()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load, prepare);
+criterion_main!(benches);
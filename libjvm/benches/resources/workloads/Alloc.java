@@ -0,0 +1,22 @@
+public class Alloc {
+    static final class Point {
+        final int x;
+        final int y;
+
+        Point(int x, int y) {
+            this.x = x;
+            this.y = y;
+        }
+    }
+
+    static int run(int n) {
+        int sum = 0;
+        Object[] live = new Object[64];
+        for (int i = 0; i < n; i++) {
+            Point p = new Point(i, i + 1);
+            sum += p.x + p.y;
+            live[i & 63] = new int[i & 15];
+        }
+        return sum + live.length;
+    }
+}
@@ -0,0 +1,10 @@
+public class Arith {
+    static int run(int n) {
+        int sum = 0;
+        for (int i = 0; i < n; i++) {
+            sum += i * i;
+            sum ^= sum >>> 3;
+        }
+        return sum;
+    }
+}
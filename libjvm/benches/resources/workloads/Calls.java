@@ -0,0 +1,35 @@
+public class Calls {
+    static abstract class Node {
+        abstract int eval(int x);
+    }
+
+    static final class Add extends Node {
+        final Node next;
+
+        Add(Node next) {
+            this.next = next;
+        }
+
+        int eval(int x) {
+            return next.eval(x + 1);
+        }
+    }
+
+    static final class Leaf extends Node {
+        int eval(int x) {
+            return x;
+        }
+    }
+
+    static int run(int n) {
+        Node chain = new Leaf();
+        for (int i = 0; i < 16; i++) {
+            chain = new Add(chain);
+        }
+        int sum = 0;
+        for (int i = 0; i < n; i++) {
+            sum += chain.eval(i);
+        }
+        return sum;
+    }
+}
@@ -0,0 +1,9 @@
+public class Recursion {
+    static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    static int run(int n) {
+        return fib(n);
+    }
+}
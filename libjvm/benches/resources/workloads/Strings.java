@@ -0,0 +1,12 @@
+public class Strings {
+    static int run(int n) {
+        StringBuilder sb = new StringBuilder();
+        for (int i = 0; i < n; i++) {
+            sb.append(i).append(',');
+            if (sb.length() > 1024) {
+                sb.setLength(0);
+            }
+        }
+        return sb.toString().length();
+    }
+}
@@ -1,6 +1,6 @@
-#![feature(once_cell)]
-
 pub mod vm;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
 
 #[cfg(test)]
 mod tests {
@@ -1,14 +1,206 @@
-pub struct Heap {}
+use crate::vm::classloader::class::Class;
+use crate::vm::descriptor::DescriptorTable;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::symbol::{Symbol, SymbolTable};
+use std::collections::{HashMap, HashSet};
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex};
 
-impl Heap {
-    pub fn new() -> Self {
-        Self {}
+pub struct MethodArea {
+    /// The VM-wide symbol table, shared with the class loaders.
+    symbols: Arc<SymbolTable>,
+    /// The VM-wide table of parsed descriptors, shared with the class loaders.
+    descriptors: Arc<DescriptorTable>,
+    /// The memory accounting of the loaded classes, shared with the class
+    /// loaders.
+    metaspace: Arc<Metaspace>,
+    hierarchy: ClassHierarchy,
+}
+
+impl Default for MethodArea {
+    fn default() -> Self {
+        Self::new()
     }
 }
-pub struct MethodArea {}
 
 impl MethodArea {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            symbols: Arc::new(SymbolTable::new()),
+            descriptors: Arc::new(DescriptorTable::new()),
+            metaspace: Arc::new(Metaspace::new()),
+            hierarchy: ClassHierarchy::new(),
+        }
+    }
+
+    pub fn symbols(&self) -> &Arc<SymbolTable> {
+        &self.symbols
+    }
+
+    pub fn descriptors(&self) -> &Arc<DescriptorTable> {
+        &self.descriptors
+    }
+
+    pub fn metaspace(&self) -> &Arc<Metaspace> {
+        &self.metaspace
+    }
+
+    /// The subclasses and implementations of the loaded classes.
+    pub fn hierarchy(&self) -> &ClassHierarchy {
+        &self.hierarchy
+    }
+
+    pub fn hierarchy_mut(&mut self) -> &mut ClassHierarchy {
+        &mut self.hierarchy
+    }
+
+    /// The memory currently used by the loaded classes.
+    pub fn stats(&self) -> MethodAreaStats {
+        self.metaspace.usage()
+    }
+
+    /// The method area of a VM that is forked from the VM of this one, see
+    /// [`VmTemplate::fork`](crate::vm::template::VmTemplate::fork). It shares
+    /// the symbol and descriptor tables, which only ever grow, and starts with
+    /// a copy of the class hierarchy. Its metaspace has the same limit, but
+    /// only accounts for the classes that the forked VM loads itself.
+    pub fn fork(&self) -> Self {
+        let metaspace = Metaspace::new();
+        metaspace.set_limit(self.metaspace.limit());
+        Self {
+            symbols: self.symbols.clone(),
+            descriptors: self.descriptors.clone(),
+            metaspace: Arc::new(metaspace),
+            hierarchy: self.hierarchy.clone(),
+        }
+    }
+}
+
+/// The direct subtypes of the loaded classes and interfaces, by name: the
+/// subclasses of a class, and the subinterfaces and implementations of an
+/// interface. The hierarchy only grows, as classes are never unloaded.
+#[derive(Clone, Default)]
+pub struct ClassHierarchy {
+    subtypes: HashMap<Symbol, Vec<Symbol>>,
+}
+
+impl ClassHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a newly loaded class as a subtype of its superclass and its
+    /// superinterfaces.
+    pub fn record(&mut self, class: &Class) {
+        for supertype in class.super_class().into_iter().chain(class.interfaces()) {
+            self.subtypes
+                .entry(supertype.name().clone())
+                .or_default()
+                .push(class.name().clone());
+        }
+    }
+
+    /// The loaded classes and interfaces that directly extend or implement the
+    /// given one, in the order they were loaded.
+    pub fn direct_subtypes(&self, name: &str) -> &[Symbol] {
+        self.subtypes.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The loaded classes and interfaces that extend or implement the given
+    /// one, directly or indirectly, without the class itself. Each subtype is
+    /// returned once, even if it implements an interface on several paths.
+    pub fn subtypes(&self, name: &str) -> Vec<Symbol> {
+        let mut seen = HashSet::new();
+        let mut subtypes = vec![];
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            for subtype in self.direct_subtypes(name) {
+                if seen.insert(subtype.clone()) {
+                    subtypes.push(subtype.clone());
+                    pending.push(subtype);
+                }
+            }
+        }
+        subtypes
+    }
+}
+
+/// The approximate memory used by loaded classes, in bytes. Interned symbols
+/// and parsed descriptors are shared between classes and not included.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MethodAreaStats {
+    /// The number of loaded classes.
+    pub classes: usize,
+    /// The resolved metadata of the classes, like method tables and field
+    /// layouts.
+    pub class_metadata: usize,
+    /// The bytecode of the methods.
+    pub bytecode: usize,
+    /// The runtime constant pools, including the contents of Utf8 entries.
+    pub constant_pools: usize,
+}
+
+impl MethodAreaStats {
+    pub fn total(&self) -> usize {
+        self.class_metadata + self.bytecode + self.constant_pools
+    }
+}
+
+impl AddAssign for MethodAreaStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.classes += rhs.classes;
+        self.class_metadata += rhs.class_metadata;
+        self.bytecode += rhs.bytecode;
+        self.constant_pools += rhs.constant_pools;
+    }
+}
+
+/// Tracks the memory used by loaded classes, and enforces an optional limit
+/// like `-XX:MaxMetaspaceSize`.
+pub struct Metaspace {
+    usage: Mutex<MethodAreaStats>,
+    limit: Mutex<Option<usize>>,
+}
+
+impl Default for Metaspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metaspace {
+    pub fn new() -> Self {
+        Self {
+            usage: Mutex::new(MethodAreaStats::default()),
+            limit: Mutex::new(None),
+        }
+    }
+
+    pub fn usage(&self) -> MethodAreaStats {
+        *self.usage.lock().unwrap()
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        *self.limit.lock().unwrap()
+    }
+
+    /// Sets the maximum number of bytes that loaded classes may use, or removes
+    /// the limit. Classes that are already loaded are kept, even if they exceed
+    /// the new limit.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        *self.limit.lock().unwrap() = limit;
+    }
+
+    /// Accounts for the memory of a newly loaded class. Fails with an
+    /// `OutOfMemoryError: Metaspace` without accounting anything if the class
+    /// would exceed the limit.
+    pub fn allocate(&self, footprint: MethodAreaStats) -> Result<(), VmError> {
+        let limit = self.limit();
+        let mut usage = self.usage.lock().unwrap();
+        if limit.is_some_and(|limit| usage.total() + footprint.total() > limit) {
+            return Err(Exception::OutOfMemory("Metaspace".to_string()).into());
+        }
+        *usage += footprint;
+        Ok(())
     }
 }
@@ -0,0 +1,153 @@
+use libvfs::FileSystem;
+
+use crate::vm::classloader::classpath::ClassPath;
+use crate::vm::error::VmError;
+use crate::vm::module::ModuleMode;
+use crate::vm::scheduler::SchedulerMode;
+use crate::vm::VM;
+
+/// Configures a [`VM`] before it is created, see [`VM::builder`]. Every
+/// option has the default of [`VM::new`], except for the file system, which
+/// is the file system of the host like for [`VM::default`].
+///
+/// This is the same as calling the setters of the VM and its
+/// [`Runtime`](crate::vm::runtime::Runtime) right after creating it, before
+/// loading any class or creating any thread, which is when they have to be
+/// called.
+pub struct VmBuilder {
+    file_system: Option<FileSystem>,
+    class_path: ClassPath,
+    module_mode: ModuleMode,
+    scheduler_mode: SchedulerMode,
+    preview_enabled: bool,
+    max_metaspace_size: Option<usize>,
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    stack_guards: Option<bool>,
+}
+
+impl VmBuilder {
+    pub fn new() -> Self {
+        Self {
+            file_system: None,
+            class_path: ClassPath::from(vec![]),
+            module_mode: ModuleMode::default(),
+            scheduler_mode: SchedulerMode::default(),
+            preview_enabled: false,
+            max_metaspace_size: None,
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            stack_guards: None,
+        }
+    }
+
+    /// The file system that classes are loaded from and that all file I/O
+    /// of Java code goes through.
+    pub fn file_system(mut self, file_system: FileSystem) -> Self {
+        self.file_system = Some(file_system);
+        self
+    }
+
+    pub fn class_path(mut self, class_path: ClassPath) -> Self {
+        self.class_path = class_path;
+        self
+    }
+
+    /// See [`VM::set_module_mode`].
+    pub fn module_mode(mut self, mode: ModuleMode) -> Self {
+        self.module_mode = mode;
+        self
+    }
+
+    /// See [`Runtime::set_scheduler_mode`](crate::vm::runtime::Runtime::set_scheduler_mode).
+    pub fn scheduler_mode(mut self, mode: SchedulerMode) -> Self {
+        self.scheduler_mode = mode;
+        self
+    }
+
+    /// See [`VM::set_preview_enabled`].
+    pub fn preview_enabled(mut self, enabled: bool) -> Self {
+        self.preview_enabled = enabled;
+        self
+    }
+
+    /// See [`VM::set_max_metaspace_size`].
+    pub fn max_metaspace_size(mut self, limit: Option<usize>) -> Self {
+        self.max_metaspace_size = limit;
+        self
+    }
+
+    /// See [`Runtime::set_stack_guards`](crate::vm::runtime::Runtime::set_stack_guards).
+    /// Unless this is set, the stack guards are enabled in debug builds only.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn stack_guards(mut self, enabled: bool) -> Self {
+        self.stack_guards = Some(enabled);
+        self
+    }
+
+    /// Creates the VM. Fails like [`VM::set_module_mode`] if the boot layer
+    /// can't be built for [`ModuleMode::Strict`].
+    pub fn build(self) -> Result<VM, VmError> {
+        let file_system = self.file_system.unwrap_or_else(FileSystem::new_os_fs);
+        let mut vm = VM::new(file_system, self.class_path);
+        vm.set_module_mode(self.module_mode)?;
+        vm.set_preview_enabled(self.preview_enabled);
+        vm.set_max_metaspace_size(self.max_metaspace_size);
+        let runtime = vm.runtime();
+        runtime.set_scheduler_mode(self.scheduler_mode);
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if let Some(enabled) = self.stack_guards {
+            runtime.set_stack_guards(enabled);
+        }
+        Ok(vm)
+    }
+}
+
+impl Default for VmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let vm = VmBuilder::new()
+            .file_system(FileSystem::new_in_memory_fs())
+            .scheduler_mode(SchedulerMode::Green { workers: 2 })
+            .preview_enabled(true)
+            .build()
+            .unwrap();
+        assert!(vm.bootstrap_class_loader.is_preview_enabled());
+        assert_eq!(
+            SchedulerMode::Green { workers: 2 },
+            vm.runtime().scheduler_mode()
+        );
+        assert_eq!(ModuleMode::ClassPath, vm.runtime().module_mode());
+
+        let vm = VM::builder()
+            .file_system(FileSystem::new_in_memory_fs())
+            .build()
+            .unwrap();
+        assert!(!vm.bootstrap_class_loader.is_preview_enabled());
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    fn test_build_stack_guards() {
+        let build = |builder: VmBuilder| {
+            builder
+                .file_system(FileSystem::new_in_memory_fs())
+                .build()
+                .unwrap()
+        };
+        assert!(!build(VmBuilder::new().stack_guards(false))
+            .runtime()
+            .stack_guards());
+        assert_eq!(
+            cfg!(debug_assertions),
+            build(VmBuilder::new()).runtime().stack_guards()
+        );
+    }
+}
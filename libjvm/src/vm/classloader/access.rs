@@ -0,0 +1,549 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::error::{Exception, LinkageError, VmError};
+use libjava::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+
+/// The access level of a field or method, as given by its access flags.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Visibility {
+    Public,
+    Protected,
+    /// Neither public, protected nor private, i.e. only accessible from the
+    /// same run-time package.
+    Package,
+    Private,
+}
+
+impl Visibility {
+    pub fn of_field(flags: FieldAccessFlags) -> Self {
+        if flags.contains(FieldAccessFlags::PUBLIC) {
+            Visibility::Public
+        } else if flags.contains(FieldAccessFlags::PROTECTED) {
+            Visibility::Protected
+        } else if flags.contains(FieldAccessFlags::PRIVATE) {
+            Visibility::Private
+        } else {
+            Visibility::Package
+        }
+    }
+
+    pub fn of_method(flags: MethodAccessFlags) -> Self {
+        if flags.contains(MethodAccessFlags::PUBLIC) {
+            Visibility::Public
+        } else if flags.contains(MethodAccessFlags::PROTECTED) {
+            Visibility::Protected
+        } else if flags.contains(MethodAccessFlags::PRIVATE) {
+            Visibility::Private
+        } else {
+            Visibility::Package
+        }
+    }
+
+    /// The modifier of this access level in Java source, which is empty for
+    /// package access.
+    fn modifier(self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Protected => "protected",
+            Visibility::Package => "",
+            Visibility::Private => "private",
+        }
+    }
+
+    /// How this access level is called in error messages, e.g. `private`.
+    fn description(self) -> &'static str {
+        match self {
+            Visibility::Package => "package-private",
+            v => v.modifier(),
+        }
+    }
+}
+
+/// A field or method of a class, as seen by the access checks.
+#[derive(Copy, Clone)]
+pub struct Member<'a> {
+    /// The class that declares the member.
+    pub declaring_class: &'a Class,
+    pub name: &'a str,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    /// Whether the member is a method rather than a field.
+    pub is_method: bool,
+}
+
+/// Whether the class is accessible from the accessor class ([`$5.4.4`]): it is
+/// public and its module allows the access (see [`is_module_accessible`]), or
+/// it is in the same run-time package.
+///
+/// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+pub fn is_class_accessible(accessor: &Class, class: &Class) -> bool {
+    (class.access_flags().contains(ClassAccessFlags::PUBLIC)
+        && is_module_accessible(accessor, class))
+        || accessor.is_same_package(class)
+}
+
+/// Whether the module of the accessor class reads the module of the class, and
+/// that module exports the package of the class to the module of the accessor
+/// ([`$5.4.4`]). This always holds if one of the classes has no module, i.e.
+/// in [`ModuleMode::ClassPath`](crate::vm::module::ModuleMode::ClassPath).
+///
+/// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+pub fn is_module_accessible(accessor: &Class, class: &Class) -> bool {
+    module_denial(accessor, class).is_none()
+}
+
+/// Why the module of the class doesn't allow access from the accessor class,
+/// e.g. `module a does not export a.internal to module b`.
+fn module_denial(accessor: &Class, class: &Class) -> Option<String> {
+    let (Some(from), Some(to)) = (accessor.module(), class.module()) else {
+        return None;
+    };
+    let package = class.package_name();
+    if !from.can_read(to) {
+        Some(format!("{} does not read {}", from, to))
+    } else if !to.is_exported(package, from) {
+        Some(format!(
+            "{} does not export {} to {}",
+            to,
+            package.replace('/', "."),
+            from
+        ))
+    } else {
+        None
+    }
+}
+
+/// The message of the error if the module of the public class doesn't allow
+/// access from the accessor class, like the one of `Reflection.verifyModuleAccess`.
+fn module_access_message(accessor: &Class, class: &Class) -> Option<String> {
+    if !class.access_flags().contains(ClassAccessFlags::PUBLIC) {
+        return None;
+    }
+    let reason = module_denial(accessor, class)?;
+    let in_module = |c: &Class| c.module().map_or(String::new(), |m| format!(" (in {})", m));
+    Some(format!(
+        "class {}{} cannot access class {}{} because {}",
+        java_name(accessor),
+        in_module(accessor),
+        java_name(class),
+        in_module(class),
+        reason
+    ))
+}
+
+/// Whether the two classes belong to the same nest ([`$5.4.4`]), which gives
+/// them access to each other's private members.
+///
+/// Both classes must name the same nest host, and the host must list the other
+/// class as a member. The host is not loaded for this, so membership is only
+/// validated if one of the classes is the host itself.
+///
+/// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+pub fn are_nestmates(a: &Class, b: &Class) -> bool {
+    if std::ptr::eq(a, b) {
+        return true;
+    }
+    let host = a.nest_host_name();
+    if host != b.nest_host_name() {
+        return false;
+    }
+    let is_member = |member: &Class, other: &Class| {
+        member.name() == host || other.name() != host || other.has_nest_member(member.name())
+    };
+    is_member(a, b) && is_member(b, a)
+}
+
+/// Whether the member is accessible from the accessor class, if it was
+/// referenced through the class `referenced` ([`$5.4.4`]).
+///
+/// Protected members are accessible from the same run-time package, and from
+/// subclasses of the declaring class. In the latter case, a protected instance
+/// member must be referenced through the accessor class, a subclass or a
+/// superclass of it.
+///
+/// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+pub fn is_member_accessible(accessor: &Class, member: &Member, referenced: &Class) -> bool {
+    let declaring_class = member.declaring_class;
+    match member.visibility {
+        Visibility::Public => true,
+        Visibility::Protected => {
+            accessor.is_same_package(declaring_class)
+                || (accessor.is_subclass_of(declaring_class)
+                    && (member.is_static
+                        || referenced.is_subclass_of(accessor)
+                        || accessor.is_subclass_of(referenced)))
+        }
+        Visibility::Package => accessor.is_same_package(declaring_class),
+        Visibility::Private => are_nestmates(accessor, declaring_class),
+    }
+}
+
+/// Fails with an `IllegalAccessError` if the class is not accessible from the
+/// accessor class, see [`is_class_accessible`].
+pub fn check_class_access(accessor: &Class, class: &Class) -> Result<(), VmError> {
+    if is_class_accessible(accessor, class) {
+        return Ok(());
+    }
+    let message = module_access_message(accessor, class).unwrap_or_else(|| {
+        format!(
+            "failed to access class {} from class {}",
+            java_name(class),
+            java_name(accessor)
+        )
+    });
+    Err(LinkageError::IllegalAccess(message).into())
+}
+
+/// Fails with an `IllegalAccessError` if the member, referenced through the
+/// class `referenced`, is not accessible from the accessor class, see
+/// [`is_member_accessible`]. `referenced` itself must be accessible as well.
+pub fn check_member_access(
+    accessor: &Class,
+    member: &Member,
+    referenced: &Class,
+) -> Result<(), VmError> {
+    check_class_access(accessor, referenced)?;
+    if is_member_accessible(accessor, member, referenced) {
+        return Ok(());
+    }
+    Err(LinkageError::IllegalAccess(format!(
+        "class {} tried to access {} {} {}.{}",
+        java_name(accessor),
+        member.visibility.description(),
+        if member.is_method { "method" } else { "field" },
+        java_name(member.declaring_class),
+        member.name
+    ))
+    .into())
+}
+
+/// Fails with an `IllegalAccessError` if a protected instance member of a
+/// class in another run-time package is accessed on an object that is neither
+/// an instance of the accessor class nor of a subclass of it, as required for
+/// `getfield`, `putfield`, `invokevirtual` and `invokespecial` ([`$4.10.1.8`]).
+///
+/// [`$4.10.1.8`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.8
+pub fn check_protected_receiver(
+    accessor: &Class,
+    member: &Member,
+    receiver: &Class,
+) -> Result<(), VmError> {
+    if is_protected_receiver_allowed(accessor, member, receiver) {
+        return Ok(());
+    }
+    Err(LinkageError::IllegalAccess(format!(
+        "class {} tried to access protected {} {}.{} of an instance of {}",
+        java_name(accessor),
+        if member.is_method { "method" } else { "field" },
+        java_name(member.declaring_class),
+        member.name,
+        java_name(receiver)
+    ))
+    .into())
+}
+
+/// Fails with an `IllegalAccessException` if reflection from the caller class
+/// may not access the member, as checked by `Reflection.verifyMemberAccess`,
+/// or its module doesn't allow the access, see [`is_module_accessible`].
+/// `target` is the class of the object that an instance member is accessed
+/// on, which is subject to the same rule as in [`check_protected_receiver`].
+pub fn check_reflective_access(
+    caller: &Class,
+    member: &Member,
+    target: Option<&Class>,
+) -> Result<(), VmError> {
+    let declaring_class = member.declaring_class;
+    if let Some(message) = module_access_message(caller, declaring_class) {
+        return Err(Exception::IllegalAccess(message).into());
+    }
+    let allowed = std::ptr::eq(caller, declaring_class)
+        || (is_class_accessible(caller, declaring_class)
+            && match member.visibility {
+                Visibility::Public => true,
+                Visibility::Protected => {
+                    (caller.is_same_package(declaring_class)
+                        || caller.is_subclass_of(declaring_class))
+                        && target.is_none_or(|t| is_protected_receiver_allowed(caller, member, t))
+                }
+                Visibility::Package => caller.is_same_package(declaring_class),
+                Visibility::Private => are_nestmates(caller, declaring_class),
+            });
+    if allowed {
+        return Ok(());
+    }
+    Err(Exception::IllegalAccess(format!(
+        "class {} cannot access a member of class {} with modifiers \"{}\"",
+        java_name(caller),
+        java_name(declaring_class),
+        modifiers(member)
+    ))
+    .into())
+}
+
+fn is_protected_receiver_allowed(accessor: &Class, member: &Member, receiver: &Class) -> bool {
+    member.visibility != Visibility::Protected
+        || member.is_static
+        || accessor.is_same_package(member.declaring_class)
+        || receiver.is_subclass_of(accessor)
+}
+
+fn modifiers(member: &Member) -> String {
+    match (member.visibility.modifier(), member.is_static) {
+        ("", true) => "static".to_string(),
+        (modifier, true) => format!("{} static", modifier),
+        (modifier, false) => modifier.to_string(),
+    }
+}
+
+fn java_name(class: &Class) -> String {
+    class.name().replace('/', ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::bootstrap::BootstrapClassLoader;
+    use crate::vm::classloader::resolve::{resolve_field, resolve_invoke, InvokeKind};
+    use crate::vm::classloader::test_util::{class_loader, class_path};
+    use crate::vm::classloader::ClassLoader;
+    use crate::vm::descriptor::DescriptorTable;
+    use crate::vm::module::ModuleLayer;
+    use crate::vm::symbol::SymbolTable;
+    use libvfs::FileSystem;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    fn load(names: [&str; 7]) -> [Rc<Class>; 7] {
+        let mut loader = class_loader(&["access"]);
+        names.map(|name| loader.find_or_load_class(name).unwrap())
+    }
+
+    fn classes() -> [Rc<Class>; 7] {
+        load([
+            "p/Base",
+            "p/Peer",
+            "p/Hidden",
+            "p/Outer",
+            "p/Outer$Inner",
+            "q/Derived",
+            "q/Other",
+        ])
+    }
+
+    fn field(accessor: &Class, class: &Rc<Class>, name: &str) -> Result<(), &'static str> {
+        resolve_field(accessor, class, name, "I")
+            .map(|_| ())
+            .map_err(|e| e.class_name())
+    }
+
+    #[test]
+    fn test_field_access() {
+        let [base, peer, hidden, _, _, derived, other] = classes();
+        let denied = Err("java/lang/IllegalAccessError");
+
+        assert_eq!(Ok(()), field(&other, &base, "publicField"));
+        assert_eq!(denied, field(&other, &base, "protectedField"));
+        assert_eq!(denied, field(&other, &base, "packageField"));
+        assert_eq!(denied, field(&other, &base, "privateField"));
+
+        assert_eq!(Ok(()), field(&peer, &base, "protectedField"));
+        assert_eq!(Ok(()), field(&peer, &base, "packageField"));
+        assert_eq!(denied, field(&peer, &base, "privateField"));
+
+        // protected members are inherited, but package members are not
+        // accessible from another package
+        assert_eq!(Ok(()), field(&derived, &derived, "protectedField"));
+        assert_eq!(Ok(()), field(&derived, &base, "protectedField"));
+        assert_eq!(Ok(()), field(&derived, &derived, "protectedStatic"));
+        assert_eq!(denied, field(&derived, &derived, "packageField"));
+        assert_eq!(denied, field(&other, &derived, "protectedField"));
+
+        // the class must be accessible, whatever the member is
+        assert_eq!(Ok(()), field(&peer, &hidden, "value"));
+        assert_eq!(denied, field(&other, &hidden, "value"));
+
+        assert_eq!(
+            Err("java/lang/NoSuchFieldError"),
+            field(&base, &base, "missing")
+        );
+        assert_eq!(
+            "java.lang.IllegalAccessError: class q.Other tried to access private field p.Base.privateField",
+            resolve_field(&other, &base, "privateField", "I")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "java.lang.IllegalAccessError: failed to access class p.Hidden from class q.Other",
+            resolve_field(&other, &hidden, "value", "I")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_method_access() {
+        let [base, peer, _, _, _, derived, other] = classes();
+        let invoke = |accessor: &Class, class: &Rc<Class>, name: &str, kind: InvokeKind| {
+            resolve_invoke(accessor, kind, class, name, "()I", false)
+                .map(|_| ())
+                .map_err(|e| e.class_name())
+        };
+        let (virt, stat) = (InvokeKind::Virtual, InvokeKind::Static);
+        let denied = Err("java/lang/IllegalAccessError");
+
+        assert_eq!(Ok(()), invoke(&other, &base, "publicMethod", virt));
+        assert_eq!(denied, invoke(&other, &base, "protectedMethod", virt));
+        assert_eq!(denied, invoke(&other, &base, "packageMethod", virt));
+        assert_eq!(denied, invoke(&other, &base, "privateMethod", virt));
+        assert_eq!(Ok(()), invoke(&peer, &base, "packageMethod", virt));
+        assert_eq!(Ok(()), invoke(&derived, &derived, "protectedMethod", virt));
+        assert_eq!(
+            Ok(()),
+            invoke(&derived, &base, "protectedStaticMethod", stat)
+        );
+        assert_eq!(denied, invoke(&derived, &base, "privateMethod", virt));
+        assert_eq!(
+            "java.lang.IllegalAccessError: class q.Derived tried to access package-private method p.Base.packageMethod",
+            resolve_invoke(&derived, virt, &base, "packageMethod", "()I", false)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_nestmates() {
+        let [_, peer, _, outer, inner, ..] = classes();
+        assert!(are_nestmates(&outer, &inner));
+        assert!(are_nestmates(&inner, &outer));
+        assert!(!are_nestmates(&outer, &peer));
+        assert!(!are_nestmates(&peer, &inner));
+
+        assert_eq!(Ok(()), field(&inner, &outer, "secret"));
+        assert_eq!(Ok(()), field(&outer, &inner, "inner"));
+        assert_eq!(
+            Err("java/lang/IllegalAccessError"),
+            field(&peer, &outer, "secret")
+        );
+    }
+
+    #[test]
+    fn test_protected_receiver() {
+        let [base, peer, _, _, _, derived, other] = classes();
+        let resolved = resolve_field(&derived, &base, "protectedField", "I").unwrap();
+        let member = resolved.as_member();
+
+        // a subclass in another package may only access protected instance
+        // members on instances of itself
+        assert!(check_protected_receiver(&derived, &member, &derived).is_ok());
+        assert!(check_protected_receiver(&derived, &member, &base).is_err());
+        // the same package may access any instance
+        assert!(check_protected_receiver(&peer, &member, &base).is_ok());
+
+        let statics = resolve_field(&derived, &base, "protectedStatic", "I").unwrap();
+        assert!(check_protected_receiver(&derived, &statics.as_member(), &other).is_ok());
+    }
+
+    #[test]
+    fn test_module_access() {
+        let class_path = class_path(&[
+            "modules/a",
+            "modules/b",
+            "modules/c",
+            "modules/d",
+            "modules/unnamed",
+        ]);
+        let fs = FileSystem::new_os_fs();
+        let layer = Arc::new(ModuleLayer::boot(&fs, &class_path).unwrap());
+        let mut loader = BootstrapClassLoader::new(
+            fs,
+            class_path,
+            Arc::new(SymbolTable::new()),
+            Arc::new(DescriptorTable::new()),
+        );
+        loader.set_module_layer(Some(layer.clone()));
+        let mut load = |name: &str| loader.find_or_load_class(name).unwrap();
+        let api = load("a/api/Api");
+        let secret = load("a/internal/Secret");
+        let friend = load("a/friend/Friend");
+        let [b, c, d, u] = ["b/B", "c/C", "d/D", "u/U"].map(&mut load);
+        assert_eq!(Some("a"), api.module().and_then(|m| m.name()));
+        assert!(u.module().is_some_and(|m| !m.is_named()));
+
+        // b and c read a, d doesn't
+        assert!(is_class_accessible(&b, &api));
+        assert!(is_class_accessible(&c, &api));
+        assert!(!is_class_accessible(&d, &api));
+        assert!(is_class_accessible(&u, &api));
+        // the friend package is only exported to b, and the internal package
+        // is only opened to c
+        assert!(is_class_accessible(&b, &friend));
+        assert!(!is_class_accessible(&c, &friend));
+        assert!(!is_class_accessible(&b, &secret));
+        assert!(is_class_accessible(&c, &secret));
+        assert!(!is_class_accessible(&u, &secret));
+        // without a java.base module, named modules read the unnamed module,
+        // which has the classes of the JDK
+        assert!(is_class_accessible(&b, &u));
+
+        assert_eq!(
+            "java.lang.IllegalAccessError: class b.B (in module b) cannot access class a.internal.Secret (in module a) because module a does not export a.internal to module b",
+            resolve_field(&b, &secret, "value", "I").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "java.lang.IllegalAccessError: class d.D (in module d) cannot access class a.api.Api (in module a) because module d does not read module a",
+            resolve_field(&d, &api, "value", "I").unwrap_err().to_string()
+        );
+        let secret_value = resolve_field(&secret, &secret, "value", "I").unwrap();
+        assert_eq!(
+            "java.lang.IllegalAccessException: class u.U (in unnamed module) cannot access class a.internal.Secret (in module a) because module a does not export a.internal to unnamed module",
+            check_reflective_access(&u, &secret_value.as_member(), None)
+                .unwrap_err()
+                .to_string()
+        );
+        let api_value = resolve_field(&api, &api, "value", "I").unwrap();
+        assert!(check_reflective_access(&u, &api_value.as_member(), None).is_ok());
+
+        // reads and exports can be added at run time
+        d.module().unwrap().add_reads(api.module().unwrap());
+        assert!(is_class_accessible(&d, &api));
+        layer
+            .find_module("a")
+            .unwrap()
+            .add_exports("a/internal", None)
+            .unwrap();
+        assert!(is_class_accessible(&b, &secret));
+    }
+
+    #[test]
+    fn test_reflective_access() {
+        let [base, peer, _, outer, inner, derived, other] = classes();
+        let member = |name: &str| resolve_field(&base, &base, name, "I").unwrap();
+
+        let private = member("privateField");
+        assert_eq!(
+            Err(Exception::IllegalAccess(
+                "class q.Other cannot access a member of class p.Base with modifiers \"private\""
+                    .into()
+            )
+            .into()),
+            check_reflective_access(&other, &private.as_member(), None)
+        );
+        assert!(check_reflective_access(&base, &private.as_member(), None).is_ok());
+
+        let protected = member("protectedField");
+        let protected = protected.as_member();
+        assert!(check_reflective_access(&derived, &protected, Some(&derived)).is_ok());
+        assert!(check_reflective_access(&derived, &protected, Some(&base)).is_err());
+        assert!(check_reflective_access(&peer, &protected, Some(&base)).is_ok());
+
+        let statics = member("protectedStatic");
+        assert_eq!(
+            "java.lang.IllegalAccessException: class q.Other cannot access a member of class p.Base with modifiers \"protected static\"",
+            check_reflective_access(&other, &statics.as_member(), None)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let secret = resolve_field(&outer, &outer, "secret", "I").unwrap();
+        assert!(check_reflective_access(&inner, &secret.as_member(), None).is_ok());
+    }
+}
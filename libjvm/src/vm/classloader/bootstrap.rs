@@ -1,27 +1,195 @@
+use crate::vm::area::Metaspace;
 use crate::vm::classloader::class::Class;
 use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use crate::vm::classloader::jar::JarFile;
+use crate::vm::classloader::services::{parse_provider_configuration, SERVICES_DIR};
+use crate::vm::classloader::trace::{ClassLoadEvent, ClassLoadTrace, ClassSource};
 use crate::vm::classloader::ClassLoader;
-use libjava::classfile::ClassFile;
+use crate::vm::clock::Instant;
+use crate::vm::descriptor::DescriptorTable;
+use crate::vm::error::{Exception, LinkageError, VmError};
+use crate::vm::module::ModuleLayer;
+use crate::vm::symbol::SymbolTable;
+use libjava::classfile::flags::ClassAccessFlags;
+use libjava::classfile::{ClassFile, ConstantPoolError, ParseMode, Version};
 use libvfs::file::File;
+use libvfs::path::VfsPath;
 use libvfs::FileSystem;
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufReader, Cursor, ErrorKind, Read};
 use std::rc::Rc;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A callback that is notified whenever a class is loaded, after its
+/// superclasses, e.g. so that compiled code that assumed that a class has no
+/// subclasses can be deoptimized.
+pub type LoadListener = Box<dyn FnMut(&Rc<Class>)>;
 
 pub struct BootstrapClassLoader {
     fs: FileSystem,
     class_path: ClassPath,
     loaded_classes: Vec<Rc<Class>>,
+    /// The classes that are currently being loaded, to detect classes that are
+    /// their own superclass.
+    loading: Vec<String>,
+    symbols: Arc<SymbolTable>,
+    descriptors: Arc<DescriptorTable>,
+    trace: Option<ClassLoadTrace>,
+    load_listeners: Vec<LoadListener>,
+    /// The memory accounting of the loaded classes, unlimited unless the VM
+    /// shares its own with [`BootstrapClassLoader::set_metaspace`].
+    metaspace: Arc<Metaspace>,
+    /// The layer whose modules the loaded classes are put into, if the VM
+    /// enforces module boundaries.
+    module_layer: Option<Arc<ModuleLayer>>,
+    /// The jar files of the class path that were opened so far, by their path,
+    /// or [`None`] for those that are missing or not valid jar files, which are
+    /// skipped like the JDK does.
+    jars: RefCell<HashMap<String, Option<Rc<JarFile>>>>,
+    /// Whether classes that depend on preview features are loaded, like
+    /// `--enable-preview`.
+    preview_enabled: bool,
+}
+
+/// The major version of the class files whose preview features the VM
+/// recognizes, the one of Java SE 17 like the class library.
+const PREVIEW_MAJOR_VERSION: u16 = 61;
+
+/// A resource that was found in an entry of the class path.
+enum Resource {
+    File(VfsPath),
+    JarEntry(Rc<JarFile>, String),
+}
+
+impl Resource {
+    fn open(&self) -> std::io::Result<File> {
+        match self {
+            Resource::File(path) => path.open(),
+            Resource::JarEntry(jar, entry) => match jar.read(entry)? {
+                Some(bytes) => Ok(File::new(Box::new(Cursor::new(bytes)))),
+                None => Err(std::io::Error::new(ErrorKind::NotFound, entry.clone())),
+            },
+        }
+    }
 }
 
 impl BootstrapClassLoader {
-    pub fn new(fs: FileSystem, class_path: ClassPath) -> Self {
+    pub fn new(
+        fs: FileSystem,
+        class_path: ClassPath,
+        symbols: Arc<SymbolTable>,
+        descriptors: Arc<DescriptorTable>,
+    ) -> Self {
         Self {
             fs,
             class_path,
             loaded_classes: vec![],
+            loading: vec![],
+            symbols,
+            descriptors,
+            trace: None,
+            load_listeners: vec![],
+            metaspace: Arc::new(Metaspace::new()),
+            module_layer: None,
+            jars: RefCell::new(HashMap::new()),
+            preview_enabled: false,
+        }
+    }
+
+    /// Accounts the memory of every class that is loaded from now on in the
+    /// given metaspace, and fails to load classes that exceed its limit.
+    pub fn set_metaspace(&mut self, metaspace: Arc<Metaspace>) {
+        self.metaspace = metaspace;
+    }
+
+    /// Records every class that is loaded from now on in the given trace.
+    pub fn set_trace(&mut self, trace: ClassLoadTrace) {
+        self.trace = Some(trace);
+    }
+
+    /// Puts every class that is loaded from now on into the module of its
+    /// package in the given layer, or the unnamed module, so that the module
+    /// checks of [`ModuleMode::Strict`](crate::vm::module::ModuleMode::Strict)
+    /// apply to it. With [`None`], classes have no module.
+    pub fn set_module_layer(&mut self, layer: Option<Arc<ModuleLayer>>) {
+        self.module_layer = layer;
+    }
+
+    /// Whether classes compiled with `--enable-preview` are loaded, which they
+    /// are not by default.
+    pub fn is_preview_enabled(&self) -> bool {
+        self.preview_enabled
+    }
+
+    /// Loads classes compiled with `--enable-preview` from now on, like the
+    /// `--enable-preview` option of `java`, if they depend on the preview
+    /// features of Java SE 17. Classes that depend on the preview features of
+    /// other releases are never loaded.
+    pub fn set_preview_enabled(&mut self, enabled: bool) {
+        self.preview_enabled = enabled;
+    }
+
+    pub fn add_load_listener(&mut self, listener: LoadListener) {
+        self.load_listeners.push(listener);
+    }
+
+    pub fn class_path(&self) -> &ClassPath {
+        &self.class_path
+    }
+
+    /// The classes loaded by this class loader, each after its superclass and
+    /// superinterfaces.
+    pub fn loaded_classes(&self) -> &[Rc<Class>] {
+        &self.loaded_classes
+    }
+
+    /// Adds classes that another class loader with the same symbol table
+    /// loaded, each after its superclass and superinterfaces, as if this class
+    /// loader had loaded them. The load listeners are not notified. This is
+    /// how a VM forked from a [`VmTemplate`](crate::vm::template::VmTemplate)
+    /// shares the classes of the template.
+    pub(crate) fn share_classes(&mut self, classes: &[Rc<Class>]) {
+        self.loaded_classes.extend(classes.iter().cloned());
+    }
+
+    /// Defines a class from the bytes of its class file instead of the class
+    /// path, like `ClassLoader.defineClass`, e.g. to restore a snapshot. The
+    /// superclass and superinterfaces are loaded from the class path if they
+    /// are not loaded yet. Fails with a `LinkageError` if a class with the name
+    /// is loaded already, or if the class file is not the one of the class.
+    pub fn define_class(
+        &mut self,
+        name: &str,
+        bytes: &[u8],
+        source: ClassSource,
+    ) -> Result<Rc<Class>, VmError> {
+        if self.find_class(name).is_some() {
+            return Err(LinkageError::DuplicateClass(format!(
+                "loader 'bootstrap' attempted duplicate class definition for {}.",
+                name.replace('/', ".")
+            ))
+            .into());
         }
+        let start = Instant::now();
+        let class_file = ClassFile::parse_with(&mut &bytes[..], ParseMode::Lazy)
+            .map_err(|e| LinkageError::ClassFormat(format!("{} in class file {}", e, name)))?;
+        let this_class = class_file
+            .this_class_name()
+            .map_err(|e| LinkageError::ClassFormat(format!("{:?} in class file {}", e, name)))?;
+        if this_class != name {
+            return Err(LinkageError::NoClassDefFound(format!(
+                "{} (wrong name: {})",
+                name, this_class
+            ))
+            .into());
+        }
+
+        self.loading.push(name.to_string());
+        let result = self.define(name, class_file, source, start.elapsed());
+        self.loading.pop();
+        result
     }
 }
 
@@ -34,58 +202,285 @@ impl ClassLoader for BootstrapClassLoader {
     where
         N: AsRef<str>,
     {
-        let path = n.as_ref();
+        // a name that was never interned can't be the name of a loaded class
+        let name = self.symbols.lookup(n.as_ref())?;
         self.loaded_classes
             .iter()
-            .find(|c| c.name() == path)
+            .find(|c| *c.name() == name)
             .cloned()
     }
 
-    fn find_or_load_class<N>(&mut self, n: N) -> Option<Rc<Class>>
+    fn find_or_load_class<N>(&mut self, n: N) -> Result<Rc<Class>, VmError>
     where
         N: AsRef<str>,
     {
-        let mut path = String::from(n.as_ref());
-        path.push_str(".class");
-
-        let file = {
-            let mut file_opt: Option<File> = None;
-            for entry in self.class_path.entries() {
-                let entry_path = match entry {
-                    ClassPathEntry::Dir(s) => s.clone(),
-                    ClassPathEntry::JarFile(_) => unimplemented!("jar class loading"),
-                };
-
-                let mut p = PathBuf::from(&entry_path);
-                p.push(path.as_str());
-                if self.fs.exists(&p).unwrap_or(false) {
-                    file_opt = Some(self.fs.open(&p).expect("unable to open file"));
-                }
-            }
-            if file_opt.is_none() {
-                // no matching file found in the classpath
-                return None;
-            }
-            file_opt.unwrap()
-        };
+        let name = n.as_ref();
+        if let Some(class) = self.find_class(name) {
+            return Ok(class);
+        }
+        if self.loading.iter().any(|c| c == name) {
+            return Err(LinkageError::ClassCircularity(name.to_string()).into());
+        }
+
+        self.loading.push(name.to_string());
+        let result = self.load_class(name);
+        self.loading.pop();
+        result
+    }
+
+    fn resource_url(&self, name: &str) -> Option<String> {
+        let (_, source) = self.find_resource(name)?;
+        Some(source.to_string())
+    }
+
+    fn resource_urls(&self, name: &str) -> Vec<String> {
+        self.resources(name)
+            .map(|(_, source)| source.to_string())
+            .collect()
+    }
 
-        let mut rd = BufReader::new(file);
-        let parse_result = ClassFile::parse(&mut rd);
-        let class_file = match parse_result {
-            Ok(f) => f,
-            Err(_) => return None,
+    fn open_resource(&self, name: &str) -> std::io::Result<File> {
+        match self.find_resource(name) {
+            Some((resource, _)) => resource.open(),
+            None => Err(std::io::Error::new(ErrorKind::NotFound, name.to_string())),
+        }
+    }
+}
+
+/// Fails with a `ClassFormatError` if a class other than `java/lang/Object`
+/// has no superclass, or if an interface has another superclass than
+/// `java/lang/Object` ([`$4.1`]). This is checked before the superclass is
+/// loaded.
+///
+/// [`$4.1`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1
+fn check_super_class_name(name: &str, class_file: &ClassFile) -> Result<(), VmError> {
+    let super_class = class_file.super_class();
+    let message = match super_class.as_deref() {
+        None if name != "java/lang/Object" => "Invalid superclass index 0",
+        Some(s)
+            if class_file
+                .access_flags()
+                .contains(ClassAccessFlags::INTERFACE)
+                && s != "java/lang/Object" =>
+        {
+            "Interfaces must have java.lang.Object as superclass"
+        }
+        _ => return Ok(()),
+    };
+    Err(LinkageError::ClassFormat(format!("{} in class file {}", message, name)).into())
+}
+
+/// Fails with an `UnsupportedClassVersionError` if the class file depends on
+/// preview features, and they are not enabled or not the ones of the release
+/// that the VM implements, with the messages of HotSpot.
+fn check_version(name: &str, class_file: &ClassFile, preview_enabled: bool) -> Result<(), VmError> {
+    let version = class_file.version();
+    if !version.is_preview() {
+        return Ok(());
+    }
+    let message = if version.major() != PREVIEW_MAJOR_VERSION {
+        format!(
+            "{} (class file version {}) was compiled with preview features that are unsupported. \
+             This version of the Java Runtime only recognizes preview features for class file \
+             version {}",
+            name,
+            version,
+            Version::new(PREVIEW_MAJOR_VERSION, Version::PREVIEW_MINOR)
+        )
+    } else if !preview_enabled {
+        format!(
+            "Preview features are not enabled for {} (class file version {}). \
+             Try running with '--enable-preview'",
+            name, version
+        )
+    } else {
+        return Ok(());
+    };
+    Err(LinkageError::UnsupportedClassVersion(message).into())
+}
+
+impl BootstrapClassLoader {
+    fn load_class(&mut self, name: &str) -> Result<Rc<Class>, VmError> {
+        let start = Instant::now();
+        let (file, source) = self.open_class_file(name)?;
+        let class_file = ClassFile::parse_with(&mut BufReader::new(file), ParseMode::Lazy)
+            .map_err(|e| LinkageError::ClassFormat(format!("{} in class file {}", e, name)))?;
+        self.define(name, class_file, source, start.elapsed())
+    }
+
+    /// Defines the class with the given name from its parsed class file,
+    /// loading its superclass and superinterfaces first. `duration` is the time
+    /// it took to read and parse the class file, for the trace.
+    fn define(
+        &mut self,
+        name: &str,
+        class_file: ClassFile,
+        source: ClassSource,
+        mut duration: Duration,
+    ) -> Result<Rc<Class>, VmError> {
+        check_version(name, &class_file, self.preview_enabled)?;
+        check_super_class_name(name, &class_file)?;
+        let format_error = |e: ConstantPoolError| {
+            LinkageError::ClassFormat(format!("{:?} in class file {}", e, name))
+        };
+        // the superclass has to be loaded first, since the field layout of this
+        // class depends on it. Superinterfaces are loaded eagerly as well, since
+        // method resolution searches them. A class that is still being loaded
+        // further up fails with a ClassCircularityError
+        let super_class = match class_file.super_class_name().map_err(format_error)? {
+            Some(super_name) => Some(self.find_or_load_class(super_name)?),
+            None => None,
         };
+        let interfaces = class_file
+            .interface_names()
+            .map_err(format_error)?
+            .iter()
+            .map(|interface| self.find_or_load_class(interface))
+            .collect::<Result<_, _>>()?;
+
+        let start = Instant::now();
+        let mut class = Class::new(
+            class_file,
+            &self.symbols,
+            &self.descriptors,
+            super_class,
+            interfaces,
+        )?;
+        if let Some(layer) = &self.module_layer {
+            class.set_module(layer.module_of_package(class.package_name()).clone());
+        }
+        duration += start.elapsed();
+        self.metaspace.allocate(class.footprint())?;
+
+        if let Some(trace) = &mut self.trace {
+            trace.record(&ClassLoadEvent {
+                name: name.to_string(),
+                source,
+                loader: "bootstrap".to_string(),
+                duration,
+            });
+        }
 
-        let class = Class::from(class_file);
         let rc = Rc::new(class);
         self.loaded_classes.push(rc.clone());
-        Some(rc)
+        for listener in &mut self.load_listeners {
+            listener(&rc);
+        }
+        Ok(rc)
+    }
+
+    /// Reads the class file of the given class from the first class path entry
+    /// that contains it, e.g. to store it in a snapshot.
+    pub fn read_class_file(&self, name: &str) -> Result<(Vec<u8>, ClassSource), VmError> {
+        let (mut file, source) = self.open_class_file(name)?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+        Ok((bytes, source))
+    }
+
+    /// Opens the class file of the given class in the first class path entry
+    /// that contains it.
+    fn open_class_file(&self, name: &str) -> Result<(File, ClassSource), VmError> {
+        match self.find_resource(&format!("{}.class", name)) {
+            Some((resource, source)) => Ok((resource.open()?, source)),
+            None => Err(LinkageError::NoClassDefFound(name.to_string()).into()),
+        }
+    }
+
+    /// Returns the binary names of the providers of the given service, e.g.
+    /// `com/example/FastDriver` for `java/sql/Driver`, from the
+    /// provider-configuration files in `META-INF/services` of all class path
+    /// entries, in the order of the class path. This is the lookup that
+    /// `ServiceLoader` does on the class path. A provider that is named in
+    /// more than one file is only returned once. Fails with a
+    /// `ServiceConfigurationError` if a file can't be read or is malformed.
+    pub fn service_providers(&self, service: &str) -> Result<Vec<String>, VmError> {
+        let service = service.replace('/', ".");
+        let mut providers: Vec<String> = vec![];
+        for (resource, source) in self.resources(&format!("{}/{}", SERVICES_DIR, service)) {
+            let url = source.to_string();
+            let mut contents = vec![];
+            resource
+                .open()
+                .and_then(|mut file| file.read_to_end(&mut contents))
+                .map_err(|e| {
+                    Exception::ServiceConfiguration(format!(
+                        "{}: Error reading configuration file {}: {}",
+                        service, url, e
+                    ))
+                })?;
+            for provider in parse_provider_configuration(&service, &url, &contents)? {
+                let provider = provider.replace('.', "/");
+                if !providers.contains(&provider) {
+                    providers.push(provider);
+                }
+            }
+        }
+        Ok(providers)
+    }
+
+    /// Finds the file with the given path, relative to the class path entries,
+    /// in the first entry that contains it.
+    fn find_resource(&self, name: &str) -> Option<(Resource, ClassSource)> {
+        self.resources(name).next()
+    }
+
+    /// Finds the files with the given path, relative to the class path
+    /// entries, in every entry that contains it, in the order of the class
+    /// path. Paths that are absolute or contain `.` or `..` segments are never
+    /// found, so that resources can't be read from outside of the class path.
+    fn resources<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = (Resource, ClassSource)> + 'a {
+        let valid = !name.is_empty()
+            && !name.starts_with('/')
+            && !name
+                .split('/')
+                .any(|s| s.is_empty() || s == "." || s == "..");
+        self.class_path
+            .entries()
+            .filter(move |_| valid)
+            .filter_map(move |entry| match entry {
+                ClassPathEntry::Dir(s) => {
+                    let path = self.fs.path(s).join(name);
+                    if !path.exists().unwrap_or(false) || path.is_dir().unwrap_or(true) {
+                        return None;
+                    }
+                    let source = ClassSource::Dir(format!("{}/{}", s.trim_end_matches('/'), name));
+                    Some((Resource::File(path), source))
+                }
+                ClassPathEntry::JarFile(s) => {
+                    let jar = self.jar(s)?;
+                    if !jar.contains(name) {
+                        return None;
+                    }
+                    let source = ClassSource::JarEntry {
+                        jar: s.clone(),
+                        entry: name.to_string(),
+                    };
+                    Some((Resource::JarEntry(jar, name.to_string()), source))
+                }
+            })
+    }
+
+    /// Opens the jar file with the given path the first time it is asked for,
+    /// or returns [`None`] if it is missing or not a valid jar file.
+    fn jar(&self, path: &str) -> Option<Rc<JarFile>> {
+        self.jars
+            .borrow_mut()
+            .entry(path.to_string())
+            .or_insert_with(|| JarFile::open(self.fs.path(path)).ok().map(Rc::new))
+            .clone()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vm::classloader::test_util::class_loader;
+    use std::cell::RefCell;
+    use std::io::{Read, Write};
 
     #[test]
     fn test_load_class() {
@@ -95,10 +490,348 @@ mod tests {
             ClassPath::from(vec![ClassPathEntry::Dir(
                 "tests/resources/vm/classloader".into(),
             )]),
+            Arc::new(SymbolTable::new()),
+            Arc::new(DescriptorTable::new()),
         );
         let res = class_loader.find_or_load_class("Test1");
-        assert!(res.is_some());
+        assert!(res.is_ok());
         let class = res.unwrap();
-        assert_eq!("Test1", class.name());
+        assert_eq!("Test1", class.name().as_str());
+        assert!(Rc::ptr_eq(
+            &class,
+            &class_loader.find_class("Test1").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_load_class_with_super_classes() {
+        let mut class_loader = class_loader(&[]);
+        let circle = class_loader.find_or_load_class("Circle").unwrap();
+        let shape = circle.super_class().unwrap().clone();
+        assert_eq!("Shape", shape.name().as_str());
+        assert!(Rc::ptr_eq(
+            &shape,
+            &class_loader.find_or_load_class("Shape").unwrap()
+        ));
+        let object = shape.super_class().unwrap();
+        assert_eq!("java/lang/Object", object.name().as_str());
+        assert!(object.super_class().is_none());
+
+        // inherited fields first, at the same offsets as in the superclass
+        let layout = circle.instance_layout();
+        let shape_layout = shape.instance_layout();
+        assert_eq!(shape_layout.fields(), &layout.fields()[..2]);
+        assert_eq!(0, shape_layout.field("id", "I").unwrap().offset());
+        assert_eq!(4, shape_layout.field("kind", "B").unwrap().offset());
+        assert_eq!(8, layout.field("radius", "D").unwrap().offset());
+        assert_eq!(
+            16,
+            layout
+                .field("label", "Ljava/lang/Object;")
+                .unwrap()
+                .offset()
+        );
+        assert_eq!(24, layout.field("symbol", "C").unwrap().offset());
+        assert_eq!(26, layout.field("filled", "Z").unwrap().offset());
+        assert_eq!(32, layout.size());
+
+        // statics are laid out separately, per class
+        assert_eq!(1, circle.static_layout().fields().len());
+        assert_eq!(
+            0,
+            circle.static_layout().field("scale", "D").unwrap().offset()
+        );
+        assert!(layout.field("scale", "D").is_none());
+        assert!(shape.static_layout().field("count", "J").is_some());
+    }
+
+    #[test]
+    fn test_load_missing_class() {
+        let mut class_loader = class_loader(&[]);
+        assert_eq!(
+            Err(LinkageError::NoClassDefFound("Missing".into()).into()),
+            class_loader.find_or_load_class("Missing").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_invalid_hierarchies() {
+        let mut class_loader = class_loader(&["hierarchy"]);
+        for (name, expected) in [
+            ("CycleA", LinkageError::ClassCircularity("CycleA".into())),
+            ("CycleB", LinkageError::ClassCircularity("CycleB".into())),
+            ("CycleC", LinkageError::ClassCircularity("CycleC".into())),
+            ("IfaceA", LinkageError::ClassCircularity("IfaceA".into())),
+            (
+                "Odd",
+                LinkageError::ClassFormat(
+                    "Interfaces must have java.lang.Object as superclass in class file Odd".into(),
+                ),
+            ),
+            (
+                "NoSuper",
+                LinkageError::ClassFormat(
+                    "Invalid superclass index 0 in class file NoSuper".into(),
+                ),
+            ),
+        ] {
+            assert_eq!(
+                Err(expected.into()),
+                class_loader.find_or_load_class(name).map(|_| ()),
+                "{}",
+                name
+            );
+        }
+        // nothing of the failed hierarchies was defined, and the superclass of
+        // Odd wasn't even loaded
+        for name in ["CycleA", "CycleB", "IfaceB", "java/lang/Number"] {
+            assert!(class_loader.find_class(name).is_none(), "{}", name);
+        }
+        assert!(class_loader.loading.is_empty());
+    }
+
+    #[test]
+    fn test_final_method_override() {
+        let mut class_loader = class_loader(&["final"]);
+        assert_eq!(
+            Err(LinkageError::Verify(
+                "class fin.Overrider overrides final method fin.Sealed.value()I".into()
+            )
+            .into()),
+            class_loader.find_or_load_class("fin/Overrider").map(|_| ())
+        );
+        assert!(class_loader.find_class("fin/Overrider").is_none());
+        // also if the final method is declared further up
+        assert_eq!(
+            Some("java/lang/VerifyError"),
+            class_loader
+                .find_or_load_class("fin/Deep")
+                .err()
+                .map(|e| e.class_name())
+        );
+        // a package-private method is not overridden from another package
+        assert!(class_loader.find_or_load_class("other/Unrelated").is_ok());
+    }
+
+    #[test]
+    fn test_resources() {
+        let class_loader = class_loader(&[]);
+        assert_eq!(
+            Some("file:tests/resources/vm/classloader/Shape.java".to_string()),
+            class_loader.resource_url("Shape.java")
+        );
+        let mut contents = String::new();
+        class_loader
+            .open_resource("Shape.java")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert!(contents.contains("class Shape"));
+
+        for missing in [
+            "Missing.txt",
+            "java",
+            "/Shape.java",
+            "java/../Shape.java",
+            "",
+        ] {
+            assert_eq!(None, class_loader.resource_url(missing), "{}", missing);
+            assert_eq!(
+                Some(ErrorKind::NotFound),
+                class_loader.open_resource(missing).err().map(|e| e.kind())
+            );
+        }
+    }
+
+    #[test]
+    fn test_jar_resources() {
+        let services = "tests/resources/vm/services";
+        let mut class_loader = BootstrapClassLoader::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(vec![
+                ClassPathEntry::JarFile(format!("{}/missing.jar", services)),
+                ClassPathEntry::Dir(format!("{}/dir", services)),
+                ClassPathEntry::JarFile(format!("{}/plugins.jar", services)),
+                ClassPathEntry::Dir("tests/resources/vm/classloader".into()),
+            ]),
+            Arc::new(SymbolTable::new()),
+            Arc::new(DescriptorTable::new()),
+        );
+        assert_eq!(
+            vec![
+                format!("file:{}/dir/META-INF/services/greet.Greeter", services),
+                format!(
+                    "jar:file:{}/plugins.jar!/META-INF/services/greet.Greeter",
+                    services
+                ),
+            ],
+            class_loader.resource_urls("META-INF/services/greet.Greeter")
+        );
+        assert!(class_loader.resource_urls("Missing.txt").is_empty());
+        assert!(class_loader.resource_urls("/plugin/Bye.java").is_empty());
+
+        let mut contents = String::new();
+        class_loader
+            .open_resource("plugin/Bye.java")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert!(contents.contains("class Bye implements Greeter"));
+
+        // classes are loaded from jars as well
+        let (_, source) = class_loader.read_class_file("plugin/Bye").unwrap();
+        assert_eq!(
+            ClassSource::JarEntry {
+                jar: format!("{}/plugins.jar", services),
+                entry: "plugin/Bye.class".to_string(),
+            },
+            source
+        );
+        let bye = class_loader.find_or_load_class("plugin/Bye").unwrap();
+        assert_eq!("greet/Greeter", bye.interfaces()[0].name().as_str());
+    }
+
+    #[test]
+    fn test_service_providers() {
+        let services = "tests/resources/vm/services";
+        let mut class_loader = BootstrapClassLoader::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(vec![
+                ClassPathEntry::Dir(format!("{}/dir", services)),
+                ClassPathEntry::JarFile(format!("{}/plugins.jar", services)),
+            ]),
+            Arc::new(SymbolTable::new()),
+            Arc::new(DescriptorTable::new()),
+        );
+        // greet.Hello is configured in both, but only returned once
+        assert_eq!(
+            Ok(vec!["greet/Hello".to_string(), "plugin/Bye".to_string()]),
+            class_loader.service_providers("greet/Greeter")
+        );
+        assert_eq!(Ok(vec![]), class_loader.service_providers("greet/Hello"));
+
+        class_loader.add_entry(ClassPathEntry::Dir(format!("{}/bad", services)));
+        assert_eq!(
+            Err(Exception::ServiceConfiguration(format!(
+                "greet.Broken: file:{}/bad/META-INF/services/greet.Broken:2: Illegal configuration-file syntax",
+                services
+            ))
+            .into()),
+            class_loader.service_providers("greet/Broken")
+        );
+    }
+
+    #[test]
+    fn test_trace_class_loading() {
+        #[derive(Clone, Default)]
+        struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut class_loader = class_loader(&[]);
+        let buffer = Buffer::default();
+        class_loader.set_trace(ClassLoadTrace::new(Box::new(buffer.clone())));
+        class_loader.find_or_load_class("Circle").unwrap();
+        class_loader.find_or_load_class("Shape").unwrap();
+
+        let output = String::from_utf8(buffer.0.take()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // superclasses are defined first, and every class is only loaded once
+        assert_eq!(3, lines.len());
+        for (line, (class, file)) in lines.iter().zip([
+            ("java.lang.Object", "java/lang/Object"),
+            ("Shape", "Shape"),
+            ("Circle", "Circle"),
+        ]) {
+            let expected = format!(
+                "[info][class,load] {} source: file:tests/resources/vm/classloader/{}.class loader: bootstrap time: ",
+                class, file
+            );
+            assert!(line.contains(&expected), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_define_class() {
+        let dir = "tests/resources/vm/classloader";
+        let mut class_loader = BootstrapClassLoader::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(vec![ClassPathEntry::Dir(dir.into())]),
+            Arc::new(SymbolTable::new()),
+            Arc::new(DescriptorTable::new()),
+        );
+        let (bytes, source) = class_loader.read_class_file("Circle").unwrap();
+        assert_eq!(ClassSource::Dir(format!("{}/Circle.class", dir)), source);
+
+        // the superclasses are loaded from the class path
+        let circle = class_loader
+            .define_class("Circle", &bytes, ClassSource::Snapshot)
+            .unwrap();
+        assert_eq!("Shape", circle.super_class().unwrap().name().as_str());
+        assert_eq!(3, class_loader.loaded_classes().len());
+        assert!(Rc::ptr_eq(
+            &circle,
+            &class_loader.find_or_load_class("Circle").unwrap()
+        ));
+
+        assert_eq!(
+            Err(LinkageError::DuplicateClass(
+                "loader 'bootstrap' attempted duplicate class definition for Circle.".to_string()
+            )
+            .into()),
+            class_loader
+                .define_class("Circle", &bytes, ClassSource::Snapshot)
+                .map(|_| ())
+        );
+        assert_eq!(
+            Err(LinkageError::NoClassDefFound("Square (wrong name: Circle)".to_string()).into()),
+            class_loader
+                .define_class("Square", &bytes, ClassSource::Snapshot)
+                .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_preview_classes() {
+        let mut class_loader = class_loader(&[]);
+        let (mut bytes, _) = class_loader.read_class_file("Test1").unwrap();
+        // the minor version follows the magic, and the major version follows it
+        bytes[4..6].copy_from_slice(&Version::PREVIEW_MINOR.to_be_bytes());
+        let define = |class_loader: &mut BootstrapClassLoader, bytes: &[u8]| {
+            class_loader
+                .define_class("Test1", bytes, ClassSource::Snapshot)
+                .map_err(|e| e.to_string())
+                .map(|_| ())
+        };
+        assert_eq!(
+            Err(
+                "java.lang.UnsupportedClassVersionError: Preview features are not enabled for \
+                 Test1 (class file version 61.65535). Try running with '--enable-preview'"
+                    .to_string()
+            ),
+            define(&mut class_loader, &bytes)
+        );
+
+        class_loader.set_preview_enabled(true);
+        let mut older = bytes.clone();
+        older[6..8].copy_from_slice(&60_u16.to_be_bytes());
+        assert_eq!(
+            Err(
+                "java.lang.UnsupportedClassVersionError: Test1 (class file version 60.65535) was \
+                 compiled with preview features that are unsupported. This version of the Java \
+                 Runtime only recognizes preview features for class file version 61.65535"
+                    .to_string()
+            ),
+            define(&mut class_loader, &older)
+        );
+        assert_eq!(Ok(()), define(&mut class_loader, &bytes));
     }
 }
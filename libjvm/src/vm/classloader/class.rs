@@ -1,25 +1,686 @@
-use libjava::bytecode::Op;
-use libjava::classfile::{ClassFile, ConstantPoolInfo};
-use std::lazy::OnceCell;
+use crate::vm::area::MethodAreaStats;
+use crate::vm::classloader::layout::{Field, FieldLayout};
+use crate::vm::descriptor::{DescriptorTable, MethodDescriptor};
+use crate::vm::error::{LinkageError, VmError};
+use crate::vm::module::Module;
+use crate::vm::symbol::{Symbol, SymbolTable};
+use libjava::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use libjava::classfile::{
+    mutf8, AttributeInfo, ClassFile, ConstantPool, ConstantPoolInfo, MethodInfo, ResolvedConstant,
+    ResolvedConstantPool,
+};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct Class {
-    /// A cache for the name of this class.
-    name: OnceCell<String>,
+    /// The interned name of this class.
+    name: Symbol,
     /// The parsed class structure of this class, as parsed from the file.
     class_file: ClassFile,
+    /// The interned Utf8 entries of the constant pool, by their index in the
+    /// pool. Other entries are [`None`].
+    symbols: Vec<Option<Symbol>>,
+    /// The constant pool with its entries resolved, which the frames of the
+    /// methods of this class share.
+    resolved_constant_pool: Arc<ResolvedConstantPool>,
+    /// The methods declared by this class, by name and then by descriptor, so
+    /// that method resolution doesn't have to scan the class file.
+    methods: HashMap<Symbol, HashMap<Symbol, Method>>,
+    /// The direct superclass, or [`None`] for `java/lang/Object`.
+    super_class: Option<Rc<Class>>,
+    /// The direct superinterfaces, in the order they are declared.
+    interfaces: Vec<Rc<Class>>,
+    /// The layout of the instance fields, including the inherited ones.
+    instance_layout: Arc<FieldLayout>,
+    /// The layout of the static fields declared by this class.
+    static_layout: FieldLayout,
+    init_state: Cell<InitState>,
+    /// The module of this class, see [`Class::module`].
+    module: Option<Arc<Module>>,
 }
 
-impl Class {
-    pub fn name(&self) -> &str {
-        self.name.get_or_init(|| self.class_file.this_class())
+/// The initialization state of a class, as specified by [`$5.5`].
+///
+/// [`$5.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.5
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InitState {
+    /// The class is loaded and linked, but its static initializer has not run.
+    Linked,
+    /// The static initializer of the class or one of its superclasses is
+    /// running.
+    BeingInitialized,
+    Initialized,
+    /// The static initializer failed, so the class can't be used.
+    Erroneous,
+}
+
+/// Resolved metadata of a method declared in a [`Class`].
+#[derive(Debug, Eq, PartialEq)]
+pub struct Method {
+    name: Symbol,
+    descriptor: Symbol,
+    /// The parsed descriptor, shared with all other methods of the same type.
+    method_descriptor: Arc<MethodDescriptor>,
+    access_flags: MethodAccessFlags,
+    /// The types of the runtime visible annotations of the method, as field
+    /// descriptors.
+    annotations: Vec<Symbol>,
+    /// The binary names of the checked exceptions that the method declares
+    /// in its `Exceptions` attribute.
+    exceptions: Vec<Symbol>,
+    /// The index of the method in the methods of the class file.
+    index: usize,
+}
+
+impl Method {
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &Symbol {
+        &self.descriptor
+    }
+
+    pub fn method_descriptor(&self) -> &Arc<MethodDescriptor> {
+        &self.method_descriptor
+    }
+
+    pub fn access_flags(&self) -> MethodAccessFlags {
+        self.access_flags
+    }
+
+    /// Whether the method has a runtime visible annotation of the given type,
+    /// e.g. `Ljava/lang/Deprecated;`.
+    pub fn has_annotation(&self, descriptor: &str) -> bool {
+        self.annotations.iter().any(|a| a == descriptor)
+    }
+
+    /// The binary names of the exceptions that the method declares to throw,
+    /// e.g. `java/io/IOException`, in the order of its `throws` clause. The
+    /// classes are only loaded when they are asked for, see
+    /// [`VM::exception_types`](crate::vm::VM::exception_types).
+    pub fn exceptions(&self) -> &[Symbol] {
+        &self.exceptions
+    }
+
+    /// Whether the method is annotated `@CallerSensitive`, i.e. its behavior
+    /// depends on the class of its caller.
+    pub fn is_caller_sensitive(&self) -> bool {
+        self.has_annotation("Ljdk/internal/reflect/CallerSensitive;")
+    }
+
+    /// Whether the method is annotated `@Hidden`, so that its frames are
+    /// skipped when looking for the caller of a caller-sensitive method.
+    pub fn is_hidden(&self) -> bool {
+        self.has_annotation("Ljdk/internal/vm/annotation/Hidden;")
+            || self.has_annotation("Ljava/lang/invoke/LambdaForm$Hidden;")
     }
 }
 
-impl From<ClassFile> for Class {
-    fn from(class_file: ClassFile) -> Self {
-        Self {
-            name: OnceCell::new(),
+impl Class {
+    /// Creates a class from the parsed class file, interning all Utf8
+    /// constants of its constant pool in the given symbol table, and the
+    /// descriptors of its fields and methods in the given descriptor table.
+    /// Fails with a `ClassFormatError` if a descriptor is invalid.
+    ///
+    /// The superclass must already be loaded, since the field layout of this
+    /// class is computed on top of the layout of the superclass. The same goes
+    /// for the superinterfaces, which method resolution searches. Fails with an
+    /// `IncompatibleClassChangeError` if the superclass is an interface or a
+    /// superinterface is a class, and with a `VerifyError` if the class
+    /// overrides a final method of a superclass.
+    pub fn new(
+        class_file: ClassFile,
+        symbol_table: &SymbolTable,
+        descriptor_table: &DescriptorTable,
+        super_class: Option<Rc<Class>>,
+        interfaces: Vec<Rc<Class>>,
+    ) -> Result<Self, VmError> {
+        let symbols: Vec<Option<Symbol>> = class_file
+            .constant_pool()
+            .iter()
+            .map(|info| match info {
+                ConstantPoolInfo::Utf8Info { bytes, .. } => {
+                    Some(symbol_table.intern(&mutf8::decode_lossy(bytes)))
+                }
+                _ => None,
+            })
+            .collect();
+        let resolved_constant_pool =
+            Arc::new(ResolvedConstantPool::new(class_file.constant_pool()));
+        let name = symbol_table.intern(&class_file.this_class());
+        let methods =
+            Self::build_method_table(&class_file, &name, &symbols, symbol_table, descriptor_table)?;
+
+        let mut statics = vec![];
+        let mut instance = vec![];
+        for f in class_file.fields() {
+            let field_name = utf8_symbol(&symbols, f.name_index())?;
+            let descriptor = utf8_symbol(&symbols, f.descriptor_index())?;
+            let field_type = descriptor_table
+                .field_type(&descriptor, symbol_table)
+                .ok_or_else(|| illegal_signature("Field", &field_name, &name, &descriptor))?;
+            let field = (field_name, descriptor, field_type, f.access_flags());
+            if f.access_flags().contains(FieldAccessFlags::STATIC) {
+                statics.push(field);
+            } else {
+                instance.push(field);
+            }
+        }
+        let instance_layout = Arc::new(FieldLayout::new(
+            &name,
+            super_class.as_ref().map(|c| c.instance_layout()),
+            instance,
+        ));
+        let static_layout = FieldLayout::new(&name, None, statics);
+
+        let class = Self {
+            name,
             class_file,
+            symbols,
+            resolved_constant_pool,
+            methods,
+            super_class,
+            interfaces,
+            instance_layout,
+            static_layout,
+            init_state: Cell::new(InitState::Linked),
+            module: None,
+        };
+        class.check_supertypes()?;
+        class.check_final_overrides()?;
+        Ok(class)
+    }
+
+    /// Fails with an `IncompatibleClassChangeError` if the superclass is an
+    /// interface, or one of the superinterfaces is not an interface
+    /// ([`$5.3.5`]), e.g. because it was changed after this class was compiled.
+    ///
+    /// [`$5.3.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.3.5
+    fn check_supertypes(&self) -> Result<(), VmError> {
+        let name = self.name.replace('/', ".");
+        if let Some(super_class) = self.super_class().filter(|c| c.is_interface()) {
+            return Err(LinkageError::IncompatibleClassChange(format!(
+                "class {} has interface {} as super class",
+                name,
+                super_class.name().replace('/', ".")
+            ))
+            .into());
+        }
+        if let Some(interface) = self.interfaces.iter().find(|i| !i.is_interface()) {
+            return Err(LinkageError::IncompatibleClassChange(format!(
+                "class {} can not implement {}, because it is not an interface",
+                name,
+                interface.name().replace('/', ".")
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Fails with a `VerifyError` if an instance method of this class overrides
+    /// a final method of one of its superclasses ([`$5.4.5`]). A package-private
+    /// method is only overridden from within the same run-time package.
+    ///
+    /// [`$5.4.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.5
+    fn check_final_overrides(&self) -> Result<(), VmError> {
+        let excluded = MethodAccessFlags::PRIVATE | MethodAccessFlags::STATIC;
+        let overriding = self
+            .methods
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|m| !m.access_flags.intersects(excluded) && m.name != "<init>");
+        for method in overriding {
+            let mut super_class = self.super_class();
+            while let Some(class) = super_class {
+                let overridden = class
+                    .find_method(&method.name, &method.descriptor)
+                    .filter(|m| {
+                        let flags = m.access_flags();
+                        flags.contains(MethodAccessFlags::FINAL)
+                            && !flags.intersects(excluded)
+                            && (flags.intersects(
+                                MethodAccessFlags::PUBLIC | MethodAccessFlags::PROTECTED,
+                            ) || self.is_same_package(class))
+                    });
+                if overridden.is_some() {
+                    return Err(LinkageError::Verify(format!(
+                        "class {} overrides final method {}.{}{}",
+                        self.name.replace('/', "."),
+                        class.name().replace('/', "."),
+                        method.name,
+                        method.descriptor
+                    ))
+                    .into());
+                }
+                super_class = class.super_class();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    pub fn super_class(&self) -> Option<&Rc<Class>> {
+        self.super_class.as_ref()
+    }
+
+    /// The direct superinterfaces of this class or interface.
+    pub fn interfaces(&self) -> &[Rc<Class>] {
+        &self.interfaces
+    }
+
+    pub fn access_flags(&self) -> ClassAccessFlags {
+        self.class_file.access_flags()
+    }
+
+    pub fn init_state(&self) -> InitState {
+        self.init_state.get()
+    }
+
+    pub(crate) fn set_init_state(&self, state: InitState) {
+        self.init_state.set(state);
+    }
+
+    /// Whether this class has a static initializer, i.e. a `<clinit>` method.
+    pub fn has_static_initializer(&self) -> bool {
+        self.find_method("<clinit>", "()V").is_some()
+    }
+
+    /// Whether this class declares a method that is neither abstract nor
+    /// static. For an interface, these are the default methods, which make
+    /// the interface initialized together with its implementations ([`$5.5`]).
+    ///
+    /// [`$5.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.5
+    pub fn declares_concrete_methods(&self) -> bool {
+        self.methods.values().flat_map(|m| m.values()).any(|m| {
+            !m.access_flags()
+                .intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::STATIC)
+        })
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags().contains(ClassAccessFlags::INTERFACE)
+    }
+
+    /// Whether this class is the given class, or one of its subclasses or
+    /// implementations.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .super_class
+                .iter()
+                .chain(&self.interfaces)
+                .any(|c| c.is_subclass_of(other))
+    }
+
+    /// The name of the package of this class, e.g. `java/lang` for
+    /// `java/lang/String`, or the empty string for the unnamed package.
+    pub fn package_name(&self) -> &str {
+        self.name
+            .rsplit_once('/')
+            .map_or("", |(package, _)| package)
+    }
+
+    /// The module that this class is a member of, or [`None`] if the class is
+    /// exempt from the module checks because it was loaded in class path mode.
+    pub fn module(&self) -> Option<&Arc<Module>> {
+        self.module.as_ref()
+    }
+
+    pub(crate) fn set_module(&mut self, module: Arc<Module>) {
+        self.module = Some(module);
+    }
+
+    /// Whether this class and the other class are in the same run-time
+    /// package. All classes are defined by the bootstrap class loader, so this
+    /// only compares the package names.
+    pub fn is_same_package(&self, other: &Class) -> bool {
+        self.package_name() == other.package_name()
+    }
+
+    /// The name of the host of the nest that this class belongs to, which is
+    /// the class itself if it has no `NestHost` attribute.
+    pub fn nest_host_name(&self) -> &Symbol {
+        self.class_file
+            .attributes()
+            .iter()
+            .find_map(|a| match a {
+                AttributeInfo::NestHost {
+                    host_class_index, ..
+                } => self.class_name_at(*host_class_index),
+                _ => None,
+            })
+            .unwrap_or(&self.name)
+    }
+
+    /// Whether this class lists the class with the given name in its
+    /// `NestMembers` attribute.
+    pub fn has_nest_member(&self, name: &str) -> bool {
+        self.class_file.attributes().iter().any(|a| match a {
+            AttributeInfo::NestMembers { classes, .. } => classes
+                .iter()
+                .any(|index| self.class_name_at(*index).is_some_and(|n| n == name)),
+            _ => false,
+        })
+    }
+
+    /// The name of the source file that this class was compiled from, without
+    /// any directories, if the class file records it.
+    pub fn source_file(&self) -> Option<&Symbol> {
+        self.class_file.attributes().iter().find_map(|a| match a {
+            AttributeInfo::SourceFile {
+                sourcefile_index, ..
+            } => self.symbol(*sourcefile_index),
+            _ => None,
+        })
+    }
+
+    /// The layout of the instance fields of this class, starting with the
+    /// fields inherited from the superclasses.
+    pub fn instance_layout(&self) -> &FieldLayout {
+        &self.instance_layout
+    }
+
+    /// The instance layout, shared with the instances of this class on the
+    /// heap.
+    pub(crate) fn shared_instance_layout(&self) -> &Arc<FieldLayout> {
+        &self.instance_layout
+    }
+
+    /// The layout of the static fields declared by this class. Static fields of
+    /// superclasses are stored with the superclass.
+    pub fn static_layout(&self) -> &FieldLayout {
+        &self.static_layout
+    }
+
+    /// The constant pool of the class file of this class.
+    pub fn constant_pool(&self) -> &ConstantPool {
+        self.class_file.constant_pool()
+    }
+
+    /// The constant pool of this class with its entries resolved, which the
+    /// interpreter looks constants and symbolic references up in.
+    pub fn resolved_constant_pool(&self) -> &Arc<ResolvedConstantPool> {
+        &self.resolved_constant_pool
+    }
+
+    /// Returns the interned Utf8 constant at the given (1-based) constant
+    /// pool index, or [`None`] if the entry is not a Utf8 constant.
+    pub fn symbol(&self, index: u16) -> Option<&Symbol> {
+        self.symbols.get(index.checked_sub(1)? as usize)?.as_ref()
+    }
+
+    /// Returns the name of the class that the `CONSTANT_Class` entry at the
+    /// given (1-based) constant pool index refers to.
+    fn class_name_at(&self, index: u16) -> Option<&Symbol> {
+        let constant_pool = self.class_file.constant_pool();
+        match constant_pool.iter().nth(index.checked_sub(1)? as usize)? {
+            ConstantPoolInfo::ClassInfo { name_index } => self.symbol(*name_index),
+            _ => None,
+        }
+    }
+
+    /// Finds a field declared in this class, static or not, by its name and
+    /// descriptor. Inherited fields are not found.
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&Field> {
+        self.static_layout
+            .field(name, descriptor)
+            .or_else(|| self.instance_layout.field(name, descriptor))
+            .filter(|f| *f.declaring_class() == self.name)
+    }
+
+    /// Finds a method declared in this class by its name and descriptor,
+    /// e.g. `find_method("main", "([Ljava/lang/String;)V")`.
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Method> {
+        self.methods.get(name)?.get(descriptor)
+    }
+
+    /// Returns all methods declared in this class with the given name, in no
+    /// particular order.
+    pub fn methods_named(&self, name: &str) -> impl Iterator<Item = &Method> {
+        self.methods.get(name).into_iter().flat_map(|m| m.values())
+    }
+
+    /// Returns all methods declared in this class, in the order of the class
+    /// file.
+    pub fn methods(&self) -> Vec<&Method> {
+        let mut methods = self
+            .methods
+            .values()
+            .flat_map(|m| m.values())
+            .collect::<Vec<_>>();
+        methods.sort_by_key(|m| m.index);
+        methods
+    }
+
+    /// Returns the parsed method structure of the given method of this class.
+    pub fn method_info(&self, method: &Method) -> &MethodInfo {
+        &self.class_file.methods()[method.index]
+    }
+
+    /// The approximate memory used by this class in the method area, see
+    /// [`MethodAreaStats`].
+    pub fn footprint(&self) -> MethodAreaStats {
+        let methods: usize = self
+            .methods
+            .values()
+            .flat_map(|m| m.values())
+            .map(|m| {
+                size_of::<Method>()
+                    + (m.annotations.len() + m.exceptions.len()) * size_of::<Symbol>()
+            })
+            .sum();
+        let fields = self.instance_layout.fields().len() + self.static_layout.fields().len();
+        let bytecode = self
+            .class_file
+            .methods()
+            .iter()
+            .flat_map(|m| m.attributes())
+            .map(|a| match a {
+                AttributeInfo::Code { code, .. } => code.len(),
+                _ => 0,
+            })
+            .sum();
+        let constant_pool: usize = self
+            .class_file
+            .constant_pool()
+            .iter()
+            .map(|info| match info {
+                ConstantPoolInfo::Utf8Info { bytes, .. } => bytes.len(),
+                _ => 0,
+            })
+            .sum();
+        MethodAreaStats {
+            classes: 1,
+            class_metadata: size_of::<Class>()
+                + methods
+                + fields * size_of::<Field>()
+                + self.interfaces.len() * size_of::<Rc<Class>>(),
+            bytecode,
+            // the resolved constant pool holds the decoded Utf8 entries again
+            constant_pools: 2 * constant_pool
+                + self.symbols.len()
+                    * (size_of::<ConstantPoolInfo>()
+                        + size_of::<Option<Symbol>>()
+                        + size_of::<ResolvedConstant>()),
         }
     }
+
+    fn build_method_table(
+        class_file: &ClassFile,
+        class_name: &Symbol,
+        symbols: &[Option<Symbol>],
+        symbol_table: &SymbolTable,
+        descriptor_table: &DescriptorTable,
+    ) -> Result<HashMap<Symbol, HashMap<Symbol, Method>>, VmError> {
+        let mut methods: HashMap<Symbol, HashMap<Symbol, Method>> = HashMap::new();
+        for (index, info) in class_file.methods().iter().enumerate() {
+            let name = utf8_symbol(symbols, info.name_index())?;
+            let descriptor = utf8_symbol(symbols, info.descriptor_index())?;
+            let method_descriptor = descriptor_table
+                .method_descriptor(&descriptor, symbol_table)
+                .ok_or_else(|| illegal_signature("Method", &name, class_name, &descriptor))?;
+            let mut annotations = vec![];
+            let mut exceptions = vec![];
+            for attribute in info.attributes() {
+                // the bootstrap loader parses annotations lazily, and only
+                // these are needed to load the class
+                let attribute = match attribute {
+                    AttributeInfo::Lazy(lazy)
+                        if utf8_symbol(symbols, lazy.name_index())?.as_str()
+                            == "RuntimeVisibleAnnotations" =>
+                    {
+                        lazy.get(class_file.constant_pool()).map_err(|e| {
+                            LinkageError::ClassFormat(format!(
+                                "{:?} in class file {}",
+                                e, class_name
+                            ))
+                        })?
+                    }
+                    attribute => attribute,
+                };
+                match attribute {
+                    AttributeInfo::RuntimeVisibleAnnotations {
+                        annotations: visible,
+                        ..
+                    } => {
+                        for annotation in visible {
+                            annotations.push(utf8_symbol(symbols, annotation.type_index())?);
+                        }
+                    }
+                    AttributeInfo::Exceptions {
+                        exception_index_table,
+                        ..
+                    } => {
+                        for index in exception_index_table {
+                            exceptions.push(class_symbol(class_file, symbols, *index)?);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            methods.entry(name.clone()).or_default().insert(
+                descriptor.clone(),
+                Method {
+                    name,
+                    descriptor,
+                    method_descriptor,
+                    access_flags: info.access_flags(),
+                    annotations,
+                    exceptions,
+                    index,
+                },
+            );
+        }
+        Ok(methods)
+    }
+}
+
+/// Returns the symbol of the Utf8 constant at the given (1-based) constant pool
+/// index, failing with a `ClassFormatError` if there is none.
+fn utf8_symbol(symbols: &[Option<Symbol>], index: u16) -> Result<Symbol, VmError> {
+    index
+        .checked_sub(1)
+        .and_then(|i| symbols.get(i as usize)?.clone())
+        .ok_or_else(|| {
+            LinkageError::ClassFormat(format!("Invalid constant pool index {}", index)).into()
+        })
+}
+
+/// Returns the symbol of the name of the `CONSTANT_Class` entry at the given
+/// (1-based) constant pool index, failing with a `ClassFormatError` if there
+/// is none.
+fn class_symbol(
+    class_file: &ClassFile,
+    symbols: &[Option<Symbol>],
+    index: u16,
+) -> Result<Symbol, VmError> {
+    match class_file.constant_pool().get(index) {
+        Some(ConstantPoolInfo::ClassInfo { name_index }) => utf8_symbol(symbols, *name_index),
+        _ => Err(LinkageError::ClassFormat(format!("Invalid class index {}", index)).into()),
+    }
+}
+
+fn illegal_signature(kind: &str, name: &Symbol, class: &Symbol, descriptor: &Symbol) -> VmError {
+    LinkageError::ClassFormat(format!(
+        "{} \"{}\" in class {} has illegal signature \"{}\"",
+        kind, name, class, descriptor
+    ))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn load_test_class() -> Class {
+        let f = File::open("tests/resources/vm/classloader/Test1.class").unwrap();
+        let mut rd = BufReader::new(f);
+        Class::new(
+            ClassFile::parse(&mut rd).unwrap(),
+            &SymbolTable::new(),
+            &DescriptorTable::new(),
+            None,
+            vec![],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_method() {
+        let class = load_test_class();
+        let method = class.find_method("method", "()I").unwrap();
+        assert_eq!("method", method.name().as_str());
+        assert_eq!("()I", method.descriptor().as_str());
+        assert_eq!(MethodAccessFlags::PUBLIC, method.access_flags());
+        assert_eq!(
+            Some(method.descriptor()),
+            class.symbol(class.method_info(method).descriptor_index())
+        );
+
+        assert!(class.find_method("<init>", "()V").is_some());
+    }
+
+    #[test]
+    fn test_symbols_shared_between_classes() {
+        let table = SymbolTable::new();
+        let descriptors = DescriptorTable::new();
+        let load = || {
+            let f = File::open("tests/resources/vm/classloader/Test1.class").unwrap();
+            Class::new(
+                ClassFile::parse(&mut BufReader::new(f)).unwrap(),
+                &table,
+                &descriptors,
+                None,
+                vec![],
+            )
+            .unwrap()
+        };
+        let a = load();
+        let b = load();
+        assert_eq!(a.name(), b.name());
+        assert_eq!(
+            a.find_method("method", "()I").unwrap().name(),
+            b.find_method("method", "()I").unwrap().name()
+        );
+        assert!(Arc::ptr_eq(
+            a.find_method("method", "()I").unwrap().method_descriptor(),
+            b.find_method("method", "()I").unwrap().method_descriptor()
+        ));
+    }
+
+    #[test]
+    fn test_find_method_wrong_descriptor() {
+        let class = load_test_class();
+        assert!(class.find_method("method", "()V").is_none());
+        assert!(class.find_method("missing", "()I").is_none());
+    }
 }
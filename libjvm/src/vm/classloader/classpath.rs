@@ -1,5 +1,6 @@
 use std::path::Path;
 
+#[derive(Clone)]
 pub struct ClassPath {
     items: Vec<ClassPathEntry>,
 }
@@ -20,6 +21,7 @@ impl From<Vec<ClassPathEntry>> for ClassPath {
     }
 }
 
+#[derive(Clone)]
 pub enum ClassPathEntry {
     Dir(String),
     JarFile(String),
@@ -0,0 +1,225 @@
+use crate::vm::classloader::class::{Class, InitState};
+use crate::vm::error::{LinkageError, VmError};
+use std::rc::Rc;
+
+/// A callback that is notified whenever a class becomes initialized, e.g. so
+/// that an embedder knows when its configuration classes are ready.
+pub type InitListener = Box<dyn FnMut(&Class)>;
+
+/// Initializes classes in the order specified by [`$5.5`], and notifies the
+/// registered listeners about every class that becomes initialized.
+///
+/// [`$5.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.5
+#[derive(Default)]
+pub struct ClassInitializer {
+    listeners: Vec<InitListener>,
+}
+
+impl ClassInitializer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_listener(&mut self, listener: InitListener) {
+        self.listeners.push(listener);
+    }
+
+    /// Initializes the given class, if it is not initialized yet. A class is
+    /// initialized after its superclass and after the superinterfaces that
+    /// declare default methods, but an interface is initialized without its
+    /// superinterfaces. `run_static_initializer` is called for every class
+    /// that has a `<clinit>` method, in that order.
+    ///
+    /// A class whose static initializer fails is erroneous. The failure is
+    /// wrapped in an `ExceptionInInitializerError` unless it is an error
+    /// already, and every later attempt to initialize the class or one of its
+    /// subclasses fails with a `NoClassDefFoundError`.
+    pub fn initialize(
+        &mut self,
+        class: &Rc<Class>,
+        run_static_initializer: &mut dyn FnMut(&Rc<Class>) -> Result<(), VmError>,
+    ) -> Result<(), VmError> {
+        match class.init_state() {
+            // a class that is being initialized can only be requested again by
+            // its own static initializer, which sees the class as it is
+            InitState::BeingInitialized | InitState::Initialized => return Ok(()),
+            InitState::Erroneous => {
+                return Err(LinkageError::NoClassDefFound(format!(
+                    "Could not initialize class {}",
+                    class.name().as_str().replace('/', ".")
+                ))
+                .into())
+            }
+            InitState::Linked => {}
+        }
+
+        class.set_init_state(InitState::BeingInitialized);
+        let result = self
+            .initialize_supertypes(class, run_static_initializer)
+            .and_then(|_| {
+                if class.has_static_initializer() {
+                    run_static_initializer(class)
+                } else {
+                    Ok(())
+                }
+            });
+        match result {
+            Ok(()) => {
+                class.set_init_state(InitState::Initialized);
+                for listener in &mut self.listeners {
+                    listener(class);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                class.set_init_state(InitState::Erroneous);
+                if e.is_error() {
+                    Err(e)
+                } else {
+                    Err(LinkageError::ExceptionInInitializer(Box::new(e)).into())
+                }
+            }
+        }
+    }
+
+    /// Marks the given class as initialized without running its static
+    /// initializer, e.g. because it was initialized before a snapshot of it
+    /// was taken, and notifies the listeners.
+    pub fn mark_initialized(&mut self, class: &Class) {
+        class.set_init_state(InitState::Initialized);
+        for listener in &mut self.listeners {
+            listener(class);
+        }
+    }
+
+    fn initialize_supertypes(
+        &mut self,
+        class: &Rc<Class>,
+        run_static_initializer: &mut dyn FnMut(&Rc<Class>) -> Result<(), VmError>,
+    ) -> Result<(), VmError> {
+        if class.is_interface() {
+            return Ok(());
+        }
+        if let Some(super_class) = class.super_class() {
+            self.initialize(super_class, run_static_initializer)?;
+        }
+        let mut interfaces = vec![];
+        collect_interfaces_with_defaults(class.interfaces(), &mut interfaces);
+        for interface in interfaces {
+            self.initialize(&interface, run_static_initializer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the given interfaces and their superinterfaces that declare
+/// default methods, each after its own superinterfaces.
+fn collect_interfaces_with_defaults(interfaces: &[Rc<Class>], out: &mut Vec<Rc<Class>>) {
+    for interface in interfaces {
+        collect_interfaces_with_defaults(interface.interfaces(), out);
+        if interface.declares_concrete_methods() {
+            out.push(interface.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::{self, class_loader};
+    use crate::vm::classloader::ClassLoader;
+    use crate::vm::error::Exception;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_initialization_order() {
+        let mut class_loader = class_loader(&["init"]);
+        let config = class_loader.find_or_load_class("Config").unwrap();
+        assert_eq!(InitState::Linked, config.init_state());
+
+        let initialized = Rc::new(RefCell::new(vec![]));
+        let mut initializer = ClassInitializer::new();
+        let listened = initialized.clone();
+        initializer.add_listener(Box::new(move |class| {
+            listened
+                .borrow_mut()
+                .push(class.name().as_str().to_string())
+        }));
+        let mut run = vec![];
+        let mut run_static_initializer = |class: &Rc<Class>| {
+            // the superclass is initialized before the static initializer runs
+            assert_eq!(InitState::BeingInitialized, class.init_state());
+            run.push(class.name().as_str().to_string());
+            Ok(())
+        };
+        initializer
+            .initialize(&config, &mut run_static_initializer)
+            .unwrap();
+        initializer
+            .initialize(&config, &mut run_static_initializer)
+            .unwrap();
+
+        let plain = class_loader.find_or_load_class("Plain").unwrap();
+        assert!(!plain.has_static_initializer());
+        assert!(config.has_static_initializer());
+
+        // Marker has no default methods, so it is not initialized
+        assert_eq!(vec!["BaseConfig", "Named", "Defaults", "Config"], run);
+        assert_eq!(
+            vec![
+                "java/lang/Object",
+                "BaseConfig",
+                "Named",
+                "Defaults",
+                "Config"
+            ],
+            *initialized.borrow()
+        );
+        assert_eq!(InitState::Initialized, config.init_state());
+        let marker = class_loader.find_class("Marker").unwrap();
+        assert_eq!(InitState::Linked, marker.init_state());
+
+        // initializing an interface doesn't initialize its superinterfaces
+        let mut class_loader = test_util::class_loader(&["init"]);
+        let defaults = class_loader.find_or_load_class("Defaults").unwrap();
+        initializer.initialize(&defaults, &mut |_| Ok(())).unwrap();
+        let named = class_loader.find_class("Named").unwrap();
+        assert_eq!(InitState::Linked, named.init_state());
+    }
+
+    #[test]
+    fn test_failing_static_initializer() {
+        let mut class_loader = class_loader(&["init"]);
+        let child = class_loader.find_or_load_class("BrokenChild").unwrap();
+        let broken = child.super_class().unwrap().clone();
+        let mut initializer = ClassInitializer::new();
+        let division_by_zero = VmError::from(Exception::Arithmetic("/ by zero".into()));
+        let mut run_static_initializer = |class: &Rc<Class>| match class.name().as_str() {
+            "Broken" => Err(division_by_zero.clone()),
+            _ => Ok(()),
+        };
+
+        assert_eq!(
+            Err(LinkageError::ExceptionInInitializer(Box::new(division_by_zero.clone())).into()),
+            initializer.initialize(&child, &mut run_static_initializer)
+        );
+        assert_eq!(InitState::Erroneous, broken.init_state());
+        assert_eq!(InitState::Erroneous, child.init_state());
+        for (class, name) in [(&broken, "Broken"), (&child, "BrokenChild")] {
+            assert_eq!(
+                Err(
+                    LinkageError::NoClassDefFound(format!("Could not initialize class {}", name))
+                        .into()
+                ),
+                initializer.initialize(class, &mut run_static_initializer)
+            );
+        }
+
+        // errors are not wrapped
+        let config = class_loader.find_or_load_class("Config").unwrap();
+        assert_eq!(
+            Err(Exception::StackOverflow.into()),
+            initializer.initialize(&config, &mut |_| Err(Exception::StackOverflow.into()))
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::vm::native::zip::{crc32, Inflater};
+use libvfs::path::VfsPath;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+/// The size of the end of central directory record without the comment.
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// A jar file of the class path. Only its central directory is read when it is
+/// opened, the entries are read and decompressed when they are asked for.
+/// Stored and deflated entries are supported, but neither zip64 archives nor
+/// encrypted entries.
+pub struct JarFile {
+    path: VfsPath,
+    /// The names of the entries, in the order of the central directory.
+    names: Vec<String>,
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    /// The offset of the local file header of the entry.
+    offset: u32,
+}
+
+impl JarFile {
+    /// Opens the jar file and reads its central directory. Fails with an
+    /// error of kind [`ErrorKind::InvalidData`] if it isn't a zip file, or
+    /// one that isn't supported.
+    pub fn open(path: VfsPath) -> io::Result<Self> {
+        let mut file = path.open()?;
+        let len = file.seek(SeekFrom::End(0))?;
+        // the record is at the end, followed by a comment of up to 64K
+        let tail_len = len.min((END_OF_CENTRAL_DIRECTORY_SIZE + 0xFFFF) as u64);
+        file.seek(SeekFrom::Start(len - tail_len))?;
+        let mut tail = vec![0; tail_len as usize];
+        file.read_exact(&mut tail)?;
+        let end = (0..(tail.len() + 1).saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+            .rev()
+            .find(|i| u32_at(&tail, *i) == END_OF_CENTRAL_DIRECTORY)
+            .ok_or_else(|| invalid("no end of central directory record"))?;
+        let count = u16_at(&tail, end + 10);
+        let size = u32_at(&tail, end + 12);
+        let offset = u32_at(&tail, end + 16);
+        if count == 0xFFFF || size == 0xFFFF_FFFF || offset == 0xFFFF_FFFF {
+            return Err(invalid("zip64 archives are not supported"));
+        }
+
+        let mut directory = vec![0; size as usize];
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.read_exact(&mut directory)?;
+        let mut jar = Self {
+            path,
+            names: Vec::with_capacity(count as usize),
+            entries: HashMap::with_capacity(count as usize),
+        };
+        let mut pos = 0;
+        for _ in 0..count {
+            if pos + CENTRAL_DIRECTORY_HEADER_SIZE > directory.len()
+                || u32_at(&directory, pos) != CENTRAL_DIRECTORY_HEADER
+            {
+                return Err(invalid("malformed central directory"));
+            }
+            let name_len = u16_at(&directory, pos + 28) as usize;
+            let extra_len = u16_at(&directory, pos + 30) as usize;
+            let comment_len = u16_at(&directory, pos + 32) as usize;
+            let name_start = pos + CENTRAL_DIRECTORY_HEADER_SIZE;
+            let name = directory
+                .get(name_start..name_start + name_len)
+                .ok_or_else(|| invalid("malformed central directory"))?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            let encrypted = u16_at(&directory, pos + 8) & 1 != 0;
+            if !name.ends_with('/') && !encrypted {
+                let entry = Entry {
+                    method: u16_at(&directory, pos + 10),
+                    crc: u32_at(&directory, pos + 16),
+                    compressed_size: u32_at(&directory, pos + 20),
+                    size: u32_at(&directory, pos + 24),
+                    offset: u32_at(&directory, pos + 42),
+                };
+                if jar.entries.insert(name.clone(), entry).is_none() {
+                    jar.names.push(name);
+                }
+            }
+            pos = name_start + name_len + extra_len + comment_len;
+        }
+        Ok(jar)
+    }
+
+    /// The path of the jar file.
+    pub fn path(&self) -> &VfsPath {
+        &self.path
+    }
+
+    /// The names of the files in the jar, e.g. `java/lang/Object.class`, in
+    /// the order of the central directory. Directories are left out.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Reads and decompresses the entry with the given name, or returns
+    /// [`None`] if there is no such entry. Fails with an error of kind
+    /// [`ErrorKind::InvalidData`] if the entry is corrupted.
+    pub fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        let mut file = self.path.open()?;
+        let mut header = [0; LOCAL_FILE_HEADER_SIZE];
+        file.seek(SeekFrom::Start(entry.offset as u64))?;
+        file.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_FILE_HEADER {
+            return Err(invalid(&format!("no local header for entry {}", name)));
+        }
+        let data_offset = entry.offset as u64
+            + LOCAL_FILE_HEADER_SIZE as u64
+            + u16_at(&header, 26) as u64
+            + u16_at(&header, 28) as u64;
+        file.seek(SeekFrom::Start(data_offset))?;
+        let mut data = vec![0; entry.compressed_size as usize];
+        file.read_exact(&mut data)?;
+
+        let contents = match entry.method {
+            STORED => data,
+            DEFLATED => inflate(&data, entry.size as usize)
+                .ok_or_else(|| invalid(&format!("corrupted entry {}", name)))?,
+            method => {
+                return Err(invalid(&format!(
+                    "unsupported compression method {} of entry {}",
+                    method, name
+                )))
+            }
+        };
+        if contents.len() != entry.size as usize || crc32(0, &contents) != entry.crc {
+            return Err(invalid(&format!("corrupted entry {}", name)));
+        }
+        Ok(Some(contents))
+    }
+}
+
+/// Decompresses raw deflate data of the given size, or returns [`None`] if it
+/// is corrupted or doesn't end where it should.
+fn inflate(data: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut inflater = Inflater::new(true);
+    // one more byte, so that the end of the data is reached with room left in
+    // the output, and data that is longer than it should be is detected
+    let mut output = vec![0; size + 1];
+    let (mut read, mut written) = (0, 0);
+    while !inflater.is_finished() {
+        let progress = inflater
+            .inflate(&data[read..], &mut output[written..])
+            .ok()?;
+        if progress.read == 0 && progress.written == 0 && !progress.finished {
+            return None;
+        }
+        read += progress.read;
+        written += progress.written;
+    }
+    output.truncate(size);
+    (written == size).then_some(output)
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid jar file: {}", message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libvfs::FileSystem;
+
+    fn jar() -> JarFile {
+        let fs = FileSystem::new_os_fs();
+        JarFile::open(fs.path("tests/resources/vm/services/plugins.jar")).unwrap()
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let jar = jar();
+        assert!(jar.contains("META-INF/services/greet.Greeter"));
+        assert!(!jar.contains("META-INF/"));
+        assert!(jar.names().any(|n| n == "plugin/Bye.class"));
+
+        let config = jar
+            .read("META-INF/services/greet.Greeter")
+            .unwrap()
+            .unwrap();
+        assert!(String::from_utf8(config).unwrap().contains("plugin.Bye"));
+        let class = jar.read("plugin/Bye.class").unwrap().unwrap();
+        assert_eq!([0xCA, 0xFE, 0xBA, 0xBE], class[..4]);
+        assert_eq!(None, jar.read("missing").unwrap());
+    }
+
+    #[test]
+    fn test_invalid_jar() {
+        let fs = FileSystem::new_os_fs();
+        let error = JarFile::open(fs.path("tests/resources/vm/services/dir/greet/Greeter.java"))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::vm::descriptor::FieldType;
+use crate::vm::symbol::Symbol;
+use libjava::classfile::flags::FieldAccessFlags;
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+/// The size of a reference field in bytes.
+pub const REFERENCE_SIZE: usize = std::mem::size_of::<usize>();
+
+/// A field with its position in the storage of an object (for instance fields)
+/// or of a class (for static fields).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Field {
+    name: Symbol,
+    descriptor: Symbol,
+    /// The parsed descriptor, shared with all other fields of the same type.
+    field_type: Arc<FieldType>,
+    access_flags: FieldAccessFlags,
+    /// The name of the class that declares this field.
+    declaring_class: Symbol,
+    offset: usize,
+    size: usize,
+}
+
+impl Field {
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &Symbol {
+        &self.descriptor
+    }
+
+    pub fn field_type(&self) -> &Arc<FieldType> {
+        &self.field_type
+    }
+
+    pub fn access_flags(&self) -> FieldAccessFlags {
+        self.access_flags
+    }
+
+    pub fn declaring_class(&self) -> &Symbol {
+        &self.declaring_class
+    }
+
+    /// The offset of this field in bytes from the start of the storage.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The size of this field in bytes, which is also its alignment.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// The flattened layout of either the instance fields or the static fields of a
+/// class.
+///
+/// Instance layouts start with the complete layout of the superclass, so that
+/// an inherited field has the same offset in every subclass. Declared fields are
+/// placed after that, largest first, each aligned to its size.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FieldLayout {
+    /// The fields in order of their offsets.
+    fields: Vec<Field>,
+    /// The size of the storage in bytes, rounded up to the alignment.
+    size: usize,
+    alignment: usize,
+}
+
+impl FieldLayout {
+    /// Lays out the given fields of a class (as name, descriptor, parsed
+    /// descriptor and access flags) after the fields of `base`, if any.
+    pub fn new<I>(declaring_class: &Symbol, base: Option<&FieldLayout>, declared: I) -> Self
+    where
+        I: IntoIterator<Item = (Symbol, Symbol, Arc<FieldType>, FieldAccessFlags)>,
+    {
+        let mut declared: Vec<Field> = declared
+            .into_iter()
+            .map(|(name, descriptor, field_type, access_flags)| Field {
+                size: field_type.size(),
+                name,
+                descriptor,
+                field_type,
+                access_flags,
+                declaring_class: declaring_class.clone(),
+                offset: 0,
+            })
+            .collect();
+        // stable, so fields of the same size stay in declaration order
+        declared.sort_by_key(|f| Reverse(f.size));
+
+        let (mut fields, mut end, mut alignment) = match base {
+            Some(base) => (base.fields.clone(), base.size, base.alignment),
+            None => (vec![], 0, 1),
+        };
+        for mut field in declared {
+            field.offset = align_up(end, field.size);
+            end = field.offset + field.size;
+            alignment = alignment.max(field.size);
+            fields.push(field);
+        }
+
+        Self {
+            fields,
+            size: align_up(end, alignment),
+            alignment,
+        }
+    }
+
+    /// All fields in this layout, including inherited ones, ordered by offset.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// The size in bytes that is needed to store all fields.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Finds a field by name and descriptor. If a subclass hides a field of a
+    /// superclass, the subclass field is returned.
+    ///
+    /// This is meant to be used once when resolving a field reference; after
+    /// that, the field should only be accessed through its offset.
+    pub fn field(&self, name: &str, descriptor: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|f| f.name == name && f.descriptor == descriptor)
+    }
+}
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::symbol::SymbolTable;
+
+    fn declare(
+        table: &SymbolTable,
+        fields: &[(&str, &str)],
+    ) -> Vec<(Symbol, Symbol, Arc<FieldType>, FieldAccessFlags)> {
+        fields
+            .iter()
+            .map(|(n, d)| {
+                (
+                    table.intern(n),
+                    table.intern(d),
+                    Arc::new(FieldType::parse(d, table).unwrap()),
+                    FieldAccessFlags::empty(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_sorted_and_aligned() {
+        let table = SymbolTable::new();
+        let layout = FieldLayout::new(
+            &table.intern("A"),
+            None,
+            declare(&table, &[("b", "B"), ("i", "I"), ("j", "J"), ("c", "C")]),
+        );
+        let offsets: Vec<(&str, usize)> = layout
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.offset()))
+            .collect();
+        assert_eq!(vec![("j", 0), ("i", 8), ("c", 12), ("b", 14)], offsets);
+        assert_eq!(8, layout.alignment());
+        assert_eq!(16, layout.size());
+    }
+
+    #[test]
+    fn test_layout_inherited_fields_first() {
+        let table = SymbolTable::new();
+        let base = FieldLayout::new(
+            &table.intern("A"),
+            None,
+            declare(&table, &[("x", "I"), ("b", "Z")]),
+        );
+        let layout = FieldLayout::new(
+            &table.intern("B"),
+            Some(&base),
+            declare(&table, &[("x", "I"), ("o", "Ljava/lang/Object;")]),
+        );
+        assert_eq!(base.fields(), &layout.fields()[..2]);
+        assert_eq!(8, layout.field("o", "Ljava/lang/Object;").unwrap().offset());
+
+        // the hiding field of the subclass is found, not the inherited one
+        let x = layout.field("x", "I").unwrap();
+        assert_eq!("B", x.declaring_class().as_str());
+        assert_eq!(8 + REFERENCE_SIZE, x.offset());
+    }
+
+    #[test]
+    fn test_empty_layout() {
+        let table = SymbolTable::new();
+        let layout = FieldLayout::new(&table.intern("A"), None, vec![]);
+        assert!(layout.fields().is_empty());
+        assert_eq!(0, layout.size());
+    }
+}
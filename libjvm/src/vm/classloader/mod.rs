@@ -1,10 +1,29 @@
 use crate::vm::classloader::class::Class;
 use crate::vm::classloader::classpath::ClassPathEntry;
+use crate::vm::error::VmError;
+use libvfs::file::File;
 use std::rc::Rc;
 
+/// Access control for classes and their members, as specified by $5.4.4.
+pub mod access;
 pub mod bootstrap;
 pub mod class;
 pub mod classpath;
+/// Class initialization, as specified by $5.5.
+pub mod init;
+/// Reading of the jar files of the class path.
+pub mod jar;
+pub mod layout;
+/// Field and method resolution, and method selection for the invoke
+/// instructions, as specified by $5.4.3.2, $5.4.3.3, $5.4.3.4 and $5.4.6.
+pub mod resolve;
+/// The provider-configuration files of `java.util.ServiceLoader`.
+pub mod services;
+/// The class loaders that the tests of the VM load their classes with.
+#[cfg(test)]
+pub(crate) mod test_util;
+/// Tracing of class loading, like `-verbose:class`.
+pub mod trace;
 
 pub trait ClassLoader {
     fn add_entry(&mut self, entry: ClassPathEntry);
@@ -13,7 +32,22 @@ pub trait ClassLoader {
     where
         N: AsRef<str>;
 
-    fn find_or_load_class<N>(&mut self, name: N) -> Option<Rc<Class>>
+    /// Returns the class with the given name, loading it and its superclasses
+    /// if it was not loaded yet.
+    fn find_or_load_class<N>(&mut self, name: N) -> Result<Rc<Class>, VmError>
     where
         N: AsRef<str>;
+
+    /// Returns the URL of the resource with the given name, e.g.
+    /// `com/example/app.properties`, or [`None`] if there is no such resource.
+    /// This is what `ClassLoader.getResource` returns.
+    fn resource_url(&self, name: &str) -> Option<String>;
+
+    /// Returns the URLs of all resources with the given name, in the order of
+    /// the class path, like `ClassLoader.getResources`.
+    fn resource_urls(&self, name: &str) -> Vec<String>;
+
+    /// Opens the resource with the given name for reading, like
+    /// `ClassLoader.getResourceAsStream`.
+    fn open_resource(&self, name: &str) -> std::io::Result<File>;
 }
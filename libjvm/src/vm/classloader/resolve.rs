@@ -0,0 +1,806 @@
+use crate::vm::classloader::access::{check_class_access, check_member_access, Member, Visibility};
+use crate::vm::classloader::class::{Class, Method};
+use crate::vm::classloader::layout::Field;
+use crate::vm::error::{LinkageError, VmError};
+use crate::vm::symbol::Symbol;
+use libjava::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+use std::rc::Rc;
+
+/// The instruction that invokes a method, which determines how the method is
+/// resolved, checked and selected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InvokeKind {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+}
+
+/// Whether a field instruction accesses a static field (`getstatic` and
+/// `putstatic`) or an instance field (`getfield` and `putfield`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FieldKind {
+    Static,
+    Instance,
+}
+
+/// A method together with the class that declares it.
+#[derive(Clone)]
+pub struct ResolvedMethod {
+    class: Rc<Class>,
+    name: Symbol,
+    descriptor: Symbol,
+    /// The descriptor of the invocation, if the method is signature
+    /// polymorphic. The arguments and the return value of such an invocation
+    /// are described by this descriptor rather than by the declared one.
+    call_site_descriptor: Option<String>,
+}
+
+impl ResolvedMethod {
+    pub(crate) fn new(class: &Rc<Class>, method: &Method) -> Self {
+        Self {
+            class: class.clone(),
+            name: method.name().clone(),
+            descriptor: method.descriptor().clone(),
+            call_site_descriptor: None,
+        }
+    }
+
+    /// The class or interface that declares the method.
+    pub fn class(&self) -> &Rc<Class> {
+        &self.class
+    }
+
+    pub fn method(&self) -> &Method {
+        self.class
+            .find_method(&self.name, &self.descriptor)
+            .expect("resolved method is declared by its class")
+    }
+
+    /// The descriptor that the invocation uses if the method is signature
+    /// polymorphic ([`$2.9.3`]), or [`None`] for all other methods, which are
+    /// invoked with their declared descriptor.
+    ///
+    /// [`$2.9.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.9.3
+    pub fn call_site_descriptor(&self) -> Option<&str> {
+        self.call_site_descriptor.as_deref()
+    }
+
+    /// The method as seen by the access checks.
+    pub fn as_member(&self) -> Member<'_> {
+        let flags = self.flags();
+        Member {
+            declaring_class: &self.class,
+            name: &self.name,
+            visibility: Visibility::of_method(flags),
+            is_static: flags.contains(MethodAccessFlags::STATIC),
+            is_method: true,
+        }
+    }
+
+    fn is_abstract(&self) -> bool {
+        self.flags().contains(MethodAccessFlags::ABSTRACT)
+    }
+
+    fn flags(&self) -> MethodAccessFlags {
+        self.method().access_flags()
+    }
+}
+
+impl PartialEq for ResolvedMethod {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.class, &other.class)
+            && self.name == other.name
+            && self.descriptor == other.descriptor
+    }
+}
+
+/// A field together with the class that declares it.
+#[derive(Clone)]
+pub struct ResolvedField {
+    class: Rc<Class>,
+    field: Field,
+}
+
+impl ResolvedField {
+    /// The class or interface that declares the field.
+    pub fn class(&self) -> &Rc<Class> {
+        &self.class
+    }
+
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.field.access_flags().contains(FieldAccessFlags::STATIC)
+    }
+
+    /// The field as seen by the access checks.
+    pub fn as_member(&self) -> Member<'_> {
+        Member {
+            declaring_class: &self.class,
+            name: self.field.name(),
+            visibility: Visibility::of_field(self.field.access_flags()),
+            is_static: self.is_static(),
+            is_method: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for ResolvedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}:{}",
+            self.class.name(),
+            self.field.name(),
+            self.field.descriptor()
+        )
+    }
+}
+
+impl std::fmt::Debug for ResolvedMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}{}", self.class.name(), self.name, self.descriptor)
+    }
+}
+
+/// Resolves the field that a field instruction of the class `accessor` refers
+/// to, as specified by [`$5.4.3.2`]: the field is looked up in the class, then
+/// in its superinterfaces, and then in its superclass. Fails with an
+/// `IllegalAccessError` if the field is not accessible from `accessor`.
+///
+/// [`$5.4.3.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.2
+pub fn resolve_field(
+    accessor: &Class,
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+) -> Result<ResolvedField, VmError> {
+    let resolved = find_field(class, name, descriptor).ok_or_else(|| {
+        VmError::from(LinkageError::NoSuchField(format!(
+            "Class {} does not have member field '{} {}'",
+            class.name().replace('/', "."),
+            descriptor,
+            name
+        )))
+    })?;
+    check_member_access(accessor, &resolved.as_member(), class)?;
+    Ok(resolved)
+}
+
+/// Resolves the field that a field instruction of the class `accessor` refers
+/// to, see [`resolve_field`], and fails with an `IncompatibleClassChangeError`
+/// if the field is static and the instruction is not, or the other way around
+/// ([`$6.5`]).
+///
+/// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+pub fn resolve_field_access(
+    accessor: &Class,
+    kind: FieldKind,
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+) -> Result<ResolvedField, VmError> {
+    let resolved = resolve_field(accessor, class, name, descriptor)?;
+    match (kind, resolved.is_static()) {
+        (FieldKind::Static, false) => Err(incompatible(format!(
+            "Expected static field {}.{}",
+            resolved.class.name().replace('/', "."),
+            name
+        ))),
+        (FieldKind::Instance, true) => Err(incompatible(format!(
+            "Expected non-static field {}.{}",
+            resolved.class.name().replace('/', "."),
+            name
+        ))),
+        _ => Ok(resolved),
+    }
+}
+
+/// Fails with an `InstantiationError` if the class that a `new` instruction
+/// of the class `accessor` refers to is an interface or abstract ([`$6.5`]),
+/// and with an `IllegalAccessError` if it is not accessible.
+///
+/// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+pub fn check_instantiation(accessor: &Class, class: &Class) -> Result<(), VmError> {
+    check_class_access(accessor, class)?;
+    if class
+        .access_flags()
+        .intersects(ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT)
+    {
+        return Err(LinkageError::Instantiation(class.name().replace('/', ".")).into());
+    }
+    Ok(())
+}
+
+/// Fails with an `IllegalAccessError` if the resolved field is final, and the
+/// `putfield` or `putstatic` instruction that stores to it is not in the
+/// method `method_name` of the declaring class that initializes it ([`$6.5`]):
+/// `<init>` for instance fields, and `<clinit>` for static fields.
+///
+/// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+pub fn check_field_store(
+    accessor: &Class,
+    method_name: &str,
+    field: &ResolvedField,
+) -> Result<(), VmError> {
+    if !field.field.access_flags().contains(FieldAccessFlags::FINAL) {
+        return Ok(());
+    }
+    let (kind, initializer) = if field.is_static() {
+        ("static", "<clinit>")
+    } else {
+        ("non-static", "<init>")
+    };
+    let name = format!(
+        "{}.{}",
+        field.class.name().replace('/', "."),
+        field.field.name()
+    );
+    let message = if !std::ptr::eq(accessor, field.class.as_ref()) {
+        format!(
+            "Update to {} final field {} attempted from a different class ({}) than the field's declaring class",
+            kind,
+            name,
+            accessor.name().replace('/', ".")
+        )
+    } else if method_name != initializer {
+        format!(
+            "Update to {} final field {} attempted from a different method ({}) than the initializer method {}",
+            kind, name, method_name, initializer
+        )
+    } else {
+        return Ok(());
+    };
+    Err(LinkageError::IllegalAccess(message).into())
+}
+
+fn find_field(class: &Rc<Class>, name: &str, descriptor: &str) -> Option<ResolvedField> {
+    if let Some(field) = class.find_field(name, descriptor) {
+        return Some(ResolvedField {
+            class: class.clone(),
+            field: field.clone(),
+        });
+    }
+    class
+        .interfaces()
+        .iter()
+        .find_map(|i| find_field(i, name, descriptor))
+        .or_else(|| find_field(class.super_class()?, name, descriptor))
+}
+
+/// Resolves the method that an invoke instruction of the class `accessor`
+/// refers to, and performs the linking checks of the instruction ([`$6.5`]),
+/// including the access checks of [`$5.4.4`].
+///
+/// `interface_ref` tells whether the instruction refers to the method with a
+/// `CONSTANT_InterfaceMethodref`, in which case `class` must be an interface.
+/// Since class file version 52, `invokestatic` and `invokespecial` may refer to
+/// interface methods, e.g. to call a static or a private interface method.
+///
+/// [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+/// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+pub fn resolve_invoke(
+    accessor: &Class,
+    kind: InvokeKind,
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+    interface_ref: bool,
+) -> Result<ResolvedMethod, VmError> {
+    let resolved = if interface_ref {
+        resolve_interface_method(class, name, descriptor)?
+    } else {
+        resolve_class_method(class, name, descriptor)?
+    };
+    check_member_access(accessor, &resolved.as_member(), class)?;
+    let is_static = resolved.flags().contains(MethodAccessFlags::STATIC);
+    match kind {
+        InvokeKind::Static if !is_static => Err(incompatible(format!(
+            "Expected static method '{:?}'",
+            resolved
+        ))),
+        InvokeKind::Special | InvokeKind::Virtual | InvokeKind::Interface if is_static => Err(
+            incompatible(format!("Expecting non-static method '{:?}'", resolved)),
+        ),
+        _ => Ok(resolved),
+    }
+}
+
+/// Resolves a method of a class, as specified by [`$5.4.3.3`]: the method is
+/// looked up in the class and its superclasses first, and then in its
+/// superinterfaces.
+///
+/// A signature polymorphic method of `MethodHandle` or `VarHandle` (like
+/// `invokeExact`) is found by its name alone, whatever the descriptor is. The
+/// descriptor is then kept as the [call site descriptor](ResolvedMethod::call_site_descriptor).
+///
+/// [`$5.4.3.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.3
+pub fn resolve_class_method(
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+) -> Result<ResolvedMethod, VmError> {
+    if class.is_interface() {
+        return Err(incompatible(format!(
+            "Found interface {}, but class was expected",
+            class.name()
+        )));
+    }
+    if let Some(method) = find_signature_polymorphic(class, name) {
+        return Ok(ResolvedMethod {
+            call_site_descriptor: Some(descriptor.to_string()),
+            ..ResolvedMethod::new(class, method)
+        });
+    }
+    if let Some(method) = find_in_superclasses(class, name, descriptor) {
+        return Ok(method);
+    }
+    find_in_superinterfaces(class, name, descriptor)
+        .ok_or_else(|| no_such_method(class, name, descriptor))
+}
+
+/// Resolves a method of an interface, as specified by [`$5.4.3.4`]: the method
+/// is looked up in the interface, then in the public instance methods of
+/// `java/lang/Object`, and then in its superinterfaces.
+///
+/// [`$5.4.3.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.4
+pub fn resolve_interface_method(
+    interface: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+) -> Result<ResolvedMethod, VmError> {
+    if !interface.is_interface() {
+        return Err(incompatible(format!(
+            "Found class {}, but interface was expected",
+            interface.name()
+        )));
+    }
+    if let Some(method) = interface.find_method(name, descriptor) {
+        return Ok(ResolvedMethod::new(interface, method));
+    }
+    // the superclass of every interface is java/lang/Object
+    if let Some(object) = interface.super_class() {
+        let public_instance = |m: &&Method| {
+            m.access_flags().contains(MethodAccessFlags::PUBLIC)
+                && !m.access_flags().contains(MethodAccessFlags::STATIC)
+        };
+        if let Some(method) = object.find_method(name, descriptor).filter(public_instance) {
+            return Ok(ResolvedMethod::new(object, method));
+        }
+    }
+    find_in_superinterfaces(interface, name, descriptor)
+        .ok_or_else(|| no_such_method(interface, name, descriptor))
+}
+
+/// Selects the method that `invokevirtual` or `invokeinterface` invokes on an
+/// object of the given class, as specified by [`$5.4.6`].
+///
+/// An instance method of the class or one of its superclasses takes precedence.
+/// Otherwise, the default method of the most specific superinterface is
+/// selected. Fails with an `IncompatibleClassChangeError` if several unrelated
+/// superinterfaces provide a default method, and with an
+/// `AbstractMethodError` if the selected method is abstract or there is none.
+///
+/// [`$5.4.6`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.6
+pub fn select_method(
+    receiver: &Rc<Class>,
+    resolved: &ResolvedMethod,
+) -> Result<ResolvedMethod, VmError> {
+    // signature polymorphic methods are final, and the selected method has to
+    // keep the call site descriptor
+    if resolved.flags().contains(MethodAccessFlags::PRIVATE)
+        || resolved.call_site_descriptor.is_some()
+    {
+        return Ok(resolved.clone());
+    }
+    let (name, descriptor) = (resolved.name.as_str(), resolved.descriptor.as_str());
+
+    // overriding also depends on the runtime package for package-private
+    // methods, which is not taken into account here
+    let mut class = Some(receiver);
+    while let Some(c) = class {
+        let overriding = c.find_method(name, descriptor).filter(|m| {
+            !m.access_flags()
+                .intersects(MethodAccessFlags::PRIVATE | MethodAccessFlags::STATIC)
+        });
+        if let Some(method) = overriding {
+            let selected = ResolvedMethod::new(c, method);
+            if selected.is_abstract() {
+                return Err(abstract_method(&selected));
+            }
+            return Ok(selected);
+        }
+        class = c.super_class();
+    }
+
+    let candidates: Vec<ResolvedMethod> = maximally_specific(receiver, name, descriptor)
+        .into_iter()
+        .filter(|m| !m.is_abstract())
+        .collect();
+    match candidates.as_slice() {
+        [selected] => Ok(selected.clone()),
+        [] => Err(abstract_method(resolved)),
+        conflicting => Err(incompatible(format!(
+            "Conflicting default methods: {}",
+            conflicting
+                .iter()
+                .map(|m| format!("{}.{}", m.class().name(), name))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))),
+    }
+}
+
+/// Returns the method with the given name if it is the only method of that
+/// name in the class, and it is signature polymorphic: declared in
+/// `java/lang/invoke/MethodHandle` or `java/lang/invoke/VarHandle`, with a
+/// single `Object[]` parameter and the flags `ACC_VARARGS` and `ACC_NATIVE`.
+fn find_signature_polymorphic<'a>(class: &'a Class, name: &str) -> Option<&'a Method> {
+    if !matches!(
+        class.name().as_str(),
+        "java/lang/invoke/MethodHandle" | "java/lang/invoke/VarHandle"
+    ) {
+        return None;
+    }
+    let mut methods = class.methods_named(name);
+    let method = methods.next().filter(|_| methods.next().is_none())?;
+    let flags = MethodAccessFlags::VARARGS | MethodAccessFlags::NATIVE;
+    let single_object_array = method.descriptor().starts_with("([Ljava/lang/Object;)");
+    (method.access_flags().contains(flags) && single_object_array).then_some(method)
+}
+
+fn find_in_superclasses(class: &Rc<Class>, name: &str, descriptor: &str) -> Option<ResolvedMethod> {
+    let mut class = Some(class);
+    while let Some(c) = class {
+        if let Some(method) = c.find_method(name, descriptor) {
+            return Some(ResolvedMethod::new(c, method));
+        }
+        class = c.super_class();
+    }
+    None
+}
+
+/// Resolves the method among the superinterfaces: the only non-abstract
+/// maximally-specific method if there is exactly one, otherwise any of the
+/// maximally-specific methods.
+fn find_in_superinterfaces(
+    class: &Rc<Class>,
+    name: &str,
+    descriptor: &str,
+) -> Option<ResolvedMethod> {
+    let candidates = maximally_specific(class, name, descriptor);
+    let mut non_abstract = candidates.iter().filter(|m| !m.is_abstract());
+    match (non_abstract.next(), non_abstract.next()) {
+        (Some(method), None) => Some(method.clone()),
+        _ => candidates.into_iter().next(),
+    }
+}
+
+/// Returns the maximally-specific superinterface methods of the class for the
+/// given name and descriptor ([`$5.4.3.3`]): the non-private, non-static
+/// methods declared in a superinterface, for which no subinterface also
+/// declares such a method.
+///
+/// [`$5.4.3.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.3.3
+fn maximally_specific(class: &Rc<Class>, name: &str, descriptor: &str) -> Vec<ResolvedMethod> {
+    let mut interfaces = vec![];
+    collect_superinterfaces(class, &mut interfaces);
+    let declaring: Vec<ResolvedMethod> = interfaces
+        .iter()
+        .filter_map(|i| {
+            let method = i.find_method(name, descriptor)?;
+            let excluded = MethodAccessFlags::PRIVATE | MethodAccessFlags::STATIC;
+            (!method.access_flags().intersects(excluded)).then(|| ResolvedMethod::new(i, method))
+        })
+        .collect();
+    declaring
+        .iter()
+        .filter(|m| {
+            !declaring.iter().any(|other| {
+                !Rc::ptr_eq(other.class(), m.class()) && other.class().is_subclass_of(m.class())
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Collects all interfaces that the class implements directly or indirectly,
+/// including the ones of its superclasses, each once.
+fn collect_superinterfaces(class: &Rc<Class>, out: &mut Vec<Rc<Class>>) {
+    for interface in class.interfaces() {
+        if !out.iter().any(|i| Rc::ptr_eq(i, interface)) {
+            out.push(interface.clone());
+            collect_superinterfaces(interface, out);
+        }
+    }
+    if let Some(super_class) = class.super_class() {
+        collect_superinterfaces(super_class, out);
+    }
+}
+
+fn incompatible(message: String) -> VmError {
+    LinkageError::IncompatibleClassChange(message).into()
+}
+
+fn no_such_method(class: &Class, name: &str, descriptor: &str) -> VmError {
+    LinkageError::NoSuchMethod(format!(
+        "'{}.{}{}'",
+        class.name().replace('/', "."),
+        name,
+        descriptor
+    ))
+    .into()
+}
+
+fn abstract_method(method: &ResolvedMethod) -> VmError {
+    LinkageError::AbstractMethod(format!("{:?}", method)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_loader;
+    use crate::vm::classloader::ClassLoader;
+
+    fn select(
+        receiver: &Rc<Class>,
+        class: &Rc<Class>,
+        kind: InvokeKind,
+    ) -> Result<String, VmError> {
+        let resolved = resolve_invoke(class, kind, class, "value", "()I", class.is_interface())?;
+        select_method(receiver, &resolved).map(|m| format!("{:?}", m))
+    }
+
+    #[test]
+    fn test_default_method_selection() {
+        let mut loader = class_loader(&["resolve"]);
+        let mut load = |name: &str| loader.find_or_load_class(name).unwrap();
+        let (a, b) = (load("A"), load("B"));
+        let (uses_b, overrides) = (load("UsesB"), load("Overrides"));
+        let sub = load("Sub");
+
+        // B.value is more specific than A.value
+        assert_eq!(
+            "B.value()I",
+            select(&uses_b, &a, InvokeKind::Interface).unwrap()
+        );
+        assert_eq!(
+            "B.value()I",
+            select(&uses_b, &uses_b, InvokeKind::Virtual).unwrap()
+        );
+        assert_eq!(
+            "B.value()I",
+            select(&sub, &sub, InvokeKind::Virtual).unwrap()
+        );
+        // a method of the class overrides the default method
+        assert_eq!(
+            "Overrides.value()I",
+            select(&overrides, &b, InvokeKind::Interface).unwrap()
+        );
+        // class method resolution finds the default method as well
+        assert_eq!(
+            "B.value()I",
+            format!(
+                "{:?}",
+                resolve_class_method(&uses_b, "value", "()I").unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_default_method_errors() {
+        let mut loader = class_loader(&["resolve"]);
+        let mut load = |name: &str| loader.find_or_load_class(name).unwrap();
+        let (b, d) = (load("B"), load("D"));
+        let (diamond, abstract_d) = (load("Diamond"), load("AbstractD"));
+
+        let e = select(&diamond, &b, InvokeKind::Interface).unwrap_err();
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: Conflicting default methods: B.value C.value",
+            e.to_string()
+        );
+        assert_eq!(
+            "java/lang/AbstractMethodError",
+            select(&abstract_d, &d, InvokeKind::Interface)
+                .unwrap_err()
+                .class_name()
+        );
+        assert_eq!(
+            "java/lang/AbstractMethodError",
+            select(&abstract_d, &abstract_d, InvokeKind::Virtual)
+                .unwrap_err()
+                .class_name()
+        );
+    }
+
+    #[test]
+    fn test_static_and_private_methods() {
+        let mut loader = class_loader(&["resolve"]);
+        let a = loader.find_or_load_class("A").unwrap();
+        let sub = loader.find_or_load_class("Sub").unwrap();
+
+        let twice = resolve_invoke(&a, InvokeKind::Static, &a, "twice", "(I)I", true).unwrap();
+        assert_eq!("A.twice(I)I", format!("{:?}", twice));
+        assert_eq!(
+            "java/lang/IncompatibleClassChangeError",
+            resolve_invoke(&a, InvokeKind::Interface, &a, "twice", "(I)I", true)
+                .unwrap_err()
+                .class_name()
+        );
+        assert_eq!(
+            "java/lang/IncompatibleClassChangeError",
+            resolve_invoke(&a, InvokeKind::Static, &a, "value", "()I", true)
+                .unwrap_err()
+                .class_name()
+        );
+        // an interface method reference to a class, and the other way around
+        assert!(resolve_invoke(&a, InvokeKind::Static, &a, "twice", "(I)I", false).is_err());
+        assert!(resolve_invoke(&a, InvokeKind::Static, &sub, "util", "()I", true).is_err());
+
+        // static methods of superinterfaces are not inherited
+        assert_eq!(
+            "java/lang/NoSuchMethodError",
+            resolve_invoke(&a, InvokeKind::Static, &sub, "twice", "(I)I", false)
+                .unwrap_err()
+                .class_name()
+        );
+        assert!(resolve_invoke(&a, InvokeKind::Static, &sub, "util", "()I", false).is_ok());
+
+        // private methods are invoked without selection
+        let hidden =
+            resolve_invoke(&sub, InvokeKind::Virtual, &sub, "hidden", "()I", false).unwrap();
+        assert_eq!(hidden, select_method(&sub, &hidden).unwrap());
+    }
+
+    #[test]
+    fn test_final_field_store() {
+        let mut loader = class_loader(&["final"]);
+        let sealed = loader.find_or_load_class("fin/Sealed").unwrap();
+        let middle = loader.find_or_load_class("fin/Middle").unwrap();
+        let id = resolve_field(&middle, &middle, "id", "I").unwrap();
+        let created = resolve_field(&sealed, &sealed, "CREATED", "J").unwrap();
+
+        assert!(check_field_store(&sealed, "<init>", &id).is_ok());
+        assert!(check_field_store(&sealed, "<clinit>", &created).is_ok());
+        assert_eq!(
+            "java.lang.IllegalAccessError: Update to non-static final field fin.Sealed.id attempted from a different method (reset) than the initializer method <init>",
+            check_field_store(&sealed, "reset", &id).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "java/lang/IllegalAccessError",
+            check_field_store(&sealed, "<init>", &created)
+                .unwrap_err()
+                .class_name()
+        );
+        // not even the constructor of a subclass may store to the field
+        assert_eq!(
+            "java.lang.IllegalAccessError: Update to non-static final field fin.Sealed.id attempted from a different class (fin.Middle) than the field's declaring class",
+            check_field_store(&middle, "<init>", &id).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_incompatible_class_changes() {
+        let mut loader = class_loader(&["change"]);
+        let message = |result: Result<(), VmError>| result.unwrap_err().to_string();
+
+        // supertypes that changed between a class and an interface
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: class Child has interface Parent as super class",
+            message(loader.find_or_load_class("Child").map(|_| ()))
+        );
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: class Impl can not implement Contract, because it is not an interface",
+            message(loader.find_or_load_class("Impl").map(|_| ()))
+        );
+
+        let mut load = |name: &str| loader.find_or_load_class(name).unwrap();
+        let (api, shape, base) = (load("Api"), load("Shape"), load("Base"));
+        let (service, worker) = (load("Service"), load("Worker"));
+
+        // a class method that is now declared by an interface
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: Found interface Api, but class was expected",
+            message(
+                resolve_invoke(&worker, InvokeKind::Virtual, &api, "call", "()I", false)
+                    .map(|_| ())
+            )
+        );
+
+        // a field that became a method, and fields that changed to or from static
+        assert_eq!(
+            "java/lang/NoSuchFieldError",
+            resolve_field(&worker, &shape, "area", "D")
+                .unwrap_err()
+                .class_name()
+        );
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: Expected static field Shape.count",
+            message(
+                resolve_field_access(&worker, FieldKind::Static, &shape, "count", "I").map(|_| ())
+            )
+        );
+        assert_eq!(
+            "java.lang.IncompatibleClassChangeError: Expected non-static field Shape.size",
+            message(
+                resolve_field_access(&worker, FieldKind::Instance, &shape, "size", "I").map(|_| ())
+            )
+        );
+        assert!(resolve_field_access(&worker, FieldKind::Static, &shape, "size", "I").is_ok());
+
+        // a class that became abstract, and an interface
+        assert_eq!(
+            "java.lang.InstantiationError: Base",
+            message(check_instantiation(&worker, &base))
+        );
+        assert_eq!(
+            "java/lang/InstantiationError",
+            check_instantiation(&worker, &service)
+                .unwrap_err()
+                .class_name()
+        );
+        assert!(check_instantiation(&worker, &worker).is_ok());
+
+        // an interface method that the class doesn't implement
+        let work = resolve_invoke(
+            &worker,
+            InvokeKind::Interface,
+            &service,
+            "work",
+            "()I",
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            "java.lang.AbstractMethodError: Service.work()I",
+            message(select_method(&worker, &work).map(|_| ()))
+        );
+    }
+
+    #[test]
+    fn test_signature_polymorphic() {
+        let mut loader = class_loader(&["resolve"]);
+        let method_handle = loader
+            .find_or_load_class("java/lang/invoke/MethodHandle")
+            .unwrap();
+        let var_handle = loader
+            .find_or_load_class("java/lang/invoke/VarHandle")
+            .unwrap();
+
+        for (class, name, descriptor) in [
+            (&method_handle, "invokeExact", "(Ljava/lang/String;I)V"),
+            (&method_handle, "invoke", "()I"),
+            (
+                &method_handle,
+                "invoke",
+                "([Ljava/lang/Object;)Ljava/lang/Object;",
+            ),
+            (&var_handle, "get", "(LFoo;)J"),
+        ] {
+            let resolved =
+                resolve_invoke(class, InvokeKind::Virtual, class, name, descriptor, false).unwrap();
+            assert_eq!(Some(descriptor), resolved.call_site_descriptor());
+            assert_eq!(
+                "([Ljava/lang/Object;)",
+                &resolved.method().descriptor()[..21]
+            );
+            let selected = select_method(class, &resolved).unwrap();
+            assert_eq!(resolved, selected);
+            assert_eq!(Some(descriptor), selected.call_site_descriptor());
+        }
+
+        // other methods are resolved by their descriptor as usual
+        let bind_to = "(Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;";
+        let resolved = resolve_class_method(&method_handle, "bindTo", bind_to).unwrap();
+        assert_eq!(None, resolved.call_site_descriptor());
+        assert_eq!(
+            "java/lang/NoSuchMethodError",
+            resolve_class_method(&method_handle, "bindTo", "(I)V")
+                .unwrap_err()
+                .class_name()
+        );
+    }
+}
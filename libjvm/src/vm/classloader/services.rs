@@ -0,0 +1,113 @@
+use crate::vm::error::{Exception, VmError};
+
+/// The directory of the class path entries that contains the
+/// provider-configuration files, one per service, named after the binary name
+/// of the service, e.g. `META-INF/services/java.sql.Driver`.
+pub const SERVICES_DIR: &str = "META-INF/services";
+
+/// Parses a provider-configuration file of the given service, as specified by
+/// `java.util.ServiceLoader`. Every line names a provider class by its binary
+/// name, e.g. `com.example.FastDriver`. Everything after a `#` is a comment,
+/// whitespace around a name is ignored, and so are empty lines. Names that
+/// appear more than once are only returned the first time.
+///
+/// `url` is the URL of the file, for the message of the
+/// `ServiceConfigurationError` that a malformed file fails with.
+pub fn parse_provider_configuration(
+    service: &str,
+    url: &str,
+    contents: &[u8],
+) -> Result<Vec<String>, VmError> {
+    let fail = |line: usize, message: &str| -> VmError {
+        Exception::ServiceConfiguration(format!("{}: {}:{}: {}", service, url, line, message))
+            .into()
+    };
+    let contents = std::str::from_utf8(contents).map_err(|e| {
+        let line = contents[..e.valid_up_to()]
+            .iter()
+            .filter(|b| **b == b'\n')
+            .count();
+        fail(line + 1, "Illegal configuration-file syntax")
+    })?;
+
+    let mut names: Vec<String> = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let name = line.split('#').next().unwrap_or_default().trim();
+        if name.is_empty() {
+            continue;
+        }
+        if name.contains(' ') || name.contains('\t') {
+            return Err(fail(index + 1, "Illegal configuration-file syntax"));
+        }
+        if !is_binary_name(name) {
+            return Err(fail(
+                index + 1,
+                &format!("Illegal provider-class name: {}", name),
+            ));
+        }
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Whether the name is a sequence of Java identifiers separated by dots.
+fn is_binary_name(name: &str) -> bool {
+    name.split('.').all(|identifier| {
+        let mut chars = identifier.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_configuration() {
+        let contents = b"# providers\n\n  com.example.Fast  # the default\ncom.example.Slow\r\ncom.example.Fast\n\xC3\xA9t\xC3\xA9.Summer\n";
+        assert_eq!(
+            Ok(vec![
+                "com.example.Fast".to_string(),
+                "com.example.Slow".to_string(),
+                "\u{e9}t\u{e9}.Summer".to_string(),
+            ]),
+            parse_provider_configuration("com.example.Driver", "file:d", contents)
+        );
+        assert_eq!(
+            Ok(vec![]),
+            parse_provider_configuration("com.example.Driver", "file:d", b"")
+        );
+    }
+
+    #[test]
+    fn test_malformed_provider_configuration() {
+        for (contents, message) in [
+            (
+                &b"com.example.Fast\ncom.example Slow\n"[..],
+                "com.example.Driver: file:d:2: Illegal configuration-file syntax",
+            ),
+            (
+                b"com.example.\n",
+                "com.example.Driver: file:d:1: Illegal provider-class name: com.example.",
+            ),
+            (
+                b"a.Fast\nb.Fast\n1st.Fast\n",
+                "com.example.Driver: file:d:3: Illegal provider-class name: 1st.Fast",
+            ),
+            (
+                b"a.Fast\n\xFF\n",
+                "com.example.Driver: file:d:2: Illegal configuration-file syntax",
+            ),
+        ] {
+            assert_eq!(
+                Err(Exception::ServiceConfiguration(message.to_string()).into()),
+                parse_provider_configuration("com.example.Driver", "file:d", contents)
+            );
+        }
+    }
+}
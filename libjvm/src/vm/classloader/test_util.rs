@@ -0,0 +1,29 @@
+use crate::vm::classloader::bootstrap::BootstrapClassLoader;
+use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use crate::vm::descriptor::DescriptorTable;
+use crate::vm::symbol::SymbolTable;
+use libvfs::FileSystem;
+use std::sync::Arc;
+
+/// The class path of the given directories under `tests/resources/vm`,
+/// followed by `tests/resources/vm/classloader`, which has `java/lang/Object`
+/// and the other classes that every test needs.
+pub fn class_path(dirs: &[&str]) -> ClassPath {
+    ClassPath::from(
+        dirs.iter()
+            .chain(&["classloader"])
+            .map(|dir| ClassPathEntry::Dir(format!("tests/resources/vm/{}", dir)))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// A bootstrap class loader for the [`class_path`] of the given directories,
+/// on the file system of the host.
+pub fn class_loader(dirs: &[&str]) -> BootstrapClassLoader {
+    BootstrapClassLoader::new(
+        FileSystem::new_os_fs(),
+        class_path(dirs),
+        Arc::new(SymbolTable::new()),
+        Arc::new(DescriptorTable::new()),
+    )
+}
@@ -0,0 +1,82 @@
+use crate::vm::clock::Instant;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::time::Duration;
+
+/// Where the class file of a loaded class came from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ClassSource {
+    /// A class file in a directory of the class path, with the path of the
+    /// class file.
+    Dir(String),
+    /// An entry of a jar file of the class path.
+    JarEntry { jar: String, entry: String },
+    /// A class file that was stored in a snapshot, see
+    /// [`Snapshot`](crate::vm::snapshot::Snapshot).
+    Snapshot,
+}
+
+impl Display for ClassSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClassSource::Dir(path) => write!(f, "file:{}", path),
+            ClassSource::JarEntry { jar, entry } => write!(f, "jar:file:{}!/{}", jar, entry),
+            // like classes from the CDS archive of HotSpot
+            ClassSource::Snapshot => write!(f, "shared objects file"),
+        }
+    }
+}
+
+/// A class that was loaded and defined by a class loader.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClassLoadEvent {
+    /// The binary name of the class, e.g. `java/lang/Object`.
+    pub name: String,
+    pub source: ClassSource,
+    /// The name of the class loader that defined the class.
+    pub loader: String,
+    /// The time it took to read, parse and define the class, not including
+    /// loading its superclasses.
+    pub duration: Duration,
+}
+
+/// Writes a line for every loaded class, like `-verbose:class` (or
+/// `-Xlog:class+load`) of HotSpot, e.g.
+///
+/// ```text
+/// [0.004s][info][class,load] java.lang.Object source: file:rt/java/lang/Object.class loader: bootstrap time: 0.112ms
+/// ```
+///
+/// The time in brackets is the time since the trace was created.
+pub struct ClassLoadTrace {
+    out: Box<dyn Write>,
+    start: Instant,
+}
+
+impl ClassLoadTrace {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+        }
+    }
+
+    /// A trace that writes to the standard output of the host, like HotSpot.
+    pub fn stdout() -> Self {
+        Self::new(Box::new(std::io::stdout()))
+    }
+
+    pub fn record(&mut self, event: &ClassLoadEvent) {
+        // the trace is a diagnostic, so failing to write it must not fail the
+        // class loading
+        let _ = writeln!(
+            self.out,
+            "[{:.3}s][info][class,load] {} source: {} loader: {} time: {:.3}ms",
+            self.start.elapsed().as_secs_f64(),
+            event.name.replace('/', "."),
+            event.source,
+            event.loader,
+            event.duration.as_secs_f64() * 1000.0,
+        );
+    }
+}
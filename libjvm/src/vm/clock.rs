@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The source of time for Java code, e.g. for `System.currentTimeMillis`,
+/// `System.nanoTime` and `Thread.sleep`.
+pub trait Clock: Send + Sync {
+    /// The wall clock time, as the duration since the Unix epoch.
+    fn now(&self) -> Duration;
+
+    /// A monotonic time in nanoseconds, with an arbitrary origin.
+    fn nano_time(&self) -> i64;
+
+    /// Blocks the calling thread for the given duration.
+    fn sleep(&self, duration: Duration);
+
+    fn current_time_millis(&self) -> i64 {
+        self.now().as_millis() as i64
+    }
+}
+
+/// The clock that a [`Runtime`](crate::vm::runtime::Runtime) uses unless
+/// another one is set: the [`SystemClock`], or on wasm32, where the standard
+/// library has no clock, a [`VirtualClock`] that starts at the Unix epoch.
+/// Hosts there can set a clock of their own, e.g. one backed by `Date.now()`
+/// of JavaScript.
+pub fn default_clock() -> Arc<dyn Clock> {
+    if cfg!(target_arch = "wasm32") {
+        Arc::new(VirtualClock::new(Duration::ZERO))
+    } else {
+        Arc::new(SystemClock::new())
+    }
+}
+
+/// Measures how long operations of the VM itself take, e.g. loading a class,
+/// like `std::time::Instant`, which panics on wasm32. There, all durations are
+/// zero.
+#[cfg(target_arch = "wasm32")]
+#[derive(Copy, Clone)]
+pub(crate) struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// The clock of the host system.
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn nano_time(&self) -> i64 {
+        self.origin.elapsed().as_nanos() as i64
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+/// A deterministic clock that only advances when told to, or when a thread
+/// sleeps. Sleeping returns immediately, so tests with timeouts run fast and
+/// always see the same times.
+pub struct VirtualClock {
+    /// The time that has passed since the start of the clock.
+    elapsed: Mutex<Duration>,
+    /// The wall clock time at the start of the clock.
+    start: Duration,
+}
+
+impl VirtualClock {
+    /// Creates a clock whose wall clock time starts at the given duration since
+    /// the Unix epoch.
+    pub fn new(start: Duration) -> Self {
+        Self {
+            elapsed: Mutex::new(Duration::ZERO),
+            start,
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.start + self.elapsed()
+    }
+
+    fn nano_time(&self) -> i64 {
+        self.elapsed().as_nanos() as i64
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = VirtualClock::new(Duration::from_secs(1_000));
+        assert_eq!(1_000_000, clock.current_time_millis());
+        assert_eq!(0, clock.nano_time());
+
+        clock.sleep(Duration::from_millis(1_500));
+        assert_eq!(1_001_500, clock.current_time_millis());
+        assert_eq!(1_500_000_000, clock.nano_time());
+    }
+
+    #[test]
+    fn test_system_clock_monotonic() {
+        let clock = SystemClock::new();
+        let a = clock.nano_time();
+        let b = clock.nano_time();
+        assert!(a <= b);
+        assert!(clock.current_time_millis() > 0);
+    }
+}
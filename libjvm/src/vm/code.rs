@@ -0,0 +1,543 @@
+#[cfg(any(debug_assertions, feature = "stack-guards"))]
+use crate::vm::stack::StackMap;
+use libjava::bytecode::{Op, OpParseError};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+/// A pre-decoded instruction of a method.
+///
+/// Besides the plain [`Op`]s, this contains superinstructions, which replace
+/// common sequences of ops, so that the interpreter only has to dispatch once
+/// for the whole sequence.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Instruction {
+    Op(Op),
+    /// `aload_0` followed by `getfield`, i.e. reading a field of `this`.
+    ALoad0GetField(u16),
+    /// `iload a`, `iload b`, `iadd`.
+    ILoadILoadIAdd(u8, u8),
+    /// An int constant (`iconst_<i>`, `bipush` or `sipush`) followed by
+    /// `istore`.
+    IConstIStore(i32, u8),
+    /// `aload array`, `iload index` and an array load like `iaload`, in a
+    /// loop that is known to keep the index within the bounds of the array,
+    /// so that the load needs no bounds check.
+    UncheckedArrayLoad(u8, u8, Op),
+}
+
+/// The pre-decoded code of a method.
+pub struct Code {
+    instructions: Vec<Instruction>,
+    /// The offset in the original bytecode of every instruction.
+    offsets: Vec<usize>,
+    /// For every instruction, the index of the basic block that it starts, if
+    /// it is the first instruction of a basic block.
+    block_starts: Vec<Option<usize>>,
+    /// The offsets of the first instructions of all basic blocks.
+    blocks: Vec<usize>,
+    /// The trap table: the original instruction of every index that was
+    /// patched with a breakpoint, see [`Code::set_breakpoint`].
+    traps: HashMap<usize, Instruction>,
+    /// The types that guarded frames are checked against, see
+    /// [`Code::set_stack_map`].
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    stack_map: Option<StackMap>,
+}
+
+impl Code {
+    /// Decodes the given bytecode and fuses common instruction sequences into
+    /// superinstructions.
+    ///
+    /// A sequence is never fused if any but its first instruction is a branch
+    /// target, so that every jump still lands on an instruction boundary.
+    /// `entry_points` are additional offsets that can be jumped to, like the
+    /// handlers of the exception table.
+    pub fn decode(code: &[u8], entry_points: &[usize]) -> Result<Self, OpParseError> {
+        let mut ops = vec![];
+        let mut cursor = Cursor::new(code);
+        while (cursor.position() as usize) < code.len() {
+            let offset = cursor.position() as usize;
+            ops.push((offset, Op::parse_at(&mut cursor, offset)?));
+        }
+
+        let mut targets: HashSet<usize> = entry_points.iter().copied().collect();
+        targets.extend(
+            ops.iter()
+                .flat_map(|(offset, op)| op.branch_targets(*offset)),
+        );
+
+        // a basic block starts at the start of the method, at every jump target,
+        // and after every instruction that doesn't fall through to the next one
+        let mut leaders = targets.clone();
+        leaders.insert(0);
+        leaders.extend(
+            ops.windows(2)
+                .filter(|pair| pair[0].1.ends_block())
+                .map(|pair| pair[1].0),
+        );
+
+        let in_bounds = in_bounds_loads(&ops, entry_points);
+
+        let mut instructions = Vec::with_capacity(ops.len());
+        let mut offsets = Vec::with_capacity(ops.len());
+        let mut i = 0;
+        while i < ops.len() {
+            let fusable = |len: usize| {
+                i + len <= ops.len()
+                    && ops[i + 1..i + len]
+                        .iter()
+                        .all(|(o, _)| !targets.contains(o))
+            };
+            let (instruction, len) = match &ops[i..] {
+                [(_, Op::ALoad(array)), (_, Op::ILoad(index)), (_, load), ..]
+                    if in_bounds.contains(&i) && fusable(3) =>
+                {
+                    (
+                        Instruction::UncheckedArrayLoad(*array, *index, load.clone()),
+                        3,
+                    )
+                }
+                [(_, Op::ALoad(0)), (_, Op::GetField(index)), ..] if fusable(2) => {
+                    (Instruction::ALoad0GetField(*index), 2)
+                }
+                [(_, Op::ILoad(a)), (_, Op::ILoad(b)), (_, Op::IAdd), ..] if fusable(3) => {
+                    (Instruction::ILoadILoadIAdd(*a, *b), 3)
+                }
+                [(_, constant), (_, Op::IStore(index)), ..] if fusable(2) => {
+                    match int_constant(constant) {
+                        Some(value) => (Instruction::IConstIStore(value, *index), 2),
+                        None => (Instruction::Op(ops[i].1.clone()), 1),
+                    }
+                }
+                _ => (Instruction::Op(ops[i].1.clone()), 1),
+            };
+            instructions.push(instruction);
+            offsets.push(ops[i].0);
+            i += len;
+        }
+
+        let mut blocks = vec![];
+        let block_starts = offsets
+            .iter()
+            .map(|offset| {
+                leaders.contains(offset).then(|| {
+                    blocks.push(*offset);
+                    blocks.len() - 1
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            instructions,
+            offsets,
+            block_starts,
+            blocks,
+            traps: HashMap::new(),
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            stack_map: None,
+        })
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Returns the offset in the original bytecode of the instruction at the
+    /// given index.
+    pub fn offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
+
+    /// Returns the index of the instruction that starts at the given offset in
+    /// the original bytecode, e.g. to resolve a branch target.
+    pub fn index_of(&self, offset: usize) -> Option<usize> {
+        self.offsets.binary_search(&offset).ok()
+    }
+
+    /// The offsets of the first instructions of the basic blocks of this code,
+    /// in ascending order. A basic block is only ever entered at its first
+    /// instruction.
+    pub fn blocks(&self) -> &[usize] {
+        &self.blocks
+    }
+
+    /// Returns the index (into [`Code::blocks`]) of the basic block that is
+    /// started by the instruction at the given index, or [`None`] if the
+    /// instruction is not the first of its block.
+    pub fn block_start(&self, index: usize) -> Option<usize> {
+        self.block_starts[index]
+    }
+
+    /// Patches a breakpoint into the instruction at the given offset in the
+    /// original bytecode, in place, and keeps the instruction in the trap
+    /// table, from which the interpreter executes it after notifying the
+    /// debugger. Returns false if there is already a breakpoint, or if no
+    /// instruction starts at the offset. An offset inside a superinstruction
+    /// has to be passed to [`Code::decode`] as an entry point to be
+    /// patchable.
+    pub fn set_breakpoint(&mut self, offset: usize) -> bool {
+        let Some(index) = self.index_of(offset) else {
+            return false;
+        };
+        if self.traps.contains_key(&index) {
+            return false;
+        }
+        let original = std::mem::replace(
+            &mut self.instructions[index],
+            Instruction::Op(Op::Breakpoint),
+        );
+        self.traps.insert(index, original);
+        true
+    }
+
+    /// Restores the original instruction at the given offset. Returns false
+    /// if there is no breakpoint at the offset.
+    pub fn clear_breakpoint(&mut self, offset: usize) -> bool {
+        let Some(index) = self.index_of(offset) else {
+            return false;
+        };
+        match self.traps.remove(&index) {
+            Some(original) => {
+                self.instructions[index] = original;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The original instruction at the given index if a breakpoint was
+    /// patched into it, or [`None`] if the instruction is executed as is.
+    pub fn trapped(&self, index: usize) -> Option<&Instruction> {
+        self.traps.get(&index)
+    }
+
+    /// Sets the stack map of the method, from its `StackMapTable`
+    /// attribute, which the interpreter checks the types of guarded frames
+    /// against at every instruction that the stack map has a frame for.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_stack_map(&mut self, stack_map: StackMap) {
+        self.stack_map = Some(stack_map);
+    }
+
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn stack_map(&self) -> Option<&StackMap> {
+        self.stack_map.as_ref()
+    }
+
+    /// The offsets of all breakpoints, in ascending order.
+    pub fn breakpoints(&self) -> Vec<usize> {
+        let mut offsets: Vec<usize> = self.traps.keys().map(|i| self.offsets[*i]).collect();
+        offsets.sort_unstable();
+        offsets
+    }
+}
+
+/// Returns the indices of the `aload` ops that start an `aload array`,
+/// `iload index`, array load sequence whose index is provably within the
+/// bounds of the array.
+///
+/// This recognizes the loop that `javac` emits for
+/// `for (int i = c; i < array.length; i++)` with a constant `c >= 0`:
+///
+/// ```text
+///     iconst c, istore i
+/// H:  iload i, aload array, arraylength, if_icmpge <exit>
+///     <body>
+///     iinc i, 1
+///     goto H
+/// ```
+///
+/// In the body, `0 <= i < array.length` holds as long as neither `i` nor
+/// `array` are written to, and the loop is only entered at its header. The
+/// increment has to be 1, since a larger one can overflow `i` to a negative
+/// index when `array.length` is close to `i32::MAX`. A loop
+/// that doesn't provably follow this pattern keeps its bounds checks.
+fn in_bounds_loads(ops: &[(usize, Op)], entry_points: &[usize]) -> HashSet<usize> {
+    let index_of = |offset: usize| ops.binary_search_by_key(&offset, |(o, _)| *o).ok();
+    let jumps: Vec<(usize, usize)> = ops
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (offset, op))| op.branch_targets(*offset).into_iter().map(move |t| (i, t)))
+        .collect();
+
+    let mut in_bounds = HashSet::new();
+    for (goto, (offset, op)) in ops.iter().enumerate() {
+        let header = match op {
+            Op::Goto(_) => op
+                .branch_targets(*offset)
+                .first()
+                .copied()
+                .and_then(index_of),
+            _ => None,
+        };
+        let header = match header {
+            Some(header) if header >= 2 && header + 4 < goto => header,
+            _ => continue,
+        };
+        let (start, end) = (ops[header].0, *offset);
+        let in_loop = |target: usize| (start..=end).contains(&target);
+
+        let (cmp_offset, cmp) = &ops[header + 3];
+        let head: Vec<&Op> = ops[header - 1..header + 3]
+            .iter()
+            .map(|(_, op)| op)
+            .collect();
+        let (index, array) = match (&ops[header - 2].1, head.as_slice(), cmp, &ops[goto - 1].1) {
+            (
+                init,
+                [Op::IStore(i), Op::ILoad(index), Op::ALoad(array), Op::ArrayLength],
+                Op::IfICmpGe(_),
+                Op::IInc(increment, n),
+            ) if int_constant(init).is_some_and(|c| c >= 0)
+                && i == index
+                && increment == index
+                && *n == 1
+                && cmp.branch_targets(*cmp_offset).iter().all(|t| !in_loop(*t)) =>
+            {
+                (*index, *array)
+            }
+            _ => continue,
+        };
+
+        // the loop is only entered at its header, after the initialization
+        let entered_inside = jumps
+            .iter()
+            .any(|(from, to)| in_loop(*to) && !(header..=goto).contains(from));
+        if entered_inside
+            || entry_points.iter().any(|e| in_loop(*e))
+            || jumps.iter().any(|(_, to)| *to == ops[header - 1].0)
+        {
+            continue;
+        }
+
+        let body = &ops[header + 4..goto - 1];
+        let unknown_writes = body.iter().any(|(_, op)| {
+            writes_local(op, index)
+                || writes_local(op, array)
+                || matches!(op, Op::Jsr(_) | Op::JsrW(_) | Op::Ret(_) | Op::Wide)
+        });
+        if unknown_writes {
+            continue;
+        }
+        for (i, window) in body.windows(3).enumerate() {
+            if let [(_, Op::ALoad(a)), (_, Op::ILoad(i2)), (_, load)] = window {
+                if *a == array && *i2 == index && is_array_load(load) {
+                    in_bounds.insert(header + 4 + i);
+                }
+            }
+        }
+    }
+    in_bounds
+}
+
+/// Whether the op stores to the given local variable, including the second
+/// slot of a long or double.
+fn writes_local(op: &Op, local: u8) -> bool {
+    match op {
+        Op::IStore(i) | Op::FStore(i) | Op::AStore(i) | Op::IInc(i, _) => *i == local,
+        Op::LStore(i) | Op::DStore(i) => *i == local || i.checked_add(1) == Some(local),
+        _ => false,
+    }
+}
+
+fn is_array_load(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::IALoad
+            | Op::LALoad
+            | Op::FALoad
+            | Op::DALoad
+            | Op::AALoad
+            | Op::BALoad
+            | Op::CALoad
+            | Op::SALoad
+    )
+}
+
+fn int_constant(op: &Op) -> Option<i32> {
+    Some(match op {
+        Op::IConstM1 => -1,
+        Op::IConst0 => 0,
+        Op::IConst1 => 1,
+        Op::IConst2 => 2,
+        Op::IConst3 => 3,
+        Op::IConst4 => 4,
+        Op::IConst5 => 5,
+        Op::BIPush(v) => *v as i32,
+        Op::SIPush(v) => *v as i16 as i32,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_fuses_sequences() {
+        let code = [
+            0x2A, 0xB4, 0x00, 0x02, // aload_0, getfield #2
+            0x1B, 0x1C, 0x60, // iload_1, iload_2, iadd
+            0x10, 0xF6, 0x3E, // bipush -10, istore_3
+            0xAC, // ireturn
+        ];
+        let code = Code::decode(&code, &[]).unwrap();
+        assert_eq!(
+            &[
+                Instruction::ALoad0GetField(2),
+                Instruction::ILoadILoadIAdd(1, 2),
+                Instruction::IConstIStore(-10, 3),
+                Instruction::Op(Op::IReturn),
+            ],
+            code.instructions()
+        );
+        assert_eq!(7, code.offset(2));
+        assert_eq!(Some(3), code.index_of(10));
+        assert_eq!(None, code.index_of(8));
+    }
+
+    #[test]
+    fn test_decode_does_not_fuse_branch_targets() {
+        let code = [
+            0x04, // iconst_1
+            0x3C, // istore_1, target of the goto
+            0xA7, 0xFF, 0xFF, // goto -1
+        ];
+        let code = Code::decode(&code, &[]).unwrap();
+        assert_eq!(
+            &[
+                Instruction::Op(Op::IConst1),
+                Instruction::Op(Op::IStore(1)),
+                Instruction::Op(Op::Goto(0xFFFF)),
+            ],
+            code.instructions()
+        );
+    }
+
+    #[test]
+    fn test_decode_does_not_fuse_entry_points() {
+        let code = [0x2A, 0xB4, 0x00, 0x02];
+        let code = Code::decode(&code, &[1]).unwrap();
+        assert_eq!(2, code.instructions().len());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let code = [
+            0x1B, 0x1C, 0x60, // iload_1, iload_2, iadd
+            0xAC, // ireturn
+        ];
+        let mut code = Code::decode(&code, &[]).unwrap();
+        assert!(code.set_breakpoint(3));
+        assert!(!code.set_breakpoint(3));
+        // inside the fused iload_1, iload_2, iadd
+        assert!(!code.set_breakpoint(1));
+        assert_eq!(Instruction::Op(Op::Breakpoint), code.instructions()[1]);
+        assert_eq!(Some(&Instruction::Op(Op::IReturn)), code.trapped(1));
+        assert_eq!(None, code.trapped(0));
+        assert_eq!(vec![3], code.breakpoints());
+
+        assert!(code.clear_breakpoint(3));
+        assert!(!code.clear_breakpoint(3));
+        assert_eq!(Instruction::Op(Op::IReturn), code.instructions()[1]);
+        assert!(code.breakpoints().is_empty());
+
+        // an entry point keeps the sequence apart
+        let mut code = Code::decode(&[0x1B, 0x1C, 0x60, 0xAC], &[1]).unwrap();
+        assert!(code.set_breakpoint(1));
+        assert_eq!(Some(&Instruction::Op(Op::ILoad(2))), code.trapped(1));
+    }
+
+    #[test]
+    fn test_basic_blocks() {
+        let code = [
+            0x1A, // 0: iload_0
+            0x99, 0x00, 0x06, // 1: ifeq +6
+            0x04, // 4: iconst_1
+            0x3C, // 5: istore_1
+            0xB1, // 6: return
+            0x03, // 7: iconst_0, target of the ifeq
+            0xAC, // 8: ireturn
+        ];
+        let code = Code::decode(&code, &[]).unwrap();
+        assert_eq!(&[0, 4, 7], code.blocks());
+        assert_eq!(Some(0), code.block_start(0));
+        assert_eq!(None, code.block_start(1));
+        // iconst_1 and istore_1 are fused, the block starts with the fused
+        // instruction
+        assert_eq!(Instruction::IConstIStore(1, 1), code.instructions()[2]);
+        assert_eq!(Some(1), code.block_start(2));
+        assert_eq!(Some(2), code.block_start(4));
+    }
+
+    /// The code of `for (int i = 0; i < a.length; i++) s += a[i];` with `a` in
+    /// local 0, `s` in local 1 and `i` in local 2.
+    const SUM: [u8; 24] = [
+        0x03, 0x3C, // 0: iconst_0, istore_1
+        0x03, 0x3D, // 2: iconst_0, istore_2
+        0x1C, 0x2A, 0xBE, // 4: iload_2, aload_0, arraylength
+        0xA2, 0x00, 0x0F, // 7: if_icmpge +15
+        0x1B, // 10: iload_1
+        0x2A, 0x1C, 0x2E, // 11: aload_0, iload_2, iaload
+        0x60, 0x3C, // 14: iadd, istore_1
+        0x84, 0x02, 0x01, // 16: iinc 2, 1
+        0xA7, 0xFF, 0xF1, // 19: goto -15
+        0x1B, 0xAC, // 22: iload_1, ireturn
+    ];
+
+    #[test]
+    fn test_unchecked_array_load() {
+        let code = Code::decode(&SUM, &[]).unwrap();
+        assert_eq!(
+            Instruction::UncheckedArrayLoad(0, 2, Op::IALoad),
+            code.instructions()[7]
+        );
+        assert_eq!(11, code.offset(7));
+        assert_eq!(Instruction::Op(Op::IAdd), code.instructions()[8]);
+    }
+
+    #[test]
+    fn test_checked_array_load() {
+        let checked = |code: &[u8], entry_points: &[usize]| {
+            let code = Code::decode(code, entry_points).unwrap();
+            !code
+                .instructions()
+                .iter()
+                .any(|i| matches!(i, Instruction::UncheckedArrayLoad(..)))
+        };
+        assert!(!checked(&SUM, &[]));
+
+        // i starts at -1
+        let mut code = SUM;
+        code[2] = 0x02;
+        assert!(checked(&code, &[]));
+
+        // i is decremented
+        let mut code = SUM;
+        code[18] = 0xFF;
+        assert!(checked(&code, &[]));
+
+        // i is incremented by 2, which can overflow
+        let mut code = SUM;
+        code[18] = 0x02;
+        assert!(checked(&code, &[]));
+
+        // i is overwritten in the body by istore_2 instead of istore_1
+        let mut code = SUM;
+        code[15] = 0x3D;
+        assert!(checked(&code, &[]));
+
+        // the array is reassigned in the body by astore_0 instead of istore_1
+        let mut code = SUM;
+        code[15] = 0x4B;
+        assert!(checked(&code, &[]));
+
+        // the loop is compared with <= instead of <
+        let mut code = SUM;
+        code[7] = 0xA3;
+        assert!(checked(&code, &[]));
+
+        // an exception handler in the body
+        assert!(checked(&SUM, &[14]));
+    }
+}
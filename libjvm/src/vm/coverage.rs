@@ -0,0 +1,296 @@
+use crate::vm::classloader::class::{Class, Method};
+use crate::vm::code::Code;
+use libjava::classfile::AttributeInfo;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Records which methods and which basic blocks of their code were executed.
+///
+/// Coverage is disabled by default. When it is enabled, the interpreter looks
+/// up the [`MethodCoverage`] of a method once when it enters the method, and
+/// then only increments counters: one per invocation, and one whenever it
+/// reaches the first instruction of a basic block (see [`Code::blocks`]).
+#[derive(Default)]
+pub struct Coverage {
+    enabled: AtomicBool,
+    methods: Mutex<HashMap<(String, String, String), Arc<MethodCoverage>>>,
+}
+
+/// The counters of a single method.
+pub struct MethodCoverage {
+    class: String,
+    name: String,
+    descriptor: String,
+    /// The path of the source file, relative to the source root, e.g.
+    /// `com/example/Foo.java`.
+    source_file: String,
+    invocations: AtomicU64,
+    blocks: Vec<Block>,
+}
+
+struct Block {
+    offset: usize,
+    /// The source lines of the instructions of the block. This is empty if the
+    /// class file has no line number table.
+    lines: Vec<u16>,
+    count: AtomicU64,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the counters of the given method, creating them on the first
+    /// call. `code` must be the decoded code of the method.
+    pub fn method(&self, class: &Class, method: &Method, code: &Code) -> Arc<MethodCoverage> {
+        let key = (
+            class.name().to_string(),
+            method.name().to_string(),
+            method.descriptor().to_string(),
+        );
+        self.methods
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| Arc::new(MethodCoverage::new(class, method, code)))
+            .clone()
+    }
+
+    /// Writes the recorded coverage in the LCOV tracefile format, with one
+    /// record per source file.
+    pub fn write_lcov(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let methods = self.methods.lock().unwrap();
+        let mut by_source: BTreeMap<&str, Vec<&MethodCoverage>> = BTreeMap::new();
+        for method in methods.values() {
+            by_source
+                .entry(&method.source_file)
+                .or_default()
+                .push(method);
+        }
+
+        writeln!(out, "TN:")?;
+        for (source_file, mut methods) in by_source {
+            methods.sort_by_key(|m| (m.first_line(), m.qualified_name()));
+            writeln!(out, "SF:{}", source_file)?;
+            for m in &methods {
+                writeln!(out, "FN:{},{}", m.first_line(), m.qualified_name())?;
+            }
+            for m in &methods {
+                writeln!(out, "FNDA:{},{}", m.invocations(), m.qualified_name())?;
+            }
+            writeln!(out, "FNF:{}", methods.len())?;
+            let hit = methods.iter().filter(|m| m.invocations() > 0).count();
+            writeln!(out, "FNH:{}", hit)?;
+
+            // a line is as often executed as the most executed block on it
+            let mut lines: BTreeMap<u16, u64> = BTreeMap::new();
+            for m in &methods {
+                for block in &m.blocks {
+                    for line in &block.lines {
+                        let count = lines.entry(*line).or_default();
+                        *count = (*count).max(block.count.load(Ordering::Relaxed));
+                    }
+                }
+            }
+            for (line, count) in &lines {
+                writeln!(out, "DA:{},{}", line, count)?;
+            }
+            writeln!(out, "LF:{}", lines.len())?;
+            writeln!(out, "LH:{}", lines.values().filter(|c| **c > 0).count())?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+}
+
+impl MethodCoverage {
+    fn new(class: &Class, method: &Method, code: &Code) -> Self {
+        let mut line_numbers: Vec<(usize, u16)> = class
+            .method_info(method)
+            .attributes()
+            .iter()
+            .filter_map(|a| match a {
+                AttributeInfo::Code { attributes, .. } => Some(attributes),
+                _ => None,
+            })
+            .flatten()
+            // a malformed table is left out like a missing one
+            .filter_map(|a| a.materialize(class.constant_pool()).ok())
+            .filter_map(|a| match a {
+                AttributeInfo::LineNumberTable {
+                    line_number_table, ..
+                } => Some(line_number_table),
+                _ => None,
+            })
+            .flatten()
+            .map(|e| (e.start_pc() as usize, e.line_number()))
+            .collect();
+        line_numbers.sort();
+        // the lines of a block are the line of its first instruction (the line
+        // of the last entry before it), and the lines of all entries within it
+        let lines_of = |start: usize, end: usize| {
+            let first = line_numbers.partition_point(|(pc, _)| *pc <= start);
+            let last = line_numbers.partition_point(|(pc, _)| *pc < end);
+            let mut lines: Vec<u16> = line_numbers[first.saturating_sub(1)..last.max(first)]
+                .iter()
+                .map(|(_, line)| *line)
+                .collect();
+            lines.dedup();
+            lines
+        };
+
+        let class_name = class.name().to_string();
+        let source_file = match class.source_file() {
+            Some(file) => match class_name.rfind('/') {
+                Some(i) => format!("{}/{}", &class_name[..i], file.as_str()),
+                None => file.to_string(),
+            },
+            // the outermost class is usually declared in a file of its name
+            None => format!("{}.java", class_name.split('$').next().unwrap()),
+        };
+
+        Self {
+            class: class_name,
+            name: method.name().to_string(),
+            descriptor: method.descriptor().to_string(),
+            source_file,
+            invocations: AtomicU64::new(0),
+            blocks: (0..code.blocks().len())
+                .map(|i| {
+                    let offset = code.blocks()[i];
+                    let end = code.blocks().get(i + 1).copied().unwrap_or(usize::MAX);
+                    Block {
+                        offset,
+                        lines: lines_of(offset, end),
+                        count: AtomicU64::new(0),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Records an invocation of the method.
+    pub fn enter(&self) {
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that the block with the given index (as returned by
+    /// [`Code::block_start`]) was entered.
+    pub fn record_block(&self, block: usize) {
+        self.blocks[block].count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn invocations(&self) -> u64 {
+        self.invocations.load(Ordering::Relaxed)
+    }
+
+    /// The offset of the first instruction of every basic block, with the
+    /// number of times the block was entered.
+    pub fn block_counts(&self) -> Vec<(usize, u64)> {
+        self.blocks
+            .iter()
+            .map(|b| (b.offset, b.count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// The name of the method as it appears in reports, e.g.
+    /// `com/example/Foo.bar(I)V`.
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}{}", self.class, self.name, self.descriptor)
+    }
+
+    fn first_line(&self) -> u16 {
+        self.blocks
+            .first()
+            .and_then(|b| b.lines.first().copied())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::descriptor::DescriptorTable;
+    use crate::vm::symbol::SymbolTable;
+    use libjava::classfile::ClassFile;
+
+    fn load_test_class() -> Class {
+        let bytes = std::fs::read("tests/resources/vm/classloader/Test1.class").unwrap();
+        let class_file = ClassFile::from_bytes(&bytes).unwrap();
+        Class::new(
+            class_file,
+            &SymbolTable::new(),
+            &DescriptorTable::new(),
+            None,
+            vec![],
+        )
+        .unwrap()
+    }
+
+    fn decode(class: &Class, method: &Method) -> Code {
+        let code = class
+            .method_info(method)
+            .attributes()
+            .iter()
+            .find_map(|a| match a {
+                AttributeInfo::Code { code, .. } => Some(code),
+                _ => None,
+            })
+            .unwrap();
+        Code::decode(code, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_lcov_report() {
+        let class = load_test_class();
+        let coverage = Coverage::new();
+        let init = class.find_method("<init>", "()V").unwrap();
+        let method = class.find_method("method", "()I").unwrap();
+        let method_code = decode(&class, method);
+        let counters = coverage.method(&class, method, &method_code);
+        coverage.method(&class, init, &decode(&class, init));
+
+        counters.enter();
+        counters.enter();
+        counters.record_block(method_code.block_start(0).unwrap());
+        // the same method always gets the same counters
+        assert_eq!(
+            2,
+            coverage.method(&class, method, &method_code).invocations()
+        );
+        assert_eq!(vec![(0, 1)], counters.block_counts());
+
+        let mut lcov = vec![];
+        coverage.write_lcov(&mut lcov).unwrap();
+        assert_eq!(
+            "TN:\n\
+             SF:Test1.java\n\
+             FN:1,Test1.<init>()V\n\
+             FN:3,Test1.method()I\n\
+             FNDA:0,Test1.<init>()V\n\
+             FNDA:2,Test1.method()I\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:1,0\n\
+             DA:3,1\n\
+             DA:4,1\n\
+             DA:5,1\n\
+             DA:6,1\n\
+             LF:5\n\
+             LH:4\n\
+             end_of_record\n",
+            String::from_utf8(lcov).unwrap()
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::PanicHookInfo;
+use std::sync::Once;
+
+/// A crash of a VM thread, i.e. a panic in the VM while it executed Java
+/// code, which is reported like the `hs_err` file of HotSpot, see
+/// [`VM::report_fatal_error`](crate::vm::VM::report_fatal_error).
+#[derive(Debug, Clone, Default)]
+pub struct Crash {
+    /// The panic message.
+    pub message: String,
+    /// The location in the VM's source code where it panicked.
+    pub location: Option<String>,
+    /// The name of the Java thread that crashed.
+    pub thread: String,
+    /// The Java frames of the thread, from the innermost to the outermost.
+    pub java_stack: Vec<String>,
+    /// The Rust stack of the thread when it panicked.
+    pub backtrace: Option<String>,
+}
+
+thread_local! {
+    /// Whether panics on this thread are reported as crashes of the VM.
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    /// The location and Rust stack of the last panic on this thread.
+    static CAPTURED: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+/// Runs the given function, and returns the crash if it panicked. The panic
+/// is not printed like other panics are: the crash is meant to be reported
+/// with [`VM::report_fatal_error`](crate::vm::VM::report_fatal_error)
+/// instead, after the caller added the Java thread and its stack.
+///
+/// This installs a panic hook on the first call, which only handles panics
+/// within this function, and otherwise calls the previous hook.
+pub fn catch_crash<R>(f: impl FnOnce() -> R) -> Result<R, Crash> {
+    install_hook();
+    let was_capturing = CAPTURING.with(|c| c.replace(true));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    CAPTURING.with(|c| c.set(was_capturing));
+    result.map_err(|payload| {
+        let (location, backtrace) = CAPTURED.with(|c| c.take()).unzip();
+        Crash {
+            message: panic_message(payload.as_ref()),
+            location,
+            backtrace,
+            ..Crash::default()
+        }
+    })
+}
+
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info: &PanicHookInfo<'_>| {
+            if !CAPTURING.with(|c| c.get()) {
+                return previous(info);
+            }
+            let location = info.location().map(|l| l.to_string()).unwrap_or_default();
+            let backtrace = Backtrace::force_capture().to_string();
+            CAPTURED.with(|c| *c.borrow_mut() = Some((location, backtrace)));
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_crash() {
+        let crash = catch_crash(|| -> i32 { panic!("broken invariant {}", 42) }).unwrap_err();
+        assert_eq!("broken invariant 42", crash.message);
+        assert!(crash.location.unwrap().contains("crash.rs"));
+        assert!(crash.backtrace.is_some());
+
+        assert_eq!(Ok(3), catch_crash(|| 3).map_err(|_| ()));
+    }
+}
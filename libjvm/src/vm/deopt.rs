@@ -0,0 +1,554 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::resolve::{select_method, ResolvedMethod};
+use crate::vm::error::VmError;
+use crate::vm::stack::Frame;
+use crate::vm::types::NativeValue;
+use libjava::classfile::flags::{ClassAccessFlags, MethodAccessFlags};
+use libjava::classfile::ResolvedConstantPool;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The type of a value of an interpreter frame, which determines how the raw
+/// bits of a register or stack slot of compiled code are read.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ValueKind {
+    Int,
+    Float,
+    Long,
+    Double,
+    Reference,
+}
+
+/// Where compiled code keeps a value of an interpreter frame at a
+/// deoptimization point.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    /// In the register with the given number.
+    Register(u16),
+    /// In the slot of the compiled frame with the given index.
+    StackSlot(u32),
+    /// Nowhere, because the compiler proved the value to be this constant.
+    Constant(NativeValue),
+    /// Nowhere, because the value is never used again. The local variable is
+    /// left unset in the interpreter frame.
+    Dead,
+}
+
+/// A value of an interpreter frame, and where compiled code keeps it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeValue {
+    pub kind: ValueKind,
+    pub location: Location,
+}
+
+/// The state of one interpreter frame at a deoptimization point: the method,
+/// the bytecode index to resume at, and the locations of its local variables
+/// and operand stack, bottom first.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub method: ResolvedMethod,
+    pub bci: usize,
+    pub locals: Vec<ScopeValue>,
+    pub stack: Vec<ScopeValue>,
+}
+
+/// An instruction of compiled code where the code can be abandoned for the
+/// interpreter, e.g. a call, a guard of a speculation or a safepoint poll.
+///
+/// Compiled code can inline methods, so one point maps back to several
+/// interpreter frames: the scopes of the compiled method and of every method
+/// that is inlined at the point, with the compiled method first.
+#[derive(Debug, Clone)]
+pub struct DeoptPoint {
+    /// The offset of the instruction in the compiled code.
+    pub pc: usize,
+    pub scopes: Vec<Scope>,
+}
+
+/// The registers and stack slots of a compiled frame that is deoptimized.
+pub trait CompiledState {
+    fn register(&self, register: u16) -> u64;
+
+    fn stack_slot(&self, slot: u32) -> u64;
+}
+
+/// An interpreter frame that was materialized from a compiled frame, see
+/// [`DeoptPoint::materialize`]. Locals that are [`None`] were dead.
+#[derive(Debug, Clone)]
+pub struct VirtualFrame {
+    pub method: ResolvedMethod,
+    pub bci: usize,
+    pub locals: Vec<Option<NativeValue>>,
+    pub stack: Vec<NativeValue>,
+}
+
+/// The metadata that a compiled method needs to be deoptimized: its
+/// deoptimization points, by the offset of their instruction.
+#[derive(Debug, Clone, Default)]
+pub struct DeoptMetadata {
+    /// The points, sorted by their offset.
+    points: Vec<DeoptPoint>,
+}
+
+/// A speculation of compiled code about the loaded classes, which is checked
+/// whenever a class is loaded, see [`DeoptTable::class_loaded`].
+#[derive(Clone)]
+pub enum Dependency {
+    /// The class has no subclasses or implementations, e.g. so that a type
+    /// check against it is an exact class check.
+    LeafType(Rc<Class>),
+    /// The method is the only implementation of its name and descriptor in
+    /// its class and all of its subclasses, e.g. so that a virtual call of it
+    /// is devirtualized by class hierarchy analysis (CHA).
+    UniqueConcreteMethod(ResolvedMethod),
+    /// Every loaded class that can be the receiver of a virtual or interface
+    /// call of `resolved` on an object of `static_type` selects `target`, so
+    /// that the call is a direct call of it, see
+    /// [`devirtualize`](crate::vm::devirt::devirtualize).
+    MonomorphicCall {
+        static_type: Rc<Class>,
+        resolved: ResolvedMethod,
+        target: ResolvedMethod,
+    },
+}
+
+/// Why compiled code was abandoned for the interpreter.
+#[derive(Debug, Clone)]
+pub enum DeoptReason {
+    /// A class was loaded that breaks the given dependency.
+    Dependency(Dependency),
+    /// An inline cache saw a receiver class that it was not compiled for.
+    InlineCacheMiss,
+    /// A branch or a type check took a path that the compiler assumed to be
+    /// never taken.
+    UncommonTrap,
+}
+
+/// A compiled method, as registered with [`DeoptTable::register`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CompiledMethodId(usize);
+
+/// The compiled methods with their deoptimization metadata and dependencies,
+/// and whether they are still valid.
+///
+/// A compiled method becomes invalid when it is invalidated explicitly, or
+/// when a class is loaded that breaks one of its dependencies. Invalid code
+/// must not be entered again, and frames of it must be deoptimized before
+/// they continue, see [`DeoptTable::deoptimize`].
+#[derive(Default)]
+pub struct DeoptTable {
+    methods: Vec<CompiledMethod>,
+}
+
+struct CompiledMethod {
+    method: ResolvedMethod,
+    metadata: DeoptMetadata,
+    dependencies: Vec<Dependency>,
+    /// Why the method was invalidated, or [`None`] if it is valid.
+    invalidated: Option<DeoptReason>,
+}
+
+impl Location {
+    fn read(&self, kind: ValueKind, state: &dyn CompiledState) -> Option<NativeValue> {
+        let bits = match self {
+            Location::Register(register) => state.register(*register),
+            Location::StackSlot(slot) => state.stack_slot(*slot),
+            Location::Constant(value) => return Some(value.clone()),
+            Location::Dead => return None,
+        };
+        Some(match kind {
+            ValueKind::Int => NativeValue::Integer(bits as u32 as i32),
+            ValueKind::Float => NativeValue::Float(f32::from_bits(bits as u32)),
+            ValueKind::Long => NativeValue::Long(bits as i64),
+            ValueKind::Double => NativeValue::Double(f64::from_bits(bits)),
+            ValueKind::Reference => NativeValue::Reference(bits as usize),
+        })
+    }
+}
+
+impl DeoptPoint {
+    /// Reads the values of all scopes of this point from the given state of
+    /// the compiled frame, and returns the interpreter frames that continue
+    /// in its place, with the frame of the compiled method first. Fails if a
+    /// value on an operand stack is dead, since operands are always used.
+    pub fn materialize(&self, state: &dyn CompiledState) -> Result<Vec<VirtualFrame>, VmError> {
+        self.scopes
+            .iter()
+            .map(|scope| {
+                let locals = scope
+                    .locals
+                    .iter()
+                    .map(|value| value.location.read(value.kind, state))
+                    .collect();
+                let stack = scope
+                    .stack
+                    .iter()
+                    .map(|value| {
+                        value.location.read(value.kind, state).ok_or_else(|| {
+                            VmError::Internal(format!(
+                                "dead operand at bci {} of {}.{}",
+                                scope.bci,
+                                scope.method.class().name().as_str().replace('/', "."),
+                                scope.method.method().name()
+                            ))
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(VirtualFrame {
+                    method: scope.method.clone(),
+                    bci: scope.bci,
+                    locals,
+                    stack,
+                })
+            })
+            .collect()
+    }
+}
+
+impl VirtualFrame {
+    /// Creates the interpreter frame with the values of this frame, which
+    /// resumes at [`VirtualFrame::bci`].
+    pub fn into_frame(self, constant_pool: Arc<ResolvedConstantPool>) -> Frame {
+        let mut frame = Frame::allocate(self.locals.len(), self.stack.len(), constant_pool);
+        for (index, value) in self.locals.into_iter().enumerate() {
+            if let Some(value) = value {
+                frame.locals.set(index, value);
+            }
+        }
+        for value in self.stack {
+            frame.operand_stack.push(value);
+        }
+        frame.method = Some(self.method);
+        frame
+    }
+}
+
+impl DeoptMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a deoptimization point, replacing the one at the same offset.
+    pub fn add_point(&mut self, point: DeoptPoint) {
+        match self.points.binary_search_by_key(&point.pc, |p| p.pc) {
+            Ok(i) => self.points[i] = point,
+            Err(i) => self.points.insert(i, point),
+        }
+    }
+
+    /// Returns the deoptimization point at the given offset of the compiled
+    /// code, if there is one.
+    pub fn point_at(&self, pc: usize) -> Option<&DeoptPoint> {
+        let i = self.points.binary_search_by_key(&pc, |p| p.pc).ok()?;
+        Some(&self.points[i])
+    }
+
+    pub fn points(&self) -> &[DeoptPoint] {
+        &self.points
+    }
+}
+
+impl Dependency {
+    /// Whether loading the given class breaks this dependency.
+    pub fn is_broken_by(&self, loaded: &Rc<Class>) -> bool {
+        match self {
+            Dependency::LeafType(class) => {
+                !Rc::ptr_eq(loaded, class) && loaded.is_subclass_of(class)
+            }
+            Dependency::UniqueConcreteMethod(method) => {
+                !Rc::ptr_eq(loaded, method.class())
+                    && loaded.is_subclass_of(method.class())
+                    && loaded
+                        .find_method(method.method().name(), method.method().descriptor())
+                        .is_some_and(|m| {
+                            !m.access_flags().intersects(
+                                MethodAccessFlags::ABSTRACT
+                                    | MethodAccessFlags::STATIC
+                                    | MethodAccessFlags::PRIVATE,
+                            )
+                        })
+            }
+            Dependency::MonomorphicCall {
+                static_type,
+                resolved,
+                target,
+            } => {
+                // abstract classes can't be receivers, but their concrete
+                // subclasses are checked once they are loaded
+                !loaded
+                    .access_flags()
+                    .intersects(ClassAccessFlags::ABSTRACT | ClassAccessFlags::INTERFACE)
+                    && loaded.is_subclass_of(static_type)
+                    && select_method(loaded, resolved).map_or(true, |m| m != *target)
+            }
+        }
+    }
+}
+
+impl Debug for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependency::LeafType(class) => write!(f, "LeafType({})", class.name()),
+            Dependency::UniqueConcreteMethod(method) => {
+                write!(f, "UniqueConcreteMethod({:?})", method)
+            }
+            Dependency::MonomorphicCall {
+                static_type,
+                resolved,
+                target,
+            } => write!(
+                f,
+                "MonomorphicCall({}, {:?}, {:?})",
+                static_type.name(),
+                resolved,
+                target
+            ),
+        }
+    }
+}
+
+impl DeoptTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a compiled method that is valid as long as none of the given
+    /// dependencies is broken.
+    pub fn register(
+        &mut self,
+        method: ResolvedMethod,
+        metadata: DeoptMetadata,
+        dependencies: Vec<Dependency>,
+    ) -> CompiledMethodId {
+        self.methods.push(CompiledMethod {
+            method,
+            metadata,
+            dependencies,
+            invalidated: None,
+        });
+        CompiledMethodId(self.methods.len() - 1)
+    }
+
+    /// The method that the given compiled method is the code of.
+    pub fn method(&self, id: CompiledMethodId) -> &ResolvedMethod {
+        &self.methods[id.0].method
+    }
+
+    pub fn metadata(&self, id: CompiledMethodId) -> &DeoptMetadata {
+        &self.methods[id.0].metadata
+    }
+
+    pub fn is_valid(&self, id: CompiledMethodId) -> bool {
+        self.methods[id.0].invalidated.is_none()
+    }
+
+    /// Why the given compiled method was invalidated, or [`None`] if it is
+    /// still valid.
+    pub fn invalidation_reason(&self, id: CompiledMethodId) -> Option<&DeoptReason> {
+        self.methods[id.0].invalidated.as_ref()
+    }
+
+    /// Invalidates the given compiled method, e.g. because a guard of it
+    /// failed. A method that is invalid already keeps its first reason.
+    pub fn invalidate(&mut self, id: CompiledMethodId, reason: DeoptReason) {
+        self.methods[id.0].invalidated.get_or_insert(reason);
+    }
+
+    /// Invalidates every valid compiled method with a dependency that is broken
+    /// by the given class, which was just loaded, and returns them. This is
+    /// meant to be called by a [`LoadListener`](crate::vm::classloader::bootstrap::LoadListener).
+    pub fn class_loaded(&mut self, class: &Rc<Class>) -> Vec<CompiledMethodId> {
+        let mut invalidated = vec![];
+        for (i, method) in self.methods.iter_mut().enumerate() {
+            if method.invalidated.is_some() {
+                continue;
+            }
+            if let Some(dependency) = method.dependencies.iter().find(|d| d.is_broken_by(class)) {
+                method.invalidated = Some(DeoptReason::Dependency(dependency.clone()));
+                invalidated.push(CompiledMethodId(i));
+            }
+        }
+        invalidated
+    }
+
+    /// Deoptimizes a frame of the given compiled method that stopped at the
+    /// given offset of its code, and returns the interpreter frames that
+    /// continue in its place, see [`DeoptPoint::materialize`]. Fails if there
+    /// is no deoptimization point at the offset.
+    pub fn deoptimize(
+        &self,
+        id: CompiledMethodId,
+        pc: usize,
+        state: &dyn CompiledState,
+    ) -> Result<Vec<VirtualFrame>, VmError> {
+        let compiled = &self.methods[id.0];
+        let point = compiled.metadata.point_at(pc).ok_or_else(|| {
+            VmError::Internal(format!(
+                "no deoptimization point at pc {} of {}.{}",
+                pc,
+                compiled.method.class().name().as_str().replace('/', "."),
+                compiled.method.method().name()
+            ))
+        })?;
+        point.materialize(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_loader;
+    use crate::vm::classloader::ClassLoader;
+    use std::cell::RefCell;
+
+    fn method(class: &Rc<Class>, name: &str) -> ResolvedMethod {
+        ResolvedMethod::new(
+            class,
+            class.find_method(name, "()Ljava/lang/String;").unwrap(),
+        )
+    }
+
+    /// Registers, in r0 and r1, and slots, in s0 and s1.
+    struct State([u64; 2], [u64; 2]);
+
+    impl CompiledState for State {
+        fn register(&self, register: u16) -> u64 {
+            self.0[register as usize]
+        }
+
+        fn stack_slot(&self, slot: u32) -> u64 {
+            self.1[slot as usize]
+        }
+    }
+
+    #[test]
+    fn test_deoptimize() {
+        let mut class_loader = class_loader(&["deopt"]);
+        let animal = class_loader.find_or_load_class("Animal").unwrap();
+        let value = |kind, location| ScopeValue { kind, location };
+
+        let mut metadata = DeoptMetadata::new();
+        // name() is inlined into sound() at bci 1 of sound()
+        metadata.add_point(DeoptPoint {
+            pc: 24,
+            scopes: vec![
+                Scope {
+                    method: method(&animal, "sound"),
+                    bci: 1,
+                    locals: vec![
+                        value(ValueKind::Reference, Location::Register(0)),
+                        value(ValueKind::Double, Location::StackSlot(1)),
+                        value(ValueKind::Int, Location::Dead),
+                    ],
+                    stack: vec![value(
+                        ValueKind::Int,
+                        Location::Constant(NativeValue::Integer(7)),
+                    )],
+                },
+                Scope {
+                    method: method(&animal, "name"),
+                    bci: 0,
+                    locals: vec![value(ValueKind::Reference, Location::Register(0))],
+                    stack: vec![value(ValueKind::Int, Location::Register(1))],
+                },
+            ],
+        });
+        metadata.add_point(DeoptPoint {
+            pc: 8,
+            scopes: vec![],
+        });
+        assert_eq!(
+            vec![8, 24],
+            metadata.points().iter().map(|p| p.pc).collect::<Vec<_>>()
+        );
+
+        let mut table = DeoptTable::new();
+        let id = table.register(method(&animal, "sound"), metadata, vec![]);
+        let state = State([42, (-3i32) as u32 as u64], [0, 2.5f64.to_bits()]);
+        let frames = table.deoptimize(id, 24, &state).unwrap();
+        assert_eq!(2, frames.len());
+        assert_eq!(1, frames[0].bci);
+        assert_eq!(
+            vec![
+                Some(NativeValue::Reference(42)),
+                Some(NativeValue::Double(2.5)),
+                None
+            ],
+            frames[0].locals
+        );
+        assert_eq!(vec![NativeValue::Integer(7)], frames[0].stack);
+        assert_eq!("name", frames[1].method.method().name().as_str());
+        assert_eq!(vec![NativeValue::Integer(-3)], frames[1].stack);
+
+        let mut frame = frames[0].clone().into_frame(Arc::default());
+        assert_eq!(42, frame.locals.get_reference(0));
+        assert_eq!(2.5, frame.locals.get_double(1));
+        assert_eq!(7, frame.operand_stack.pop_int());
+        assert!(frame.operand_stack.is_empty());
+        assert_eq!("sound", frame.method.unwrap().method().name().as_str());
+
+        assert_eq!(
+            Err(VmError::Internal(
+                "no deoptimization point at pc 12 of Animal.sound".to_string()
+            )),
+            table.deoptimize(id, 12, &state).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_class_loaded() {
+        let mut class_loader = class_loader(&["deopt"]);
+        let animal = class_loader.find_or_load_class("Animal").unwrap();
+        let table = Rc::new(RefCell::new(DeoptTable::new()));
+        let invalidated = Rc::new(RefCell::new(vec![]));
+        let (listened_table, listened) = (table.clone(), invalidated.clone());
+        class_loader.add_load_listener(Box::new(move |class| {
+            let ids = listened_table.borrow_mut().class_loaded(class);
+            listened.borrow_mut().extend(ids);
+        }));
+
+        // calls of sound() and name() are devirtualized
+        let sound = table.borrow_mut().register(
+            method(&animal, "sound"),
+            DeoptMetadata::new(),
+            vec![Dependency::UniqueConcreteMethod(method(&animal, "sound"))],
+        );
+        let name = table.borrow_mut().register(
+            method(&animal, "name"),
+            DeoptMetadata::new(),
+            vec![Dependency::UniqueConcreteMethod(method(&animal, "name"))],
+        );
+
+        // Cat overrides name(), but not sound()
+        class_loader.find_or_load_class("Cat").unwrap();
+        assert_eq!(vec![name], *invalidated.borrow());
+        assert!(table.borrow().is_valid(sound));
+        assert!(!table.borrow().is_valid(name));
+
+        let dog = class_loader.find_or_load_class("Dog").unwrap();
+        assert_eq!(vec![name, sound], *invalidated.borrow());
+        assert!(matches!(
+            table.borrow().invalidation_reason(sound),
+            Some(DeoptReason::Dependency(Dependency::UniqueConcreteMethod(m)))
+                if m.method().name().as_str() == "sound"
+        ));
+
+        // a type check against Dog is exact until a subclass is loaded
+        let exact = table.borrow_mut().register(
+            method(&dog, "sound"),
+            DeoptMetadata::new(),
+            vec![Dependency::LeafType(dog.clone())],
+        );
+        table
+            .borrow_mut()
+            .invalidate(sound, DeoptReason::InlineCacheMiss);
+        class_loader.find_or_load_class("Puppy").unwrap();
+        assert_eq!(vec![name, sound, exact], *invalidated.borrow());
+        // the first reason is kept
+        assert!(matches!(
+            table.borrow().invalidation_reason(sound),
+            Some(DeoptReason::Dependency(_))
+        ));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::vm::classloader::layout::REFERENCE_SIZE;
+use crate::vm::symbol::{Symbol, SymbolTable};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of dimensions of an array type, as specified by
+/// [`$4.3.2`].
+///
+/// [`$4.3.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+pub const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+/// The type of a field, parameter or return value, as described by a field
+/// descriptor ([`$4.3.2`]).
+///
+/// [`$4.3.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.2
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// An instance of the class with the given binary name, e.g.
+    /// `java/lang/Object`.
+    Object(Symbol),
+    /// An array with the given component type.
+    Array(Box<FieldType>),
+}
+
+/// The parameter and return types of a method, as described by a method
+/// descriptor ([`$4.3.3`]).
+///
+/// [`$4.3.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.3.3
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MethodDescriptor {
+    parameters: Vec<FieldType>,
+    /// The return type, or [`None`] for `void`.
+    return_type: Option<FieldType>,
+}
+
+impl FieldType {
+    /// Parses a complete field descriptor, interning class names in the given
+    /// symbol table. Returns [`None`] if the descriptor is invalid.
+    pub fn parse(descriptor: &str, symbols: &SymbolTable) -> Option<Self> {
+        match Self::parse_prefix(descriptor, symbols)? {
+            (field_type, "") => Some(field_type),
+            _ => None,
+        }
+    }
+
+    /// Parses the field descriptor at the start of `s`, returning it and the
+    /// rest of `s`.
+    fn parse_prefix<'a>(s: &'a str, symbols: &SymbolTable) -> Option<(Self, &'a str)> {
+        let dimensions = s.bytes().take_while(|b| *b == b'[').count();
+        if dimensions > MAX_ARRAY_DIMENSIONS {
+            return None;
+        }
+        let s = &s[dimensions..];
+        let (mut field_type, rest) = match s.as_bytes().first()? {
+            b'B' => (FieldType::Byte, &s[1..]),
+            b'C' => (FieldType::Char, &s[1..]),
+            b'D' => (FieldType::Double, &s[1..]),
+            b'F' => (FieldType::Float, &s[1..]),
+            b'I' => (FieldType::Int, &s[1..]),
+            b'J' => (FieldType::Long, &s[1..]),
+            b'S' => (FieldType::Short, &s[1..]),
+            b'Z' => (FieldType::Boolean, &s[1..]),
+            b'L' => {
+                let end = s.find(';')?;
+                let name = &s[1..end];
+                // an unqualified name must not contain any of . ; [ /, but
+                // the binary name of a class uses / as separator ($4.2.1)
+                if name.is_empty()
+                    || name.contains(['.', '[', ';'])
+                    || name.split('/').any(str::is_empty)
+                {
+                    return None;
+                }
+                (FieldType::Object(symbols.intern(name)), &s[end + 1..])
+            }
+            _ => return None,
+        };
+        for _ in 0..dimensions {
+            field_type = FieldType::Array(Box::new(field_type));
+        }
+        Some((field_type, rest))
+    }
+
+    /// Whether values of this type are references, i.e. objects or arrays.
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    /// The size of a field of this type in bytes, which is also its alignment.
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::Byte | FieldType::Boolean => 1,
+            FieldType::Char | FieldType::Short => 2,
+            FieldType::Int | FieldType::Float => 4,
+            FieldType::Long | FieldType::Double => 8,
+            FieldType::Object(_) | FieldType::Array(_) => REFERENCE_SIZE,
+        }
+    }
+
+    /// The number of local variable or operand stack slots that a value of
+    /// this type occupies, which is 2 for `long` and `double` and 1 otherwise.
+    pub fn slots(&self) -> usize {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Formats the type as a field descriptor, e.g. `[Ljava/lang/String;`.
+impl Display for FieldType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(name) => write!(f, "L{};", name),
+            FieldType::Array(component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+impl MethodDescriptor {
+    /// Parses a method descriptor, interning class names in the given symbol
+    /// table. Returns [`None`] if the descriptor is invalid.
+    pub fn parse(descriptor: &str, symbols: &SymbolTable) -> Option<Self> {
+        let mut rest = descriptor.strip_prefix('(')?;
+        let mut parameters = vec![];
+        while !rest.starts_with(')') {
+            let (parameter, r) = FieldType::parse_prefix(rest, symbols)?;
+            parameters.push(parameter);
+            rest = r;
+        }
+        let return_type = match &rest[1..] {
+            "V" => None,
+            r => Some(FieldType::parse(r, symbols)?),
+        };
+        let descriptor = Self {
+            parameters,
+            return_type,
+        };
+        // the parameters must fit into the 255 local variable slots ($4.3.3),
+        // including `this`, which is checked when the method is linked
+        if descriptor.parameter_slots() > 255 {
+            return None;
+        }
+        Some(descriptor)
+    }
+
+    pub fn parameters(&self) -> &[FieldType] {
+        &self.parameters
+    }
+
+    /// The return type, or [`None`] for `void` methods.
+    pub fn return_type(&self) -> Option<&FieldType> {
+        self.return_type.as_ref()
+    }
+
+    /// The number of local variable slots that the parameters occupy, not
+    /// including `this`.
+    pub fn parameter_slots(&self) -> usize {
+        self.parameters.iter().map(FieldType::slots).sum()
+    }
+}
+
+/// Formats the descriptor as it appears in a class file, e.g. `(IJ)V`.
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        match &self.return_type {
+            Some(return_type) => write!(f, "){}", return_type),
+            None => write!(f, ")V"),
+        }
+    }
+}
+
+/// The VM-wide table of parsed descriptors.
+///
+/// Every descriptor is parsed only once, and all classes that use it share the
+/// same [`FieldType`] or [`MethodDescriptor`]. Descriptors obtained from the
+/// same table are equal if and only if they are the same allocation, so they
+/// can be compared with [`Arc::ptr_eq`].
+#[derive(Default)]
+pub struct DescriptorTable {
+    field_types: Mutex<HashMap<Symbol, Arc<FieldType>>>,
+    method_descriptors: Mutex<HashMap<Symbol, Arc<MethodDescriptor>>>,
+}
+
+impl DescriptorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the parsed field descriptor, parsing it on the first call.
+    /// Returns [`None`] if the descriptor is invalid. `symbols` must be the
+    /// table that `descriptor` was interned in.
+    pub fn field_type(&self, descriptor: &Symbol, symbols: &SymbolTable) -> Option<Arc<FieldType>> {
+        let mut field_types = self.field_types.lock().unwrap();
+        if let Some(existing) = field_types.get(descriptor) {
+            return Some(existing.clone());
+        }
+        let field_type = Arc::new(FieldType::parse(descriptor, symbols)?);
+        field_types.insert(descriptor.clone(), field_type.clone());
+        Some(field_type)
+    }
+
+    /// Returns the parsed method descriptor, see [`DescriptorTable::field_type`].
+    pub fn method_descriptor(
+        &self,
+        descriptor: &Symbol,
+        symbols: &SymbolTable,
+    ) -> Option<Arc<MethodDescriptor>> {
+        let mut method_descriptors = self.method_descriptors.lock().unwrap();
+        if let Some(existing) = method_descriptors.get(descriptor) {
+            return Some(existing.clone());
+        }
+        let method_descriptor = Arc::new(MethodDescriptor::parse(descriptor, symbols)?);
+        method_descriptors.insert(descriptor.clone(), method_descriptor.clone());
+        Some(method_descriptor)
+    }
+
+    /// The number of distinct descriptors in this table.
+    pub fn len(&self) -> usize {
+        self.field_types.lock().unwrap().len() + self.method_descriptors.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field_type() {
+        let symbols = SymbolTable::new();
+        assert_eq!(Some(FieldType::Int), FieldType::parse("I", &symbols));
+        assert_eq!(
+            Some(FieldType::Array(Box::new(FieldType::Array(Box::new(
+                FieldType::Object(symbols.intern("java/lang/String"))
+            ))))),
+            FieldType::parse("[[Ljava/lang/String;", &symbols)
+        );
+        for invalid in [
+            "",
+            "V",
+            "II",
+            "L;",
+            "Ljava/lang/String",
+            "Ljava//String;",
+            "[",
+            "La.b;",
+        ] {
+            assert_eq!(None, FieldType::parse(invalid, &symbols), "{}", invalid);
+        }
+        assert!(FieldType::parse(&format!("{}I", "[".repeat(255)), &symbols).is_some());
+        assert!(FieldType::parse(&format!("{}I", "[".repeat(256)), &symbols).is_none());
+    }
+
+    #[test]
+    fn test_parse_method_descriptor() {
+        let symbols = SymbolTable::new();
+        let descriptor = MethodDescriptor::parse("(IJ[DLjava/lang/Object;)V", &symbols).unwrap();
+        assert_eq!(4, descriptor.parameters().len());
+        assert_eq!(5, descriptor.parameter_slots());
+        assert_eq!(None, descriptor.return_type());
+        assert_eq!("(IJ[DLjava/lang/Object;)V", descriptor.to_string());
+
+        let descriptor = MethodDescriptor::parse("()[I", &symbols).unwrap();
+        assert!(descriptor.return_type().unwrap().is_reference());
+
+        for invalid in ["", "()", "(V)V", "I", "(I", "()VV", "()II"] {
+            assert_eq!(
+                None,
+                MethodDescriptor::parse(invalid, &symbols),
+                "{}",
+                invalid
+            );
+        }
+        let too_many = format!("({})V", "J".repeat(128));
+        assert_eq!(None, MethodDescriptor::parse(&too_many, &symbols));
+    }
+
+    #[test]
+    fn test_descriptors_shared() {
+        let symbols = SymbolTable::new();
+        let table = DescriptorTable::new();
+        let descriptor = symbols.intern("(Ljava/lang/String;)I");
+        let a = table.method_descriptor(&descriptor, &symbols).unwrap();
+        let b = table.method_descriptor(&descriptor, &symbols).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let field = symbols.intern("Ljava/lang/String;");
+        let field_type = table.field_type(&field, &symbols).unwrap();
+        assert!(Arc::ptr_eq(
+            &field_type,
+            &table.field_type(&field, &symbols).unwrap()
+        ));
+        // class names in descriptors are interned like all other names
+        assert_eq!(
+            FieldType::Object(symbols.intern("java/lang/String")),
+            *field_type
+        );
+        assert_eq!(2, table.len());
+
+        assert!(table.field_type(&symbols.intern("V"), &symbols).is_none());
+        assert_eq!(2, table.len());
+    }
+}
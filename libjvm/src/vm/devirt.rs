@@ -0,0 +1,315 @@
+use crate::vm::area::ClassHierarchy;
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::resolve::{select_method, ResolvedMethod};
+use crate::vm::classloader::ClassLoader;
+use crate::vm::deopt::Dependency;
+use crate::vm::symbol::Symbol;
+use crate::vm::VM;
+use libjava::classfile::flags::{ClassAccessFlags, MethodAccessFlags};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// An `invokevirtual` or `invokeinterface` instruction: the method that
+/// contains it and its offset in the code of the method.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CallSite {
+    class: Symbol,
+    name: Symbol,
+    descriptor: Symbol,
+    pc: usize,
+}
+
+/// The method that a call site always invokes, as found by [`devirtualize`].
+#[derive(Debug, Clone)]
+pub struct Devirtualized {
+    target: ResolvedMethod,
+    /// The assumption that the call is monomorphic, or [`None`] if no class
+    /// that is loaded later can change the target.
+    dependency: Option<Dependency>,
+}
+
+/// The devirtualized call sites, so that the interpreter can invoke the target
+/// of a monomorphic call directly instead of selecting it for every receiver.
+///
+/// A call site stops being devirtualized when a class is loaded that selects
+/// another method for the call, see [`DevirtTable::class_loaded`].
+#[derive(Default)]
+pub struct DevirtTable {
+    sites: HashMap<CallSite, Devirtualized>,
+}
+
+impl CallSite {
+    pub fn new(caller: &ResolvedMethod, pc: usize) -> Self {
+        let method = caller.method();
+        Self {
+            class: caller.class().name().clone(),
+            name: method.name().clone(),
+            descriptor: method.descriptor().clone(),
+            pc,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+}
+
+impl Devirtualized {
+    /// The method that the call invokes, whatever the receiver is.
+    pub fn target(&self) -> &ResolvedMethod {
+        &self.target
+    }
+
+    /// What the devirtualization assumes about the loaded classes. Compiled
+    /// code that calls the target directly must be registered with this
+    /// dependency, see [`DeoptTable::register`](crate::vm::deopt::DeoptTable::register).
+    pub fn dependency(&self) -> Option<&Dependency> {
+        self.dependency.as_ref()
+    }
+}
+
+/// Finds the method that a virtual or interface call of `resolved` on an
+/// object of `static_type` invokes, if it is the same for every possible
+/// receiver, by class hierarchy analysis (CHA) of the loaded classes.
+///
+/// Private and final methods, and methods of final classes, can't be
+/// overridden, so their calls are devirtualized without a dependency. For
+/// other calls, the method is selected for `static_type` and each of its
+/// loaded concrete subtypes. Returns [`None`] if they select different methods,
+/// if one of them selects none, or if there is no concrete subtype yet.
+pub fn devirtualize(
+    hierarchy: &ClassHierarchy,
+    loader: &impl ClassLoader,
+    static_type: &Rc<Class>,
+    resolved: &ResolvedMethod,
+) -> Option<Devirtualized> {
+    let flags = resolved.method().access_flags();
+    let is_interface_method = resolved.class().is_interface();
+    if flags.contains(MethodAccessFlags::PRIVATE)
+        || (flags.contains(MethodAccessFlags::FINAL) && !is_interface_method)
+    {
+        return Some(Devirtualized {
+            target: resolved.clone(),
+            dependency: None,
+        });
+    }
+
+    let subtypes = hierarchy.subtypes(static_type.name());
+    let receivers = std::iter::once(static_type.clone())
+        .chain(
+            subtypes
+                .iter()
+                .filter_map(|name| loader.find_class(name.as_str())),
+        )
+        .filter(|class| {
+            !class
+                .access_flags()
+                .intersects(ClassAccessFlags::ABSTRACT | ClassAccessFlags::INTERFACE)
+        });
+    let mut target: Option<ResolvedMethod> = None;
+    for receiver in receivers {
+        let selected = select_method(&receiver, resolved).ok()?;
+        match &target {
+            Some(target) if *target != selected => return None,
+            Some(_) => {}
+            None => target = Some(selected),
+        }
+    }
+
+    let is_final = static_type.access_flags().contains(ClassAccessFlags::FINAL);
+    target.map(|target| Devirtualized {
+        dependency: (!is_final).then(|| Dependency::MonomorphicCall {
+            static_type: static_type.clone(),
+            resolved: resolved.clone(),
+            target: target.clone(),
+        }),
+        target,
+    })
+}
+
+impl DevirtTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The method that the call site invokes directly, or [`None`] if the call
+    /// site is not devirtualized and the method has to be selected for the
+    /// receiver.
+    pub fn target(&self, site: &CallSite) -> Option<&ResolvedMethod> {
+        self.sites.get(site).map(Devirtualized::target)
+    }
+
+    /// Devirtualizes the call site, replacing a previous target.
+    pub fn insert(&mut self, site: CallSite, devirtualized: Devirtualized) {
+        self.sites.insert(site, devirtualized);
+    }
+
+    /// Stops devirtualizing every call site whose dependency is broken by the
+    /// given class, which was just loaded, and returns them. This is meant to
+    /// be called by a [`LoadListener`](crate::vm::classloader::bootstrap::LoadListener),
+    /// like [`DeoptTable::class_loaded`](crate::vm::deopt::DeoptTable::class_loaded).
+    pub fn class_loaded(&mut self, class: &Rc<Class>) -> Vec<CallSite> {
+        let broken: Vec<CallSite> = self
+            .sites
+            .iter()
+            .filter(|(_, d)| d.dependency.as_ref().is_some_and(|d| d.is_broken_by(class)))
+            .map(|(site, _)| site.clone())
+            .collect();
+        for site in &broken {
+            self.sites.remove(site);
+        }
+        broken
+    }
+}
+
+impl VM {
+    /// Devirtualizes a call with the class hierarchy of the classes that this
+    /// VM loaded, see [`devirtualize`].
+    pub fn devirtualize(
+        &self,
+        static_type: &Rc<Class>,
+        resolved: &ResolvedMethod,
+    ) -> Option<Devirtualized> {
+        let method_area = self.method_area.read().unwrap();
+        devirtualize(
+            method_area.hierarchy(),
+            &self.bootstrap_class_loader,
+            static_type,
+            resolved,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::resolve::{resolve_class_method, resolve_interface_method};
+    use crate::vm::classloader::test_util::class_path;
+    use libvfs::FileSystem;
+    use std::cell::RefCell;
+
+    fn vm() -> VM {
+        VM::new(FileSystem::new_os_fs(), class_path(&["devirt"]))
+    }
+
+    fn target_name(devirtualized: &Option<Devirtualized>) -> Option<String> {
+        devirtualized.as_ref().map(|d| {
+            let target = d.target();
+            format!("{}.{}", target.class().name(), target.method().name())
+        })
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let mut vm = vm();
+        vm.for_name("Tile").unwrap();
+        vm.for_name("Circle").unwrap();
+        let method_area = vm.method_area.read().unwrap();
+        let hierarchy = method_area.hierarchy();
+        let names = |symbols: &[Symbol]| symbols.iter().map(Symbol::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            vec!["Square", "Circle"],
+            names(hierarchy.direct_subtypes("Shape"))
+        );
+        assert_eq!(
+            vec!["Square", "Circle", "Tile"],
+            names(&hierarchy.subtypes("Shape"))
+        );
+        assert!(hierarchy.direct_subtypes("Tile").is_empty());
+    }
+
+    #[test]
+    fn test_devirtualize() {
+        let mut vm = vm();
+        let shape = vm.for_name("Shape").unwrap();
+        let area = resolve_interface_method(&shape, "area", "()D").unwrap();
+        let label = resolve_interface_method(&shape, "label", "()Ljava/lang/String;").unwrap();
+        // nothing implements Shape yet
+        assert_eq!(None, target_name(&vm.devirtualize(&shape, &area)));
+
+        let square = vm.for_name("Square").unwrap();
+        assert_eq!(
+            Some("Square.area".to_string()),
+            target_name(&vm.devirtualize(&shape, &area))
+        );
+        assert_eq!(
+            Some("Shape.label".to_string()),
+            target_name(&vm.devirtualize(&shape, &label))
+        );
+        let side = resolve_class_method(&square, "side", "()D").unwrap();
+        let devirtualized = vm.devirtualize(&square, &side);
+        assert_eq!(Some("Square.side".to_string()), target_name(&devirtualized));
+        assert!(devirtualized.unwrap().dependency().is_none());
+
+        // Tile inherits area() from Square, Circle overrides both methods
+        vm.for_name("Tile").unwrap();
+        assert_eq!(
+            Some("Square.area".to_string()),
+            target_name(&vm.devirtualize(&shape, &area))
+        );
+        let circle = vm.for_name("Circle").unwrap();
+        assert_eq!(None, target_name(&vm.devirtualize(&shape, &area)));
+        assert_eq!(None, target_name(&vm.devirtualize(&shape, &label)));
+        let circle_area = resolve_class_method(&circle, "area", "()D").unwrap();
+        assert_eq!(
+            Some("Circle.area".to_string()),
+            target_name(&vm.devirtualize(&circle, &circle_area))
+        );
+    }
+
+    #[test]
+    fn test_class_loaded() {
+        let mut vm = vm();
+        let table = Rc::new(RefCell::new(DevirtTable::new()));
+        let listened = table.clone();
+        vm.add_load_listener(Box::new(move |class| {
+            listened.borrow_mut().class_loaded(class);
+        }));
+        let shape = vm.for_name("Shape").unwrap();
+        let square = vm.for_name("Square").unwrap();
+        let caller = resolve_class_method(&square, "side", "()D").unwrap();
+        let devirtualize = |pc, static_type: &Rc<Class>, resolved: &ResolvedMethod| {
+            let site = CallSite::new(&caller, pc);
+            let devirtualized = vm.devirtualize(static_type, resolved).unwrap();
+            table.borrow_mut().insert(site.clone(), devirtualized);
+            site
+        };
+        let shape_area = devirtualize(
+            0,
+            &shape,
+            &resolve_interface_method(&shape, "area", "()D").unwrap(),
+        );
+        let square_area = devirtualize(
+            4,
+            &square,
+            &resolve_class_method(&square, "area", "()D").unwrap(),
+        );
+        let side = devirtualize(
+            8,
+            &square,
+            &resolve_class_method(&square, "side", "()D").unwrap(),
+        );
+
+        vm.for_name("Tile").unwrap();
+        assert!(table.borrow().target(&shape_area).is_some());
+        assert!(table.borrow().target(&square_area).is_some());
+
+        // Circle is a Shape but not a Square
+        vm.for_name("Circle").unwrap();
+        assert!(table.borrow().target(&shape_area).is_none());
+        assert!(table.borrow().target(&square_area).is_some());
+
+        vm.for_name("Rotated").unwrap();
+        assert!(table.borrow().target(&square_area).is_none());
+        assert_eq!(
+            "side",
+            table
+                .borrow()
+                .target(&side)
+                .unwrap()
+                .method()
+                .name()
+                .as_str()
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+
+/// An error that stops the VM from executing the current instruction or
+/// native method.
+///
+/// Every error ends up as a Java throwable at the interpreter boundary, see
+/// [`VmError::class_name`]. Only [`VmError::Internal`] indicates a problem with
+/// the VM itself rather than with the executed code.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VmError {
+    /// A bug in the VM, or a feature that is not implemented yet. This is
+    /// thrown as `java.lang.InternalError`.
+    Internal(String),
+    /// A class could not be loaded, linked or initialized.
+    Linkage(LinkageError),
+    /// An exception that is thrown by the executed code, e.g. because it
+    /// dereferenced `null`.
+    Exception(Exception),
+}
+
+/// The subclasses of `java.lang.LinkageError`, as specified by [`$5.3`] and
+/// [`$5.4`].
+///
+/// [`$5.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.3
+/// [`$5.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LinkageError {
+    /// No class file was found for the class with the given name.
+    NoClassDefFound(String),
+    /// A class file is malformed.
+    ClassFormat(String),
+    /// The version of a class file is not supported, e.g. because it depends
+    /// on preview features that are not enabled. This is a
+    /// `ClassFormatError` as well.
+    UnsupportedClassVersion(String),
+    /// The class with the given name is its own superclass or superinterface.
+    ClassCircularity(String),
+    /// A class loader attempted to define a class that it defined already.
+    DuplicateClass(String),
+    /// The class file failed verification.
+    Verify(String),
+    IncompatibleClassChange(String),
+    /// `new` of an abstract class or an interface, which is an
+    /// `IncompatibleClassChangeError` as well.
+    Instantiation(String),
+    NoSuchField(String),
+    NoSuchMethod(String),
+    AbstractMethod(String),
+    IllegalAccess(String),
+    /// The static initializer of a class threw the given exception, see
+    /// [`VmError::is_error`].
+    ExceptionInInitializer(Box<VmError>),
+    /// There is no implementation for the native method with the given class,
+    /// name and descriptor.
+    UnsatisfiedLink(String, String, String),
+}
+
+/// Exceptions (and errors like `StackOverflowError`) that are thrown by the
+/// VM on behalf of the executed code, and that the code can catch.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Exception {
+    NullPointer,
+    /// An integer division or remainder by zero.
+    Arithmetic(String),
+    ArrayIndexOutOfBounds {
+        index: i32,
+        length: i32,
+    },
+    NegativeArraySize(i32),
+    /// An object of the first class can't be cast to the second class.
+    ClassCast(String, String),
+    IllegalArgument(String),
+    /// No class with the given name was found by a lookup by name, e.g.
+    /// `Class.forName`.
+    ClassNotFound(String),
+    /// Reflection on a member that the caller has no access to.
+    IllegalAccess(String),
+    IllegalMonitorState,
+    Interrupted,
+    FileNotFound(String),
+    Io(String),
+    /// The data passed to an `Inflater` is not in the deflate format.
+    DataFormat(String),
+    StackOverflow,
+    OutOfMemory(String),
+    /// A module that another module requires is missing.
+    ModuleNotFound(String),
+    /// The modules of a layer are inconsistent, e.g. because two of them
+    /// contain the same package.
+    ModuleResolution(String),
+    /// A provider-configuration file in `META-INF/services` is malformed, or
+    /// names a class that isn't a provider of the service.
+    ServiceConfiguration(String),
+}
+
+impl VmError {
+    /// The binary name of the Java class that this error is thrown as, e.g.
+    /// `java/lang/NoClassDefFoundError`.
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            VmError::Internal(_) => "java/lang/InternalError",
+            VmError::Linkage(e) => match e {
+                LinkageError::NoClassDefFound(_) => "java/lang/NoClassDefFoundError",
+                LinkageError::ClassFormat(_) => "java/lang/ClassFormatError",
+                LinkageError::UnsupportedClassVersion(_) => {
+                    "java/lang/UnsupportedClassVersionError"
+                }
+                LinkageError::ClassCircularity(_) => "java/lang/ClassCircularityError",
+                LinkageError::DuplicateClass(_) => "java/lang/LinkageError",
+                LinkageError::Verify(_) => "java/lang/VerifyError",
+                LinkageError::IncompatibleClassChange(_) => {
+                    "java/lang/IncompatibleClassChangeError"
+                }
+                LinkageError::Instantiation(_) => "java/lang/InstantiationError",
+                LinkageError::NoSuchField(_) => "java/lang/NoSuchFieldError",
+                LinkageError::NoSuchMethod(_) => "java/lang/NoSuchMethodError",
+                LinkageError::AbstractMethod(_) => "java/lang/AbstractMethodError",
+                LinkageError::IllegalAccess(_) => "java/lang/IllegalAccessError",
+                LinkageError::ExceptionInInitializer(_) => "java/lang/ExceptionInInitializerError",
+                LinkageError::UnsatisfiedLink(..) => "java/lang/UnsatisfiedLinkError",
+            },
+            VmError::Exception(e) => match e {
+                Exception::NullPointer => "java/lang/NullPointerException",
+                Exception::Arithmetic(_) => "java/lang/ArithmeticException",
+                Exception::ArrayIndexOutOfBounds { .. } => {
+                    "java/lang/ArrayIndexOutOfBoundsException"
+                }
+                Exception::NegativeArraySize(_) => "java/lang/NegativeArraySizeException",
+                Exception::ClassCast(..) => "java/lang/ClassCastException",
+                Exception::IllegalArgument(_) => "java/lang/IllegalArgumentException",
+                Exception::ClassNotFound(_) => "java/lang/ClassNotFoundException",
+                Exception::IllegalAccess(_) => "java/lang/IllegalAccessException",
+                Exception::IllegalMonitorState => "java/lang/IllegalMonitorStateException",
+                Exception::Interrupted => "java/lang/InterruptedException",
+                Exception::FileNotFound(_) => "java/io/FileNotFoundException",
+                Exception::Io(_) => "java/io/IOException",
+                Exception::DataFormat(_) => "java/util/zip/DataFormatException",
+                Exception::StackOverflow => "java/lang/StackOverflowError",
+                Exception::OutOfMemory(_) => "java/lang/OutOfMemoryError",
+                Exception::ModuleNotFound(_) => "java/lang/module/FindException",
+                Exception::ModuleResolution(_) => "java/lang/module/ResolutionException",
+                Exception::ServiceConfiguration(_) => "java/util/ServiceConfigurationError",
+            },
+        }
+    }
+
+    /// Whether this error is thrown as a subclass of `java.lang.Error` rather
+    /// than of `java.lang.Exception`.
+    pub fn is_error(&self) -> bool {
+        match self {
+            VmError::Internal(_) | VmError::Linkage(_) => true,
+            VmError::Exception(e) => {
+                matches!(
+                    e,
+                    Exception::StackOverflow
+                        | Exception::OutOfMemory(_)
+                        | Exception::ServiceConfiguration(_)
+                )
+            }
+        }
+    }
+
+    /// The detail message of the throwable, as returned by `getMessage`.
+    pub fn message(&self) -> Option<String> {
+        Some(match self {
+            VmError::Internal(message) => message.clone(),
+            VmError::Linkage(e) => match e {
+                // like HotSpot, the exception is only reported as the cause
+                LinkageError::ExceptionInInitializer(_) => return None,
+                LinkageError::UnsatisfiedLink(class, name, descriptor) => {
+                    format!("'{}.{}{}'", class.replace('/', "."), name, descriptor)
+                }
+                LinkageError::NoClassDefFound(s)
+                | LinkageError::ClassFormat(s)
+                | LinkageError::UnsupportedClassVersion(s)
+                | LinkageError::ClassCircularity(s)
+                | LinkageError::DuplicateClass(s)
+                | LinkageError::Verify(s)
+                | LinkageError::IncompatibleClassChange(s)
+                | LinkageError::Instantiation(s)
+                | LinkageError::NoSuchField(s)
+                | LinkageError::NoSuchMethod(s)
+                | LinkageError::AbstractMethod(s)
+                | LinkageError::IllegalAccess(s) => s.clone(),
+            },
+            VmError::Exception(e) => match e {
+                Exception::NullPointer
+                | Exception::IllegalMonitorState
+                | Exception::Interrupted
+                | Exception::StackOverflow => return None,
+                Exception::ArrayIndexOutOfBounds { index, length } => {
+                    format!("Index {} out of bounds for length {}", index, length)
+                }
+                Exception::NegativeArraySize(size) => size.to_string(),
+                Exception::ClassCast(from, to) => format!(
+                    "class {} cannot be cast to class {}",
+                    from.replace('/', "."),
+                    to.replace('/', ".")
+                ),
+                Exception::Arithmetic(s)
+                | Exception::IllegalArgument(s)
+                | Exception::ClassNotFound(s)
+                | Exception::IllegalAccess(s)
+                | Exception::FileNotFound(s)
+                | Exception::Io(s)
+                | Exception::DataFormat(s)
+                | Exception::OutOfMemory(s)
+                | Exception::ModuleNotFound(s)
+                | Exception::ModuleResolution(s)
+                | Exception::ServiceConfiguration(s) => s.clone(),
+            },
+        })
+    }
+}
+
+/// Formats the error like the `toString` of the Java throwable, e.g.
+/// `java.lang.NoClassDefFoundError: Foo`.
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.class_name().replace('/', "."))?;
+        match self.message() {
+            Some(message) => write!(f, ": {}", message),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl From<LinkageError> for VmError {
+    fn from(e: LinkageError) -> Self {
+        VmError::Linkage(e)
+    }
+}
+
+impl From<Exception> for VmError {
+    fn from(e: Exception) -> Self {
+        VmError::Exception(e)
+    }
+}
+
+impl From<std::io::Error> for VmError {
+    fn from(e: std::io::Error) -> Self {
+        let exception = match e.kind() {
+            ErrorKind::NotFound => Exception::FileNotFound(e.to_string()),
+            _ => Exception::Io(e.to_string()),
+        };
+        VmError::Exception(exception)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "java.lang.NoClassDefFoundError: com/example/Foo",
+            VmError::from(LinkageError::NoClassDefFound("com/example/Foo".into())).to_string()
+        );
+        assert_eq!(
+            "java.lang.NullPointerException",
+            VmError::from(Exception::NullPointer).to_string()
+        );
+        assert_eq!(
+            "java.lang.UnsatisfiedLinkError: 'java.lang.Object.hashCode()I'",
+            VmError::from(LinkageError::UnsatisfiedLink(
+                "java/lang/Object".into(),
+                "hashCode".into(),
+                "()I".into()
+            ))
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_from_io_error() {
+        let not_found = std::io::Error::new(ErrorKind::NotFound, "/missing");
+        assert_eq!(
+            "java/io/FileNotFoundException",
+            VmError::from(not_found).class_name()
+        );
+        let other = std::io::Error::other("broken pipe");
+        assert_eq!("java/io/IOException", VmError::from(other).class_name());
+    }
+}
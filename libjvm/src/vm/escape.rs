@@ -0,0 +1,878 @@
+use crate::vm::classloader::bootstrap::BootstrapClassLoader;
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::resolve::{
+    resolve_class_method, resolve_interface_method, InvokeKind, ResolvedMethod,
+};
+use crate::vm::classloader::ClassLoader;
+use crate::vm::error::VmError;
+use crate::vm::VM;
+use libjava::bytecode::Op;
+use libjava::classfile::flags::MethodAccessFlags;
+use libjava::classfile::{
+    AttributeInfo, ConstantPool, ConstantPoolInfo, ExceptionTableEntry, MemberRef,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Cursor;
+use std::rc::Rc;
+
+/// How deep [`VM::escape_analysis`] follows calls into the code of callees,
+/// like `-XX:MaxBCEAEstimateLevel`.
+const MAX_CALLEE_DEPTH: usize = 5;
+
+/// What the escape analysis found out about the objects that a `new`
+/// instruction allocates.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EscapeState {
+    /// The objects never escape the method, and at every point of the method,
+    /// a variable or stack slot that may hold one of them holds nothing else,
+    /// and at most one of them is live. Their fields can be kept in locals of
+    /// the frame instead of allocating them on the heap.
+    ScalarReplaceable,
+    /// The objects never escape the method, but they are merged with other
+    /// values, or several of them are live at the same time, so their fields
+    /// can't be mapped to a fixed set of locals.
+    NoEscape,
+    /// The objects may be reachable after the method returns or from another
+    /// thread, e.g. because they are returned, stored in a field or an array,
+    /// thrown, or passed to a method that lets them escape.
+    Escapes,
+}
+
+/// The result of [`analyze`] for a method.
+#[derive(Debug, Clone)]
+pub struct EscapeAnalysis {
+    /// The allocations, by the offset of their `new` instruction.
+    allocations: BTreeMap<usize, EscapeState>,
+    /// Whether each argument, with the receiver of an instance method first,
+    /// escapes the method.
+    escaping_arguments: Vec<bool>,
+}
+
+/// Tells the escape analysis whether a called method lets its arguments
+/// escape.
+pub trait Callees {
+    /// Whether the argument at the given position escapes a call of the given
+    /// method, where position 0 is the receiver of an instance method.
+    fn argument_escapes(&mut self, kind: InvokeKind, method: &MemberRef, argument: usize) -> bool;
+}
+
+/// Callees whose code is not known, so that every argument of a call escapes,
+/// except for the receiver of the empty constructor of `java/lang/Object`.
+pub struct OpaqueCallees;
+
+impl Callees for OpaqueCallees {
+    fn argument_escapes(&mut self, _: InvokeKind, _: &MemberRef, _: usize) -> bool {
+        true
+    }
+}
+
+impl EscapeAnalysis {
+    /// What the analysis found out about the `new` instruction at the given
+    /// offset, or [`None`] if there is no `new` instruction there.
+    pub fn allocation(&self, pc: usize) -> Option<EscapeState> {
+        self.allocations.get(&pc).copied()
+    }
+
+    /// The offsets of the `new` instructions of the method, with what the
+    /// analysis found out about them, in ascending order.
+    pub fn allocations(&self) -> impl Iterator<Item = (usize, EscapeState)> + '_ {
+        self.allocations.iter().map(|(pc, state)| (*pc, *state))
+    }
+
+    /// Whether the argument at the given position escapes the method, where
+    /// position 0 is the receiver of an instance method.
+    pub fn argument_escapes(&self, argument: usize) -> bool {
+        self.escaping_arguments
+            .get(argument)
+            .copied()
+            .unwrap_or(true)
+    }
+}
+
+/// Where a reference in a local variable or on the operand stack came from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum Source {
+    /// Anything that is not tracked, like `null` or a loaded field.
+    Unknown,
+    /// The argument at the given position.
+    Argument(usize),
+    /// The `new` instruction at the given offset.
+    New(usize),
+}
+
+/// The possible sources of the value of a slot. Primitive values and unused
+/// slots have none.
+type Sources = BTreeSet<Source>;
+
+/// The sources of the local variables and the operand stack before an
+/// instruction. Longs and doubles take up two slots, like in the frame.
+#[derive(Clone, Eq, PartialEq)]
+struct State {
+    locals: Vec<Sources>,
+    stack: Vec<Sources>,
+}
+
+/// The code can't be analyzed, e.g. because it uses subroutines, so that
+/// everything escapes.
+struct Unsupported;
+
+/// The kind of a value of a descriptor.
+#[derive(Copy, Clone)]
+enum Kind {
+    Primitive,
+    /// A long or a double, which takes up two slots.
+    Wide,
+    Reference,
+}
+
+/// Analyzes which objects that the given method allocates escape it, and
+/// which of its arguments escape it, by abstract interpretation of its
+/// bytecode. Calls are resolved by the given callees. An object that is stored
+/// into another object escapes, even if that object doesn't.
+///
+/// Code that can't be analyzed, like code with subroutines (`jsr` and `ret`),
+/// is assumed to let everything escape.
+#[allow(clippy::too_many_arguments)]
+pub fn analyze(
+    constant_pool: &ConstantPool,
+    code: &[u8],
+    max_locals: usize,
+    exception_table: &[ExceptionTableEntry],
+    is_static: bool,
+    descriptor: &str,
+    callees: &mut impl Callees,
+) -> EscapeAnalysis {
+    let mut arguments = vec![];
+    if !is_static {
+        arguments.push(Kind::Reference);
+    }
+    arguments.extend(parameter_kinds(descriptor).unwrap_or_default());
+
+    let mut analyzer = Analyzer {
+        constant_pool,
+        callees,
+        escaping: BTreeSet::new(),
+    };
+    let ops = decode(code);
+    let result = ops
+        .as_ref()
+        .map_err(|_| Unsupported)
+        .and_then(|ops| analyzer.run(ops, max_locals, exception_table, &arguments));
+    let allocations = ops
+        .iter()
+        .flatten()
+        .filter(|(_, op)| matches!(op, Op::New(_)))
+        .map(|(pc, _)| *pc);
+    match result {
+        Ok(not_replaceable) => EscapeAnalysis {
+            allocations: allocations
+                .map(|pc| {
+                    let state = if analyzer.escaping.contains(&Source::New(pc)) {
+                        EscapeState::Escapes
+                    } else if not_replaceable.contains(&pc) {
+                        EscapeState::NoEscape
+                    } else {
+                        EscapeState::ScalarReplaceable
+                    };
+                    (pc, state)
+                })
+                .collect(),
+            escaping_arguments: (0..arguments.len())
+                .map(|i| analyzer.escaping.contains(&Source::Argument(i)))
+                .collect(),
+        },
+        Err(Unsupported) => EscapeAnalysis {
+            allocations: allocations.map(|pc| (pc, EscapeState::Escapes)).collect(),
+            escaping_arguments: vec![true; arguments.len()],
+        },
+    }
+}
+
+fn decode(code: &[u8]) -> Result<Vec<(usize, Op)>, Unsupported> {
+    let mut ops = vec![];
+    let mut cursor = Cursor::new(code);
+    while (cursor.position() as usize) < code.len() {
+        let offset = cursor.position() as usize;
+        ops.push((
+            offset,
+            Op::parse_at(&mut cursor, offset).map_err(|_| Unsupported)?,
+        ));
+    }
+    Ok(ops)
+}
+
+struct Analyzer<'a, C> {
+    constant_pool: &'a ConstantPool,
+    callees: &'a mut C,
+    escaping: BTreeSet<Source>,
+}
+
+impl<C: Callees> Analyzer<'_, C> {
+    /// Computes the states before every instruction until they don't change
+    /// anymore, and returns the allocations that are not scalar replaceable.
+    fn run(
+        &mut self,
+        ops: &[(usize, Op)],
+        max_locals: usize,
+        exception_table: &[ExceptionTableEntry],
+        arguments: &[Kind],
+    ) -> Result<BTreeSet<usize>, Unsupported> {
+        if ops.is_empty() {
+            return Err(Unsupported);
+        }
+        let mut entry = State {
+            locals: vec![Sources::new(); max_locals],
+            stack: vec![],
+        };
+        let mut slot = 0;
+        for (i, kind) in arguments.iter().enumerate() {
+            if let Kind::Reference = kind {
+                *entry.locals.get_mut(slot).ok_or(Unsupported)? =
+                    Sources::from([Source::Argument(i)]);
+            }
+            slot += if let Kind::Wide = kind { 2 } else { 1 };
+        }
+
+        let index_of = |pc: usize| {
+            ops.binary_search_by_key(&pc, |(o, _)| *o)
+                .map_err(|_| Unsupported)
+        };
+        let mut states: Vec<Option<State>> = vec![None; ops.len()];
+        let mut not_replaceable = BTreeSet::new();
+        let mut pending = vec![0];
+        states[0] = Some(entry);
+        while let Some(i) = pending.pop() {
+            let (pc, op) = &ops[i];
+            let mut state = states[i]
+                .clone()
+                .expect("pending instructions have a state");
+
+            let mut successors = vec![];
+            for entry in exception_table {
+                if (entry.start_pc() as usize..entry.end_pc() as usize).contains(pc) {
+                    let handler = State {
+                        locals: state.locals.clone(),
+                        stack: vec![Sources::from([Source::Unknown])],
+                    };
+                    successors.push((index_of(entry.handler_pc() as usize)?, handler));
+                }
+            }
+
+            if let Op::New(_) = op {
+                // the object of the previous execution is still live
+                let mut live = state.locals.iter().chain(&state.stack);
+                if live.any(|s| s.contains(&Source::New(*pc))) {
+                    not_replaceable.insert(*pc);
+                }
+            }
+            self.step(*pc, op, &mut state)?;
+            for target in op.branch_targets(*pc) {
+                successors.push((index_of(target)?, state.clone()));
+            }
+            if op.falls_through() {
+                successors.push((i + 1, state));
+            }
+
+            for (successor, state) in successors {
+                if successor >= ops.len() {
+                    return Err(Unsupported);
+                }
+                let merged = match &states[successor] {
+                    None => state,
+                    Some(existing) => merge(existing, &state)?,
+                };
+                if states[successor].as_ref() != Some(&merged) {
+                    states[successor] = Some(merged);
+                    pending.push(successor);
+                }
+            }
+        }
+
+        // objects that are merged with other values can't be replaced
+        for state in states.iter().flatten() {
+            for sources in state.locals.iter().chain(&state.stack) {
+                if sources.len() > 1 {
+                    not_replaceable.extend(sources.iter().filter_map(|s| match s {
+                        Source::New(pc) => Some(*pc),
+                        _ => None,
+                    }));
+                }
+            }
+        }
+        Ok(not_replaceable)
+    }
+
+    fn escape(&mut self, sources: &Sources) {
+        self.escaping
+            .extend(sources.iter().filter(|s| **s != Source::Unknown));
+    }
+
+    /// Applies the effect of the instruction on the sources of the slots, and
+    /// records the values that escape.
+    fn step(&mut self, pc: usize, op: &Op, state: &mut State) -> Result<(), Unsupported> {
+        let stack = &mut state.stack;
+        let locals = &mut state.locals;
+        let (popped, pushed) = match op {
+            Op::Nop | Op::Breakpoint | Op::Goto(_) | Op::GotoW(_) | Op::IInc(_, _) => (0, vec![]),
+            Op::Jsr(_) | Op::JsrW(_) | Op::Ret(_) | Op::Wide => return Err(Unsupported),
+
+            Op::AConstNull | Op::LDC(_) | Op::LDCW(_) => (0, vec![Kind::Reference]),
+            Op::IConstM1
+            | Op::IConst0
+            | Op::IConst1
+            | Op::IConst2
+            | Op::IConst3
+            | Op::IConst4
+            | Op::IConst5
+            | Op::BIPush(_)
+            | Op::SIPush(_)
+            | Op::FConst0
+            | Op::FConst1
+            | Op::FConst2
+            | Op::ILoad(_)
+            | Op::FLoad(_) => (0, vec![Kind::Primitive]),
+            Op::LConst0
+            | Op::LConst1
+            | Op::DConst0
+            | Op::DConst1
+            | Op::LDC2W(_)
+            | Op::LLoad(_)
+            | Op::DLoad(_)
+            | Op::DLoad0
+            | Op::DLoad1
+            | Op::DLoad2
+            | Op::DLoad3 => (0, vec![Kind::Wide]),
+            Op::ALoad(index) => {
+                let value = locals.get(*index as usize).ok_or(Unsupported)?.clone();
+                stack.push(value);
+                return Ok(());
+            }
+
+            Op::AStore(index) => {
+                let value = pop(stack, 1)?.remove(0);
+                *locals.get_mut(*index as usize).ok_or(Unsupported)? = value;
+                return Ok(());
+            }
+            Op::IStore(index) | Op::FStore(index) => {
+                pop(stack, 1)?;
+                *locals.get_mut(*index as usize).ok_or(Unsupported)? = Sources::new();
+                return Ok(());
+            }
+            Op::LStore(index) | Op::DStore(index) => {
+                pop(stack, 2)?;
+                let index = *index as usize;
+                locals
+                    .get_mut(index..index + 2)
+                    .ok_or(Unsupported)?
+                    .fill(Sources::new());
+                return Ok(());
+            }
+
+            Op::IALoad | Op::BALoad | Op::CALoad | Op::SALoad | Op::FALoad => {
+                (2, vec![Kind::Primitive])
+            }
+            Op::LALoad | Op::DALoad => (2, vec![Kind::Wide]),
+            Op::AALoad => (2, vec![Kind::Reference]),
+            Op::IAStore | Op::BAStore | Op::CAStore | Op::SAStore | Op::FAStore => (3, vec![]),
+            Op::LAStore | Op::DAStore => (4, vec![]),
+            Op::AAStore => {
+                let values = pop(stack, 3)?;
+                self.escape(&values[2]);
+                return Ok(());
+            }
+
+            Op::IAdd
+            | Op::ISub
+            | Op::IMul
+            | Op::IDiv
+            | Op::IRem
+            | Op::IAnd
+            | Op::IOr
+            | Op::IXor
+            | Op::IShl
+            | Op::IShr
+            | Op::IUShr
+            | Op::FAdd
+            | Op::FSub
+            | Op::FMul
+            | Op::FDiv
+            | Op::FRem
+            | Op::FCmpL
+            | Op::FCmpG => (2, vec![Kind::Primitive]),
+            Op::LAdd
+            | Op::LSub
+            | Op::LMul
+            | Op::LDiv
+            | Op::LRem
+            | Op::LAnd
+            | Op::LOr
+            | Op::LXor
+            | Op::DAdd
+            | Op::DSub
+            | Op::DMul
+            | Op::DDiv
+            | Op::DRem => (4, vec![Kind::Wide]),
+            Op::LShl | Op::LShr | Op::LUShr => (3, vec![Kind::Wide]),
+            Op::LCmp | Op::DCmpL | Op::DCmpG => (4, vec![Kind::Primitive]),
+            Op::INeg
+            | Op::FNeg
+            | Op::I2F
+            | Op::F2I
+            | Op::I2B
+            | Op::I2C
+            | Op::I2S
+            | Op::ArrayLength
+            | Op::InstanceOf(_) => (1, vec![Kind::Primitive]),
+            Op::LNeg | Op::DNeg | Op::L2D | Op::D2L => (2, vec![Kind::Wide]),
+            Op::I2L | Op::I2D | Op::F2L | Op::F2D => (1, vec![Kind::Wide]),
+            Op::L2I | Op::L2F | Op::D2I | Op::D2F => (2, vec![Kind::Primitive]),
+
+            Op::IfEq(_)
+            | Op::IfNe(_)
+            | Op::IfLt(_)
+            | Op::IfGe(_)
+            | Op::IfGt(_)
+            | Op::IfLe(_)
+            | Op::IfNull(_)
+            | Op::IfNonNull(_)
+            | Op::TableSwitch { .. }
+            | Op::LookupSwitch { .. }
+            | Op::IReturn
+            | Op::FReturn
+            | Op::Pop => (1, vec![]),
+            Op::IfICmpEq(_)
+            | Op::IfICmpNe(_)
+            | Op::IfICmpLt(_)
+            | Op::IfICmpGe(_)
+            | Op::IfICmpGt(_)
+            | Op::IfICmpLe(_)
+            | Op::IfACmpEq(_)
+            | Op::IfACmpNe(_)
+            | Op::LReturn
+            | Op::DReturn
+            | Op::Pop2 => (2, vec![]),
+            Op::Return => (0, vec![]),
+            // locking needs the header of the object
+            Op::AReturn | Op::AThrow | Op::MonitorEnter | Op::MonitorExit => {
+                let value = pop(stack, 1)?;
+                self.escape(&value[0]);
+                return Ok(());
+            }
+
+            Op::Dup => return dup(stack, 1, 0),
+            Op::DupX1 => return dup(stack, 1, 1),
+            Op::DupX2 => return dup(stack, 1, 2),
+            Op::Dup2 => return dup(stack, 2, 0),
+            Op::Dup2X1 => return dup(stack, 2, 1),
+            Op::Dup2X2 => return dup(stack, 2, 2),
+            Op::Swap => {
+                let len = stack.len();
+                if len < 2 {
+                    return Err(Unsupported);
+                }
+                stack.swap(len - 1, len - 2);
+                return Ok(());
+            }
+
+            Op::New(_) => {
+                stack.push(Sources::from([Source::New(pc)]));
+                return Ok(());
+            }
+            Op::NewArray(_) | Op::ANewArray(_) => (1, vec![Kind::Reference]),
+            Op::MultiANewArray(_, dimensions) => (*dimensions as usize, vec![Kind::Reference]),
+            Op::CheckCast(_) => return stack.last().map(|_| ()).ok_or(Unsupported),
+
+            Op::GetStatic(index) => (0, vec![self.field_kind(*index)?]),
+            Op::GetField(index) => (1, vec![self.field_kind(*index)?]),
+            Op::PutStatic(index) | Op::PutField(index) => {
+                let kind = self.field_kind(*index)?;
+                let value = pop(stack, slots(kind))?;
+                if let Op::PutField(_) = op {
+                    pop(stack, 1)?;
+                }
+                if let Kind::Reference = kind {
+                    self.escape(&value[0]);
+                }
+                return Ok(());
+            }
+
+            Op::InvokeVirtual(index) => return self.invoke(state, InvokeKind::Virtual, *index),
+            Op::InvokeSpecial(index) => return self.invoke(state, InvokeKind::Special, *index),
+            Op::InvokeStatic(index) => return self.invoke(state, InvokeKind::Static, *index),
+            Op::InvokeInterface(index, _) => {
+                return self.invoke(state, InvokeKind::Interface, *index)
+            }
+            Op::InvokeDynamic(index) => {
+                let descriptor = match self.constant_pool.get(*index) {
+                    Some(ConstantPoolInfo::InvokeDynamicInfo {
+                        name_and_type_index,
+                        ..
+                    }) => {
+                        self.constant_pool
+                            .get_name_and_type(*name_and_type_index)
+                            .map_err(|_| Unsupported)?
+                            .descriptor
+                    }
+                    _ => return Err(Unsupported),
+                };
+                let (parameters, return_kind) = method_kinds(&descriptor)?;
+                let arguments = pop(stack, parameters.iter().copied().map(slots).sum())?;
+                // the bootstrap method decides what happens to the arguments
+                for argument in &arguments {
+                    self.escape(argument);
+                }
+                (0, return_kind.into_iter().collect())
+            }
+        };
+        pop(stack, popped)?;
+        for kind in pushed {
+            push(stack, kind);
+        }
+        Ok(())
+    }
+
+    fn field_kind(&self, index: u16) -> Result<Kind, Unsupported> {
+        let field = self
+            .constant_pool
+            .get_field_ref(index)
+            .map_err(|_| Unsupported)?;
+        let kinds = kinds(&field.descriptor).ok_or(Unsupported)?;
+        match kinds.as_slice() {
+            [kind] => Ok(*kind),
+            _ => Err(Unsupported),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        state: &mut State,
+        kind: InvokeKind,
+        index: u16,
+    ) -> Result<(), Unsupported> {
+        let method = match self.constant_pool.get(index) {
+            Some(ConstantPoolInfo::MethodrefInfo { .. }) => {
+                self.constant_pool.get_method_ref(index)
+            }
+            Some(ConstantPoolInfo::InterfaceMethodrefInfo { .. }) => {
+                self.constant_pool.get_interface_method_ref(index)
+            }
+            _ => return Err(Unsupported),
+        }
+        .map_err(|_| Unsupported)?;
+        let (mut parameters, return_kind) = method_kinds(&method.descriptor)?;
+        if kind != InvokeKind::Static {
+            parameters.insert(0, Kind::Reference);
+        }
+
+        let values = pop(
+            &mut state.stack,
+            parameters.iter().copied().map(slots).sum(),
+        )?;
+        // the constructor of java/lang/Object is empty
+        let is_object_init = method.class_name == "java/lang/Object" && method.name == "<init>";
+        let mut slot = 0;
+        for (i, parameter) in parameters.iter().enumerate() {
+            if let Kind::Reference = parameter {
+                if !is_object_init && self.callees.argument_escapes(kind, &method, i) {
+                    self.escape(&values[slot]);
+                }
+            }
+            slot += slots(*parameter);
+        }
+        if let Some(kind) = return_kind {
+            push(&mut state.stack, kind);
+        }
+        Ok(())
+    }
+}
+
+fn pop(stack: &mut Vec<Sources>, n: usize) -> Result<Vec<Sources>, Unsupported> {
+    let len = stack.len().checked_sub(n).ok_or(Unsupported)?;
+    Ok(stack.split_off(len))
+}
+
+/// Copies the top `n` slots of the stack below the `depth` slots below them.
+fn dup(stack: &mut Vec<Sources>, n: usize, depth: usize) -> Result<(), Unsupported> {
+    let len = stack.len();
+    if len < n + depth {
+        return Err(Unsupported);
+    }
+    let copied = stack[len - n..].to_vec();
+    let at = len - n - depth;
+    stack.splice(at..at, copied);
+    Ok(())
+}
+
+fn push(stack: &mut Vec<Sources>, kind: Kind) {
+    match kind {
+        Kind::Primitive => stack.push(Sources::new()),
+        Kind::Wide => stack.extend([Sources::new(), Sources::new()]),
+        Kind::Reference => stack.push(Sources::from([Source::Unknown])),
+    }
+}
+
+fn slots(kind: Kind) -> usize {
+    match kind {
+        Kind::Wide => 2,
+        _ => 1,
+    }
+}
+
+/// Merges the states of two paths to the same instruction, slot by slot.
+fn merge(a: &State, b: &State) -> Result<State, Unsupported> {
+    if a.stack.len() != b.stack.len() || a.locals.len() != b.locals.len() {
+        return Err(Unsupported);
+    }
+    let union = |a: &[Sources], b: &[Sources]| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| a.union(b).copied().collect())
+            .collect()
+    };
+    Ok(State {
+        locals: union(&a.locals, &b.locals),
+        stack: union(&a.stack, &b.stack),
+    })
+}
+
+/// The kinds of the values of a sequence of field descriptors, like the
+/// parameters of a method descriptor.
+fn kinds(mut descriptors: &str) -> Option<Vec<Kind>> {
+    let mut kinds = vec![];
+    while let Some(first) = descriptors.chars().next() {
+        let (kind, len) = match first {
+            'J' | 'D' => (Kind::Wide, 1),
+            'B' | 'C' | 'F' | 'I' | 'S' | 'Z' => (Kind::Primitive, 1),
+            'L' => (Kind::Reference, descriptors.find(';')? + 1),
+            '[' => {
+                let dimensions = descriptors.find(|c| c != '[')?;
+                let element = match descriptors[dimensions..].chars().next()? {
+                    'L' => descriptors.find(';')? + 1,
+                    _ => dimensions + 1,
+                };
+                (Kind::Reference, element)
+            }
+            _ => return None,
+        };
+        kinds.push(kind);
+        descriptors = &descriptors[len..];
+    }
+    Some(kinds)
+}
+
+fn parameter_kinds(descriptor: &str) -> Option<Vec<Kind>> {
+    let (parameters, _) = descriptor.strip_prefix('(')?.split_once(')')?;
+    kinds(parameters)
+}
+
+/// The kinds of the parameters and of the return value of a method
+/// descriptor.
+fn method_kinds(descriptor: &str) -> Result<(Vec<Kind>, Option<Kind>), Unsupported> {
+    let parameters = parameter_kinds(descriptor).ok_or(Unsupported)?;
+    let return_kind = match descriptor.split_once(')').ok_or(Unsupported)?.1 {
+        "V" => None,
+        descriptor => Some(
+            *kinds(descriptor)
+                .ok_or(Unsupported)?
+                .first()
+                .ok_or(Unsupported)?,
+        ),
+    };
+    Ok((parameters, return_kind))
+}
+
+/// Analyzes the code of the given method, see [`analyze`]. Fails if the
+/// method has no code.
+pub fn analyze_method(
+    method: &ResolvedMethod,
+    callees: &mut impl Callees,
+) -> Result<EscapeAnalysis, VmError> {
+    let class = method.class();
+    let declared = method.method();
+    let (code, max_locals, exception_table) = class
+        .method_info(declared)
+        .attributes()
+        .iter()
+        .find_map(|a| match a {
+            AttributeInfo::Code {
+                code,
+                max_locals,
+                exception_table,
+                ..
+            } => Some((code, *max_locals, exception_table)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            VmError::Internal(format!(
+                "{}.{}{} has no code",
+                class.name().replace('/', "."),
+                declared.name(),
+                declared.descriptor()
+            ))
+        })?;
+    Ok(analyze(
+        class.constant_pool(),
+        code,
+        max_locals as usize,
+        exception_table,
+        declared.access_flags().contains(MethodAccessFlags::STATIC),
+        declared.descriptor(),
+        callees,
+    ))
+}
+
+/// Callees that are summarized by analyzing their code, if the call is bound
+/// statically. Virtual calls are opaque, since the loaded classes may select
+/// other methods later.
+struct ResolvingCallees<'a> {
+    loader: &'a mut BootstrapClassLoader,
+    depth: usize,
+    /// The analyses of the callees, by class, name and descriptor, or [`None`]
+    /// if a callee can't be analyzed.
+    summaries: HashMap<(String, String, String), Option<EscapeAnalysis>>,
+}
+
+impl ResolvingCallees<'_> {
+    fn resolve(&mut self, kind: InvokeKind, method: &MemberRef) -> Option<ResolvedMethod> {
+        let class: Rc<Class> = self.loader.find_or_load_class(&*method.class_name).ok()?;
+        let resolved = if class.is_interface() {
+            resolve_interface_method(&class, &method.name, &method.descriptor)
+        } else {
+            resolve_class_method(&class, &method.name, &method.descriptor)
+        }
+        .ok()?;
+        // invokespecial selects an overriding method of a superclass for
+        // anything but constructors and private methods
+        let is_private = resolved
+            .method()
+            .access_flags()
+            .contains(MethodAccessFlags::PRIVATE);
+        match kind {
+            InvokeKind::Static => Some(resolved),
+            InvokeKind::Special if method.name == "<init>" || is_private => Some(resolved),
+            _ => None,
+        }
+    }
+}
+
+impl Callees for ResolvingCallees<'_> {
+    fn argument_escapes(&mut self, kind: InvokeKind, method: &MemberRef, argument: usize) -> bool {
+        let key = (
+            method.class_name.to_string(),
+            method.name.to_string(),
+            method.descriptor.to_string(),
+        );
+        if !self.summaries.contains_key(&key) {
+            if self.depth >= MAX_CALLEE_DEPTH {
+                return true;
+            }
+            let summary = self.resolve(kind, method).and_then(|resolved| {
+                self.depth += 1;
+                let summary = analyze_method(&resolved, self).ok();
+                self.depth -= 1;
+                summary
+            });
+            self.summaries.insert(key.clone(), summary);
+        }
+        self.summaries[&key]
+            .as_ref()
+            .is_none_or(|summary| summary.argument_escapes(argument))
+    }
+}
+
+impl VM {
+    /// Analyzes which objects that the given method allocates escape it, see
+    /// [`analyze`]. Static calls, constructors and private methods are
+    /// followed into the code of the callee, loading its class if necessary.
+    pub fn escape_analysis(&mut self, method: &ResolvedMethod) -> Result<EscapeAnalysis, VmError> {
+        let mut callees = ResolvingCallees {
+            loader: &mut self.bootstrap_class_loader,
+            depth: 0,
+            summaries: HashMap::new(),
+        };
+        analyze_method(method, &mut callees)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_path;
+    use libvfs::FileSystem;
+
+    fn vm() -> VM {
+        VM::new(FileSystem::new_os_fs(), class_path(&["escape"]))
+    }
+
+    fn method(class: &Rc<Class>, name: &str) -> ResolvedMethod {
+        let method = class.methods_named(name).next().unwrap();
+        ResolvedMethod::new(class, method)
+    }
+
+    fn states(analysis: &EscapeAnalysis) -> Vec<EscapeState> {
+        analysis.allocations().map(|(_, state)| state).collect()
+    }
+
+    #[test]
+    fn test_escape_analysis() {
+        let mut vm = vm();
+        let point = vm.for_name("Point").unwrap();
+        let mut analyze = |name| {
+            let method = method(&point, name);
+            states(&vm.escape_analysis(&method).unwrap())
+        };
+        use EscapeState::*;
+        assert_eq!(vec![ScalarReplaceable], analyze("local"));
+        assert_eq!(vec![Escapes], analyze("returned"));
+        assert_eq!(vec![Escapes], analyze("stored"));
+        assert_eq!(vec![NoEscape, NoEscape], analyze("merged"));
+        assert_eq!(vec![NoEscape, NoEscape], analyze("loop"));
+        assert_eq!(vec![ScalarReplaceable], analyze("passed"));
+        assert_eq!(vec![ScalarReplaceable], analyze("thrown"));
+        // link is virtual, and stores its argument
+        assert_eq!(vec![Escapes, Escapes], analyze("linked"));
+    }
+
+    #[test]
+    fn test_arguments() {
+        let mut vm = vm();
+        let point = vm.for_name("Point").unwrap();
+        let link = vm.escape_analysis(&method(&point, "link")).unwrap();
+        assert!(!link.argument_escapes(0));
+        assert!(link.argument_escapes(1));
+        let init = vm.escape_analysis(&method(&point, "<init>")).unwrap();
+        assert!(!init.argument_escapes(0));
+        let x = vm.escape_analysis(&method(&point, "x")).unwrap();
+        assert!(!x.argument_escapes(0));
+    }
+
+    #[test]
+    fn test_opaque_callees() {
+        let mut vm = vm();
+        let point = vm.for_name("Point").unwrap();
+        let local = method(&point, "local");
+        let analysis = analyze_method(&local, &mut OpaqueCallees).unwrap();
+        // the constructor of Point is not known
+        assert_eq!(vec![EscapeState::Escapes], states(&analysis));
+        assert_eq!(Some(EscapeState::Escapes), analysis.allocation(0));
+        assert_eq!(None, analysis.allocation(3));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let constant_pool = ConstantPool::from(vec![]);
+        // jsr +3, return, astore_0, ret 0
+        let code = [0xA8, 0x00, 0x04, 0xB1, 0x4B, 0xA9, 0x00];
+        let analysis = analyze(
+            &constant_pool,
+            &code,
+            1,
+            &[],
+            true,
+            "(Ljava/lang/Object;)V",
+            &mut OpaqueCallees,
+        );
+        assert_eq!(0, analysis.allocations().count());
+        assert!(analysis.argument_escapes(0));
+    }
+}
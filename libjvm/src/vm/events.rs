@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// The number of events of each kind that an [`EventLog`] keeps by default,
+/// like `-XX:LogEventsBufferEntries` of HotSpot.
+pub const DEFAULT_CAPACITY: usize = 20;
+
+/// The kinds of events that an [`EventLog`] records, each in a ring buffer of
+/// its own, so that frequent events don't push out the rare ones.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EventKind {
+    ClassLoad,
+    GarbageCollection,
+    Exception,
+    Compilation,
+}
+
+impl EventKind {
+    const ALL: [EventKind; 4] = [
+        EventKind::Compilation,
+        EventKind::GarbageCollection,
+        EventKind::Exception,
+        EventKind::ClassLoad,
+    ];
+
+    /// The heading of the events of this kind in [`EventLog::dump`], as in
+    /// the `hs_err` files of HotSpot.
+    fn title(self) -> &'static str {
+        match self {
+            EventKind::ClassLoad => "Classes loaded",
+            EventKind::GarbageCollection => "GC Heap History",
+            EventKind::Exception => "Exceptions",
+            EventKind::Compilation => "Compilation events",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Event {
+    /// The time of the runtime's clock when the event occurred, in
+    /// nanoseconds.
+    pub time: i64,
+    pub message: String,
+}
+
+/// The most recent significant events of a VM, which are dumped when the VM
+/// crashes, like the event records in the `hs_err` files of HotSpot.
+///
+/// Each kind of event is kept in a fixed-size ring buffer, so that recording
+/// an event is cheap and the log doesn't grow while the VM runs.
+pub struct EventLog {
+    capacity: usize,
+    logs: Mutex<Vec<Log>>,
+}
+
+#[derive(Default)]
+struct Log {
+    events: VecDeque<Event>,
+    /// The number of events that were recorded, including the dropped ones.
+    total: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl EventLog {
+    /// Creates an event log that keeps the given number of events of each
+    /// kind.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            logs: Mutex::new(EventKind::ALL.iter().map(|_| Log::default()).collect()),
+        }
+    }
+
+    /// Records an event, dropping the oldest event of the same kind if the
+    /// buffer is full.
+    pub fn log(&self, kind: EventKind, time: i64, message: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut logs = self.logs.lock().unwrap();
+        let log = &mut logs[Self::slot(kind)];
+        if log.events.len() == self.capacity {
+            log.events.pop_front();
+        }
+        log.events.push_back(Event { time, message });
+        log.total += 1;
+    }
+
+    /// The retained events of the given kind, from the oldest to the newest.
+    pub fn events(&self, kind: EventKind) -> Vec<Event> {
+        let logs = self.logs.lock().unwrap();
+        logs[Self::slot(kind)].events.iter().cloned().collect()
+    }
+
+    /// Writes all retained events, grouped by their kind, e.g.
+    ///
+    /// ```text
+    /// Classes loaded (2 events):
+    /// Event: 0.004 Loaded class java/lang/Object
+    /// Event: 0.005 Loaded class Main
+    /// ```
+    ///
+    /// The times are the seconds of the runtime's clock.
+    pub fn dump(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let logs = self.logs.lock().unwrap();
+        for kind in EventKind::ALL {
+            let log = &logs[Self::slot(kind)];
+            writeln!(out, "{} ({} events):", kind.title(), log.total)?;
+            if log.events.is_empty() {
+                writeln!(out, "No events")?;
+            }
+            for event in &log.events {
+                let seconds = event.time as f64 / 1e9;
+                writeln!(out, "Event: {:.3} {}", seconds, event.message)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    fn slot(kind: EventKind) -> usize {
+        EventKind::ALL.iter().position(|k| *k == kind).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer() {
+        let log = EventLog::new(2);
+        for i in 0..3 {
+            log.log(EventKind::ClassLoad, i, format!("Loaded class C{}", i));
+        }
+        log.log(
+            EventKind::Exception,
+            1_500_000_000,
+            "Exception thrown".into(),
+        );
+
+        let messages: Vec<_> = log
+            .events(EventKind::ClassLoad)
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(vec!["Loaded class C1", "Loaded class C2"], messages);
+
+        let mut out = vec![];
+        log.dump(&mut out).unwrap();
+        assert_eq!(
+            "Compilation events (0 events):\n\
+             No events\n\
+             \n\
+             GC Heap History (0 events):\n\
+             No events\n\
+             \n\
+             Exceptions (1 events):\n\
+             Event: 1.500 Exception thrown\n\
+             \n\
+             Classes loaded (3 events):\n\
+             Event: 0.000 Loaded class C1\n\
+             Event: 0.000 Loaded class C2\n\
+             \n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
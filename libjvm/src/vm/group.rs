@@ -0,0 +1,154 @@
+use crate::vm::error::VmError;
+use std::sync::{Arc, RwLock};
+
+/// Handles a throwable that terminated a thread, like
+/// `Thread.UncaughtExceptionHandler`. It is called with the name of the thread
+/// and the throwable.
+pub type UncaughtHandler = Arc<dyn Fn(&str, &VmError) + Send + Sync>;
+
+/// A group of threads, like `java.lang.ThreadGroup`. Every thread belongs to a
+/// group, and every group but the system group has a parent.
+///
+/// The VM creates the `system` group and the `main` group in it, which the
+/// main thread belongs to, like HotSpot.
+pub struct ThreadGroup {
+    name: String,
+    parent: Option<Arc<ThreadGroup>>,
+    /// The highest priority that threads in this group can have.
+    max_priority: i32,
+    daemon: bool,
+    /// Handles the uncaught throwables of the threads in this group instead of
+    /// the parent, like an overridden `ThreadGroup.uncaughtException`.
+    uncaught_handler: RwLock<Option<UncaughtHandler>>,
+}
+
+/// `Thread.MAX_PRIORITY`.
+pub const MAX_PRIORITY: i32 = 10;
+
+impl ThreadGroup {
+    /// Creates the system group, the root of all groups.
+    pub fn system() -> Arc<Self> {
+        Arc::new(Self {
+            name: "system".to_string(),
+            parent: None,
+            max_priority: MAX_PRIORITY,
+            daemon: false,
+            uncaught_handler: RwLock::new(None),
+        })
+    }
+
+    /// Creates a group in the given parent group, which inherits the maximum
+    /// priority and the daemon status of the parent, like the constructors of
+    /// `ThreadGroup`.
+    pub fn new(name: &str, parent: &Arc<ThreadGroup>) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            parent: Some(parent.clone()),
+            max_priority: parent.max_priority,
+            daemon: parent.daemon,
+            uncaught_handler: RwLock::new(None),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<&Arc<ThreadGroup>> {
+        self.parent.as_ref()
+    }
+
+    pub fn max_priority(&self) -> i32 {
+        self.max_priority
+    }
+
+    pub fn is_daemon(&self) -> bool {
+        self.daemon
+    }
+
+    /// Whether this group is the given group or one of its descendants, like
+    /// `ThreadGroup.parentOf` with the groups swapped.
+    pub fn is_descendant_of(&self, other: &ThreadGroup) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|p| p.is_descendant_of(other))
+    }
+
+    pub fn set_uncaught_handler(&self, handler: Option<UncaughtHandler>) {
+        *self.uncaught_handler.write().unwrap() = handler;
+    }
+
+    /// Handles a throwable that terminated a thread of this group, like
+    /// `ThreadGroup.uncaughtException`: the handler of the closest group that
+    /// has one handles it, otherwise the given default handler. Returns
+    /// `false` if neither exists, in which case the caller has to report the
+    /// throwable itself.
+    pub fn uncaught_exception(
+        &self,
+        thread_name: &str,
+        error: &VmError,
+        default_handler: Option<&UncaughtHandler>,
+    ) -> bool {
+        if let Some(handler) = self.uncaught_handler.read().unwrap().clone() {
+            handler(thread_name, error);
+            return true;
+        }
+        match (&self.parent, default_handler) {
+            (Some(parent), _) => parent.uncaught_exception(thread_name, error, default_handler),
+            (None, Some(handler)) => {
+                handler(thread_name, error);
+                true
+            }
+            (None, None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn recording(name: &'static str, log: &Arc<Mutex<Vec<String>>>) -> UncaughtHandler {
+        let log = log.clone();
+        Arc::new(move |thread, error| {
+            log.lock()
+                .unwrap()
+                .push(format!("{} {} {}", name, thread, error))
+        })
+    }
+
+    #[test]
+    fn test_uncaught_exception() {
+        let system = ThreadGroup::system();
+        let main = ThreadGroup::new("main", &system);
+        let workers = ThreadGroup::new("workers", &main);
+        assert!(workers.is_descendant_of(&system));
+        assert!(!system.is_descendant_of(&workers));
+        assert_eq!(MAX_PRIORITY, workers.max_priority());
+
+        let error = VmError::Internal("boom".into());
+        let log = Arc::new(Mutex::new(vec![]));
+        assert!(!workers.uncaught_exception("worker-1", &error, None));
+
+        let default_handler = recording("default", &log);
+        assert!(workers.uncaught_exception("worker-1", &error, Some(&default_handler)));
+
+        // the closest group with a handler wins over the default handler
+        main.set_uncaught_handler(Some(recording("main", &log)));
+        assert!(workers.uncaught_exception("worker-2", &error, Some(&default_handler)));
+        workers.set_uncaught_handler(Some(recording("workers", &log)));
+        assert!(workers.uncaught_exception("worker-3", &error, None));
+
+        assert_eq!(
+            vec![
+                "default worker-1 java.lang.InternalError: boom",
+                "main worker-2 java.lang.InternalError: boom",
+                "workers worker-3 java.lang.InternalError: boom",
+            ],
+            *log.lock().unwrap()
+        );
+    }
+}
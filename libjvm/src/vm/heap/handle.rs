@@ -0,0 +1,266 @@
+use crate::vm::error::VmError;
+use crate::vm::heap::NULL;
+use std::sync::Mutex;
+
+/// A handle to a heap object that is only valid within the local scope it was
+/// created in, see [`LocalHandles`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Local {
+    index: usize,
+    id: u64,
+}
+
+/// A handle to a heap object that is valid until it is explicitly deleted, see
+/// [`GlobalHandles`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Global {
+    index: usize,
+    id: u64,
+}
+
+/// The local handles of a thread, organized in nested scopes.
+///
+/// Native code must not hold on to raw references across anything that may
+/// run the garbage collector: the object may be collected, or moved to another
+/// reference. Instead, it creates a handle, which is a GC root, and resolves it
+/// whenever it needs the reference. When the innermost scope is closed, all
+/// handles that were created in it are released, and resolving them fails.
+///
+/// The interpreter opens a scope for every native method, so handles that a
+/// native creates are released when it returns.
+pub struct LocalHandles {
+    /// The handles of all open scopes, as id and reference.
+    slots: Vec<(u64, usize)>,
+    /// The length of `slots` when each of the open scopes was opened.
+    scopes: Vec<usize>,
+    next_id: u64,
+}
+
+/// The global handles of a VM, for objects that native code or the embedder
+/// keep beyond a single native call. Global handles are GC roots until they are
+/// deleted.
+#[derive(Default)]
+pub struct GlobalHandles {
+    inner: Mutex<GlobalSlots>,
+}
+
+#[derive(Default)]
+struct GlobalSlots {
+    /// The handles as id and reference. A deleted handle has the reference
+    /// [`NULL`] and its slot is on the free list.
+    slots: Vec<(u64, usize)>,
+    free: Vec<usize>,
+    next_id: u64,
+}
+
+impl LocalHandles {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            scopes: vec![],
+            // ids start at 1, so that no handle matches a cleared slot
+            next_id: 1,
+        }
+    }
+
+    /// Opens a new innermost scope.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(self.slots.len());
+    }
+
+    /// Closes the innermost scope, releasing all handles that were created in
+    /// it. Prefer [`Thread::local_scope`](crate::vm::thread::Thread::local_scope),
+    /// which can't forget to close the scope.
+    pub fn pop_scope(&mut self) {
+        let start = self.scopes.pop().expect("no local scope is open");
+        self.slots.truncate(start);
+    }
+
+    /// The number of scopes that are currently open.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Creates a handle to the given object in the innermost scope.
+    ///
+    /// # Panics
+    /// Panics if no scope is open.
+    pub fn create(&mut self, reference: usize) -> Local {
+        assert!(!self.scopes.is_empty(), "no local scope is open");
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.push((id, reference));
+        Local {
+            index: self.slots.len() - 1,
+            id,
+        }
+    }
+
+    /// Returns the reference of the object that the handle refers to. Fails if
+    /// the scope of the handle was already closed.
+    pub fn resolve(&self, handle: Local) -> Result<usize, VmError> {
+        match self.slots.get(handle.index) {
+            Some((id, reference)) if *id == handle.id => Ok(*reference),
+            _ => Err(VmError::Internal(format!(
+                "use of released local handle {:?}",
+                handle
+            ))),
+        }
+    }
+
+    /// Calls the given function with every root in the open scopes, which may
+    /// replace the reference, e.g. because the object was moved.
+    pub fn visit_roots(&mut self, mut f: impl FnMut(&mut usize)) {
+        self.slots
+            .iter_mut()
+            .map(|(_, reference)| reference)
+            .filter(|r| **r != NULL)
+            .for_each(&mut f);
+    }
+}
+
+impl Default for LocalHandles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlobalHandles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a handle to the given object, which stays valid until it is
+    /// passed to [`GlobalHandles::delete`].
+    pub fn create(&self, reference: usize) -> Global {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        let index = match inner.free.pop() {
+            Some(index) => {
+                inner.slots[index] = (id, reference);
+                index
+            }
+            None => {
+                inner.slots.push((id, reference));
+                inner.slots.len() - 1
+            }
+        };
+        Global { index, id }
+    }
+
+    /// Returns the reference of the object that the handle refers to. Fails if
+    /// the handle was deleted.
+    pub fn resolve(&self, handle: Global) -> Result<usize, VmError> {
+        let inner = self.inner.lock().unwrap();
+        match inner.slots.get(handle.index) {
+            Some((id, reference)) if *id == handle.id => Ok(*reference),
+            _ => Err(stale_global(handle)),
+        }
+    }
+
+    /// Deletes the handle, so that it no longer keeps the object alive. Fails
+    /// if the handle was already deleted.
+    pub fn delete(&self, handle: Global) -> Result<(), VmError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.slots.get_mut(handle.index) {
+            Some(slot) if slot.0 == handle.id => *slot = (0, NULL),
+            _ => return Err(stale_global(handle)),
+        }
+        inner.free.push(handle.index);
+        Ok(())
+    }
+
+    /// The number of handles that were created and not deleted yet.
+    pub fn len(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.slots.len() - inner.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls the given function with every root, see
+    /// [`LocalHandles::visit_roots`].
+    pub fn visit_roots(&self, mut f: impl FnMut(&mut usize)) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .slots
+            .iter_mut()
+            .map(|(_, reference)| reference)
+            .filter(|r| **r != NULL)
+            .for_each(&mut f);
+    }
+}
+
+fn stale_global(handle: Global) -> VmError {
+    VmError::Internal(format!("use of deleted global handle {:?}", handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_scopes() {
+        let mut handles = LocalHandles::new();
+        handles.push_scope();
+        let outer = handles.create(1);
+        handles.push_scope();
+        let inner = handles.create(2);
+        assert_eq!(Ok(2), handles.resolve(inner));
+        handles.pop_scope();
+
+        assert!(handles.resolve(inner).is_err());
+        // a new handle in the same slot doesn't revive the released one
+        let reused = handles.create(3);
+        assert!(handles.resolve(inner).is_err());
+        assert_eq!(Ok(3), handles.resolve(reused));
+        assert_eq!(Ok(1), handles.resolve(outer));
+
+        handles.pop_scope();
+        assert_eq!(0, handles.depth());
+        assert!(handles.resolve(outer).is_err());
+    }
+
+    #[test]
+    fn test_visit_roots_updates_references() {
+        let mut handles = LocalHandles::new();
+        handles.push_scope();
+        let a = handles.create(1);
+        let null = handles.create(NULL);
+        let b = handles.create(2);
+
+        let mut roots = vec![];
+        handles.visit_roots(|r| {
+            roots.push(*r);
+            *r += 10;
+        });
+        assert_eq!(vec![1, 2], roots);
+        assert_eq!(Ok(11), handles.resolve(a));
+        assert_eq!(Ok(12), handles.resolve(b));
+        assert_eq!(Ok(NULL), handles.resolve(null));
+    }
+
+    #[test]
+    fn test_global_handles() {
+        let handles = GlobalHandles::new();
+        let a = handles.create(1);
+        let b = handles.create(2);
+        assert_eq!(2, handles.len());
+        handles.delete(a).unwrap();
+        assert!(handles.resolve(a).is_err());
+        assert!(handles.delete(a).is_err());
+
+        let c = handles.create(3);
+        assert!(handles.resolve(a).is_err());
+        assert_eq!(Ok(3), handles.resolve(c));
+        assert_eq!(Ok(2), handles.resolve(b));
+
+        let mut roots = vec![];
+        handles.visit_roots(|r| roots.push(*r));
+        roots.sort();
+        assert_eq!(vec![2, 3], roots);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::vm::classloader::layout::Field;
+use crate::vm::heap::{Heap, HeapObject, NULL};
+use crate::vm::types::NativeValue;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+
+impl Heap {
+    /// Returns the instance fields of the given object with their values,
+    /// including inherited fields, ordered by offset. Returns [`None`] if the
+    /// reference doesn't point to an instance.
+    pub fn fields(&self, reference: usize) -> Option<Vec<(&Field, NativeValue)>> {
+        let instance = self.get(reference)?.as_instance()?;
+        Some(
+            instance
+                .layout()
+                .fields()
+                .iter()
+                .map(|f| (f, instance.field(f)))
+                .collect(),
+        )
+    }
+
+    /// Returns the elements of the given array. Returns [`None`] if the
+    /// reference doesn't point to an array.
+    pub fn elements(&self, reference: usize) -> Option<Vec<NativeValue>> {
+        let array = self.get(reference)?.as_array()?;
+        Some(
+            (0..array.len() as i32)
+                .map(|i| array.element(i).unwrap())
+                .collect(),
+        )
+    }
+
+    /// Returns the non-null references that the given object holds in its
+    /// fields or elements, in order and including duplicates.
+    pub fn references(&self, reference: usize) -> Vec<usize> {
+        let values = match self.get(reference) {
+            Some(HeapObject::Instance(_)) => self.fields(reference).unwrap_or_default(),
+            Some(HeapObject::Array(array)) if array.component().is_reference() => {
+                return references_in(self.elements(reference).unwrap_or_default().iter())
+            }
+            _ => vec![],
+        };
+        references_in(values.iter().map(|(_, v)| v))
+    }
+
+    /// Returns all objects that are reachable from the given object, including
+    /// the object itself, in breadth-first order. Every object is visited
+    /// once, so this terminates for cyclic graphs.
+    pub fn reachable(&self, root: usize) -> Vec<usize> {
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        let mut queue = VecDeque::from([root]);
+        while let Some(reference) = queue.pop_front() {
+            if self.get(reference).is_none() || !visited.insert(reference) {
+                continue;
+            }
+            order.push(reference);
+            queue.extend(self.references(reference));
+        }
+        order
+    }
+
+    /// Renders the object graph starting at the given reference, e.g.
+    ///
+    /// ```text
+    /// Circle@1 { id: 0, radius: 1.5, label: [C@2 ['h', 'i'], symbol: 'x' }
+    /// ```
+    ///
+    /// Objects are written as `<class>@<reference>`, followed by their fields or
+    /// elements the first time they are encountered. Later occurrences, e.g.
+    /// because of a cycle, are written without their contents.
+    pub fn debug_string(&self, reference: usize) -> String {
+        let mut out = String::new();
+        self.write_debug(&mut out, reference, &mut HashSet::new());
+        out
+    }
+
+    fn write_debug(&self, out: &mut String, reference: usize, visited: &mut HashSet<usize>) {
+        let object = match self.get(reference) {
+            Some(object) => object,
+            None if reference == NULL => return out.push_str("null"),
+            None => return write!(out, "<invalid reference {}>", reference).unwrap(),
+        };
+        write!(out, "{}@{}", object.class_name(), reference).unwrap();
+        if !visited.insert(reference) {
+            return;
+        }
+
+        let (empty, open, close, values): (_, _, _, Vec<(Option<&Field>, NativeValue)>) =
+            match object {
+                HeapObject::Instance(_) => {
+                    let fields = self.fields(reference).unwrap();
+                    let values = fields.into_iter().map(|(f, v)| (Some(f), v)).collect();
+                    (" {}", " {", " }", values)
+                }
+                HeapObject::Array(_) => {
+                    let elements = self.elements(reference).unwrap();
+                    let values = elements.into_iter().map(|v| (None, v)).collect();
+                    (" []", " [", "]", values)
+                }
+            };
+        if values.is_empty() {
+            return out.push_str(empty);
+        }
+        out.push_str(open);
+        for (i, (field, value)) in values.into_iter().enumerate() {
+            out.push_str(if i == 0 { "" } else { "," });
+            if let Some(field) = field {
+                write!(out, " {}: ", field.name()).unwrap();
+            } else if i > 0 {
+                out.push(' ');
+            }
+            match value {
+                NativeValue::Reference(r) => self.write_debug(out, r, visited),
+                value => write_primitive(out, &value),
+            }
+        }
+        out.push_str(close);
+    }
+}
+
+fn references_in<'a>(values: impl Iterator<Item = &'a NativeValue>) -> Vec<usize> {
+    values
+        .filter_map(|v| match v {
+            NativeValue::Reference(r) if *r != NULL => Some(*r),
+            _ => None,
+        })
+        .collect()
+}
+
+fn write_primitive(out: &mut String, value: &NativeValue) {
+    match value {
+        NativeValue::Boolean(v) => write!(out, "{}", v),
+        NativeValue::Byte(v) => write!(out, "{}", v),
+        NativeValue::Char(v) => match char::from_u32(*v as u32) {
+            Some(c) => write!(out, "{:?}", c),
+            None => write!(out, "'\\u{{{:04x}}}'", v),
+        },
+        NativeValue::Short(v) => write!(out, "{}", v),
+        NativeValue::Integer(v) => write!(out, "{}", v),
+        NativeValue::Float(v) => write!(out, "{:?}", v),
+        NativeValue::Long(v) => write!(out, "{}", v),
+        NativeValue::Double(v) => write!(out, "{:?}", v),
+        NativeValue::Reference(v) | NativeValue::ReturnAddress(v) => write!(out, "{}", v),
+    }
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::descriptor::FieldType;
+    use crate::vm::heap::tests::load_class;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_inspect_cyclic_graph() {
+        let circle = load_class("Circle");
+        let layout = circle.instance_layout();
+        let mut heap = Heap::new();
+        let a = heap.allocate_instance(&circle);
+        let b = heap.allocate_instance(&circle);
+        let objects = heap
+            .allocate_array(Arc::new(FieldType::Object(circle.name().clone())), 3)
+            .unwrap();
+
+        // a.label = b, b.label = objects, objects = [a, null, b]
+        let label = layout.field("label", "Ljava/lang/Object;").unwrap();
+        let set = |heap: &mut Heap, object: usize, value: usize| {
+            let instance = heap.get_mut(object).unwrap().as_instance_mut().unwrap();
+            instance
+                .set_field(label, NativeValue::Reference(value))
+                .unwrap();
+        };
+        set(&mut heap, a, b);
+        set(&mut heap, b, objects);
+        let array = heap.get_mut(objects).unwrap().as_array_mut().unwrap();
+        array.set_element(0, NativeValue::Reference(a)).unwrap();
+        array.set_element(2, NativeValue::Reference(b)).unwrap();
+        let instance = heap.get_mut(a).unwrap().as_instance_mut().unwrap();
+        instance
+            .set_field(
+                layout.field("symbol", "C").unwrap(),
+                NativeValue::Char('x' as u16),
+            )
+            .unwrap();
+        instance
+            .set_field(
+                layout.field("radius", "D").unwrap(),
+                NativeValue::Double(1.5),
+            )
+            .unwrap();
+
+        assert_eq!(vec![b], heap.references(a));
+        assert_eq!(vec![a, b], heap.references(objects));
+        assert_eq!(vec![a, b, objects], heap.reachable(a));
+        assert_eq!(vec![objects, a, b], heap.reachable(objects));
+        assert_eq!(
+            vec!["id", "kind", "radius", "label", "symbol", "filled"],
+            heap.fields(a)
+                .unwrap()
+                .iter()
+                .map(|(f, _)| f.name().as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(heap.elements(a).is_none());
+
+        assert_eq!(
+            "Circle@1 { id: 0, kind: 0, radius: 1.5, \
+             label: Circle@2 { id: 0, kind: 0, radius: 0.0, \
+             label: [LCircle;@3 [Circle@1, null, Circle@2], \
+             symbol: '\\0', filled: false }, \
+             symbol: 'x', filled: false }",
+            heap.debug_string(a)
+        );
+        assert_eq!("null", heap.debug_string(NULL));
+    }
+
+    #[test]
+    fn test_debug_string_empty() {
+        let mut heap = Heap::new();
+        let object = heap.allocate_instance(&load_class("java/lang/Object"));
+        let array = heap.allocate_array(Arc::new(FieldType::Int), 0).unwrap();
+        assert_eq!("java/lang/Object@1 {}", heap.debug_string(object));
+        assert_eq!("[I@2 []", heap.debug_string(array));
+        assert!(heap.references(array).is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::layout::{Field, FieldLayout, REFERENCE_SIZE};
+use crate::vm::descriptor::FieldType;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::symbol::Symbol;
+use crate::vm::types::NativeValue;
+use std::sync::Arc;
+
+/// Handles that keep heap objects alive and track them when they move.
+pub mod handle;
+/// Walking the object graph, for debuggers, heap dumps and embedders.
+pub mod inspect;
+/// The fixed-size regions that the heap is divided into, with the metadata
+/// that collectors need about them.
+pub mod region;
+/// The allocation sites of objects, to find out where leaked objects come
+/// from.
+pub mod sites;
+
+/// The `null` reference. No object on the [`Heap`] has this reference.
+pub const NULL: usize = 0;
+
+/// The storage of all objects and arrays, specified by [`$2.5.3`].
+///
+/// Objects are addressed by references, which are what the interpreter keeps
+/// in slots and what natives get as [`NativeValue::Reference`]. References are
+/// never reused, and [`NULL`] is never a valid reference. Every object is
+/// placed in one of the fixed-size [`region`]s of the heap, whose metadata
+/// collectors share.
+///
+/// [`$2.5.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.5.3
+#[derive(Clone, Default)]
+pub struct Heap {
+    /// The objects by their reference minus one.
+    objects: Vec<HeapObject>,
+    /// The allocation sites of the objects, if they are tracked.
+    sites: Option<sites::AllocationSites>,
+    regions: region::Regions,
+}
+
+#[derive(Clone)]
+pub enum HeapObject {
+    Instance(Instance),
+    Array(Array),
+}
+
+/// An instance of a class, with the storage for its instance fields as laid
+/// out by the [`FieldLayout`] of the class.
+///
+/// The instance refers to its class by name, which identifies the class since
+/// all classes are defined by the bootstrap class loader, and holds on to the
+/// layout, so that the heap can be shared between threads while the classes
+/// stay on the thread that loaded them.
+#[derive(Clone)]
+pub struct Instance {
+    class: Symbol,
+    layout: Arc<FieldLayout>,
+    fields: Box<[u8]>,
+}
+
+/// An array, with its elements stored back to back.
+#[derive(Clone)]
+pub struct Array {
+    component: Arc<FieldType>,
+    length: usize,
+    elements: Box<[u8]>,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocates an instance of the given class, with all fields set to their
+    /// default values, and returns the reference to it.
+    pub fn allocate_instance(&mut self, class: &Class) -> usize {
+        self.allocate_with_layout(class.name().clone(), class.shared_instance_layout().clone())
+    }
+
+    /// Like [`Heap::allocate_instance`], but with the name and the instance
+    /// layout of the class, e.g. as natives get them from
+    /// [`Runtime::instance_layout`](crate::vm::runtime::Runtime::instance_layout).
+    pub fn allocate_with_layout(&mut self, class: Symbol, layout: Arc<FieldLayout>) -> usize {
+        let fields = vec![0; layout.size()].into_boxed_slice();
+        self.push(HeapObject::Instance(Instance {
+            class,
+            layout,
+            fields,
+        }))
+    }
+
+    /// Allocates an array with the given component type and length, with all
+    /// elements set to their default values. Fails with a
+    /// `NegativeArraySizeException` if the length is negative.
+    pub fn allocate_array(
+        &mut self,
+        component: Arc<FieldType>,
+        length: i32,
+    ) -> Result<usize, VmError> {
+        let length = usize::try_from(length).map_err(|_| Exception::NegativeArraySize(length))?;
+        let size = length.checked_mul(component.size()).ok_or_else(|| {
+            Exception::OutOfMemory("Requested array size exceeds VM limit".to_string())
+        })?;
+        let elements = vec![0; size].into_boxed_slice();
+        Ok(self.push(HeapObject::Array(Array {
+            component,
+            length,
+            elements,
+        })))
+    }
+
+    /// Returns the object with the given reference, or [`None`] for [`NULL`]
+    /// and references that don't point to an object.
+    pub fn get(&self, reference: usize) -> Option<&HeapObject> {
+        self.objects.get(reference.checked_sub(1)?)
+    }
+
+    pub fn get_mut(&mut self, reference: usize) -> Option<&mut HeapObject> {
+        self.objects.get_mut(reference.checked_sub(1)?)
+    }
+
+    /// The number of objects on the heap.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Allocates the given object, e.g. one that was restored from a
+    /// snapshot, and returns the reference to it. Objects get consecutive
+    /// references, so restoring the objects of a heap in order into an empty
+    /// heap keeps all of their references.
+    pub(crate) fn restore(&mut self, object: HeapObject) -> usize {
+        self.push(object)
+    }
+
+    fn push(&mut self, object: HeapObject) -> usize {
+        self.regions.place(object.size());
+        self.objects.push(object);
+        self.objects.len()
+    }
+}
+
+impl HeapObject {
+    pub fn as_instance(&self) -> Option<&Instance> {
+        match self {
+            HeapObject::Instance(instance) => Some(instance),
+            HeapObject::Array(_) => None,
+        }
+    }
+
+    pub fn as_instance_mut(&mut self) -> Option<&mut Instance> {
+        match self {
+            HeapObject::Instance(instance) => Some(instance),
+            HeapObject::Array(_) => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Array> {
+        match self {
+            HeapObject::Array(array) => Some(array),
+            HeapObject::Instance(_) => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            HeapObject::Array(array) => Some(array),
+            HeapObject::Instance(_) => None,
+        }
+    }
+
+    /// The binary name of the class of this object, e.g. `java/lang/Object`
+    /// or `[I`.
+    pub fn class_name(&self) -> String {
+        match self {
+            HeapObject::Instance(instance) => instance.class.to_string(),
+            HeapObject::Array(array) => format!("[{}", array.component),
+        }
+    }
+}
+
+impl Instance {
+    /// Creates an instance of the given class with the given storage for its
+    /// fields, as returned by [`Instance::raw_fields`]. Fails if the storage
+    /// doesn't have the size of the instance layout of the class.
+    pub(crate) fn from_raw(class: &Class, fields: Box<[u8]>) -> Result<Self, VmError> {
+        if fields.len() != class.instance_layout().size() {
+            return Err(VmError::Internal(format!(
+                "{} bytes of fields for an instance of {}",
+                fields.len(),
+                class.name()
+            )));
+        }
+        Ok(Self {
+            class: class.name().clone(),
+            layout: class.shared_instance_layout().clone(),
+            fields,
+        })
+    }
+
+    /// The binary name of the class of this instance.
+    pub fn class_name(&self) -> &Symbol {
+        &self.class
+    }
+
+    /// The instance layout of the class of this instance.
+    pub fn layout(&self) -> &FieldLayout {
+        &self.layout
+    }
+
+    /// The storage of the fields of this instance, in the byte order of the
+    /// platform, see [`Array::raw_elements`].
+    pub fn raw_fields(&self) -> &[u8] {
+        &self.fields
+    }
+
+    /// Returns the value of the given field, which must be a field of the
+    /// instance layout of the class of this object.
+    pub fn field(&self, field: &Field) -> NativeValue {
+        read_value(&self.fields[field.offset()..], field.field_type())
+    }
+
+    /// Sets the value of the given field, see [`Instance::field`]. Fails if the
+    /// value doesn't have the type of the field.
+    pub fn set_field(&mut self, field: &Field, value: NativeValue) -> Result<(), VmError> {
+        write_value(
+            &mut self.fields[field.offset()..],
+            field.field_type(),
+            value,
+        )
+    }
+}
+
+impl Array {
+    /// Creates an array with the given component type and elements, as
+    /// returned by [`Array::raw_elements`]. Fails if the elements are not a
+    /// whole number of components.
+    pub(crate) fn from_raw(
+        component: Arc<FieldType>,
+        elements: Box<[u8]>,
+    ) -> Result<Self, VmError> {
+        if !elements.len().is_multiple_of(component.size()) {
+            return Err(VmError::Internal(format!(
+                "{} bytes of elements for an array of {}",
+                elements.len(),
+                component
+            )));
+        }
+        Ok(Self {
+            length: elements.len() / component.size(),
+            component,
+            elements,
+        })
+    }
+
+    pub fn component(&self) -> &Arc<FieldType> {
+        &self.component
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The elements of this array as they are stored, back to back in the byte
+    /// order of the platform, e.g. for intrinsics that copy whole ranges.
+    pub fn raw_elements(&self) -> &[u8] {
+        &self.elements
+    }
+
+    pub fn raw_elements_mut(&mut self) -> &mut [u8] {
+        &mut self.elements
+    }
+
+    /// Returns the element at the given index, failing with an
+    /// `ArrayIndexOutOfBoundsException` if there is none.
+    pub fn element(&self, index: i32) -> Result<NativeValue, VmError> {
+        let offset = self.offset(index)?;
+        Ok(read_value(&self.elements[offset..], &self.component))
+    }
+
+    /// Returns the element at an index that is known to be within the bounds
+    /// of the array, without the check of [`Array::element`], like the loads
+    /// whose bounds check was eliminated by [`Code`](crate::vm::code::Code).
+    pub fn element_unchecked(&self, index: usize) -> NativeValue {
+        read_value(
+            &self.elements[index * self.component.size()..],
+            &self.component,
+        )
+    }
+
+    /// Sets the element at the given index, see [`Array::element`]. Fails if
+    /// the value doesn't have the component type of the array.
+    pub fn set_element(&mut self, index: i32, value: NativeValue) -> Result<(), VmError> {
+        let offset = self.offset(index)?;
+        write_value(&mut self.elements[offset..], &self.component, value)
+    }
+
+    fn offset(&self, index: i32) -> Result<usize, VmError> {
+        match usize::try_from(index) {
+            Ok(i) if i < self.length => Ok(i * self.component.size()),
+            _ => Err(Exception::ArrayIndexOutOfBounds {
+                index,
+                length: self.length as i32,
+            }
+            .into()),
+        }
+    }
+}
+
+/// Reads a value of the given type from the start of `bytes`.
+fn read_value(bytes: &[u8], field_type: &FieldType) -> NativeValue {
+    fn read<const N: usize>(bytes: &[u8]) -> [u8; N] {
+        bytes[..N].try_into().unwrap()
+    }
+
+    match field_type {
+        FieldType::Boolean => NativeValue::Boolean(bytes[0] != 0),
+        FieldType::Byte => NativeValue::Byte(bytes[0] as i8),
+        FieldType::Char => NativeValue::Char(u16::from_ne_bytes(read(bytes))),
+        FieldType::Short => NativeValue::Short(i16::from_ne_bytes(read(bytes))),
+        FieldType::Int => NativeValue::Integer(i32::from_ne_bytes(read(bytes))),
+        FieldType::Float => NativeValue::Float(f32::from_ne_bytes(read(bytes))),
+        FieldType::Long => NativeValue::Long(i64::from_ne_bytes(read(bytes))),
+        FieldType::Double => NativeValue::Double(f64::from_ne_bytes(read(bytes))),
+        FieldType::Object(_) | FieldType::Array(_) => {
+            NativeValue::Reference(usize::from_ne_bytes(read::<REFERENCE_SIZE>(bytes)))
+        }
+    }
+}
+
+/// Writes a value of the given type to the start of `bytes`.
+fn write_value(
+    bytes: &mut [u8],
+    field_type: &FieldType,
+    value: NativeValue,
+) -> Result<(), VmError> {
+    let mut write = |b: &[u8]| bytes[..b.len()].copy_from_slice(b);
+    match (field_type, value) {
+        (FieldType::Boolean, NativeValue::Boolean(v)) => write(&[v as u8]),
+        (FieldType::Byte, NativeValue::Byte(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Char, NativeValue::Char(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Short, NativeValue::Short(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Int, NativeValue::Integer(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Float, NativeValue::Float(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Long, NativeValue::Long(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Double, NativeValue::Double(v)) => write(&v.to_ne_bytes()),
+        (FieldType::Object(_) | FieldType::Array(_), NativeValue::Reference(v)) => {
+            write(&v.to_ne_bytes())
+        }
+        (field_type, value) => {
+            return Err(VmError::Internal(format!(
+                "can't store {:?} in a location of type {}",
+                value, field_type
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_loader;
+    use crate::vm::classloader::ClassLoader;
+    use std::rc::Rc;
+
+    pub(super) fn load_class(name: &str) -> Rc<Class> {
+        class_loader(&[]).find_or_load_class(name).unwrap()
+    }
+
+    #[test]
+    fn test_instance_fields() {
+        let circle = load_class("Circle");
+        let mut heap = Heap::new();
+        let reference = heap.allocate_instance(&circle);
+        assert_ne!(NULL, reference);
+        assert!(heap.get(NULL).is_none());
+
+        let layout = circle.instance_layout();
+        let radius = layout.field("radius", "D").unwrap();
+        let id = layout.field("id", "I").unwrap();
+        let instance = heap.get_mut(reference).unwrap().as_instance_mut().unwrap();
+        assert_eq!(NativeValue::Double(0.0), instance.field(radius));
+        instance
+            .set_field(radius, NativeValue::Double(1.5))
+            .unwrap();
+        instance.set_field(id, NativeValue::Integer(-7)).unwrap();
+        assert_eq!(NativeValue::Double(1.5), instance.field(radius));
+        assert_eq!(NativeValue::Integer(-7), instance.field(id));
+        assert!(instance.set_field(id, NativeValue::Long(1)).is_err());
+        assert_eq!("Circle", heap.get(reference).unwrap().class_name());
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let mut heap = Heap::new();
+        let reference = heap.allocate_array(Arc::new(FieldType::Char), 3).unwrap();
+        let array = heap.get_mut(reference).unwrap().as_array_mut().unwrap();
+        assert_eq!(3, array.len());
+        array.set_element(2, NativeValue::Char('x' as u16)).unwrap();
+        assert_eq!(NativeValue::Char('x' as u16), array.element(2).unwrap());
+        assert_eq!(NativeValue::Char(0), array.element(0).unwrap());
+        assert_eq!(
+            "java/lang/ArrayIndexOutOfBoundsException",
+            array.element(3).unwrap_err().class_name()
+        );
+        assert!(array.element(-1).is_err());
+        assert_eq!("[C", heap.get(reference).unwrap().class_name());
+
+        assert_eq!(
+            "java/lang/NegativeArraySizeException",
+            heap.allocate_array(Arc::new(FieldType::Int), -1)
+                .unwrap_err()
+                .class_name()
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use crate::vm::heap::{Heap, HeapObject};
+use std::collections::{BTreeSet, HashSet};
+
+/// The size of the regions of a [`Heap`] unless another one is set with
+/// [`Heap::with_region_size`].
+pub const DEFAULT_REGION_SIZE: usize = 1024 * 1024;
+
+/// What a [`Region`] holds.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RegionKind {
+    /// Objects that are allocated one after the other until the region is
+    /// full.
+    Regular,
+    /// The first region of a humongous object, i.e. an object of at least half
+    /// the region size, which gets regions of its own so that it is never
+    /// copied.
+    Humongous,
+    /// A further region of the humongous object that starts in the region
+    /// with the given index.
+    HumongousContinuation(usize),
+}
+
+/// A fixed-size part of the heap, with the metadata that collectors need
+/// about it, independent of the collector.
+#[derive(Debug, Clone)]
+pub struct Region {
+    index: usize,
+    kind: RegionKind,
+    /// The bytes that were allocated in the region.
+    used: usize,
+    /// The bytes of the objects in the region that were reachable at the
+    /// last marking, see [`Heap::mark_regions`].
+    live: usize,
+    /// The number of markings that found live objects in the region.
+    age: u8,
+    /// The objects in other regions that may hold references to objects in
+    /// this region.
+    remembered_set: BTreeSet<usize>,
+}
+
+impl Region {
+    fn new(index: usize, kind: RegionKind) -> Self {
+        Self {
+            index,
+            kind,
+            used: 0,
+            live: 0,
+            age: 0,
+            remembered_set: BTreeSet::new(),
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn kind(&self) -> RegionKind {
+        self.kind
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn live_bytes(&self) -> usize {
+        self.live
+    }
+
+    pub fn age(&self) -> u8 {
+        self.age
+    }
+
+    /// The references of the objects in other regions that may point into
+    /// this region, in ascending order. This can contain objects that don't
+    /// point into the region anymore, but never misses one that does.
+    pub fn remembered_set(&self) -> impl Iterator<Item = usize> + '_ {
+        self.remembered_set.iter().copied()
+    }
+}
+
+/// The regions of a heap and the region of every object.
+#[derive(Clone)]
+pub(super) struct Regions {
+    size: usize,
+    regions: Vec<Region>,
+    /// The regular region that objects are allocated in.
+    current: Option<usize>,
+    /// The index of the region of every object, by reference minus one.
+    objects: Vec<usize>,
+}
+
+impl Default for Regions {
+    fn default() -> Self {
+        Self::new(DEFAULT_REGION_SIZE)
+    }
+}
+
+impl Regions {
+    pub(super) fn new(size: usize) -> Self {
+        assert!(size > 0, "the region size must not be 0");
+        Self {
+            size,
+            regions: vec![],
+            current: None,
+            objects: vec![],
+        }
+    }
+
+    /// Places the next object, which has the given size, in a region.
+    pub(super) fn place(&mut self, size: usize) {
+        let index = if size >= self.size / 2 {
+            self.place_humongous(size)
+        } else {
+            let index = match self.current {
+                Some(index) if self.regions[index].used + size <= self.size => index,
+                _ => {
+                    let index = self.add(RegionKind::Regular);
+                    self.current = Some(index);
+                    index
+                }
+            };
+            self.regions[index].used += size;
+            index
+        };
+        self.objects.push(index);
+    }
+
+    fn place_humongous(&mut self, size: usize) -> usize {
+        let start = self.add(RegionKind::Humongous);
+        let count = size.div_ceil(self.size).max(1);
+        for _ in 1..count {
+            self.add(RegionKind::HumongousContinuation(start));
+        }
+        // the used bytes of the continuations count for the first region
+        self.regions[start].used = size;
+        start
+    }
+
+    fn add(&mut self, kind: RegionKind) -> usize {
+        let index = self.regions.len();
+        self.regions.push(Region::new(index, kind));
+        index
+    }
+
+    fn of(&self, reference: usize) -> Option<usize> {
+        self.objects.get(reference.checked_sub(1)?).copied()
+    }
+}
+
+impl Heap {
+    /// Creates an empty heap whose regions have the given size in bytes.
+    pub fn with_region_size(size: usize) -> Self {
+        Self {
+            regions: Regions::new(size),
+            ..Self::default()
+        }
+    }
+
+    pub fn region_size(&self) -> usize {
+        self.regions.size
+    }
+
+    /// The regions of the heap, by their index.
+    pub fn regions(&self) -> &[Region] {
+        &self.regions.regions
+    }
+
+    /// The region of the object with the given reference. For a humongous
+    /// object, this is the first of its regions.
+    pub fn region_of(&self, reference: usize) -> Option<&Region> {
+        self.regions
+            .of(reference)
+            .map(|index| &self.regions.regions[index])
+    }
+
+    /// The write barrier: records that the given object now holds a
+    /// reference to the other object, which has to be called whenever a
+    /// reference is stored into a field or an element.
+    pub fn record_reference_store(&mut self, holder: usize, value: usize) {
+        let (Some(from), Some(to)) = (self.regions.of(holder), self.regions.of(value)) else {
+            return;
+        };
+        if from != to {
+            self.regions.regions[to].remembered_set.insert(holder);
+        }
+    }
+
+    /// Rebuilds the remembered sets of all regions from the references that
+    /// the objects hold, e.g. after objects were restored from a snapshot
+    /// without the write barrier. This drops the stale entries too.
+    pub fn rebuild_remembered_sets(&mut self) {
+        for region in &mut self.regions.regions {
+            region.remembered_set.clear();
+        }
+        for holder in 1..=self.len() {
+            for value in self.references(holder) {
+                self.record_reference_store(holder, value);
+            }
+        }
+    }
+
+    /// Marks the objects that are reachable from the given roots and updates
+    /// the live bytes of every region, and the age of every region with live
+    /// objects, like the marking phase of a collection.
+    pub fn mark_regions(&mut self, roots: impl IntoIterator<Item = usize>) {
+        let mut live = HashSet::new();
+        for root in roots {
+            if !live.contains(&root) {
+                live.extend(self.reachable(root));
+            }
+        }
+        let mut live_bytes = vec![0; self.regions.regions.len()];
+        for reference in live {
+            if let (Some(index), Some(object)) = (self.regions.of(reference), self.get(reference)) {
+                live_bytes[index] += object.size();
+            }
+        }
+        for (region, live) in self.regions.regions.iter_mut().zip(live_bytes) {
+            region.live = live;
+            if live > 0 {
+                region.age = region.age.saturating_add(1);
+            }
+        }
+    }
+}
+
+impl HeapObject {
+    /// The size of the fields or elements of the object in bytes, which is
+    /// what the object takes up in its region.
+    pub fn size(&self) -> usize {
+        match self {
+            HeapObject::Instance(instance) => instance.raw_fields().len(),
+            HeapObject::Array(array) => array.raw_elements().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::descriptor::FieldType;
+    use crate::vm::heap::tests::load_class;
+    use crate::vm::heap::NULL;
+    use crate::vm::types::NativeValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_regions() {
+        let circle = load_class("Circle");
+        let size = circle.instance_layout().size();
+        let mut heap = Heap::with_region_size(4 * size);
+        let objects: Vec<usize> = (0..5).map(|_| heap.allocate_instance(&circle)).collect();
+        // half the region size or more makes an array humongous
+        let small = heap
+            .allocate_array(Arc::new(FieldType::Byte), (2 * size - 1) as i32)
+            .unwrap();
+        let humongous = heap
+            .allocate_array(Arc::new(FieldType::Byte), (9 * size) as i32)
+            .unwrap();
+
+        let kinds: Vec<RegionKind> = heap.regions().iter().map(Region::kind).collect();
+        assert_eq!(
+            vec![
+                RegionKind::Regular,
+                RegionKind::Regular,
+                RegionKind::Humongous,
+                RegionKind::HumongousContinuation(2),
+                RegionKind::HumongousContinuation(2),
+            ],
+            kinds
+        );
+        assert_eq!(0, heap.region_of(objects[3]).unwrap().index());
+        assert_eq!(1, heap.region_of(objects[4]).unwrap().index());
+        assert_eq!(1, heap.region_of(small).unwrap().index());
+        assert_eq!(2, heap.region_of(humongous).unwrap().index());
+        assert_eq!(4 * size, heap.regions()[0].used_bytes());
+        assert_eq!(3 * size - 1, heap.regions()[1].used_bytes());
+        assert_eq!(9 * size, heap.regions()[2].used_bytes());
+        assert!(heap.region_of(NULL).is_none());
+    }
+
+    #[test]
+    fn test_remembered_sets_and_marking() {
+        let circle = load_class("Circle");
+        let size = circle.instance_layout().size();
+        // two instances per region, each less than half of it
+        let mut heap = Heap::with_region_size(2 * size + 2);
+        let (a, b, c) = (
+            heap.allocate_instance(&circle),
+            heap.allocate_instance(&circle),
+            heap.allocate_instance(&circle),
+        );
+        let label = circle
+            .instance_layout()
+            .field("label", "Ljava/lang/Object;")
+            .unwrap();
+        // a.label = b in the same region, c.label = a across regions
+        for (holder, value) in [(a, b), (c, a)] {
+            let instance = heap.get_mut(holder).unwrap().as_instance_mut().unwrap();
+            instance
+                .set_field(label, NativeValue::Reference(value))
+                .unwrap();
+            heap.record_reference_store(holder, value);
+        }
+        assert_eq!(
+            vec![c],
+            heap.regions()[0].remembered_set().collect::<Vec<_>>()
+        );
+        assert_eq!(0, heap.regions()[1].remembered_set().count());
+
+        // c.label = null leaves a stale entry until the sets are rebuilt
+        let instance = heap.get_mut(c).unwrap().as_instance_mut().unwrap();
+        instance
+            .set_field(label, NativeValue::Reference(NULL))
+            .unwrap();
+        heap.rebuild_remembered_sets();
+        assert_eq!(0, heap.regions()[0].remembered_set().count());
+
+        heap.mark_regions([a]);
+        assert_eq!(2 * size, heap.regions()[0].live_bytes());
+        assert_eq!(0, heap.regions()[1].live_bytes());
+        heap.mark_regions([a, c]);
+        assert_eq!(size, heap.regions()[1].live_bytes());
+        assert_eq!(
+            vec![2, 1],
+            heap.regions().iter().map(Region::age).collect::<Vec<_>>()
+        );
+    }
+}
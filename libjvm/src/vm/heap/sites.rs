@@ -0,0 +1,250 @@
+use crate::vm::classloader::class::{Class, Method};
+use crate::vm::heap::Heap;
+use crate::vm::symbol::Symbol;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+
+/// An instruction that allocates objects, like `new` or `newarray`, by its
+/// method and offset.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AllocationSite {
+    class: Symbol,
+    name: Symbol,
+    descriptor: Symbol,
+    pc: usize,
+}
+
+impl AllocationSite {
+    /// The instruction at the given offset of the code of the given method of
+    /// the given class.
+    pub fn new(class: &Class, method: &Method, pc: usize) -> Self {
+        Self {
+            class: class.name().clone(),
+            name: method.name().clone(),
+            descriptor: method.descriptor().clone(),
+            pc,
+        }
+    }
+
+    /// The binary name of the class that declares the method.
+    pub fn class(&self) -> &Symbol {
+        &self.class
+    }
+
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &Symbol {
+        &self.descriptor
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+}
+
+/// Writes the site like `java.util.ArrayList.grow(I)[Ljava/lang/Object; @ 14`.
+impl Display for AllocationSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}{} @ {}",
+            self.class.replace('/', "."),
+            self.name,
+            self.descriptor,
+            self.pc
+        )
+    }
+}
+
+/// The allocation sites of the objects of a heap, see
+/// [`Heap::track_allocation_sites`]. Every site is stored once.
+#[derive(Clone, Default)]
+pub(super) struct AllocationSites {
+    sites: Vec<AllocationSite>,
+    ids: HashMap<AllocationSite, usize>,
+    /// The index of the site of every tagged object, by reference.
+    objects: HashMap<usize, usize>,
+}
+
+/// The live objects that were allocated at a site, see
+/// [`Heap::live_objects_by_site`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SiteStats {
+    pub site: AllocationSite,
+    pub objects: usize,
+    /// The sum of the sizes of the fields or elements of the objects.
+    pub bytes: usize,
+}
+
+impl Heap {
+    /// Starts recording the allocation sites of objects, a diagnostic mode to
+    /// find out where the objects that a program leaks come from. Objects
+    /// that were allocated before have no site.
+    pub fn track_allocation_sites(&mut self) {
+        self.sites.get_or_insert_with(AllocationSites::default);
+    }
+
+    pub fn is_tracking_allocation_sites(&self) -> bool {
+        self.sites.is_some()
+    }
+
+    /// Records that the object with the given reference was allocated at the
+    /// given site, which the interpreter does right after allocating it. Does
+    /// nothing unless allocation sites are tracked.
+    pub fn record_allocation_site(&mut self, reference: usize, site: &AllocationSite) {
+        let Some(sites) = &mut self.sites else {
+            return;
+        };
+        let id = match sites.ids.get(site) {
+            Some(id) => *id,
+            None => {
+                sites.sites.push(site.clone());
+                sites.ids.insert(site.clone(), sites.sites.len() - 1);
+                sites.sites.len() - 1
+            }
+        };
+        sites.objects.insert(reference, id);
+    }
+
+    /// The site where the object with the given reference was allocated, if
+    /// it was recorded.
+    pub fn allocation_site(&self, reference: usize) -> Option<&AllocationSite> {
+        let sites = self.sites.as_ref()?;
+        sites.objects.get(&reference).map(|id| &sites.sites[*id])
+    }
+
+    /// Counts the live objects by the site where they were allocated, most
+    /// bytes first. Live objects are those reachable from the given roots,
+    /// i.e. those that a collection would keep. Objects without a recorded
+    /// site are left out.
+    pub fn live_objects_by_site(&self, roots: impl IntoIterator<Item = usize>) -> Vec<SiteStats> {
+        let Some(sites) = &self.sites else {
+            return vec![];
+        };
+        let mut live = HashSet::new();
+        for root in roots {
+            if !live.contains(&root) {
+                live.extend(self.reachable(root));
+            }
+        }
+
+        let mut stats: HashMap<usize, SiteStats> = HashMap::new();
+        for reference in live {
+            let (Some(id), Some(object)) = (sites.objects.get(&reference), self.get(reference))
+            else {
+                continue;
+            };
+            let entry = stats.entry(*id).or_insert_with(|| SiteStats {
+                site: sites.sites[*id].clone(),
+                objects: 0,
+                bytes: 0,
+            });
+            entry.objects += 1;
+            entry.bytes += object.size();
+        }
+        let mut stats = stats.into_iter().collect::<Vec<_>>();
+        // ties are in the order the sites were first seen
+        stats.sort_by(|(a_id, a), (b_id, b)| {
+            (b.bytes, b.objects, a_id).cmp(&(a.bytes, a.objects, b_id))
+        });
+        stats.into_iter().map(|(_, stats)| stats).collect()
+    }
+
+    /// Writes the live objects by the site where they were allocated, see
+    /// [`Heap::live_objects_by_site`], as a table like
+    ///
+    /// ```text
+    ///  objects      bytes  site
+    ///       12        384  Cache.put(Ljava/lang/Object;)V @ 9
+    /// ```
+    pub fn write_allocation_report(
+        &self,
+        roots: impl IntoIterator<Item = usize>,
+        out: &mut impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(out, "{:>8} {:>10}  site", "objects", "bytes")?;
+        for stats in self.live_objects_by_site(roots) {
+            writeln!(
+                out,
+                "{:>8} {:>10}  {}",
+                stats.objects, stats.bytes, stats.site
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::descriptor::FieldType;
+    use crate::vm::heap::tests::load_class;
+    use crate::vm::types::NativeValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_live_objects_by_site() {
+        let circle = load_class("Circle");
+        let method = circle.methods()[0];
+        let (first, second) = (
+            AllocationSite::new(&circle, method, 0),
+            AllocationSite::new(&circle, method, 8),
+        );
+        let mut heap = Heap::new();
+        let untracked = heap.allocate_instance(&circle);
+        heap.record_allocation_site(untracked, &first);
+        assert_eq!(None, heap.allocation_site(untracked));
+
+        heap.track_allocation_sites();
+        let a = heap.allocate_instance(&circle);
+        heap.record_allocation_site(a, &first);
+        let b = heap.allocate_instance(&circle);
+        heap.record_allocation_site(b, &first);
+        let leaked = heap.allocate_instance(&circle);
+        heap.record_allocation_site(leaked, &first);
+        let array = heap.allocate_array(Arc::new(FieldType::Long), 16).unwrap();
+        heap.record_allocation_site(array, &second);
+        assert_eq!(Some(&second), heap.allocation_site(array));
+
+        // a.label = b, and the root holds a and the array
+        let label = circle
+            .instance_layout()
+            .field("label", "Ljava/lang/Object;")
+            .unwrap();
+        let instance = heap.get_mut(a).unwrap().as_instance_mut().unwrap();
+        instance
+            .set_field(label, NativeValue::Reference(b))
+            .unwrap();
+        let size = circle.instance_layout().size();
+        assert_eq!(
+            vec![
+                SiteStats {
+                    site: second.clone(),
+                    objects: 1,
+                    bytes: 128,
+                },
+                SiteStats {
+                    site: first.clone(),
+                    objects: 2,
+                    bytes: 2 * size,
+                },
+            ],
+            heap.live_objects_by_site([a, array, untracked])
+        );
+
+        let mut report = vec![];
+        heap.write_allocation_report([leaked], &mut report).unwrap();
+        assert_eq!(
+            format!(
+                " objects      bytes  site\n       1 {:>10}  Circle.{}{} @ 0\n",
+                size,
+                method.name(),
+                method.descriptor()
+            ),
+            String::from_utf8(report).unwrap()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use crate::vm::classloader::class::{Class, Method};
+use crate::vm::error::LinkageError;
+use libjava::bytecode::Op;
+use libjava::classfile::{AttributeInfo, ExceptionTableEntry};
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+/// How a method violates structured locking.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LockingViolationKind {
+    /// A `monitorexit` on a path that holds no monitor that the method
+    /// entered.
+    UnmatchedExit,
+    /// A return on a path that still holds the given number of monitors.
+    HeldAtReturn(usize),
+    /// An instruction that may throw an exception that no handler of the
+    /// method catches, on a path that holds the given number of monitors.
+    HeldAtThrow(usize),
+    /// Two paths that hold the given, different numbers of monitors reach the
+    /// same instruction.
+    InconsistentMerge(usize, usize),
+}
+
+/// A violation of structured locking, found by [`check`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LockingViolation {
+    /// The offset of the instruction where the violation was found.
+    pub pc: usize,
+    pub kind: LockingViolationKind,
+}
+
+impl Display for LockingViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            LockingViolationKind::UnmatchedExit => {
+                write!(f, "monitorexit without a matching monitorenter")?
+            }
+            LockingViolationKind::HeldAtReturn(held) => {
+                write!(f, "return with {} monitor(s) held", held)?
+            }
+            LockingViolationKind::HeldAtThrow(held) => {
+                write!(f, "uncaught exception with {} monitor(s) held", held)?
+            }
+            LockingViolationKind::InconsistentMerge(a, b) => {
+                write!(f, "paths with {} and {} monitor(s) held merge", a, b)?
+            }
+        }
+        write!(f, " at pc {}", self.pc)
+    }
+}
+
+/// Checks that the given code locks structurally: every `monitorenter` is
+/// matched by a `monitorexit` on every path through the method, including the
+/// paths of exceptions that the exception handlers catch and of exceptions
+/// that leave the method, and no `monitorexit` exits a monitor that the
+/// method didn't enter. The monitor of a synchronized method is not part of
+/// the code, and not checked.
+///
+/// Only the number of held monitors is tracked, not which objects they belong
+/// to, like javac balances every `monitorenter` on the same object. An
+/// exception goes to the first handler that covers the instruction, up to the
+/// first one that catches all exceptions, so that every instruction that may
+/// throw while a monitor is held has to be covered by such a handler.
+/// Asynchronous exceptions and errors of the VM, like a `StackOverflowError`,
+/// are not considered.
+///
+/// Code that can't be checked, like malformed code or code with subroutines
+/// (`jsr` and `ret`), is assumed to be correct.
+pub fn check(code: &[u8], exception_table: &[ExceptionTableEntry]) -> Result<(), LockingViolation> {
+    match decode(code) {
+        Some(ops) => run(&ops, exception_table),
+        None => Ok(()),
+    }
+}
+
+fn decode(code: &[u8]) -> Option<Vec<(usize, Op)>> {
+    let mut ops = vec![];
+    let mut cursor = Cursor::new(code);
+    while (cursor.position() as usize) < code.len() {
+        let offset = cursor.position() as usize;
+        match Op::parse_at(&mut cursor, offset).ok()? {
+            Op::Jsr(_) | Op::JsrW(_) | Op::Ret(_) | Op::Wide => return None,
+            op => ops.push((offset, op)),
+        }
+    }
+    Some(ops)
+}
+
+/// Computes the number of held monitors before every instruction, and stops
+/// at the first violation. Returns `Ok` as well if the code can't be checked.
+fn run(
+    ops: &[(usize, Op)],
+    exception_table: &[ExceptionTableEntry],
+) -> Result<(), LockingViolation> {
+    let index_of = |pc: usize| ops.binary_search_by_key(&pc, |(o, _)| *o).ok();
+    let mut held: Vec<Option<usize>> = vec![None; ops.len()];
+    let mut pending = vec![];
+    if !ops.is_empty() {
+        held[0] = Some(0);
+        pending.push(0);
+    }
+    while let Some(i) = pending.pop() {
+        let (pc, op) = &ops[i];
+        let before = held[i].expect("pending instructions have a state");
+        let violation = |kind| Err(LockingViolation { pc: *pc, kind });
+
+        let mut successors = vec![];
+        if may_throw(op) {
+            let mut caught = false;
+            for entry in exception_table {
+                if (entry.start_pc() as usize..entry.end_pc() as usize).contains(pc) {
+                    successors.push((entry.handler_pc() as usize, before));
+                    if entry.catch_type() == 0 {
+                        caught = true;
+                        break;
+                    }
+                }
+            }
+            if !caught && before > 0 {
+                return violation(LockingViolationKind::HeldAtThrow(before));
+            }
+        }
+
+        let after = match op {
+            Op::MonitorEnter => before + 1,
+            Op::MonitorExit if before == 0 => {
+                return violation(LockingViolationKind::UnmatchedExit)
+            }
+            Op::MonitorExit => before - 1,
+            Op::Return | Op::AReturn | Op::IReturn | Op::LReturn | Op::FReturn | Op::DReturn
+                if before > 0 =>
+            {
+                return violation(LockingViolationKind::HeldAtReturn(before))
+            }
+            _ => before,
+        };
+        for target in op.branch_targets(*pc) {
+            successors.push((target, after));
+        }
+        if op.falls_through() {
+            match ops.get(i + 1) {
+                Some((next, _)) => successors.push((*next, after)),
+                None => return Ok(()),
+            }
+        }
+
+        for (target, count) in successors {
+            let Some(successor) = index_of(target) else {
+                return Ok(());
+            };
+            match held[successor] {
+                None => {
+                    held[successor] = Some(count);
+                    pending.push(successor);
+                }
+                Some(existing) if existing != count => {
+                    return Err(LockingViolation {
+                        pc: target,
+                        kind: LockingViolationKind::InconsistentMerge(existing, count),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the instruction may throw an exception, other than an
+/// asynchronous one. Returns are checked separately.
+fn may_throw(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::AALoad
+            | Op::AAStore
+            | Op::BALoad
+            | Op::BAStore
+            | Op::CALoad
+            | Op::CAStore
+            | Op::SALoad
+            | Op::SAStore
+            | Op::IALoad
+            | Op::IAStore
+            | Op::LALoad
+            | Op::LAStore
+            | Op::FALoad
+            | Op::FAStore
+            | Op::DALoad
+            | Op::DAStore
+            | Op::ArrayLength
+            | Op::AThrow
+            | Op::CheckCast(_)
+            | Op::InstanceOf(_)
+            | Op::GetField(_)
+            | Op::PutField(_)
+            | Op::GetStatic(_)
+            | Op::PutStatic(_)
+            | Op::InvokeDynamic(_)
+            | Op::InvokeInterface(_, _)
+            | Op::InvokeSpecial(_)
+            | Op::InvokeStatic(_)
+            | Op::InvokeVirtual(_)
+            | Op::New(_)
+            | Op::NewArray(_)
+            | Op::ANewArray(_)
+            | Op::MultiANewArray(_, _)
+            | Op::IDiv
+            | Op::IRem
+            | Op::LDiv
+            | Op::LRem
+            | Op::LDC(_)
+            | Op::LDCW(_)
+            | Op::LDC2W(_)
+            | Op::MonitorEnter
+            | Op::MonitorExit
+    )
+}
+
+/// Checks the structured locking of every method of the given class that has
+/// code, see [`check`], and returns the methods that violate it, in the order
+/// of the class file.
+pub fn violations(class: &Class) -> Vec<(&Method, LockingViolation)> {
+    class
+        .methods()
+        .into_iter()
+        .filter_map(|method| {
+            let violation =
+                class
+                    .method_info(method)
+                    .attributes()
+                    .iter()
+                    .find_map(|a| match a {
+                        AttributeInfo::Code {
+                            code,
+                            exception_table,
+                            ..
+                        } => check(code, exception_table).err(),
+                        _ => None,
+                    })?;
+            Some((method, violation))
+        })
+        .collect()
+}
+
+/// Fails with a `VerifyError` naming the first method of the given class that
+/// violates structured locking, see [`violations`].
+pub fn verify(class: &Class) -> Result<(), LinkageError> {
+    match violations(class).first() {
+        None => Ok(()),
+        Some((method, violation)) => Err(LinkageError::Verify(format!(
+            "method {}.{}{} violates structured locking: {}",
+            class.name().replace('/', "."),
+            method.name(),
+            method.descriptor(),
+            violation
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_path;
+    use crate::vm::VM;
+    use libjava::bytecode::assemble;
+    use libvfs::FileSystem;
+    use LockingViolationKind::*;
+
+    /// A handler for all exceptions.
+    fn any(start_pc: u16, end_pc: u16, handler_pc: u16) -> ExceptionTableEntry {
+        let bytes = [start_pc, end_pc, handler_pc, 0].map(u16::to_be_bytes);
+        ExceptionTableEntry::parse(&mut bytes.concat().as_slice()).unwrap()
+    }
+
+    fn violation(pc: usize, kind: LockingViolationKind) -> Result<(), LockingViolation> {
+        Err(LockingViolation { pc, kind })
+    }
+
+    #[test]
+    fn test_javac() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&["locking"]));
+        let locks = vm.for_name("Locks").unwrap();
+        assert!(violations(&locks).is_empty());
+        assert_eq!(Ok(()), verify(&locks));
+    }
+
+    #[test]
+    fn test_violations() {
+        // aload_0, monitorexit, return
+        let code = assemble(&[Op::ALoad(0), Op::MonitorExit, Op::Return]);
+        assert_eq!(violation(1, UnmatchedExit), check(&code, &[]));
+
+        // aload_0, monitorenter, return
+        let code = assemble(&[Op::ALoad(0), Op::MonitorEnter, Op::Return]);
+        assert_eq!(violation(2, HeldAtReturn(1)), check(&code, &[]));
+
+        // the exit of the exceptional path is missing
+        let code = assemble(&[
+            Op::ALoad(0),
+            Op::MonitorEnter,
+            Op::ALoad(0),
+            Op::ArrayLength,
+            Op::Pop,
+            Op::ALoad(0),
+            Op::MonitorExit,
+            Op::Return,
+        ]);
+        assert_eq!(violation(3, HeldAtThrow(1)), check(&code, &[]));
+        // the handler doesn't exit the monitor either
+        let code = [code, assemble(&[Op::AStore(1), Op::ALoad(1), Op::AThrow])].concat();
+        assert_eq!(violation(10, HeldAtThrow(1)), check(&code, &[any(2, 7, 8)]));
+
+        // only one branch enters the monitor
+        let code = assemble(&[
+            Op::ILoad(1),
+            Op::IfEq(5),
+            Op::ALoad(0),
+            Op::MonitorEnter,
+            Op::Return,
+        ]);
+        assert_eq!(violation(6, InconsistentMerge(0, 1)), check(&code, &[]));
+
+        let code = assemble(&[
+            Op::ALoad(0),
+            Op::MonitorEnter,
+            Op::ALoad(0),
+            Op::MonitorExit,
+            Op::Return,
+        ]);
+        // monitorexit may throw as well
+        assert_eq!(violation(3, HeldAtThrow(1)), check(&code, &[]));
+        let code = [
+            code,
+            assemble(&[
+                Op::AStore(1),
+                Op::ALoad(0),
+                Op::MonitorExit,
+                Op::ALoad(1),
+                Op::AThrow,
+            ]),
+        ]
+        .concat();
+        assert_eq!(Ok(()), check(&code, &[any(2, 4, 5), any(5, 8, 5)]));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // jsr +3, return, astore_0, ret 0
+        let code = [0xA8, 0x00, 0x04, 0xB1, 0x4B, 0xA9, 0x00];
+        assert_eq!(Ok(()), check(&code, &[]));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::ClassLoader;
+use crate::vm::descriptor::MethodDescriptor;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::Heap;
+use crate::vm::symbol::Symbol;
+use crate::vm::VM;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The `java.lang.invoke.MethodType` objects of a VM, one per method
+/// descriptor, like the intern table of `MethodType` in the class library.
+///
+/// Method handles, call sites of `invokedynamic` and reflection all obtain
+/// their method types from this table, so method types with the same
+/// descriptor are the same object and can be compared by reference. Each
+/// object is backed by the [`MethodDescriptor`] of the VM's descriptor table,
+/// which the VM uses instead of the fields of the object.
+#[derive(Clone, Default)]
+pub struct MethodTypes {
+    by_descriptor: HashMap<Symbol, usize>,
+    descriptors: HashMap<usize, Arc<MethodDescriptor>>,
+}
+
+impl MethodTypes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the method type object of the given descriptor, allocating it
+    /// on the heap on the first call. `method_descriptor` must be the parsed
+    /// `descriptor`, and `method_type_class` the class
+    /// `java/lang/invoke/MethodType`.
+    pub fn get_or_create(
+        &mut self,
+        descriptor: &Symbol,
+        method_descriptor: Arc<MethodDescriptor>,
+        heap: &mut Heap,
+        method_type_class: Rc<Class>,
+    ) -> usize {
+        if let Some(method_type) = self.by_descriptor.get(descriptor) {
+            return *method_type;
+        }
+        let method_type = heap.allocate_instance(&method_type_class);
+        self.by_descriptor.insert(descriptor.clone(), method_type);
+        self.descriptors.insert(method_type, method_descriptor);
+        method_type
+    }
+
+    /// Returns the descriptor of the given method type object, or [`None`] if
+    /// it is not a method type of this table.
+    pub fn descriptor(&self, method_type: usize) -> Option<&Arc<MethodDescriptor>> {
+        self.descriptors.get(&method_type)
+    }
+
+    /// The number of method types that were created so far.
+    pub fn len(&self) -> usize {
+        self.by_descriptor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl VM {
+    /// Returns the `java.lang.invoke.MethodType` object of the method
+    /// descriptor, e.g. `(ILjava/lang/String;)V`, like
+    /// `MethodType.fromMethodDescriptorString`. Fails with an
+    /// `IllegalArgumentException` if the descriptor is invalid.
+    pub fn method_type(&mut self, descriptor: &str) -> Result<usize, VmError> {
+        // before locking the method area, which loading a class writes to
+        let method_type_class = self
+            .bootstrap_class_loader
+            .find_or_load_class("java/lang/invoke/MethodType")?;
+        let method_area = self.method_area.read().unwrap();
+        let (symbols, descriptors) = (method_area.symbols(), method_area.descriptors());
+        let invalid =
+            || Exception::IllegalArgument(format!("not a method descriptor: {}", descriptor));
+        // only valid descriptors are interned, see SymbolTable::lookup
+        let symbol = match symbols.lookup(descriptor) {
+            Some(symbol) => symbol,
+            None => {
+                MethodDescriptor::parse(descriptor, symbols).ok_or_else(invalid)?;
+                symbols.intern(descriptor)
+            }
+        };
+        let method_descriptor = descriptors
+            .method_descriptor(&symbol, symbols)
+            .ok_or_else(invalid)?;
+        let mut heap = self.heap.write().unwrap();
+        Ok(self.method_types.get_or_create(
+            &symbol,
+            method_descriptor,
+            &mut heap,
+            method_type_class,
+        ))
+    }
+
+    /// Returns the descriptor of the given `MethodType` object, or [`None`]
+    /// if it was not created by [`VM::method_type`].
+    pub fn method_type_descriptor(&self, method_type: usize) -> Option<Arc<MethodDescriptor>> {
+        self.method_types.descriptor(method_type).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_path;
+    use crate::vm::heap::NULL;
+    use libvfs::FileSystem;
+
+    fn vm() -> VM {
+        VM::new(FileSystem::new_os_fs(), class_path(&[]))
+    }
+
+    #[test]
+    fn test_method_types_are_interned() {
+        let mut vm = vm();
+        let method_type = vm.method_type("(ILjava/lang/String;)V").unwrap();
+        assert_eq!(
+            "java/lang/invoke/MethodType",
+            vm.heap()
+                .read()
+                .unwrap()
+                .get(method_type)
+                .unwrap()
+                .class_name()
+        );
+        assert_eq!(
+            method_type,
+            vm.method_type(&String::from("(ILjava/lang/String;)V"))
+                .unwrap()
+        );
+        let other = vm.method_type("()Ljava/lang/String;").unwrap();
+        assert_ne!(method_type, other);
+
+        // the descriptors are shared with the classes that use them
+        let descriptor = vm.method_type_descriptor(method_type).unwrap();
+        assert_eq!("(ILjava/lang/String;)V", descriptor.to_string());
+        let method_area = vm.method_area.read().unwrap();
+        let symbols = method_area.symbols();
+        assert!(Arc::ptr_eq(
+            &descriptor,
+            &method_area
+                .descriptors()
+                .method_descriptor(&symbols.intern("(ILjava/lang/String;)V"), symbols)
+                .unwrap()
+        ));
+        assert_eq!(None, vm.method_type_descriptor(NULL));
+    }
+
+    #[test]
+    fn test_invalid_method_type() {
+        let mut vm = vm();
+        for descriptor in ["", "(V)V", "(I", "(I)Lnone"] {
+            assert_eq!(
+                "java/lang/IllegalArgumentException",
+                vm.method_type(descriptor).unwrap_err().class_name()
+            );
+            assert!(vm
+                .method_area
+                .read()
+                .unwrap()
+                .symbols()
+                .lookup(descriptor)
+                .is_none());
+        }
+        assert!(vm.method_types.is_empty());
+    }
+}
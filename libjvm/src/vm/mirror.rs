@@ -0,0 +1,287 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::layout::{Field, FieldLayout};
+use crate::vm::descriptor::FieldType;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::{Heap, NULL};
+use crate::vm::types::NativeValue;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The primitive types and `void`, which have a `java.lang.Class` object like
+/// `int.class`, but no class file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PrimitiveType {
+    Boolean,
+    Byte,
+    Char,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    Void,
+}
+
+impl PrimitiveType {
+    pub const ALL: [PrimitiveType; 9] = [
+        PrimitiveType::Boolean,
+        PrimitiveType::Byte,
+        PrimitiveType::Char,
+        PrimitiveType::Short,
+        PrimitiveType::Int,
+        PrimitiveType::Long,
+        PrimitiveType::Float,
+        PrimitiveType::Double,
+        PrimitiveType::Void,
+    ];
+
+    /// Returns the type with the given name as used in Java source, e.g.
+    /// `int`, which is what `Class.getPrimitiveClass` is called with.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// Returns the primitive type that the given wrapper class boxes, e.g.
+    /// [`PrimitiveType::Int`] for `java/lang/Integer`. `java/lang/Void` has no
+    /// instances, so it is not considered a wrapper.
+    pub fn from_wrapper_class(class_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|t| *t != PrimitiveType::Void && t.wrapper_class() == class_name)
+    }
+
+    /// Returns the type of the given value, or [`None`] if it is not a value
+    /// of a primitive type.
+    pub fn of(value: &NativeValue) -> Option<Self> {
+        Some(match value {
+            NativeValue::Boolean(_) => PrimitiveType::Boolean,
+            NativeValue::Byte(_) => PrimitiveType::Byte,
+            NativeValue::Char(_) => PrimitiveType::Char,
+            NativeValue::Short(_) => PrimitiveType::Short,
+            NativeValue::Integer(_) => PrimitiveType::Int,
+            NativeValue::Long(_) => PrimitiveType::Long,
+            NativeValue::Float(_) => PrimitiveType::Float,
+            NativeValue::Double(_) => PrimitiveType::Double,
+            NativeValue::Reference(_) | NativeValue::ReturnAddress(_) => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimitiveType::Boolean => "boolean",
+            PrimitiveType::Byte => "byte",
+            PrimitiveType::Char => "char",
+            PrimitiveType::Short => "short",
+            PrimitiveType::Int => "int",
+            PrimitiveType::Long => "long",
+            PrimitiveType::Float => "float",
+            PrimitiveType::Double => "double",
+            PrimitiveType::Void => "void",
+        }
+    }
+
+    /// The binary name of the class that boxes values of this type, e.g.
+    /// `java/lang/Integer`.
+    pub fn wrapper_class(self) -> &'static str {
+        match self {
+            PrimitiveType::Boolean => "java/lang/Boolean",
+            PrimitiveType::Byte => "java/lang/Byte",
+            PrimitiveType::Char => "java/lang/Character",
+            PrimitiveType::Short => "java/lang/Short",
+            PrimitiveType::Int => "java/lang/Integer",
+            PrimitiveType::Long => "java/lang/Long",
+            PrimitiveType::Float => "java/lang/Float",
+            PrimitiveType::Double => "java/lang/Double",
+            PrimitiveType::Void => "java/lang/Void",
+        }
+    }
+
+    /// The type of fields of this type, or [`None`] for `void`.
+    pub fn field_type(self) -> Option<FieldType> {
+        Some(match self {
+            PrimitiveType::Boolean => FieldType::Boolean,
+            PrimitiveType::Byte => FieldType::Byte,
+            PrimitiveType::Char => FieldType::Char,
+            PrimitiveType::Short => FieldType::Short,
+            PrimitiveType::Int => FieldType::Int,
+            PrimitiveType::Long => FieldType::Long,
+            PrimitiveType::Float => FieldType::Float,
+            PrimitiveType::Double => FieldType::Double,
+            PrimitiveType::Void => return None,
+        })
+    }
+}
+
+/// The `java.lang.Class` objects of the primitive types, which are created
+/// once per VM when they are first requested.
+#[derive(Clone, Default)]
+pub struct PrimitiveClasses {
+    mirrors: HashMap<PrimitiveType, usize>,
+}
+
+impl PrimitiveClasses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the class object of the given type, allocating it on the heap
+    /// on the first call. `class_class` must be the class `java/lang/Class`.
+    pub fn get_or_create(
+        &mut self,
+        primitive_type: PrimitiveType,
+        heap: &mut Heap,
+        class_class: Rc<Class>,
+    ) -> usize {
+        *self
+            .mirrors
+            .entry(primitive_type)
+            .or_insert_with(|| heap.allocate_instance(&class_class))
+    }
+
+    /// The class objects that were created so far, e.g. to snapshot them.
+    pub fn mirrors(&self) -> impl Iterator<Item = (PrimitiveType, usize)> + '_ {
+        self.mirrors.iter().map(|(t, r)| (*t, *r))
+    }
+
+    /// Uses the given object as the class object of the given type, e.g. one
+    /// that was restored from a snapshot.
+    pub(crate) fn restore(&mut self, primitive_type: PrimitiveType, mirror: usize) {
+        self.mirrors.insert(primitive_type, mirror);
+    }
+
+    /// Returns the primitive type that the given class object represents, or
+    /// [`None`] if it doesn't represent a primitive type, as needed for
+    /// `Class.isPrimitive`.
+    pub fn primitive_type(&self, mirror: usize) -> Option<PrimitiveType> {
+        self.mirrors
+            .iter()
+            .find(|(_, r)| **r == mirror)
+            .map(|(t, _)| *t)
+    }
+}
+
+/// Allocates an instance of the given wrapper class that holds the given value,
+/// like `Integer.valueOf`, but without any caching.
+pub fn box_value(
+    heap: &mut Heap,
+    wrapper: Rc<Class>,
+    value: NativeValue,
+) -> Result<usize, VmError> {
+    let primitive_type = PrimitiveType::of(&value)
+        .filter(|t| t.wrapper_class() == wrapper.name().as_str())
+        .ok_or_else(|| {
+            Exception::IllegalArgument(format!("can't box {:?} as {}", value, wrapper.name()))
+        })?;
+    let field = value_field(wrapper.instance_layout(), wrapper.name(), primitive_type)?.clone();
+    let reference = heap.allocate_instance(&wrapper);
+    let instance = heap.get_mut(reference).unwrap().as_instance_mut().unwrap();
+    instance.set_field(&field, value)?;
+    Ok(reference)
+}
+
+/// Returns the value that the given wrapper object holds. Fails with a
+/// `NullPointerException` for `null`, and with an `IllegalArgumentException`
+/// if the object is not an instance of a wrapper class.
+pub fn unbox(heap: &Heap, reference: usize) -> Result<NativeValue, VmError> {
+    if reference == NULL {
+        return Err(Exception::NullPointer.into());
+    }
+    let object = heap
+        .get(reference)
+        .ok_or_else(|| VmError::Internal(format!("invalid reference {}", reference)))?;
+    let instance = object
+        .as_instance()
+        .zip(PrimitiveType::from_wrapper_class(&object.class_name()));
+    match instance {
+        Some((instance, primitive_type)) => {
+            let field = value_field(instance.layout(), instance.class_name(), primitive_type)?;
+            Ok(instance.field(field))
+        }
+        None => Err(Exception::IllegalArgument(format!(
+            "{} is not a primitive wrapper",
+            object.class_name().replace('/', ".")
+        ))
+        .into()),
+    }
+}
+
+/// Returns the `value` field of the given wrapper class.
+fn value_field<'a>(
+    layout: &'a FieldLayout,
+    wrapper: &str,
+    primitive_type: PrimitiveType,
+) -> Result<&'a Field, VmError> {
+    let descriptor = primitive_type.field_type().unwrap().to_string();
+    layout.field("value", &descriptor).ok_or_else(|| {
+        VmError::Internal(format!(
+            "{} has no field value of type {}",
+            wrapper, descriptor
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::test_util::class_path;
+    use crate::vm::VM;
+    use libvfs::FileSystem;
+
+    fn vm() -> VM {
+        VM::new(FileSystem::new_os_fs(), class_path(&[]))
+    }
+
+    #[test]
+    fn test_primitive_classes() {
+        let mut vm = vm();
+        let int = vm.primitive_class("int").unwrap();
+        let void = vm.primitive_class("void").unwrap();
+        assert_ne!(int, void);
+        assert_eq!(int, vm.primitive_class("int").unwrap());
+        assert_eq!(Some(PrimitiveType::Void), vm.primitive_type_of(void));
+        assert_eq!(None, vm.primitive_type_of(NULL));
+        assert_eq!(
+            "java/lang/Class",
+            vm.heap().read().unwrap().get(int).unwrap().class_name()
+        );
+
+        assert_eq!(
+            "java/lang/ClassNotFoundException",
+            vm.primitive_class("Integer").unwrap_err().class_name()
+        );
+    }
+
+    #[test]
+    fn test_box_unbox() {
+        let mut vm = vm();
+        for value in [
+            NativeValue::Boolean(true),
+            NativeValue::Byte(-1),
+            NativeValue::Char('x' as u16),
+            NativeValue::Short(300),
+            NativeValue::Integer(42),
+            NativeValue::Long(-1 << 40),
+            NativeValue::Float(1.5),
+            NativeValue::Double(-0.25),
+        ] {
+            let boxed = vm.box_value(value.clone()).unwrap();
+            let wrapper = PrimitiveType::of(&value).unwrap().wrapper_class();
+            assert_eq!(
+                wrapper,
+                vm.heap().read().unwrap().get(boxed).unwrap().class_name()
+            );
+            assert_eq!(value, vm.unbox(boxed).unwrap());
+        }
+
+        assert_eq!(
+            "java/lang/NullPointerException",
+            vm.unbox(NULL).unwrap_err().class_name()
+        );
+        let mirror = vm.primitive_class("int").unwrap();
+        assert_eq!(
+            "java/lang/IllegalArgumentException",
+            vm.unbox(mirror).unwrap_err().class_name()
+        );
+        assert!(vm.box_value(NativeValue::Reference(mirror)).is_err());
+    }
+}
@@ -1,19 +1,62 @@
-use std::path::{Path, PathBuf};
+use std::io::Write;
 use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 
-use classloader::classpath::ClassPathEntry;
+use libvfs::file::File;
 use libvfs::FileSystem;
 
-use crate::vm::area::{Heap, MethodArea};
-use crate::vm::classloader::bootstrap::BootstrapClassLoader;
-use crate::vm::classloader::classpath::ClassPath;
-use crate::vm::thread::Thread;
+use crate::vm::area::{MethodArea, MethodAreaStats};
+use crate::vm::builder::VmBuilder;
+use crate::vm::classloader::bootstrap::{BootstrapClassLoader, LoadListener};
+use crate::vm::classloader::class::{Class, Method};
+use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use crate::vm::classloader::init::{ClassInitializer, InitListener};
+use crate::vm::classloader::trace::ClassLoadTrace;
+use crate::vm::classloader::ClassLoader;
+use crate::vm::crash::Crash;
+use crate::vm::error::{Exception, LinkageError, VmError};
+use crate::vm::events::EventKind;
+use crate::vm::heap::Heap;
+use crate::vm::method_type::MethodTypes;
+use crate::vm::mirror::{PrimitiveClasses, PrimitiveType};
+use crate::vm::module::{Module, ModuleLayer, ModuleMode};
+use crate::vm::runtime::Runtime;
+use crate::vm::shutdown::ShutdownHook;
+use crate::vm::thread::{Thread, Unwind};
+use crate::vm::types::NativeValue;
 
 pub mod area;
+pub mod builder;
 pub mod classloader;
+pub mod clock;
+pub mod code;
+pub mod coverage;
+pub mod crash;
+pub mod deopt;
+pub mod descriptor;
+pub mod devirt;
+pub mod error;
+pub mod escape;
+pub mod events;
+pub mod group;
+pub mod heap;
+pub mod locking;
+pub mod method_type;
+pub mod mirror;
+/// The module system: the modules of the boot layer and their readability and
+/// exports, which the access checks enforce in strict mode.
+pub mod module;
+pub mod native;
+pub mod runtime;
+pub mod scheduler;
+pub mod shutdown;
+pub mod snapshot;
 pub mod stack;
+pub mod symbol;
+/// Template VMs with warmed up classes, which VMs are forked from cheaply.
+pub mod template;
 pub mod thread;
+pub mod threads;
 pub mod types;
 
 pub struct VM {
@@ -22,8 +65,11 @@ pub struct VM {
     /// [`$2.5.3`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.5.3
     heap: Arc<RwLock<Heap>>,
     method_area: Arc<RwLock<MethodArea>>,
-    file_system: Rc<FileSystem>,
     bootstrap_class_loader: BootstrapClassLoader,
+    initializer: ClassInitializer,
+    primitive_classes: PrimitiveClasses,
+    method_types: MethodTypes,
+    runtime: Arc<Runtime>,
 }
 
 impl Default for VM {
@@ -35,19 +81,583 @@ impl Default for VM {
 
 impl VM {
     pub fn new(fs: FileSystem, cp: ClassPath) -> Self {
+        Self::with_method_area(fs, cp, MethodArea::new())
+    }
+
+    /// Configures a VM before creating it, see [`VmBuilder`].
+    pub fn builder() -> VmBuilder {
+        VmBuilder::new()
+    }
+
+    /// Creates a VM that interns its symbols and descriptors in the tables of
+    /// the given method area, e.g. one forked from a
+    /// [`VmTemplate`](template::VmTemplate).
+    fn with_method_area(fs: FileSystem, cp: ClassPath, method_area: MethodArea) -> Self {
+        let symbols = method_area.symbols().clone();
+        let descriptors = method_area.descriptors().clone();
+        let mut bootstrap_class_loader =
+            BootstrapClassLoader::new(fs.clone(), cp, symbols, descriptors);
+        bootstrap_class_loader.set_metaspace(method_area.metaspace().clone());
+        let method_area = Arc::new(RwLock::new(method_area));
+        let hierarchy_area = method_area.clone();
+        bootstrap_class_loader.add_load_listener(Box::new(move |class| {
+            hierarchy_area
+                .write()
+                .unwrap()
+                .hierarchy_mut()
+                .record(class);
+        }));
+        let runtime = Arc::new(Runtime::new(fs));
+        let events = runtime.clone();
+        bootstrap_class_loader.add_load_listener(Box::new(move |class| {
+            events.log_event(
+                EventKind::ClassLoad,
+                format!("Loaded class {}", class.name()),
+            );
+        }));
+        let layouts = runtime.clone();
+        bootstrap_class_loader
+            .add_load_listener(Box::new(move |class| layouts.add_instance_layout(class)));
         Self {
-            heap: Arc::new(RwLock::new(Heap::new())),
-            method_area: Arc::new(RwLock::new(MethodArea::new())),
-            bootstrap_class_loader: BootstrapClassLoader::new(fs.clone(), cp),
-            file_system: Rc::new(fs),
+            heap: runtime.heap().clone(),
+            method_area,
+            bootstrap_class_loader,
+            initializer: ClassInitializer::new(),
+            primitive_classes: PrimitiveClasses::new(),
+            method_types: MethodTypes::new(),
+            runtime,
         }
     }
 
-    pub fn run_main_class(self, class_name: &'static str) {
-        let thread = std::thread::spawn(move || {
-            let mut main_thread = Thread::new();
-            main_thread.run_method(class_name, "main:([Ljava/lang/String;)V");
+    /// The state shared by all threads of this VM, e.g. to configure the clock
+    /// or the network policy before running any code.
+    pub fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
+    /// The heap of this VM, e.g. to inspect the objects that Java code
+    /// returned (see [`Heap::debug_string`]).
+    pub fn heap(&self) -> &Arc<RwLock<Heap>> {
+        &self.heap
+    }
+
+    /// The memory currently used by the classes loaded by this VM.
+    pub fn method_area_stats(&self) -> MethodAreaStats {
+        self.method_area.read().unwrap().stats()
+    }
+
+    /// Limits the memory that loaded classes may use, like
+    /// `-XX:MaxMetaspaceSize`. Loading a class that would exceed the limit
+    /// fails with an `OutOfMemoryError: Metaspace`.
+    pub fn set_max_metaspace_size(&self, limit: Option<usize>) {
+        self.method_area
+            .read()
+            .unwrap()
+            .metaspace()
+            .set_limit(limit);
+    }
+
+    /// Sets whether class files that depend on the preview features of Java
+    /// 17 may be loaded, like `--enable-preview`. See
+    /// [`BootstrapClassLoader::set_preview_enabled`].
+    pub fn set_preview_enabled(&mut self, enabled: bool) {
+        self.bootstrap_class_loader.set_preview_enabled(enabled);
+    }
+
+    /// Sets whether the VM enforces the boundaries of named modules. For
+    /// [`ModuleMode::Strict`], this builds the boot layer from the class path,
+    /// see [`ModuleLayer::boot`], and fails like it. Classes that were already
+    /// loaded keep their module, so this has to be set before loading any
+    /// class.
+    pub fn set_module_mode(&mut self, mode: ModuleMode) -> Result<(), VmError> {
+        let layer = Arc::new(match mode {
+            ModuleMode::ClassPath => ModuleLayer::default(),
+            ModuleMode::Strict => ModuleLayer::boot(
+                self.runtime.file_system(),
+                self.bootstrap_class_loader.class_path(),
+            )?,
         });
-        let _ = thread.join();
+        self.bootstrap_class_loader
+            .set_module_layer((mode == ModuleMode::Strict).then(|| layer.clone()));
+        self.runtime.set_boot_layer(mode, layer);
+        Ok(())
+    }
+
+    /// The module of the class, like `Class.getModule`. In class path mode,
+    /// this is the unnamed module.
+    pub fn module_of(&self, class: &Class) -> Arc<Module> {
+        match class.module() {
+            Some(module) => module.clone(),
+            None => self.runtime.boot_layer().module_of(class).clone(),
+        }
+    }
+
+    /// Loads the class with the given name like `Class.forName`, with the
+    /// bootstrap class loader. The name is a binary name like
+    /// `java.lang.String`. Fails with a `ClassNotFoundException` if there is no
+    /// class file for the class, but with the usual linkage errors if the class
+    /// file exists and the class can't be loaded, e.g. because its superclass
+    /// is missing.
+    pub fn for_name(&mut self, name: &str) -> Result<Rc<Class>, VmError> {
+        if name.is_empty() || name.contains('/') {
+            return Err(Exception::ClassNotFound(name.to_string()).into());
+        }
+        let binary_name = name.replace('.', "/");
+        match self.bootstrap_class_loader.find_or_load_class(&binary_name) {
+            Err(VmError::Linkage(LinkageError::NoClassDefFound(n))) if n == binary_name => {
+                Err(Exception::ClassNotFound(name.to_string()).into())
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the classes of the checked exceptions that the given method
+    /// declares to throw, like `Method.getExceptionTypes`, loading them if
+    /// they were not loaded yet. Fails with the linkage error of the first
+    /// class that can't be loaded.
+    pub fn exception_types(&mut self, method: &Method) -> Result<Vec<Rc<Class>>, VmError> {
+        method
+            .exceptions()
+            .iter()
+            .map(|name| {
+                self.bootstrap_class_loader
+                    .find_or_load_class(name.as_str())
+            })
+            .collect()
+    }
+
+    /// Initializes the given class and its superclasses, see
+    /// [`ClassInitializer::initialize`].
+    ///
+    /// The interpreter can't invoke methods yet, so initializing a class with a
+    /// static initializer fails with an `InternalError`.
+    pub fn initialize(&mut self, class: &Rc<Class>) -> Result<(), VmError> {
+        self.initializer.initialize(class, &mut |class| {
+            Err(VmError::Internal(format!(
+                "can't run the static initializer of {}",
+                class.name().as_str().replace('/', ".")
+            )))
+        })
+    }
+
+    /// Registers a listener that is notified whenever a class becomes
+    /// initialized, see [`Class::init_state`].
+    pub fn add_init_listener(&mut self, listener: InitListener) {
+        self.initializer.add_listener(listener);
+    }
+
+    /// Registers a listener that is notified whenever a class is loaded, e.g.
+    /// [`DeoptTable::class_loaded`](crate::vm::deopt::DeoptTable::class_loaded).
+    pub fn add_load_listener(&mut self, listener: LoadListener) {
+        self.bootstrap_class_loader.add_load_listener(listener);
+    }
+
+    /// Returns the URL of the resource with the given name on the class path,
+    /// like `ClassLoader.getResource`, see [`ClassLoader::resource_url`].
+    pub fn resource_url(&self, name: &str) -> Option<String> {
+        self.bootstrap_class_loader.resource_url(name)
+    }
+
+    /// Opens the resource with the given name on the class path for reading,
+    /// like `ClassLoader.getResourceAsStream`.
+    pub fn open_resource(&self, name: &str) -> std::io::Result<File> {
+        self.bootstrap_class_loader.open_resource(name)
+    }
+
+    /// Loads the providers of the service with the given binary name, e.g.
+    /// `java.sql.Driver`, that are configured in `META-INF/services` of the
+    /// class path, like iterating over `ServiceLoader.load`, in the order of
+    /// the class path. Fails with a `ServiceConfigurationError` if a provider
+    /// class is missing or isn't a subtype of the service, and with the usual
+    /// linkage errors if the service or a provider can't be loaded.
+    pub fn service_providers(&mut self, service: &str) -> Result<Vec<Rc<Class>>, VmError> {
+        let service_class = self.for_name(service)?;
+        self.bootstrap_class_loader
+            .service_providers(service_class.name().as_str())?
+            .into_iter()
+            .map(|name| {
+                let name = name.replace('/', ".");
+                let provider = match self.for_name(&name) {
+                    Err(VmError::Exception(Exception::ClassNotFound(_))) => {
+                        return Err(Exception::ServiceConfiguration(format!(
+                            "{}: Provider {} not found",
+                            service, name
+                        ))
+                        .into())
+                    }
+                    result => result?,
+                };
+                if !provider.is_subclass_of(&service_class) {
+                    return Err(Exception::ServiceConfiguration(format!(
+                        "{}: Provider {} not a subtype",
+                        service, name
+                    ))
+                    .into());
+                }
+                Ok(provider)
+            })
+            .collect()
+    }
+
+    /// Returns the class object of the primitive type with the given name, e.g.
+    /// `int.class` for `int`, like `Class.getPrimitiveClass`. Fails with a
+    /// `ClassNotFoundException` if there is no such primitive type.
+    pub fn primitive_class(&mut self, name: &str) -> Result<usize, VmError> {
+        let primitive_type = PrimitiveType::from_name(name)
+            .ok_or_else(|| Exception::ClassNotFound(name.to_string()))?;
+        let class_class = self
+            .bootstrap_class_loader
+            .find_or_load_class("java/lang/Class")?;
+        let mut heap = self.heap.write().unwrap();
+        Ok(self
+            .primitive_classes
+            .get_or_create(primitive_type, &mut heap, class_class))
+    }
+
+    /// Returns the primitive type that the given class object represents, or
+    /// [`None`] if it is not the class object of a primitive type.
+    pub fn primitive_type_of(&self, mirror: usize) -> Option<PrimitiveType> {
+        self.primitive_classes.primitive_type(mirror)
+    }
+
+    /// Allocates an instance of the wrapper class of the given primitive value,
+    /// e.g. a `java.lang.Integer` for an int, see [`mirror::box_value`].
+    pub fn box_value(&mut self, value: NativeValue) -> Result<usize, VmError> {
+        let primitive_type = PrimitiveType::of(&value)
+            .ok_or_else(|| Exception::IllegalArgument(format!("can't box {:?}", value)))?;
+        let wrapper = self
+            .bootstrap_class_loader
+            .find_or_load_class(primitive_type.wrapper_class())?;
+        mirror::box_value(&mut self.heap.write().unwrap(), wrapper, value)
+    }
+
+    /// Returns the primitive value of the given wrapper object, see
+    /// [`mirror::unbox`].
+    pub fn unbox(&self, reference: usize) -> Result<NativeValue, VmError> {
+        mirror::unbox(&self.heap.read().unwrap(), reference)
+    }
+
+    /// Logs every class that is loaded from now on to the given trace, e.g.
+    /// [`ClassLoadTrace::stdout`] for the equivalent of `-verbose:class`.
+    pub fn trace_class_loading(&mut self, trace: ClassLoadTrace) {
+        self.bootstrap_class_loader.set_trace(trace);
+    }
+
+    /// Registers a hook that is run when the VM exits, either because the main
+    /// method returned or because of `Runtime.exit`.
+    pub fn add_shutdown_hook(&self, hook: ShutdownHook) {
+        self.runtime.shutdown().add_hook(hook);
+    }
+
+    /// Writes a report of a crash of the VM, like the `hs_err` file of
+    /// HotSpot: the panic, the configuration of the VM, the Java and Rust
+    /// stacks of the crashed thread, the memory usage, the recent events of
+    /// the runtime (see [`EventLog`](crate::vm::events::EventLog)) and the
+    /// loaded classes.
+    pub fn report_fatal_error(&self, crash: &Crash, out: &mut impl Write) {
+        // the VM is crashing already, so there is nothing to do if this fails
+        let _ = self.write_fatal_error(crash, out);
+    }
+
+    fn write_fatal_error(&self, crash: &Crash, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "#")?;
+        writeln!(out, "# A fatal error has been detected by rjvm:")?;
+        writeln!(out, "#")?;
+        match &crash.location {
+            Some(location) => writeln!(out, "#  panicked at {}:", location)?,
+            None => writeln!(out, "#  panicked:")?,
+        }
+        for line in crash.message.lines() {
+            writeln!(out, "#  {}", line)?;
+        }
+        writeln!(out, "#")?;
+        writeln!(out, "# VM: rjvm {}", env!("CARGO_PKG_VERSION"))?;
+        // the locks may be poisoned by the panic, but the data is still
+        // worth reporting
+        let method_area = self.method_area.read().unwrap_or_else(|e| e.into_inner());
+        let class_path: Vec<_> = self
+            .bootstrap_class_loader
+            .class_path()
+            .entries()
+            .map(|entry| match entry {
+                ClassPathEntry::Dir(path) | ClassPathEntry::JarFile(path) => path.as_str(),
+            })
+            .collect();
+        write!(out, "# Options: -cp {}", class_path.join(":"))?;
+        if let Some(limit) = method_area.metaspace().limit() {
+            write!(out, " -XX:MaxMetaspaceSize={}", limit)?;
+        }
+        writeln!(out, "\n#\n")?;
+
+        writeln!(out, "---------------  T H R E A D  ---------------\n")?;
+        writeln!(out, "Current thread: \"{}\"\n", crash.thread)?;
+        writeln!(out, "Java frames:")?;
+        for frame in &crash.java_stack {
+            writeln!(out, "j  {}", frame)?;
+        }
+        writeln!(out, "\nRust frames:")?;
+        writeln!(
+            out,
+            "{}",
+            crash.backtrace.as_deref().unwrap_or("<not captured>")
+        )?;
+
+        writeln!(out, "---------------  P R O C E S S  ---------------\n")?;
+        let heap = self.heap.read().unwrap_or_else(|e| e.into_inner());
+        writeln!(out, "Heap: {} objects", heap.len())?;
+        let stats = method_area.stats();
+        writeln!(
+            out,
+            "Metaspace: {} classes, {} bytes (class metadata {}, bytecode {}, constant pools {})\n",
+            stats.classes,
+            stats.total(),
+            stats.class_metadata,
+            stats.bytecode,
+            stats.constant_pools
+        )?;
+        self.runtime.events().dump(out)?;
+
+        let classes = self.bootstrap_class_loader.loaded_classes();
+        writeln!(out, "Loaded classes ({}):", classes.len())?;
+        for class in classes {
+            writeln!(out, "{}", class.name())?;
+        }
+        Ok(())
+    }
+
+    /// Runs the main method of the given class and returns the exit status of
+    /// the VM. This never exits the host process, even if the Java code calls
+    /// `System.exit` or `Runtime.halt`.
+    ///
+    /// The main thread of the VM runs on a new thread of the host, so that
+    /// its stack doesn't depend on the caller. On wasm32, which has no
+    /// threads, this is [`VM::run_main_class_on_current_thread`].
+    pub fn run_main_class(self, class_name: &'static str) -> i32 {
+        if cfg!(target_arch = "wasm32") {
+            return self.run_main_class_on_current_thread(class_name);
+        }
+        let runtime = self.runtime.clone();
+        let thread = std::thread::spawn(move || run_main(runtime, class_name));
+        // a thread that died abnormally terminates the VM with a failure
+        // status, like an uncaught exception in the main thread
+        let status = match thread.join() {
+            Ok(result) => self.main_exit_status(result),
+            Err(_) => 1,
+        };
+        self.exit(status)
+    }
+
+    /// Like [`VM::run_main_class`], but runs the main thread of the VM on the
+    /// calling thread, e.g. for hosts without threads like browsers.
+    pub fn run_main_class_on_current_thread(self, class_name: &'static str) -> i32 {
+        let result = run_main(self.runtime.clone(), class_name);
+        let status = self.main_exit_status(result);
+        self.exit(status)
+    }
+
+    fn main_exit_status(&self, result: Result<i32, Crash>) -> i32 {
+        match result {
+            Ok(status) => status,
+            Err(crash) => {
+                self.report_fatal_error(&crash, &mut std::io::stderr());
+                1
+            }
+        }
+    }
+
+    fn exit(&self, status: i32) -> i32 {
+        let shutdown = self.runtime.shutdown();
+        shutdown.exit(status);
+        shutdown.exit_status().unwrap_or(status)
+    }
+}
+
+/// Runs the main method of the given class on a new Java thread, and returns
+/// the exit status of the thread, or the crash that it died of.
+fn run_main(runtime: Arc<Runtime>, class_name: &'static str) -> Result<i32, Crash> {
+    let mut main_thread = Thread::new(runtime);
+    let result =
+        crash::catch_crash(|| main_thread.run_method(class_name, "main:([Ljava/lang/String;)V"));
+    match result {
+        Ok(Ok(())) | Ok(Err(Unwind::Shutdown)) => Ok(0),
+        Ok(Err(Unwind::Throw(e))) => {
+            main_thread.dispatch_uncaught_exception(&e);
+            Ok(1)
+        }
+        Err(mut crash) => {
+            crash.thread = main_thread.name().to_string();
+            crash.java_stack = main_thread.java_stack();
+            Err(crash)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::class::InitState;
+    use crate::vm::classloader::test_util::class_path;
+    use crate::vm::clock::VirtualClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_for_name() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&[]));
+        let class = vm.for_name("java.lang.Integer").unwrap();
+        assert_eq!("java/lang/Integer", class.name().as_str());
+        assert!(Rc::ptr_eq(
+            &class,
+            &vm.for_name("java.lang.Integer").unwrap()
+        ));
+
+        for missing in ["Missing", "java/lang/Integer", ""] {
+            assert_eq!(
+                Err(Exception::ClassNotFound(missing.to_string()).into()),
+                vm.for_name(missing).map(|_| ()),
+            );
+        }
+    }
+
+    #[test]
+    fn test_service_providers() {
+        let services = "tests/resources/vm/services";
+        let mut vm = VM::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(vec![
+                ClassPathEntry::Dir(format!("{}/dir", services)),
+                ClassPathEntry::JarFile(format!("{}/plugins.jar", services)),
+                ClassPathEntry::Dir("tests/resources/vm/classloader".into()),
+            ]),
+        );
+        let providers = vm.service_providers("greet.Greeter").unwrap();
+        let names: Vec<&str> = providers.iter().map(|c| c.name().as_str()).collect();
+        assert_eq!(vec!["greet/Hello", "plugin/Bye"], names);
+        assert!(vm
+            .service_providers("java.lang.Integer")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Err(Exception::ClassNotFound("greet.Missing".into()).into()),
+            vm.service_providers("greet.Missing").map(|_| ())
+        );
+
+        vm.bootstrap_class_loader
+            .add_entry(ClassPathEntry::Dir(format!("{}/bad", services)));
+        let error = vm.service_providers("greet.Greeter").err().unwrap();
+        assert_eq!("java/util/ServiceConfigurationError", error.class_name());
+        assert!(error.is_error());
+        assert_eq!(
+            Some("greet.Greeter: Provider greet.Silent not a subtype".to_string()),
+            error.message()
+        );
+    }
+
+    #[test]
+    fn test_fatal_error_report() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&[]));
+        vm.runtime()
+            .set_clock(Arc::new(VirtualClock::new(Duration::ZERO)));
+        vm.for_name("java.lang.Integer").unwrap();
+        let thread = Thread::new(vm.runtime().clone());
+        thread.exception_thrown(&Exception::IllegalArgument("bad".into()).into());
+
+        let crash = crash::catch_crash(|| panic!("broken\ninvariant")).unwrap_err();
+        let crash = Crash {
+            thread: "main".to_string(),
+            java_stack: vec!["Main.main([Ljava/lang/String;)V".to_string()],
+            ..crash
+        };
+
+        let mut out = vec![];
+        vm.report_fatal_error(&crash, &mut out);
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.starts_with("#\n# A fatal error has been detected by rjvm:\n#\n"));
+        assert!(report.contains("#  broken\n#  invariant\n#\n# VM: rjvm 0.1.0\n"));
+        assert!(report.contains("# Options: -cp tests/resources/vm/classloader\n"));
+        assert!(report.contains(
+            "Current thread: \"main\"\n\nJava frames:\nj  Main.main([Ljava/lang/String;)V\n"
+        ));
+        assert!(report.contains("Heap: 0 objects\nMetaspace: 3 classes, "));
+        assert!(report.contains(
+            "Exceptions (1 events):\nEvent: 0.000 Exception \
+             <java.lang.IllegalArgumentException: bad> thrown in thread \"main\"\n"
+        ));
+        assert!(report.contains("Event: 0.000 Loaded class java/lang/Integer\n"));
+        // each after its superclasses
+        assert!(report.ends_with(
+            "Loaded classes (3):\njava/lang/Object\njava/lang/Number\njava/lang/Integer\n"
+        ));
+    }
+
+    #[test]
+    fn test_method_area_stats() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&[]));
+        assert_eq!(MethodAreaStats::default(), vm.method_area_stats());
+
+        vm.for_name("java.lang.Object").unwrap();
+        let object = vm.method_area_stats();
+        assert_eq!(1, object.classes);
+        assert!(object.class_metadata > 0);
+        assert!(object.bytecode > 0);
+        assert!(object.constant_pools > 0);
+
+        // Circle extends Shape, so two more classes are loaded
+        vm.set_max_metaspace_size(Some(object.total() + 1));
+        assert_eq!(
+            Err(Exception::OutOfMemory("Metaspace".to_string()).into()),
+            vm.for_name("Circle").map(|_| ())
+        );
+        assert_eq!(object, vm.method_area_stats());
+
+        vm.set_max_metaspace_size(None);
+        vm.for_name("Circle").unwrap();
+        let stats = vm.method_area_stats();
+        assert_eq!(3, stats.classes);
+        assert!(stats.total() > object.total());
+    }
+
+    #[test]
+    fn test_exception_types() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&["exceptions"]));
+        let reader = vm.for_name("Reader").unwrap();
+        let read = reader.find_method("read", "()I").unwrap();
+        assert_eq!(read.exceptions(), ["ReadFailure", "Closed"]);
+        // the exceptions are only loaded when asked for
+        assert!(vm.bootstrap_class_loader.find_class("Closed").is_none());
+        let types = vm.exception_types(read).unwrap();
+        let names: Vec<_> = types.iter().map(|class| class.name().as_str()).collect();
+        assert_eq!(vec!["ReadFailure", "Closed"], names);
+        assert!(Rc::ptr_eq(&types[0], types[1].super_class().unwrap()));
+
+        let close = reader.find_method("close", "()V").unwrap();
+        assert!(vm.exception_types(close).unwrap().is_empty());
+
+        // the class file of Gone was deleted after compiling Reader
+        let reset = reader.find_method("reset", "()V").unwrap();
+        assert_eq!(
+            Err(LinkageError::NoClassDefFound("Gone".to_string()).into()),
+            vm.exception_types(reset).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_initialize() {
+        let mut vm = VM::new(FileSystem::new_os_fs(), class_path(&["init"]));
+        let initialized = Rc::new(std::cell::RefCell::new(vec![]));
+        let listened = initialized.clone();
+        vm.add_init_listener(Box::new(move |class| {
+            listened
+                .borrow_mut()
+                .push(class.name().as_str().to_string())
+        }));
+
+        let plain = vm.for_name("Plain").unwrap();
+        vm.initialize(&plain).unwrap();
+        assert_eq!(InitState::Initialized, plain.init_state());
+        assert_eq!(vec!["java/lang/Object", "Plain"], *initialized.borrow());
+
+        let config = vm.for_name("Config").unwrap();
+        assert!(vm.initialize(&config).is_err());
+        assert_eq!(InitState::Erroneous, config.init_state());
     }
 }
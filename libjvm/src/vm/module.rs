@@ -0,0 +1,564 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use crate::vm::error::{Exception, LinkageError, VmError};
+use libjava::classfile::flags::{ModuleFlags, RequiresFlags};
+use libjava::classfile::{ClassFile, ConstantPoolError};
+use libvfs::path::VfsPath;
+use libvfs::FileSystem;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::BufReader;
+use std::sync::{Arc, RwLock};
+
+/// Whether the VM enforces the boundaries of named modules, see
+/// [`VM::set_module_mode`](crate::vm::VM::set_module_mode).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ModuleMode {
+    /// Classes have no module, like classes on the class path of HotSpot, so
+    /// only the access flags of classes and members are checked.
+    #[default]
+    ClassPath,
+    /// The class path entries with a `module-info.class` are the named modules
+    /// of the boot layer, and all other classes are in the unnamed module. A
+    /// public class is only accessible if the module of the accessor reads its
+    /// module, and its module exports the package of the class to the module
+    /// of the accessor ([`$5.4.4`]).
+    ///
+    /// [`$5.4.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4
+    Strict,
+}
+
+/// A run-time module, as described by its `Module` attribute ([`$4.7.25`]),
+/// or the unnamed module. The unnamed module reads every module and exports
+/// all of its packages, while named modules only read the modules that they
+/// require, and export the packages that they declare.
+///
+/// What a module reads, exports and opens can be extended at run time, like
+/// with `Module.addReads`.
+///
+/// [`$4.7.25`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25
+#[derive(Debug)]
+pub struct Module {
+    /// The name of the module, or [`None`] for the unnamed module.
+    name: Option<String>,
+    version: Option<String>,
+    /// Whether this is an `open module`, whose packages are all open.
+    is_open: bool,
+    /// The packages of the module, in internal form like `java/lang`.
+    packages: HashSet<String>,
+    /// The names of the required modules, and whether they are required
+    /// transitively. `requires static` is left out, since optional
+    /// dependencies are not resolved.
+    requires: Vec<(String, bool)>,
+    edges: RwLock<Edges>,
+}
+
+#[derive(Debug, Default)]
+struct Edges {
+    /// The names of the modules that the module reads, [`None`] for the
+    /// unnamed module.
+    reads: HashSet<Option<String>>,
+    /// The exported packages, and the modules they are exported to.
+    exports: HashMap<String, Targets>,
+    /// The open packages, and the modules they are opened to.
+    opens: HashMap<String, Targets>,
+}
+
+/// The modules that a package is exported or opened to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Targets {
+    All,
+    /// The names of the modules, [`None`] for the unnamed module.
+    To(HashSet<Option<String>>),
+}
+
+impl Targets {
+    fn contains(&self, module: &Module) -> bool {
+        match self {
+            Targets::All => true,
+            Targets::To(modules) => modules.contains(&module.name),
+        }
+    }
+
+    /// Adds the given module, or all modules for [`None`].
+    fn add(&mut self, module: Option<&Module>) {
+        match (module, &mut *self) {
+            (_, Targets::All) => {}
+            (None, _) => *self = Targets::All,
+            (Some(module), Targets::To(modules)) => {
+                modules.insert(module.name.clone());
+            }
+        }
+    }
+}
+
+impl Module {
+    fn unnamed() -> Self {
+        Self {
+            name: None,
+            version: None,
+            is_open: true,
+            packages: HashSet::new(),
+            requires: vec![],
+            edges: RwLock::default(),
+        }
+    }
+
+    /// Creates a named module from the class file of its `module-info`. The
+    /// packages of the module are those of its `ModulePackages` attribute and
+    /// those that it exports or opens, see [`Module::with_packages`] for the
+    /// others. Fails with a `ClassFormatError` if the class file has no
+    /// `Module` attribute, or one with invalid constant pool indices.
+    pub fn from_class_file(class_file: &ClassFile) -> Result<Self, VmError> {
+        let invalid =
+            |e: ConstantPoolError| LinkageError::ClassFormat(format!("{:?} in module-info", e));
+        let descriptor = class_file
+            .module_descriptor()
+            .map_err(invalid)?
+            .ok_or_else(|| {
+                LinkageError::ClassFormat("module-info has no Module attribute".to_string())
+            })?;
+
+        let mut module = Self {
+            name: Some(descriptor.name),
+            version: descriptor.version,
+            is_open: descriptor.flags.contains(ModuleFlags::OPEN),
+            packages: descriptor.packages.into_iter().collect(),
+            requires: descriptor
+                .requires
+                .into_iter()
+                .filter(|r| !r.flags.contains(RequiresFlags::STATIC_PHASE))
+                .map(|r| (r.name, r.flags.contains(RequiresFlags::TRANSITIVE)))
+                .collect(),
+            edges: RwLock::default(),
+        };
+        let edges = module.edges.get_mut().unwrap();
+        for e in descriptor.exports {
+            edges.exports.insert(e.package, targets(e.to));
+        }
+        for o in descriptor.opens {
+            edges.opens.insert(o.package, targets(o.to));
+        }
+        module.packages.extend(edges.exports.keys().cloned());
+        module.packages.extend(edges.opens.keys().cloned());
+        Ok(module)
+    }
+
+    /// Adds the given packages to the module, e.g. those found in the
+    /// directory of an exploded module.
+    pub fn with_packages(mut self, packages: impl IntoIterator<Item = String>) -> Self {
+        self.packages.extend(packages);
+        self
+    }
+
+    /// The name of the module, or [`None`] for the unnamed module.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn is_named(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// The version of the module, if its `module-info` records one.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Whether the module contains the package with the given name in
+    /// internal form, e.g. `java/lang`. The unnamed module contains every
+    /// package that no named module contains, which is up to the layer.
+    pub fn contains_package(&self, package: &str) -> bool {
+        self.packages.contains(package)
+    }
+
+    /// Whether this module reads the other module, like `Module.canRead`.
+    pub fn can_read(&self, other: &Module) -> bool {
+        !self.is_named()
+            || self.name == other.name
+            || self.edges.read().unwrap().reads.contains(&other.name)
+    }
+
+    /// Whether this module exports the package to the other module, like
+    /// `Module.isExported`. Open packages are exported as well at run time.
+    pub fn is_exported(&self, package: &str, other: &Module) -> bool {
+        self.is_open_to(package, other)
+            || self
+                .edges
+                .read()
+                .unwrap()
+                .exports
+                .get(package)
+                .is_some_and(|targets| targets.contains(other))
+    }
+
+    /// Whether this module opens the package to the other module for deep
+    /// reflection, like `Module.isOpen`.
+    pub fn is_open_to(&self, package: &str, other: &Module) -> bool {
+        if !self.is_named() || self.name == other.name {
+            return true;
+        }
+        if !self.contains_package(package) {
+            return false;
+        }
+        self.is_open
+            || self
+                .edges
+                .read()
+                .unwrap()
+                .opens
+                .get(package)
+                .is_some_and(|targets| targets.contains(other))
+    }
+
+    /// Makes this module read the other module, like `Module.addReads`.
+    pub fn add_reads(&self, other: &Module) {
+        self.edges.write().unwrap().reads.insert(other.name.clone());
+    }
+
+    /// Exports the package to the other module, or to all modules for
+    /// [`None`], like `Module.addExports`. Fails with an
+    /// `IllegalArgumentException` if the package is not in this module.
+    pub fn add_exports(&self, package: &str, other: Option<&Module>) -> Result<(), VmError> {
+        if !self.is_named() {
+            return Ok(());
+        }
+        if !self.contains_package(package) {
+            return Err(Exception::IllegalArgument(format!(
+                "package {} not in contents",
+                package.replace('/', ".")
+            ))
+            .into());
+        }
+        self.edges
+            .write()
+            .unwrap()
+            .exports
+            .entry(package.to_string())
+            .or_insert_with(|| Targets::To(HashSet::new()))
+            .add(other);
+        Ok(())
+    }
+}
+
+/// Formats the module like `Module.toString`, e.g. `module java.base` or
+/// `unnamed module`.
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "module {}", name),
+            None => write!(f, "unnamed module"),
+        }
+    }
+}
+
+/// The modules that an `exports ... to` or `opens ... to` names, which are
+/// all modules if it names none.
+fn targets(modules: Vec<String>) -> Targets {
+    if modules.is_empty() {
+        return Targets::All;
+    }
+    Targets::To(modules.into_iter().map(Some).collect())
+}
+
+/// A set of modules whose dependencies are resolved, together with the
+/// unnamed module that gets the classes of all other packages, like a
+/// `java.lang.ModuleLayer`. The VM has only the boot layer.
+#[derive(Debug)]
+pub struct ModuleLayer {
+    modules: HashMap<String, Arc<Module>>,
+    /// The named modules by their packages.
+    packages: HashMap<String, Arc<Module>>,
+    unnamed: Arc<Module>,
+    /// The modules by the references of their `java.lang.Module` objects.
+    mirrors: RwLock<HashMap<usize, Arc<Module>>>,
+}
+
+impl Default for ModuleLayer {
+    /// A layer without named modules, so that all classes are in the unnamed
+    /// module.
+    fn default() -> Self {
+        Self {
+            modules: HashMap::new(),
+            packages: HashMap::new(),
+            unnamed: Arc::new(Module::unnamed()),
+            mirrors: RwLock::default(),
+        }
+    }
+}
+
+impl ModuleLayer {
+    /// Resolves the given modules into a layer. Every module reads the
+    /// modules that it requires, and the modules that those require
+    /// transitively. A module that requires `java.base` reads the unnamed
+    /// module instead if there is no `java.base` module, since the classes of
+    /// the JDK are on the class path then.
+    ///
+    /// Fails with a `FindException` if a required module is missing, and with
+    /// a `ResolutionException` if two modules have the same name or contain
+    /// the same package.
+    pub fn new(modules: Vec<Module>) -> Result<Self, VmError> {
+        let mut layer = Self::default();
+        let mut resolved = Vec::with_capacity(modules.len());
+        for module in modules {
+            let name = module.name.clone().ok_or_else(|| {
+                VmError::Internal("the unnamed module can't be part of a layer".to_string())
+            })?;
+            if layer.modules.contains_key(&name) {
+                return Err(Exception::ModuleResolution(format!(
+                    "Two versions of module {} found",
+                    name
+                ))
+                .into());
+            }
+            let module = Arc::new(module);
+            for package in &module.packages {
+                if let Some(other) = layer.packages.get(package) {
+                    return Err(Exception::ModuleResolution(format!(
+                        "Package {} in both {} and {}",
+                        package.replace('/', "."),
+                        other,
+                        module
+                    ))
+                    .into());
+                }
+                layer.packages.insert(package.clone(), module.clone());
+            }
+            layer.modules.insert(name, module.clone());
+            resolved.push(module);
+        }
+
+        for module in &resolved {
+            let mut reads = HashSet::new();
+            for (required, _) in &module.requires {
+                if !layer.modules.contains_key(required) && required != "java.base" {
+                    return Err(Exception::ModuleNotFound(format!(
+                        "Module {} not found, required by {}",
+                        required,
+                        module.name().unwrap_or_default()
+                    ))
+                    .into());
+                }
+                layer.read(required, &mut reads);
+            }
+            module.edges.write().unwrap().reads = reads;
+        }
+        Ok(layer)
+    }
+
+    /// Adds the required module and the modules that it requires
+    /// transitively to the modules that are read.
+    fn read(&self, required: &str, reads: &mut HashSet<Option<String>>) {
+        let Some(module) = self.modules.get(required) else {
+            reads.insert(None);
+            return;
+        };
+        if !reads.insert(Some(required.to_string())) {
+            return;
+        }
+        for (name, transitive) in &module.requires {
+            if *transitive {
+                self.read(name, reads);
+            }
+        }
+    }
+
+    /// Builds the boot layer from the class path. Every directory on the class
+    /// path with a `module-info.class` is an exploded module, with the
+    /// packages of the class files below it. Fails with a `ClassFormatError`
+    /// if a `module-info.class` is malformed, and like [`ModuleLayer::new`].
+    pub fn boot(fs: &FileSystem, class_path: &ClassPath) -> Result<Self, VmError> {
+        let mut modules = vec![];
+        for entry in class_path.entries() {
+            let ClassPathEntry::Dir(dir) = entry else {
+                continue;
+            };
+            let root = fs.path(dir);
+            let descriptor = root.join("module-info.class");
+            if !descriptor.exists().unwrap_or(false) {
+                continue;
+            }
+            let class_file =
+                ClassFile::parse(&mut BufReader::new(descriptor.open()?)).map_err(|e| {
+                    LinkageError::ClassFormat(format!("{} in class file {}/module-info", e, dir))
+                })?;
+            let mut packages = HashSet::new();
+            find_packages(&root, "", &mut packages)?;
+            modules.push(Module::from_class_file(&class_file)?.with_packages(packages));
+        }
+        Self::new(modules)
+    }
+
+    /// The named module with the given name.
+    pub fn find_module(&self, name: &str) -> Option<&Arc<Module>> {
+        self.modules.get(name)
+    }
+
+    /// The named modules of this layer, in no particular order.
+    pub fn modules(&self) -> impl Iterator<Item = &Arc<Module>> {
+        self.modules.values()
+    }
+
+    pub fn unnamed_module(&self) -> &Arc<Module> {
+        &self.unnamed
+    }
+
+    /// The module that contains the package with the given name in internal
+    /// form, which is the unnamed module if no named module contains it.
+    pub fn module_of_package(&self, package: &str) -> &Arc<Module> {
+        self.packages.get(package).unwrap_or(&self.unnamed)
+    }
+
+    /// The module of the given class, like `Class.getModule`. This is the
+    /// module of its package even if the class was loaded before the layer
+    /// was in effect.
+    pub fn module_of(&self, class: &Class) -> &Arc<Module> {
+        self.module_of_package(class.package_name())
+    }
+
+    /// Associates the `java.lang.Module` object with the given reference with
+    /// the module, so that natives like `Module.addReads0` can find it.
+    pub fn bind_mirror(&self, reference: usize, module: Arc<Module>) {
+        self.mirrors.write().unwrap().insert(reference, module);
+    }
+
+    /// The module of the `java.lang.Module` object with the given reference.
+    pub fn module_of_mirror(&self, reference: usize) -> Option<Arc<Module>> {
+        self.mirrors.read().unwrap().get(&reference).cloned()
+    }
+}
+
+/// Adds the packages below the directory that contain class files to the
+/// given set, in internal form.
+fn find_packages(
+    dir: &VfsPath,
+    package: &str,
+    packages: &mut HashSet<String>,
+) -> Result<(), VmError> {
+    for child in dir.read_dir()? {
+        let Some(name) = child.file_name() else {
+            continue;
+        };
+        if child.is_dir()? {
+            let sub_package = match package {
+                "" => name.to_string(),
+                _ => format!("{}/{}", package, name),
+            };
+            find_packages(&child, &sub_package, packages)?;
+        } else if name.ends_with(".class") && !package.is_empty() {
+            packages.insert(package.to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boot_layer() -> ModuleLayer {
+        let class_path = ClassPath::from(
+            ["a", "b", "c", "d", "unnamed"]
+                .map(|m| ClassPathEntry::Dir(format!("tests/resources/vm/modules/{}", m)))
+                .into_iter()
+                .collect::<Vec<_>>(),
+        );
+        ModuleLayer::boot(&FileSystem::new_os_fs(), &class_path).unwrap()
+    }
+
+    #[test]
+    fn test_boot_layer() {
+        let layer = boot_layer();
+        let mut names: Vec<_> = layer.modules().filter_map(|m| m.name()).collect();
+        names.sort();
+        assert_eq!(vec!["a", "b", "c", "d"], names);
+
+        let a = layer.find_module("a").unwrap();
+        assert!(a.contains_package("a/api"));
+        assert!(a.contains_package("a/internal"));
+        assert_eq!("module a", a.to_string());
+        assert!(Arc::ptr_eq(a, layer.module_of_package("a/friend")));
+        assert!(Arc::ptr_eq(
+            layer.unnamed_module(),
+            layer.module_of_package("u")
+        ));
+        assert_eq!("unnamed module", layer.unnamed_module().to_string());
+    }
+
+    #[test]
+    fn test_readability() {
+        let layer = boot_layer();
+        let module = |name| layer.find_module(name).unwrap();
+        let (a, b, c, d) = (module("a"), module("b"), module("c"), module("d"));
+        let unnamed = layer.unnamed_module();
+
+        assert!(b.can_read(a));
+        // b requires a transitively, so c reads it as well
+        assert!(c.can_read(b));
+        assert!(c.can_read(a));
+        assert!(!a.can_read(b));
+        assert!(!d.can_read(a));
+        // there is no java.base module, so the unnamed module takes its place
+        assert!(d.can_read(unnamed));
+        assert!(unnamed.can_read(d));
+
+        d.add_reads(a);
+        assert!(d.can_read(a));
+    }
+
+    #[test]
+    fn test_exports() {
+        let layer = boot_layer();
+        let module = |name| layer.find_module(name).unwrap();
+        let (a, b, c, d) = (module("a"), module("b"), module("c"), module("d"));
+        let unnamed = layer.unnamed_module();
+
+        assert!(a.is_exported("a/api", d));
+        assert!(a.is_exported("a/api", unnamed));
+        assert!(a.is_exported("a/friend", b));
+        assert!(!a.is_exported("a/friend", c));
+        assert!(!a.is_exported("a/internal", b));
+        // open packages are exported at run time
+        assert!(a.is_exported("a/internal", c));
+        assert!(a.is_open_to("a/internal", c));
+        assert!(!a.is_open_to("a/api", c));
+        assert!(a.is_exported("a/internal", a));
+        assert!(unnamed.is_exported("u", a));
+
+        a.add_exports("a/internal", Some(b)).unwrap();
+        assert!(a.is_exported("a/internal", b));
+        assert!(!a.is_exported("a/internal", d));
+        a.add_exports("a/internal", None).unwrap();
+        assert!(a.is_exported("a/internal", d));
+        assert_eq!(
+            Err(Exception::IllegalArgument("package x not in contents".into()).into()),
+            a.add_exports("x", None)
+        );
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let module = |dir: &str| {
+            let path = format!("tests/resources/vm/modules/{}/module-info.class", dir);
+            let class_file = ClassFile::parse(&mut &std::fs::read(path).unwrap()[..]).unwrap();
+            Module::from_class_file(&class_file).unwrap()
+        };
+        assert_eq!(
+            "java.lang.module.FindException: Module a not found, required by b",
+            ModuleLayer::new(vec![module("b")]).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "java.lang.module.ResolutionException: Two versions of module d found",
+            ModuleLayer::new(vec![module("d"), module("d")])
+                .unwrap_err()
+                .to_string()
+        );
+        let split = module("d").with_packages(["a/api".to_string()]);
+        assert_eq!(
+            "java.lang.module.ResolutionException: Package a.api in both module a and module d",
+            ModuleLayer::new(vec![module("a"), split])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
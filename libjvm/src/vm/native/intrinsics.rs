@@ -0,0 +1,142 @@
+use crate::vm::native::{find_intrinsic, NativeMethod};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// The intrinsics of a VM: methods with a Java implementation that the
+/// interpreter substitutes with a Rust implementation, which computes the same
+/// result faster. Methods without an intrinsic are interpreted as usual.
+///
+/// The built-in intrinsics of [`find_intrinsic`] are always registered, and
+/// embedders register more for their own (class, method) pairs, or replace
+/// built-in ones. Intrinsics are enabled by default. Disabling them makes the
+/// interpreter run the bytecode of every method, so that a program can be run
+/// with and without them to test that they compute the same results.
+pub struct Intrinsics {
+    enabled: AtomicBool,
+    /// The registered intrinsics, by the binary name of the class, and the
+    /// name and descriptor of the method.
+    methods: RwLock<HashMap<(String, String, String), NativeMethod>>,
+}
+
+impl Intrinsics {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            methods: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables all intrinsics, the built-in ones and the
+    /// registered ones. This has to be set before running any code, since
+    /// methods that were already linked keep their implementation.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Registers the intrinsic of the method with the given class, name and
+    /// descriptor, e.g. `("java/lang/String", "equals",
+    /// "(Ljava/lang/Object;)Z")`. The intrinsic is invoked with the arguments
+    /// of the method, starting with the receiver for instance methods, and
+    /// has to compute what the bytecode of the method computes. Replaces and
+    /// returns the intrinsic that was registered for the method before.
+    pub fn register(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+        method: NativeMethod,
+    ) -> Option<NativeMethod> {
+        self.methods.write().unwrap().insert(
+            (class.to_string(), name.to_string(), descriptor.to_string()),
+            method,
+        )
+    }
+
+    /// Finds the intrinsic of the method with the given class, name and
+    /// descriptor, preferring registered intrinsics over the built-in ones.
+    /// Returns [`None`] if the method has no intrinsic, or intrinsics are
+    /// disabled, in which case the method is interpreted.
+    pub fn find(&self, class: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = (class.to_string(), name.to_string(), descriptor.to_string());
+        self.methods
+            .read()
+            .unwrap()
+            .get(&key)
+            .copied()
+            .or_else(|| find_intrinsic(class, name, descriptor))
+    }
+}
+
+impl Default for Intrinsics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::native::math;
+    use crate::vm::native::NativeResult;
+    use crate::vm::runtime::Runtime;
+    use crate::vm::thread::Thread;
+    use crate::vm::types::NativeValue;
+    use std::sync::Arc;
+
+    fn hash_code(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+        Ok(Some(NativeValue::Integer(0)))
+    }
+
+    #[test]
+    fn test_find() {
+        let intrinsics = Intrinsics::new();
+        let find = |name, descriptor| {
+            intrinsics
+                .find("java/lang/Math", name, descriptor)
+                .map(|m| m as *const () as usize)
+        };
+        assert_eq!(
+            Some(math::math_multiply_high as *const () as usize),
+            find("multiplyHigh", "(JJ)J")
+        );
+        assert_eq!(None, find("multiplyHigh", "(II)I"));
+
+        assert!(intrinsics
+            .register("java/util/Arrays", "hashCode", "([I)I", hash_code)
+            .is_none());
+        // replaces the built-in intrinsic
+        intrinsics.register("java/lang/Math", "multiplyHigh", "(JJ)J", hash_code);
+        assert_eq!(
+            Some(hash_code as *const () as usize),
+            find("multiplyHigh", "(JJ)J")
+        );
+        let mut thread = Thread::new(Arc::new(Runtime::default()));
+        let method = intrinsics
+            .find("java/util/Arrays", "hashCode", "([I)I")
+            .unwrap();
+        assert_eq!(
+            Ok(Some(NativeValue::Integer(0))),
+            method(&mut thread, &[NativeValue::Reference(0)])
+        );
+
+        intrinsics.set_enabled(false);
+        assert_eq!(None, find("multiplyHigh", "(JJ)J"));
+        assert_eq!(None, find("floorDiv", "(II)I"));
+        assert!(intrinsics
+            .find("java/util/Arrays", "hashCode", "([I)I")
+            .is_none());
+        intrinsics.set_enabled(true);
+        assert_eq!(
+            Some(math::math_floor_div_int as *const () as usize),
+            find("floorDiv", "(II)I")
+        );
+    }
+}
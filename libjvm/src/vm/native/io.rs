@@ -0,0 +1,1081 @@
+use crate::vm::descriptor::FieldType;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::NULL;
+use crate::vm::native::string::{new_string, read_string, STRING};
+use crate::vm::native::{
+    array_mut, boolean_arg, byte_array_range, file_descriptor, int_arg, int_field, long_arg,
+    read_into_byte_array, reference_arg, reference_field, set_field, set_file_descriptor,
+    NativeResult,
+};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use libjvm_macros::jvm_native;
+use libvfs::file::File;
+use libvfs::FileSystem;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+
+const STDIN_FD: i32 = 0;
+const STDOUT_FD: i32 = 1;
+const STDERR_FD: i32 = 2;
+/// The first file descriptor handed out by a [`FileTable`].
+const FIRST_FD: i32 = 3;
+
+/// `BA_EXISTS` of `java.io.FileSystem`.
+pub const BA_EXISTS: i32 = 0x01;
+/// `BA_REGULAR` of `java.io.FileSystem`.
+pub const BA_REGULAR: i32 = 0x02;
+/// `BA_DIRECTORY` of `java.io.FileSystem`.
+pub const BA_DIRECTORY: i32 = 0x04;
+
+/// How a file is opened.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OpenMode {
+    /// For a `FileInputStream`, or a `RandomAccessFile` in mode `r`. The file
+    /// must exist.
+    Read,
+    /// For a `FileOutputStream`. The file is created if it doesn't exist, and
+    /// truncated unless `append` is set.
+    Write { append: bool },
+    /// For a `RandomAccessFile` in mode `rw`. The file is created if it
+    /// doesn't exist, but never truncated.
+    ReadWrite,
+}
+
+/// The files that are opened by Java code, by their file descriptor (the `fd`
+/// field of `java.io.FileDescriptor`).
+///
+/// The file descriptors 0, 1 and 2 are the standard streams of the VM. They are
+/// the streams of the host process by default, but can be replaced, e.g. to
+/// capture the output of a Java program.
+pub struct FileTable {
+    files: Mutex<HashMap<i32, Arc<Mutex<File>>>>,
+    next_fd: AtomicI32,
+    stdin: Arc<Mutex<Box<dyn Read + Send>>>,
+    stdout: Arc<Mutex<Box<dyn Write + Send>>>,
+    stderr: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+/// A handle to the standard input of the VM that can be moved to another
+/// thread, e.g. to forward it to a child process. It reads from the stream
+/// that is currently set, even if it is replaced after the handle was created.
+#[derive(Clone)]
+pub struct StdInput(Arc<Mutex<Box<dyn Read + Send>>>);
+
+impl Read for StdInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+/// A handle to the standard output or error of the VM, see [`StdInput`].
+#[derive(Clone)]
+pub struct StdOutput(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Write for StdOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// A handle to a file of a [`FileTable`], see [`FileTable::file_handle`].
+#[derive(Clone)]
+pub struct FileHandle(Arc<Mutex<File>>);
+
+impl Read for FileHandle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+impl Write for FileHandle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+impl Default for FileTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileTable {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::new(HashMap::new()),
+            next_fd: AtomicI32::new(FIRST_FD),
+            stdin: Arc::new(Mutex::new(Box::new(std::io::stdin()))),
+            stdout: Arc::new(Mutex::new(Box::new(std::io::stdout()))),
+            stderr: Arc::new(Mutex::new(Box::new(std::io::stderr()))),
+        }
+    }
+
+    pub fn set_stdin(&self, stdin: Box<dyn Read + Send>) {
+        *self.stdin.lock().unwrap() = stdin;
+    }
+
+    pub fn set_stdout(&self, stdout: Box<dyn Write + Send>) {
+        *self.stdout.lock().unwrap() = stdout;
+    }
+
+    pub fn set_stderr(&self, stderr: Box<dyn Write + Send>) {
+        *self.stderr.lock().unwrap() = stderr;
+    }
+
+    pub fn stdin_handle(&self) -> StdInput {
+        StdInput(self.stdin.clone())
+    }
+
+    pub fn stdout_handle(&self) -> StdOutput {
+        StdOutput(self.stdout.clone())
+    }
+
+    pub fn stderr_handle(&self) -> StdOutput {
+        StdOutput(self.stderr.clone())
+    }
+
+    /// Opens the file at the given path, as done by the `open0` natives, and
+    /// returns its file descriptor.
+    pub fn open(&self, fs: &FileSystem, path: &str, mode: OpenMode) -> std::io::Result<i32> {
+        if fs.is_dir(path).unwrap_or(false) {
+            return Err(Error::other("Is a directory"));
+        }
+        let exists = fs.exists(path).unwrap_or(false);
+        let file = match mode {
+            OpenMode::Read => fs.open(path)?,
+            OpenMode::Write { append: true } if exists => {
+                let mut file = fs.open_read_write(path)?;
+                file.seek(SeekFrom::End(0))?;
+                file
+            }
+            OpenMode::Write { .. } => {
+                if exists {
+                    fs.remove_file(path)?;
+                }
+                fs.create(path)?
+            }
+            OpenMode::ReadWrite if exists => fs.open_read_write(path)?,
+            OpenMode::ReadWrite => fs.create(path)?,
+        };
+        Ok(self.insert(file))
+    }
+
+    /// Adds a stream that is not a file of the file system, e.g. a pipe to a
+    /// child process, and returns its file descriptor.
+    pub fn insert(&self, file: File) -> i32 {
+        let fd = self.next_fd.fetch_add(1, Ordering::Relaxed);
+        self.files
+            .lock()
+            .unwrap()
+            .insert(fd, Arc::new(Mutex::new(file)));
+        fd
+    }
+
+    /// A handle to an open file that stays usable after the file descriptor is
+    /// closed, e.g. to forward the output of a child process to a file that
+    /// Java code redirected it to.
+    pub fn file_handle(&self, fd: i32) -> std::io::Result<FileHandle> {
+        let files = self.files.lock().unwrap();
+        Ok(FileHandle(files.get(&fd).cloned().ok_or_else(bad_fd)?))
+    }
+
+    /// Reads into the given buffer, as done by `readBytes`. Returns the number
+    /// of bytes read, which is 0 at the end of the file.
+    pub fn read(&self, fd: i32, buf: &mut [u8]) -> std::io::Result<usize> {
+        match fd {
+            STDIN_FD => self.stdin.lock().unwrap().read(buf),
+            _ => self.with_file(fd, |f| f.read(buf)),
+        }
+    }
+
+    /// Writes the complete buffer, as done by `writeBytes`.
+    pub fn write(&self, fd: i32, buf: &[u8]) -> std::io::Result<()> {
+        match fd {
+            STDOUT_FD => self.stdout.lock().unwrap().write_all(buf),
+            STDERR_FD => self.stderr.lock().unwrap().write_all(buf),
+            _ => self.with_file(fd, |f| f.write_all(buf)),
+        }
+    }
+
+    /// The current position in the file, as returned by `getFilePointer`.
+    pub fn position(&self, fd: i32) -> std::io::Result<u64> {
+        self.with_file(fd, |f| f.stream_position())
+    }
+
+    /// Sets the position in the file, as done by `seek0`.
+    pub fn seek(&self, fd: i32, position: u64) -> std::io::Result<()> {
+        self.with_file(fd, |f| f.seek(SeekFrom::Start(position)).map(|_| ()))
+    }
+
+    /// The length of the file, as returned by `length`.
+    pub fn length(&self, fd: i32) -> std::io::Result<u64> {
+        self.with_file(fd, |f| {
+            let position = f.stream_position()?;
+            let length = f.seek(SeekFrom::End(0))?;
+            f.seek(SeekFrom::Start(position))?;
+            Ok(length)
+        })
+    }
+
+    /// The number of bytes that can be read without blocking, as returned by
+    /// `available0`.
+    /// Streams that can't seek, like pipes, have nothing available.
+    pub fn available(&self, fd: i32) -> std::io::Result<u64> {
+        match self.length(fd) {
+            Ok(length) => Ok(length.saturating_sub(self.position(fd)?)),
+            Err(e) if e.kind() == ErrorKind::Unsupported => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Closes the file, as done by `close0`. Closing a file descriptor that is
+    /// not open is an error. The standard streams are only flushed, since they
+    /// are shared with the host.
+    pub fn close(&self, fd: i32) -> std::io::Result<()> {
+        match fd {
+            STDIN_FD => return Ok(()),
+            STDOUT_FD => return self.stdout.lock().unwrap().flush(),
+            STDERR_FD => return self.stderr.lock().unwrap().flush(),
+            _ => {}
+        }
+        let file = self.files.lock().unwrap().remove(&fd).ok_or_else(bad_fd)?;
+        let mut file = file.lock().unwrap();
+        file.flush()
+    }
+
+    /// The number of currently open files.
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn with_file<T>(
+        &self,
+        fd: i32,
+        f: impl FnOnce(&mut File) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        // don't hold the table lock during I/O, only the lock of this file
+        let file = self
+            .files
+            .lock()
+            .unwrap()
+            .get(&fd)
+            .cloned()
+            .ok_or_else(bad_fd)?;
+        let mut file = file.lock().unwrap();
+        f(&mut file)
+    }
+}
+
+fn bad_fd() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Bad file descriptor")
+}
+
+/// `getBooleanAttributes0`, a combination of [`BA_EXISTS`], [`BA_REGULAR`] and
+/// [`BA_DIRECTORY`].
+pub fn boolean_attributes(fs: &FileSystem, path: &str) -> i32 {
+    if !fs.exists(path).unwrap_or(false) {
+        return 0;
+    }
+    if fs.is_dir(path).unwrap_or(false) {
+        BA_EXISTS | BA_DIRECTORY
+    } else {
+        BA_EXISTS | BA_REGULAR
+    }
+}
+
+/// `getLength`, which is 0 if the file doesn't exist.
+pub fn length(fs: &FileSystem, path: &str) -> u64 {
+    fs.open(path)
+        .and_then(|mut f| f.seek(SeekFrom::End(0)))
+        .unwrap_or(0)
+}
+
+/// `delete0`, which deletes a file or an (empty) directory.
+pub fn delete(fs: &FileSystem, path: &str) -> bool {
+    if fs.is_dir(path).unwrap_or(false) {
+        fs.remove_dir(path).is_ok()
+    } else {
+        fs.remove_file(path).is_ok()
+    }
+}
+
+/// `createDirectory`, which fails if the directory already exists.
+pub fn create_directory(fs: &FileSystem, path: &str) -> bool {
+    !fs.exists(path).unwrap_or(false) && fs.create_dir(path).is_ok()
+}
+
+/// `list`, which returns the names of the entries of a directory, or [`None`]
+/// if the path is not a directory.
+pub fn list(fs: &FileSystem, path: &str) -> Option<Vec<String>> {
+    if !fs.is_dir(path).unwrap_or(false) {
+        return None;
+    }
+    let entries = fs.read_dir(path).ok()?;
+    Some(
+        entries
+            .iter()
+            .filter_map(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect(),
+    )
+}
+
+/// `private native void open0(String name)` of `FileInputStream`.
+#[jvm_native(
+    class = "java/io/FileInputStream",
+    name = "open0",
+    sig = "(Ljava/lang/String;)V"
+)]
+pub fn file_input_stream_open0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    open(thread, args, OpenMode::Read)
+}
+
+/// `private native int read0()` of `FileInputStream`.
+#[jvm_native(class = "java/io/FileInputStream", name = "read0", sig = "()I")]
+pub fn file_input_stream_read0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    read_byte(thread, args)
+}
+
+/// `private native int readBytes(byte[] b, int off, int len)` of
+/// `FileInputStream`.
+#[jvm_native(class = "java/io/FileInputStream", name = "readBytes", sig = "([BII)I")]
+pub fn file_input_stream_read_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    read_bytes(thread, args)
+}
+
+/// `private native int available0()` of `FileInputStream`.
+#[jvm_native(class = "java/io/FileInputStream", name = "available0", sig = "()I")]
+pub fn file_input_stream_available0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let available = thread
+        .runtime()
+        .files()
+        .available(fd)
+        .map_err(VmError::from)?;
+    Ok(Some(NativeValue::Integer(
+        available.min(i32::MAX as u64) as i32
+    )))
+}
+
+/// `private native void open0(String name, boolean append)` of
+/// `FileOutputStream`.
+#[jvm_native(
+    class = "java/io/FileOutputStream",
+    name = "open0",
+    sig = "(Ljava/lang/String;Z)V"
+)]
+pub fn file_output_stream_open0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let append = boolean_arg(args, 2)?;
+    open(thread, args, OpenMode::Write { append })
+}
+
+/// `private native void write(int b, boolean append)` of `FileOutputStream`.
+/// Files opened for appending are positioned at their end already.
+#[jvm_native(class = "java/io/FileOutputStream", name = "write", sig = "(IZ)V")]
+pub fn file_output_stream_write(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    write_byte(thread, args)
+}
+
+/// `private native void writeBytes(byte[] b, int off, int len, boolean
+/// append)` of `FileOutputStream`.
+#[jvm_native(
+    class = "java/io/FileOutputStream",
+    name = "writeBytes",
+    sig = "([BIIZ)V"
+)]
+pub fn file_output_stream_write_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    write_bytes(thread, args)
+}
+
+/// `private native void open0(String name, int mode)` of `RandomAccessFile`,
+/// where the mode is a combination of `O_RDONLY`, `O_RDWR` and the sync flags,
+/// which have no effect.
+#[jvm_native(
+    class = "java/io/RandomAccessFile",
+    name = "open0",
+    sig = "(Ljava/lang/String;I)V"
+)]
+pub fn random_access_file_open0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let mode = match int_arg(args, 2)? & O_RDWR {
+        0 => OpenMode::Read,
+        _ => OpenMode::ReadWrite,
+    };
+    open(thread, args, mode)
+}
+
+/// `private native int read0()` of `RandomAccessFile`.
+#[jvm_native(class = "java/io/RandomAccessFile", name = "read0", sig = "()I")]
+pub fn random_access_file_read0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    read_byte(thread, args)
+}
+
+/// `private native int readBytes(byte[] b, int off, int len)` of
+/// `RandomAccessFile`.
+#[jvm_native(
+    class = "java/io/RandomAccessFile",
+    name = "readBytes",
+    sig = "([BII)I"
+)]
+pub fn random_access_file_read_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    read_bytes(thread, args)
+}
+
+/// `private native void write0(int b)` of `RandomAccessFile`.
+#[jvm_native(class = "java/io/RandomAccessFile", name = "write0", sig = "(I)V")]
+pub fn random_access_file_write0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    write_byte(thread, args)
+}
+
+/// `private native void writeBytes(byte[] b, int off, int len)` of
+/// `RandomAccessFile`.
+#[jvm_native(
+    class = "java/io/RandomAccessFile",
+    name = "writeBytes",
+    sig = "([BII)V"
+)]
+pub fn random_access_file_write_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    write_bytes(thread, args)
+}
+
+/// `public native long getFilePointer()` of `RandomAccessFile`.
+#[jvm_native(
+    class = "java/io/RandomAccessFile",
+    name = "getFilePointer",
+    sig = "()J"
+)]
+pub fn random_access_file_get_file_pointer(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let position = thread
+        .runtime()
+        .files()
+        .position(fd)
+        .map_err(VmError::from)?;
+    Ok(Some(NativeValue::Long(position as i64)))
+}
+
+/// `private native void seek0(long pos)` of `RandomAccessFile`.
+#[jvm_native(class = "java/io/RandomAccessFile", name = "seek0", sig = "(J)V")]
+pub fn random_access_file_seek0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let position = u64::try_from(long_arg(args, 1)?)
+        .map_err(|_| VmError::from(Exception::Io("Negative seek offset".to_string())))?;
+    thread
+        .runtime()
+        .files()
+        .seek(fd, position)
+        .map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// `public native long length()` of `RandomAccessFile`.
+#[jvm_native(class = "java/io/RandomAccessFile", name = "length", sig = "()J")]
+pub fn random_access_file_length(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let length = thread.runtime().files().length(fd).map_err(VmError::from)?;
+    Ok(Some(NativeValue::Long(length as i64)))
+}
+
+/// `private native void close0()` of `FileDescriptor`, which closes the file
+/// and invalidates the descriptor. Closing it again does nothing.
+#[jvm_native(class = "java/io/FileDescriptor", name = "close0", sig = "()V")]
+pub fn file_descriptor_close0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let descriptor = reference_arg(args, 0)?;
+    let runtime = thread.runtime();
+    let fd = {
+        let mut heap = runtime.heap().write().unwrap();
+        let fd = int_field(&heap, descriptor, "fd")?;
+        set_field(&mut heap, descriptor, "fd", "I", NativeValue::Integer(-1))?;
+        fd
+    };
+    if fd != -1 {
+        runtime.files().close(fd).map_err(VmError::from)?;
+    }
+    Ok(None)
+}
+
+/// `public native int getBooleanAttributes0(File f)` of `UnixFileSystem`.
+#[jvm_native(
+    class = "java/io/UnixFileSystem",
+    name = "getBooleanAttributes0",
+    sig = "(Ljava/io/File;)I"
+)]
+pub fn unix_file_system_get_boolean_attributes0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let path = path_of(thread, args)?;
+    let attributes = boolean_attributes(thread.runtime().file_system(), &path);
+    Ok(Some(NativeValue::Integer(attributes)))
+}
+
+/// `public native long getLength(File f)` of `UnixFileSystem`.
+#[jvm_native(
+    class = "java/io/UnixFileSystem",
+    name = "getLength",
+    sig = "(Ljava/io/File;)J"
+)]
+pub fn unix_file_system_get_length(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let path = path_of(thread, args)?;
+    let length = length(thread.runtime().file_system(), &path);
+    Ok(Some(NativeValue::Long(length as i64)))
+}
+
+/// `private native boolean delete0(File f)` of `UnixFileSystem`.
+#[jvm_native(
+    class = "java/io/UnixFileSystem",
+    name = "delete0",
+    sig = "(Ljava/io/File;)Z"
+)]
+pub fn unix_file_system_delete0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let path = path_of(thread, args)?;
+    let deleted = delete(thread.runtime().file_system(), &path);
+    Ok(Some(NativeValue::Boolean(deleted)))
+}
+
+/// `public native boolean createDirectory(File f)` of `UnixFileSystem`, which
+/// `File.mkdir` and `File.mkdirs` call.
+#[jvm_native(
+    class = "java/io/UnixFileSystem",
+    name = "createDirectory",
+    sig = "(Ljava/io/File;)Z"
+)]
+pub fn unix_file_system_create_directory(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let path = path_of(thread, args)?;
+    let created = create_directory(thread.runtime().file_system(), &path);
+    Ok(Some(NativeValue::Boolean(created)))
+}
+
+/// `public native String[] list(File f)` of `UnixFileSystem`, which returns
+/// `null` if the file is not a directory.
+#[jvm_native(
+    class = "java/io/UnixFileSystem",
+    name = "list",
+    sig = "(Ljava/io/File;)[Ljava/lang/String;"
+)]
+pub fn unix_file_system_list(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let path = path_of(thread, args)?;
+    let runtime = thread.runtime();
+    let Some(entries) = list(runtime.file_system(), &path) else {
+        return Ok(Some(NativeValue::Reference(NULL)));
+    };
+    let (string, _) = runtime
+        .instance_layout(STRING)
+        .ok_or_else(|| VmError::Internal(format!("{} is not loaded", STRING)))?;
+    let mut heap = runtime.heap().write().unwrap();
+    let names = heap.allocate_array(Arc::new(FieldType::Object(string)), entries.len() as i32)?;
+    for (i, entry) in entries.iter().enumerate() {
+        let name = new_string(runtime, &mut heap, entry)?;
+        array_mut(&mut heap, names)?.set_element(i as i32, NativeValue::Reference(name))?;
+    }
+    Ok(Some(NativeValue::Reference(names)))
+}
+
+/// `O_RDWR` of `RandomAccessFile`.
+const O_RDWR: i32 = 2;
+
+/// Opens the file at the path of the `String` argument at index 1, and stores
+/// its file descriptor in the `FileDescriptor` of the stream. Fails with a
+/// `FileNotFoundException` like `open0`, whatever the reason is.
+fn open(thread: &mut Thread, args: &[NativeValue], mode: OpenMode) -> NativeResult {
+    let stream = reference_arg(args, 0)?;
+    let runtime = thread.runtime();
+    let path = read_string(&runtime.heap().read().unwrap(), reference_arg(args, 1)?)?;
+    let fd = runtime
+        .files()
+        .open(runtime.file_system(), &path, mode)
+        .map_err(|e| VmError::from(Exception::FileNotFound(format!("{} ({})", path, e))))?;
+    set_file_descriptor(&mut runtime.heap().write().unwrap(), stream, fd)?;
+    Ok(None)
+}
+
+/// Reads a single byte, or returns -1 at the end of the file.
+fn read_byte(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let mut buf = [0_u8];
+    let n = thread
+        .runtime()
+        .files()
+        .read(fd, &mut buf)
+        .map_err(VmError::from)?;
+    Ok(Some(NativeValue::Integer(match n {
+        0 => -1,
+        _ => buf[0] as i32,
+    })))
+}
+
+/// Reads into the `byte[]` argument at index 1 at the offset and up to the
+/// length of the arguments after it, see [`read_into_byte_array`].
+fn read_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let (bytes, off, len) = (
+        reference_arg(args, 1)?,
+        int_arg(args, 2)?,
+        int_arg(args, 3)?,
+    );
+    let runtime = thread.runtime();
+    let n = read_into_byte_array(runtime.heap(), bytes, off, len, |buf| {
+        runtime.files().read(fd, buf)
+    })?;
+    Ok(Some(NativeValue::Integer(n)))
+}
+
+/// Writes the `int` argument at index 1 as a single byte.
+fn write_byte(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let b = int_arg(args, 1)? as u8;
+    thread
+        .runtime()
+        .files()
+        .write(fd, &[b])
+        .map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// Writes the bytes of the `byte[]` argument at index 1 at the offset and with
+/// the length of the arguments after it.
+fn write_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = fd_of(thread, args)?;
+    let (bytes, off, len) = (
+        reference_arg(args, 1)?,
+        int_arg(args, 2)?,
+        int_arg(args, 3)?,
+    );
+    let runtime = thread.runtime();
+    let buf = byte_array_range(&runtime.heap().read().unwrap(), bytes, off, len)?;
+    runtime.files().write(fd, &buf).map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// The file descriptor of the stream that a native is invoked on, see
+/// [`file_descriptor`].
+fn fd_of(thread: &Thread, args: &[NativeValue]) -> Result<i32, VmError> {
+    file_descriptor(
+        &thread.runtime().heap().read().unwrap(),
+        reference_arg(args, 0)?,
+    )
+}
+
+/// The path of the `File` argument at index 1, its `path` field.
+fn path_of(thread: &Thread, args: &[NativeValue]) -> Result<String, VmError> {
+    let heap = thread.runtime().heap().read().unwrap();
+    let path = reference_field(&heap, reference_arg(args, 1)?, "path", "Ljava/lang/String;")?;
+    read_string(&heap, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::native::array;
+    use crate::vm::native::test_util::{
+        self, bytes_of, call, new_bytes, new_java_string, new_with_fd, thrown,
+    };
+    use crate::vm::types::NativeValue::{Boolean, Integer, Long, Reference};
+    use crate::vm::VM;
+
+    const STREAM: &str = "java/io/FileInputStream";
+    const OUTPUT_STREAM: &str = "java/io/FileOutputStream";
+    const RANDOM_ACCESS_FILE: &str = "java/io/RandomAccessFile";
+    const FILE_DESCRIPTOR: &str = "java/io/FileDescriptor";
+    const UNIX_FILE_SYSTEM: &str = "java/io/UnixFileSystem";
+
+    fn vm() -> VM {
+        test_util::vm(&[
+            ("io", "java/io/File"),
+            ("io", STREAM),
+            ("io", OUTPUT_STREAM),
+            ("io", RANDOM_ACCESS_FILE),
+        ])
+    }
+
+    fn new_file(vm: &mut VM, path: &str) -> usize {
+        let path = new_java_string(vm, path);
+        let class = vm.for_name("java.io.File").unwrap();
+        let mut heap = vm.heap().write().unwrap();
+        let file = heap.allocate_instance(&class);
+        set_field(
+            &mut heap,
+            file,
+            "path",
+            "Ljava/lang/String;",
+            Reference(path),
+        )
+        .unwrap();
+        file
+    }
+
+    /// Closes the `FileDescriptor` of a stream, like `close` does.
+    fn close(vm: &VM, thread: &mut Thread, stream: usize) -> NativeResult {
+        let heap = vm.heap().read().unwrap();
+        let fd = reference_field(&heap, stream, "fd", "Ljava/io/FileDescriptor;").unwrap();
+        drop(heap);
+        call(thread, FILE_DESCRIPTOR, "close0", "()V", &[Reference(fd)])
+    }
+
+    #[test]
+    fn test_file_stream_natives() {
+        let mut vm = vm();
+        let mut thread = Thread::new(vm.runtime().clone());
+        let path = Reference(new_java_string(&vm, "out.txt"));
+
+        let out = new_with_fd(&mut vm, OUTPUT_STREAM);
+        let open = "(Ljava/lang/String;Z)V";
+        let args = [Reference(out), path.clone(), Boolean(false)];
+        call(&mut thread, OUTPUT_STREAM, "open0", open, &args).unwrap();
+        let hello = new_bytes(&vm, b"-hello-");
+        let args = [
+            Reference(out),
+            Reference(hello),
+            Integer(1),
+            Integer(5),
+            Boolean(false),
+        ];
+        call(&mut thread, OUTPUT_STREAM, "writeBytes", "([BIIZ)V", &args).unwrap();
+        let args = [Reference(out), Integer(b'!' as i32), Boolean(false)];
+        call(&mut thread, OUTPUT_STREAM, "write", "(IZ)V", &args).unwrap();
+        close(&vm, &mut thread, out).unwrap();
+        // closing again does nothing, but writing fails
+        close(&vm, &mut thread, out).unwrap();
+        assert_eq!(
+            "java/io/IOException",
+            thrown(call(&mut thread, OUTPUT_STREAM, "write", "(IZ)V", &args))
+        );
+
+        let input = new_with_fd(&mut vm, STREAM);
+        let open = "(Ljava/lang/String;)V";
+        call(
+            &mut thread,
+            STREAM,
+            "open0",
+            open,
+            &[Reference(input), path],
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(Integer(6))),
+            call(
+                &mut thread,
+                STREAM,
+                "available0",
+                "()I",
+                &[Reference(input)]
+            )
+        );
+        assert_eq!(
+            Ok(Some(Integer(b'h' as i32))),
+            call(&mut thread, STREAM, "read0", "()I", &[Reference(input)])
+        );
+        let buf = new_bytes(&vm, &[0; 8]);
+        let args = [Reference(input), Reference(buf), Integer(2), Integer(6)];
+        assert_eq!(
+            Ok(Some(Integer(5))),
+            call(&mut thread, STREAM, "readBytes", "([BII)I", &args)
+        );
+        assert_eq!(b"\0\0ello!\0", bytes_of(&vm, buf).as_slice());
+        assert_eq!(
+            Ok(Some(Integer(-1))),
+            call(&mut thread, STREAM, "read0", "()I", &[Reference(input)])
+        );
+        let args = [Reference(input), Reference(buf), Integer(4), Integer(5)];
+        assert_eq!(
+            "java/lang/ArrayIndexOutOfBoundsException",
+            thrown(call(&mut thread, STREAM, "readBytes", "([BII)I", &args))
+        );
+        close(&vm, &mut thread, input).unwrap();
+        assert!(vm.runtime().files().is_empty());
+
+        let missing = Reference(new_java_string(&vm, "missing"));
+        assert_eq!(
+            "java/io/FileNotFoundException",
+            thrown(call(
+                &mut thread,
+                STREAM,
+                "open0",
+                open,
+                &[Reference(input), missing]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_random_access_file_natives() {
+        let mut vm = vm();
+        let mut thread = Thread::new(vm.runtime().clone());
+        let raf = new_with_fd(&mut vm, RANDOM_ACCESS_FILE);
+        let path = new_java_string(&vm, "raf");
+
+        let args = [Reference(raf), Reference(path), Integer(O_RDWR)];
+        let open = "(Ljava/lang/String;I)V";
+        call(&mut thread, RANDOM_ACCESS_FILE, "open0", open, &args).unwrap();
+        let digits = new_bytes(&vm, b"0123456789");
+        let args = [Reference(raf), Reference(digits), Integer(0), Integer(10)];
+        call(
+            &mut thread,
+            RANDOM_ACCESS_FILE,
+            "writeBytes",
+            "([BII)V",
+            &args,
+        )
+        .unwrap();
+        let seek = |thread: &mut Thread, position| {
+            let args = [Reference(raf), Long(position)];
+            call(thread, RANDOM_ACCESS_FILE, "seek0", "(J)V", &args)
+        };
+        seek(&mut thread, 2).unwrap();
+        let args = [Reference(raf), Integer(b'a' as i32)];
+        call(&mut thread, RANDOM_ACCESS_FILE, "write0", "(I)V", &args).unwrap();
+        assert_eq!(
+            Ok(Some(Long(3))),
+            call(
+                &mut thread,
+                RANDOM_ACCESS_FILE,
+                "getFilePointer",
+                "()J",
+                &[Reference(raf)]
+            )
+        );
+        assert_eq!(
+            Ok(Some(Long(10))),
+            call(
+                &mut thread,
+                RANDOM_ACCESS_FILE,
+                "length",
+                "()J",
+                &[Reference(raf)]
+            )
+        );
+        assert_eq!("java/io/IOException", thrown(seek(&mut thread, -1)));
+
+        seek(&mut thread, 1).unwrap();
+        let buf = new_bytes(&vm, &[0; 3]);
+        let args = [Reference(raf), Reference(buf), Integer(0), Integer(3)];
+        assert_eq!(
+            Ok(Some(Integer(3))),
+            call(
+                &mut thread,
+                RANDOM_ACCESS_FILE,
+                "readBytes",
+                "([BII)I",
+                &args
+            )
+        );
+        assert_eq!(b"1a3", bytes_of(&vm, buf).as_slice());
+        assert_eq!(
+            Ok(Some(Integer(b'4' as i32))),
+            call(
+                &mut thread,
+                RANDOM_ACCESS_FILE,
+                "read0",
+                "()I",
+                &[Reference(raf)]
+            )
+        );
+        close(&vm, &mut thread, raf).unwrap();
+    }
+
+    #[test]
+    fn test_unix_file_system_natives() {
+        let mut vm = vm();
+        let mut thread = Thread::new(vm.runtime().clone());
+        let fs = vm.runtime().file_system().clone();
+        let (dir, file) = (new_file(&mut vm, "a"), new_file(&mut vm, "a/file"));
+        let file_system = |thread: &mut Thread, name, descriptor, file| {
+            let args = [Reference(NULL), Reference(file)];
+            call(thread, UNIX_FILE_SYSTEM, name, descriptor, &args)
+        };
+
+        let create = "(Ljava/io/File;)Z";
+        assert_eq!(
+            Ok(Some(Boolean(true))),
+            file_system(&mut thread, "createDirectory", create, dir)
+        );
+        assert_eq!(
+            Ok(Some(Boolean(false))),
+            file_system(&mut thread, "createDirectory", create, dir)
+        );
+        fs.create("a/file").unwrap().write_all(b"content").unwrap();
+
+        let attributes = "(Ljava/io/File;)I";
+        assert_eq!(
+            Ok(Some(Integer(BA_EXISTS | BA_DIRECTORY))),
+            file_system(&mut thread, "getBooleanAttributes0", attributes, dir)
+        );
+        assert_eq!(
+            Ok(Some(Integer(BA_EXISTS | BA_REGULAR))),
+            file_system(&mut thread, "getBooleanAttributes0", attributes, file)
+        );
+        assert_eq!(
+            Ok(Some(Long(7))),
+            file_system(&mut thread, "getLength", "(Ljava/io/File;)J", file)
+        );
+
+        let list = "(Ljava/io/File;)[Ljava/lang/String;";
+        let Ok(Some(Reference(names))) = file_system(&mut thread, "list", list, dir) else {
+            panic!("no entries")
+        };
+        {
+            let heap = vm.heap().read().unwrap();
+            let names = array(&heap, names).unwrap();
+            assert_eq!("Ljava/lang/String;", names.component().to_string());
+            assert_eq!(1, names.len());
+            let Ok(Reference(name)) = names.element(0) else {
+                panic!("not a string")
+            };
+            assert_eq!("file", read_string(&heap, name).unwrap());
+        }
+        assert_eq!(
+            Ok(Some(Reference(NULL))),
+            file_system(&mut thread, "list", list, file)
+        );
+
+        let delete = "(Ljava/io/File;)Z";
+        assert_eq!(
+            Ok(Some(Boolean(true))),
+            file_system(&mut thread, "delete0", delete, file)
+        );
+        assert_eq!(
+            Ok(Some(Integer(0))),
+            file_system(&mut thread, "getBooleanAttributes0", attributes, file)
+        );
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        let fs = FileSystem::new_in_memory_fs();
+        let files = FileTable::new();
+
+        let out = files
+            .open(&fs, "/out.txt", OpenMode::Write { append: false })
+            .unwrap();
+        files.write(out, b"hello").unwrap();
+        files.close(out).unwrap();
+
+        let out = files
+            .open(&fs, "/out.txt", OpenMode::Write { append: true })
+            .unwrap();
+        files.write(out, b" world").unwrap();
+        files.close(out).unwrap();
+
+        let input = files.open(&fs, "/out.txt", OpenMode::Read).unwrap();
+        assert_eq!(11, files.available(input).unwrap());
+        let mut buf = [0_u8; 32];
+        let n = files.read(input, &mut buf).unwrap();
+        assert_eq!(b"hello world", &buf[..n]);
+        assert_eq!(0, files.read(input, &mut buf).unwrap());
+        files.close(input).unwrap();
+
+        assert!(files.is_empty());
+        assert!(files.close(input).is_err());
+    }
+
+    #[test]
+    fn test_truncate_on_write() {
+        let fs = FileSystem::new_in_memory_fs();
+        let files = FileTable::new();
+        for content in [&b"long content"[..], &b"short"[..]] {
+            let fd = files
+                .open(&fs, "f", OpenMode::Write { append: false })
+                .unwrap();
+            files.write(fd, content).unwrap();
+            files.close(fd).unwrap();
+        }
+        assert_eq!(5, length(&fs, "f"));
+    }
+
+    #[test]
+    fn test_random_access() {
+        let fs = FileSystem::new_in_memory_fs();
+        let files = FileTable::new();
+        let fd = files.open(&fs, "raf", OpenMode::ReadWrite).unwrap();
+        files.write(fd, b"0123456789").unwrap();
+        files.seek(fd, 2).unwrap();
+        files.write(fd, b"ab").unwrap();
+        assert_eq!(4, files.position(fd).unwrap());
+        assert_eq!(10, files.length(fd).unwrap());
+        assert_eq!(4, files.position(fd).unwrap());
+
+        files.seek(fd, 0).unwrap();
+        let mut buf = [0_u8; 10];
+        files.read(fd, &mut buf).unwrap();
+        assert_eq!(b"01ab456789", &buf);
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let fs = FileSystem::new_in_memory_fs();
+        let files = FileTable::new();
+        assert!(files.open(&fs, "missing", OpenMode::Read).is_err());
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_file_operations() {
+        let fs = FileSystem::new_in_memory_fs();
+        assert_eq!(0, boolean_attributes(&fs, "a"));
+
+        assert!(create_directory(&fs, "a"));
+        assert!(create_directory(&fs, "a/b"));
+        assert_eq!(BA_EXISTS | BA_DIRECTORY, boolean_attributes(&fs, "a/b"));
+        assert!(!create_directory(&fs, "a/b"));
+        assert!(create_directory(&fs, "a/d"));
+
+        fs.create("a/file").unwrap();
+        assert_eq!(BA_EXISTS | BA_REGULAR, boolean_attributes(&fs, "a/file"));
+
+        let mut entries = list(&fs, "a").unwrap();
+        entries.sort();
+        assert_eq!(vec!["b", "d", "file"], entries);
+        assert_eq!(None, list(&fs, "a/file"));
+
+        assert!(delete(&fs, "a/file"));
+        assert!(!delete(&fs, "a/file"));
+        assert_eq!(0, boolean_attributes(&fs, "a/file"));
+    }
+
+    #[test]
+    fn test_standard_streams() {
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let files = FileTable::new();
+        let out = Arc::new(Mutex::new(vec![]));
+        files.set_stdout(Box::new(Shared(out.clone())));
+        files.set_stdin(Box::new(&b"input"[..]));
+
+        files.write(1, b"hello").unwrap();
+        files.close(1).unwrap();
+        assert_eq!(b"hello", out.lock().unwrap().as_slice());
+
+        let mut buf = [0_u8; 8];
+        let n = files.read(0, &mut buf).unwrap();
+        assert_eq!(b"input", &buf[..n]);
+    }
+}
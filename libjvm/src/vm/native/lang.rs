@@ -0,0 +1,227 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::NULL;
+use crate::vm::module::{Module, ModuleLayer};
+use crate::vm::native::{int_arg, long_arg, reference_arg, NativeResult};
+use crate::vm::thread::{Thread, Unwind};
+use crate::vm::types::NativeValue;
+use libjvm_macros::jvm_native;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// `public void exit(int status)`
+#[jvm_native(class = "java/lang/Runtime", name = "exit", sig = "(I)V")]
+pub fn runtime_exit(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().shutdown().exit(int_arg(args, 1)?);
+    Err(Unwind::Shutdown)
+}
+
+/// `public void halt(int status)`
+#[jvm_native(class = "java/lang/Runtime", name = "halt", sig = "(I)V")]
+pub fn runtime_halt(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().shutdown().halt(int_arg(args, 1)?);
+    Err(Unwind::Shutdown)
+}
+
+/// `static native void beforeHalt()`, which notifies the VM that it is about
+/// to halt. There is nothing to prepare.
+#[jvm_native(class = "java/lang/Shutdown", name = "beforeHalt", sig = "()V")]
+pub fn shutdown_before_halt(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(None)
+}
+
+/// `static native void halt0(int status)`, which is called by the class library
+/// after it has run the shutdown hooks.
+#[jvm_native(class = "java/lang/Shutdown", name = "halt0", sig = "(I)V")]
+pub fn shutdown_halt0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().shutdown().halt(int_arg(args, 0)?);
+    Err(Unwind::Shutdown)
+}
+
+/// `public static native long currentTimeMillis()`
+#[jvm_native(class = "java/lang/System", name = "currentTimeMillis", sig = "()J")]
+pub fn system_current_time_millis(thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    let millis = thread.runtime().clock().current_time_millis();
+    Ok(Some(NativeValue::Long(millis)))
+}
+
+/// `public static native long nanoTime()`
+#[jvm_native(class = "java/lang/System", name = "nanoTime", sig = "()J")]
+pub fn system_nano_time(thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    let nanos = thread.runtime().clock().nano_time();
+    Ok(Some(NativeValue::Long(nanos)))
+}
+
+/// `public static native void sleep(long millis)`
+#[jvm_native(class = "java/lang/Thread", name = "sleep", sig = "(J)V")]
+pub fn thread_sleep(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let millis = long_arg(args, 0)?;
+    if millis < 0 {
+        let message = "timeout value is negative".to_string();
+        return Err(VmError::from(Exception::IllegalArgument(message)).into());
+    }
+    let clock = thread.runtime().clock();
+    thread.waiting(|| clock.sleep(Duration::from_millis(millis as u64)));
+    // the VM may have been terminated while this thread was sleeping
+    thread.safepoint()?;
+    Ok(None)
+}
+
+/// `private static native void addReads0(Module from, Module to)`, where a
+/// null `to` stands for all unnamed modules.
+#[jvm_native(
+    class = "java/lang/Module",
+    name = "addReads0",
+    sig = "(Ljava/lang/Module;Ljava/lang/Module;)V"
+)]
+pub fn module_add_reads0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let layer = thread.runtime().boot_layer();
+    let from = module_arg(&layer, args, 0)?;
+    let to = match reference_arg(args, 1)? {
+        NULL => layer.unnamed_module().clone(),
+        _ => module_arg(&layer, args, 1)?,
+    };
+    from.add_reads(&to);
+    Ok(None)
+}
+
+/// The module of the `java.lang.Module` argument at the given index, which
+/// must have been bound with [`ModuleLayer::bind_mirror`].
+fn module_arg(
+    layer: &ModuleLayer,
+    args: &[NativeValue],
+    index: usize,
+) -> Result<Arc<Module>, VmError> {
+    match reference_arg(args, index)? {
+        NULL => Err(Exception::NullPointer.into()),
+        reference => layer.module_of_mirror(reference).ok_or_else(|| {
+            VmError::Internal(format!("no module for the Module object {:#x}", reference))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::clock::VirtualClock;
+    use crate::vm::module::ModuleMode;
+    use crate::vm::native::find_native;
+    use crate::vm::runtime::Runtime;
+    use crate::vm::VM;
+    use libvfs::FileSystem;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_runtime_exit_and_halt() {
+        for (name, runs_hooks) in [("exit", true), ("halt", false)] {
+            let runtime = Arc::new(Runtime::default());
+            let shutdown = runtime.shutdown();
+            let hook_ran = Arc::new(AtomicBool::new(false));
+            let h = hook_ran.clone();
+            shutdown.add_hook(Box::new(move || h.store(true, Ordering::SeqCst)));
+            let mut thread = Thread::new(runtime.clone());
+
+            let native = find_native("java/lang/Runtime", name, "(I)V").unwrap();
+            let result = native(
+                &mut thread,
+                &[NativeValue::Reference(1), NativeValue::Integer(42)],
+            );
+
+            assert_eq!(Err(Unwind::Shutdown), result);
+            assert_eq!(Some(42), shutdown.exit_status());
+            assert_eq!(runs_hooks, hook_ran.load(Ordering::SeqCst));
+            assert_eq!(Err(Unwind::Shutdown), thread.safepoint());
+        }
+    }
+
+    #[test]
+    fn test_time_natives_use_clock() {
+        let runtime = Arc::new(Runtime::default());
+        let clock = Arc::new(VirtualClock::new(Duration::from_secs(10)));
+        runtime.set_clock(clock.clone());
+        let mut thread = Thread::new(runtime);
+
+        let millis = find_native("java/lang/System", "currentTimeMillis", "()J").unwrap();
+        let nanos = find_native("java/lang/System", "nanoTime", "()J").unwrap();
+        let sleep = find_native("java/lang/Thread", "sleep", "(J)V").unwrap();
+
+        assert_eq!(
+            Ok(Some(NativeValue::Long(10_000))),
+            millis(&mut thread, &[])
+        );
+        assert_eq!(Ok(None), sleep(&mut thread, &[NativeValue::Long(250)]));
+        assert_eq!(
+            Ok(Some(NativeValue::Long(10_250))),
+            millis(&mut thread, &[])
+        );
+        assert_eq!(
+            Ok(Some(NativeValue::Long(250_000_000))),
+            nanos(&mut thread, &[])
+        );
+        clock.advance(Duration::from_nanos(1));
+        assert_eq!(
+            Ok(Some(NativeValue::Long(250_000_001))),
+            nanos(&mut thread, &[])
+        );
+    }
+
+    #[test]
+    fn test_sleep_negative() {
+        let mut thread = Thread::new(Arc::new(Runtime::default()));
+        let sleep = find_native("java/lang/Thread", "sleep", "(J)V").unwrap();
+        match sleep(&mut thread, &[NativeValue::Long(-1)]) {
+            Err(Unwind::Throw(e)) => {
+                assert_eq!("java/lang/IllegalArgumentException", e.class_name())
+            }
+            other => panic!("expected IllegalArgumentException, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_module_add_reads() {
+        let mut vm = VM::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(vec![
+                ClassPathEntry::Dir("tests/resources/vm/modules/a".into()),
+                ClassPathEntry::Dir("tests/resources/vm/modules/d".into()),
+            ]),
+        );
+        vm.set_module_mode(ModuleMode::Strict).unwrap();
+        let runtime = vm.runtime().clone();
+        let layer = runtime.boot_layer();
+        let a = layer.find_module("a").unwrap();
+        let d = layer.find_module("d").unwrap();
+        layer.bind_mirror(0x10, a.clone());
+        layer.bind_mirror(0x20, d.clone());
+        let mut thread = Thread::new(runtime.clone());
+        let add_reads = find_native(
+            "java/lang/Module",
+            "addReads0",
+            "(Ljava/lang/Module;Ljava/lang/Module;)V",
+        )
+        .unwrap();
+
+        assert!(!d.can_read(a));
+        let args = [NativeValue::Reference(0x20), NativeValue::Reference(0x10)];
+        assert_eq!(Ok(None), add_reads(&mut thread, &args));
+        assert!(d.can_read(a));
+
+        let args = [NativeValue::Reference(0x10), NativeValue::Reference(NULL)];
+        assert_eq!(Ok(None), add_reads(&mut thread, &args));
+        assert!(a.can_read(layer.unnamed_module()));
+
+        let args = [NativeValue::Reference(NULL), NativeValue::Reference(0x10)];
+        assert_eq!(
+            Err(Unwind::Throw(Exception::NullPointer.into())),
+            add_reads(&mut thread, &args)
+        );
+    }
+
+    #[test]
+    fn test_unsatisfied_link() {
+        let err = find_native("java/lang/Object", "hashCode", "()I")
+            .err()
+            .unwrap();
+        assert_eq!("java/lang/UnsatisfiedLinkError", err.class_name());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::vm::native::{boolean_arg, long_arg, NativeResult};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+
+/// `private static native long getThreadTotalCpuTime0(long id)` and
+/// `getThreadUserCpuTime0`, which back `ThreadMXBean.getThreadCpuTime`. The
+/// VM doesn't distinguish user and system time, so both return the CPU time
+/// of the thread with the given id, or of the current thread if the id is 0.
+/// Returns -1 if there is no such thread or CPU time measurement is disabled.
+pub fn thread_impl_get_thread_cpu_time0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let id = match long_arg(args, 0)? {
+        0 => thread.id(),
+        id => id,
+    };
+    let nanos = thread
+        .runtime()
+        .threads()
+        .cpu_time(id)
+        .map_or(-1, |time| time.as_nanos() as i64);
+    Ok(Some(NativeValue::Long(nanos)))
+}
+
+/// `private static native void setThreadCpuTimeEnabled0(boolean enable)`
+pub fn thread_impl_set_thread_cpu_time_enabled0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let enabled = boolean_arg(args, 0)?;
+    thread.runtime().threads().set_cpu_time_enabled(enabled);
+    Ok(None)
+}
+
+/// `private static native void setThreadContentionMonitoringEnabled0(boolean
+/// enable)`
+pub fn thread_impl_set_thread_contention_monitoring_enabled0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let enabled = boolean_arg(args, 0)?;
+    thread
+        .runtime()
+        .threads()
+        .set_contention_monitoring_enabled(enabled);
+    Ok(None)
+}
+
+/// `private static native void resetContentionTimes0(long tid)`, which resets
+/// the blocked and waited times of the thread with the given id, or of all
+/// threads if the id is 0.
+pub fn thread_impl_reset_contention_times0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let threads = thread.runtime().threads();
+    let ids = match long_arg(args, 0)? {
+        0 => threads.ids(),
+        id => vec![id],
+    };
+    for stats in ids.into_iter().filter_map(|id| threads.stats(id)) {
+        stats.reset_contention_times();
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::clock::VirtualClock;
+    use crate::vm::native::find_native;
+    use crate::vm::runtime::Runtime;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_thread_statistics() {
+        let runtime = Arc::new(Runtime::default());
+        runtime.set_clock(Arc::new(VirtualClock::new(Duration::ZERO)));
+        let mut thread = Thread::new(runtime.clone());
+        let worker = Thread::new_in_group(
+            runtime.clone(),
+            "worker",
+            runtime.main_thread_group().clone(),
+        );
+
+        let sleep = find_native("java/lang/Thread", "sleep", "(J)V").unwrap();
+        let cpu_time = find_native(
+            "sun/management/ThreadImpl",
+            "getThreadTotalCpuTime0",
+            "(J)J",
+        )
+        .unwrap();
+        let monitoring = find_native(
+            "sun/management/ThreadImpl",
+            "setThreadContentionMonitoringEnabled0",
+            "(Z)V",
+        )
+        .unwrap();
+        let reset =
+            find_native("sun/management/ThreadImpl", "resetContentionTimes0", "(J)V").unwrap();
+
+        sleep(&mut thread, &[NativeValue::Long(5)]).unwrap();
+        monitoring(&mut thread, &[NativeValue::Boolean(true)]).unwrap();
+        sleep(&mut thread, &[NativeValue::Long(20)]).unwrap();
+        thread.blocked(|| runtime.clock().sleep(Duration::from_millis(3)));
+
+        let metrics = runtime.threads().metrics();
+        assert_eq!(
+            vec!["main", "worker"],
+            metrics.iter().map(|m| &m.name).collect::<Vec<_>>()
+        );
+        let main = &metrics[0];
+        assert_eq!(thread.id(), main.id);
+        // sleeping is neither CPU time, nor was the first sleep monitored
+        assert_eq!(Some(Duration::ZERO), main.cpu_time);
+        assert_eq!(2, main.waited_count);
+        assert_eq!(Some(Duration::from_millis(20)), main.waited_time);
+        assert_eq!(1, main.blocked_count);
+        assert_eq!(Some(Duration::from_millis(3)), main.blocked_time);
+
+        assert_eq!(
+            Ok(Some(NativeValue::Long(0))),
+            cpu_time(&mut thread, &[NativeValue::Long(0)])
+        );
+        assert_eq!(
+            Ok(Some(NativeValue::Long(-1))),
+            cpu_time(&mut thread, &[NativeValue::Long(42)])
+        );
+
+        reset(&mut thread, &[NativeValue::Long(0)]).unwrap();
+        assert_eq!(Duration::ZERO, thread.stats().waited_time());
+        assert_eq!(2, thread.stats().waited_count());
+
+        drop(worker);
+        assert_eq!(vec![thread.id()], runtime.threads().ids());
+    }
+}
@@ -0,0 +1,611 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::native::{double_arg, float_arg, int_arg, long_arg, NativeResult};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+
+/// `public static native double sin(double a)` of `StrictMath`, which
+/// `Math.sin` delegates to.
+pub fn strict_math_sin(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Double(sin(double_arg(args, 0)?))))
+}
+
+/// `public static native double cos(double a)` of `StrictMath`, which
+/// `Math.cos` delegates to.
+pub fn strict_math_cos(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Double(cos(double_arg(args, 0)?))))
+}
+
+/// `public static native double sqrt(double a)` of `StrictMath`. The square
+/// root is correctly rounded, so it is the same on every platform.
+pub fn strict_math_sqrt(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Double(double_arg(args, 0)?.sqrt())))
+}
+
+// The methods below have a Java implementation, which the VM replaces with
+// these intrinsics, see `find_intrinsic`.
+
+/// `Math.fma(double a, double b, double c)`: `a * b + c`, rounded once.
+pub fn math_fma_double(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (a, b, c) = (
+        double_arg(args, 0)?,
+        double_arg(args, 1)?,
+        double_arg(args, 2)?,
+    );
+    Ok(Some(NativeValue::Double(a.mul_add(b, c))))
+}
+
+/// `Math.fma(float a, float b, float c)`: `a * b + c`, rounded once.
+pub fn math_fma_float(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (a, b, c) = (
+        float_arg(args, 0)?,
+        float_arg(args, 1)?,
+        float_arg(args, 2)?,
+    );
+    Ok(Some(NativeValue::Float(a.mul_add(b, c))))
+}
+
+/// `Math.floorDiv(int x, int y)`
+pub fn math_floor_div_int(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (x, y) = (int_arg(args, 0)?, int_arg(args, 1)?);
+    if y == 0 {
+        return Err(division_by_zero().into());
+    }
+    let q = x.wrapping_div(y);
+    let rounded_down = x.wrapping_rem(y) != 0 && (x ^ y) < 0;
+    Ok(Some(NativeValue::Integer(if rounded_down {
+        q - 1
+    } else {
+        q
+    })))
+}
+
+/// `Math.floorDiv(long x, long y)`
+pub fn math_floor_div_long(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (x, y) = (long_arg(args, 0)?, long_arg(args, 1)?);
+    if y == 0 {
+        return Err(division_by_zero().into());
+    }
+    let q = x.wrapping_div(y);
+    let rounded_down = x.wrapping_rem(y) != 0 && (x ^ y) < 0;
+    Ok(Some(NativeValue::Long(if rounded_down {
+        q - 1
+    } else {
+        q
+    })))
+}
+
+/// `Math.multiplyHigh(long x, long y)`: the upper 64 bits of the 128 bit
+/// product.
+pub fn math_multiply_high(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (x, y) = (long_arg(args, 0)?, long_arg(args, 1)?);
+    let high = ((x as i128 * y as i128) >> 64) as i64;
+    Ok(Some(NativeValue::Long(high)))
+}
+
+fn division_by_zero() -> VmError {
+    Exception::Arithmetic("/ by zero".to_string()).into()
+}
+
+// `StrictMath` requires the results of fdlibm 5.3, bit for bit, which the
+// platform's libm doesn't guarantee. The functions below are a port of the
+// fdlibm sources that the JDK ships (e_rem_pio2.c, k_rem_pio2.c, k_sin.c,
+// k_cos.c, s_sin.c and s_cos.c), keeping the order of all operations.
+
+fn sin(x: f64) -> f64 {
+    let ix = high_word(x) & 0x7FFF_FFFF;
+    if ix <= 0x3FE9_21FB {
+        // |x| ~< pi/4
+        return kernel_sin(x, 0.0, false);
+    }
+    if ix >= 0x7FF0_0000 {
+        // NaN or infinity
+        return f64::NAN;
+    }
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => kernel_sin(y0, y1, true),
+        1 => kernel_cos(y0, y1),
+        2 => -kernel_sin(y0, y1, true),
+        _ => -kernel_cos(y0, y1),
+    }
+}
+
+fn cos(x: f64) -> f64 {
+    let ix = high_word(x) & 0x7FFF_FFFF;
+    if ix <= 0x3FE9_21FB {
+        return kernel_cos(x, 0.0);
+    }
+    if ix >= 0x7FF0_0000 {
+        return f64::NAN;
+    }
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => kernel_cos(y0, y1),
+        1 => -kernel_sin(y0, y1, true),
+        2 => -kernel_cos(y0, y1),
+        _ => kernel_sin(y0, y1, true),
+    }
+}
+
+/// The sine of `x + y` on [-pi/4, pi/4], where `y` is the tail of `x` if
+/// `has_tail`.
+fn kernel_sin(x: f64, y: f64, has_tail: bool) -> f64 {
+    const S1: f64 = f64::from_bits(0xBFC5_5555_5555_5549); // -1.66666666666666324348e-01
+    const S2: f64 = f64::from_bits(0x3F81_1111_1110_F8A6); // 8.33333333332248946124e-03
+    const S3: f64 = f64::from_bits(0xBF2A_01A0_19C1_61D5); // -1.98412698298579493134e-04
+    const S4: f64 = f64::from_bits(0x3EC7_1DE3_57B1_FE7D); // 2.75573137070700676789e-06
+    const S5: f64 = f64::from_bits(0xBE5A_E5E6_8A2B_9CEB); // -2.50507602534068634195e-08
+    const S6: f64 = f64::from_bits(0x3DE5_D93A_5ACF_D57C); // 1.58969099521155010221e-10
+
+    let ix = high_word(x) & 0x7FFF_FFFF;
+    if ix < 0x3E40_0000 && x as i32 == 0 {
+        // |x| < 2**-27
+        return x;
+    }
+    let z = x * x;
+    let v = z * x;
+    let r = S2 + z * (S3 + z * (S4 + z * (S5 + z * S6)));
+    if !has_tail {
+        x + v * (S1 + z * r)
+    } else {
+        x - ((z * (0.5 * y - v * r) - y) - v * S1)
+    }
+}
+
+/// The cosine of `x + y` on [-pi/4, pi/4], where `y` is the tail of `x`.
+fn kernel_cos(x: f64, y: f64) -> f64 {
+    const C1: f64 = f64::from_bits(0x3FA5_5555_5555_554C); // 4.16666666666666019037e-02
+    const C2: f64 = f64::from_bits(0xBF56_C16C_16C1_5177); // -1.38888888888741095749e-03
+    const C3: f64 = f64::from_bits(0x3EFA_01A0_19CB_1590); // 2.48015872894767294178e-05
+    const C4: f64 = f64::from_bits(0xBE92_7E4F_809C_52AD); // -2.75573143513906633035e-07
+    const C5: f64 = f64::from_bits(0x3E21_EE9E_BDB4_B1C4); // 2.08757232129817482790e-09
+    const C6: f64 = f64::from_bits(0xBDA8_FAE9_BE88_38D4); // -1.13596475577881948265e-11
+
+    let ix = high_word(x) & 0x7FFF_FFFF;
+    if ix < 0x3E40_0000 && x as i32 == 0 {
+        // |x| < 2**-27
+        return 1.0;
+    }
+    let z = x * x;
+    let r = z * (C1 + z * (C2 + z * (C3 + z * (C4 + z * (C5 + z * C6)))));
+    if ix < 0x3FD3_3333 {
+        // |x| < 0.3
+        return 1.0 - (0.5 * z - (z * r - x * y));
+    }
+    let qx = if ix > 0x3FE9_0000 {
+        // |x| > 0.78125
+        0.28125
+    } else {
+        from_words(ix - 0x0020_0000, 0)
+    };
+    let hz = 0.5 * z - qx;
+    let a = 1.0 - qx;
+    a - (hz - (z * r - x * y))
+}
+
+/// Reduces `x` to `y0 + y1` on [-pi/4, pi/4], returning `n` with
+/// `x = n * pi/2 + y0 + y1`.
+fn rem_pio2(x: f64) -> (i32, f64, f64) {
+    const INVPIO2: f64 = f64::from_bits(0x3FE4_5F30_6DC9_C883); // 6.36619772367581382433e-01
+    const PIO2_1: f64 = f64::from_bits(0x3FF9_21FB_5440_0000); // 1.57079632673412561417e+00
+    const PIO2_1T: f64 = f64::from_bits(0x3DD0_B461_1A62_6331); // 6.07710050650619224932e-11
+    const PIO2_2: f64 = f64::from_bits(0x3DD0_B461_1A60_0000); // 6.07710050630396597660e-11
+    const PIO2_2T: f64 = f64::from_bits(0x3BA3_198A_2E03_7073); // 2.02226624879595063154e-21
+    const PIO2_3: f64 = f64::from_bits(0x3BA3_198A_2E00_0000); // 2.02226624871116645580e-21
+    const PIO2_3T: f64 = f64::from_bits(0x397B_839A_2520_49C1); // 8.47842766036889956997e-32
+    /// The high words of n * pi/2 for n in 1..=32.
+    const NPIO2_HW: [u32; 32] = [
+        0x3FF921FB, 0x400921FB, 0x4012D97C, 0x401921FB, 0x401F6A7A, 0x4022D97C, 0x4025FDBB,
+        0x402921FB, 0x402C463A, 0x402F6A7A, 0x4031475C, 0x4032D97C, 0x40346B9C, 0x4035FDBB,
+        0x40378FDB, 0x403921FB, 0x403AB41B, 0x403C463A, 0x403DD85A, 0x403F6A7A, 0x40407E4C,
+        0x4041475C, 0x4042106C, 0x4042D97C, 0x4043A28C, 0x40446B9C, 0x404534AC, 0x4045FDBB,
+        0x4046C6CB, 0x40478FDB, 0x404858EB, 0x404921FB,
+    ];
+
+    let hx = high_word(x) as i32;
+    let ix = (hx & 0x7FFF_FFFF) as u32;
+    if ix <= 0x3FE9_21FB {
+        return (0, x, 0.0);
+    }
+    if ix < 0x4002_D97C {
+        // |x| < 3pi/4, special case with n = +-1
+        return if hx > 0 {
+            let mut z = x - PIO2_1;
+            if ix != 0x3FF9_21FB {
+                let y0 = z - PIO2_1T;
+                (1, y0, (z - y0) - PIO2_1T)
+            } else {
+                // near pi/2, use 33+33+53 bit pi
+                z -= PIO2_2;
+                let y0 = z - PIO2_2T;
+                (1, y0, (z - y0) - PIO2_2T)
+            }
+        } else {
+            let mut z = x + PIO2_1;
+            if ix != 0x3FF9_21FB {
+                let y0 = z + PIO2_1T;
+                (-1, y0, (z - y0) + PIO2_1T)
+            } else {
+                z += PIO2_2;
+                let y0 = z + PIO2_2T;
+                (-1, y0, (z - y0) + PIO2_2T)
+            }
+        };
+    }
+    if ix <= 0x4139_21FB {
+        // |x| ~<= 2^19 * (pi/2), medium size
+        let t = x.abs();
+        let n = (t * INVPIO2 + 0.5) as i32;
+        let f = n as f64;
+        let mut r = t - f * PIO2_1;
+        // 1st round good to 85 bits
+        let mut w = f * PIO2_1T;
+        let mut y0 = r - w;
+        if !(n < 32 && ix != NPIO2_HW[n as usize - 1]) {
+            let j = ix >> 20;
+            let i = j - ((high_word(y0) >> 20) & 0x7FF);
+            if i > 16 {
+                // 2nd iteration needed, good to 118 bits
+                let t = r;
+                w = f * PIO2_2;
+                r = t - w;
+                w = f * PIO2_2T - ((t - r) - w);
+                y0 = r - w;
+                let i = j - ((high_word(y0) >> 20) & 0x7FF);
+                if i > 49 {
+                    // 3rd iteration needed, 151 bits
+                    let t = r;
+                    w = f * PIO2_3;
+                    r = t - w;
+                    w = f * PIO2_3T - ((t - r) - w);
+                    y0 = r - w;
+                }
+            }
+        }
+        let y1 = (r - y0) - w;
+        return if hx < 0 { (-n, -y0, -y1) } else { (n, y0, y1) };
+    }
+
+    // all other (large) arguments, split |x| into three 24 bit chunks
+    let e0 = (ix >> 20) as i32 - 1046;
+    let mut z = from_words((ix as i32 - (e0 << 20)) as u32, low_word(x));
+    let mut tx = [0.0; 3];
+    for chunk in tx.iter_mut().take(2) {
+        *chunk = z as i32 as f64;
+        z = (z - *chunk) * TWO24;
+    }
+    tx[2] = z;
+    let mut nx = 3;
+    while tx[nx - 1] == 0.0 {
+        nx -= 1;
+    }
+    let (n, y0, y1) = kernel_rem_pio2(&tx[..nx], e0);
+    if hx < 0 {
+        (-n, -y0, -y1)
+    } else {
+        (n, y0, y1)
+    }
+}
+
+const TWO24: f64 = f64::from_bits(0x4170_0000_0000_0000); // 1.67772160000000000000e+07
+const TWON24: f64 = f64::from_bits(0x3E70_0000_0000_0000); // 5.96046447753906250000e-08
+
+/// The bits of 2/pi, 24 bits per entry.
+const TWO_OVER_PI: [i32; 66] = [
+    0xA2F983, 0x6E4E44, 0x1529FC, 0x2757D1, 0xF534DD, 0xC0DB62, 0x95993C, 0x439041, 0xFE5163,
+    0xABDEBB, 0xC561B7, 0x246E3A, 0x424DD2, 0xE00649, 0x2EEA09, 0xD1921C, 0xFE1DEB, 0x1CB129,
+    0xA73EE8, 0x8235F5, 0x2EBB44, 0x84E99C, 0x7026B4, 0x5F7E41, 0x3991D6, 0x398353, 0x39F49C,
+    0x845F8B, 0xBDF928, 0x3B1FF8, 0x97FFDE, 0x05980F, 0xEF2F11, 0x8B5A0A, 0x6D1F6D, 0x367ECF,
+    0x27CB09, 0xB74F46, 0x3F669E, 0x5FEA2D, 0x7527BA, 0xC7EBE5, 0xF17B3D, 0x0739F7, 0x8A5292,
+    0xEA6BFB, 0x5FB11F, 0x8D5D08, 0x560330, 0x46FC7B, 0x6BABF0, 0xCFBC20, 0x9AF436, 0x1DA9E3,
+    0x91615E, 0xE61B08, 0x659985, 0x5F14A0, 0x68408D, 0xFFD880, 0x4D7327, 0x310606, 0x1556CA,
+    0x73A8C9, 0x60E27B, 0xC08C6B,
+];
+
+/// pi/2 in chunks of 24 bits.
+const PIO2: [f64; 8] = [
+    f64::from_bits(0x3FF9_21FB_4000_0000), // 1.57079625129699707031e+00
+    f64::from_bits(0x3E74_442D_0000_0000), // 7.54978941586159635335e-08
+    f64::from_bits(0x3CF8_4698_8000_0000), // 5.39030252995776476554e-15
+    f64::from_bits(0x3B78_CC51_6000_0000), // 3.28200341580791294123e-22
+    f64::from_bits(0x39F0_1B83_8000_0000), // 1.27065575308067607349e-29
+    f64::from_bits(0x387A_2520_4000_0000), // 1.22933308981111328932e-36
+    f64::from_bits(0x36E3_8222_8000_0000), // 2.73370053816464559624e-44
+    f64::from_bits(0x3569_F31D_0000_0000), // 2.16741683877804819444e-51
+];
+
+/// Reduces the large argument `x * 2^e0`, whose 24 bit chunks are given, to
+/// `y0 + y1`, returning the last three bits of `n` as well. This is
+/// `__kernel_rem_pio2` with the precision that `e_rem_pio2.c` uses.
+fn kernel_rem_pio2(x: &[f64], e0: i32) -> (i32, f64, f64) {
+    // the number of terms of 2/pi needed, e_rem_pio2.c asks for 64 bits
+    const JK: usize = 4;
+    let jp = JK;
+    let jx = x.len() - 1;
+    let jv = ((e0 - 3) / 24).max(0) as usize;
+    let mut q0 = e0 - 24 * (jv as i32 + 1);
+
+    let mut f = [0.0; 20];
+    for (j, fi) in (jv as i32 - jx as i32..).zip(f.iter_mut().take(jx + JK + 1)) {
+        *fi = if j < 0 {
+            0.0
+        } else {
+            TWO_OVER_PI[j as usize] as f64
+        };
+    }
+    let mut q = [0.0; 20];
+    for i in 0..=JK {
+        let mut fw = 0.0;
+        for j in 0..=jx {
+            fw += x[j] * f[jx + i - j];
+        }
+        q[i] = fw;
+    }
+
+    let mut iq = [0_i32; 20];
+    let mut jz = JK;
+    let (n, ih, z) = loop {
+        // distill q into iq, reversingly
+        let mut z = q[jz];
+        for (i, j) in (1..=jz).rev().enumerate() {
+            let fw = (TWON24 * z) as i32 as f64;
+            iq[i] = (z - TWO24 * fw) as i32;
+            z = q[j - 1] + fw;
+        }
+
+        // compute n
+        z = scalbn(z, q0);
+        z -= 8.0 * (z * 0.125).floor();
+        let mut n = z as i32;
+        z -= n as f64;
+        let mut ih = 0;
+        if q0 > 0 {
+            // need iq[jz-1] to determine n
+            let i = iq[jz - 1] >> (24 - q0);
+            n += i;
+            iq[jz - 1] -= i << (24 - q0);
+            ih = iq[jz - 1] >> (23 - q0);
+        } else if q0 == 0 {
+            ih = iq[jz - 1] >> 23;
+        } else if z >= 0.5 {
+            ih = 2;
+        }
+
+        if ih > 0 {
+            // q > 0.5
+            n += 1;
+            let mut carry = false;
+            for digit in iq.iter_mut().take(jz) {
+                if !carry {
+                    if *digit != 0 {
+                        carry = true;
+                        *digit = 0x100_0000 - *digit;
+                    }
+                } else {
+                    *digit = 0xFF_FFFF - *digit;
+                }
+            }
+            // rare case: chance is 1 in 12
+            match q0 {
+                1 => iq[jz - 1] &= 0x7F_FFFF,
+                2 => iq[jz - 1] &= 0x3F_FFFF,
+                _ => {}
+            }
+            if ih == 2 {
+                z = 1.0 - z;
+                if carry {
+                    z -= scalbn(1.0, q0);
+                }
+            }
+        }
+
+        // check if recomputation is needed
+        if z == 0.0 && iq[JK..jz].iter().all(|d| *d == 0) {
+            // the number of additional terms needed
+            let mut k = 1;
+            while iq[JK - k] == 0 {
+                k += 1;
+            }
+            for i in jz + 1..=jz + k {
+                f[jx + i] = TWO_OVER_PI[jv + i] as f64;
+                let mut fw = 0.0;
+                for j in 0..=jx {
+                    fw += x[j] * f[jx + i - j];
+                }
+                q[i] = fw;
+            }
+            jz += k;
+            continue;
+        }
+        break (n, ih, z);
+    };
+
+    // chop off zero terms
+    if z == 0.0 {
+        jz -= 1;
+        q0 -= 24;
+        while iq[jz] == 0 {
+            jz -= 1;
+            q0 -= 24;
+        }
+    } else {
+        // break z into 24 bit chunks if necessary
+        let z = scalbn(z, -q0);
+        if z >= TWO24 {
+            let fw = (TWON24 * z) as i32 as f64;
+            iq[jz] = (z - TWO24 * fw) as i32;
+            jz += 1;
+            q0 += 24;
+            iq[jz] = fw as i32;
+        } else {
+            iq[jz] = z as i32;
+        }
+    }
+
+    // convert the integer bit chunks to floating point values
+    let mut fw = scalbn(1.0, q0);
+    for i in (0..=jz).rev() {
+        q[i] = fw * iq[i] as f64;
+        fw *= TWON24;
+    }
+
+    // compute PIO2[0..=jp] * q[jz..=0]
+    let mut fq = [0.0; 20];
+    for i in (0..=jz).rev() {
+        let mut fw = 0.0;
+        let mut k = 0;
+        while k <= jp && k <= jz - i {
+            fw += PIO2[k] * q[i + k];
+            k += 1;
+        }
+        fq[jz - i] = fw;
+    }
+
+    // compress fq into y0 and y1
+    let mut fw = 0.0;
+    for i in (0..=jz).rev() {
+        fw += fq[i];
+    }
+    let y0 = if ih == 0 { fw } else { -fw };
+    let mut fw = fq[0] - fw;
+    for value in fq.iter().take(jz + 1).skip(1) {
+        fw += value;
+    }
+    let y1 = if ih == 0 { fw } else { -fw };
+    (n & 7, y0, y1)
+}
+
+/// `x * 2^n`, computed without overflowing the exponent on the way.
+fn scalbn(x: f64, mut n: i32) -> f64 {
+    let mut y = x;
+    if n > 1023 {
+        y *= f64::from_bits(0x7FE0_0000_0000_0000);
+        n -= 1023;
+        if n > 1023 {
+            y *= f64::from_bits(0x7FE0_0000_0000_0000);
+            n = (n - 1023).min(1023);
+        }
+    } else if n < -1022 {
+        // 2^-1022 * 2^53, so that y stays normal
+        y *= f64::from_bits(0x0360_0000_0000_0000);
+        n += 1022 - 53;
+        if n < -1022 {
+            y *= f64::from_bits(0x0360_0000_0000_0000);
+            n = (n + 1022 - 53).max(-1022);
+        }
+    }
+    y * f64::from_bits(((0x3FF + n) as u64) << 52)
+}
+
+fn high_word(x: f64) -> u32 {
+    (x.to_bits() >> 32) as u32
+}
+
+fn low_word(x: f64) -> u32 {
+    x.to_bits() as u32
+}
+
+fn from_words(high: u32, low: u32) -> f64 {
+    f64::from_bits(((high as u64) << 32) | low as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::runtime::Runtime;
+    use crate::vm::thread::Unwind;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_strict_sin_and_cos() {
+        // the bits that StrictMath returns for these arguments
+        let expected = [
+            (0.5, 0x3FDEAEE8744B05F0, 0x3FEC1528065B7D50),
+            (1.0, 0x3FEAED548F090CEE, 0x3FE14A280FB5068C),
+            (-2.5, 0xBFE326AF0DCFCAB0, 0xBFE9A2F7EF858B7D),
+            (100.0, 0xBFE03425B78C4DB8, 0x3FEB981DBF665FDF),
+            (1e6, 0xBFD6664B2568D867, 0x3FEDF9DF9906D32C),
+            (1e22, 0xBFEB453AB76BF397, 0x3FE0BE2CEF01C8F4),
+            (1e300, 0xBFEA2C16B010E385, 0xBFE2699022ADC4C1),
+            (
+                8.534910332544734e280,
+                0xBFEAB5D2AC4EB806,
+                0xBFE19F6F3CA0ACBD,
+            ),
+        ];
+        for (x, sin_bits, cos_bits) in expected {
+            assert_eq!(sin_bits, sin(x).to_bits(), "sin({})", x);
+            assert_eq!(cos_bits, cos(x).to_bits(), "cos({})", x);
+        }
+
+        assert_eq!((-0.0_f64).to_bits(), sin(-0.0).to_bits());
+        assert_eq!(1.0, cos(1e-10));
+        assert!(sin(f64::INFINITY).is_nan());
+        assert!(cos(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn test_intrinsics() {
+        let mut thread = Thread::new(Arc::new(Runtime::default()));
+        let call =
+            |thread: &mut Thread,
+             method: fn(&mut Thread, &[NativeValue]) -> NativeResult,
+             args: &[NativeValue]| { method(thread, args).ok().flatten() };
+        use NativeValue::{Double, Float, Integer, Long};
+
+        assert_eq!(
+            Some(Integer(-4)),
+            call(&mut thread, math_floor_div_int, &[Integer(-7), Integer(2)])
+        );
+        assert_eq!(
+            Some(Integer(i32::MIN)),
+            call(
+                &mut thread,
+                math_floor_div_int,
+                &[Integer(i32::MIN), Integer(-1)]
+            )
+        );
+        assert_eq!(
+            Some(Long(3)),
+            call(&mut thread, math_floor_div_long, &[Long(7), Long(2)])
+        );
+        assert!(matches!(
+            math_floor_div_long(&mut thread, &[Long(1), Long(0)]),
+            Err(Unwind::Throw(_))
+        ));
+        assert_eq!(
+            Some(Long(-1)),
+            call(&mut thread, math_multiply_high, &[Long(-1), Long(1)])
+        );
+        assert_eq!(
+            Some(Long(0x3FFF_FFFF_FFFF_FFFF)),
+            call(
+                &mut thread,
+                math_multiply_high,
+                &[Long(i64::MAX), Long(i64::MAX)]
+            )
+        );
+
+        // rounded once: 0.1 * 10 - 1 is not 0 with fma
+        assert_eq!(
+            Some(Double(5.551115123125783e-17)),
+            call(
+                &mut thread,
+                math_fma_double,
+                &[Double(0.1), Double(10.0), Double(-1.0)]
+            )
+        );
+        assert_eq!(
+            Some(Float(1.4901161e-8)),
+            call(
+                &mut thread,
+                math_fma_float,
+                &[Float(0.1), Float(10.0), Float(-1.0)]
+            )
+        );
+        assert_eq!(
+            Some(Double(3.0)),
+            call(&mut thread, strict_math_sqrt, &[Double(9.0)])
+        );
+    }
+}
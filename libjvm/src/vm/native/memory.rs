@@ -0,0 +1,541 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::NULL;
+use crate::vm::native::{
+    byte_arg, char_arg, double_arg, float_arg, int_arg, long_arg, reference_arg, short_arg,
+    NativeResult,
+};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// The address of the first block, so that no block starts at 0, which is
+/// the address of freed memory in Java.
+const FIRST_ADDRESS: i64 = 0x1_0000;
+
+/// The alignment of blocks, which is enough for any primitive, like `malloc`.
+const ALIGNMENT: i64 = 16;
+
+/// The off-heap memory that Java code allocates with `Unsafe.allocateMemory`,
+/// e.g. for direct `ByteBuffer`s, by the address of each block.
+///
+/// Addresses are only valid within their block, and there is an unmapped gap
+/// after every block, so that an access out of bounds fails with an
+/// `InternalError` (like a fault in HotSpot) instead of corrupting another
+/// block.
+pub struct DirectMemory {
+    state: Mutex<State>,
+}
+
+struct State {
+    blocks: BTreeMap<i64, Box<[u8]>>,
+    next_address: i64,
+    /// The number of bytes of all blocks.
+    reserved: usize,
+    /// The largest number of bytes that may be reserved, like
+    /// `-XX:MaxDirectMemorySize`.
+    limit: Option<usize>,
+}
+
+impl Default for DirectMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirectMemory {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                blocks: BTreeMap::new(),
+                next_address: FIRST_ADDRESS,
+                reserved: 0,
+                limit: None,
+            }),
+        }
+    }
+
+    /// The number of allocated blocks.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes of all allocated blocks.
+    pub fn reserved(&self) -> usize {
+        self.state.lock().unwrap().reserved
+    }
+
+    /// Limits the number of bytes that may be allocated at once. Allocations
+    /// beyond it fail with an `OutOfMemoryError`.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.state.lock().unwrap().limit = limit;
+    }
+
+    /// Allocates a block of the given size, with undefined contents like
+    /// `malloc` (in fact zeroed), and returns its address. A size of 0 returns
+    /// the address 0, like `Unsafe.allocateMemory`.
+    pub fn allocate(&self, size: i64) -> Result<i64, VmError> {
+        let size = checked_size(size)?;
+        if size == 0 {
+            return Ok(0);
+        }
+        let mut state = self.state.lock().unwrap();
+        let reserved = state.reserved + size;
+        if state.limit.is_some_and(|limit| reserved > limit) {
+            return Err(Exception::OutOfMemory(format!(
+                "Cannot reserve {} bytes of direct buffer memory (allocated: {}, limit: {})",
+                size,
+                state.reserved,
+                state.limit.unwrap()
+            ))
+            .into());
+        }
+        let address = state.next_address;
+        // the block is followed by at least one unmapped byte
+        state.next_address += (size as i64 / ALIGNMENT + 1) * ALIGNMENT;
+        state.reserved = reserved;
+        state
+            .blocks
+            .insert(address, vec![0; size].into_boxed_slice());
+        Ok(address)
+    }
+
+    /// Resizes the block at the given address, like `realloc`: the contents
+    /// are moved to a new block of the given size, as far as they fit. The
+    /// address 0 allocates a new block.
+    pub fn reallocate(&self, address: i64, size: i64) -> Result<i64, VmError> {
+        if address == 0 {
+            return self.allocate(size);
+        }
+        let old = self.block_len(address)?;
+        let new = self.allocate(size)?;
+        let moved = old.min(checked_size(size)?);
+        if moved > 0 {
+            self.copy(address, new, moved as i64)?;
+        }
+        self.free(address)?;
+        Ok(new)
+    }
+
+    /// Frees the block at the given address. Freeing the address 0 does
+    /// nothing, like `free`.
+    pub fn free(&self, address: i64) -> Result<(), VmError> {
+        if address == 0 {
+            return Ok(());
+        }
+        let mut state = self.state.lock().unwrap();
+        let block = state
+            .blocks
+            .remove(&address)
+            .ok_or_else(|| fault(address))?;
+        state.reserved -= block.len();
+        Ok(())
+    }
+
+    /// Reads as many bytes as the buffer holds, starting at the given address.
+    pub fn read(&self, address: i64, buf: &mut [u8]) -> Result<(), VmError> {
+        self.with_range(address, buf.len(), |bytes| buf.copy_from_slice(bytes))
+    }
+
+    /// Writes the given bytes, starting at the given address.
+    pub fn write(&self, address: i64, bytes: &[u8]) -> Result<(), VmError> {
+        self.with_range(address, bytes.len(), |range| range.copy_from_slice(bytes))
+    }
+
+    /// Sets the given number of bytes to the given value, like `memset`.
+    pub fn fill(&self, address: i64, len: i64, value: u8) -> Result<(), VmError> {
+        self.with_range(address, checked_size(len)?, |range| range.fill(value))
+    }
+
+    /// Copies bytes from one address to another, which may overlap, like
+    /// `memmove`.
+    pub fn copy(&self, from: i64, to: i64, len: i64) -> Result<(), VmError> {
+        self.copy_swap(from, to, len, 1)
+    }
+
+    /// Copies elements of the given size (1, 2, 4 or 8 bytes) from one address
+    /// to another, reversing the bytes of every element, like
+    /// `Unsafe.copySwapMemory`. An element size of 1 copies the bytes as they
+    /// are.
+    pub fn copy_swap(
+        &self,
+        from: i64,
+        to: i64,
+        len: i64,
+        element_size: i64,
+    ) -> Result<(), VmError> {
+        let len = checked_size(len)?;
+        if !matches!(element_size, 1 | 2 | 4 | 8) || len % element_size as usize != 0 {
+            return Err(Exception::IllegalArgument(format!(
+                "{} bytes can't be copied as elements of {} bytes",
+                len, element_size
+            ))
+            .into());
+        }
+        let mut bytes = vec![0; len];
+        self.read(from, &mut bytes)?;
+        if element_size > 1 {
+            bytes
+                .chunks_exact_mut(element_size as usize)
+                .for_each(|element| element.reverse());
+        }
+        self.write(to, &bytes)
+    }
+
+    fn block_len(&self, address: i64) -> Result<usize, VmError> {
+        let state = self.state.lock().unwrap();
+        let block = state.blocks.get(&address).ok_or_else(|| fault(address))?;
+        Ok(block.len())
+    }
+
+    /// Runs the given function on the bytes from the given address on, which
+    /// have to be within a single block.
+    fn with_range<T>(
+        &self,
+        address: i64,
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> T,
+    ) -> Result<T, VmError> {
+        let mut state = self.state.lock().unwrap();
+        let (start, block) = state
+            .blocks
+            .range_mut(..=address)
+            .next_back()
+            .ok_or_else(|| fault(address))?;
+        let offset = (address - start) as usize;
+        match offset.checked_add(len) {
+            Some(end) if end <= block.len() => Ok(f(&mut block[offset..end])),
+            _ => Err(fault(address)),
+        }
+    }
+}
+
+fn checked_size(size: i64) -> Result<usize, VmError> {
+    usize::try_from(size)
+        .map_err(|_| Exception::IllegalArgument(format!("negative size: {}", size)).into())
+}
+
+/// The error of an access to memory that isn't allocated, which is thrown as
+/// an `InternalError` like HotSpot reports faults in `Unsafe` accesses.
+fn fault(address: i64) -> VmError {
+    VmError::Internal(format!(
+        "a fault occurred in an unsafe memory access at {:#x}",
+        address
+    ))
+}
+
+/// The address of an access of `Unsafe` to the given base object and
+/// offset. Only off-heap memory is supported, where the base is `null` and
+/// the offset is the address.
+fn address(args: &[NativeValue], base: usize, offset: usize) -> Result<i64, VmError> {
+    if reference_arg(args, base)? != NULL {
+        return Err(VmError::Internal(
+            "Unsafe access to heap objects is not supported".to_string(),
+        ));
+    }
+    long_arg(args, offset)
+}
+
+/// `private native int addressSize0()` of `Unsafe`. Addresses are `long`s.
+pub fn unsafe_address_size0(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Integer(8)))
+}
+
+/// `private native boolean isBigEndian0()` of `Unsafe`, which is the
+/// `ByteOrder.nativeOrder()`. Direct buffers in that order access memory
+/// with `get<Type>` and `put<Type>` directly, and swap bytes otherwise.
+pub fn unsafe_is_big_endian0(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Boolean(cfg!(target_endian = "big"))))
+}
+
+/// `public native int pageSize()` of `Unsafe`, by which direct buffers may
+/// be page-aligned. Blocks are only aligned to 16 bytes, so page-aligned
+/// buffers over-allocate and align themselves, like they do in HotSpot.
+pub fn unsafe_page_size(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Integer(4096)))
+}
+
+/// `private native boolean unalignedAccess0()` of `Unsafe`. Accesses are
+/// copies of bytes, so any address works.
+pub fn unsafe_unaligned_access0(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Boolean(true)))
+}
+
+/// `private native long allocateMemory0(long bytes)` of `Unsafe`.
+pub fn unsafe_allocate_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let address = thread
+        .runtime()
+        .direct_memory()
+        .allocate(long_arg(args, 1)?)?;
+    Ok(Some(NativeValue::Long(address)))
+}
+
+/// `private native long reallocateMemory0(long address, long bytes)`.
+pub fn unsafe_reallocate_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let memory = thread.runtime().direct_memory();
+    let address = memory.reallocate(long_arg(args, 1)?, long_arg(args, 2)?)?;
+    Ok(Some(NativeValue::Long(address)))
+}
+
+/// `private native void freeMemory0(long address)`.
+pub fn unsafe_free_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().direct_memory().free(long_arg(args, 1)?)?;
+    Ok(None)
+}
+
+/// `private native void setMemory0(Object o, long offset, long bytes, byte value)`.
+pub fn unsafe_set_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let address = address(args, 1, 2)?;
+    let value = byte_arg(args, 4)? as u8;
+    thread
+        .runtime()
+        .direct_memory()
+        .fill(address, long_arg(args, 3)?, value)?;
+    Ok(None)
+}
+
+/// `private native void copyMemory0(Object srcBase, long srcOffset, Object
+/// destBase, long destOffset, long bytes)`.
+pub fn unsafe_copy_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (from, to) = (address(args, 1, 2)?, address(args, 3, 4)?);
+    thread
+        .runtime()
+        .direct_memory()
+        .copy(from, to, long_arg(args, 5)?)?;
+    Ok(None)
+}
+
+/// `private native void copySwapMemory0(Object srcBase, long srcOffset,
+/// Object destBase, long destOffset, long bytes, long elemSize)`, which
+/// backs the views of direct buffers in the non-native byte order.
+pub fn unsafe_copy_swap_memory0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (from, to) = (address(args, 1, 2)?, address(args, 3, 4)?);
+    thread
+        .runtime()
+        .direct_memory()
+        .copy_swap(from, to, long_arg(args, 5)?, long_arg(args, 6)?)?;
+    Ok(None)
+}
+
+/// Defines the natives `get<Type>(Object o, long offset)` and
+/// `put<Type>(Object o, long offset, <type> x)` of `Unsafe`, which access
+/// memory in the byte order of the platform. Accesses in the other order,
+/// e.g. of a big-endian `IntBuffer` view, are swapped by `Unsafe` in Java.
+macro_rules! unsafe_accessors {
+    ($($get:ident, $put:ident, $arg:ident, $variant:ident, $type:ty;)*) => {
+        $(
+            pub fn $get(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+                let mut bytes = [0; std::mem::size_of::<$type>()];
+                thread
+                    .runtime()
+                    .direct_memory()
+                    .read(address(args, 1, 2)?, &mut bytes)?;
+                Ok(Some(NativeValue::$variant(<$type>::from_ne_bytes(bytes))))
+            }
+
+            pub fn $put(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+                let bytes = $arg(args, 3)?.to_ne_bytes();
+                thread
+                    .runtime()
+                    .direct_memory()
+                    .write(address(args, 1, 2)?, &bytes)?;
+                Ok(None)
+            }
+        )*
+    };
+}
+
+unsafe_accessors! {
+    unsafe_get_byte, unsafe_put_byte, byte_arg, Byte, i8;
+    unsafe_get_short, unsafe_put_short, short_arg, Short, i16;
+    unsafe_get_char, unsafe_put_char, char_arg, Char, u16;
+    unsafe_get_int, unsafe_put_int, int_arg, Integer, i32;
+    unsafe_get_long, unsafe_put_long, long_arg, Long, i64;
+    unsafe_get_float, unsafe_put_float, float_arg, Float, f32;
+    unsafe_get_double, unsafe_put_double, double_arg, Double, f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::native::find_native;
+    use crate::vm::runtime::Runtime;
+    use crate::vm::thread::Unwind;
+    use std::sync::Arc;
+
+    const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+
+    fn call(
+        thread: &mut Thread,
+        name: &str,
+        descriptor: &str,
+        args: &[NativeValue],
+    ) -> NativeResult {
+        let native = find_native(UNSAFE, name, descriptor).unwrap();
+        // the receiver is the singleton instance of Unsafe
+        let args: Vec<_> = [NativeValue::Reference(1)]
+            .into_iter()
+            .chain(args.iter().cloned())
+            .collect();
+        native(thread, &args)
+    }
+
+    #[test]
+    fn test_allocate_and_free() {
+        let memory = DirectMemory::new();
+        let a = memory.allocate(10).unwrap();
+        let b = memory.allocate(16).unwrap();
+        assert_ne!(0, a);
+        assert_eq!(0, a % ALIGNMENT);
+        assert!(b >= a + 10);
+        assert_eq!((2, 26), (memory.len(), memory.reserved()));
+        assert_eq!(Ok(0), memory.allocate(0));
+
+        // accesses beyond a block don't reach the next one
+        assert!(memory.write(a + 8, &[1, 2, 3]).is_err());
+        assert!(memory.read(b + 16, &mut [0]).is_err());
+        memory.write(b, &[7; 16]).unwrap();
+
+        let c = memory.reallocate(b, 20).unwrap();
+        let mut bytes = [0; 20];
+        memory.read(c, &mut bytes).unwrap();
+        assert_eq!([[7; 16].as_slice(), &[0; 4]].concat(), bytes);
+
+        memory.free(a).unwrap();
+        memory.free(c).unwrap();
+        assert!(memory.free(c).is_err());
+        assert!(memory.is_empty());
+        assert_eq!(0, memory.reserved());
+    }
+
+    #[test]
+    fn test_limit() {
+        let memory = DirectMemory::new();
+        memory.set_limit(Some(100));
+        let a = memory.allocate(60).unwrap();
+        assert_eq!(
+            Err(Exception::OutOfMemory(
+                "Cannot reserve 50 bytes of direct buffer memory (allocated: 60, limit: 100)"
+                    .into()
+            )
+            .into()),
+            memory.allocate(50)
+        );
+        memory.free(a).unwrap();
+        assert!(memory.allocate(50).is_ok());
+    }
+
+    #[test]
+    fn test_copy_swap() {
+        let memory = DirectMemory::new();
+        let a = memory.allocate(8).unwrap();
+        memory.write(a, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let b = memory.allocate(8).unwrap();
+        memory.copy_swap(a, b, 8, 4).unwrap();
+        let mut bytes = [0; 8];
+        memory.read(b, &mut bytes).unwrap();
+        assert_eq!([4, 3, 2, 1, 8, 7, 6, 5], bytes);
+
+        // overlapping copies work like memmove
+        memory.copy(a, a + 2, 6).unwrap();
+        memory.read(a, &mut bytes).unwrap();
+        assert_eq!([1, 2, 1, 2, 3, 4, 5, 6], bytes);
+        assert!(memory.copy_swap(a, b, 6, 4).is_err());
+    }
+
+    #[test]
+    fn test_unsafe_natives() {
+        let runtime = Arc::new(Runtime::default());
+        let mut thread = Thread::new(runtime.clone());
+        let null = NativeValue::Reference(NULL);
+
+        let Ok(Some(NativeValue::Long(address))) = call(
+            &mut thread,
+            "allocateMemory0",
+            "(J)J",
+            &[NativeValue::Long(16)],
+        ) else {
+            panic!("allocation failed");
+        };
+        assert_eq!(16, runtime.direct_memory().reserved());
+
+        call(
+            &mut thread,
+            "putInt",
+            "(Ljava/lang/Object;JI)V",
+            &[
+                null.clone(),
+                NativeValue::Long(address),
+                NativeValue::Integer(0x0102_0304),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(NativeValue::Integer(0x0102_0304))),
+            call(
+                &mut thread,
+                "getInt",
+                "(Ljava/lang/Object;J)I",
+                &[null.clone(), NativeValue::Long(address)]
+            )
+        );
+        // the int is stored in the byte order of the platform
+        let first = if cfg!(target_endian = "big") { 1 } else { 4 };
+        assert_eq!(
+            Ok(Some(NativeValue::Byte(first))),
+            call(
+                &mut thread,
+                "getByte",
+                "(Ljava/lang/Object;J)B",
+                &[null.clone(), NativeValue::Long(address)]
+            )
+        );
+
+        call(
+            &mut thread,
+            "putDouble",
+            "(Ljava/lang/Object;JD)V",
+            &[
+                null.clone(),
+                NativeValue::Long(address + 8),
+                NativeValue::Double(0.5),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(NativeValue::Double(0.5))),
+            call(
+                &mut thread,
+                "getDouble",
+                "(Ljava/lang/Object;J)D",
+                &[null.clone(), NativeValue::Long(address + 8)]
+            )
+        );
+
+        // reading past the end is a fault
+        assert_eq!(
+            Err(Unwind::Throw(VmError::Internal(
+                "a fault occurred in an unsafe memory access at 0x10010".into()
+            ))),
+            call(
+                &mut thread,
+                "getLong",
+                "(Ljava/lang/Object;J)J",
+                &[null, NativeValue::Long(address + 16)]
+            )
+        );
+
+        call(
+            &mut thread,
+            "freeMemory0",
+            "(J)V",
+            &[NativeValue::Long(address)],
+        )
+        .unwrap();
+        assert!(runtime.direct_memory().is_empty());
+    }
+}
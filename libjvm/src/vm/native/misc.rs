@@ -0,0 +1,51 @@
+use crate::vm::native::{long_arg, NativeResult};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+
+/// The maximum distance in seconds between the current time and the offset
+/// of `getNanoTimeAdjustment`, as in the reference implementation.
+const MAX_NANO_TIME_ADJUSTMENT_OFFSET: i64 = 0x0000_0001_0000_0000;
+
+/// `public static native long getNanoTimeAdjustment(long offsetInSeconds)`,
+/// which backs `java.time.Clock`. Returns the current time in nanoseconds
+/// relative to the given offset from the epoch, or -1 if the offset is too far
+/// away from the current time.
+pub fn vm_get_nano_time_adjustment(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let offset = long_arg(args, 0)?;
+    let now = thread.runtime().clock().now();
+    let seconds = now.as_secs() as i64;
+    let diff = seconds - offset;
+    if !(-MAX_NANO_TIME_ADJUSTMENT_OFFSET..MAX_NANO_TIME_ADJUSTMENT_OFFSET).contains(&diff) {
+        return Ok(Some(NativeValue::Long(-1)));
+    }
+    let adjustment = diff * 1_000_000_000 + now.subsec_nanos() as i64;
+    Ok(Some(NativeValue::Long(adjustment)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::clock::VirtualClock;
+    use crate::vm::runtime::Runtime;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_nano_time_adjustment() {
+        let runtime = Arc::new(Runtime::default());
+        runtime.set_clock(Arc::new(VirtualClock::new(Duration::new(100, 5))));
+        let mut thread = Thread::new(runtime);
+
+        let adjustment = |thread: &mut Thread, offset: i64| {
+            vm_get_nano_time_adjustment(thread, &[NativeValue::Long(offset)])
+        };
+        assert_eq!(
+            Ok(Some(NativeValue::Long(2_000_000_005))),
+            adjustment(&mut thread, 98)
+        );
+        assert_eq!(
+            Ok(Some(NativeValue::Long(-1))),
+            adjustment(&mut thread, i64::MAX / 2)
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use crate::vm::classloader::layout::Field;
+use crate::vm::error::{Exception, LinkageError, VmError};
+use crate::vm::heap::{Array, Heap, HeapObject, Instance, NULL};
+use crate::vm::runtime::Runtime;
+use crate::vm::thread::{Thread, Unwind};
+use crate::vm::types::NativeValue;
+use std::sync::RwLock;
+
+/// The registry of intrinsics, which the interpreter invokes instead of
+/// interpreting their methods.
+pub mod intrinsics;
+/// The file natives of `java.io` (`FileInputStream`, `FileOutputStream`,
+/// `RandomAccessFile` and `UnixFileSystem`). They operate on the VM's
+/// [`FileSystem`](libvfs::FileSystem) instead of the host file system, so file
+/// I/O of Java code is sandboxed by the file system the VM was created with.
+pub mod io;
+mod lang;
+mod management;
+/// The natives of `StrictMath` and the intrinsics of `Math`, with the results
+/// of fdlibm where `StrictMath` requires them.
+pub mod math;
+/// The off-heap memory of direct `ByteBuffer`s, and the address-based memory
+/// access of `jdk.internal.misc.Unsafe` to it.
+pub mod memory;
+mod misc;
+/// The socket natives of `java.net` (`PlainSocketImpl` and its socket streams),
+/// on top of `std::net` and restricted by a
+/// [`NetworkPolicy`](net::NetworkPolicy).
+#[cfg(feature = "net")]
+pub mod net;
+/// The process natives of `ProcessBuilder` and `Runtime.exec` (`ProcessImpl`
+/// and `ProcessHandleImpl`), on top of `std::process` and restricted by a
+/// [`ProcessPolicy`](process::ProcessPolicy).
+#[cfg(feature = "process")]
+pub mod process;
+/// The compact string support of `java.lang.String`: the coder of a string,
+/// and the intrinsics that convert between Latin-1 and UTF-16.
+pub mod string;
+/// The fixtures that the tests of the natives share.
+#[cfg(test)]
+mod test_util;
+/// The compression natives of `java.util.zip` (`Deflater`, `Inflater`,
+/// `CRC32` and `Adler32`), on top of an implementation of the deflate format
+/// in Rust rather than libzip.
+pub mod zip;
+
+/// The result of a native method. `Ok` holds the return value ([`None`] for
+/// `void` methods).
+pub type NativeResult = Result<Option<NativeValue>, Unwind>;
+
+/// The implementation of a method declared `native`. The arguments start with
+/// the receiver (`this`) for instance methods.
+pub type NativeMethod = fn(&mut Thread, &[NativeValue]) -> NativeResult;
+
+/// A native method together with the Java method that it implements, as
+/// generated next to the function by [`jvm_native`](libjvm_macros::jvm_native).
+#[derive(Copy, Clone)]
+pub struct NativeBinding {
+    /// The binary name of the class, e.g. `java/lang/System`.
+    pub class: &'static str,
+    pub name: &'static str,
+    pub descriptor: &'static str,
+    pub method: NativeMethod,
+}
+
+/// The natives that are bound with [`jvm_native`](libjvm_macros::jvm_native).
+/// The others are still matched by hand in [`find_native`].
+const BINDINGS: &[NativeBinding] = &[
+    io::FILE_DESCRIPTOR_CLOSE0,
+    io::FILE_INPUT_STREAM_AVAILABLE0,
+    io::FILE_INPUT_STREAM_OPEN0,
+    io::FILE_INPUT_STREAM_READ0,
+    io::FILE_INPUT_STREAM_READ_BYTES,
+    io::FILE_OUTPUT_STREAM_OPEN0,
+    io::FILE_OUTPUT_STREAM_WRITE,
+    io::FILE_OUTPUT_STREAM_WRITE_BYTES,
+    io::RANDOM_ACCESS_FILE_GET_FILE_POINTER,
+    io::RANDOM_ACCESS_FILE_LENGTH,
+    io::RANDOM_ACCESS_FILE_OPEN0,
+    io::RANDOM_ACCESS_FILE_READ0,
+    io::RANDOM_ACCESS_FILE_READ_BYTES,
+    io::RANDOM_ACCESS_FILE_SEEK0,
+    io::RANDOM_ACCESS_FILE_WRITE0,
+    io::RANDOM_ACCESS_FILE_WRITE_BYTES,
+    io::UNIX_FILE_SYSTEM_CREATE_DIRECTORY,
+    io::UNIX_FILE_SYSTEM_DELETE0,
+    io::UNIX_FILE_SYSTEM_GET_BOOLEAN_ATTRIBUTES0,
+    io::UNIX_FILE_SYSTEM_GET_LENGTH,
+    io::UNIX_FILE_SYSTEM_LIST,
+    lang::MODULE_ADD_READS0,
+    lang::RUNTIME_EXIT,
+    lang::RUNTIME_HALT,
+    lang::SHUTDOWN_BEFORE_HALT,
+    lang::SHUTDOWN_HALT0,
+    lang::SYSTEM_CURRENT_TIME_MILLIS,
+    lang::SYSTEM_NANO_TIME,
+    lang::THREAD_SLEEP,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_ACCEPT,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_BIND,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_CLOSE0,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_CONNECT,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_CREATE,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_LISTEN,
+    #[cfg(feature = "net")]
+    net::PLAIN_SOCKET_IMPL_SOCKET_SHUTDOWN,
+    #[cfg(feature = "net")]
+    net::SOCKET_INPUT_STREAM_SOCKET_READ0,
+    #[cfg(feature = "net")]
+    net::SOCKET_OUTPUT_STREAM_SOCKET_WRITE0,
+    #[cfg(feature = "process")]
+    process::PROCESS_HANDLE_IMPL_DESTROY0,
+    #[cfg(feature = "process")]
+    process::PROCESS_HANDLE_IMPL_GET_CURRENT_PID0,
+    #[cfg(feature = "process")]
+    process::PROCESS_HANDLE_IMPL_INIT_NATIVE,
+    #[cfg(feature = "process")]
+    process::PROCESS_HANDLE_IMPL_IS_ALIVE0,
+    #[cfg(feature = "process")]
+    process::PROCESS_HANDLE_IMPL_WAIT_FOR_PROCESS_EXIT0,
+    #[cfg(feature = "process")]
+    process::PROCESS_IMPL_FORK_AND_EXEC,
+    #[cfg(feature = "process")]
+    process::PROCESS_IMPL_INIT,
+];
+
+/// Finds the implementation of a native method by the name of its class, and
+/// its name and descriptor. Fails with an `UnsatisfiedLinkError` if the VM
+/// doesn't implement the method.
+pub fn find_native(class: &str, name: &str, descriptor: &str) -> Result<NativeMethod, VmError> {
+    if let Some(binding) = BINDINGS
+        .iter()
+        .find(|b| b.class == class && b.name == name && b.descriptor == descriptor)
+    {
+        return Ok(binding.method);
+    }
+    Ok(match (class, name, descriptor) {
+        ("java/lang/StrictMath", "cos", "(D)D") => math::strict_math_cos,
+        ("java/lang/StrictMath", "sin", "(D)D") => math::strict_math_sin,
+        ("java/lang/StrictMath", "sqrt", "(D)D") => math::strict_math_sqrt,
+        ("java/lang/StringUTF16", "isBigEndian", "()Z") => string::string_utf16_is_big_endian,
+        ("java/util/zip/Adler32", "update", "(II)I") => zip::adler32_update,
+        ("java/util/zip/Adler32", "updateBytes", "(I[BII)I") => zip::adler32_update_bytes,
+        ("java/util/zip/Adler32", "updateByteBuffer", "(IJII)I") => zip::adler32_update_byte_buffer,
+        ("java/util/zip/CRC32", "update", "(II)I") => zip::crc32_update,
+        ("java/util/zip/CRC32", "updateBytes0", "(I[BII)I") => zip::crc32_update_bytes,
+        ("java/util/zip/CRC32", "updateByteBuffer0", "(IJII)I") => zip::crc32_update_byte_buffer,
+        ("java/util/zip/Deflater", "init", "(IIZ)J") => zip::deflater_init,
+        ("java/util/zip/Deflater", "deflateBytesBytes", "(J[BII[BIIII)J") => {
+            zip::deflater_deflate_bytes_bytes
+        }
+        ("java/util/zip/Deflater", "deflateBytesBuffer", "(J[BIIJIII)J") => {
+            zip::deflater_deflate_bytes_buffer
+        }
+        ("java/util/zip/Deflater", "deflateBufferBytes", "(JJI[BIIII)J") => {
+            zip::deflater_deflate_buffer_bytes
+        }
+        ("java/util/zip/Deflater", "deflateBufferBuffer", "(JJIJIII)J") => {
+            zip::deflater_deflate_buffer_buffer
+        }
+        ("java/util/zip/Inflater", "init", "(Z)J") => zip::inflater_init,
+        ("java/util/zip/Inflater", "inflateBytesBytes", "(J[BII[BII)J") => {
+            zip::inflater_inflate_bytes_bytes
+        }
+        ("java/util/zip/Inflater", "inflateBytesBuffer", "(J[BIIJI)J") => {
+            zip::inflater_inflate_bytes_buffer
+        }
+        ("java/util/zip/Inflater", "inflateBufferBytes", "(JJI[BII)J") => {
+            zip::inflater_inflate_buffer_bytes
+        }
+        ("java/util/zip/Inflater", "inflateBufferBuffer", "(JJIJI)J") => {
+            zip::inflater_inflate_buffer_buffer
+        }
+        ("java/util/zip/Deflater" | "java/util/zip/Inflater", "setDictionary", "(J[BII)V") => {
+            zip::zip_stream_set_dictionary
+        }
+        ("java/util/zip/Deflater" | "java/util/zip/Inflater", "setDictionaryBuffer", "(JJI)V") => {
+            zip::zip_stream_set_dictionary_buffer
+        }
+        ("java/util/zip/Deflater" | "java/util/zip/Inflater", "getAdler", "(J)I") => {
+            zip::zip_stream_get_adler
+        }
+        ("java/util/zip/Deflater" | "java/util/zip/Inflater", "reset", "(J)V") => {
+            zip::zip_stream_reset
+        }
+        ("java/util/zip/Deflater" | "java/util/zip/Inflater", "end", "(J)V") => zip::zip_stream_end,
+        ("jdk/internal/misc/Unsafe", "addressSize0", "()I") => memory::unsafe_address_size0,
+        ("jdk/internal/misc/Unsafe", "isBigEndian0", "()Z") => memory::unsafe_is_big_endian0,
+        ("jdk/internal/misc/Unsafe", "pageSize", "()I") => memory::unsafe_page_size,
+        ("jdk/internal/misc/Unsafe", "unalignedAccess0", "()Z") => memory::unsafe_unaligned_access0,
+        ("jdk/internal/misc/Unsafe", "allocateMemory0", "(J)J") => memory::unsafe_allocate_memory0,
+        ("jdk/internal/misc/Unsafe", "reallocateMemory0", "(JJ)J") => {
+            memory::unsafe_reallocate_memory0
+        }
+        ("jdk/internal/misc/Unsafe", "freeMemory0", "(J)V") => memory::unsafe_free_memory0,
+        ("jdk/internal/misc/Unsafe", "setMemory0", "(Ljava/lang/Object;JJB)V") => {
+            memory::unsafe_set_memory0
+        }
+        (
+            "jdk/internal/misc/Unsafe",
+            "copyMemory0",
+            "(Ljava/lang/Object;JLjava/lang/Object;JJ)V",
+        ) => memory::unsafe_copy_memory0,
+        (
+            "jdk/internal/misc/Unsafe",
+            "copySwapMemory0",
+            "(Ljava/lang/Object;JLjava/lang/Object;JJJ)V",
+        ) => memory::unsafe_copy_swap_memory0,
+        ("jdk/internal/misc/Unsafe", "getByte", "(Ljava/lang/Object;J)B") => {
+            memory::unsafe_get_byte
+        }
+        ("jdk/internal/misc/Unsafe", "putByte", "(Ljava/lang/Object;JB)V") => {
+            memory::unsafe_put_byte
+        }
+        ("jdk/internal/misc/Unsafe", "getShort", "(Ljava/lang/Object;J)S") => {
+            memory::unsafe_get_short
+        }
+        ("jdk/internal/misc/Unsafe", "putShort", "(Ljava/lang/Object;JS)V") => {
+            memory::unsafe_put_short
+        }
+        ("jdk/internal/misc/Unsafe", "getChar", "(Ljava/lang/Object;J)C") => {
+            memory::unsafe_get_char
+        }
+        ("jdk/internal/misc/Unsafe", "putChar", "(Ljava/lang/Object;JC)V") => {
+            memory::unsafe_put_char
+        }
+        ("jdk/internal/misc/Unsafe", "getInt", "(Ljava/lang/Object;J)I") => memory::unsafe_get_int,
+        ("jdk/internal/misc/Unsafe", "putInt", "(Ljava/lang/Object;JI)V") => memory::unsafe_put_int,
+        ("jdk/internal/misc/Unsafe", "getLong", "(Ljava/lang/Object;J)J") => {
+            memory::unsafe_get_long
+        }
+        ("jdk/internal/misc/Unsafe", "putLong", "(Ljava/lang/Object;JJ)V") => {
+            memory::unsafe_put_long
+        }
+        ("jdk/internal/misc/Unsafe", "getFloat", "(Ljava/lang/Object;J)F") => {
+            memory::unsafe_get_float
+        }
+        ("jdk/internal/misc/Unsafe", "putFloat", "(Ljava/lang/Object;JF)V") => {
+            memory::unsafe_put_float
+        }
+        ("jdk/internal/misc/Unsafe", "getDouble", "(Ljava/lang/Object;J)D") => {
+            memory::unsafe_get_double
+        }
+        ("jdk/internal/misc/Unsafe", "putDouble", "(Ljava/lang/Object;JD)V") => {
+            memory::unsafe_put_double
+        }
+        ("jdk/internal/misc/VM", "getNanoTimeAdjustment", "(J)J") => {
+            misc::vm_get_nano_time_adjustment
+        }
+        (
+            "sun/management/ThreadImpl",
+            "getThreadTotalCpuTime0" | "getThreadUserCpuTime0",
+            "(J)J",
+        ) => management::thread_impl_get_thread_cpu_time0,
+        ("sun/management/ThreadImpl", "setThreadCpuTimeEnabled0", "(Z)V") => {
+            management::thread_impl_set_thread_cpu_time_enabled0
+        }
+        ("sun/management/ThreadImpl", "setThreadContentionMonitoringEnabled0", "(Z)V") => {
+            management::thread_impl_set_thread_contention_monitoring_enabled0
+        }
+        ("sun/management/ThreadImpl", "resetContentionTimes0", "(J)V") => {
+            management::thread_impl_reset_contention_times0
+        }
+        _ => {
+            return Err(LinkageError::UnsatisfiedLink(
+                class.to_string(),
+                name.to_string(),
+                descriptor.to_string(),
+            )
+            .into())
+        }
+    })
+}
+
+/// Finds the intrinsic of a method that has a Java implementation, by the name
+/// of its class, and its name and descriptor. The VM invokes the intrinsic
+/// instead of interpreting the method, which computes the same result. These
+/// are the built-in intrinsics of every
+/// [`Intrinsics`](intrinsics::Intrinsics) registry.
+pub fn find_intrinsic(class: &str, name: &str, descriptor: &str) -> Option<NativeMethod> {
+    Some(match (class, name, descriptor) {
+        ("java/lang/Math", "floorDiv", "(II)I") => math::math_floor_div_int,
+        ("java/lang/Math", "floorDiv", "(JJ)J") => math::math_floor_div_long,
+        ("java/lang/Math", "fma", "(DDD)D") => math::math_fma_double,
+        ("java/lang/Math", "fma", "(FFF)F") => math::math_fma_float,
+        ("java/lang/Math", "multiplyHigh", "(JJ)J") => math::math_multiply_high,
+        _ => return None,
+    })
+}
+
+/// Returns the int argument at the given index. Natives are only invoked with
+/// arguments that match their descriptor, so anything else is a bug in the VM.
+fn int_arg(args: &[NativeValue], index: usize) -> Result<i32, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Integer(v)) => Ok(*v),
+        other => Err(invalid_arg("int", index, other)),
+    }
+}
+
+/// Returns the boolean argument at the given index, see [`int_arg`].
+fn boolean_arg(args: &[NativeValue], index: usize) -> Result<bool, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Boolean(v)) => Ok(*v),
+        other => Err(invalid_arg("boolean", index, other)),
+    }
+}
+
+/// Returns the byte argument at the given index, see [`int_arg`].
+fn byte_arg(args: &[NativeValue], index: usize) -> Result<i8, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Byte(v)) => Ok(*v),
+        other => Err(invalid_arg("byte", index, other)),
+    }
+}
+
+/// Returns the short argument at the given index, see [`int_arg`].
+fn short_arg(args: &[NativeValue], index: usize) -> Result<i16, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Short(v)) => Ok(*v),
+        other => Err(invalid_arg("short", index, other)),
+    }
+}
+
+/// Returns the char argument at the given index, see [`int_arg`].
+fn char_arg(args: &[NativeValue], index: usize) -> Result<u16, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Char(v)) => Ok(*v),
+        other => Err(invalid_arg("char", index, other)),
+    }
+}
+
+/// Returns the long argument at the given index, see [`int_arg`].
+fn long_arg(args: &[NativeValue], index: usize) -> Result<i64, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Long(v)) => Ok(*v),
+        other => Err(invalid_arg("long", index, other)),
+    }
+}
+
+/// Returns the float argument at the given index, see [`int_arg`].
+fn float_arg(args: &[NativeValue], index: usize) -> Result<f32, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Float(v)) => Ok(*v),
+        other => Err(invalid_arg("float", index, other)),
+    }
+}
+
+/// Returns the double argument at the given index, see [`int_arg`].
+fn double_arg(args: &[NativeValue], index: usize) -> Result<f64, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Double(v)) => Ok(*v),
+        other => Err(invalid_arg("double", index, other)),
+    }
+}
+
+/// Returns the reference argument at the given index, see [`int_arg`].
+fn reference_arg(args: &[NativeValue], index: usize) -> Result<usize, VmError> {
+    match args.get(index) {
+        Some(NativeValue::Reference(v)) => Ok(*v),
+        other => Err(invalid_arg("reference", index, other)),
+    }
+}
+
+/// Returns the instance with the given reference, failing with a
+/// `NullPointerException` for `null`.
+fn instance(heap: &Heap, reference: usize) -> Result<&Instance, VmError> {
+    match heap.get(reference) {
+        Some(HeapObject::Instance(instance)) => Ok(instance),
+        _ if reference == NULL => Err(Exception::NullPointer.into()),
+        _ => Err(VmError::Internal(format!(
+            "{:#x} is not a reference to an instance",
+            reference
+        ))),
+    }
+}
+
+/// Returns the array with the given reference, see [`instance`].
+pub(crate) fn array(heap: &Heap, reference: usize) -> Result<&Array, VmError> {
+    match heap.get(reference) {
+        Some(HeapObject::Array(array)) => Ok(array),
+        _ if reference == NULL => Err(Exception::NullPointer.into()),
+        _ => Err(VmError::Internal(format!(
+            "{:#x} is not a reference to an array",
+            reference
+        ))),
+    }
+}
+
+fn array_mut(heap: &mut Heap, reference: usize) -> Result<&mut Array, VmError> {
+    match heap.get_mut(reference) {
+        Some(HeapObject::Array(array)) => Ok(array),
+        _ if reference == NULL => Err(Exception::NullPointer.into()),
+        _ => Err(VmError::Internal(format!(
+            "{:#x} is not a reference to an array",
+            reference
+        ))),
+    }
+}
+
+/// Returns the value of the instance field with the given name and descriptor
+/// of an object, see [`instance`]. The natives only read fields that the class
+/// library declares, so a missing field is a bug in the VM.
+fn get_field(
+    heap: &Heap,
+    object: usize,
+    name: &str,
+    descriptor: &str,
+) -> Result<NativeValue, VmError> {
+    let instance = instance(heap, object)?;
+    Ok(instance.field(find_field(instance, name, descriptor)?))
+}
+
+/// Sets the value of an instance field of an object, see [`get_field`].
+fn set_field(
+    heap: &mut Heap,
+    object: usize,
+    name: &str,
+    descriptor: &str,
+    value: NativeValue,
+) -> Result<(), VmError> {
+    let field = find_field(instance(heap, object)?, name, descriptor)?.clone();
+    let instance = heap.get_mut(object).and_then(HeapObject::as_instance_mut);
+    instance.unwrap().set_field(&field, value)
+}
+
+fn reference_field(
+    heap: &Heap,
+    object: usize,
+    name: &str,
+    descriptor: &str,
+) -> Result<usize, VmError> {
+    match get_field(heap, object, name, descriptor)? {
+        NativeValue::Reference(reference) => Ok(reference),
+        other => Err(VmError::Internal(format!("{} is {:?}", name, other))),
+    }
+}
+
+fn int_field(heap: &Heap, object: usize, name: &str) -> Result<i32, VmError> {
+    match get_field(heap, object, name, "I")? {
+        NativeValue::Integer(value) => Ok(value),
+        other => Err(VmError::Internal(format!("{} is {:?}", name, other))),
+    }
+}
+
+/// Allocates an instance of the loaded class with the given binary name, see
+/// [`Runtime::instance_layout`].
+fn new_instance(runtime: &Runtime, heap: &mut Heap, class: &str) -> Result<usize, VmError> {
+    let (name, layout) = runtime
+        .instance_layout(class)
+        .ok_or_else(|| VmError::Internal(format!("{} is not loaded", class)))?;
+    Ok(heap.allocate_with_layout(name, layout))
+}
+
+/// The file descriptor of a stream or a socket: the `fd` field of the
+/// `FileDescriptor` in its `fd` field, which is -1 if it is not open.
+fn file_descriptor(heap: &Heap, object: usize) -> Result<i32, VmError> {
+    let descriptor = reference_field(heap, object, "fd", "Ljava/io/FileDescriptor;")?;
+    int_field(heap, descriptor, "fd")
+}
+
+/// Sets the file descriptor of a stream or a socket, see [`file_descriptor`].
+fn set_file_descriptor(heap: &mut Heap, object: usize, fd: i32) -> Result<(), VmError> {
+    let descriptor = reference_field(heap, object, "fd", "Ljava/io/FileDescriptor;")?;
+    set_field(heap, descriptor, "fd", "I", NativeValue::Integer(fd))
+}
+
+/// Copies the elements `off..off + len` of a `byte[]`, e.g. to write them
+/// without holding the lock of the heap. Fails with an
+/// `ArrayIndexOutOfBoundsException` if they are out of bounds.
+fn byte_array_range(heap: &Heap, bytes: usize, off: i32, len: i32) -> Result<Vec<u8>, VmError> {
+    let elements = array(heap, bytes)?.raw_elements();
+    Ok(elements[string::checked_range(elements.len(), off, len)?].to_vec())
+}
+
+/// Reads into the elements `off..off + len` of a `byte[]` with the given
+/// function, like the `readBytes` natives of streams. Returns the number of
+/// bytes read, or -1 at the end of the stream. The heap is not locked while
+/// reading, which may block, e.g. on the standard input.
+fn read_into_byte_array(
+    heap: &RwLock<Heap>,
+    bytes: usize,
+    off: i32,
+    len: i32,
+    read: impl FnOnce(&mut [u8]) -> std::io::Result<usize>,
+) -> Result<i32, VmError> {
+    let length = array(&heap.read().unwrap(), bytes)?.len();
+    let range = string::checked_range(length, off, len)?;
+    if range.is_empty() {
+        return Ok(0);
+    }
+    let mut buf = vec![0_u8; range.len()];
+    let n = read(&mut buf)?;
+    if n == 0 {
+        return Ok(-1);
+    }
+    let mut heap = heap.write().unwrap();
+    array_mut(&mut heap, bytes)?.raw_elements_mut()[range.start..range.start + n]
+        .copy_from_slice(&buf[..n]);
+    Ok(n as i32)
+}
+
+fn find_field<'a>(
+    instance: &'a Instance,
+    name: &str,
+    descriptor: &str,
+) -> Result<&'a Field, VmError> {
+    instance.layout().field(name, descriptor).ok_or_else(|| {
+        VmError::Internal(format!(
+            "{} has no field {} {}",
+            instance.class_name(),
+            name,
+            descriptor
+        ))
+    })
+}
+
+fn invalid_arg(expected: &str, index: usize, actual: Option<&NativeValue>) -> VmError {
+    VmError::Internal(format!(
+        "expected {} argument at {}, got {:?}",
+        expected, index, actual
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_bound_native() {
+        for (i, binding) in BINDINGS.iter().enumerate() {
+            let method = find_native(binding.class, binding.name, binding.descriptor).unwrap();
+            assert_eq!(binding.method as usize, method as usize);
+            // every method is bound only once
+            assert!(!BINDINGS[i + 1..]
+                .iter()
+                .any(|b| (b.class, b.name, b.descriptor)
+                    == (binding.class, binding.name, binding.descriptor)));
+        }
+        assert_eq!(
+            Some("java/lang/UnsatisfiedLinkError"),
+            find_native("java/lang/System", "nanoTime", "()I")
+                .err()
+                .map(|e| e.class_name())
+        );
+    }
+}
@@ -0,0 +1,682 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::Heap;
+use crate::vm::native::{
+    byte_array_range, file_descriptor, int_arg, int_field, new_instance, read_into_byte_array,
+    reference_arg, reference_field, set_field, set_file_descriptor, NativeResult,
+};
+use crate::vm::runtime::Runtime;
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use libjvm_macros::jvm_native;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The first file descriptor handed out by a [`SocketTable`].
+const FIRST_FD: i32 = 3;
+
+/// Which addresses Java code is allowed to connect to or listen on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum NetworkPolicy {
+    /// No network access at all.
+    #[default]
+    Deny,
+    /// Only loopback addresses, e.g. for tests of client/server programs.
+    Loopback,
+    /// Any address.
+    Allow,
+}
+
+impl NetworkPolicy {
+    pub fn permits(&self, addr: &SocketAddr) -> bool {
+        match self {
+            NetworkPolicy::Deny => false,
+            NetworkPolicy::Loopback => addr.ip().is_loopback(),
+            NetworkPolicy::Allow => true,
+        }
+    }
+}
+
+enum Socket {
+    Stream(TcpStream),
+    Listener(TcpListener),
+}
+
+/// The sockets that are opened by Java code (through `java.net.Socket` and
+/// `java.net.ServerSocket`), by their file descriptor.
+///
+/// Every address is checked against the [`NetworkPolicy`] before a socket is
+/// connected or bound.
+#[derive(Default)]
+pub struct SocketTable {
+    sockets: Mutex<HashMap<i32, Arc<Socket>>>,
+    next_fd: AtomicI32,
+    policy: RwLock<NetworkPolicy>,
+}
+
+impl SocketTable {
+    pub fn new() -> Self {
+        Self {
+            next_fd: AtomicI32::new(FIRST_FD),
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> NetworkPolicy {
+        *self.policy.read().unwrap()
+    }
+
+    pub fn set_policy(&self, policy: NetworkPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// Connects a stream socket to the given host and port, and returns its file
+    /// descriptor. Only resolved addresses that the policy permits are tried.
+    pub fn connect(&self, host: &str, port: u16) -> std::io::Result<i32> {
+        let addrs = self.permitted_addrs(host, port)?;
+        let stream = TcpStream::connect(addrs.as_slice())?;
+        Ok(self.insert(Socket::Stream(stream)))
+    }
+
+    /// Binds a listening socket to the given host and port (0 for any free
+    /// port), and returns its file descriptor.
+    pub fn bind(&self, host: &str, port: u16) -> std::io::Result<i32> {
+        let addrs = self.permitted_addrs(host, port)?;
+        let listener = TcpListener::bind(addrs.as_slice())?;
+        Ok(self.insert(Socket::Listener(listener)))
+    }
+
+    /// Waits for a connection on a listening socket, and returns the file
+    /// descriptor of the connected stream socket.
+    pub fn accept(&self, fd: i32) -> std::io::Result<i32> {
+        let (stream, _) = match &*self.get(fd)? {
+            Socket::Listener(listener) => listener.accept()?,
+            Socket::Stream(_) => return Err(not_listening()),
+        };
+        Ok(self.insert(Socket::Stream(stream)))
+    }
+
+    /// Reads from a stream socket. Returns 0 if the peer closed the connection.
+    pub fn read(&self, fd: i32, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &*self.get(fd)? {
+            Socket::Stream(stream) => (&*stream).read(buf),
+            Socket::Listener(_) => Err(not_connected()),
+        }
+    }
+
+    /// Writes the complete buffer to a stream socket.
+    pub fn write(&self, fd: i32, buf: &[u8]) -> std::io::Result<()> {
+        match &*self.get(fd)? {
+            Socket::Stream(stream) => (&*stream).write_all(buf),
+            Socket::Listener(_) => Err(not_connected()),
+        }
+    }
+
+    /// Shuts down the reading or writing half of a stream socket, as done by
+    /// `shutdownInput` and `shutdownOutput`.
+    pub fn shutdown(&self, fd: i32, how: Shutdown) -> std::io::Result<()> {
+        match &*self.get(fd)? {
+            Socket::Stream(stream) => stream.shutdown(how),
+            Socket::Listener(_) => Err(not_connected()),
+        }
+    }
+
+    /// The local address that the socket is bound to, e.g. to find out the
+    /// port of a socket bound to port 0.
+    pub fn local_addr(&self, fd: i32) -> std::io::Result<SocketAddr> {
+        match &*self.get(fd)? {
+            Socket::Stream(stream) => stream.local_addr(),
+            Socket::Listener(listener) => listener.local_addr(),
+        }
+    }
+
+    /// The address of the peer that a stream socket is connected to.
+    pub fn peer_addr(&self, fd: i32) -> std::io::Result<SocketAddr> {
+        match &*self.get(fd)? {
+            Socket::Stream(stream) => stream.peer_addr(),
+            Socket::Listener(_) => Err(not_connected()),
+        }
+    }
+
+    /// Closes the socket. Closing a file descriptor that is not open is an
+    /// error.
+    pub fn close(&self, fd: i32) -> std::io::Result<()> {
+        self.sockets
+            .lock()
+            .unwrap()
+            .remove(&fd)
+            .map(|_| ())
+            .ok_or_else(bad_fd)
+    }
+
+    fn permitted_addrs(&self, host: &str, port: u16) -> std::io::Result<Vec<SocketAddr>> {
+        let policy = self.policy();
+        let addrs: Vec<SocketAddr> = (host, port)
+            .to_socket_addrs()?
+            .filter(|addr| policy.permits(addr))
+            .collect();
+        if addrs.is_empty() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("network access to {}:{} is not permitted", host, port),
+            ));
+        }
+        Ok(addrs)
+    }
+
+    fn insert(&self, socket: Socket) -> i32 {
+        let fd = self.next_fd.fetch_add(1, Ordering::Relaxed);
+        self.sockets.lock().unwrap().insert(fd, Arc::new(socket));
+        fd
+    }
+
+    fn get(&self, fd: i32) -> std::io::Result<Arc<Socket>> {
+        // don't hold the table lock during blocking I/O
+        self.sockets
+            .lock()
+            .unwrap()
+            .get(&fd)
+            .cloned()
+            .ok_or_else(bad_fd)
+    }
+}
+
+// The natives below are the ones of `PlainSocketImpl`, which `Socket` and
+// `ServerSocket` use with `-Djdk.net.usePlainSocketImpl`, and of its socket
+// streams. The table only has connected and listening sockets, so a socket is
+// only created when it is connected or bound, and binding it also makes it
+// listen. Only IPv4 addresses are supported.
+
+/// `native void socketCreate(boolean stream, boolean isServer)`. The socket is
+/// created by `socketConnect` or `socketBind`.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketCreate",
+    sig = "(ZZ)V"
+)]
+pub fn plain_socket_impl_socket_create(
+    _thread: &mut Thread,
+    _args: &[NativeValue],
+) -> NativeResult {
+    Ok(None)
+}
+
+/// `native void socketConnect(InetAddress address, int port, int timeout)`.
+/// The timeout is ignored.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketConnect",
+    sig = "(Ljava/net/InetAddress;II)V"
+)]
+pub fn plain_socket_impl_socket_connect(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (socket, address) = (reference_arg(args, 0)?, reference_arg(args, 1)?);
+    let port = port_arg(args, 2)?;
+    let runtime = thread.runtime();
+    let host = host_of(&runtime.heap().read().unwrap(), address)?;
+    let fd = runtime
+        .sockets()
+        .connect(&host, port)
+        .map_err(VmError::from)?;
+    let local = runtime.sockets().local_addr(fd).map_err(VmError::from)?;
+    let mut heap = runtime.heap().write().unwrap();
+    set_file_descriptor(&mut heap, socket, fd)?;
+    set_field(
+        &mut heap,
+        socket,
+        "address",
+        INET_ADDRESS,
+        NativeValue::Reference(address),
+    )?;
+    set_field(
+        &mut heap,
+        socket,
+        "port",
+        "I",
+        NativeValue::Integer(port as i32),
+    )?;
+    set_field(
+        &mut heap,
+        socket,
+        "localport",
+        "I",
+        NativeValue::Integer(local.port() as i32),
+    )?;
+    Ok(None)
+}
+
+/// `native void socketBind(InetAddress address, int port)`, with port 0 for
+/// any free port.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketBind",
+    sig = "(Ljava/net/InetAddress;I)V"
+)]
+pub fn plain_socket_impl_socket_bind(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (socket, address) = (reference_arg(args, 0)?, reference_arg(args, 1)?);
+    let port = port_arg(args, 2)?;
+    let runtime = thread.runtime();
+    let host = host_of(&runtime.heap().read().unwrap(), address)?;
+    let fd = runtime.sockets().bind(&host, port).map_err(VmError::from)?;
+    let local = runtime.sockets().local_addr(fd).map_err(VmError::from)?;
+    let mut heap = runtime.heap().write().unwrap();
+    set_file_descriptor(&mut heap, socket, fd)?;
+    set_field(
+        &mut heap,
+        socket,
+        "address",
+        INET_ADDRESS,
+        NativeValue::Reference(address),
+    )?;
+    set_field(
+        &mut heap,
+        socket,
+        "localport",
+        "I",
+        NativeValue::Integer(local.port() as i32),
+    )?;
+    Ok(None)
+}
+
+/// `native void socketListen(int count)`. The socket listens since it was
+/// bound, with the default backlog of the host.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketListen",
+    sig = "(I)V"
+)]
+pub fn plain_socket_impl_socket_listen(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = socket_fd(thread, args)?;
+    thread
+        .runtime()
+        .sockets()
+        .local_addr(fd)
+        .map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// `native void socketAccept(SocketImpl s)`, which waits for a connection and
+/// connects the given socket to it.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketAccept",
+    sig = "(Ljava/net/SocketImpl;)V"
+)]
+pub fn plain_socket_impl_socket_accept(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let fd = socket_fd(thread, args)?;
+    let socket = reference_arg(args, 1)?;
+    let runtime = thread.runtime();
+    let sockets = runtime.sockets();
+    let accepted = sockets.accept(fd).map_err(VmError::from)?;
+    let (local, peer) = match (sockets.local_addr(accepted), sockets.peer_addr(accepted)) {
+        (Ok(local), Ok(peer)) => (local, peer),
+        (Err(e), _) | (_, Err(e)) => {
+            let _ = sockets.close(accepted);
+            return Err(VmError::from(e).into());
+        }
+    };
+    let mut heap = runtime.heap().write().unwrap();
+    let address = new_inet_address(runtime, &mut heap, &peer)?;
+    set_file_descriptor(&mut heap, socket, accepted)?;
+    set_field(
+        &mut heap,
+        socket,
+        "address",
+        INET_ADDRESS,
+        NativeValue::Reference(address),
+    )?;
+    set_field(
+        &mut heap,
+        socket,
+        "port",
+        "I",
+        NativeValue::Integer(peer.port() as i32),
+    )?;
+    set_field(
+        &mut heap,
+        socket,
+        "localport",
+        "I",
+        NativeValue::Integer(local.port() as i32),
+    )?;
+    Ok(None)
+}
+
+/// `native void socketClose0(boolean useDeferredClose)`, which closes the
+/// socket and invalidates its file descriptor. Closing it again, or closing a
+/// socket that was never connected or bound, does nothing.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketClose0",
+    sig = "(Z)V"
+)]
+pub fn plain_socket_impl_socket_close0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let socket = reference_arg(args, 0)?;
+    let runtime = thread.runtime();
+    let fd = {
+        let mut heap = runtime.heap().write().unwrap();
+        let fd = file_descriptor(&heap, socket)?;
+        set_file_descriptor(&mut heap, socket, -1)?;
+        fd
+    };
+    if fd != -1 {
+        runtime.sockets().close(fd).map_err(VmError::from)?;
+    }
+    Ok(None)
+}
+
+/// `native void socketShutdown(int howto)`, with `SHUT_RD` or `SHUT_WR` of
+/// `SocketImpl`.
+#[jvm_native(
+    class = "java/net/PlainSocketImpl",
+    name = "socketShutdown",
+    sig = "(I)V"
+)]
+pub fn plain_socket_impl_socket_shutdown(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let fd = socket_fd(thread, args)?;
+    let how = match int_arg(args, 1)? {
+        SHUT_RD => Shutdown::Read,
+        _ => Shutdown::Write,
+    };
+    thread
+        .runtime()
+        .sockets()
+        .shutdown(fd, how)
+        .map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// `private native int socketRead0(FileDescriptor fd, byte[] b, int off, int
+/// len, int timeout)` of `SocketInputStream`, which returns -1 if the peer
+/// closed the connection. The timeout is ignored.
+#[jvm_native(
+    class = "java/net/SocketInputStream",
+    name = "socketRead0",
+    sig = "(Ljava/io/FileDescriptor;[BIII)I"
+)]
+pub fn socket_input_stream_socket_read0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let runtime = thread.runtime();
+    let fd = int_field(
+        &runtime.heap().read().unwrap(),
+        reference_arg(args, 1)?,
+        "fd",
+    )?;
+    let (bytes, off, len) = (
+        reference_arg(args, 2)?,
+        int_arg(args, 3)?,
+        int_arg(args, 4)?,
+    );
+    let n = read_into_byte_array(runtime.heap(), bytes, off, len, |buf| {
+        runtime.sockets().read(fd, buf)
+    })?;
+    Ok(Some(NativeValue::Integer(n)))
+}
+
+/// `private native void socketWrite0(FileDescriptor fd, byte[] b, int off, int
+/// len)` of `SocketOutputStream`.
+#[jvm_native(
+    class = "java/net/SocketOutputStream",
+    name = "socketWrite0",
+    sig = "(Ljava/io/FileDescriptor;[BII)V"
+)]
+pub fn socket_output_stream_socket_write0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let runtime = thread.runtime();
+    let (fd, buf) = {
+        let heap = runtime.heap().read().unwrap();
+        let fd = int_field(&heap, reference_arg(args, 1)?, "fd")?;
+        let (bytes, off, len) = (
+            reference_arg(args, 2)?,
+            int_arg(args, 3)?,
+            int_arg(args, 4)?,
+        );
+        (fd, byte_array_range(&heap, bytes, off, len)?)
+    };
+    runtime.sockets().write(fd, &buf).map_err(VmError::from)?;
+    Ok(None)
+}
+
+/// The descriptor of the `address` field of `SocketImpl`.
+const INET_ADDRESS: &str = "Ljava/net/InetAddress;";
+/// The descriptor of the `holder` field of `InetAddress`.
+const INET_ADDRESS_HOLDER: &str = "Ljava/net/InetAddress$InetAddressHolder;";
+/// `InetAddress.IPv4`, the `family` of IPv4 addresses.
+const IPV4: i32 = 1;
+/// `SocketImpl.SHUT_RD`.
+const SHUT_RD: i32 = 0;
+
+/// The file descriptor of the socket that a native is invoked on.
+fn socket_fd(thread: &Thread, args: &[NativeValue]) -> Result<i32, VmError> {
+    file_descriptor(
+        &thread.runtime().heap().read().unwrap(),
+        reference_arg(args, 0)?,
+    )
+}
+
+fn port_arg(args: &[NativeValue], index: usize) -> Result<u16, VmError> {
+    let port = int_arg(args, index)?;
+    u16::try_from(port)
+        .map_err(|_| Exception::IllegalArgument(format!("port out of range:{}", port)).into())
+}
+
+/// The host of an `InetAddress` as a string, from the `address` and `family`
+/// of its `holder`.
+fn host_of(heap: &Heap, address: usize) -> Result<String, VmError> {
+    let holder = reference_field(heap, address, "holder", INET_ADDRESS_HOLDER)?;
+    match int_field(heap, holder, "family")? {
+        IPV4 => Ok(Ipv4Addr::from(int_field(heap, holder, "address")? as u32).to_string()),
+        _ => Err(Exception::Io("Protocol family unavailable".to_string()).into()),
+    }
+}
+
+/// Allocates an `Inet4Address` for the IP address of the given socket address.
+fn new_inet_address(
+    runtime: &Runtime,
+    heap: &mut Heap,
+    addr: &SocketAddr,
+) -> Result<usize, VmError> {
+    let SocketAddr::V4(addr) = addr else {
+        return Err(Exception::Io("Protocol family unavailable".to_string()).into());
+    };
+    let holder = new_instance(runtime, heap, "java/net/InetAddress$InetAddressHolder")?;
+    let ip = u32::from(*addr.ip()) as i32;
+    set_field(heap, holder, "address", "I", NativeValue::Integer(ip))?;
+    set_field(heap, holder, "family", "I", NativeValue::Integer(IPV4))?;
+    let address = new_instance(runtime, heap, "java/net/Inet4Address")?;
+    let holder = NativeValue::Reference(holder);
+    set_field(heap, address, "holder", INET_ADDRESS_HOLDER, holder)?;
+    Ok(address)
+}
+
+fn bad_fd() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Bad file descriptor")
+}
+
+fn not_connected() -> Error {
+    Error::new(ErrorKind::NotConnected, "Socket is not connected")
+}
+
+fn not_listening() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Socket is not listening")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::heap::NULL;
+    use crate::vm::native::test_util::{self, bytes_of, call, new_bytes, new_with_fd, thrown};
+    use crate::vm::types::NativeValue::{Boolean, Integer, Reference};
+
+    const SOCKET_IMPL: &str = "java/net/PlainSocketImpl";
+    const INPUT_STREAM: &str = "java/net/SocketInputStream";
+    const OUTPUT_STREAM: &str = "java/net/SocketOutputStream";
+    const READ: &str = "(Ljava/io/FileDescriptor;[BIII)I";
+    const WRITE: &str = "(Ljava/io/FileDescriptor;[BII)V";
+
+    #[test]
+    fn test_deny_by_default() {
+        let sockets = SocketTable::new();
+        assert_eq!(NetworkPolicy::Deny, sockets.policy());
+        let err = sockets.bind("127.0.0.1", 0).err().unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+    }
+
+    #[test]
+    fn test_loopback_policy() {
+        let policy = NetworkPolicy::Loopback;
+        assert!(policy.permits(&"127.0.0.1:80".parse().unwrap()));
+        assert!(policy.permits(&"[::1]:80".parse().unwrap()));
+        assert!(!policy.permits(&"10.0.0.1:80".parse().unwrap()));
+    }
+
+    /// Creates the socket of a `PlainSocketImpl` and connects or binds it with
+    /// the given native.
+    fn open(thread: &mut Thread, name: &str, descriptor: &str, args: &[NativeValue]) {
+        let create = [args[0].clone(), Boolean(true), Boolean(false)];
+        call(thread, SOCKET_IMPL, "socketCreate", "(ZZ)V", &create).unwrap();
+        call(thread, SOCKET_IMPL, name, descriptor, args).unwrap();
+    }
+
+    /// The `FileDescriptor` of a socket, which its streams read and write.
+    fn fd_of(runtime: &Runtime, socket: usize) -> usize {
+        let heap = runtime.heap().read().unwrap();
+        reference_field(&heap, socket, "fd", "Ljava/io/FileDescriptor;").unwrap()
+    }
+
+    /// Reads into the given `byte[16]` with `socketRead0`.
+    fn read(thread: &mut Thread, fd: usize, buf: usize) -> NativeResult {
+        let args = [NULL, fd, buf].map(Reference);
+        let args = [&args[..], &[Integer(0), Integer(16), Integer(0)]].concat();
+        call(thread, INPUT_STREAM, "socketRead0", READ, &args)
+    }
+
+    fn write(thread: &mut Thread, fd: usize, buf: usize, len: i32) -> NativeResult {
+        let args = [NULL, fd, buf].map(Reference);
+        let args = [&args[..], &[Integer(0), Integer(len)]].concat();
+        call(thread, OUTPUT_STREAM, "socketWrite0", WRITE, &args)
+    }
+
+    fn close(thread: &mut Thread, socket: usize) -> NativeResult {
+        let args = [Reference(socket), Boolean(false)];
+        call(thread, SOCKET_IMPL, "socketClose0", "(Z)V", &args)
+    }
+
+    #[test]
+    fn test_socket_natives() {
+        let mut vm = test_util::vm(&[
+            ("net", "java/net/InetAddress"),
+            ("net", "java/net/InetAddress$InetAddressHolder"),
+            ("net", "java/net/Inet4Address"),
+            ("net", "java/net/SocketImpl"),
+            ("net", SOCKET_IMPL),
+        ]);
+        let runtime = vm.runtime().clone();
+        runtime.sockets().set_policy(NetworkPolicy::Loopback);
+        let localhost = {
+            let mut heap = vm.heap().write().unwrap();
+            let addr = "127.0.0.1:0".parse().unwrap();
+            new_inet_address(&runtime, &mut heap, &addr).unwrap()
+        };
+        let sockets = [(); 3].map(|_| new_with_fd(&mut vm, SOCKET_IMPL));
+        let [server, accepted, client] = sockets;
+        let [echo_buf, ping, pong] = [&[0; 16][..], b"ping", &[0; 16]].map(|b| new_bytes(&vm, b));
+        let mut thread = Thread::new(runtime.clone());
+
+        let bind = "(Ljava/net/InetAddress;I)V";
+        let args = [Reference(server), Reference(localhost), Integer(0)];
+        open(&mut thread, "socketBind", bind, &args);
+        let args = [Reference(server), Integer(50)];
+        call(&mut thread, SOCKET_IMPL, "socketListen", "(I)V", &args).unwrap();
+        let port = int_field(&runtime.heap().read().unwrap(), server, "localport").unwrap();
+
+        let echo_runtime = runtime.clone();
+        let echo = std::thread::spawn(move || {
+            let mut thread = Thread::new(echo_runtime.clone());
+            let accept = "(Ljava/net/SocketImpl;)V";
+            let args = [Reference(server), Reference(accepted)];
+            call(&mut thread, SOCKET_IMPL, "socketAccept", accept, &args).unwrap();
+            let fd = fd_of(&echo_runtime, accepted);
+            let Ok(Some(Integer(n))) = read(&mut thread, fd, echo_buf) else {
+                panic!("nothing was read")
+            };
+            write(&mut thread, fd, echo_buf, n).unwrap();
+            close(&mut thread, accepted).unwrap();
+        });
+
+        let connect = "(Ljava/net/InetAddress;II)V";
+        let args = [client, localhost].map(Reference);
+        let args = [&args[..], &[Integer(port), Integer(0)]].concat();
+        open(&mut thread, "socketConnect", connect, &args);
+        let fd = fd_of(&runtime, client);
+        write(&mut thread, fd, ping, 4).unwrap();
+        let args = [Reference(client), Integer(1)];
+        call(&mut thread, SOCKET_IMPL, "socketShutdown", "(I)V", &args).unwrap();
+        assert_eq!(Ok(Some(Integer(4))), read(&mut thread, fd, pong));
+        assert_eq!(b"ping", &bytes_of(&vm, pong)[..4]);
+        assert_eq!(Ok(Some(Integer(-1))), read(&mut thread, fd, pong));
+        echo.join().unwrap();
+
+        {
+            // the accepted socket is connected to the port of the client
+            let heap = runtime.heap().read().unwrap();
+            let peer = reference_field(&heap, accepted, "address", INET_ADDRESS).unwrap();
+            assert_eq!("127.0.0.1", host_of(&heap, peer).unwrap());
+            let client_port = int_field(&heap, client, "localport");
+            assert_eq!(client_port, int_field(&heap, accepted, "port"));
+            assert_eq!(Ok(port), int_field(&heap, client, "port"));
+        }
+        for socket in [client, server] {
+            close(&mut thread, socket).unwrap();
+            // closing again does nothing
+            close(&mut thread, socket).unwrap();
+        }
+        assert!(runtime.sockets().sockets.lock().unwrap().is_empty());
+
+        runtime.sockets().set_policy(NetworkPolicy::Deny);
+        let args = [Reference(server), Reference(localhost), Integer(0)];
+        assert_eq!(
+            "java/io/IOException",
+            thrown(call(&mut thread, SOCKET_IMPL, "socketBind", bind, &args))
+        );
+        let args = [Reference(server), Reference(localhost), Integer(65536)];
+        assert_eq!(
+            "java/lang/IllegalArgumentException",
+            thrown(call(&mut thread, SOCKET_IMPL, "socketBind", bind, &args))
+        );
+    }
+
+    #[test]
+    fn test_echo() {
+        let sockets = Arc::new(SocketTable::new());
+        sockets.set_policy(NetworkPolicy::Loopback);
+        let server = sockets.bind("127.0.0.1", 0).unwrap();
+        let port = sockets.local_addr(server).unwrap().port();
+
+        let s = sockets.clone();
+        let echo = std::thread::spawn(move || {
+            let conn = s.accept(server).unwrap();
+            let mut buf = [0_u8; 16];
+            let n = s.read(conn, &mut buf).unwrap();
+            s.write(conn, &buf[..n]).unwrap();
+            s.close(conn).unwrap();
+        });
+
+        let client = sockets.connect("localhost", port).unwrap();
+        sockets.write(client, b"ping").unwrap();
+        sockets.shutdown(client, Shutdown::Write).unwrap();
+        let mut buf = [0_u8; 16];
+        let n = sockets.read(client, &mut buf).unwrap();
+        assert_eq!(b"ping", &buf[..n]);
+        echo.join().unwrap();
+
+        sockets.close(client).unwrap();
+        sockets.close(server).unwrap();
+        assert!(sockets.close(server).is_err());
+    }
+}
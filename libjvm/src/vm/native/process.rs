@@ -0,0 +1,949 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::{Array, Heap, NULL};
+use crate::vm::native::io::{FileTable, StdOutput};
+use crate::vm::native::{
+    array, array_mut, boolean_arg, int_arg, long_arg, reference_arg, NativeResult,
+};
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use libjvm_macros::jvm_native;
+use libvfs::file::File;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often [`ProcessTable::wait_for`] checks whether a child has terminated.
+const WAIT_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Which programs Java code is allowed to run.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub enum ProcessPolicy {
+    /// No child processes at all.
+    #[default]
+    Deny,
+    /// Only the programs with the given names, e.g. `git`. A program is
+    /// matched by the last component of its path, so `/usr/bin/git` is
+    /// permitted as well.
+    Only(Vec<String>),
+    /// Any program.
+    Allow,
+}
+
+impl ProcessPolicy {
+    pub fn permits(&self, program: &str) -> bool {
+        match self {
+            ProcessPolicy::Deny => false,
+            ProcessPolicy::Only(programs) => {
+                let name = Path::new(program)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or(program);
+                programs.iter().any(|p| p == name)
+            }
+            ProcessPolicy::Allow => true,
+        }
+    }
+}
+
+/// Where a standard stream of a child process is connected to, like
+/// `ProcessBuilder.Redirect`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Redirect {
+    /// A pipe that Java code reads from or writes to through the process table,
+    /// or through the file table once it is opened there, the default of
+    /// `ProcessBuilder`.
+    #[default]
+    Pipe,
+    /// The corresponding standard stream of the VM (see [`FileTable`]), so
+    /// that the output of the child ends up wherever the output of the VM is
+    /// redirected to.
+    Inherit,
+    /// Nothing is read, and the output is discarded.
+    Discard,
+    /// A file that Java code opened, by its file descriptor in the
+    /// [`FileTable`], for `Redirect.from`, `Redirect.to` and
+    /// `Redirect.appendTo`.
+    File(i32),
+}
+
+/// The arguments of `ProcessImpl.forkAndExec`.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ProcessSpec {
+    /// The program and its arguments.
+    pub command: Vec<String>,
+    /// The complete environment of the child, or [`None`] for the environment
+    /// of the VM.
+    pub env: Option<Vec<(String, String)>>,
+    /// The working directory of the child, or [`None`] for the one of the VM.
+    pub dir: Option<String>,
+    pub stdin: Redirect,
+    pub stdout: Redirect,
+    pub stderr: Redirect,
+    /// Whether the error output of the child is merged into its standard
+    /// output, like `ProcessBuilder.redirectErrorStream`.
+    pub redirect_error_stream: bool,
+}
+
+/// The streams that are forwarded between a child and the VM, see
+/// [`ProcessTable::spawn`].
+struct Forwards {
+    stdin: Option<Box<dyn Read + Send>>,
+    stdout: Option<Box<dyn Write + Send>>,
+    stderr: Option<Box<dyn Write + Send>>,
+}
+
+struct Process {
+    child: Mutex<Child>,
+    stdin: Mutex<Option<ChildStdin>>,
+    stdout: Mutex<Option<Output>>,
+    stderr: Mutex<Option<Output>>,
+}
+
+/// The reading end of an output pipe of a child process.
+type Output = Box<dyn Read + Send>;
+
+/// A pipe to or from a child process as a [`File`] of the [`FileTable`].
+/// Closing it closes the pipe, so that the child reads the end of its input.
+enum Pipe {
+    Input(ChildStdin),
+    Output(Output),
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Pipe::Input(_) => Err(Error::new(ErrorKind::Unsupported, "pipe is write-only")),
+            Pipe::Output(output) => output.read(buf),
+        }
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Pipe::Input(input) => input.write(buf),
+            Pipe::Output(_) => Err(Error::new(ErrorKind::Unsupported, "pipe is read-only")),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Pipe::Input(input) => input.flush(),
+            Pipe::Output(_) => Ok(()),
+        }
+    }
+}
+
+impl Seek for Pipe {
+    fn seek(&mut self, _: SeekFrom) -> std::io::Result<u64> {
+        Err(Error::new(ErrorKind::Unsupported, "Illegal seek"))
+    }
+}
+
+/// The child processes that are started by Java code (through
+/// `ProcessBuilder` or `Runtime.exec`), by their process id.
+///
+/// Every program is checked against the [`ProcessPolicy`] before it is
+/// started.
+#[derive(Default)]
+pub struct ProcessTable {
+    processes: Mutex<HashMap<u32, Arc<Process>>>,
+    policy: RwLock<ProcessPolicy>,
+}
+
+impl ProcessTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn policy(&self) -> ProcessPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    pub fn set_policy(&self, policy: ProcessPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    /// Starts a child process and returns its process id. Streams that are
+    /// inherited or redirected to files are forwarded from and to the streams
+    /// in `files` by background threads.
+    pub fn spawn(&self, spec: &ProcessSpec, files: &FileTable) -> std::io::Result<u32> {
+        let (program, args) = spec
+            .command
+            .split_first()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "empty command"))?;
+        if !self.policy().permits(program) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("running {} is not permitted", program),
+            ));
+        }
+        // a bad file descriptor fails before the child is started
+        let forwards = Forwards {
+            stdin: source(spec.stdin, files)?,
+            stdout: sink(spec.stdout, files, || files.stdout_handle())?,
+            stderr: sink(spec.stderr, files, || files.stderr_handle())?,
+        };
+
+        let mut command = Command::new(program);
+        command.args(args);
+        if let Some(env) = &spec.env {
+            command.env_clear().envs(env.iter().cloned());
+        }
+        if let Some(dir) = &spec.dir {
+            command.current_dir(dir);
+        }
+        command
+            .stdin(stdio(spec.stdin))
+            .stdout(stdio(spec.stdout))
+            .stderr(stdio(spec.stderr));
+        if spec.redirect_error_stream && spec.stdout == Redirect::Discard {
+            command.stderr(Stdio::null());
+        } else if spec.redirect_error_stream {
+            // both streams are read through the same pipe, so that their
+            // order is kept, like the JDK does with dup2
+            let (reader, writer) = std::io::pipe()?;
+            command.stdout(writer.try_clone()?).stderr(writer);
+            let mut child = command.spawn()?;
+            // the pipe is only closed once the command doesn't hold the
+            // writing ends anymore
+            drop(command);
+            let stdin = child.stdin.take();
+            let stdout = Some(Box::new(reader) as Output);
+            return Ok(self.insert(child, stdin, stdout, None, forwards));
+        }
+
+        let mut child = command.spawn()?;
+        drop(command);
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().map(|s| Box::new(s) as Output);
+        let stderr = child.stderr.take().map(|s| Box::new(s) as Output);
+        Ok(self.insert(child, stdin, stdout, stderr, forwards))
+    }
+
+    /// Moves the pipes of the child to `files`, so that Java code reads and
+    /// writes them through file descriptors like any other stream. Returns
+    /// the file descriptors of the standard input, output and error of the
+    /// child, or -1 for a stream that is not piped, which is what
+    /// `forkAndExec` stores in its `fds`.
+    pub fn open_pipes(&self, pid: u32, files: &FileTable) -> std::io::Result<[i32; 3]> {
+        let process = self.get(pid)?;
+        let stdin = process.stdin.lock().unwrap().take().map(Pipe::Input);
+        let stdout = process.stdout.lock().unwrap().take().map(Pipe::Output);
+        let stderr = process.stderr.lock().unwrap().take().map(Pipe::Output);
+        Ok([stdin, stdout, stderr]
+            .map(|pipe| pipe.map_or(-1, |p| files.insert(File::new(Box::new(p))))))
+    }
+
+    /// Writes the complete buffer to the standard input of the child.
+    pub fn write_stdin(&self, pid: u32, buf: &[u8]) -> std::io::Result<()> {
+        match &mut *self.get(pid)?.stdin.lock().unwrap() {
+            Some(stdin) => stdin.write_all(buf),
+            None => Err(not_piped()),
+        }
+    }
+
+    /// Closes the standard input of the child, so that it reads the end of
+    /// the stream.
+    pub fn close_stdin(&self, pid: u32) -> std::io::Result<()> {
+        self.get(pid)?.stdin.lock().unwrap().take();
+        Ok(())
+    }
+
+    /// Reads from the standard output of the child. Returns 0 at the end of
+    /// the stream.
+    pub fn read_stdout(&self, pid: u32, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut *self.get(pid)?.stdout.lock().unwrap() {
+            Some(stdout) => stdout.read(buf),
+            None => Err(not_piped()),
+        }
+    }
+
+    /// Reads from the error output of the child, see [`Self::read_stdout`].
+    pub fn read_stderr(&self, pid: u32, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut *self.get(pid)?.stderr.lock().unwrap() {
+            Some(stderr) => stderr.read(buf),
+            None => Err(not_piped()),
+        }
+    }
+
+    /// Waits for the child to terminate and returns its exit code, like
+    /// `Process.waitFor`.
+    pub fn wait_for(&self, pid: u32) -> std::io::Result<i32> {
+        // poll instead of blocking in wait, so that other threads can still
+        // destroy the child in the meantime
+        loop {
+            if let Some(code) = self.try_wait(pid)? {
+                return Ok(code);
+            }
+            std::thread::sleep(WAIT_INTERVAL);
+        }
+    }
+
+    /// Returns the exit code of the child, or [`None`] if it is still running,
+    /// like `Process.exitValue` and `Process.isAlive`.
+    pub fn try_wait(&self, pid: u32) -> std::io::Result<Option<i32>> {
+        let status = self.get(pid)?.child.lock().unwrap().try_wait()?;
+        Ok(status.map(exit_code))
+    }
+
+    /// Kills the child, like `Process.destroy` and `Process.destroyForcibly`.
+    /// The standard library can only kill a process forcibly, so both are the
+    /// same. Destroying a child that has terminated already does nothing.
+    pub fn destroy(&self, pid: u32) -> std::io::Result<()> {
+        let process = self.get(pid)?;
+        let mut child = process.child.lock().unwrap();
+        match child.try_wait()? {
+            Some(_) => Ok(()),
+            None => child.kill(),
+        }
+    }
+
+    /// Removes a terminated child from the table, and closes its pipes.
+    pub fn release(&self, pid: u32) -> std::io::Result<()> {
+        if self.try_wait(pid)?.is_none() {
+            return Err(Error::new(ErrorKind::InvalidInput, "process hasn't exited"));
+        }
+        self.processes.lock().unwrap().remove(&pid);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.processes.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(
+        &self,
+        child: Child,
+        stdin: Option<ChildStdin>,
+        stdout: Option<Output>,
+        stderr: Option<Output>,
+        forwards: Forwards,
+    ) -> u32 {
+        let mut stdin = stdin;
+        if let Some(mut from) = forwards.stdin {
+            if let Some(mut to) = stdin.take() {
+                forward(move || std::io::copy(&mut from, &mut to));
+            }
+        }
+        let stdout = forward_output(stdout, forwards.stdout);
+        let stderr = forward_output(stderr, forwards.stderr);
+
+        let pid = child.id();
+        let process = Process {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            stdout: Mutex::new(stdout),
+            stderr: Mutex::new(stderr),
+        };
+        self.processes
+            .lock()
+            .unwrap()
+            .insert(pid, Arc::new(process));
+        pid
+    }
+
+    fn get(&self, pid: u32) -> std::io::Result<Arc<Process>> {
+        // don't hold the table lock during blocking I/O
+        self.processes
+            .lock()
+            .unwrap()
+            .get(&pid)
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no process {}", pid)))
+    }
+}
+
+fn stdio(redirect: Redirect) -> Stdio {
+    match redirect {
+        // inherited streams are piped and forwarded, since the standard
+        // streams of the VM aren't necessarily the ones of the host, and
+        // neither are its files
+        Redirect::Pipe | Redirect::Inherit | Redirect::File(_) => Stdio::piped(),
+        Redirect::Discard => Stdio::null(),
+    }
+}
+
+/// Where the standard input of a child is forwarded from, if it is inherited
+/// or read from a file.
+fn source(redirect: Redirect, files: &FileTable) -> std::io::Result<Option<Box<dyn Read + Send>>> {
+    Ok(match redirect {
+        Redirect::Inherit => Some(Box::new(files.stdin_handle())),
+        Redirect::File(fd) => Some(Box::new(files.file_handle(fd)?)),
+        Redirect::Pipe | Redirect::Discard => None,
+    })
+}
+
+/// Where an output stream of a child is forwarded to, if it is inherited or
+/// written to a file, see [`source`].
+fn sink(
+    redirect: Redirect,
+    files: &FileTable,
+    vm_stream: impl FnOnce() -> StdOutput,
+) -> std::io::Result<Option<Box<dyn Write + Send>>> {
+    Ok(match redirect {
+        Redirect::Inherit => Some(Box::new(vm_stream())),
+        Redirect::File(fd) => Some(Box::new(files.file_handle(fd)?)),
+        Redirect::Pipe | Redirect::Discard => None,
+    })
+}
+
+/// Forwards an output stream of the child if it has a sink, and returns it
+/// otherwise.
+fn forward_output(output: Option<Output>, sink: Option<Box<dyn Write + Send>>) -> Option<Output> {
+    match (output, sink) {
+        (Some(mut from), Some(mut to)) => {
+            forward(move || std::io::copy(&mut from, &mut to));
+            None
+        }
+        (output, _) => output,
+    }
+}
+
+fn forward(copy: impl FnOnce() -> std::io::Result<u64> + Send + 'static) {
+    // the copy ends when either side is closed, so the thread never outlives
+    // the child by much, and there is nobody to report an error to
+    std::thread::spawn(move || {
+        let _ = copy();
+    });
+}
+
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        // like the JDK, a child killed by a signal exits with 128 + signal
+        if let Some(signal) = status.signal() {
+            return 0x80 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+fn not_piped() -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        "stream is not redirected to a pipe",
+    )
+}
+
+/// `private static native void init()` of `ProcessImpl`, which initializes the
+/// launch mechanisms. There is nothing to initialize.
+#[jvm_native(class = "java/lang/ProcessImpl", name = "init", sig = "()V")]
+pub fn process_impl_init(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(None)
+}
+
+/// `private native int forkAndExec(int mode, byte[] helperpath, byte[] prog,
+/// byte[] argBlock, int argc, byte[] envBlock, int envc, byte[] dir,
+/// int[] fds, boolean redirectErrorStream)` of `ProcessImpl`. The child is
+/// started by [`ProcessTable::spawn`] whatever the launch mechanism is, and
+/// its pipes are opened in the [`FileTable`], see [`ProcessTable::open_pipes`].
+#[jvm_native(
+    class = "java/lang/ProcessImpl",
+    name = "forkAndExec",
+    sig = "(I[B[B[BI[BI[B[IZ)I"
+)]
+pub fn process_impl_fork_and_exec(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let runtime = thread.runtime();
+    let fds = reference_arg(args, 9)?;
+    let spec = {
+        let heap = runtime.heap().read().unwrap();
+        let mut command = c_strings(&heap, reference_arg(args, 3)?, 1)?;
+        command.extend(c_strings(
+            &heap,
+            reference_arg(args, 4)?,
+            int_arg(args, 5)?,
+        )?);
+        let env = match reference_arg(args, 6)? {
+            NULL => None,
+            block => Some(
+                c_strings(&heap, block, int_arg(args, 7)?)?
+                    .into_iter()
+                    .map(|entry| match entry.split_once('=') {
+                        Some((key, value)) => (key.to_string(), value.to_string()),
+                        None => (entry, String::new()),
+                    })
+                    .collect(),
+            ),
+        };
+        let dir = match reference_arg(args, 8)? {
+            NULL => None,
+            dir => c_strings(&heap, dir, 1)?.pop(),
+        };
+        let fds = array(&heap, fds)?;
+        ProcessSpec {
+            command,
+            env,
+            dir,
+            stdin: redirect(fds, 0)?,
+            stdout: redirect(fds, 1)?,
+            stderr: redirect(fds, 2)?,
+            redirect_error_stream: boolean_arg(args, 10)?,
+        }
+    };
+
+    let processes = runtime.processes();
+    let pid = processes
+        .spawn(&spec, runtime.files())
+        .map_err(io_exception)?;
+    let pipes = processes
+        .open_pipes(pid, runtime.files())
+        .map_err(io_exception)?;
+    let mut heap = runtime.heap().write().unwrap();
+    let fds = array_mut(&mut heap, fds)?;
+    for (i, fd) in pipes.into_iter().enumerate() {
+        fds.set_element(i as i32, NativeValue::Integer(fd))?;
+    }
+    Ok(Some(NativeValue::Integer(pid as i32)))
+}
+
+/// `private static native void initNative()` of `ProcessHandleImpl`. There is
+/// nothing to initialize.
+#[jvm_native(
+    class = "java/lang/ProcessHandleImpl",
+    name = "initNative",
+    sig = "()V"
+)]
+pub fn process_handle_impl_init_native(
+    _thread: &mut Thread,
+    _args: &[NativeValue],
+) -> NativeResult {
+    Ok(None)
+}
+
+/// `private static native long getCurrentPid0()` of `ProcessHandleImpl`.
+#[jvm_native(
+    class = "java/lang/ProcessHandleImpl",
+    name = "getCurrentPid0",
+    sig = "()J"
+)]
+pub fn process_handle_impl_get_current_pid0(
+    _thread: &mut Thread,
+    _args: &[NativeValue],
+) -> NativeResult {
+    Ok(Some(NativeValue::Long(std::process::id() as i64)))
+}
+
+/// `private static native int waitForProcessExit0(long pid, boolean
+/// reapStatus)` of `ProcessHandleImpl`, which the reaper thread of a child
+/// calls. A reaped child is released from the [`ProcessTable`].
+#[jvm_native(
+    class = "java/lang/ProcessHandleImpl",
+    name = "waitForProcessExit0",
+    sig = "(JZ)I"
+)]
+pub fn process_handle_impl_wait_for_process_exit0(
+    thread: &mut Thread,
+    args: &[NativeValue],
+) -> NativeResult {
+    let pid = pid_arg(args, 0)?;
+    let processes = thread.runtime().processes();
+    let code = processes.wait_for(pid).map_err(io_exception)?;
+    if boolean_arg(args, 1)? {
+        processes.release(pid).map_err(io_exception)?;
+    }
+    Ok(Some(NativeValue::Integer(code)))
+}
+
+/// `private static native long isAlive0(long pid)` of `ProcessHandleImpl`,
+/// which returns the start time of a running process, or -1 if it is not
+/// alive. The start time of a child is not known, which is 0.
+#[jvm_native(class = "java/lang/ProcessHandleImpl", name = "isAlive0", sig = "(J)J")]
+pub fn process_handle_impl_is_alive0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let processes = thread.runtime().processes();
+    let alive = pid_arg(args, 0).is_ok_and(|pid| matches!(processes.try_wait(pid), Ok(None)));
+    Ok(Some(NativeValue::Long(if alive { 0 } else { -1 })))
+}
+
+/// `private static native boolean destroy0(long pid, long startTime, boolean
+/// forcibly)` of `ProcessHandleImpl`, see [`ProcessTable::destroy`]. Only the
+/// children of the VM can be destroyed.
+#[jvm_native(
+    class = "java/lang/ProcessHandleImpl",
+    name = "destroy0",
+    sig = "(JJZ)Z"
+)]
+pub fn process_handle_impl_destroy0(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let processes = thread.runtime().processes();
+    let destroyed = pid_arg(args, 0).is_ok_and(|pid| processes.destroy(pid).is_ok());
+    Ok(Some(NativeValue::Boolean(destroyed)))
+}
+
+/// The first `count` NUL-terminated strings of a `byte[]`, as `ProcessImpl`
+/// encodes the program, its arguments, the environment and the directory.
+fn c_strings(heap: &Heap, bytes: usize, count: i32) -> Result<Vec<String>, VmError> {
+    Ok(array(heap, bytes)?
+        .raw_elements()
+        .split(|b| *b == 0)
+        .take(count.max(0) as usize)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect())
+}
+
+/// The redirect of a standard stream in the `fds` of `forkAndExec`: -1 for a
+/// pipe, the stream itself if it is inherited, and the file descriptor of a
+/// file otherwise.
+fn redirect(fds: &Array, stream: i32) -> Result<Redirect, VmError> {
+    Ok(match fds.element(stream)? {
+        NativeValue::Integer(-1) => Redirect::Pipe,
+        NativeValue::Integer(fd) if fd == stream => Redirect::Inherit,
+        NativeValue::Integer(fd) => Redirect::File(fd),
+        other => return Err(VmError::Internal(format!("fd is {:?}", other))),
+    })
+}
+
+/// The process id argument at the given index. An id that doesn't fit is not
+/// the one of a child.
+fn pid_arg(args: &[NativeValue], index: usize) -> Result<u32, VmError> {
+    let pid = long_arg(args, index)?;
+    u32::try_from(pid).map_err(|_| {
+        io_exception(Error::new(
+            ErrorKind::NotFound,
+            format!("no process {}", pid),
+        ))
+    })
+}
+
+/// Fails with an `IOException`, since a missing program or process is not a
+/// missing file.
+fn io_exception(e: Error) -> VmError {
+    Exception::Io(e.to_string()).into()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::vm::descriptor::FieldType;
+    use crate::vm::native::io::OpenMode;
+    use crate::vm::native::test_util::{self, call, new_bytes, thrown};
+    use crate::vm::types::NativeValue::{Boolean, Integer, Long, Reference};
+    use crate::vm::VM;
+
+    const PROCESS_IMPL: &str = "java/lang/ProcessImpl";
+    const PROCESS_HANDLE_IMPL: &str = "java/lang/ProcessHandleImpl";
+
+    fn sh(script: &str) -> ProcessSpec {
+        ProcessSpec {
+            command: vec!["/bin/sh".into(), "-c".into(), script.into()],
+            ..Default::default()
+        }
+    }
+
+    fn read_to_string(
+        read: impl Fn(&mut [u8]) -> std::io::Result<usize>,
+    ) -> std::io::Result<String> {
+        let mut out = vec![];
+        let mut buf = [0_u8; 64];
+        loop {
+            match read(&mut buf)? {
+                0 => return Ok(String::from_utf8(out).unwrap()),
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    fn allowed() -> ProcessTable {
+        let processes = ProcessTable::new();
+        processes.set_policy(ProcessPolicy::Allow);
+        processes
+    }
+
+    #[test]
+    fn test_deny_by_default() {
+        let processes = ProcessTable::new();
+        assert_eq!(ProcessPolicy::Deny, processes.policy());
+        let err = processes
+            .spawn(&sh("true"), &FileTable::new())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+
+        let policy = ProcessPolicy::Only(vec!["sh".into()]);
+        assert!(policy.permits("/bin/sh"));
+        assert!(policy.permits("sh"));
+        assert!(!policy.permits("/bin/bash"));
+    }
+
+    #[test]
+    fn test_pipes() {
+        let processes = allowed();
+        let pid = processes
+            .spawn(&sh("cat; echo done >&2; exit 3"), &FileTable::new())
+            .unwrap();
+        processes.write_stdin(pid, b"hello").unwrap();
+        processes.close_stdin(pid).unwrap();
+        assert_eq!(
+            "hello",
+            read_to_string(|b| processes.read_stdout(pid, b)).unwrap()
+        );
+        assert_eq!(
+            "done\n",
+            read_to_string(|b| processes.read_stderr(pid, b)).unwrap()
+        );
+        assert_eq!(3, processes.wait_for(pid).unwrap());
+        assert_eq!(Some(3), processes.try_wait(pid).unwrap());
+
+        processes.release(pid).unwrap();
+        assert!(processes.is_empty());
+    }
+
+    #[test]
+    fn test_environment_and_directory() {
+        let processes = allowed();
+        let mut spec = sh("echo $GREETING; pwd");
+        spec.env = Some(vec![("GREETING".into(), "hi".into())]);
+        spec.dir = Some("/".into());
+        let pid = processes.spawn(&spec, &FileTable::new()).unwrap();
+        assert_eq!(
+            "hi\n/\n",
+            read_to_string(|b| processes.read_stdout(pid, b)).unwrap()
+        );
+        assert_eq!(0, processes.wait_for(pid).unwrap());
+    }
+
+    #[test]
+    fn test_redirects() {
+        let processes = allowed();
+        let mut spec = sh("echo out; echo err >&2");
+        spec.redirect_error_stream = true;
+        let pid = processes.spawn(&spec, &FileTable::new()).unwrap();
+        assert_eq!(
+            "out\nerr\n",
+            read_to_string(|b| processes.read_stdout(pid, b)).unwrap()
+        );
+        assert!(processes.read_stderr(pid, &mut [0; 1]).is_err());
+
+        // inherited output ends up in the redirected streams of the VM
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let files = FileTable::new();
+        let out = Arc::new(Mutex::new(vec![]));
+        files.set_stdout(Box::new(Shared(out.clone())));
+        files.set_stdin(Box::new(&b"forwarded"[..]));
+        let mut spec = sh("cat; echo discarded >&2");
+        spec.stdin = Redirect::Inherit;
+        spec.stdout = Redirect::Inherit;
+        spec.stderr = Redirect::Discard;
+        let pid = processes.spawn(&spec, &files).unwrap();
+        assert_eq!(0, processes.wait_for(pid).unwrap());
+        // the output is forwarded by another thread
+        for _ in 0..100 {
+            if out.lock().unwrap().len() == 9 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(b"forwarded", out.lock().unwrap().as_slice());
+        assert!(processes.read_stdout(pid, &mut [0; 1]).is_err());
+    }
+
+    #[test]
+    fn test_destroy() {
+        let processes = allowed();
+        let pid = processes.spawn(&sh("sleep 10"), &FileTable::new()).unwrap();
+        assert_eq!(None, processes.try_wait(pid).unwrap());
+        assert!(processes.release(pid).is_err());
+        processes.destroy(pid).unwrap();
+        // SIGKILL
+        assert_eq!(137, processes.wait_for(pid).unwrap());
+        processes.destroy(pid).unwrap();
+    }
+
+    /// Starts `/bin/sh -c script` through `forkAndExec`, with the given
+    /// environment block and `fds`, and returns the pid and the `fds`.
+    fn fork_and_exec(
+        vm: &VM,
+        thread: &mut Thread,
+        script: &str,
+        env: &[&str],
+        fds: [i32; 3],
+    ) -> (NativeResult, [i32; 3]) {
+        let block = |strings: &[&str]| {
+            let joined: String = strings.iter().map(|s| format!("{}\0", s)).collect();
+            new_bytes(vm, joined.as_bytes())
+        };
+        let fds_array = {
+            let mut heap = vm.heap().write().unwrap();
+            let array = heap.allocate_array(Arc::new(FieldType::Int), 3).unwrap();
+            for (i, fd) in fds.into_iter().enumerate() {
+                array_mut(&mut heap, array)
+                    .unwrap()
+                    .set_element(i as i32, Integer(fd))
+                    .unwrap();
+            }
+            array
+        };
+        let env = match env {
+            [] => Reference(NULL),
+            env => Reference(block(env)),
+        };
+        let args = [
+            Reference(NULL),
+            Integer(1),
+            Reference(NULL),
+            Reference(block(&["/bin/sh"])),
+            Reference(block(&["-c", script])),
+            Integer(2),
+            env,
+            Integer(1),
+            Reference(block(&["/"])),
+            Reference(fds_array),
+            Boolean(false),
+        ];
+        let result = call(
+            thread,
+            PROCESS_IMPL,
+            "forkAndExec",
+            "(I[B[B[BI[BI[B[IZ)I",
+            &args,
+        );
+        let heap = vm.heap().read().unwrap();
+        let fds = array(&heap, fds_array).unwrap();
+        let fds = [0, 1, 2].map(|i| match fds.element(i).unwrap() {
+            Integer(fd) => fd,
+            other => panic!("{:?}", other),
+        });
+        (result, fds)
+    }
+
+    fn wait_for_process_exit(thread: &mut Thread, pid: i32) -> NativeResult {
+        call(
+            thread,
+            PROCESS_HANDLE_IMPL,
+            "waitForProcessExit0",
+            "(JZ)I",
+            &[Long(pid as i64), Boolean(true)],
+        )
+    }
+
+    #[test]
+    fn test_process_natives() {
+        let vm = test_util::vm(&[]);
+        let mut thread = Thread::new(vm.runtime().clone());
+        let (result, _) = fork_and_exec(&vm, &mut thread, "true", &[], [-1; 3]);
+        assert_eq!("java/io/IOException", thrown(result));
+
+        vm.runtime().processes().set_policy(ProcessPolicy::Allow);
+        let script = "cat; echo $GREETING $(pwd) >&2; exit 3";
+        let (result, fds) = fork_and_exec(&vm, &mut thread, script, &["GREETING=hi"], [-1; 3]);
+        let Some(Integer(pid)) = result.unwrap() else {
+            panic!()
+        };
+        let files = vm.runtime().files();
+        files.write(fds[0], b"hello").unwrap();
+        files.close(fds[0]).unwrap();
+        let read = |fd| read_to_string(|b| files.read(fd, b));
+        assert_eq!("hello", read(fds[1]).unwrap());
+        assert_eq!("hi /\n", read(fds[2]).unwrap());
+        assert_eq!(0, files.available(fds[1]).unwrap());
+        assert_eq!(
+            Some(Integer(3)),
+            wait_for_process_exit(&mut thread, pid).unwrap()
+        );
+        assert!(vm.runtime().processes().is_empty());
+        let is_alive = call(
+            &mut thread,
+            PROCESS_HANDLE_IMPL,
+            "isAlive0",
+            "(J)J",
+            &[Long(pid as i64)],
+        );
+        assert_eq!(Some(Long(-1)), is_alive.unwrap());
+    }
+
+    #[test]
+    fn test_process_natives_redirects() {
+        let vm = test_util::vm(&[]);
+        let mut thread = Thread::new(vm.runtime().clone());
+        vm.runtime().processes().set_policy(ProcessPolicy::Allow);
+        let runtime = vm.runtime();
+        let files = runtime.files();
+        let out = OpenMode::Write { append: false };
+        let fd = files.open(runtime.file_system(), "out", out).unwrap();
+
+        // the output ends up in the file, and the error output is inherited
+        let (result, fds) = fork_and_exec(&vm, &mut thread, "echo redirected", &[], [0, fd, 2]);
+        let Some(Integer(pid)) = result.unwrap() else {
+            panic!()
+        };
+        assert_eq!([-1; 3], fds);
+        // like the JDK, the stream of the file is closed right away
+        files.close(fd).unwrap();
+        assert_eq!(
+            Some(Integer(0)),
+            wait_for_process_exit(&mut thread, pid).unwrap()
+        );
+        // the output is forwarded by another thread
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents.clear();
+            let mut file = runtime.file_system().open("out").unwrap();
+            file.read_to_string(&mut contents).unwrap();
+            if !contents.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!("redirected\n", contents);
+    }
+
+    #[test]
+    fn test_process_natives_destroy() {
+        let vm = test_util::vm(&[]);
+        let mut thread = Thread::new(vm.runtime().clone());
+        vm.runtime().processes().set_policy(ProcessPolicy::Allow);
+        let (result, _) = fork_and_exec(&vm, &mut thread, "sleep 10", &[], [-1; 3]);
+        let Some(Integer(pid)) = result.unwrap() else {
+            panic!()
+        };
+        let is_alive = |thread: &mut Thread| {
+            call(
+                thread,
+                PROCESS_HANDLE_IMPL,
+                "isAlive0",
+                "(J)J",
+                &[Long(pid as i64)],
+            )
+            .unwrap()
+        };
+        assert_eq!(Some(Long(0)), is_alive(&mut thread));
+        let destroy = call(
+            &mut thread,
+            PROCESS_HANDLE_IMPL,
+            "destroy0",
+            "(JJZ)Z",
+            &[Long(pid as i64), Long(0), Boolean(true)],
+        );
+        assert_eq!(Some(Boolean(true)), destroy.unwrap());
+        // SIGKILL
+        assert_eq!(
+            Some(Integer(137)),
+            wait_for_process_exit(&mut thread, pid).unwrap()
+        );
+        assert_eq!(Some(Long(-1)), is_alive(&mut thread));
+        let current = call(
+            &mut thread,
+            PROCESS_HANDLE_IMPL,
+            "getCurrentPid0",
+            "()J",
+            &[],
+        );
+        assert_eq!(Some(Long(std::process::id() as i64)), current.unwrap());
+    }
+}
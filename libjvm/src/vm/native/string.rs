@@ -0,0 +1,233 @@
+use crate::vm::descriptor::FieldType;
+use crate::vm::error::{Exception, VmError};
+use crate::vm::heap::Heap;
+use crate::vm::native::{array, array_mut, get_field, new_instance, set_field, NativeResult};
+use crate::vm::runtime::Runtime;
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The binary name of `java.lang.String`.
+pub const STRING: &str = "java/lang/String";
+
+/// The encoding of the `value` of a `java.lang.String`, the `coder` field.
+/// Strings whose characters all fit into one byte are stored as Latin-1, the
+/// others as UTF-16 in the byte order of the platform, two bytes per `char`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(i8)]
+pub enum Coder {
+    /// `String.LATIN1`
+    Latin1 = 0,
+    /// `String.UTF16`
+    Utf16 = 1,
+}
+
+impl Coder {
+    /// The coder for the given UTF-16 characters, i.e. [`Coder::Latin1`] if
+    /// they can all be compressed, like the constructors of `String` decide.
+    pub fn of(utf16: &[u8]) -> Self {
+        if chars(utf16).all(|c| c <= 0xFF) {
+            Coder::Latin1
+        } else {
+            Coder::Utf16
+        }
+    }
+}
+
+/// Returns the characters of the `java.lang.String` with the given reference,
+/// e.g. a path that Java code passed to a native. Fails with a
+/// `NullPointerException` for `null`. Unpaired surrogates are replaced.
+pub fn read_string(heap: &Heap, string: usize) -> Result<String, VmError> {
+    let value = match get_field(heap, string, "value", "[B")? {
+        NativeValue::Reference(value) => array(heap, value)?.raw_elements(),
+        other => return Err(VmError::Internal(format!("string value {:?}", other))),
+    };
+    Ok(match get_field(heap, string, "coder", "B")? {
+        NativeValue::Byte(c) if c == Coder::Latin1 as i8 => {
+            value.iter().map(|b| *b as char).collect()
+        }
+        _ => String::from_utf16_lossy(&chars(value).collect::<Vec<_>>()),
+    })
+}
+
+/// Allocates a `java.lang.String` with the given characters, compressed to
+/// Latin-1 if possible, and returns the reference to it. `String` must be
+/// loaded.
+pub fn new_string(runtime: &Runtime, heap: &mut Heap, s: &str) -> Result<usize, VmError> {
+    let utf16: Vec<u8> = s.encode_utf16().flat_map(|c| c.to_ne_bytes()).collect();
+    let coder = Coder::of(&utf16);
+    let bytes = match coder {
+        Coder::Latin1 => chars(&utf16).map(|c| c as u8).collect(),
+        Coder::Utf16 => utf16,
+    };
+    let value = heap.allocate_array(Arc::new(FieldType::Byte), bytes.len() as i32)?;
+    array_mut(heap, value)?
+        .raw_elements_mut()
+        .copy_from_slice(&bytes);
+    let string = new_instance(runtime, heap, STRING)?;
+    set_field(heap, string, "value", "[B", NativeValue::Reference(value))?;
+    set_field(heap, string, "coder", "B", NativeValue::Byte(coder as i8))?;
+    Ok(string)
+}
+
+/// `private static native boolean isBigEndian()` of `StringUTF16`. UTF-16
+/// strings and `char` arrays use the byte order of the platform, so that they
+/// can be copied into each other without swapping bytes.
+pub fn string_utf16_is_big_endian(_thread: &mut Thread, _args: &[NativeValue]) -> NativeResult {
+    Ok(Some(NativeValue::Boolean(cfg!(target_endian = "big"))))
+}
+
+// The functions below are the intrinsics of the string code of `java.base`.
+// They work on the elements of `byte[]` and `char[]` arrays as they are stored
+// on the heap, see `Array::raw_elements`. Offsets and lengths are in elements,
+// i.e. in chars for UTF-16 data, and are checked like `System.arraycopy`
+// checks them.
+
+/// `StringCoding.hasNegatives(byte[] ba, int off, int len)`: whether any of the
+/// bytes is negative, i.e. not ASCII.
+pub fn has_negatives(bytes: &[u8], off: i32, len: i32) -> Result<bool, VmError> {
+    let range = checked_range(bytes.len(), off, len)?;
+    Ok(bytes[range].iter().any(|b| *b >= 0x80))
+}
+
+/// `StringUTF16.compress(byte[] src, int srcOff, byte[] dst, int dstOff, int
+/// len)` (and the overload for `char[]`): copies the UTF-16 characters to
+/// Latin-1 bytes. Returns the number of characters copied, which is 0 if any
+/// of them doesn't fit into a byte, in which case `dst` is left unspecified.
+pub fn compress(
+    src: &[u8],
+    src_off: i32,
+    dst: &mut [u8],
+    dst_off: i32,
+    len: i32,
+) -> Result<i32, VmError> {
+    let copied = encode_iso_array(src, src_off, dst, dst_off, len)?;
+    Ok(if copied == len { len } else { 0 })
+}
+
+/// `StringCoding.implEncodeISOArray(byte[] sa, int sp, byte[] da, int dp, int
+/// len)`: copies UTF-16 characters to Latin-1 bytes up to the first character
+/// that doesn't fit into a byte, and returns the number of characters copied.
+pub fn encode_iso_array(
+    src: &[u8],
+    src_off: i32,
+    dst: &mut [u8],
+    dst_off: i32,
+    len: i32,
+) -> Result<i32, VmError> {
+    let src_range = checked_range(src.len() / 2, src_off, len)?;
+    let dst_range = checked_range(dst.len(), dst_off, len)?;
+    let src = &src[src_range.start * 2..src_range.end * 2];
+    let dst = &mut dst[dst_range];
+    let mut copied = 0;
+    for (c, b) in chars(src).zip(dst.iter_mut()) {
+        if c > 0xFF {
+            break;
+        }
+        *b = c as u8;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// `StringLatin1.inflate(byte[] src, int srcOff, byte[] dst, int dstOff, int
+/// len)` (and the overload for `char[]`): copies Latin-1 bytes to UTF-16
+/// characters.
+pub fn inflate(
+    src: &[u8],
+    src_off: i32,
+    dst: &mut [u8],
+    dst_off: i32,
+    len: i32,
+) -> Result<(), VmError> {
+    let src = &src[checked_range(src.len(), src_off, len)?];
+    let dst_range = checked_range(dst.len() / 2, dst_off, len)?;
+    let dst = &mut dst[dst_range.start * 2..dst_range.end * 2];
+    for (b, c) in src.iter().zip(dst.chunks_exact_mut(2)) {
+        c.copy_from_slice(&(*b as u16).to_ne_bytes());
+    }
+    Ok(())
+}
+
+/// The UTF-16 characters in the given bytes, in the byte order of the
+/// platform.
+fn chars(utf16: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    utf16
+        .chunks_exact(2)
+        .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+}
+
+/// The elements `off..off + len` of an array with the given length, failing
+/// with an `ArrayIndexOutOfBoundsException` if they are out of bounds.
+pub(super) fn checked_range(length: usize, off: i32, len: i32) -> Result<Range<usize>, VmError> {
+    match (usize::try_from(off), usize::try_from(len)) {
+        (Ok(start), Ok(len)) if start + len <= length => Ok(start..start + len),
+        _ => Err(Exception::ArrayIndexOutOfBounds {
+            // the first index that is out of bounds
+            index: if off < 0 || len < 0 {
+                off
+            } else {
+                off.max(length as i32)
+            },
+            length: length as i32,
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|c| c.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_coder() {
+        assert_eq!(Coder::Latin1, Coder::of(&utf16("")));
+        assert_eq!(Coder::Latin1, Coder::of(&utf16("caf\u{e9}")));
+        assert_eq!(Coder::Utf16, Coder::of(&utf16("\u{20ac}5")));
+        assert_eq!(1, Coder::Utf16 as i8);
+    }
+
+    #[test]
+    fn test_has_negatives() {
+        let bytes = "ascii \u{e9}".as_bytes();
+        assert!(!has_negatives(bytes, 0, 6).unwrap());
+        assert!(has_negatives(bytes, 0, bytes.len() as i32).unwrap());
+        assert!(!has_negatives(bytes, 3, 0).unwrap());
+        assert_eq!(
+            Err(Exception::ArrayIndexOutOfBounds {
+                index: 8,
+                length: 8
+            }
+            .into()),
+            has_negatives(bytes, 4, 5)
+        );
+        assert!(has_negatives(bytes, -1, 1).is_err());
+    }
+
+    #[test]
+    fn test_compress_and_inflate() {
+        let src = utf16("-caf\u{e9}");
+        let mut latin1 = [0_u8; 6];
+        assert_eq!(Ok(4), compress(&src, 1, &mut latin1, 2, 4));
+        assert_eq!(b"\0\0caf\xe9", &latin1);
+
+        let mut inflated = vec![0_u8; 10];
+        inflate(&latin1, 2, &mut inflated, 1, 4).unwrap();
+        assert_eq!(&src[2..], &inflated[2..]);
+
+        // compressing is all or nothing, encoding stops at the first wide char
+        let euro = utf16("ab\u{20ac}c");
+        let mut dst = [0_u8; 4];
+        assert_eq!(Ok(0), compress(&euro, 0, &mut dst, 0, 4));
+        assert_eq!(Ok(2), encode_iso_array(&euro, 0, &mut dst, 0, 4));
+        assert_eq!(b"ab", &dst[..2]);
+
+        assert!(compress(&euro, 1, &mut dst, 0, 4).is_err());
+        assert!(inflate(&latin1, 0, &mut dst, 0, 3).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+use crate::vm::descriptor::FieldType;
+use crate::vm::native::string::new_string;
+use crate::vm::native::{array_mut, find_native, set_field, NativeResult};
+use crate::vm::thread::{Thread, Unwind};
+use crate::vm::types::NativeValue;
+use crate::vm::VM;
+use libvfs::FileSystem;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+/// Creates a VM whose file system is in memory, and loads the given classes
+/// into it, as the directory under `tests/resources/vm` that has the class file
+/// and the binary name. `Object` and the `String` and `FileDescriptor` of the
+/// `io` stubs are always loaded, since natives create strings, and streams and
+/// sockets have a file descriptor.
+pub fn vm(classes: &[(&str, &str)]) -> VM {
+    let os = FileSystem::new_os_fs();
+    let fs = FileSystem::new_in_memory_fs();
+    let defaults = [
+        ("classloader", "java/lang/Object"),
+        ("io", "java/lang/String"),
+        ("io", "java/io/FileDescriptor"),
+    ];
+    for (dir, class) in defaults.iter().chain(classes) {
+        let mut bytes = vec![];
+        let path = format!("tests/resources/vm/{}/{}.class", dir, class);
+        os.open(path).unwrap().read_to_end(&mut bytes).unwrap();
+        let mut file = fs.create(format!("classes/{}.class", class)).unwrap();
+        file.write_all(&bytes).unwrap();
+    }
+    let mut vm = VM::new(
+        fs,
+        ClassPath::from(vec![ClassPathEntry::Dir("classes".into())]),
+    );
+    for (_, class) in defaults.iter().chain(classes) {
+        vm.for_name(&class.replace('/', ".")).unwrap();
+    }
+    vm
+}
+
+/// Allocates an instance of the given class, and a `FileDescriptor` that is
+/// not valid yet for its `fd` field, like the constructors of streams and
+/// sockets do.
+pub fn new_with_fd(vm: &mut VM, class: &str) -> usize {
+    let class = vm.for_name(&class.replace('/', ".")).unwrap();
+    let descriptor = vm.for_name("java.io.FileDescriptor").unwrap();
+    let mut heap = vm.heap().write().unwrap();
+    let object = heap.allocate_instance(&class);
+    let fd = heap.allocate_instance(&descriptor);
+    set_field(&mut heap, fd, "fd", "I", NativeValue::Integer(-1)).unwrap();
+    let fd = NativeValue::Reference(fd);
+    set_field(&mut heap, object, "fd", "Ljava/io/FileDescriptor;", fd).unwrap();
+    object
+}
+
+pub fn new_java_string(vm: &VM, s: &str) -> usize {
+    let mut heap = vm.heap().write().unwrap();
+    new_string(vm.runtime(), &mut heap, s).unwrap()
+}
+
+pub fn new_bytes(vm: &VM, bytes: &[u8]) -> usize {
+    let mut heap = vm.heap().write().unwrap();
+    let array = heap
+        .allocate_array(Arc::new(FieldType::Byte), bytes.len() as i32)
+        .unwrap();
+    array_mut(&mut heap, array)
+        .unwrap()
+        .raw_elements_mut()
+        .copy_from_slice(bytes);
+    array
+}
+
+pub fn bytes_of(vm: &VM, array: usize) -> Vec<u8> {
+    let heap = vm.heap().read().unwrap();
+    super::array(&heap, array).unwrap().raw_elements().to_vec()
+}
+
+/// Invokes the native with the given class, name and descriptor.
+pub fn call(
+    thread: &mut Thread,
+    class: &str,
+    name: &str,
+    descriptor: &str,
+    args: &[NativeValue],
+) -> NativeResult {
+    find_native(class, name, descriptor).unwrap()(thread, args)
+}
+
+/// The binary name of the class of the exception that a native threw.
+pub fn thrown(result: NativeResult) -> String {
+    match result {
+        Err(Unwind::Throw(e)) => e.class_name().to_string(),
+        other => panic!("expected an exception, got {:?}", other),
+    }
+}
@@ -0,0 +1,566 @@
+use crate::vm::native::zip::{
+    adler32, Progress, DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA, WINDOW_SIZE,
+};
+
+/// The strategies of `Deflater`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Strategy {
+    #[default]
+    Default,
+    /// Meant for data with small values, compressed like the default here.
+    Filtered,
+    /// Only literals, no matches.
+    HuffmanOnly,
+}
+
+impl Strategy {
+    /// The strategy for the value of `Deflater.DEFAULT_STRATEGY`, `FILTERED`
+    /// or `HUFFMAN_ONLY`.
+    pub fn from_java(value: i32) -> Self {
+        match value {
+            1 => Strategy::Filtered,
+            2 => Strategy::HuffmanOnly,
+            _ => Strategy::Default,
+        }
+    }
+}
+
+/// How much of the input a call to [`Deflater::deflate`] has to compress.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Flush {
+    /// As much as the deflater decides, `Deflater.NO_FLUSH`.
+    None,
+    /// All input, so that the output can be inflated up to here,
+    /// `Deflater.SYNC_FLUSH`.
+    Sync,
+    /// Like [`Flush::Sync`], but later data doesn't refer back to earlier
+    /// data, `Deflater.FULL_FLUSH`.
+    Full,
+    /// All input, and the end of the compressed data after it, like after
+    /// `Deflater.finish`.
+    Finish,
+}
+
+impl Flush {
+    /// The flush mode for the `flush` argument of the `deflateBytes` natives:
+    /// `Deflater.NO_FLUSH`, `SYNC_FLUSH`, `FULL_FLUSH` or `FINISH`.
+    pub fn from_java(value: i32) -> Self {
+        match value {
+            2 => Flush::Sync,
+            3 => Flush::Full,
+            4 => Flush::Finish,
+            _ => Flush::None,
+        }
+    }
+}
+
+/// The largest amount of input that is compressed into one block, unless
+/// flushing.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// The longest match that the format can express.
+const MAX_MATCH: usize = 258;
+const MIN_MATCH: usize = 3;
+
+const HASH_BITS: u32 = 15;
+
+/// A compressor to the deflate format ([RFC 1951]), optionally wrapped in the
+/// zlib format ([RFC 1950]), like `java.util.zip.Deflater`.
+///
+/// Matches are found with hash chains, whose length depends on the level, and
+/// blocks are encoded with the fixed Huffman codes. Level 0 stores the data
+/// uncompressed.
+///
+/// [RFC 1950]: https://www.rfc-editor.org/rfc/rfc1950
+/// [RFC 1951]: https://www.rfc-editor.org/rfc/rfc1951
+pub struct Deflater {
+    level: i32,
+    strategy: Strategy,
+    /// Whether the data gets a zlib header and trailer.
+    wrapped: bool,
+    header_written: bool,
+    /// The checksum of the preset dictionary, which is written to the header.
+    dictionary_id: Option<u32>,
+    /// The input that is not compressed yet.
+    input: Vec<u8>,
+    /// The last 32K of the compressed input, which matches can refer to.
+    history: Vec<u8>,
+    bits: BitWriter,
+    /// The number of output bytes that were already returned.
+    drained: usize,
+    /// Whether nothing was added since the last flush.
+    flushed: bool,
+    /// Whether the end of the compressed data was written.
+    finished: bool,
+    adler: u32,
+}
+
+impl Deflater {
+    /// Creates a deflater with the given level (-1 for the default level 6),
+    /// like the constructors of `Deflater`.
+    pub fn new(level: i32, strategy: Strategy, nowrap: bool) -> Self {
+        Self {
+            level: Self::effective_level(level),
+            strategy,
+            wrapped: !nowrap,
+            header_written: false,
+            dictionary_id: None,
+            input: vec![],
+            history: vec![],
+            bits: BitWriter::default(),
+            drained: 0,
+            flushed: true,
+            finished: false,
+            adler: 1,
+        }
+    }
+
+    fn effective_level(level: i32) -> i32 {
+        if level < 0 {
+            6
+        } else {
+            level.min(9)
+        }
+    }
+
+    /// The Adler-32 checksum of the input so far.
+    pub fn adler(&self) -> u32 {
+        self.adler
+    }
+
+    /// Whether the end of the compressed data was written and returned.
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.drained == self.bits.out.len()
+    }
+
+    /// Starts over with the same level and strategy.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.level, self.strategy, !self.wrapped);
+    }
+
+    /// Changes the level and strategy. The input so far is compressed with the
+    /// previous ones.
+    pub fn set_params(&mut self, level: i32, strategy: Strategy) {
+        if !self.finished && !self.input.is_empty() {
+            self.write_header();
+            let input = std::mem::take(&mut self.input);
+            self.compress(&input, false);
+        }
+        self.level = Self::effective_level(level);
+        self.strategy = strategy;
+    }
+
+    /// Sets the dictionary that the data can refer back into. It has to be
+    /// set before any data is compressed.
+    pub fn set_dictionary(&mut self, dictionary: &[u8]) {
+        let start = dictionary.len().saturating_sub(WINDOW_SIZE);
+        self.history = dictionary[start..].to_vec();
+        self.dictionary_id = Some(adler32(1, dictionary));
+        if self.wrapped {
+            self.adler = adler32(1, dictionary);
+        }
+    }
+
+    /// Consumes all of the input, and returns as much compressed data as fits
+    /// into the output. Compressed data that doesn't fit is returned by the
+    /// next calls, which may pass no input for that.
+    pub fn deflate(&mut self, input: &[u8], output: &mut [u8], flush: Flush) -> Progress {
+        let mut read = 0;
+        if !self.finished {
+            self.write_header();
+            if !input.is_empty() {
+                self.input.extend_from_slice(input);
+                if self.wrapped {
+                    self.adler = adler32(self.adler, input);
+                }
+                self.flushed = false;
+                read = input.len();
+            }
+
+            while self.input.len() >= BLOCK_SIZE {
+                let rest = self.input.split_off(BLOCK_SIZE);
+                let block = std::mem::replace(&mut self.input, rest);
+                self.compress(&block, false);
+            }
+            match flush {
+                Flush::None => {}
+                Flush::Sync | Flush::Full if !self.flushed => {
+                    let input = std::mem::take(&mut self.input);
+                    if !input.is_empty() {
+                        self.compress(&input, false);
+                    }
+                    // an empty stored block, which ends at a byte boundary
+                    self.bits.write_bits(0, 3);
+                    self.bits.align();
+                    self.bits.out.extend_from_slice(&[0x00, 0x00, 0xFF, 0xFF]);
+                    if flush == Flush::Full {
+                        self.history.clear();
+                    }
+                    self.flushed = true;
+                }
+                Flush::Sync | Flush::Full => {}
+                Flush::Finish => {
+                    let input = std::mem::take(&mut self.input);
+                    self.compress(&input, true);
+                    self.bits.align();
+                    if self.wrapped {
+                        let adler = self.adler.to_be_bytes();
+                        self.bits.out.extend_from_slice(&adler);
+                    }
+                    self.finished = true;
+                }
+            }
+        }
+
+        let count = (self.bits.out.len() - self.drained).min(output.len());
+        output[..count].copy_from_slice(&self.bits.out[self.drained..self.drained + count]);
+        self.drained += count;
+        if self.drained == self.bits.out.len() && !self.finished {
+            self.bits.out.clear();
+            self.drained = 0;
+        }
+        Progress {
+            read,
+            written: count,
+            finished: self.is_finished(),
+            needs_dictionary: false,
+        }
+    }
+
+    /// Writes the zlib header, if it wasn't written yet.
+    fn write_header(&mut self) {
+        if !self.wrapped || self.header_written {
+            return;
+        }
+        self.header_written = true;
+        // a window of 32K, and the level in the same categories as zlib
+        let cmf = 0x78_u16;
+        let level = match self.level {
+            0 | 1 => 0,
+            2..=5 => 1,
+            6 => 2,
+            _ => 3,
+        };
+        let mut flg = level << 6 | u16::from(self.dictionary_id.is_some()) << 5;
+        flg += 31 - (cmf << 8 | flg) % 31;
+        self.bits.out.extend_from_slice(&[cmf as u8, flg as u8]);
+        if let Some(id) = self.dictionary_id {
+            self.bits.out.extend_from_slice(&id.to_be_bytes());
+            self.adler = 1;
+        }
+    }
+
+    /// Compresses the data into blocks, the last of which is marked as the
+    /// final block if `last`.
+    fn compress(&mut self, data: &[u8], last: bool) {
+        if self.level == 0 {
+            self.store(data, last);
+        } else {
+            self.compress_fixed(data, last);
+        }
+        let mut history = std::mem::take(&mut self.history);
+        history.extend_from_slice(data);
+        let start = history.len().saturating_sub(WINDOW_SIZE);
+        history.drain(..start);
+        self.history = history;
+    }
+
+    fn store(&mut self, data: &[u8], last: bool) {
+        let mut chunks = data.chunks(u16::MAX as usize).peekable();
+        if chunks.peek().is_none() {
+            self.store_block(&[], last);
+        }
+        while let Some(chunk) = chunks.next() {
+            self.store_block(chunk, last && chunks.peek().is_none());
+        }
+    }
+
+    fn store_block(&mut self, chunk: &[u8], last: bool) {
+        self.bits.write_bits(u32::from(last), 3);
+        self.bits.align();
+        let len = chunk.len() as u16;
+        self.bits.out.extend_from_slice(&len.to_le_bytes());
+        self.bits.out.extend_from_slice(&(!len).to_le_bytes());
+        self.bits.out.extend_from_slice(chunk);
+    }
+
+    fn compress_fixed(&mut self, data: &[u8], last: bool) {
+        let max_chain = match self.level {
+            1..=3 => 4,
+            4..=6 => 32,
+            _ => 256,
+        };
+        let buf = [&self.history[..], data].concat();
+        let start = self.history.len();
+        let mut chains = HashChains::new(buf.len());
+        for i in 0..start {
+            chains.insert(&buf, i);
+        }
+
+        self.bits.write_bits(u32::from(last) | 1 << 1, 3);
+        let mut i = start;
+        while i < buf.len() {
+            let (length, distance) = if self.strategy == Strategy::HuffmanOnly {
+                (0, 0)
+            } else {
+                chains.longest_match(&buf, i, max_chain)
+            };
+            if length >= MIN_MATCH {
+                self.write_match(length, distance);
+                for j in i..i + length {
+                    chains.insert(&buf, j);
+                }
+                i += length;
+            } else {
+                self.write_symbol(buf[i] as u16);
+                chains.insert(&buf, i);
+                i += 1;
+            }
+        }
+        // the end of the block
+        self.write_symbol(256);
+    }
+
+    /// Writes the fixed code of a literal or length symbol.
+    fn write_symbol(&mut self, symbol: u16) {
+        let (code, length) = match symbol {
+            0..=143 => (0x30 + symbol, 8),
+            144..=255 => (0x190 + symbol - 144, 9),
+            256..=279 => (symbol - 256, 7),
+            _ => (0xC0 + symbol - 280, 8),
+        };
+        self.bits.write_code(code as u32, length);
+    }
+
+    fn write_match(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE.partition_point(|base| *base as usize <= length) - 1;
+        self.write_symbol(257 + index as u16);
+        let extra = (length - LENGTH_BASE[index] as usize) as u32;
+        self.bits.write_bits(extra, LENGTH_EXTRA[index] as u32);
+
+        let index = DISTANCE_BASE.partition_point(|base| *base as usize <= distance) - 1;
+        self.bits.write_code(index as u32, 5);
+        let extra = (distance - DISTANCE_BASE[index] as usize) as u32;
+        self.bits.write_bits(extra, DISTANCE_EXTRA[index] as u32);
+    }
+}
+
+/// The positions in the data by the hash of the three bytes at them, latest
+/// first.
+struct HashChains {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+const NONE: usize = usize::MAX;
+
+impl HashChains {
+    fn new(len: usize) -> Self {
+        Self {
+            head: vec![NONE; 1 << HASH_BITS],
+            prev: vec![NONE; len],
+        }
+    }
+
+    fn hash(buf: &[u8], i: usize) -> Option<usize> {
+        let bytes = buf.get(i..i + MIN_MATCH)?;
+        let hash = (bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize;
+        Some(hash & ((1 << HASH_BITS) - 1))
+    }
+
+    fn insert(&mut self, buf: &[u8], i: usize) {
+        if let Some(hash) = Self::hash(buf, i) {
+            self.prev[i] = self.head[hash];
+            self.head[hash] = i;
+        }
+    }
+
+    /// The length and distance of the longest match for the data at `i` among
+    /// the first `max_chain` candidates.
+    fn longest_match(&self, buf: &[u8], i: usize, max_chain: usize) -> (usize, usize) {
+        let Some(hash) = Self::hash(buf, i) else {
+            return (0, 0);
+        };
+        let max = (buf.len() - i).min(MAX_MATCH);
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = self.head[hash];
+        let mut chain = max_chain;
+        while candidate != NONE && i - candidate <= WINDOW_SIZE && chain > 0 {
+            let length = buf[candidate..]
+                .iter()
+                .zip(&buf[i..i + max])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best_length {
+                best_length = length;
+                best_distance = i - candidate;
+                if length == max {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+            chain -= 1;
+        }
+        (best_length, best_distance)
+    }
+}
+
+/// Writes bits least significant bit first, like the format reads them.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is stored most significant bit first.
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// Pads the current byte with zeros.
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::native::zip::Inflater;
+
+    fn deflate_all(deflater: &mut Deflater, data: &[u8], flush: Flush) -> Vec<u8> {
+        let mut result = vec![];
+        let mut output = [0; 100];
+        let mut progress = deflater.deflate(data, &mut output, flush);
+        loop {
+            result.extend_from_slice(&output[..progress.written]);
+            if progress.written < output.len() {
+                return result;
+            }
+            progress = deflater.deflate(&[], &mut output, flush);
+        }
+    }
+
+    fn inflate(data: &[u8], nowrap: bool) -> Vec<u8> {
+        let mut output = vec![0; 1 << 20];
+        let progress = Inflater::new(nowrap).inflate(data, &mut output).unwrap();
+        assert!(progress.finished);
+        assert_eq!(data.len(), progress.read);
+        output.truncate(progress.written);
+        output
+    }
+
+    fn sample() -> Vec<u8> {
+        (0..20_000)
+            .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 28) as u8 + b'a')
+            .chain(b"abcabcabc".repeat(5_000))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = sample();
+        for (level, strategy) in [
+            (0, Strategy::Default),
+            (1, Strategy::Default),
+            (-1, Strategy::Default),
+            (9, Strategy::Filtered),
+            (6, Strategy::HuffmanOnly),
+        ] {
+            for nowrap in [false, true] {
+                let mut deflater = Deflater::new(level, strategy, nowrap);
+                let compressed = deflate_all(&mut deflater, &data, Flush::Finish);
+                assert!(deflater.is_finished());
+                assert_eq!(data, inflate(&compressed, nowrap));
+                if level != 0 && strategy != Strategy::HuffmanOnly {
+                    assert!(compressed.len() < data.len() / 2);
+                }
+                if !nowrap {
+                    assert_eq!(adler32(1, &data), deflater.adler());
+                }
+            }
+        }
+
+        let mut deflater = Deflater::new(-1, Strategy::Default, false);
+        assert_eq!(
+            vec![0x78, 0x9C, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+            deflate_all(&mut deflater, &[], Flush::Finish)
+        );
+        // nothing more after the end
+        assert_eq!(
+            Progress {
+                finished: true,
+                ..Default::default()
+            },
+            deflater.deflate(b"more", &mut [0; 8], Flush::Finish)
+        );
+    }
+
+    #[test]
+    fn test_flush() {
+        let mut deflater = Deflater::new(-1, Strategy::Default, true);
+        let mut compressed = deflate_all(&mut deflater, b"hello hello", Flush::None);
+        compressed.extend(deflate_all(&mut deflater, &[], Flush::Sync));
+        // everything so far can be inflated, and ends with the sync marker
+        assert!(compressed.ends_with(&[0x00, 0x00, 0xFF, 0xFF]));
+        let mut inflater = Inflater::new(true);
+        let mut output = [0; 64];
+        let progress = inflater.inflate(&compressed, &mut output).unwrap();
+        assert_eq!(b"hello hello", &output[..progress.written]);
+        // flushing again without input adds nothing
+        assert!(deflate_all(&mut deflater, &[], Flush::Sync).is_empty());
+
+        compressed.extend(deflate_all(&mut deflater, b" world, hello", Flush::Full));
+        compressed.extend(deflate_all(&mut deflater, b" again", Flush::Finish));
+        assert_eq!(
+            b"hello hello world, hello again".to_vec(),
+            inflate(&compressed, true)
+        );
+    }
+
+    #[test]
+    fn test_dictionary_and_params() {
+        let dictionary = b"the quick brown fox";
+        let mut deflater = Deflater::new(9, Strategy::Default, false);
+        deflater.set_dictionary(dictionary);
+        let mut compressed = deflate_all(&mut deflater, b"the quick brown fox jumps", Flush::None);
+        deflater.set_params(0, Strategy::Default);
+        compressed.extend(deflate_all(
+            &mut deflater,
+            b" over the lazy dog",
+            Flush::Finish,
+        ));
+        // the header asks for the dictionary
+        assert_eq!(0x20, compressed[1] & 0x20);
+
+        let mut inflater = Inflater::new(false);
+        let mut output = [0; 64];
+        let progress = inflater.inflate(&compressed, &mut output).unwrap();
+        assert!(progress.needs_dictionary);
+        inflater.set_dictionary(dictionary).unwrap();
+        let progress = inflater
+            .inflate(&compressed[progress.read..], &mut output)
+            .unwrap();
+        assert!(progress.finished);
+        assert_eq!(
+            b"the quick brown fox jumps over the lazy dog",
+            &output[..progress.written]
+        );
+    }
+}
@@ -0,0 +1,629 @@
+use crate::vm::native::zip::{
+    adler32, Progress, CODE_LENGTH_ORDER, DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA,
+    WINDOW_SIZE,
+};
+use std::borrow::Cow;
+
+/// A decompressor for the deflate format ([RFC 1951]), optionally wrapped in
+/// the zlib format ([RFC 1950]), like `java.util.zip.Inflater`.
+///
+/// Input and output can be passed in pieces of any size. The inflater only
+/// consumes the input that it needs, so that the bytes after the end of the
+/// compressed data, e.g. the trailer of a gzip file, are left to the caller.
+///
+/// [RFC 1950]: https://www.rfc-editor.org/rfc/rfc1950
+/// [RFC 1951]: https://www.rfc-editor.org/rfc/rfc1951
+pub struct Inflater {
+    /// Whether the data has a zlib header and trailer.
+    wrapped: bool,
+    state: State,
+    last_block: bool,
+    /// The input that was consumed but not decoded yet, because it ended in
+    /// the middle of a header or code. The first `bit_offset` bits of it are
+    /// decoded already.
+    pending: Vec<u8>,
+    bit_offset: usize,
+    /// The last 32K of the output, which matches refer to.
+    window: Vec<u8>,
+    /// The number of bytes written to the window in total.
+    window_written: usize,
+    /// The checksum of the output for the zlib trailer, or the identifier of
+    /// the dictionary while it is needed.
+    adler: u32,
+}
+
+enum State {
+    Header,
+    /// The data was compressed with a dictionary with the given checksum,
+    /// which has to be set before inflating can continue.
+    Dictionary(u32),
+    BlockHeader,
+    Stored(usize),
+    Codes {
+        literals: Huffman,
+        distances: Huffman,
+        /// The length and distance of a match that didn't fit into the output.
+        copy: Option<(usize, usize)>,
+    },
+    Trailer,
+    Done,
+}
+
+/// The result of decoding one header or code.
+enum Step {
+    Continue,
+    NeedsInput,
+    OutputFull,
+    NeedsDictionary,
+    Done,
+}
+
+/// The data is not in the deflate format. The message is the one of zlib.
+pub type InflateError = &'static str;
+
+impl Inflater {
+    /// Creates an inflater for zlib data, or for raw deflate data if `nowrap`,
+    /// like the constructors of `Inflater`.
+    pub fn new(nowrap: bool) -> Self {
+        Self {
+            wrapped: !nowrap,
+            state: if nowrap {
+                State::BlockHeader
+            } else {
+                State::Header
+            },
+            last_block: false,
+            pending: vec![],
+            bit_offset: 0,
+            window: vec![0; WINDOW_SIZE],
+            window_written: 0,
+            adler: 1,
+        }
+    }
+
+    /// The Adler-32 checksum of the output so far, or the identifier of the
+    /// dictionary if one is needed.
+    pub fn adler(&self) -> u32 {
+        self.adler
+    }
+
+    pub fn needs_dictionary(&self) -> bool {
+        matches!(self.state, State::Dictionary(_))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// Starts over, with the input that is pending and the window discarded.
+    pub fn reset(&mut self) {
+        *self = Self::new(!self.wrapped);
+    }
+
+    /// Sets the dictionary that the data was compressed with. For zlib data,
+    /// this is only possible when it is needed, and it has to be the one with
+    /// the checksum that the data asks for.
+    pub fn set_dictionary(&mut self, dictionary: &[u8]) -> Result<(), InflateError> {
+        match self.state {
+            State::Dictionary(id) if id == adler32(1, dictionary) => {
+                self.state = State::BlockHeader;
+                self.adler = 1;
+            }
+            State::BlockHeader if !self.wrapped && self.window_written == 0 => {}
+            _ => return Err("invalid dictionary"),
+        }
+        let start = dictionary.len().saturating_sub(WINDOW_SIZE);
+        for b in &dictionary[start..] {
+            self.push_window(*b);
+        }
+        Ok(())
+    }
+
+    /// Decompresses as much of the input into the output as possible.
+    pub fn inflate(&mut self, input: &[u8], output: &mut [u8]) -> Result<Progress, InflateError> {
+        let data: Cow<[u8]> = if self.pending.is_empty() {
+            Cow::Borrowed(input)
+        } else {
+            Cow::Owned([&self.pending, input].concat())
+        };
+        let mut bits = Bits {
+            data: &data,
+            pos: self.bit_offset,
+        };
+        let mut written = 0;
+        let step = loop {
+            let checkpoint = bits.pos;
+            match self.step(&mut bits, output, &mut written)? {
+                Step::Continue => {}
+                Step::NeedsInput => {
+                    bits.pos = checkpoint;
+                    break Step::NeedsInput;
+                }
+                step => break step,
+            }
+        };
+        if self.wrapped && !matches!(self.state, State::Dictionary(_)) {
+            self.adler = adler32(self.adler, &output[..written]);
+        }
+
+        let pending = self.pending.len();
+        let (byte, bit) = (bits.pos / 8, bits.pos % 8);
+        let read = match step {
+            Step::NeedsInput => {
+                // keep everything up to the end, all of it is needed
+                self.pending = data[byte..].to_vec();
+                self.bit_offset = bit;
+                input.len()
+            }
+            Step::Done => {
+                // the rest of the last byte is padding
+                self.pending.clear();
+                self.bit_offset = 0;
+                bits.pos.div_ceil(8).saturating_sub(pending)
+            }
+            _ if byte < pending => {
+                // nothing was decoded, the pending input is still needed
+                self.pending.drain(..byte);
+                self.bit_offset = bit;
+                0
+            }
+            _ => {
+                // keep the partially decoded byte
+                let read = byte - pending + usize::from(bit > 0);
+                self.pending = data[byte..byte + usize::from(bit > 0)].to_vec();
+                self.bit_offset = bit;
+                read
+            }
+        };
+        Ok(Progress {
+            read,
+            written,
+            finished: self.is_finished(),
+            needs_dictionary: self.needs_dictionary(),
+        })
+    }
+
+    /// Decodes one header or code, without changing the state if the input
+    /// ends before it.
+    fn step(
+        &mut self,
+        bits: &mut Bits,
+        output: &mut [u8],
+        written: &mut usize,
+    ) -> Result<Step, InflateError> {
+        macro_rules! need {
+            ($e:expr) => {
+                match $e {
+                    Some(value) => value,
+                    None => return Ok(Step::NeedsInput),
+                }
+            };
+        }
+
+        match &mut self.state {
+            State::Header => {
+                let cmf = need!(bits.bits(8));
+                let flg = need!(bits.bits(8));
+                if cmf & 0x0F != 8 {
+                    return Err("unknown compression method");
+                }
+                if (cmf >> 4) + 8 > 15 {
+                    return Err("invalid window size");
+                }
+                if (cmf << 8 | flg) % 31 != 0 {
+                    return Err("incorrect header check");
+                }
+                self.state = if flg & 0x20 != 0 {
+                    let id = need!(bits.bits(32));
+                    State::Dictionary(id.swap_bytes())
+                } else {
+                    State::BlockHeader
+                };
+            }
+            State::Dictionary(id) => {
+                self.adler = *id;
+                return Ok(Step::NeedsDictionary);
+            }
+            State::BlockHeader => {
+                if self.last_block {
+                    self.state = if self.wrapped {
+                        State::Trailer
+                    } else {
+                        State::Done
+                    };
+                    return Ok(Step::Continue);
+                }
+                let last = need!(bits.bits(1)) == 1;
+                self.state = match need!(bits.bits(2)) {
+                    0 => {
+                        bits.align();
+                        let len = need!(bits.bits(16));
+                        let nlen = need!(bits.bits(16));
+                        if len != !nlen & 0xFFFF {
+                            return Err("invalid stored block lengths");
+                        }
+                        State::Stored(len as usize)
+                    }
+                    1 => State::Codes {
+                        literals: Huffman::fixed_literals(),
+                        distances: Huffman::fixed_distances(),
+                        copy: None,
+                    },
+                    2 => {
+                        let (literals, distances) = need!(read_dynamic_tables(bits)?);
+                        State::Codes {
+                            literals,
+                            distances,
+                            copy: None,
+                        }
+                    }
+                    _ => return Err("invalid block type"),
+                };
+                self.last_block = last;
+            }
+            State::Stored(remaining) => {
+                if *remaining == 0 {
+                    self.state = State::BlockHeader;
+                    return Ok(Step::Continue);
+                }
+                let available = bits.data.len() - bits.pos / 8;
+                let count = (*remaining).min(available).min(output.len() - *written);
+                if count == 0 {
+                    return Ok(if available == 0 {
+                        Step::NeedsInput
+                    } else {
+                        Step::OutputFull
+                    });
+                }
+                *remaining -= count;
+                let start = bits.pos / 8;
+                bits.pos += count * 8;
+                for b in &bits.data[start..start + count] {
+                    output[*written] = *b;
+                    *written += 1;
+                    self.push_window(*b);
+                }
+            }
+            State::Codes {
+                literals,
+                distances,
+                copy,
+            } => {
+                if let Some((length, distance)) = *copy {
+                    let count = length.min(output.len() - *written);
+                    if count == 0 {
+                        return Ok(Step::OutputFull);
+                    }
+                    for _ in 0..count {
+                        let b = self.window[(self.window_written - distance) % WINDOW_SIZE];
+                        output[*written] = b;
+                        *written += 1;
+                        self.push_window(b);
+                    }
+                    // the state may not be borrowed while writing to the window
+                    if let State::Codes { copy, .. } = &mut self.state {
+                        *copy = (count < length).then_some((length - count, distance));
+                    }
+                    return Ok(Step::Continue);
+                }
+                if *written == output.len() {
+                    return Ok(Step::OutputFull);
+                }
+                let symbol = need!(literals.decode(bits)?) as usize;
+                match symbol {
+                    0..=255 => {
+                        output[*written] = symbol as u8;
+                        *written += 1;
+                        self.push_window(symbol as u8);
+                    }
+                    256 => self.state = State::BlockHeader,
+                    257..=285 => {
+                        let index = symbol - 257;
+                        let length = LENGTH_BASE[index] as usize
+                            + need!(bits.bits(LENGTH_EXTRA[index])) as usize;
+                        let index = need!(distances.decode(bits)?) as usize;
+                        if index >= DISTANCE_BASE.len() {
+                            return Err("invalid distance code");
+                        }
+                        let distance = DISTANCE_BASE[index] as usize
+                            + need!(bits.bits(DISTANCE_EXTRA[index])) as usize;
+                        if distance > self.window_written.min(WINDOW_SIZE) {
+                            return Err("invalid distance too far back");
+                        }
+                        *copy = Some((length, distance));
+                    }
+                    _ => return Err("invalid literal/length code"),
+                }
+            }
+            State::Trailer => {
+                bits.align();
+                let adler = need!(bits.bits(32)).swap_bytes();
+                if adler != self.adler32_so_far(output, *written) {
+                    return Err("incorrect data check");
+                }
+                self.state = State::Done;
+            }
+            State::Done => return Ok(Step::Done),
+        }
+        Ok(Step::Continue)
+    }
+
+    /// The checksum of the output including what was written in this call,
+    /// which is only added to `adler` at the end of the call.
+    fn adler32_so_far(&self, output: &[u8], written: usize) -> u32 {
+        adler32(self.adler, &output[..written])
+    }
+
+    fn push_window(&mut self, b: u8) {
+        self.window[self.window_written % WINDOW_SIZE] = b;
+        self.window_written += 1;
+    }
+}
+
+/// Reads the code lengths of a dynamic block and builds its codes.
+fn read_dynamic_tables(bits: &mut Bits) -> Result<Option<(Huffman, Huffman)>, InflateError> {
+    macro_rules! need {
+        ($e:expr) => {
+            match $e {
+                Some(value) => value,
+                None => return Ok(None),
+            }
+        };
+    }
+
+    let literal_count = need!(bits.bits(5)) as usize + 257;
+    let distance_count = need!(bits.bits(5)) as usize + 1;
+    let code_length_count = need!(bits.bits(4)) as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err("too many length or distance symbols");
+    }
+    let mut code_lengths = [0; 19];
+    for index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[*index] = need!(bits.bits(3)) as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths).ok_or("invalid code lengths set")?;
+
+    let mut lengths = vec![0; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = need!(code_length_code.decode(bits)?);
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if i == 0 {
+                    return Err("invalid bit length repeat");
+                }
+                (lengths[i - 1], 3 + need!(bits.bits(2)) as usize)
+            }
+            17 => (0, 3 + need!(bits.bits(3)) as usize),
+            _ => (0, 11 + need!(bits.bits(7)) as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err("invalid bit length repeat");
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err("invalid code -- missing end-of-block");
+    }
+    let literals = Huffman::new(&lengths[..literal_count]).ok_or("invalid literal/lengths set")?;
+    let distances = Huffman::new(&lengths[literal_count..]).ok_or("invalid distances set")?;
+    Ok(Some((literals, distances)))
+}
+
+/// The input of a decoding step, read least significant bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    /// The position in bits.
+    pos: usize,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u8) -> Option<u32> {
+        let end = self.pos + count as usize;
+        if end > self.data.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for i in 0..count as usize {
+            let pos = self.pos + i;
+            let bit = (self.data[pos / 8] >> (pos % 8)) & 1;
+            value |= (bit as u32) << i;
+        }
+        self.pos = end;
+        Some(value)
+    }
+
+    /// Skips the rest of the current byte.
+    fn align(&mut self) {
+        self.pos = self.pos.next_multiple_of(8);
+    }
+}
+
+/// A canonical Huffman code, decoded bit by bit like zlib's `puff`.
+struct Huffman {
+    /// The number of codes of each length.
+    counts: [u16; 16],
+    /// The symbols, ordered by their codes.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code for the given code lengths of the symbols, or [`None`]
+    /// if there are more codes of some length than possible. Incomplete codes
+    /// are allowed, decoding a missing code fails.
+    fn new(lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0_u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        let mut left = 1_i32;
+        for count in &counts[1..] {
+            left = left * 2 - *count as i32;
+            if left < 0 {
+                return None;
+            }
+        }
+        let mut offsets = [0_u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        counts[0] = 0;
+        Some(Self { counts, symbols })
+    }
+
+    fn fixed_literals() -> Self {
+        let mut lengths = [8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        Self::new(&lengths).unwrap()
+    }
+
+    fn fixed_distances() -> Self {
+        Self::new(&[5; 30]).unwrap()
+    }
+
+    /// Decodes a symbol, [`None`] if the input ends before it.
+    fn decode(&self, bits: &mut Bits) -> Result<Option<u16>, InflateError> {
+        let (mut code, mut first, mut index) = (0_i32, 0_i32, 0_i32);
+        for count in &self.counts[1..] {
+            let Some(bit) = bits.bits(1) else {
+                return Ok(None);
+            };
+            code |= bit as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(Some(self.symbols[(index + code - first) as usize]));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid code")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `zlib.compress(b"hello hello hello hello")`
+    const FIXED: &[u8] = &[
+        0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x27, 0x01, 0x68, 0x03, 0x08,
+        0xB1,
+    ];
+
+    fn inflate_all(inflater: &mut Inflater, data: &[u8], chunk: usize) -> Vec<u8> {
+        let mut result = vec![];
+        let mut input = data;
+        let mut output = vec![0; chunk];
+        while !inflater.is_finished() {
+            let end = input.len().min(chunk);
+            let progress = inflater.inflate(&input[..end], &mut output).unwrap();
+            result.extend_from_slice(&output[..progress.written]);
+            input = &input[progress.read..];
+            assert!(progress.read > 0 || progress.written > 0 || progress.finished);
+        }
+        result
+    }
+
+    #[test]
+    fn test_inflate_fixed() {
+        for chunk in [1, 2, 3, 100] {
+            let mut inflater = Inflater::new(false);
+            assert_eq!(
+                b"hello hello hello hello".to_vec(),
+                inflate_all(&mut inflater, FIXED, chunk)
+            );
+            assert_eq!(0x680308B1, inflater.adler());
+        }
+    }
+
+    #[test]
+    fn test_inflate_stored_and_dynamic() {
+        // zlib.compress(bytes(range(20)), 0)
+        let stored = [
+            0x78, 0x01, 0x01, 0x14, 0x00, 0xEB, 0xFF, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0x12, 0x13, 0x05,
+            0x46, 0x00, 0xBF,
+        ];
+        assert_eq!(
+            (0..20).collect::<Vec<u8>>(),
+            inflate_all(&mut Inflater::new(false), &stored, 7)
+        );
+
+        // zlib.compress(b"".join(b"%d," % (i * i % 1000) for i in range(200)))
+        let dynamic = include_bytes!("../../../../tests/resources/vm/zip/squares.zlib");
+        let expected = (0..200)
+            .map(|i| format!("{},", i * i % 1000))
+            .collect::<String>();
+        for chunk in [1, 5, 4096] {
+            assert_eq!(
+                expected.as_bytes(),
+                inflate_all(&mut Inflater::new(false), dynamic, chunk)
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailing_bytes_are_not_consumed() {
+        // the raw deflate data of FIXED, followed by a trailer of something else
+        let mut data = FIXED[2..FIXED.len() - 4].to_vec();
+        data.extend_from_slice(b"trailer");
+        let mut inflater = Inflater::new(true);
+        let mut output = [0; 64];
+        let progress = inflater.inflate(&data, &mut output).unwrap();
+        assert!(progress.finished);
+        assert_eq!(b"trailer", &data[progress.read..]);
+        assert_eq!(b"hello hello hello hello", &output[..progress.written]);
+    }
+
+    #[test]
+    fn test_dictionary() {
+        // c = zlib.compressobj(zdict=b"hello"); c.compress(b"hello, hello") + c.flush()
+        let data = [
+            0x78, 0xBB, 0x06, 0x2C, 0x02, 0x15, 0xCB, 0x00, 0x11, 0x3A, 0x0A, 0x60, 0x0A, 0x00,
+            0x1C, 0xDA, 0x04, 0x75,
+        ];
+        let mut inflater = Inflater::new(false);
+        let mut output = [0; 64];
+        let progress = inflater.inflate(&data, &mut output).unwrap();
+        assert!(progress.needs_dictionary);
+        assert_eq!(6, progress.read);
+        assert_eq!(adler32(1, b"hello"), inflater.adler());
+        assert_eq!(Err("invalid dictionary"), inflater.set_dictionary(b"world"));
+
+        inflater.set_dictionary(b"hello").unwrap();
+        let progress = inflater.inflate(&data[6..], &mut output).unwrap();
+        assert!(progress.finished);
+        assert_eq!(b"hello, hello", &output[..progress.written]);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let mut output = [0; 64];
+        assert_eq!(
+            Err("incorrect header check"),
+            Inflater::new(false).inflate(&[0x78, 0x9D], &mut output)
+        );
+        // a block type of 3
+        assert_eq!(
+            Err("invalid block type"),
+            Inflater::new(true).inflate(&[0x07], &mut output)
+        );
+        let mut corrupt = FIXED.to_vec();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            Err("incorrect data check"),
+            Inflater::new(false).inflate(&corrupt, &mut output)
+        );
+    }
+}
@@ -0,0 +1,796 @@
+use crate::vm::error::{Exception, VmError};
+use crate::vm::native::string::checked_range;
+use crate::vm::native::{
+    self, array_mut, boolean_arg, byte_array_range, int_arg, long_arg, reference_arg, NativeResult,
+};
+use crate::vm::runtime::Runtime;
+use crate::vm::thread::Thread;
+use crate::vm::types::NativeValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub use deflate::{Deflater, Flush, Strategy};
+pub use inflate::Inflater;
+
+mod deflate;
+mod inflate;
+
+/// The size of the window that matches can refer back into.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// The smallest lengths of the length codes 257 to 285, and the number of
+/// extra bits that follow them.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The smallest distances of the distance codes, and the number of extra bits
+/// that follow them.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the lengths of the code length code are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Updates a CRC-32 checksum, as used by zip and gzip files, with the given
+/// bytes. The checksum of no bytes is 0.
+pub fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Updates an Adler-32 checksum, as used by the zlib format, with the given
+/// bytes. The checksum of no bytes is 1.
+pub fn adler32(adler: u32, bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // the largest number of bytes that can be summed up without overflowing
+    const CHUNK: usize = 5552;
+    let (mut a, mut b) = (adler & 0xFFFF, adler >> 16);
+    for chunk in bytes.chunks(CHUNK) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// The progress of one call to deflate or inflate.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct Progress {
+    /// The number of input bytes that were consumed.
+    pub read: usize,
+    /// The number of output bytes that were produced.
+    pub written: usize,
+    /// Whether the end of the compressed data was reached.
+    pub finished: bool,
+    /// Whether inflating needs a dictionary to continue.
+    pub needs_dictionary: bool,
+}
+
+impl Progress {
+    /// Packs the progress into the result of `deflateBytesBytes` and
+    /// `inflateBytesBytes`: the bytes read in bits 0 to 30, the bytes written
+    /// in bits 31 to 61, and the flags in bits 62 and 63.
+    pub fn pack(&self) -> i64 {
+        (self.read as i64 & 0x7FFF_FFFF)
+            | (self.written as i64 & 0x7FFF_FFFF) << 31
+            | (self.finished as i64) << 62
+            | (self.needs_dictionary as i64) << 63
+    }
+}
+
+enum Stream {
+    Deflater(Deflater),
+    Inflater(Inflater),
+}
+
+/// The deflaters and inflaters of Java code, by the address that their
+/// natives return to `java.util.zip.Deflater` and `Inflater`.
+pub struct ZipStreamTable {
+    streams: Mutex<HashMap<i64, Arc<Mutex<Stream>>>>,
+    next_address: AtomicI64,
+}
+
+impl Default for ZipStreamTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZipStreamTable {
+    pub fn new() -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+            // 0 is the address of a closed stream in Java
+            next_address: AtomicI64::new(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn new_deflater(&self, level: i32, strategy: Strategy, nowrap: bool) -> i64 {
+        self.insert(Stream::Deflater(Deflater::new(level, strategy, nowrap)))
+    }
+
+    pub fn new_inflater(&self, nowrap: bool) -> i64 {
+        self.insert(Stream::Inflater(Inflater::new(nowrap)))
+    }
+
+    /// Compresses the input into the output, see [`Deflater::deflate`].
+    pub fn deflate(
+        &self,
+        address: i64,
+        input: &[u8],
+        output: &mut [u8],
+        flush: Flush,
+    ) -> Result<Progress, VmError> {
+        self.with_deflater(address, |d| Ok(d.deflate(input, output, flush)))
+    }
+
+    /// Changes the level and strategy of the data that is compressed from now
+    /// on.
+    pub fn set_deflate_params(
+        &self,
+        address: i64,
+        level: i32,
+        strategy: Strategy,
+    ) -> Result<(), VmError> {
+        self.with_deflater(address, |d| {
+            d.set_params(level, strategy);
+            Ok(())
+        })
+    }
+
+    /// Decompresses the input into the output, failing with a
+    /// `DataFormatException` if it is not in the deflate format.
+    pub fn inflate(
+        &self,
+        address: i64,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<Progress, VmError> {
+        self.with_inflater(address, |i| {
+            i.inflate(input, output)
+                .map_err(|e| Exception::DataFormat(e.to_string()).into())
+        })
+    }
+
+    /// Sets the preset dictionary of a deflater or inflater.
+    pub fn set_dictionary(&self, address: i64, dictionary: &[u8]) -> Result<(), VmError> {
+        match &mut *self.get(address)?.lock().unwrap() {
+            Stream::Deflater(d) => d.set_dictionary(dictionary),
+            Stream::Inflater(i) => i
+                .set_dictionary(dictionary)
+                .map_err(|e| VmError::from(Exception::IllegalArgument(e.to_string())))?,
+        }
+        Ok(())
+    }
+
+    /// The Adler-32 checksum of the uncompressed data so far.
+    pub fn adler(&self, address: i64) -> Result<u32, VmError> {
+        Ok(match &*self.get(address)?.lock().unwrap() {
+            Stream::Deflater(d) => d.adler(),
+            Stream::Inflater(i) => i.adler(),
+        })
+    }
+
+    /// Resets a deflater or inflater, so that it can process new data.
+    pub fn reset(&self, address: i64) -> Result<(), VmError> {
+        match &mut *self.get(address)?.lock().unwrap() {
+            Stream::Deflater(d) => d.reset(),
+            Stream::Inflater(i) => i.reset(),
+        }
+        Ok(())
+    }
+
+    /// Releases a deflater or inflater.
+    pub fn end(&self, address: i64) -> Result<(), VmError> {
+        self.streams
+            .lock()
+            .unwrap()
+            .remove(&address)
+            .map(|_| ())
+            .ok_or_else(|| invalid_address(address))
+    }
+
+    fn insert(&self, stream: Stream) -> i64 {
+        let address = self.next_address.fetch_add(1, Ordering::Relaxed);
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(address, Arc::new(Mutex::new(stream)));
+        address
+    }
+
+    fn get(&self, address: i64) -> Result<Arc<Mutex<Stream>>, VmError> {
+        self.streams
+            .lock()
+            .unwrap()
+            .get(&address)
+            .cloned()
+            .ok_or_else(|| invalid_address(address))
+    }
+
+    fn with_deflater<T>(
+        &self,
+        address: i64,
+        f: impl FnOnce(&mut Deflater) -> Result<T, VmError>,
+    ) -> Result<T, VmError> {
+        match &mut *self.get(address)?.lock().unwrap() {
+            Stream::Deflater(d) => f(d),
+            Stream::Inflater(_) => Err(invalid_address(address)),
+        }
+    }
+
+    fn with_inflater<T>(
+        &self,
+        address: i64,
+        f: impl FnOnce(&mut Inflater) -> Result<T, VmError>,
+    ) -> Result<T, VmError> {
+        match &mut *self.get(address)?.lock().unwrap() {
+            Stream::Inflater(i) => f(i),
+            Stream::Deflater(_) => Err(invalid_address(address)),
+        }
+    }
+}
+
+/// The class library never passes the address of a stream that was ended, so
+/// this is a bug in the VM.
+fn invalid_address(address: i64) -> VmError {
+    VmError::Internal(format!("invalid zip stream address {}", address))
+}
+
+/// `private static native int update(int crc, int b)` of `CRC32`
+pub fn crc32_update(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let crc = int_arg(args, 0)? as u32;
+    let b = int_arg(args, 1)? as u8;
+    Ok(Some(NativeValue::Integer(crc32(crc, &[b]) as i32)))
+}
+
+/// `private static native int update(int adler, int b)` of `Adler32`
+pub fn adler32_update(_thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let adler = int_arg(args, 0)? as u32;
+    let b = int_arg(args, 1)? as u8;
+    Ok(Some(NativeValue::Integer(adler32(adler, &[b]) as i32)))
+}
+
+/// `private static native long init(int level, int strategy, boolean nowrap)`
+/// of `Deflater`
+pub fn deflater_init(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let level = int_arg(args, 0)?;
+    let strategy = Strategy::from_java(int_arg(args, 1)?);
+    let nowrap = boolean_arg(args, 2)?;
+    let address = thread
+        .runtime()
+        .zip_streams()
+        .new_deflater(level, strategy, nowrap);
+    Ok(Some(NativeValue::Long(address)))
+}
+
+/// `private static native long init(boolean nowrap)` of `Inflater`
+pub fn inflater_init(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let address = thread
+        .runtime()
+        .zip_streams()
+        .new_inflater(boolean_arg(args, 0)?);
+    Ok(Some(NativeValue::Long(address)))
+}
+
+/// `private static native int getAdler(long addr)` of `Deflater` and
+/// `Inflater`
+pub fn zip_stream_get_adler(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let adler = thread.runtime().zip_streams().adler(long_arg(args, 0)?)?;
+    Ok(Some(NativeValue::Integer(adler as i32)))
+}
+
+/// `private static native void reset(long addr)` of `Deflater` and `Inflater`
+pub fn zip_stream_reset(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().zip_streams().reset(long_arg(args, 0)?)?;
+    Ok(None)
+}
+
+/// `private static native void end(long addr)` of `Deflater` and `Inflater`
+pub fn zip_stream_end(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    thread.runtime().zip_streams().end(long_arg(args, 0)?)?;
+    Ok(None)
+}
+
+/// `private static native int updateBytes0(int crc, byte[] b, int off, int
+/// len)` of `CRC32`
+pub fn crc32_update_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let crc = int_arg(args, 0)? as u32;
+    let bytes = Buffer::array(args, 1)?.read(thread.runtime())?;
+    Ok(Some(NativeValue::Integer(crc32(crc, &bytes) as i32)))
+}
+
+/// `private static native int updateByteBuffer0(int crc, long addr, int off,
+/// int len)` of `CRC32`
+pub fn crc32_update_byte_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let crc = int_arg(args, 0)? as u32;
+    let bytes = Buffer::byte_buffer(args, 1)?.read(thread.runtime())?;
+    Ok(Some(NativeValue::Integer(crc32(crc, &bytes) as i32)))
+}
+
+/// `private static native int updateBytes(int adler, byte[] b, int off, int
+/// len)` of `Adler32`
+pub fn adler32_update_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let adler = int_arg(args, 0)? as u32;
+    let bytes = Buffer::array(args, 1)?.read(thread.runtime())?;
+    Ok(Some(NativeValue::Integer(adler32(adler, &bytes) as i32)))
+}
+
+/// `private static native int updateByteBuffer(int adler, long addr, int off,
+/// int len)` of `Adler32`
+pub fn adler32_update_byte_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let adler = int_arg(args, 0)? as u32;
+    let bytes = Buffer::byte_buffer(args, 1)?.read(thread.runtime())?;
+    Ok(Some(NativeValue::Integer(adler32(adler, &bytes) as i32)))
+}
+
+/// `private native long deflateBytesBytes(long addr, byte[] inputArray, int
+/// inputOff, int inputLen, byte[] outputArray, int outputOff, int outputLen,
+/// int flush, int params)` of `Deflater`
+pub fn deflater_deflate_bytes_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::array(args, 2)?, Buffer::array(args, 5)?);
+    deflate(thread, args, input, output, 8)
+}
+
+/// `private native long deflateBytesBuffer(long addr, byte[] inputArray, int
+/// inputOff, int inputLen, long outputAddress, int outputLen, int flush, int
+/// params)` of `Deflater`
+pub fn deflater_deflate_bytes_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::array(args, 2)?, Buffer::address(args, 5)?);
+    deflate(thread, args, input, output, 7)
+}
+
+/// `private native long deflateBufferBytes(long addr, long inputAddress, int
+/// inputLen, byte[] outputArray, int outputOff, int outputLen, int flush, int
+/// params)` of `Deflater`
+pub fn deflater_deflate_buffer_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::address(args, 2)?, Buffer::array(args, 4)?);
+    deflate(thread, args, input, output, 7)
+}
+
+/// `private native long deflateBufferBuffer(long addr, long inputAddress, int
+/// inputLen, long outputAddress, int outputLen, int flush, int params)` of
+/// `Deflater`
+pub fn deflater_deflate_buffer_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::address(args, 2)?, Buffer::address(args, 4)?);
+    deflate(thread, args, input, output, 6)
+}
+
+/// `private native long inflateBytesBytes(long addr, byte[] inputArray, int
+/// inputOff, int inputLen, byte[] outputArray, int outputOff, int outputLen)`
+/// of `Inflater`
+pub fn inflater_inflate_bytes_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::array(args, 2)?, Buffer::array(args, 5)?);
+    inflate(thread, args, input, output)
+}
+
+/// `private native long inflateBytesBuffer(long addr, byte[] inputArray, int
+/// inputOff, int inputLen, long outputAddress, int outputLen)` of `Inflater`
+pub fn inflater_inflate_bytes_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::array(args, 2)?, Buffer::address(args, 5)?);
+    inflate(thread, args, input, output)
+}
+
+/// `private native long inflateBufferBytes(long addr, long inputAddress, int
+/// inputLen, byte[] outputArray, int outputOff, int outputLen)` of `Inflater`
+pub fn inflater_inflate_buffer_bytes(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::address(args, 2)?, Buffer::array(args, 4)?);
+    inflate(thread, args, input, output)
+}
+
+/// `private native long inflateBufferBuffer(long addr, long inputAddress, int
+/// inputLen, long outputAddress, int outputLen)` of `Inflater`
+pub fn inflater_inflate_buffer_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let (input, output) = (Buffer::address(args, 2)?, Buffer::address(args, 4)?);
+    inflate(thread, args, input, output)
+}
+
+/// `private static native void setDictionary(long addr, byte[] b, int off, int
+/// len)` of `Deflater` and `Inflater`
+pub fn zip_stream_set_dictionary(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let dictionary = Buffer::array(args, 1)?.read(thread.runtime())?;
+    let streams = thread.runtime().zip_streams();
+    streams.set_dictionary(long_arg(args, 0)?, &dictionary)?;
+    Ok(None)
+}
+
+/// `private static native void setDictionaryBuffer(long addr, long bufAddress,
+/// int len)` of `Deflater` and `Inflater`
+pub fn zip_stream_set_dictionary_buffer(thread: &mut Thread, args: &[NativeValue]) -> NativeResult {
+    let dictionary = Buffer::address(args, 1)?.read(thread.runtime())?;
+    let streams = thread.runtime().zip_streams();
+    streams.set_dictionary(long_arg(args, 0)?, &dictionary)?;
+    Ok(None)
+}
+
+/// Where the input of the natives comes from, or the output of a deflater or
+/// inflater goes to: a range of a `byte[]`, or the memory of a direct
+/// `ByteBuffer`.
+enum Buffer {
+    Array { array: usize, off: i32, len: i32 },
+    Address { address: i64, len: i32 },
+}
+
+impl Buffer {
+    /// The array, offset and length arguments starting at the given index.
+    fn array(args: &[NativeValue], index: usize) -> Result<Self, VmError> {
+        Ok(Buffer::Array {
+            array: reference_arg(args, index)?,
+            off: int_arg(args, index + 1)?,
+            len: int_arg(args, index + 2)?,
+        })
+    }
+
+    /// The address and length arguments starting at the given index.
+    fn address(args: &[NativeValue], index: usize) -> Result<Self, VmError> {
+        Ok(Buffer::Address {
+            address: long_arg(args, index)?,
+            len: int_arg(args, index + 1)?,
+        })
+    }
+
+    /// The address, offset and length arguments starting at the given index,
+    /// as the checksums take them.
+    fn byte_buffer(args: &[NativeValue], index: usize) -> Result<Self, VmError> {
+        Ok(Buffer::Address {
+            address: long_arg(args, index)? + int_arg(args, index + 1)? as i64,
+            len: int_arg(args, index + 2)?,
+        })
+    }
+
+    /// Copies the bytes of the buffer, so that the heap isn't locked while
+    /// they are processed.
+    fn read(&self, runtime: &Runtime) -> Result<Vec<u8>, VmError> {
+        match *self {
+            Buffer::Array { array, off, len } => {
+                byte_array_range(&runtime.heap().read().unwrap(), array, off, len)
+            }
+            Buffer::Address { address, len } => {
+                let mut bytes = vec![0; len.max(0) as usize];
+                runtime.direct_memory().read(address, &mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// The number of bytes that fit into the buffer, failing with an
+    /// `ArrayIndexOutOfBoundsException` if the range of an array is out of
+    /// bounds.
+    fn len(&self, runtime: &Runtime) -> Result<usize, VmError> {
+        match *self {
+            Buffer::Array { array, off, len } => {
+                let length = native::array(&runtime.heap().read().unwrap(), array)?.len();
+                Ok(checked_range(length, off, len)?.len())
+            }
+            Buffer::Address { len, .. } => Ok(len.max(0) as usize),
+        }
+    }
+
+    /// Writes the given bytes to the start of the buffer.
+    fn write(&self, runtime: &Runtime, bytes: &[u8]) -> Result<(), VmError> {
+        match *self {
+            Buffer::Array { array, off, .. } => {
+                let mut heap = runtime.heap().write().unwrap();
+                let start = off as usize;
+                array_mut(&mut heap, array)?.raw_elements_mut()[start..start + bytes.len()]
+                    .copy_from_slice(bytes);
+                Ok(())
+            }
+            Buffer::Address { address, .. } => runtime.direct_memory().write(address, bytes),
+        }
+    }
+}
+
+/// Deflates the input into the output with the flush and params arguments at
+/// the given index, see [`ZipStreamTable::deflate`]. Params that are set take
+/// effect before the input is compressed, and are not pending anymore
+/// afterwards.
+fn deflate(
+    thread: &mut Thread,
+    args: &[NativeValue],
+    input: Buffer,
+    output: Buffer,
+    flush_index: usize,
+) -> NativeResult {
+    let runtime = thread.runtime();
+    let address = long_arg(args, 1)?;
+    let flush = Flush::from_java(int_arg(args, flush_index)?);
+    // bit 0 is set to change the params, bits 1 and 2 are the strategy, and
+    // the others the level
+    let params = int_arg(args, flush_index + 1)?;
+    let streams = runtime.zip_streams();
+    if params & 1 != 0 {
+        let strategy = Strategy::from_java((params >> 1) & 3);
+        streams.set_deflate_params(address, params >> 3, strategy)?;
+    }
+    let input = input.read(runtime)?;
+    let mut buf = vec![0; output.len(runtime)?];
+    let progress = streams.deflate(address, &input, &mut buf, flush)?;
+    output.write(runtime, &buf[..progress.written])?;
+    Ok(Some(NativeValue::Long(progress.pack())))
+}
+
+/// Inflates the input into the output, see [`ZipStreamTable::inflate`].
+fn inflate(
+    thread: &mut Thread,
+    args: &[NativeValue],
+    input: Buffer,
+    output: Buffer,
+) -> NativeResult {
+    let runtime = thread.runtime();
+    let input = input.read(runtime)?;
+    let mut buf = vec![0; output.len(runtime)?];
+    let progress = runtime
+        .zip_streams()
+        .inflate(long_arg(args, 1)?, &input, &mut buf)?;
+    output.write(runtime, &buf[..progress.written])?;
+    Ok(Some(NativeValue::Long(progress.pack())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::heap::NULL;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0, crc32(0, b""));
+        assert_eq!(0xCBF4_3926, crc32(0, b"123456789"));
+        assert_eq!(0xCBF4_3926, crc32(crc32(0, b"1234"), b"56789"));
+        assert_eq!(1, adler32(1, b""));
+        assert_eq!(0x11E6_0398, adler32(1, b"Wikipedia"));
+        // long enough to need the modulo in between
+        let zeros = adler32(1, &[0xFF; 100_000]);
+        assert_eq!(zeros, adler32(adler32(1, &[0xFF; 7]), &[0xFF; 99_993]));
+        assert_eq!(0x149A_302C, zeros);
+    }
+
+    #[test]
+    fn test_natives() {
+        let mut thread = Thread::new(Arc::new(Runtime::default()));
+        assert_eq!(
+            Ok(Some(NativeValue::Integer(0xE8B7BE43_u32 as i32))),
+            crc32_update(
+                &mut thread,
+                &[NativeValue::Integer(0), NativeValue::Integer(b'a' as i32)]
+            )
+        );
+        assert_eq!(
+            Ok(Some(NativeValue::Integer(0x0062_0062))),
+            adler32_update(
+                &mut thread,
+                &[NativeValue::Integer(1), NativeValue::Integer(b'a' as i32)]
+            )
+        );
+
+        let deflater = match deflater_init(
+            &mut thread,
+            &[
+                NativeValue::Integer(-1),
+                NativeValue::Integer(0),
+                NativeValue::Boolean(false),
+            ],
+        ) {
+            Ok(Some(NativeValue::Long(address))) => address,
+            other => panic!("{:?}", other),
+        };
+        let inflater = match inflater_init(&mut thread, &[NativeValue::Boolean(false)]) {
+            Ok(Some(NativeValue::Long(address))) => address,
+            other => panic!("{:?}", other),
+        };
+        let streams = thread.runtime().zip_streams();
+        assert_eq!(2, streams.len());
+
+        // round trip through the table, a few bytes at a time
+        let data = b"to be or not to be, that is the question".repeat(50);
+        let mut compressed = vec![];
+        let mut buf = [0; 16];
+        let mut input = &data[..];
+        loop {
+            let flush = if input.len() <= 100 {
+                Flush::Finish
+            } else {
+                Flush::None
+            };
+            let end = input.len().min(100);
+            let progress = streams
+                .deflate(deflater, &input[..end], &mut buf, flush)
+                .unwrap();
+            input = &input[progress.read..];
+            compressed.extend_from_slice(&buf[..progress.written]);
+            if progress.finished {
+                break;
+            }
+        }
+        assert!(compressed.len() < data.len() / 5);
+        assert_eq!(adler32(1, &data), streams.adler(deflater).unwrap());
+
+        let mut inflated = vec![];
+        let mut input = &compressed[..];
+        loop {
+            let progress = streams.inflate(inflater, input, &mut buf).unwrap();
+            input = &input[progress.read..];
+            inflated.extend_from_slice(&buf[..progress.written]);
+            if progress.finished {
+                let packed = 1 << 62 | (progress.written as i64) << 31 | progress.read as i64;
+                assert_eq!(packed, progress.pack());
+                break;
+            }
+        }
+        assert_eq!(data, inflated);
+
+        assert_eq!(
+            Err(Exception::DataFormat("incorrect header check".to_string()).into()),
+            streams
+                .reset(inflater)
+                .and_then(|_| { streams.inflate(inflater, &[0x78, 0x00], &mut buf) })
+        );
+        zip_stream_end(&mut thread, &[NativeValue::Long(deflater)]).unwrap();
+        zip_stream_end(&mut thread, &[NativeValue::Long(inflater)]).unwrap();
+        assert!(thread.runtime().zip_streams().is_empty());
+        assert!(zip_stream_reset(&mut thread, &[NativeValue::Long(inflater)]).is_err());
+    }
+
+    #[test]
+    fn test_array_and_buffer_natives() {
+        use crate::vm::native::test_util::{self, bytes_of, call, new_bytes};
+        use NativeValue::{Boolean, Integer, Long, Reference};
+
+        let vm = test_util::vm(&[]);
+        let mut thread = Thread::new(vm.runtime().clone());
+        let memory = vm.runtime().direct_memory();
+        let long = |result: NativeResult| match result {
+            Ok(Some(Long(value))) => value,
+            other => panic!("{:?}", other),
+        };
+
+        let digits = new_bytes(&vm, b"0123456789");
+        let buffer = memory.allocate(10).unwrap();
+        memory.write(buffer, b"0123456789").unwrap();
+        for (class, name, checksum, initial) in [
+            ("CRC32", "updateBytes0", 0xCBF4_3926_u32, 0),
+            ("CRC32", "updateByteBuffer0", 0xCBF4_3926, 0),
+            ("Adler32", "updateBytes", 0x091E_01DE, 1),
+            ("Adler32", "updateByteBuffer", 0x091E_01DE, 1),
+        ] {
+            let (array, descriptor) = match name.contains("Buffer") {
+                true => (Long(buffer), "(IJII)I"),
+                false => (Reference(digits), "(I[BII)I"),
+            };
+            let class = format!("java/util/zip/{}", class);
+            let args = [Integer(initial), array, Integer(1), Integer(9)];
+            let result = call(&mut thread, &class, name, descriptor, &args);
+            assert_eq!(Some(Integer(checksum as i32)), result.unwrap(), "{}", name);
+        }
+        let args = [Integer(0), Reference(digits), Integer(5), Integer(6)];
+        let result = call(
+            &mut thread,
+            "java/util/zip/CRC32",
+            "updateBytes0",
+            "(I[BII)I",
+            &args,
+        );
+        assert!(result.is_err());
+
+        // deflate with a dictionary and a pending level from an array into
+        // memory
+        let deflater = long(deflater_init(
+            &mut thread,
+            &[Integer(1), Integer(0), Boolean(false)],
+        ));
+        let dictionary = new_bytes(&vm, b"to be or not");
+        let args = [
+            Long(deflater),
+            Reference(dictionary),
+            Integer(0),
+            Integer(12),
+        ];
+        let result = call(
+            &mut thread,
+            "java/util/zip/Deflater",
+            "setDictionary",
+            "(J[BII)V",
+            &args,
+        );
+        assert_eq!(None, result.unwrap());
+        let data = b"to be or not to be, that is the question".repeat(50);
+        let input = new_bytes(&vm, &data);
+        let compressed = memory.allocate(data.len() as i64).unwrap();
+        let args = [
+            Reference(NULL),
+            Long(deflater),
+            Reference(input),
+            Integer(0),
+            Integer(data.len() as i32),
+            Long(compressed),
+            Integer(data.len() as i32),
+            // FINISH, and level 9 with the default strategy
+            Integer(4),
+            Integer(9 << 3 | 1),
+        ];
+        let progress = long(call(
+            &mut thread,
+            "java/util/zip/Deflater",
+            "deflateBytesBuffer",
+            "(J[BIIJIII)J",
+            &args,
+        ));
+        assert_eq!(data.len() as i64, progress & 0x7FFF_FFFF);
+        assert_eq!(1, progress >> 62 & 1);
+        let written = (progress >> 31 & 0x7FFF_FFFF) as usize;
+        assert!(written < data.len() / 5);
+
+        // inflate from memory into an array, which needs the dictionary first
+        let inflater = long(inflater_init(&mut thread, &[Boolean(false)]));
+        // with room to spare, so that the end is reached in one call
+        let output = new_bytes(&vm, &vec![0; data.len() + 10]);
+        let inflate = |thread: &mut Thread, read: i64| {
+            let args = [
+                Reference(NULL),
+                Long(inflater),
+                Long(compressed + read),
+                Integer(written as i32 - read as i32),
+                Reference(output),
+                Integer(2),
+                Integer(data.len() as i32 + 8),
+            ];
+            long(call(
+                thread,
+                "java/util/zip/Inflater",
+                "inflateBufferBytes",
+                "(JJI[BII)J",
+                &args,
+            ))
+        };
+        let progress = inflate(&mut thread, 0);
+        assert_eq!(1, progress >> 63 & 1);
+        let read = progress & 0x7FFF_FFFF;
+        let dictionary = memory.allocate(12).unwrap();
+        memory.write(dictionary, b"to be or not").unwrap();
+        let args = [Long(inflater), Long(dictionary), Integer(12)];
+        let result = call(
+            &mut thread,
+            "java/util/zip/Inflater",
+            "setDictionaryBuffer",
+            "(JJI)V",
+            &args,
+        );
+        assert_eq!(None, result.unwrap());
+        let progress = inflate(&mut thread, read);
+        assert_eq!(1, progress >> 62 & 1);
+        assert_eq!(written as i64, read + (progress & 0x7FFF_FFFF));
+        assert_eq!(data.len() as i64, progress >> 31 & 0x7FFF_FFFF);
+        assert_eq!(data, bytes_of(&vm, output)[2..data.len() + 2]);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::layout::FieldLayout;
+use crate::vm::clock::{default_clock, Clock};
+use crate::vm::coverage::Coverage;
+use crate::vm::events::{EventKind, EventLog};
+use crate::vm::group::{ThreadGroup, UncaughtHandler};
+use crate::vm::heap::handle::GlobalHandles;
+use crate::vm::heap::Heap;
+use crate::vm::module::{ModuleLayer, ModuleMode};
+use crate::vm::native::intrinsics::Intrinsics;
+use crate::vm::native::io::FileTable;
+use crate::vm::native::memory::DirectMemory;
+#[cfg(feature = "net")]
+use crate::vm::native::net::SocketTable;
+#[cfg(feature = "process")]
+use crate::vm::native::process::ProcessTable;
+use crate::vm::native::zip::ZipStreamTable;
+use crate::vm::scheduler::{GreenScheduler, SchedulerMode};
+use crate::vm::shutdown::Shutdown;
+use crate::vm::symbol::Symbol;
+use crate::vm::thread::BreakpointHandler;
+use crate::vm::threads::ThreadRegistry;
+use libvfs::FileSystem;
+use std::collections::HashMap;
+#[cfg(any(debug_assertions, feature = "stack-guards"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The state of a VM that is shared by all of its threads, and that native
+/// methods operate on.
+pub struct Runtime {
+    /// The heap of the VM, which natives read and allocate objects on.
+    heap: Arc<RwLock<Heap>>,
+    /// The instance layouts of the loaded classes by their binary names, so
+    /// that natives can allocate objects, e.g. strings, without the classes,
+    /// which stay on the thread that loaded them.
+    instance_layouts: RwLock<HashMap<Symbol, Arc<FieldLayout>>>,
+    /// The file system that all file I/O of Java code goes through.
+    file_system: FileSystem,
+    files: FileTable,
+    #[cfg(feature = "net")]
+    sockets: SocketTable,
+    #[cfg(feature = "process")]
+    processes: ProcessTable,
+    zip_streams: ZipStreamTable,
+    direct_memory: DirectMemory,
+    shutdown: Shutdown,
+    clock: RwLock<Arc<dyn Clock>>,
+    coverage: Coverage,
+    intrinsics: Intrinsics,
+    threads: ThreadRegistry,
+    events: EventLog,
+    global_handles: GlobalHandles,
+    /// The group of the main thread, whose parent is the system group.
+    main_thread_group: Arc<ThreadGroup>,
+    /// Like `Thread.setDefaultUncaughtExceptionHandler`.
+    default_uncaught_handler: RwLock<Option<UncaughtHandler>>,
+    breakpoint_handler: RwLock<Option<BreakpointHandler>>,
+    scheduler_mode: RwLock<SchedulerMode>,
+    /// The scheduler of the green threads, which is started on first use.
+    green_scheduler: Mutex<Option<Arc<GreenScheduler>>>,
+    module_mode: RwLock<ModuleMode>,
+    boot_layer: RwLock<Arc<ModuleLayer>>,
+    /// Whether the frames of new threads are guarded, see
+    /// [`Runtime::set_stack_guards`].
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    stack_guards: AtomicBool,
+}
+
+impl Runtime {
+    pub fn new(file_system: FileSystem) -> Self {
+        Self {
+            heap: Arc::new(RwLock::new(Heap::new())),
+            instance_layouts: RwLock::new(HashMap::new()),
+            file_system,
+            files: FileTable::new(),
+            #[cfg(feature = "net")]
+            sockets: SocketTable::new(),
+            #[cfg(feature = "process")]
+            processes: ProcessTable::new(),
+            zip_streams: ZipStreamTable::new(),
+            direct_memory: DirectMemory::new(),
+            shutdown: Shutdown::new(),
+            clock: RwLock::new(default_clock()),
+            coverage: Coverage::new(),
+            intrinsics: Intrinsics::new(),
+            threads: ThreadRegistry::new(),
+            events: EventLog::default(),
+            global_handles: GlobalHandles::new(),
+            main_thread_group: ThreadGroup::new("main", &ThreadGroup::system()),
+            default_uncaught_handler: RwLock::new(None),
+            breakpoint_handler: RwLock::new(None),
+            scheduler_mode: RwLock::new(SchedulerMode::default()),
+            green_scheduler: Mutex::new(None),
+            module_mode: RwLock::new(ModuleMode::default()),
+            boot_layer: RwLock::new(Arc::new(ModuleLayer::default())),
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            stack_guards: AtomicBool::new(cfg!(debug_assertions)),
+        }
+    }
+
+    pub fn heap(&self) -> &Arc<RwLock<Heap>> {
+        &self.heap
+    }
+
+    /// Records the instance layout of a loaded class, see
+    /// [`Runtime::instance_layout`].
+    pub(crate) fn add_instance_layout(&self, class: &Class) {
+        self.instance_layouts
+            .write()
+            .unwrap()
+            .insert(class.name().clone(), class.shared_instance_layout().clone());
+    }
+
+    /// The binary name and the instance layout of the loaded class with the
+    /// given name, or [`None`] if no such class is loaded.
+    pub fn instance_layout(&self, class: &str) -> Option<(Symbol, Arc<FieldLayout>)> {
+        self.instance_layouts
+            .read()
+            .unwrap()
+            .get_key_value(class)
+            .map(|(name, layout)| (name.clone(), layout.clone()))
+    }
+
+    pub fn file_system(&self) -> &FileSystem {
+        &self.file_system
+    }
+
+    /// The files that are currently opened by Java code.
+    pub fn files(&self) -> &FileTable {
+        &self.files
+    }
+
+    /// The sockets that are currently opened by Java code. Network access is
+    /// denied unless a less restrictive policy is set on this table.
+    #[cfg(feature = "net")]
+    pub fn sockets(&self) -> &SocketTable {
+        &self.sockets
+    }
+
+    /// The child processes that were started by Java code. No program can be
+    /// started unless a less restrictive policy is set on this table.
+    #[cfg(feature = "process")]
+    pub fn processes(&self) -> &ProcessTable {
+        &self.processes
+    }
+
+    /// The deflaters and inflaters that are currently used by Java code.
+    pub fn zip_streams(&self) -> &ZipStreamTable {
+        &self.zip_streams
+    }
+
+    /// The off-heap memory that is currently allocated by Java code, e.g. for
+    /// direct `ByteBuffer`s.
+    pub fn direct_memory(&self) -> &DirectMemory {
+        &self.direct_memory
+    }
+
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// The source of time for Java code. This is the
+    /// [`default_clock`](crate::vm::clock::default_clock) unless another
+    /// clock is set, e.g. a [`VirtualClock`](crate::vm::clock::VirtualClock)
+    /// for deterministic tests.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.read().unwrap().clone()
+    }
+
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.write().unwrap() = clock;
+    }
+
+    /// The executed methods and basic blocks, if coverage was enabled before
+    /// running any code.
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+
+    /// The methods that are invoked as Rust implementations instead of being
+    /// interpreted, unless intrinsics are disabled.
+    pub fn intrinsics(&self) -> &Intrinsics {
+        &self.intrinsics
+    }
+
+    /// The live threads and their CPU time and contention statistics.
+    pub fn threads(&self) -> &ThreadRegistry {
+        &self.threads
+    }
+
+    /// The recent significant events of the VM, which are dumped if it
+    /// crashes.
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    /// Records an event at the current time of the clock.
+    pub fn log_event(&self, kind: EventKind, message: String) {
+        self.events.log(kind, self.clock().nano_time(), message);
+    }
+
+    /// The handles to heap objects that natives and the embedder keep across
+    /// native calls.
+    pub fn global_handles(&self) -> &GlobalHandles {
+        &self.global_handles
+    }
+
+    pub fn main_thread_group(&self) -> &Arc<ThreadGroup> {
+        &self.main_thread_group
+    }
+
+    /// The handler for uncaught throwables of threads that neither have a
+    /// handler themselves nor belong to a group with one.
+    pub fn default_uncaught_handler(&self) -> Option<UncaughtHandler> {
+        self.default_uncaught_handler.read().unwrap().clone()
+    }
+
+    pub fn set_default_uncaught_handler(&self, handler: Option<UncaughtHandler>) {
+        *self.default_uncaught_handler.write().unwrap() = handler;
+    }
+
+    /// The debugger that threads notify when they reach a breakpoint, see
+    /// [`Code::set_breakpoint`](crate::vm::code::Code::set_breakpoint).
+    pub fn breakpoint_handler(&self) -> Option<BreakpointHandler> {
+        self.breakpoint_handler.read().unwrap().clone()
+    }
+
+    pub fn set_breakpoint_handler(&self, handler: Option<BreakpointHandler>) {
+        *self.breakpoint_handler.write().unwrap() = handler;
+    }
+
+    /// How Java threads are mapped to threads of the host. This is
+    /// [`SchedulerMode::Native`] unless another mode is set.
+    pub fn scheduler_mode(&self) -> SchedulerMode {
+        *self.scheduler_mode.read().unwrap()
+    }
+
+    /// Sets how Java threads are mapped to threads of the host. This has to
+    /// be set before running any code, since threads that were already
+    /// created, and a green scheduler that was already started, keep their
+    /// mode.
+    pub fn set_scheduler_mode(&self, mode: SchedulerMode) {
+        *self.scheduler_mode.write().unwrap() = mode;
+    }
+
+    /// The scheduler that runs the green threads, which is started on the
+    /// first call, or [`None`] unless the mode is [`SchedulerMode::Green`].
+    pub fn green_scheduler(&self) -> Option<Arc<GreenScheduler>> {
+        let workers = match self.scheduler_mode() {
+            SchedulerMode::Green { workers } => workers,
+            SchedulerMode::Native => return None,
+        };
+        let mut scheduler = self.green_scheduler.lock().unwrap();
+        Some(
+            scheduler
+                .get_or_insert_with(|| Arc::new(GreenScheduler::new(workers)))
+                .clone(),
+        )
+    }
+
+    /// Whether the boundaries of named modules are enforced. This is
+    /// [`ModuleMode::ClassPath`] unless another mode is set with
+    /// [`VM::set_module_mode`](crate::vm::VM::set_module_mode).
+    pub fn module_mode(&self) -> ModuleMode {
+        *self.module_mode.read().unwrap()
+    }
+
+    /// The modules of the VM, which has no named modules unless the module
+    /// mode is [`ModuleMode::Strict`].
+    pub fn boot_layer(&self) -> Arc<ModuleLayer> {
+        self.boot_layer.read().unwrap().clone()
+    }
+
+    /// Whether the interpreter checks every value that it pushes and pops
+    /// against the types that were pushed and the `max_stack` of the method.
+    /// This is enabled in debug builds unless disabled.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn stack_guards(&self) -> bool {
+        self.stack_guards.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables the checks of the operand stack and the local
+    /// variables, see [`SlotType`](crate::vm::stack::SlotType). With the
+    /// checks, code that corrupts the stack, due to a bug in the interpreter
+    /// or unverified code, panics at the instruction that does, with the
+    /// method and pc, instead of computing wrong values. Release builds only
+    /// have the checks with the `stack-guards` feature, which is for
+    /// reproducing bugs for a report. This has to be set before creating any
+    /// thread, since threads keep the setting.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_stack_guards(&self, enabled: bool) {
+        self.stack_guards.store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_boot_layer(&self, mode: ModuleMode, layer: Arc<ModuleLayer>) {
+        *self.module_mode.write().unwrap() = mode;
+        *self.boot_layer.write().unwrap() = layer;
+    }
+}
+
+/// A runtime with an empty in-memory file system, so that nothing leaks to the
+/// host.
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new(FileSystem::new_in_memory_fs())
+    }
+}
@@ -0,0 +1,348 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How the Java threads of a VM are mapped to threads of the host, see
+/// [`Runtime::set_scheduler_mode`](crate::vm::runtime::Runtime::set_scheduler_mode).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SchedulerMode {
+    /// Every Java thread runs on a thread of its own, like in HotSpot.
+    #[default]
+    Native,
+    /// Java threads are green threads, which are multiplexed over the given
+    /// number of threads of the host by a [`GreenScheduler`], so that programs
+    /// with thousands of threads run on hosts with few resources.
+    Green { workers: usize },
+}
+
+/// How many safepoints a green thread passes before it yields to the other
+/// green threads, see [`Thread::should_yield`](crate::vm::thread::Thread::should_yield).
+pub const YIELD_QUANTUM: u32 = 1_000;
+
+/// What a [`GreenTask`] does after a step.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Step {
+    /// The task wants to run again, after the other ready tasks.
+    Yield,
+    /// The task waits for the given time, e.g. in `Thread.sleep`.
+    Sleep(Duration),
+    /// The task waits until it is unparked with [`GreenHandle::unpark`], e.g.
+    /// for a monitor or in `Object.wait`. If it was unparked since its last
+    /// park, it runs again immediately, like `LockSupport.park`.
+    Park,
+    /// The task completed.
+    Done,
+}
+
+/// A green thread. The interpreter keeps the Java stack of a thread in the
+/// heap, so instead of switching stacks, a green thread runs in steps that
+/// return to the scheduler at a safepoint or a blocking operation.
+pub trait GreenTask: Send {
+    fn step(&mut self) -> Step;
+}
+
+impl<F> GreenTask for F
+where
+    F: FnMut() -> Step + Send,
+{
+    fn step(&mut self) -> Step {
+        self()
+    }
+}
+
+/// A cooperative scheduler that runs [`GreenTask`]s on a fixed pool of
+/// worker threads. Dropping the scheduler stops the workers after their
+/// current steps, even if tasks didn't complete yet.
+pub struct GreenScheduler {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// A handle to a task of a [`GreenScheduler`].
+#[derive(Clone)]
+pub struct GreenHandle {
+    id: u64,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Notified when a task becomes ready, or the scheduler stops.
+    ready: Condvar,
+    /// Notified when the last task completed.
+    idle: Condvar,
+}
+
+#[derive(Default)]
+struct Queue {
+    next_id: u64,
+    ready: VecDeque<(u64, Box<dyn GreenTask>)>,
+    /// The sleeping tasks by their deadline, the earliest first.
+    sleeping: BinaryHeap<Reverse<(Instant, u64)>>,
+    /// The tasks that are sleeping or parked.
+    waiting: HashMap<u64, Box<dyn GreenTask>>,
+    parked: HashSet<u64>,
+    /// The tasks that were unparked while they weren't parked, so that their
+    /// next park returns immediately.
+    permits: HashSet<u64>,
+    live: usize,
+    stopped: bool,
+}
+
+impl Queue {
+    /// Moves the sleeping tasks whose deadline passed to the ready tasks, and
+    /// returns the earliest deadline of those that still sleep.
+    fn wake_sleepers(&mut self, now: Instant) -> Option<Instant> {
+        while let Some(Reverse((deadline, id))) = self.sleeping.peek().copied() {
+            if deadline > now {
+                return Some(deadline);
+            }
+            self.sleeping.pop();
+            let task = self.waiting.remove(&id).unwrap();
+            self.ready.push_back((id, task));
+        }
+        None
+    }
+}
+
+impl GreenScheduler {
+    /// Starts a scheduler with the given number of worker threads, at least
+    /// one.
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+            idle: Condvar::new(),
+        });
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let shared = shared.clone();
+                std::thread::Builder::new()
+                    .name(format!("green-worker-{}", i))
+                    .spawn(move || shared.work())
+                    .expect("failed to start a worker of the green scheduler")
+            })
+            .collect();
+        Self { shared, workers }
+    }
+
+    /// Schedules a new task, which runs after the tasks that are ready.
+    pub fn spawn(&self, task: impl GreenTask + 'static) -> GreenHandle {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let id = queue.next_id;
+        queue.next_id += 1;
+        queue.live += 1;
+        queue.ready.push_back((id, Box::new(task)));
+        self.shared.ready.notify_one();
+        GreenHandle {
+            id,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// The number of tasks that didn't complete yet.
+    pub fn live_tasks(&self) -> usize {
+        self.shared.queue.lock().unwrap().live
+    }
+
+    /// Blocks until all tasks completed. Tasks that are parked forever block
+    /// this forever as well.
+    pub fn join(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        while queue.live > 0 {
+            queue = self.shared.idle.wait(queue).unwrap();
+        }
+    }
+}
+
+impl Drop for GreenScheduler {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().stopped = true;
+        self.shared.ready.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl GreenHandle {
+    /// Makes the task ready if it is parked, or else lets its next park
+    /// return immediately.
+    pub fn unpark(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.parked.remove(&self.id) {
+            let task = queue.waiting.remove(&self.id).unwrap();
+            queue.ready.push_back((self.id, task));
+            self.shared.ready.notify_one();
+        } else {
+            queue.permits.insert(self.id);
+        }
+    }
+}
+
+impl Shared {
+    fn work(&self) {
+        while let Some((id, mut task)) = self.next_task() {
+            // a task that panics is dead, but must not take the worker with it
+            let step =
+                std::panic::catch_unwind(AssertUnwindSafe(|| task.step())).unwrap_or(Step::Done);
+            let mut queue = self.queue.lock().unwrap();
+            match step {
+                Step::Yield => queue.ready.push_back((id, task)),
+                Step::Sleep(duration) => {
+                    queue
+                        .sleeping
+                        .push(Reverse((Instant::now() + duration, id)));
+                    queue.waiting.insert(id, task);
+                    // the deadline may be earlier than the one that the
+                    // other workers wait for
+                    self.ready.notify_all();
+                }
+                Step::Park if queue.permits.remove(&id) => queue.ready.push_back((id, task)),
+                Step::Park => {
+                    queue.parked.insert(id);
+                    queue.waiting.insert(id, task);
+                }
+                Step::Done => {
+                    queue.permits.remove(&id);
+                    queue.live -= 1;
+                    if queue.live == 0 {
+                        self.idle.notify_all();
+                    }
+                }
+            }
+            if !queue.ready.is_empty() {
+                self.ready.notify_one();
+            }
+        }
+    }
+
+    /// Takes the next ready task, waiting for one if there is none. Returns
+    /// [`None`] once the scheduler stops.
+    fn next_task(&self) -> Option<(u64, Box<dyn GreenTask>)> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if queue.stopped {
+                return None;
+            }
+            let now = Instant::now();
+            let next_deadline = queue.wake_sleepers(now);
+            if let Some(task) = queue.ready.pop_front() {
+                return Some(task);
+            }
+            queue = match next_deadline {
+                Some(deadline) => self.ready.wait_timeout(queue, deadline - now).unwrap().0,
+                None => self.ready.wait(queue).unwrap(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_many_tasks_on_few_workers() {
+        let scheduler = GreenScheduler::new(2);
+        let steps = Arc::new(AtomicUsize::new(0));
+        for _ in 0..5_000 {
+            let steps = steps.clone();
+            let mut remaining = 3;
+            scheduler.spawn(move || {
+                steps.fetch_add(1, Ordering::SeqCst);
+                remaining -= 1;
+                if remaining == 0 {
+                    Step::Done
+                } else {
+                    Step::Yield
+                }
+            });
+        }
+        scheduler.join();
+        assert_eq!(15_000, steps.load(Ordering::SeqCst));
+        assert_eq!(0, scheduler.live_tasks());
+    }
+
+    #[test]
+    fn test_sleep() {
+        let scheduler = GreenScheduler::new(1);
+        let start = Instant::now();
+        let mut slept = false;
+        scheduler.spawn(move || {
+            if slept {
+                return Step::Done;
+            }
+            slept = true;
+            Step::Sleep(Duration::from_millis(20))
+        });
+        // the sleeping task doesn't block the only worker
+        let ran = Arc::new(AtomicUsize::new(0));
+        let r = ran.clone();
+        scheduler.spawn(move || {
+            r.fetch_add(1, Ordering::SeqCst);
+            Step::Done
+        });
+        scheduler.join();
+        assert_eq!(1, ran.load(Ordering::SeqCst));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_park_and_unpark() {
+        let scheduler = GreenScheduler::new(1);
+        let order = Arc::new(Mutex::new(vec![]));
+        let o = order.clone();
+        let mut parked = false;
+        let waiter = scheduler.spawn(move || {
+            if parked {
+                o.lock().unwrap().push("waiter");
+                return Step::Done;
+            }
+            parked = true;
+            Step::Park
+        });
+        let o = order.clone();
+        scheduler.spawn(move || {
+            o.lock().unwrap().push("notifier");
+            waiter.unpark();
+            Step::Done
+        });
+        scheduler.join();
+        assert_eq!(vec!["notifier", "waiter"], *order.lock().unwrap());
+    }
+
+    #[test]
+    fn test_unpark_before_park() {
+        let scheduler = GreenScheduler::new(1);
+        let parks = Arc::new(AtomicUsize::new(0));
+        let p = parks.clone();
+        let handle = Arc::new(Mutex::new(None::<GreenHandle>));
+        let h = handle.clone();
+        // holding the lock keeps the task from running before it has its
+        // own handle
+        let mut slot = handle.lock().unwrap();
+        *slot = Some(scheduler.spawn(move || {
+            if p.fetch_add(1, Ordering::SeqCst) == 1 {
+                return Step::Done;
+            }
+            h.lock().unwrap().as_ref().unwrap().unpark();
+            Step::Park
+        }));
+        drop(slot);
+        scheduler.join();
+        assert_eq!(2, parks.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_panicking_task_completes() {
+        let scheduler = GreenScheduler::new(1);
+        scheduler.spawn(|| -> Step { panic!("task failed") });
+        scheduler.join();
+        assert_eq!(0, scheduler.live_tasks());
+    }
+}
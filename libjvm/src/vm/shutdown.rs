@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// A hook that is run once when the VM exits, but not when it halts.
+pub type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+/// Coordinates the termination of the VM, as requested by `Runtime.exit` or
+/// `Runtime.halt`.
+///
+/// Terminating never exits the host process. Instead, the exit status is
+/// recorded and all threads unwind at their next safepoint, so that the status
+/// can be returned to the embedder.
+#[derive(Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    exit_status: Mutex<Option<i32>>,
+    hooks: Mutex<Vec<ShutdownHook>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_hook(&self, hook: ShutdownHook) {
+        self.hooks.lock().unwrap().push(hook);
+    }
+
+    /// Runs all shutdown hooks and then halts the VM with the given status.
+    /// Hooks are run only once, even if this is called multiple times.
+    pub fn exit(&self, status: i32) {
+        let hooks = std::mem::take(&mut *self.hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+        self.halt(status);
+    }
+
+    /// Halts the VM with the given status, without running any shutdown hooks.
+    /// If the VM is already terminating, the original status is kept.
+    pub fn halt(&self, status: i32) {
+        self.exit_status.lock().unwrap().get_or_insert(status);
+        self.requested.store(true, Ordering::Release);
+    }
+
+    /// Whether the VM is terminating. This is polled by every thread at its
+    /// safepoints.
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// The status that the VM terminated with, or [`None`] if it is still
+    /// running.
+    pub fn exit_status(&self) -> Option<i32> {
+        *self.exit_status.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_exit_runs_hooks_once() {
+        let shutdown = Shutdown::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let r = runs.clone();
+        shutdown.add_hook(Box::new(move || {
+            r.fetch_add(1, Ordering::SeqCst);
+        }));
+        assert!(!shutdown.is_requested());
+        assert_eq!(None, shutdown.exit_status());
+
+        shutdown.exit(3);
+        shutdown.exit(4);
+        assert_eq!(1, runs.load(Ordering::SeqCst));
+        assert!(shutdown.is_requested());
+        assert_eq!(Some(3), shutdown.exit_status());
+    }
+
+    #[test]
+    fn test_halt_skips_hooks() {
+        let shutdown = Shutdown::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let r = runs.clone();
+        shutdown.add_hook(Box::new(move || {
+            r.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        shutdown.halt(7);
+        assert_eq!(0, runs.load(Ordering::SeqCst));
+        assert_eq!(Some(7), shutdown.exit_status());
+    }
+}
@@ -0,0 +1,473 @@
+use crate::vm::classloader::class::InitState;
+use crate::vm::classloader::trace::ClassSource;
+use crate::vm::classloader::ClassLoader;
+use crate::vm::error::VmError;
+use crate::vm::heap::{Array, HeapObject, Instance};
+use crate::vm::mirror::PrimitiveType;
+use crate::vm::VM;
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// The first bytes of every serialized snapshot.
+const MAGIC: &[u8; 8] = b"RJVMSNAP";
+/// The version of the format, which is incremented whenever it changes, so
+/// that snapshots of older versions are rejected instead of misread.
+const VERSION: u16 = 1;
+
+/// The state of a VM after warming it up, like the CDS archive of HotSpot
+/// with `-XX:ArchiveClassesAtExit`: the class files of all loaded classes in
+/// the order they were loaded, which of them were initialized, and the
+/// objects on the heap.
+///
+/// A snapshot is taken at build time with [`VM::dump_snapshot`] and restored
+/// at startup with [`VM::restore_snapshot`], which defines the classes from
+/// the stored class files without searching the class path, and copies the
+/// heap as it was instead of running the static initializers again. Nothing
+/// is shared between processes, the snapshot is simply read into the VM.
+///
+/// Fields and array elements are stored as they are laid out on the heap, in
+/// the byte order of the platform, so a snapshot can only be restored on a
+/// platform with the same byte order and pointer width.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot {
+    classes: Vec<SnapshotClass>,
+    objects: Vec<SnapshotObject>,
+    primitive_mirrors: Vec<(PrimitiveType, usize)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct SnapshotClass {
+    name: String,
+    bytes: Vec<u8>,
+    initialized: bool,
+}
+
+/// An object on the heap, with the binary name of its class or the descriptor
+/// of its component type.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum SnapshotObject {
+    Instance {
+        class: String,
+        fields: Vec<u8>,
+    },
+    Array {
+        component: String,
+        elements: Vec<u8>,
+    },
+}
+
+/// Returns the binary names in the given class list, like the `classlist`
+/// files of HotSpot: one name per line, e.g. `java/lang/Object`, with blank
+/// lines and comments starting with `#` ignored.
+pub fn parse_class_list(class_list: &str) -> impl Iterator<Item = &str> {
+    class_list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+impl VM {
+    /// Loads and initializes the classes with the given binary names, e.g.
+    /// from [`parse_class_list`], and takes a snapshot of this VM. Fails with
+    /// the error of the first class that can't be loaded or initialized.
+    ///
+    /// The snapshot contains all loaded classes, not only the listed ones, and
+    /// all objects on the heap. Handles to objects are not part of it.
+    pub fn dump_snapshot<'a>(
+        &mut self,
+        class_list: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Snapshot, VmError> {
+        for name in class_list {
+            let class = self.bootstrap_class_loader.find_or_load_class(name)?;
+            self.initialize(&class)?;
+        }
+
+        let classes = self
+            .bootstrap_class_loader
+            .loaded_classes()
+            .iter()
+            .map(|class| {
+                let name = class.name().to_string();
+                let (bytes, _) = self.bootstrap_class_loader.read_class_file(&name)?;
+                Ok(SnapshotClass {
+                    name,
+                    bytes,
+                    initialized: class.init_state() == InitState::Initialized,
+                })
+            })
+            .collect::<Result<_, VmError>>()?;
+
+        let heap = self.heap.read().unwrap();
+        let objects = (1..=heap.len())
+            .map(|reference| match heap.get(reference).unwrap() {
+                HeapObject::Instance(instance) => SnapshotObject::Instance {
+                    class: instance.class_name().to_string(),
+                    fields: instance.raw_fields().to_vec(),
+                },
+                HeapObject::Array(array) => SnapshotObject::Array {
+                    component: array.component().to_string(),
+                    elements: array.raw_elements().to_vec(),
+                },
+            })
+            .collect();
+
+        let mut primitive_mirrors = self.primitive_classes.mirrors().collect::<Vec<_>>();
+        primitive_mirrors.sort_by_key(|(_, mirror)| *mirror);
+        Ok(Snapshot {
+            classes,
+            objects,
+            primitive_mirrors,
+        })
+    }
+
+    /// Restores the given snapshot into this VM, which must not have loaded
+    /// any classes or allocated any objects yet. The classes are defined from
+    /// the class files in the snapshot, and the classes that were initialized
+    /// are marked as initialized without running their static initializers,
+    /// which notifies the init listeners. Classes that are not in the snapshot
+    /// are loaded from the class path as usual.
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), VmError> {
+        if !self.bootstrap_class_loader.loaded_classes().is_empty()
+            || !self.heap.read().unwrap().is_empty()
+        {
+            return Err(VmError::Internal(
+                "a snapshot can only be restored into a new VM".to_string(),
+            ));
+        }
+
+        let mut initialized = vec![];
+        for class in &snapshot.classes {
+            let defined = self.bootstrap_class_loader.define_class(
+                &class.name,
+                &class.bytes,
+                ClassSource::Snapshot,
+            )?;
+            if class.initialized {
+                initialized.push(defined);
+            }
+        }
+        // after defining all classes, so that listeners see them all
+        for class in initialized {
+            self.initializer.mark_initialized(&class);
+        }
+
+        let method_area = self.method_area.read().unwrap();
+        let (symbols, descriptors) = (method_area.symbols(), method_area.descriptors());
+        let mut heap = self.heap.write().unwrap();
+        for object in &snapshot.objects {
+            let object = match object {
+                SnapshotObject::Instance { class, fields } => {
+                    let class = self
+                        .bootstrap_class_loader
+                        .find_class(class)
+                        .ok_or_else(|| {
+                            VmError::Internal(format!("class {} is not in the snapshot", class))
+                        })?;
+                    HeapObject::Instance(Instance::from_raw(&class, fields.clone().into())?)
+                }
+                SnapshotObject::Array {
+                    component,
+                    elements,
+                } => {
+                    let component = descriptors
+                        .field_type(&symbols.intern(component), symbols)
+                        .ok_or_else(|| {
+                            VmError::Internal(format!("invalid component type {}", component))
+                        })?;
+                    HeapObject::Array(Array::from_raw(component, elements.clone().into())?)
+                }
+            };
+            heap.restore(object);
+        }
+        // the objects were restored without the write barrier
+        heap.rebuild_remembered_sets();
+        for (primitive_type, mirror) in &snapshot.primitive_mirrors {
+            self.primitive_classes.restore(*primitive_type, *mirror);
+        }
+        Ok(())
+    }
+}
+
+impl Snapshot {
+    /// The binary names of the classes in this snapshot, in the order they
+    /// are defined when it is restored.
+    pub fn class_names(&self) -> impl Iterator<Item = &str> {
+        self.classes.iter().map(|class| class.name.as_str())
+    }
+
+    /// The number of objects on the heap of this snapshot.
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Writes this snapshot to the given output, e.g. a file that is shipped
+    /// with the application.
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_be_bytes())?;
+        out.write_all(&[usize::BITS as u8, cfg!(target_endian = "big") as u8])?;
+
+        write_len(out, self.classes.len())?;
+        for class in &self.classes {
+            write_bytes(out, class.name.as_bytes())?;
+            out.write_all(&[class.initialized as u8])?;
+            write_bytes(out, &class.bytes)?;
+        }
+        write_len(out, self.objects.len())?;
+        for object in &self.objects {
+            let (tag, name, contents) = match object {
+                SnapshotObject::Instance { class, fields } => (0, class, fields),
+                SnapshotObject::Array {
+                    component,
+                    elements,
+                } => (1, component, elements),
+            };
+            out.write_all(&[tag])?;
+            write_bytes(out, name.as_bytes())?;
+            write_bytes(out, contents)?;
+        }
+        write_len(out, self.primitive_mirrors.len())?;
+        for (primitive_type, mirror) in &self.primitive_mirrors {
+            write_bytes(out, primitive_type.name().as_bytes())?;
+            write_len(out, *mirror)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot that was written by [`Snapshot::write_to`]. Fails with
+    /// an error of kind [`ErrorKind::InvalidData`] if the input is not a
+    /// snapshot, or one of another version or platform.
+    pub fn read_from(input: &mut impl Read) -> std::io::Result<Self> {
+        let mut header = [0; 12];
+        input.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid_data("not a snapshot"));
+        }
+        let version = u16::from_be_bytes([header[8], header[9]]);
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "snapshot version {} is not supported",
+                version
+            )));
+        }
+        if header[10..] != [usize::BITS as u8, cfg!(target_endian = "big") as u8] {
+            return Err(invalid_data("snapshot of another platform"));
+        }
+
+        let mut classes = vec![];
+        for _ in 0..read_len(input)? {
+            let name = read_string(input)?;
+            let initialized = match read_u8(input)? {
+                0 => false,
+                1 => true,
+                _ => return Err(invalid_data("invalid initialization state")),
+            };
+            let bytes = read_bytes(input)?;
+            classes.push(SnapshotClass {
+                name,
+                bytes,
+                initialized,
+            });
+        }
+        let mut objects = vec![];
+        for _ in 0..read_len(input)? {
+            let tag = read_u8(input)?;
+            let name = read_string(input)?;
+            let contents = read_bytes(input)?;
+            objects.push(match tag {
+                0 => SnapshotObject::Instance {
+                    class: name,
+                    fields: contents,
+                },
+                1 => SnapshotObject::Array {
+                    component: name,
+                    elements: contents,
+                },
+                _ => return Err(invalid_data("invalid object tag")),
+            });
+        }
+        let mut primitive_mirrors = vec![];
+        for _ in 0..read_len(input)? {
+            let name = read_string(input)?;
+            let primitive_type = PrimitiveType::from_name(&name)
+                .ok_or_else(|| invalid_data(&format!("invalid primitive type {}", name)))?;
+            primitive_mirrors.push((primitive_type, read_len(input)?));
+        }
+        Ok(Self {
+            classes,
+            objects,
+            primitive_mirrors,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_len(out: &mut impl Write, len: usize) -> std::io::Result<()> {
+    out.write_all(&(len as u64).to_be_bytes())
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    write_len(out, bytes.len())?;
+    out.write_all(bytes)
+}
+
+fn read_u8(input: &mut impl Read) -> std::io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_len(input: &mut impl Read) -> std::io::Result<usize> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_be_bytes(bytes)).map_err(|_| invalid_data("length out of range"))
+}
+
+fn read_bytes(input: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let len = read_len(input)?;
+    // the length is not trusted for the allocation, a truncated input fails
+    // when it ends instead
+    let mut bytes = vec![];
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_string(input: &mut impl Read) -> std::io::Result<String> {
+    String::from_utf8(read_bytes(input)?).map_err(|_| invalid_data("invalid string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::classloader::trace::ClassLoadTrace;
+    use crate::vm::error::LinkageError;
+    use crate::vm::types::NativeValue;
+    use libvfs::FileSystem;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+
+    fn new_vm(dirs: &[&str]) -> VM {
+        VM::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(
+                dirs.iter()
+                    .map(|dir| ClassPathEntry::Dir(dir.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+    }
+
+    /// Collects the output of a trace, which needs a `'static` writer.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dump_and_restore() {
+        let mut vm = new_vm(&["tests/resources/vm/init", "tests/resources/vm/classloader"]);
+        let boxed = vm.box_value(NativeValue::Integer(42)).unwrap();
+        let int_class = vm.primitive_class("int").unwrap();
+        let class_list = "# warmed up at build time\n\nPlain\n";
+        let snapshot = vm.dump_snapshot(parse_class_list(class_list)).unwrap();
+        assert!(snapshot.class_names().any(|name| name == "Plain"));
+        assert!(snapshot
+            .class_names()
+            .any(|name| name == "java/lang/Integer"));
+        assert_eq!(2, snapshot.object_count());
+
+        let mut bytes = vec![];
+        snapshot.write_to(&mut bytes).unwrap();
+        let read = Snapshot::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(snapshot, read);
+
+        // the restored VM has no class path, so everything comes from the
+        // snapshot
+        let mut restored = new_vm(&[]);
+        let output = Output::default();
+        restored.trace_class_loading(ClassLoadTrace::new(Box::new(output.clone())));
+        let initialized = Rc::new(RefCell::new(vec![]));
+        let listened = initialized.clone();
+        restored.add_init_listener(Box::new(move |class| {
+            listened
+                .borrow_mut()
+                .push(class.name().as_str().to_string())
+        }));
+        restored.restore_snapshot(&read).unwrap();
+
+        let plain = restored.for_name("Plain").unwrap();
+        assert_eq!(InitState::Initialized, plain.init_state());
+        assert_eq!(vec!["java/lang/Object", "Plain"], *initialized.borrow());
+        assert_eq!(
+            InitState::Linked,
+            restored.for_name("java.lang.Integer").unwrap().init_state()
+        );
+        assert_eq!(NativeValue::Integer(42), restored.unbox(boxed).unwrap());
+        assert_eq!(
+            Some(PrimitiveType::Int),
+            restored.primitive_type_of(int_class)
+        );
+        assert_eq!(int_class, restored.primitive_class("int").unwrap());
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("Plain source: shared objects file loader: bootstrap"));
+
+        // only into a new VM, and only once
+        assert_eq!(
+            Err(VmError::Internal(
+                "a snapshot can only be restored into a new VM".to_string()
+            )),
+            restored.restore_snapshot(&read)
+        );
+    }
+
+    #[test]
+    fn test_dump_failure() {
+        let mut vm = new_vm(&["tests/resources/vm/init", "tests/resources/vm/classloader"]);
+        // the static initializer of Config can't be run yet
+        assert!(vm.dump_snapshot(["Config"]).is_err());
+        assert_eq!(
+            Err(LinkageError::NoClassDefFound("Missing".to_string()).into()),
+            vm.dump_snapshot(["Missing"])
+        );
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let snapshot = new_vm(&[]).dump_snapshot([]).unwrap();
+        let mut bytes = vec![];
+        snapshot.write_to(&mut bytes).unwrap();
+        assert_eq!(snapshot, Snapshot::read_from(&mut &bytes[..]).unwrap());
+
+        let mut other_version = bytes.clone();
+        other_version[9] += 1;
+        let mut other_platform = bytes.clone();
+        other_platform[10] = 16;
+        for invalid in [&b"CAFEBABE0000"[..], &other_version, &other_platform] {
+            assert_eq!(
+                ErrorKind::InvalidData,
+                Snapshot::read_from(&mut &invalid[..]).unwrap_err().kind()
+            );
+        }
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            Snapshot::read_from(&mut &bytes[..bytes.len() - 1])
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
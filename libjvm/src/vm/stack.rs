@@ -1,135 +1,665 @@
+use crate::vm::classloader::resolve::ResolvedMethod;
 use crate::vm::types::NativeValue;
-use libjava::classfile::ConstantPool;
+use libjava::classfile::ResolvedConstantPool;
 use std::sync::Arc;
 
+#[cfg(any(debug_assertions, feature = "stack-guards"))]
+mod stackmap;
+
+#[cfg(any(debug_assertions, feature = "stack-guards"))]
+pub use stackmap::{ExpectedTypes, StackMap};
+
 pub struct Stack {
     frames: Vec<Frame>,
+    pool: FramePool,
+    /// Whether the new frames of this stack check the types of their slots,
+    /// see [`Stack::set_guarded`].
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    guarded: bool,
 }
 
 impl Stack {
     pub fn allocate(stack_capacity: usize) -> Self {
         Self {
             frames: Vec::with_capacity(stack_capacity),
+            pool: FramePool::new(),
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            guarded: cfg!(debug_assertions),
         }
     }
 
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Sets whether the frames that [`Stack::push_new_frame`] pushes from now
+    /// on check every access to their operand stack and local variables, see
+    /// [`SlotType`]. This is the default in debug builds.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_guarded(&mut self, guarded: bool) {
+        self.guarded = guarded;
+    }
+
     pub fn push_frame(&mut self, frame: Frame) {
         self.frames.push(frame);
     }
 
+    /// Pushes a frame with the given sizes, reusing the buffers of a previously
+    /// dropped frame if possible.
+    pub fn push_new_frame(
+        &mut self,
+        num_locals: usize,
+        operand_stack_size: usize,
+        constant_pool: Arc<ResolvedConstantPool>,
+    ) {
+        let frame = self
+            .pool
+            .acquire(num_locals, operand_stack_size, constant_pool);
+        self.frames.push(frame);
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        self.frames.last_mut().unwrap().set_guarded(self.guarded);
+    }
+
     pub fn pop_frame(&mut self) -> Frame {
         self.frames.pop().unwrap()
     }
 
+    /// Pops the current frame and returns it to the frame pool, so that its
+    /// buffers can be reused by the next invocation.
+    pub fn drop_frame(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        self.pool.release(frame);
+    }
+
     pub fn current_frame_mut(&mut self) -> &mut Frame {
         self.frames.last_mut().unwrap()
     }
+
+    /// All frames of this stack, with the current frame last.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
 }
 
 pub struct Frame {
-    pub locals: Vec<NativeValue>,
+    pub locals: Locals,
     pub operand_stack: OperandStack,
-    pub constant_pool: Arc<ConstantPool>,
+    pub constant_pool: Arc<ResolvedConstantPool>,
+    /// The method that this frame executes, if it is known.
+    pub method: Option<ResolvedMethod>,
 }
 
 impl Frame {
     pub fn allocate(
         num_locals: usize,
         operand_stack_size: usize,
-        constant_pool: Arc<ConstantPool>,
+        constant_pool: Arc<ResolvedConstantPool>,
     ) -> Self {
         Self {
-            locals: Vec::with_capacity(num_locals),
+            locals: Locals::new(num_locals),
             operand_stack: OperandStack::new(operand_stack_size),
             constant_pool,
+            method: None,
         }
     }
+
+    /// Sets whether this frame checks the types of its slots, and the size of
+    /// its operand stack. The frame has to be empty, i.e. its operand stack
+    /// and local variables must not hold any values yet.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_guarded(&mut self, guarded: bool) {
+        self.locals.set_guarded(guarded);
+        self.operand_stack.set_guarded(guarded);
+    }
+
+    fn reset(
+        &mut self,
+        num_locals: usize,
+        operand_stack_size: usize,
+        constant_pool: Arc<ResolvedConstantPool>,
+    ) {
+        self.locals.reset(num_locals);
+        self.operand_stack.reset(operand_stack_size);
+        self.constant_pool = constant_pool;
+        self.method = None;
+    }
+}
+
+/// The maximum number of free frames that are kept per size class.
+const MAX_POOLED_FRAMES_PER_CLASS: usize = 256;
+
+/// The number of size classes for the locals and the operand stack each. Both
+/// sizes are limited to `u16::MAX` by the class file format, so they round up
+/// to at most `1 << 16`.
+const SIZE_CLASSES: usize = 17;
+
+/// A pool of unused frames, so that invocations don't have to allocate fresh
+/// buffers for the local variables and operand stack of every frame.
+///
+/// Frames are pooled by size class, which is the power of two that the capacity
+/// of the locals and the operand stack is rounded up to.
+pub struct FramePool {
+    /// The free frames, indexed by `locals_class * SIZE_CLASSES + stack_class`.
+    free: Vec<Vec<Frame>>,
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePool {
+    pub fn new() -> Self {
+        Self {
+            free: (0..SIZE_CLASSES * SIZE_CLASSES).map(|_| vec![]).collect(),
+        }
+    }
+
+    pub fn acquire(
+        &mut self,
+        num_locals: usize,
+        operand_stack_size: usize,
+        constant_pool: Arc<ResolvedConstantPool>,
+    ) -> Frame {
+        let locals_class = Self::size_class(num_locals);
+        let stack_class = Self::size_class(operand_stack_size);
+        match self.free[locals_class * SIZE_CLASSES + stack_class].pop() {
+            Some(mut frame) => {
+                frame.reset(num_locals, operand_stack_size, constant_pool);
+                frame
+            }
+            None => {
+                let mut frame = Frame::allocate(1 << locals_class, 1 << stack_class, constant_pool);
+                frame.locals.reset(num_locals);
+                frame.operand_stack.reset(operand_stack_size);
+                frame
+            }
+        }
+    }
+
+    pub fn release(&mut self, frame: Frame) {
+        let index = match (
+            Self::capacity_class(frame.locals.capacity()),
+            Self::capacity_class(frame.operand_stack.capacity()),
+        ) {
+            (Some(locals_class), Some(stack_class)) => locals_class * SIZE_CLASSES + stack_class,
+            _ => return,
+        };
+        let frames = &mut self.free[index];
+        if frames.len() < MAX_POOLED_FRAMES_PER_CLASS {
+            frames.push(frame);
+        }
+    }
+
+    /// The number of frames that are currently free in this pool.
+    pub fn len(&self) -> usize {
+        self.free.iter().map(|frames| frames.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The smallest class whose frames can hold the given number of slots.
+    fn size_class(size: usize) -> usize {
+        size.max(1).next_power_of_two().trailing_zeros() as usize
+    }
+
+    /// The largest class that a buffer with the given capacity can serve.
+    fn capacity_class(capacity: usize) -> Option<usize> {
+        if capacity == 0 {
+            None
+        } else {
+            Some(((usize::BITS - 1 - capacity.leading_zeros()) as usize).min(SIZE_CLASSES - 1))
+        }
+    }
+}
+
+/// The type of the value in a slot of the operand stack or the local variables.
+///
+/// Slots are untagged; types are only tracked by guarded frames, where a
+/// mismatch (like popping an int that was pushed as a reference, or a local
+/// variable that doesn't match the stack map of the method), and pushing
+/// more values than `max_stack` of the method allows, panics. Frames are
+/// guarded in debug builds. Release builds only have the guards with the
+/// `stack-guards` feature, and only if
+/// [`Runtime::set_stack_guards`](crate::vm::runtime::Runtime::set_stack_guards)
+/// enables them. Otherwise, the interpreter relies on the code having been
+/// verified.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SlotType {
+    Int,
+    Float,
+    Long,
+    Double,
+    Reference,
+    ReturnAddress,
+}
+
+/// A raw 64-bit slot. Every value occupies exactly one slot, including longs and
+/// doubles.
+pub type Slot = u64;
+
+macro_rules! slot_accessors {
+    ($($ty:ty, $slot_type:ident, $push:ident, $pop:ident, $get:ident, $set:ident, $to_slot:expr, $from_slot:expr;)*) => {
+        impl OperandStack {
+            $(
+                #[track_caller]
+                pub fn $push(&mut self, value: $ty) {
+                    let to_slot: fn($ty) -> Slot = $to_slot;
+                    self.push_slot(to_slot(value), SlotType::$slot_type);
+                }
+
+                #[track_caller]
+                pub fn $pop(&mut self) -> $ty {
+                    let from_slot: fn(Slot) -> $ty = $from_slot;
+                    from_slot(self.pop_slot(SlotType::$slot_type))
+                }
+            )*
+        }
+
+        impl Locals {
+            $(
+                #[track_caller]
+                pub fn $get(&self, index: usize) -> $ty {
+                    let from_slot: fn(Slot) -> $ty = $from_slot;
+                    from_slot(self.get_slot(index, SlotType::$slot_type))
+                }
+
+                #[track_caller]
+                pub fn $set(&mut self, index: usize, value: $ty) {
+                    let to_slot: fn($ty) -> Slot = $to_slot;
+                    self.set_slot(index, to_slot(value), SlotType::$slot_type);
+                }
+            )*
+        }
+    };
+}
+
+slot_accessors! {
+    i32, Int, push_int, pop_int, get_int, set_int, |v| v as u32 as Slot, |s| s as u32 as i32;
+    f32, Float, push_float, pop_float, get_float, set_float, |v| v.to_bits() as Slot, |s| f32::from_bits(s as u32);
+    i64, Long, push_long, pop_long, get_long, set_long, |v| v as Slot, |s| s as i64;
+    f64, Double, push_double, pop_double, get_double, set_double, |v| v.to_bits(), f64::from_bits;
+    usize, Reference, push_reference, pop_reference, get_reference, set_reference, |v| v as Slot, |s| s as usize;
+    usize, ReturnAddress, push_return_address, pop_return_address, get_return_address, set_return_address, |v| v as Slot, |s| s as usize;
 }
 
 pub struct OperandStack {
-    inner: Vec<NativeValue>,
+    slots: Vec<Slot>,
+    /// The types of the slots, if this stack is guarded.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    types: Vec<SlotType>,
+    /// The number of slots that the method may use, its `max_stack`.
+    max_stack: usize,
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    guarded: bool,
 }
 
 impl OperandStack {
     pub fn new(capacity: usize) -> Self {
         Self {
-            inner: Vec::with_capacity(capacity),
+            slots: Vec::with_capacity(capacity),
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            types: Vec::with_capacity(capacity),
+            max_stack: capacity,
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            guarded: cfg!(debug_assertions),
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+        self.slots.is_empty()
     }
 
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.slots.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    pub fn max_stack(&self) -> usize {
+        self.max_stack
+    }
+
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Sets whether this stack checks the types of the values that are popped
+    /// and its size, see [`SlotType`]. The stack has to be empty.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_guarded(&mut self, guarded: bool) {
+        assert!(self.is_empty(), "operand stack is not empty");
+        self.guarded = guarded;
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        self.types.clear();
+    }
+
+    /// Clears the stack for a method with the given `max_stack`, while keeping
+    /// the allocated buffer.
+    fn reset(&mut self, max_stack: usize) {
+        self.clear();
+        self.max_stack = max_stack;
     }
 
+    /// Pushes a value of any type. Booleans, bytes, chars and shorts are
+    /// sign- or zero-extended to ints, as they are on the JVM operand stack.
+    #[track_caller]
     pub fn push(&mut self, value: NativeValue) {
-        self.inner.push(value)
+        match value {
+            NativeValue::Boolean(v) => self.push_int(v as i32),
+            NativeValue::Byte(v) => self.push_int(v as i32),
+            NativeValue::Char(v) => self.push_int(v as i32),
+            NativeValue::Short(v) => self.push_int(v as i32),
+            NativeValue::Integer(v) => self.push_int(v),
+            NativeValue::Float(v) => self.push_float(v),
+            NativeValue::Long(v) => self.push_long(v),
+            NativeValue::Double(v) => self.push_double(v),
+            NativeValue::Reference(v) => self.push_reference(v),
+            NativeValue::ReturnAddress(v) => self.push_return_address(v),
+        }
     }
 
-    pub fn last(&mut self) -> &NativeValue {
-        self.inner.last().unwrap()
+    /// Returns the reference on top of the stack without popping it.
+    #[track_caller]
+    pub fn peek_reference(&self) -> usize {
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        self.check_top(SlotType::Reference);
+        *self.slots.last().unwrap() as usize
     }
 
-    pub fn pop(&mut self) -> NativeValue {
-        self.inner.pop().unwrap()
+    /// Duplicates the top slot, regardless of its type.
+    #[track_caller]
+    pub fn dup(&mut self) {
+        let top = *self.slots.last().unwrap();
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.guarded {
+            self.check_size();
+            let top_type = *self.types.last().unwrap();
+            self.types.push(top_type);
+        }
+        self.slots.push(top);
     }
 
-    pub fn pop_boolean(&mut self) -> bool {
-        match self.pop() {
-            NativeValue::Boolean(v) => v,
-            _ => panic!("invalid type"),
+    #[track_caller]
+    fn push_slot(&mut self, slot: Slot, _slot_type: SlotType) {
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.guarded {
+            self.check_size();
+            self.types.push(_slot_type);
         }
+        self.slots.push(slot);
     }
 
-    pub fn pop_byte(&mut self) -> i8 {
-        match self.pop() {
-            NativeValue::Byte(v) => v,
-            _ => panic!("invalid type"),
+    #[track_caller]
+    fn pop_slot(&mut self, _expected: SlotType) -> Slot {
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.guarded {
+            self.check_top(_expected);
+            self.types.pop();
         }
+        self.slots.pop().unwrap()
+    }
+
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[track_caller]
+    fn check_size(&self) {
+        assert!(
+            self.slots.len() < self.max_stack,
+            "operand stack overflow, max_stack is {}",
+            self.max_stack
+        );
     }
 
-    pub fn pop_char(&mut self) -> u16 {
-        match self.pop() {
-            NativeValue::Char(v) => v,
-            _ => panic!("invalid type"),
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[track_caller]
+    fn check_top(&self, expected: SlotType) {
+        if self.guarded {
+            let actual = self.types.last().expect("operand stack is empty");
+            assert_eq!(expected, *actual, "invalid type on operand stack");
         }
     }
+}
 
-    pub fn pop_short(&mut self) -> i16 {
-        match self.pop() {
-            NativeValue::Short(v) => v,
-            _ => panic!("invalid type"),
+/// The local variables of a frame.
+pub struct Locals {
+    slots: Vec<Slot>,
+    /// The types of the slots, if the locals are guarded, or [`None`] for
+    /// slots that were not set yet.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    types: Vec<Option<SlotType>>,
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    guarded: bool,
+}
+
+impl Locals {
+    pub fn new(num_locals: usize) -> Self {
+        Self {
+            slots: vec![0; num_locals],
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            types: vec![None; num_locals],
+            #[cfg(any(debug_assertions, feature = "stack-guards"))]
+            guarded: cfg!(debug_assertions),
         }
     }
 
-    pub fn pop_integer(&mut self) -> i32 {
-        match self.pop() {
-            NativeValue::Integer(v) => v,
-            _ => panic!("invalid type"),
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Sets whether the locals check the types of the values that are read,
+    /// see [`SlotType`]. This forgets the types of the slots that were set
+    /// before, so it has to be called before setting any.
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    pub fn set_guarded(&mut self, guarded: bool) {
+        self.guarded = guarded;
+        self.types.clear();
+        if guarded {
+            self.types.resize(self.slots.len(), None);
+        }
+    }
+
+    /// Sets a local variable to a value of any type. Booleans, bytes, chars
+    /// and shorts are stored as ints, see [`OperandStack::push`].
+    #[track_caller]
+    pub fn set(&mut self, index: usize, value: NativeValue) {
+        match value {
+            NativeValue::Boolean(v) => self.set_int(index, v as i32),
+            NativeValue::Byte(v) => self.set_int(index, v as i32),
+            NativeValue::Char(v) => self.set_int(index, v as i32),
+            NativeValue::Short(v) => self.set_int(index, v as i32),
+            NativeValue::Integer(v) => self.set_int(index, v),
+            NativeValue::Float(v) => self.set_float(index, v),
+            NativeValue::Long(v) => self.set_long(index, v),
+            NativeValue::Double(v) => self.set_double(index, v),
+            NativeValue::Reference(v) => self.set_reference(index, v),
+            NativeValue::ReturnAddress(v) => self.set_return_address(index, v),
         }
     }
 
-    pub fn pop_long(&mut self) -> i64 {
-        match self.pop() {
-            NativeValue::Long(v) => v,
-            _ => panic!("invalid type"),
+    /// Resizes the locals to the given number of slots and zeroes them, while
+    /// keeping the allocated buffer.
+    fn reset(&mut self, num_locals: usize) {
+        self.slots.clear();
+        self.slots.resize(num_locals, 0);
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        self.set_guarded(self.guarded);
+    }
+
+    #[track_caller]
+    fn get_slot(&self, index: usize, _expected: SlotType) -> Slot {
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.guarded {
+            assert_eq!(
+                Some(_expected),
+                self.types[index],
+                "invalid type in local variable {}",
+                index
+            );
         }
+        self.slots[index]
     }
 
-    pub fn pop_reference(&mut self) -> usize {
-        match self.pop() {
-            NativeValue::Reference(v) => v,
-            _ => panic!("invalid type"),
+    #[track_caller]
+    fn set_slot(&mut self, index: usize, slot: Slot, _slot_type: SlotType) {
+        self.slots[index] = slot;
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.guarded {
+            self.types[index] = Some(_slot_type);
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop_round_trip() {
+        let mut stack = OperandStack::new(6);
+        stack.push_int(-17);
+        stack.push_float(1.5);
+        stack.push_long(i64::MIN);
+        stack.push_double(-0.25);
+        stack.push_reference(42);
+        stack.push_return_address(7);
+
+        assert_eq!(6, stack.len());
+        assert_eq!(7, stack.pop_return_address());
+        assert_eq!(42, stack.pop_reference());
+        assert_eq!(-0.25, stack.pop_double());
+        assert_eq!(i64::MIN, stack.pop_long());
+        assert_eq!(1.5, stack.pop_float());
+        assert_eq!(-17, stack.pop_int());
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_push_native_value_widens_to_int() {
+        let mut stack = OperandStack::new(4);
+        stack.push(NativeValue::Boolean(true));
+        stack.push(NativeValue::Byte(-1));
+        stack.push(NativeValue::Char(u16::MAX));
+        stack.push(NativeValue::Short(-2));
+
+        assert_eq!(-2, stack.pop_int());
+        assert_eq!(65535, stack.pop_int());
+        assert_eq!(-1, stack.pop_int());
+        assert_eq!(1, stack.pop_int());
+    }
 
-    pub fn pop_return_address(&mut self) -> usize {
-        match self.pop() {
-            NativeValue::ReturnAddress(v) => v,
-            _ => panic!("invalid type"),
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[should_panic(expected = "invalid type on operand stack")]
+    fn test_pop_wrong_type() {
+        let mut stack = OperandStack::new(1);
+        stack.set_guarded(true);
+        stack.push_reference(1);
+        stack.pop_int();
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[should_panic(expected = "operand stack overflow, max_stack is 1")]
+    fn test_push_beyond_max_stack() {
+        let mut stack = OperandStack::new(1);
+        stack.set_guarded(true);
+        stack.push_int(1);
+        stack.push_int(2);
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    fn test_unguarded() {
+        let mut stack = OperandStack::new(1);
+        stack.set_guarded(false);
+        stack.push_reference(1);
+        stack.push_reference(2);
+        assert_eq!(2, stack.pop_int());
+
+        let mut locals = Locals::new(1);
+        locals.set_guarded(false);
+        assert_eq!(0.0, locals.get_float(0));
+    }
+
+    #[test]
+    fn test_frame_pool_reuses_buffers() {
+        let cp = Arc::new(ResolvedConstantPool::default());
+        let mut stack = Stack::allocate(2);
+        stack.push_new_frame(3, 5, cp.clone());
+        {
+            let frame = stack.current_frame_mut();
+            assert_eq!(3, frame.locals.len());
+            frame.locals.set_int(2, 7);
+            frame.operand_stack.push_int(1);
         }
+        let locals_ptr = stack.current_frame_mut().locals.slots.as_ptr();
+        stack.drop_frame();
+        assert_eq!(1, stack.pool.len());
+
+        // same size class (4 locals, 8 operand stack slots)
+        stack.push_new_frame(4, 8, cp);
+        assert!(stack.pool.is_empty());
+        let frame = stack.current_frame_mut();
+        assert_eq!(locals_ptr, frame.locals.slots.as_ptr());
+        assert_eq!(4, frame.locals.len());
+        assert_eq!(0, frame.locals.slots[2]);
+        assert!(frame.operand_stack.is_empty());
+    }
+
+    #[test]
+    fn test_frame_pool_size_classes() {
+        let cp = Arc::new(ResolvedConstantPool::default());
+        let mut pool = FramePool::new();
+        pool.release(Frame::allocate(4, 4, cp.clone()));
+        let frame = pool.acquire(5, 4, cp.clone());
+        assert!(frame.locals.capacity() >= 5);
+        assert_eq!(1, pool.len());
+
+        // frames without capacity can't serve any request
+        pool.release(Frame::allocate(0, 0, cp));
+        assert_eq!(1, pool.len());
+    }
+
+    #[test]
+    fn test_locals() {
+        let mut locals = Locals::new(2);
+        locals.set_long(0, 1 << 40);
+        locals.set_reference(1, 3);
+        assert_eq!(1 << 40, locals.get_long(0));
+        assert_eq!(3, locals.get_reference(1));
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[should_panic(expected = "invalid type in local variable 0")]
+    fn test_locals_wrong_type() {
+        let mut locals = Locals::new(1);
+        locals.set_guarded(true);
+        locals.set_float(0, 1.0);
+        locals.get_int(0);
     }
 }
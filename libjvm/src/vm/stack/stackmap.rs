@@ -0,0 +1,248 @@
+use crate::vm::stack::{Frame, SlotType};
+use libjava::classfile::{StackMapFrame, VerificationTypeInfo};
+use std::collections::BTreeMap;
+
+/// The types of the local variables and the operand stack that a frame of the
+/// `StackMapTable` attribute ([`$4.7.4`]) of a method declares at an
+/// instruction.
+///
+/// [`$4.7.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ExpectedTypes {
+    /// The type of every local variable by its index, or [`None`] for the
+    /// ones that are `top`, which may not be read before they are set. Like
+    /// in the frame, a long or double takes two indices, the second of which
+    /// is `top`.
+    pub locals: Vec<Option<SlotType>>,
+    /// The types on the operand stack, from the bottom, where [`None`] is
+    /// `top`.
+    pub stack: Vec<Option<SlotType>>,
+}
+
+/// The [`ExpectedTypes`] at every instruction that the `StackMapTable` of a
+/// method has a frame for, by the offset of the instruction. The interpreter
+/// checks guarded frames against them, see
+/// [`Code::set_stack_map`](crate::vm::code::Code::set_stack_map).
+#[derive(Debug, Clone, Default)]
+pub struct StackMap {
+    frames: BTreeMap<usize, ExpectedTypes>,
+}
+
+impl StackMap {
+    /// Expands the entries of the `StackMapTable` of a method, each of which
+    /// describes its frame relative to the previous one, starting with the
+    /// implicit frame whose locals are the parameters of the method, with
+    /// `this` first for instance methods.
+    pub fn new(parameters: &[SlotType], entries: &[StackMapFrame]) -> Self {
+        // the locals as in the attribute, where a long or double is one entry
+        let mut locals: Vec<Option<SlotType>> = parameters.iter().copied().map(Some).collect();
+        let mut frames = BTreeMap::new();
+        let mut previous: Option<usize> = None;
+        for entry in entries {
+            let (delta, stack) = match entry {
+                StackMapFrame::Same { frame_type } => (*frame_type as usize, vec![]),
+                StackMapFrame::SameLocals1StackItem { frame_type, stack } => {
+                    (*frame_type as usize - 64, vec![slot_type(stack)])
+                }
+                StackMapFrame::SameLocals1StackItemExtended {
+                    offset_delta,
+                    stack,
+                    ..
+                } => (*offset_delta as usize, vec![slot_type(stack)]),
+                StackMapFrame::Chop {
+                    frame_type,
+                    offset_delta,
+                } => {
+                    let chopped = 251 - *frame_type as usize;
+                    locals.truncate(locals.len().saturating_sub(chopped));
+                    (*offset_delta as usize, vec![])
+                }
+                StackMapFrame::SameExtended { offset_delta, .. } => {
+                    (*offset_delta as usize, vec![])
+                }
+                StackMapFrame::Append {
+                    offset_delta,
+                    locals: appended,
+                    ..
+                } => {
+                    locals.extend(appended.iter().map(slot_type));
+                    (*offset_delta as usize, vec![])
+                }
+                StackMapFrame::Full {
+                    offset_delta,
+                    locals: full,
+                    stack,
+                    ..
+                } => {
+                    locals = full.iter().map(slot_type).collect();
+                    (
+                        *offset_delta as usize,
+                        stack.iter().map(slot_type).collect(),
+                    )
+                }
+            };
+            // every frame but the first one is at least one byte after the
+            // previous one
+            let offset = match previous {
+                None => delta,
+                Some(previous) => previous + delta + 1,
+            };
+            frames.insert(
+                offset,
+                ExpectedTypes {
+                    locals: by_index(&locals),
+                    stack,
+                },
+            );
+            previous = Some(offset);
+        }
+        Self { frames }
+    }
+
+    /// The types at the instruction at the given offset, or [`None`] if the
+    /// stack map has no frame for it.
+    pub fn at(&self, offset: usize) -> Option<&ExpectedTypes> {
+        self.frames.get(&offset)
+    }
+}
+
+impl Frame {
+    /// Checks the types of the slots of this frame against the types that the
+    /// stack map of its method declares at the current instruction, and
+    /// forgets the types of the locals that the stack map declares as `top`,
+    /// so that reading one of them before setting it panics, like the
+    /// verifier would reject it ([`$4.10.1.4`]). Does nothing unless this
+    /// frame is guarded.
+    ///
+    /// [`$4.10.1.4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.4
+    #[track_caller]
+    pub fn check_stack_map(&mut self, expected: &ExpectedTypes) {
+        if !self.locals.guarded || !self.operand_stack.guarded {
+            return;
+        }
+        for (index, actual) in self.locals.types.iter_mut().enumerate() {
+            match expected.locals.get(index).copied().flatten() {
+                None => *actual = None,
+                Some(expected) => assert_eq!(
+                    Some(expected),
+                    *actual,
+                    "invalid type in local variable {} for the stack map frame",
+                    index
+                ),
+            }
+        }
+        let actual = &self.operand_stack.types;
+        assert_eq!(
+            expected.stack.len(),
+            actual.len(),
+            "invalid operand stack size for the stack map frame"
+        );
+        for (expected, actual) in expected.stack.iter().zip(actual) {
+            if let Some(expected) = expected {
+                assert_eq!(
+                    expected, actual,
+                    "invalid type on operand stack for the stack map frame"
+                );
+            }
+        }
+    }
+}
+
+/// The type of the slot of a value of the given verification type, or
+/// [`None`] for `top`.
+fn slot_type(verification_type: &VerificationTypeInfo) -> Option<SlotType> {
+    match verification_type {
+        VerificationTypeInfo::TopVariable { .. } => None,
+        VerificationTypeInfo::IntegerVariable { .. } => Some(SlotType::Int),
+        VerificationTypeInfo::FloatVariable { .. } => Some(SlotType::Float),
+        VerificationTypeInfo::LongVariable { .. } => Some(SlotType::Long),
+        VerificationTypeInfo::DoubleVariable { .. } => Some(SlotType::Double),
+        VerificationTypeInfo::NullVariable { .. }
+        | VerificationTypeInfo::UninitializedThisVariable { .. }
+        | VerificationTypeInfo::ObjectVariable { .. }
+        | VerificationTypeInfo::UninitializedVariable { .. } => Some(SlotType::Reference),
+    }
+}
+
+/// The locals of a frame by their index, where a long or double is followed
+/// by the `top` of its second index.
+fn by_index(locals: &[Option<SlotType>]) -> Vec<Option<SlotType>> {
+    let mut slots = Vec::with_capacity(locals.len());
+    for local in locals {
+        slots.push(*local);
+        if matches!(local, Some(SlotType::Long | SlotType::Double)) {
+            slots.push(None);
+        }
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libjava::classfile::ResolvedConstantPool;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_expand_frames() {
+        let entries = [
+            StackMapFrame::Append {
+                frame_type: 252,
+                offset_delta: 4,
+                locals: vec![VerificationTypeInfo::LongVariable { tag: 4 }],
+            },
+            StackMapFrame::SameLocals1StackItem {
+                frame_type: 64 + 2,
+                stack: VerificationTypeInfo::NullVariable { tag: 5 },
+            },
+            StackMapFrame::Chop {
+                frame_type: 250,
+                offset_delta: 0,
+            },
+        ];
+        let map = StackMap::new(&[SlotType::Reference, SlotType::Int], &entries);
+
+        let long = ExpectedTypes {
+            locals: vec![
+                Some(SlotType::Reference),
+                Some(SlotType::Int),
+                Some(SlotType::Long),
+                None,
+            ],
+            stack: vec![],
+        };
+        assert_eq!(Some(&long), map.at(4));
+        assert_eq!(
+            Some(&ExpectedTypes {
+                stack: vec![Some(SlotType::Reference)],
+                ..long
+            }),
+            map.at(7)
+        );
+        assert_eq!(
+            vec![Some(SlotType::Reference), Some(SlotType::Int)],
+            map.at(8).unwrap().locals
+        );
+        assert_eq!(None, map.at(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid type in local variable 1 for the stack map frame")]
+    fn test_check_stack_map() {
+        let mut frame = Frame::allocate(2, 1, Arc::new(ResolvedConstantPool::default()));
+        frame.set_guarded(true);
+        frame.locals.set_int(0, 1);
+        frame.locals.set_float(1, 1.0);
+        frame.operand_stack.push_reference(3);
+        let expected = ExpectedTypes {
+            locals: vec![None, Some(SlotType::Float)],
+            stack: vec![Some(SlotType::Reference)],
+        };
+        frame.check_stack_map(&expected);
+
+        // the stack map declares local 0 as top, so it can't be read anymore
+        assert_eq!(None, frame.locals.types[0]);
+        frame.locals.set_int(1, 2);
+        frame.check_stack_map(&expected);
+    }
+}
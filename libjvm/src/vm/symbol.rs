@@ -0,0 +1,146 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// An interned string, such as a class name, method name or descriptor.
+///
+/// Symbols obtained from the same [`SymbolTable`] are equal if and only if
+/// they point to the same allocation, so comparing two symbols is a pointer
+/// comparison instead of a byte-wise one.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+/// Hashes the content (not the pointer), so that maps keyed by [`Symbol`] can
+/// be queried with a plain `&str` through [`Borrow`]. For symbols of the same
+/// table, content equality and pointer equality are the same.
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+/// The VM-wide table of interned [`Symbol`]s.
+#[derive(Default)]
+pub struct SymbolTable {
+    symbols: Mutex<HashSet<Arc<str>>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol for the given string, creating it if it doesn't
+    /// exist yet.
+    pub fn intern(&self, s: &str) -> Symbol {
+        let mut symbols = self.symbols.lock().unwrap();
+        if let Some(existing) = symbols.get(s) {
+            return Symbol(existing.clone());
+        }
+        let symbol: Arc<str> = Arc::from(s);
+        symbols.insert(symbol.clone());
+        Symbol(symbol)
+    }
+
+    /// Returns the symbol for the given string, if it has been interned before.
+    /// Unlike [`SymbolTable::intern`], this never grows the table, which makes
+    /// it suitable for lookups with untrusted names.
+    pub fn lookup(&self, s: &str) -> Option<Symbol> {
+        self.symbols.lock().unwrap().get(s).cloned().map(Symbol)
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_same_pointer() {
+        let table = SymbolTable::new();
+        let a = table.intern("java/lang/Object");
+        let b = table.intern(&String::from("java/lang/Object"));
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.as_str(), b.as_str()));
+        assert_eq!(1, table.len());
+    }
+
+    #[test]
+    fn test_different_tables_not_equal() {
+        let a = SymbolTable::new().intern("main");
+        let b = SymbolTable::new().intern("main");
+        assert_ne!(a, b);
+        assert_eq!(a, "main");
+    }
+
+    #[test]
+    fn test_lookup_does_not_intern() {
+        let table = SymbolTable::new();
+        assert!(table.lookup("main").is_none());
+        assert!(table.is_empty());
+        let main = table.intern("main");
+        assert_eq!(Some(main), table.lookup("main"));
+    }
+}
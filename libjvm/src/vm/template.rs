@@ -0,0 +1,209 @@
+use std::rc::Rc;
+
+use crate::vm::classloader::class::{Class, InitState};
+use crate::vm::classloader::ClassLoader;
+use crate::vm::error::VmError;
+use crate::vm::module::ModuleMode;
+use crate::vm::VM;
+
+/// A VM whose classes were loaded and initialized once, which VMs are forked
+/// from, e.g. one per test of a large test suite, so that the tests don't have
+/// to load the same boot classes again.
+///
+/// A fork shares the loaded classes of the template (their parsed class files,
+/// method tables and field layouts) and its symbol and descriptor tables,
+/// which are never changed once the template is built. Everything a fork can
+/// change is its own: the classes it loads itself, its heap, which starts as a
+/// copy of the heap of the template, and its [`Runtime`](crate::vm::runtime::Runtime).
+/// So forks don't see each other's classes or objects, like separate VMs.
+///
+/// The initialization state of a class is part of the shared class, so every
+/// class of the template has to be initialized (or have failed to initialize)
+/// before it can be shared, see [`VmTemplate::new`].
+///
+/// The classes are reference counted without synchronization, so a template
+/// and its forks stay on the thread that built the template. A test suite
+/// keeps one template per thread, e.g. in a `thread_local!`.
+pub struct VmTemplate {
+    vm: VM,
+}
+
+impl VmTemplate {
+    /// Loads and initializes the classes with the given binary names in the
+    /// given VM, like [`VM::dump_snapshot`], and makes it a template. Fails
+    /// with the error of the first class that can't be loaded or initialized.
+    ///
+    /// Loading a class loads its superinterfaces, which initializing it
+    /// doesn't initialize, and the VM may have loaded classes without
+    /// initializing them, e.g. to box a value. Those without a static
+    /// initializer are initialized here, which has no effect other than their
+    /// state. Fails with an `InternalError` if any other loaded class is not
+    /// initialized, since forks could observe each other initializing it.
+    /// Such a class has to be in the class list.
+    pub fn new<'a>(
+        mut vm: VM,
+        class_list: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, VmError> {
+        for name in class_list {
+            let class = vm.bootstrap_class_loader.find_or_load_class(name)?;
+            vm.initialize(&class)?;
+        }
+
+        let linked = vm
+            .bootstrap_class_loader
+            .loaded_classes()
+            .iter()
+            .filter(|class| class.init_state() == InitState::Linked)
+            .cloned()
+            .collect::<Vec<Rc<Class>>>();
+        for class in linked {
+            if class.has_static_initializer() {
+                return Err(VmError::Internal(format!(
+                    "{} is loaded but not initialized, so it can't be shared",
+                    class.name().as_str().replace('/', ".")
+                )));
+            }
+            vm.initialize(&class)?;
+        }
+        Ok(Self { vm })
+    }
+
+    /// The classes of this template, which every fork shares.
+    pub fn classes(&self) -> &[Rc<Class>] {
+        self.vm.bootstrap_class_loader.loaded_classes()
+    }
+
+    /// Creates a VM with the class path, file system, module mode and preview
+    /// setting of this template, that shares the classes of this template and starts with a
+    /// copy of its heap. Classes that are not in the template are loaded by
+    /// the fork as usual, and only it sees them.
+    pub fn fork(&self) -> VM {
+        let template = &self.vm;
+        let method_area = template.method_area.read().unwrap().fork();
+        let mut vm = VM::with_method_area(
+            template.runtime.file_system().clone(),
+            template.bootstrap_class_loader.class_path().clone(),
+            method_area,
+        );
+        let classes = template.bootstrap_class_loader.loaded_classes();
+        vm.bootstrap_class_loader.share_classes(classes);
+        for class in classes {
+            vm.runtime.add_instance_layout(class);
+        }
+        let (mode, layer) = (
+            template.runtime.module_mode(),
+            template.runtime.boot_layer(),
+        );
+        vm.bootstrap_class_loader
+            .set_module_layer((mode == ModuleMode::Strict).then(|| layer.clone()));
+        vm.runtime.set_boot_layer(mode, layer);
+        vm.bootstrap_class_loader
+            .set_preview_enabled(template.bootstrap_class_loader.is_preview_enabled());
+
+        *vm.heap.write().unwrap() = template.heap.read().unwrap().clone();
+        vm.primitive_classes = template.primitive_classes.clone();
+        vm.method_types = template.method_types.clone();
+        vm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+    use crate::vm::types::NativeValue;
+    use libvfs::FileSystem;
+
+    fn new_vm(dirs: &[&str]) -> VM {
+        VM::new(
+            FileSystem::new_os_fs(),
+            ClassPath::from(
+                dirs.iter()
+                    .map(|dir| ClassPathEntry::Dir(dir.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut vm = new_vm(&[
+            "tests/resources/vm/devirt",
+            "tests/resources/vm/classloader",
+        ]);
+        let boxed = vm.box_value(NativeValue::Integer(42)).unwrap();
+        let template = VmTemplate::new(vm, ["Circle"]).unwrap();
+        let circle = template
+            .classes()
+            .iter()
+            .find(|class| *class.name() == "Circle")
+            .unwrap()
+            .clone();
+        assert!(template
+            .classes()
+            .iter()
+            .all(|class| class.init_state() == InitState::Initialized));
+
+        let mut first = template.fork();
+        let mut second = template.fork();
+        for vm in [&mut first, &mut second] {
+            assert!(Rc::ptr_eq(&circle, &vm.for_name("Circle").unwrap()));
+            assert_eq!(
+                Ok(NativeValue::Integer(42)),
+                vm.unbox(boxed),
+                "the heap is copied"
+            );
+        }
+
+        // what a fork loads and allocates is its own
+        let square = first.for_name("Square").unwrap();
+        first.initialize(&square).unwrap();
+        first.box_value(NativeValue::Integer(7)).unwrap();
+        let other = second.for_name("Square").unwrap();
+        assert!(!Rc::ptr_eq(&square, &other));
+        assert_eq!(InitState::Linked, other.init_state());
+        assert_eq!(1, second.heap().read().unwrap().len());
+        assert_eq!(2, first.heap().read().unwrap().len());
+        assert!(template
+            .classes()
+            .iter()
+            .all(|class| *class.name() != "Square"));
+        assert!(square.is_subclass_of(&circle.interfaces()[0]));
+    }
+
+    #[test]
+    fn test_uninitialized_classes() {
+        // the superclass has a static initializer, which can't run yet
+        let vm = new_vm(&["tests/resources/vm/init", "tests/resources/vm/classloader"]);
+        assert_eq!(
+            Some(VmError::Internal(
+                "can't run the static initializer of BaseConfig".to_string()
+            )),
+            VmTemplate::new(vm, ["Config"]).err()
+        );
+
+        // an interface without a static initializer is initialized anyway
+        let vm = new_vm(&[
+            "tests/resources/vm/template",
+            "tests/resources/vm/classloader",
+        ]);
+        let template = VmTemplate::new(vm, ["Simple"]).unwrap();
+        let marked = template
+            .classes()
+            .iter()
+            .find(|class| *class.name() == "Marked")
+            .unwrap();
+        assert_eq!(InitState::Initialized, marked.init_state());
+
+        let vm = new_vm(&[
+            "tests/resources/vm/template",
+            "tests/resources/vm/classloader",
+        ]);
+        assert_eq!(
+            Some(VmError::Internal(
+                "Tag is loaded but not initialized, so it can't be shared".to_string()
+            )),
+            VmTemplate::new(vm, ["Tagged"]).err()
+        );
+    }
+}
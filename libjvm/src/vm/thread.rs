@@ -1,6 +1,42 @@
-use crate::vm::stack::{OperandStack, Stack};
-use crate::vm::types::NativeValue::*;
+use crate::vm::classloader::class::Class;
+use crate::vm::classloader::resolve::ResolvedMethod;
+use crate::vm::code::{Code, Instruction};
+use crate::vm::error::VmError;
+use crate::vm::events::EventKind;
+use crate::vm::group::{ThreadGroup, UncaughtHandler};
+use crate::vm::heap::handle::{Local, LocalHandles};
+use crate::vm::heap::{HeapObject, NULL};
+use crate::vm::native;
+use crate::vm::runtime::Runtime;
+use crate::vm::scheduler::{SchedulerMode, YIELD_QUANTUM};
+use crate::vm::stack::{Locals, OperandStack, Stack};
+use crate::vm::threads::ThreadStats;
 use libjava::bytecode::Op;
+use std::cmp::Ordering;
+#[cfg(any(debug_assertions, feature = "stack-guards"))]
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The reason why a thread stops executing before its method completes.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Unwind {
+    /// The VM is terminating, see [`Shutdown`](crate::vm::shutdown::Shutdown).
+    Shutdown,
+    /// A throwable was thrown and not caught in the method.
+    Throw(VmError),
+}
+
+impl From<VmError> for Unwind {
+    fn from(e: VmError) -> Self {
+        Unwind::Throw(e)
+    }
+}
+
+/// The debugger that is notified when a thread reaches a breakpoint, with the
+/// thread and the offset of the breakpoint in the bytecode of the method on
+/// top of its stack, see [`Runtime::set_breakpoint_handler`].
+pub type BreakpointHandler = Arc<dyn Fn(&Thread, usize) + Send + Sync>;
 
 pub struct Thread {
     /// The pc register of this thread. As per [`$2.5.1`], this
@@ -12,53 +48,468 @@ pub struct Thread {
     ///
     /// [`$2.5.2`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-2.html#jvms-2.5.2
     stack: Stack,
+    /// The shared state of the VM that this thread belongs to.
+    runtime: Arc<Runtime>,
+    /// The handles to heap objects that the natives on this thread use.
+    handles: LocalHandles,
+    name: String,
+    group: Arc<ThreadGroup>,
+    /// Like `Thread.setUncaughtExceptionHandler`.
+    uncaught_handler: Option<UncaughtHandler>,
+    /// The id in the [`ThreadRegistry`](crate::vm::threads::ThreadRegistry)
+    /// of the runtime, like `Thread.getId`.
+    id: i64,
+    stats: Arc<ThreadStats>,
+    /// The time of the clock when this thread started running Java code, or
+    /// [`None`] if it is not running, or blocked or waiting.
+    running_since: Option<i64>,
+    /// Whether this is a green thread, see [`Thread::should_yield`].
+    green: bool,
+    safepoints_until_yield: u32,
 }
 
 impl Thread {
-    pub fn new() -> Self {
+    /// Creates the main thread of the VM, in the main thread group.
+    pub fn new(runtime: Arc<Runtime>) -> Self {
+        let group = runtime.main_thread_group().clone();
+        Self::new_in_group(runtime, "main", group)
+    }
+
+    pub fn new_in_group(runtime: Arc<Runtime>, name: &str, group: Arc<ThreadGroup>) -> Self {
+        let (id, stats) = runtime.threads().register(name);
+        let green = matches!(runtime.scheduler_mode(), SchedulerMode::Green { .. });
+        let stack = Stack::allocate(10);
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        let stack = {
+            let mut stack = stack;
+            stack.set_guarded(runtime.stack_guards());
+            stack
+        };
         Self {
             pc: 0,
-            stack: Stack::allocate(10),
+            stack,
+            runtime,
+            handles: LocalHandles::new(),
+            name: name.to_string(),
+            group,
+            uncaught_handler: None,
+            id,
+            stats,
+            running_since: None,
+            green,
+            safepoints_until_yield: YIELD_QUANTUM,
+        }
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn group(&self) -> &Arc<ThreadGroup> {
+        &self.group
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// The CPU time and contention statistics of this thread, see
+    /// [`ThreadRegistry`](crate::vm::threads::ThreadRegistry).
+    pub fn stats(&self) -> &Arc<ThreadStats> {
+        &self.stats
+    }
+
+    /// Runs the given function, which blocks until this thread acquires a
+    /// contended monitor, and records it in the statistics of this thread.
+    pub fn blocked<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let (result, nanos) = self.off_cpu(f);
+        self.stats.add_blocked(nanos);
+        result
+    }
+
+    /// Runs the given function, which waits, e.g. for a notification in
+    /// `Object.wait` or for a timeout in `Thread.sleep`, and records it in
+    /// the statistics of this thread.
+    pub fn waiting<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let (result, nanos) = self.off_cpu(f);
+        self.stats.add_waited(nanos);
+        result
+    }
+
+    /// Runs the given function without counting it as CPU time. Returns its
+    /// result, and how long it took if contention monitoring is enabled.
+    fn off_cpu<R>(&mut self, f: impl FnOnce() -> R) -> (R, Option<u64>) {
+        let was_running = self.running_since.is_some();
+        self.stop_running();
+        let clock = self.runtime.clock();
+        let start = clock.nano_time();
+        let result = f();
+        let end = clock.nano_time();
+        if was_running {
+            self.running_since = Some(end);
         }
+        let monitored = self.runtime.threads().is_contention_monitoring_enabled();
+        (result, monitored.then(|| end.saturating_sub(start) as u64))
     }
 
-    pub fn run_method(&mut self, _class_name: &'static str, _method_name: &'static str) {}
+    fn start_running(&mut self) -> bool {
+        if self.running_since.is_some() {
+            return false;
+        }
+        self.running_since = Some(self.runtime.clock().nano_time());
+        true
+    }
+
+    fn stop_running(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            let now = self.runtime.clock().nano_time();
+            self.stats.add_cpu_time(now.saturating_sub(since) as u64);
+        }
+    }
+
+    pub fn set_uncaught_handler(&mut self, handler: Option<UncaughtHandler>) {
+        self.uncaught_handler = handler;
+    }
+
+    /// Handles a throwable that terminated this thread, like
+    /// `Thread.dispatchUncaughtException`: the handler of the thread, or else
+    /// the thread group (see [`ThreadGroup::uncaught_exception`]) handles it.
+    /// If there is no handler at all, the throwable is printed to the standard
+    /// error of the host.
+    pub fn dispatch_uncaught_exception(&self, error: &VmError) {
+        if let Some(handler) = &self.uncaught_handler {
+            return handler(&self.name, error);
+        }
+        let default_handler = self.runtime.default_uncaught_handler();
+        if !self
+            .group
+            .uncaught_exception(&self.name, error, default_handler.as_ref())
+        {
+            eprintln!("Exception in thread \"{}\" {}", self.name, error);
+        }
+    }
+
+    /// Runs the given function in a new local handle scope. All local handles
+    /// that are created in it are released when it returns, also if it returns
+    /// an error.
+    pub fn local_scope<R>(&mut self, f: impl FnOnce(&mut Thread) -> R) -> R {
+        self.handles.push_scope();
+        let result = f(self);
+        self.handles.pop_scope();
+        result
+    }
+
+    /// Creates a handle to the given object in the innermost local scope, see
+    /// [`Thread::local_scope`].
+    pub fn new_local(&mut self, reference: usize) -> Local {
+        self.handles.create(reference)
+    }
+
+    /// Returns the current reference of the object that the handle refers to.
+    pub fn resolve_local(&self, handle: Local) -> Result<usize, VmError> {
+        self.handles.resolve(handle)
+    }
+
+    pub fn local_handles_mut(&mut self) -> &mut LocalHandles {
+        &mut self.handles
+    }
+
+    /// Returns the class of the method that called the current method, which
+    /// must be caller-sensitive, like `Reflection.getCallerClass`. Frames of
+    /// hidden methods and of the reflection machinery are skipped, so that a
+    /// method that is invoked reflectively or through a method handle sees its
+    /// actual caller.
+    ///
+    /// Returns [`None`] if there is no caller, i.e. the method was invoked by
+    /// the embedder. Fails if the current method is not caller-sensitive.
+    pub fn caller_class(&self) -> Result<Option<Rc<Class>>, VmError> {
+        let mut frames = self
+            .stack
+            .frames()
+            .iter()
+            .rev()
+            .filter_map(|f| f.method.as_ref());
+        match frames.next() {
+            Some(current) if current.method().is_caller_sensitive() => {}
+            current => {
+                return Err(VmError::Internal(format!(
+                    "CallerSensitive annotation expected at {:?}",
+                    current
+                )))
+            }
+        }
+        Ok(frames
+            .find(|m| !is_ignored_by_stack_walk(m))
+            .map(|m| m.class().clone()))
+    }
+
+    /// Describes the frames of the Java stack of this thread, from the
+    /// innermost to the outermost, e.g. `java.lang.Integer.parseInt(Ljava/lang/String;)I`.
+    pub fn java_stack(&self) -> Vec<String> {
+        self.stack
+            .frames()
+            .iter()
+            .rev()
+            .map(|f| match &f.method {
+                Some(m) => format!(
+                    "{}.{}{}",
+                    m.class().name().as_str().replace('/', "."),
+                    m.method().name(),
+                    m.method().descriptor()
+                ),
+                None => "<unknown method>".to_string(),
+            })
+            .collect()
+    }
 
-    fn execute(&mut self) {
-        loop {
-            self.pc += 1;
+    /// Checks whether this thread has to stop executing, e.g. because another
+    /// thread requested the VM to exit.
+    pub fn safepoint(&self) -> Result<(), Unwind> {
+        if self.runtime.shutdown().is_requested() {
+            Err(Unwind::Shutdown)
+        } else {
+            Ok(())
         }
-        /*
-           do {
-               atomically calculate pc and fetch opcode at pc;
-               if (operands) fetch operands;
-               execute the action for the opcode;
-           } while (there is more to do);
-        */
     }
 
-    fn evaluate(&mut self, op: Op) {
+    /// Whether this thread should return to the green scheduler at the
+    /// current safepoint, so that other green threads get to run. Green
+    /// threads yield after every [`YIELD_QUANTUM`] safepoints, native threads
+    /// never do.
+    pub fn should_yield(&mut self) -> bool {
+        if !self.green {
+            return false;
+        }
+        self.safepoints_until_yield -= 1;
+        if self.safepoints_until_yield == 0 {
+            self.safepoints_until_yield = YIELD_QUANTUM;
+            return true;
+        }
+        false
+    }
+
+    pub fn run_method(
+        &mut self,
+        _class_name: &'static str,
+        _method_name: &'static str,
+    ) -> Result<(), Unwind> {
+        // only the outermost invocation measures the CPU time
+        let outermost = self.start_running();
+        if outermost {
+            self.stop_running();
+        }
+        Ok(())
+    }
+
+    /// Interprets the given code of the method in the frame on top of the
+    /// stack, from its first instruction until it returns or runs past its
+    /// end. Every instruction is a safepoint.
+    pub fn execute(&mut self, code: &Code) -> Result<(), Unwind> {
+        let mut index = 0;
+        while index < code.instructions().len() {
+            self.safepoint()?;
+            match self.execute_at(code, index)? {
+                Some(next) => index = next,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes the instruction at the given index of the code of the current
+    /// method, and returns the index of the instruction to execute next, or
+    /// [`None`] if the method returns. If a breakpoint was patched into the
+    /// instruction, this notifies the breakpoint handler of the runtime and
+    /// then executes the original instruction from the trap table of the
+    /// code.
+    fn execute_at(&mut self, code: &Code, index: usize) -> Result<Option<usize>, VmError> {
+        self.pc = code.offset(index);
+        let instruction = match (&code.instructions()[index], code.trapped(index)) {
+            (Instruction::Op(Op::Breakpoint), Some(original)) => {
+                if let Some(handler) = self.runtime.breakpoint_handler() {
+                    handler(self, self.pc);
+                }
+                original.clone()
+            }
+            (instruction, _) => instruction.clone(),
+        };
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.stack.is_guarded() {
+            if let Some(expected) = code.stack_map().and_then(|map| map.at(self.pc)) {
+                self.guarded(|t| t.stack.current_frame_mut().check_stack_map(expected));
+            }
+        }
+        let falls_through = match &instruction {
+            Instruction::Op(op) => op.falls_through(),
+            _ => true,
+        };
+        match self.dispatch(instruction)? {
+            Some(target) => code.index_of(target).map(Some).ok_or_else(|| {
+                VmError::Internal(format!(
+                    "no instruction starts at the branch target {}",
+                    target
+                ))
+            }),
+            None if falls_through => Ok(Some(index + 1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Executes the instruction, and returns the offset that it jumps to, or
+    /// [`None`] if it continues with the next instruction or returns.
+    fn dispatch(&mut self, instruction: Instruction) -> Result<Option<usize>, VmError> {
+        #[cfg(any(debug_assertions, feature = "stack-guards"))]
+        if self.stack.is_guarded() {
+            return self
+                .guarded(|t| t.execute_instruction(instruction))
+                .inspect_err(|e| self.exception_thrown(e));
+        }
+        self.execute_instruction(instruction)
+            .inspect_err(|e| self.exception_thrown(e))
+    }
+
+    /// Runs the given function, and adds the method and pc to the message if
+    /// it panics, e.g. because an instruction found a value of the wrong type
+    /// on the operand stack, see [`Runtime::set_stack_guards`].
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    fn guarded<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<String>()
+                    .map(String::as_str)
+                    .or_else(|| payload.downcast_ref::<&str>().copied())
+                    .unwrap_or("panicked");
+                let method = self.java_stack().into_iter().next().unwrap_or_default();
+                panic!("{} at pc {} in {}", message, self.pc, method)
+            }
+        }
+    }
+
+    /// Records a throwable that was thrown on this thread in the event log of
+    /// the runtime.
+    pub fn exception_thrown(&self, error: &VmError) {
+        self.runtime.log_event(
+            EventKind::Exception,
+            format!("Exception <{}> thrown in thread \"{}\"", error, self.name),
+        );
+    }
+
+    fn execute_instruction(&mut self, instruction: Instruction) -> Result<Option<usize>, VmError> {
+        match instruction {
+            Instruction::Op(op) if op.ends_block() => return self.jump(op),
+            Instruction::Op(op) => self.evaluate(op)?,
+            Instruction::ALoad0GetField(index) => {
+                self.aload(0);
+                self.evaluate(Op::GetField(index))?;
+            }
+            Instruction::ILoadILoadIAdd(a, b) => self.iload_iload_iadd(a, b),
+            Instruction::IConstIStore(value, index) => {
+                self.locals_mut().set_int(index as usize, value)
+            }
+            Instruction::UncheckedArrayLoad(array, index, _) => {
+                self.unchecked_array_load(array, index)?
+            }
+        }
+        Ok(None)
+    }
+
+    /// Evaluates an op that may continue somewhere else than at the next
+    /// instruction, and returns the offset that it jumps to, or [`None`] if
+    /// it falls through or returns.
+    fn jump(&mut self, op: Op) -> Result<Option<usize>, VmError> {
+        let taken = match &op {
+            Op::Goto(_) | Op::GotoW(_) => true,
+            Op::IfEq(_) | Op::IfNe(_) | Op::IfLt(_) | Op::IfGe(_) | Op::IfGt(_) | Op::IfLe(_) => {
+                let value = self.operand_stack_mut().pop_int();
+                compare(&op, value.cmp(&0))
+            }
+            Op::IfICmpEq(_)
+            | Op::IfICmpNe(_)
+            | Op::IfICmpLt(_)
+            | Op::IfICmpGe(_)
+            | Op::IfICmpGt(_)
+            | Op::IfICmpLe(_) => {
+                let stack = self.operand_stack_mut();
+                let op2 = stack.pop_int();
+                let op1 = stack.pop_int();
+                compare(&op, op1.cmp(&op2))
+            }
+            Op::IfACmpEq(_) | Op::IfACmpNe(_) => {
+                let stack = self.operand_stack_mut();
+                let op2 = stack.pop_reference();
+                let op1 = stack.pop_reference();
+                (op1 == op2) == matches!(op, Op::IfACmpEq(_))
+            }
+            Op::IfNull(_) | Op::IfNonNull(_) => {
+                let value = self.operand_stack_mut().pop_reference();
+                (value == NULL) == matches!(op, Op::IfNull(_))
+            }
+            Op::TableSwitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                let key = self.operand_stack_mut().pop_int();
+                let offset = if (*low..=*high).contains(&key) {
+                    offsets[(key - low) as usize]
+                } else {
+                    *default
+                };
+                return Ok(self.pc.checked_add_signed(offset as isize));
+            }
+            Op::LookupSwitch { default, npairs } => {
+                let key = self.operand_stack_mut().pop_int();
+                let offset = npairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map_or(*default as i32, |(_, offset)| *offset);
+                return Ok(self.pc.checked_add_signed(offset as isize));
+            }
+            Op::Return | Op::AReturn | Op::IReturn | Op::LReturn | Op::FReturn | Op::DReturn => {
+                return Ok(None)
+            }
+            op => {
+                return Err(VmError::Internal(format!(
+                    "{} is not supported by the interpreter yet",
+                    op.mnemonic()
+                )))
+            }
+        };
+        if !taken {
+            return Ok(None);
+        }
+        Ok(op.branch_targets(self.pc).first().copied())
+    }
+
+    fn evaluate(&mut self, op: Op) -> Result<(), VmError> {
         match op {
-            Op::AALoad => {}
+            Op::AALoad => self.array_load()?,
             Op::AAStore => {}
             Op::AConstNull => self.a_const_null(),
-            Op::ALoad(_) => {}
+            Op::ALoad(index) => self.aload(index),
             Op::ANewArray(_) => {}
             Op::AReturn => {}
-            Op::ArrayLength => {}
+            Op::ArrayLength => self.array_length()?,
             Op::AStore(_) => {}
             Op::AThrow => {}
-            Op::BALoad => {}
+            Op::BALoad => self.array_load()?,
             Op::BAStore => {}
-            Op::BIPush(_) => {}
-            Op::CALoad => {}
+            Op::BIPush(value) => self.iconst(value as i32),
+            Op::CALoad => self.array_load()?,
             Op::CAStore => {}
-            Op::CheckCast(index) => self.check_cast(index),
+            Op::CheckCast(index) => self.check_cast(index)?,
             Op::D2F => {}
             Op::D2I => {}
             Op::D2L => {}
             Op::DAdd => {}
-            Op::DALoad => {}
+            Op::DALoad => self.array_load()?,
             Op::DAStore => {}
             Op::DCmpG => {}
             Op::DCmpL => {}
@@ -86,7 +537,7 @@ impl Thread {
             Op::F2I => {}
             Op::F2L => {}
             Op::FAdd => {}
-            Op::FALoad => {}
+            Op::FALoad => self.array_load()?,
             Op::FAStore => {}
             Op::FCmpG => {}
             Op::FCmpL => {}
@@ -112,16 +563,16 @@ impl Thread {
             Op::I2L => self.i2l(),
             Op::I2S => self.i2s(),
             Op::IAdd => self.iadd(),
-            Op::IALoad => {}
+            Op::IALoad => self.array_load()?,
             Op::IAnd => {}
             Op::IAStore => {}
-            Op::IConstM1 => {}
-            Op::IConst0 => {}
-            Op::IConst1 => {}
-            Op::IConst2 => {}
-            Op::IConst3 => {}
-            Op::IConst4 => {}
-            Op::IConst5 => {}
+            Op::IConstM1 => self.iconst(-1),
+            Op::IConst0 => self.iconst(0),
+            Op::IConst1 => self.iconst(1),
+            Op::IConst2 => self.iconst(2),
+            Op::IConst3 => self.iconst(3),
+            Op::IConst4 => self.iconst(4),
+            Op::IConst5 => self.iconst(5),
             Op::IDiv => {}
             Op::IfACmpEq(_) => {}
             Op::IfACmpNe(_) => {}
@@ -139,8 +590,8 @@ impl Thread {
             Op::IfLe(_) => {}
             Op::IfNonNull(_) => {}
             Op::IfNull(_) => {}
-            Op::IInc(_, _) => {}
-            Op::ILoad(_) => {}
+            Op::IInc(index, value) => self.iinc(index, value),
+            Op::ILoad(index) => self.iload(index),
             Op::IMul => self.imul(),
             Op::INeg => {}
             Op::InstanceOf(_) => {}
@@ -154,7 +605,7 @@ impl Thread {
             Op::IReturn => {}
             Op::IShl => {}
             Op::IShr => {}
-            Op::IStore(_) => {}
+            Op::IStore(index) => self.istore(index),
             Op::ISub => {}
             Op::IUShr => {}
             Op::IXor => {}
@@ -164,7 +615,7 @@ impl Thread {
             Op::L2F => {}
             Op::L2I => {}
             Op::LAdd => {}
-            Op::LALoad => {}
+            Op::LALoad => self.array_load()?,
             Op::LAnd => {}
             Op::LAStore => {}
             Op::LCmp => {}
@@ -199,102 +650,222 @@ impl Thread {
             Op::PutStatic(_) => {}
             Op::Ret(_) => {}
             Op::Return => {}
-            Op::SALoad => {}
+            Op::SALoad => self.array_load()?,
             Op::SAStore => {}
-            Op::SIPush(_) => {}
+            Op::SIPush(value) => self.iconst(value as i16 as i32),
             Op::Swap => {}
             Op::TableSwitch { .. } => {}
             Op::Wide => {}
             Op::Breakpoint => {}
         }
+        Ok(())
     }
 
     fn operand_stack_mut(&mut self) -> &mut OperandStack {
         &mut self.stack.current_frame_mut().operand_stack
     }
 
+    fn locals_mut(&mut self) -> &mut Locals {
+        &mut self.stack.current_frame_mut().locals
+    }
+
     fn a_const_null(&mut self) {
         let stack = self.operand_stack_mut();
-        stack.push(Reference(0));
+        stack.push_reference(0);
     }
 
-    fn check_cast(&mut self, _index: u16) {
+    fn aload(&mut self, index: u8) {
+        let frame = self.stack.current_frame_mut();
+        let v = frame.locals.get_reference(index as usize);
+        frame.operand_stack.push_reference(v);
+    }
+
+    /// Pushes the element of an array, e.g. for `iaload`, failing with a
+    /// `NullPointerException` for `null` and an
+    /// `ArrayIndexOutOfBoundsException` for an index out of bounds.
+    fn array_load(&mut self) -> Result<(), VmError> {
         let stack = self.operand_stack_mut();
-        if let Reference(r) = *stack.last() {
-            if r == 0 {
-                return;
+        let index = stack.pop_int();
+        let array = stack.pop_reference();
+        let value = native::array(&self.runtime.heap().read().unwrap(), array)?.element(index)?;
+        self.operand_stack_mut().push(value);
+        Ok(())
+    }
+
+    /// The [`Instruction::UncheckedArrayLoad`] of a loop whose header already
+    /// compared the index to the length of the array, so that the array is
+    /// not `null` and the index is within its bounds, and neither is checked
+    /// again.
+    fn unchecked_array_load(&mut self, array: u8, index: u8) -> Result<(), VmError> {
+        let locals = self.locals_mut();
+        let array = locals.get_reference(array as usize);
+        let index = locals.get_int(index as usize) as usize;
+        let heap = self.runtime.heap().read().unwrap();
+        let value = match heap.get(array).and_then(HeapObject::as_array) {
+            Some(array) => array.element_unchecked(index),
+            None => {
+                return Err(VmError::Internal(format!(
+                    "{:#x} is not a reference to an array",
+                    array
+                )))
             }
+        };
+        drop(heap);
+        self.operand_stack_mut().push(value);
+        Ok(())
+    }
+
+    fn array_length(&mut self) -> Result<(), VmError> {
+        let array = self.operand_stack_mut().pop_reference();
+        let length = native::array(&self.runtime.heap().read().unwrap(), array)?.len();
+        self.operand_stack_mut().push_int(length as i32);
+        Ok(())
+    }
+
+    fn check_cast(&mut self, _index: u16) -> Result<(), VmError> {
+        let stack = self.operand_stack_mut();
+        if stack.peek_reference() == 0 {
+            return Ok(());
         }
 
         let _reference = stack.pop_reference();
-        todo!("cast")
+        Err(VmError::Internal(
+            "checkcast is not implemented for non-null references".into(),
+        ))
     }
 
     fn dup(&mut self) {
         let stack = self.operand_stack_mut();
-        let dup = stack.last().clone();
-        stack.push(dup);
+        stack.dup();
     }
 
     fn i2b(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Byte(v as i8));
+        let v = stack.pop_int();
+        stack.push_int(v as i8 as i32);
     }
 
     fn i2c(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Char(v as u16));
+        let v = stack.pop_int();
+        stack.push_int(v as u16 as i32);
     }
 
     fn i2d(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Double(v as f64));
+        let v = stack.pop_int();
+        stack.push_double(v as f64);
     }
 
     fn i2f(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Float(v as f32));
+        let v = stack.pop_int();
+        stack.push_float(v as f32);
     }
 
     fn i2l(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Long(v as i64));
+        let v = stack.pop_int();
+        stack.push_long(v as i64);
     }
 
     fn i2s(&mut self) {
         let stack = self.operand_stack_mut();
-        let v = stack.pop_integer();
-        stack.push(Short(v as i16));
+        let v = stack.pop_int();
+        stack.push_int(v as i16 as i32);
     }
 
     fn iadd(&mut self) {
         let stack = self.operand_stack_mut();
-        let op2 = stack.pop_integer();
-        let op1 = stack.pop_integer();
-        stack.push(Integer(op1.wrapping_add(op2)));
+        let op2 = stack.pop_int();
+        let op1 = stack.pop_int();
+        stack.push_int(op1.wrapping_add(op2));
+    }
+
+    fn iconst(&mut self, value: i32) {
+        self.operand_stack_mut().push_int(value);
+    }
+
+    fn iinc(&mut self, index: u8, value: i8) {
+        let locals = self.locals_mut();
+        let v = locals.get_int(index as usize);
+        locals.set_int(index as usize, v.wrapping_add(value as i32));
+    }
+
+    fn iload(&mut self, index: u8) {
+        let frame = self.stack.current_frame_mut();
+        let v = frame.locals.get_int(index as usize);
+        frame.operand_stack.push_int(v);
+    }
+
+    fn iload_iload_iadd(&mut self, a: u8, b: u8) {
+        let frame = self.stack.current_frame_mut();
+        let op1 = frame.locals.get_int(a as usize);
+        let op2 = frame.locals.get_int(b as usize);
+        frame.operand_stack.push_int(op1.wrapping_add(op2));
+    }
+
+    fn istore(&mut self, index: u8) {
+        let frame = self.stack.current_frame_mut();
+        let v = frame.operand_stack.pop_int();
+        frame.locals.set_int(index as usize, v);
     }
 
     fn imul(&mut self) {
         let stack = self.operand_stack_mut();
-        let op2 = stack.pop_integer();
-        let op1 = stack.pop_integer();
-        stack.push(Integer(op1.wrapping_mul(op2)));
+        let op2 = stack.pop_int();
+        let op1 = stack.pop_int();
+        stack.push_int(op1.wrapping_mul(op2));
+    }
+}
+
+/// Whether the conditional branch jumps, by the ordering of its first operand
+/// and its second one, or zero for the branches with one operand.
+fn compare(op: &Op, ordering: Ordering) -> bool {
+    match op {
+        Op::IfEq(_) | Op::IfICmpEq(_) => ordering.is_eq(),
+        Op::IfNe(_) | Op::IfICmpNe(_) => ordering.is_ne(),
+        Op::IfLt(_) | Op::IfICmpLt(_) => ordering.is_lt(),
+        Op::IfGe(_) | Op::IfICmpGe(_) => ordering.is_ge(),
+        Op::IfGt(_) | Op::IfICmpGt(_) => ordering.is_gt(),
+        Op::IfLe(_) | Op::IfICmpLe(_) => ordering.is_le(),
+        op => unreachable!("{} is not an int comparison", op.mnemonic()),
+    }
+}
+
+/// Whether frames of the given method are skipped when looking for the caller
+/// of a caller-sensitive method: hidden methods, `Method.invoke` and the
+/// generated accessors that it calls.
+fn is_ignored_by_stack_walk(method: &ResolvedMethod) -> bool {
+    let class = method.class().name();
+    method.method().is_hidden()
+        || (*class == "java/lang/reflect/Method" && *method.method().name() == "invoke")
+        || class.starts_with("jdk/internal/reflect/")
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        self.runtime.threads().unregister(self.id);
     }
 }
 
+/// Table-driven tests of the instructions against the specification.
+#[cfg(test)]
+mod conformance;
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use crate::vm::stack::Frame;
-    use libjava::classfile::{ConstantPool, ConstantPoolInfo};
+    use libjava::classfile::{
+        ConstantPool, ConstantPoolInfo, ResolvedConstant, ResolvedConstantPool,
+    };
 
     use super::*;
+    use crate::vm::descriptor::FieldType;
+    use crate::vm::error::Exception;
+    use crate::vm::types::NativeValue::*;
 
     macro_rules! setup_thread {
         ($op_stack_size:expr) => {
@@ -302,8 +873,9 @@ mod tests {
         };
 
         ($op_stack_size:expr,$cp:expr) => {{
-            let mut t = Thread::new();
-            let frame = Frame::allocate(0, $op_stack_size, Arc::new($cp));
+            let mut t = Thread::new(Arc::default());
+            let cp = Arc::new(ResolvedConstantPool::new(&$cp));
+            let frame = Frame::allocate(0, $op_stack_size, cp);
             t.stack.push_frame(frame);
             t
         }};
@@ -331,11 +903,8 @@ mod tests {
         assert_eq!(0, t.stack.current_frame_mut().locals.len());
         assert_eq!(1, t.stack.current_frame_mut().constant_pool.len());
         assert_eq!(
-            ConstantPoolInfo::Utf8Info {
-                length: 5,
-                bytes: "hello".as_bytes().into()
-            },
-            t.stack.current_frame_mut().constant_pool[0]
+            ResolvedConstant::Utf8("hello".into()),
+            *t.stack.current_frame_mut().constant_pool.get(1).unwrap()
         );
         assert_eq!(0, t.operand_stack_mut().len());
     }
@@ -344,10 +913,10 @@ mod tests {
     fn test_a_const_null() {
         let mut t = setup_thread!(1);
 
-        t.evaluate(Op::AConstNull);
+        t.evaluate(Op::AConstNull).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Reference(0), operand_stack.pop());
+        assert_eq!(0, operand_stack.pop_reference());
     }
 
     #[test]
@@ -355,11 +924,11 @@ mod tests {
         let mut t = setup_thread!(2);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::Dup);
+        t.evaluate(Op::Dup).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Integer(17), operand_stack.pop());
-        assert_eq!(Integer(17), operand_stack.pop());
+        assert_eq!(17, operand_stack.pop_int());
+        assert_eq!(17, operand_stack.pop_int());
     }
 
     #[test]
@@ -367,10 +936,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2B);
+        t.evaluate(Op::I2B).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Byte(17), operand_stack.pop());
+        assert_eq!(17, operand_stack.pop_int());
     }
 
     #[test]
@@ -378,10 +947,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(250));
-        t.evaluate(Op::I2B);
+        t.evaluate(Op::I2B).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Byte(-6), operand_stack.pop());
+        assert_eq!(-6, operand_stack.pop_int());
     }
 
     #[test]
@@ -389,10 +958,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2C);
+        t.evaluate(Op::I2C).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Char(17), operand_stack.pop());
+        assert_eq!(17, operand_stack.pop_int());
     }
 
     #[test]
@@ -400,10 +969,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(70000));
-        t.evaluate(Op::I2C);
+        t.evaluate(Op::I2C).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Char(4464), operand_stack.pop());
+        assert_eq!(4464, operand_stack.pop_int());
     }
 
     #[test]
@@ -411,10 +980,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2D);
+        t.evaluate(Op::I2D).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Double(17.0), operand_stack.pop());
+        assert_eq!(17.0, operand_stack.pop_double());
     }
 
     #[test]
@@ -422,10 +991,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(-17));
-        t.evaluate(Op::I2D);
+        t.evaluate(Op::I2D).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Double(-17.0), operand_stack.pop());
+        assert_eq!(-17.0, operand_stack.pop_double());
     }
 
     #[test]
@@ -433,10 +1002,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2F);
+        t.evaluate(Op::I2F).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Float(17.0), operand_stack.pop());
+        assert_eq!(17.0, operand_stack.pop_float());
     }
 
     #[test]
@@ -444,10 +1013,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(-17));
-        t.evaluate(Op::I2F);
+        t.evaluate(Op::I2F).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Float(-17.0), operand_stack.pop());
+        assert_eq!(-17.0, operand_stack.pop_float());
     }
 
     #[test]
@@ -455,10 +1024,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2L);
+        t.evaluate(Op::I2L).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Long(17), operand_stack.pop());
+        assert_eq!(17, operand_stack.pop_long());
     }
 
     #[test]
@@ -466,10 +1035,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(17));
-        t.evaluate(Op::I2S);
+        t.evaluate(Op::I2S).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Short(17), operand_stack.pop());
+        assert_eq!(17, operand_stack.pop_int());
     }
 
     #[test]
@@ -477,10 +1046,10 @@ mod tests {
         let mut t = setup_thread!(1);
 
         t.operand_stack_mut().push(Integer(40000));
-        t.evaluate(Op::I2S);
+        t.evaluate(Op::I2S).unwrap();
 
         let operand_stack = t.operand_stack_mut();
-        assert_eq!(Short(-25536), operand_stack.pop());
+        assert_eq!(-25536, operand_stack.pop_int());
     }
 
     #[test]
@@ -489,10 +1058,10 @@ mod tests {
 
         t.operand_stack_mut().push(Integer(9));
         t.operand_stack_mut().push(Integer(18));
-        t.evaluate(Op::IAdd);
+        t.evaluate(Op::IAdd).unwrap();
         let operand_stack = t.operand_stack_mut();
         assert_eq!(1, operand_stack.len());
-        assert_eq!(Integer(27), operand_stack.pop());
+        assert_eq!(27, operand_stack.pop_int());
     }
 
     #[test]
@@ -501,10 +1070,10 @@ mod tests {
 
         t.operand_stack_mut().push(Integer(2147483647));
         t.operand_stack_mut().push(Integer(1));
-        t.evaluate(Op::IAdd);
+        t.evaluate(Op::IAdd).unwrap();
         let operand_stack = t.operand_stack_mut();
         assert_eq!(1, operand_stack.len());
-        assert_eq!(Integer(-2147483648), operand_stack.pop());
+        assert_eq!(-2147483648, operand_stack.pop_int());
     }
 
     #[test]
@@ -513,10 +1082,10 @@ mod tests {
 
         t.operand_stack_mut().push(Integer(9));
         t.operand_stack_mut().push(Integer(18));
-        t.evaluate(Op::IMul);
+        t.evaluate(Op::IMul).unwrap();
         let operand_stack = t.operand_stack_mut();
         assert_eq!(1, operand_stack.len());
-        assert_eq!(Integer(162), operand_stack.pop());
+        assert_eq!(162, operand_stack.pop_int());
     }
 
     #[test]
@@ -525,9 +1094,351 @@ mod tests {
 
         t.operand_stack_mut().push(Integer(1763));
         t.operand_stack_mut().push(Integer(2487369));
-        t.evaluate(Op::IMul);
+        t.evaluate(Op::IMul).unwrap();
         let operand_stack = t.operand_stack_mut();
         assert_eq!(1, operand_stack.len());
-        assert_eq!(Integer(90264251), operand_stack.pop());
+        assert_eq!(90264251, operand_stack.pop_int());
+    }
+
+    #[test]
+    fn test_iload_iload_iadd_superinstruction() {
+        let mut fused = Thread::new(Arc::default());
+        let mut plain = Thread::new(Arc::default());
+        for t in [&mut fused, &mut plain] {
+            let cp = Arc::default();
+            t.stack.push_frame(Frame::allocate(3, 2, cp));
+            t.locals_mut().set_int(1, i32::MAX);
+            t.locals_mut().set_int(2, 5);
+        }
+
+        fused.dispatch(Instruction::ILoadILoadIAdd(1, 2)).unwrap();
+        for op in [Op::ILoad(1), Op::ILoad(2), Op::IAdd] {
+            plain.dispatch(Instruction::Op(op)).unwrap();
+        }
+
+        assert_eq!(1, fused.operand_stack_mut().len());
+        assert_eq!(
+            plain.operand_stack_mut().pop_int(),
+            fused.operand_stack_mut().pop_int()
+        );
+    }
+
+    #[test]
+    fn test_iconst_istore_superinstruction() {
+        let mut t = Thread::new(Arc::default());
+        let cp = Arc::default();
+        t.stack.push_frame(Frame::allocate(4, 1, cp));
+
+        t.dispatch(Instruction::IConstIStore(-300, 3)).unwrap();
+
+        assert!(t.operand_stack_mut().is_empty());
+        assert_eq!(-300, t.locals_mut().get_int(3));
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut t = setup_thread!(2);
+        let hits = Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = hits.clone();
+        t.runtime
+            .set_breakpoint_handler(Some(Arc::new(move |t: &Thread, pc| {
+                recorded.lock().unwrap().push((t.name().to_string(), pc));
+            })));
+        let bytecode = [
+            0x04, // iconst_1
+            0x05, // iconst_2
+            0xa7, 0x00, 0x04, // goto 6
+            0x06, // iconst_3
+            0x60, // iadd
+            0xb1, // return
+            0x07, // iconst_4
+        ];
+        let mut code = Code::decode(&bytecode, &[]).unwrap();
+        assert!(code.set_breakpoint(1));
+
+        t.execute(&code).unwrap();
+        assert_eq!(3, t.operand_stack_mut().pop_int());
+        assert!(t.operand_stack_mut().is_empty());
+        assert_eq!(vec![(t.name().to_string(), 1)], *hits.lock().unwrap());
+
+        // a cleared breakpoint doesn't notify the debugger anymore
+        assert!(code.clear_breakpoint(1));
+        assert_eq!(Some(2), t.execute_at(&code, 1).unwrap());
+        assert_eq!(2, t.operand_stack_mut().pop_int());
+        assert_eq!(1, hits.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_counted_loop() {
+        let mut t = Thread::new(Arc::default());
+        t.stack.push_new_frame(2, 2, Arc::default());
+        let bytecode = [
+            0x03, // iconst_0
+            0x3b, // istore_0
+            0x03, // iconst_0
+            0x3c, // istore_1
+            0x1a, // iload_0
+            0x10, 0x05, // bipush 5
+            0xa2, 0x00, 0x0d, // if_icmpge 20
+            0x1b, // iload_1
+            0x1a, // iload_0
+            0x60, // iadd
+            0x3c, // istore_1
+            0x84, 0x00, 0x01, // iinc 0 1
+            0xa7, 0xff, 0xf3, // goto 4
+            0x1b, // iload_1
+            0xac, // ireturn
+        ];
+        let code = Code::decode(&bytecode, &[]).unwrap();
+
+        t.execute(&code).unwrap();
+        assert_eq!(10, t.operand_stack_mut().pop_int());
+        assert!(t.operand_stack_mut().is_empty());
+        assert_eq!(5, t.locals_mut().get_int(0));
+    }
+
+    /// Allocates an `int[]` with the given elements.
+    fn new_int_array(t: &Thread, elements: &[i32]) -> usize {
+        let mut heap = t.runtime.heap().write().unwrap();
+        let array = heap
+            .allocate_array(Arc::new(FieldType::Int), elements.len() as i32)
+            .unwrap();
+        let target = heap.get_mut(array).and_then(HeapObject::as_array_mut);
+        let target = target.unwrap();
+        for (i, element) in elements.iter().enumerate() {
+            target.set_element(i as i32, Integer(*element)).unwrap();
+        }
+        array
+    }
+
+    #[test]
+    fn test_array_loads() {
+        let mut t = setup_thread!(2);
+        let array = new_int_array(&t, &[1, 20, 300]);
+
+        t.operand_stack_mut().push_reference(array);
+        t.dispatch(Instruction::Op(Op::ArrayLength)).unwrap();
+        assert_eq!(3, t.operand_stack_mut().pop_int());
+        t.operand_stack_mut().push_reference(array);
+        t.operand_stack_mut().push_int(2);
+        t.dispatch(Instruction::Op(Op::IALoad)).unwrap();
+        assert_eq!(300, t.operand_stack_mut().pop_int());
+
+        t.operand_stack_mut().push_reference(array);
+        t.operand_stack_mut().push_int(3);
+        assert_eq!(
+            Err(Exception::ArrayIndexOutOfBounds {
+                index: 3,
+                length: 3
+            }
+            .into()),
+            t.dispatch(Instruction::Op(Op::IALoad))
+        );
+        t.operand_stack_mut().push_reference(NULL);
+        assert_eq!(
+            Err(Exception::NullPointer.into()),
+            t.dispatch(Instruction::Op(Op::ArrayLength))
+        );
+    }
+
+    #[test]
+    fn test_unchecked_array_load() {
+        let mut t = Thread::new(Arc::default());
+        t.stack.push_new_frame(3, 2, Arc::default());
+        let array = new_int_array(&t, &[1, 20, 300]);
+        t.locals_mut().set_reference(0, array);
+        // for (int i = 0; i < a.length; i++) s += a[i];
+        let bytecode = [
+            0x03, 0x3C, // iconst_0, istore_1
+            0x03, 0x3D, // iconst_0, istore_2
+            0x1C, 0x2A, 0xBE, // iload_2, aload_0, arraylength
+            0xA2, 0x00, 0x0F, // if_icmpge +15
+            0x1B, // iload_1
+            0x2A, 0x1C, 0x2E, // aload_0, iload_2, iaload
+            0x60, 0x3C, // iadd, istore_1
+            0x84, 0x02, 0x01, // iinc 2, 1
+            0xA7, 0xFF, 0xF1, // goto -15
+            0x1B, 0xAC, // iload_1, ireturn
+        ];
+        let code = Code::decode(&bytecode, &[]).unwrap();
+        assert!(code
+            .instructions()
+            .contains(&Instruction::UncheckedArrayLoad(0, 2, Op::IALoad)));
+
+        t.execute(&code).unwrap();
+        assert_eq!(321, t.operand_stack_mut().pop_int());
+        assert!(t.operand_stack_mut().is_empty());
+    }
+
+    #[test]
+    fn test_switch() {
+        let mut t = setup_thread!(1);
+        let bytecode = [
+            0xaa, 0x00, 0x00, 0x00, // tableswitch, padded to 4
+            0x00, 0x00, 0x00, 0x1a, // default 26
+            0x00, 0x00, 0x00, 0x01, // low 1
+            0x00, 0x00, 0x00, 0x02, // high 2
+            0x00, 0x00, 0x00, 0x18, // 1: 24
+            0x00, 0x00, 0x00, 0x1a, // 2: 26
+            0x04, // iconst_1
+            0xac, // ireturn
+            0x05, // iconst_2
+            0xac, // ireturn
+        ];
+        let code = Code::decode(&bytecode, &[]).unwrap();
+        for (key, expected) in [(1, 1), (2, 2), (7, 2)] {
+            t.operand_stack_mut().push_int(key);
+            t.execute(&code).unwrap();
+            assert_eq!(expected, t.operand_stack_mut().pop_int(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_control_flow() {
+        let mut t = setup_thread!(1);
+        let code = Code::decode(&[0x01, 0xbf], &[]).unwrap(); // aconst_null, athrow
+        t.execute_at(&code, 0).unwrap();
+        assert!(matches!(
+            t.execute_at(&code, 1),
+            Err(VmError::Internal(message)) if message == "athrow is not supported by the interpreter yet"
+        ));
+    }
+
+    #[test]
+    fn test_local_scope_releases_handles() {
+        let mut t = Thread::new(Arc::default());
+        let (handle, result) = t.local_scope(|t| {
+            let handle = t.new_local(7);
+            let result: Result<(), Unwind> = Err(Unwind::Shutdown);
+            assert_eq!(Ok(7), t.resolve_local(handle));
+            (handle, result)
+        });
+        assert_eq!(Err(Unwind::Shutdown), result);
+        assert!(t.resolve_local(handle).is_err());
+        assert_eq!(0, t.local_handles_mut().depth());
+    }
+
+    #[test]
+    fn test_caller_class() {
+        use crate::vm::classloader::resolve::resolve_class_method;
+        use crate::vm::classloader::test_util::class_loader;
+        use crate::vm::classloader::ClassLoader;
+
+        let mut loader = class_loader(&["caller"]);
+        let mut method = |class: &str, name: &str, descriptor: &str| {
+            let class = loader.find_or_load_class(class).unwrap();
+            resolve_class_method(&class, name, descriptor).unwrap()
+        };
+        let main = method("App", "main", "([Ljava/lang/String;)V");
+        let bounce = method("Trampoline", "bounce", "()V");
+        let lookup = method("Sensitive", "lookup", "()V");
+        let plain = method("Sensitive", "plain", "()V");
+
+        let mut t = Thread::new(Arc::default());
+        for m in [main, bounce, lookup.clone()] {
+            t.stack.push_new_frame(0, 0, Arc::default());
+            t.stack.current_frame_mut().method = Some(m);
+        }
+        // Trampoline.bounce is hidden, so the caller is App
+        assert_eq!("App", t.caller_class().unwrap().unwrap().name().as_str());
+
+        t.stack.drop_frame();
+        t.stack.drop_frame();
+        t.stack.push_new_frame(0, 0, Arc::default());
+        t.stack.current_frame_mut().method = Some(plain);
+        assert!(t.caller_class().is_err());
+
+        let mut t = Thread::new(Arc::default());
+        t.stack.push_new_frame(0, 0, Arc::default());
+        t.stack.current_frame_mut().method = Some(lookup);
+        assert!(t.caller_class().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_dispatch_uncaught_exception() {
+        let runtime = Arc::new(Runtime::default());
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let recording = |name: &'static str| -> UncaughtHandler {
+            let log = log.clone();
+            Arc::new(move |thread, _| log.lock().unwrap().push(format!("{} {}", name, thread)))
+        };
+        let workers = ThreadGroup::new("workers", runtime.main_thread_group());
+        let mut t = Thread::new_in_group(runtime.clone(), "worker", workers.clone());
+        assert_eq!("main", t.group().parent().unwrap().name());
+        let error = VmError::Internal("boom".into());
+
+        runtime.set_default_uncaught_handler(Some(recording("default")));
+        t.dispatch_uncaught_exception(&error);
+        runtime
+            .main_thread_group()
+            .set_uncaught_handler(Some(recording("main")));
+        t.dispatch_uncaught_exception(&error);
+        t.set_uncaught_handler(Some(recording("thread")));
+        t.dispatch_uncaught_exception(&error);
+        Thread::new(runtime).dispatch_uncaught_exception(&error);
+
+        assert_eq!(
+            vec![
+                "default worker",
+                "main worker",
+                "thread worker",
+                "main main"
+            ],
+            *log.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_green_threads_yield() {
+        let runtime = Arc::new(Runtime::default());
+        let mut native = Thread::new(runtime.clone());
+        runtime.set_scheduler_mode(SchedulerMode::Green { workers: 1 });
+        let mut green = Thread::new(runtime);
+
+        let yields = |t: &mut Thread| (0..YIELD_QUANTUM * 3).filter(|_| t.should_yield()).count();
+        assert_eq!(0, yields(&mut native));
+        assert_eq!(3, yields(&mut green));
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[should_panic(expected = "operand stack overflow, max_stack is 1 at pc 0 in <unknown method>")]
+    fn test_stack_guards() {
+        let runtime = Runtime::default();
+        runtime.set_stack_guards(true);
+        let mut t = Thread::new(Arc::new(runtime));
+        t.stack.push_new_frame(0, 1, Arc::default());
+        t.dispatch(Instruction::Op(Op::IConst1)).unwrap();
+        t.dispatch(Instruction::Op(Op::IConst1)).unwrap();
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "stack-guards"))]
+    #[should_panic(expected = "right: None at pc 2 in <unknown method>")]
+    fn test_stack_map_guards() {
+        use crate::vm::stack::StackMap;
+        use libjava::classfile::{StackMapFrame, VerificationTypeInfo};
+
+        let mut t = Thread::new(Arc::default());
+        t.stack.set_guarded(true);
+        t.stack.push_new_frame(1, 1, Arc::default());
+        let bytecode = [
+            0x04, // iconst_1
+            0x3b, // istore_0
+            0x1a, // iload_0
+            0xb1, // return
+        ];
+        let mut code = Code::decode(&bytecode, &[]).unwrap();
+        // local 0 is top at the iload, which the verifier would reject
+        code.set_stack_map(StackMap::new(
+            &[],
+            &[StackMapFrame::Full {
+                frame_type: 255,
+                offset_delta: 2,
+                locals: vec![VerificationTypeInfo::TopVariable { tag: 0 }],
+                stack: vec![],
+            }],
+        ));
+        t.execute(&code).unwrap();
     }
 }
@@ -0,0 +1,352 @@
+//! A data-driven test of the interpreter, against the behavior of the
+//! instructions as specified by [`$6.5`]. The cases are rows of the tables in
+//! `tests/resources/vm/conformance`, so that the edge cases of an instruction
+//! (overflow, NaN, truncation and so on) are a line each instead of a test.
+//!
+//! Every row has the columns
+//!
+//! ```text
+//! instruction | locals | stack before | stack after | locals after
+//! ```
+//!
+//! where the instruction is a mnemonic as in [`Op::mnemonic`] with its
+//! operands, e.g. `bipush -3` or `iinc 1 -1`. Values are written as a type
+//! and a value, separated by whitespace and bottom of the stack first:
+//! `I:-1`, `J:9223372036854775807`, `F:NaN`, `D:-0.0` and `A:null`, or `A:3`
+//! for a reference. An unset local is written as `_`. Instead of the stack
+//! after, a row can expect a throwable with `throws java/lang/ArithmeticException`.
+//! The last column is optional, and only the locals that it lists are compared.
+//! Lines starting with `#` are comments.
+//!
+//! Floats are compared by their bits, so `-0.0` is not `0.0`, but any NaN
+//! matches any other.
+//!
+//! [`$6.5`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use libjava::bytecode::Op;
+
+use super::Thread;
+use crate::vm::stack::Frame;
+use crate::vm::types::NativeValue;
+
+const TABLES: &str = "tests/resources/vm/conformance";
+
+/// A row of a table.
+#[derive(Debug)]
+struct Case {
+    /// The file and line of the row, e.g. `arithmetic.txt:12`.
+    location: String,
+    op: Op,
+    locals: Vec<Option<NativeValue>>,
+    stack: Vec<NativeValue>,
+    expected: Expected,
+}
+
+#[derive(Debug)]
+enum Expected {
+    Values {
+        stack: Vec<NativeValue>,
+        locals: Vec<Option<NativeValue>>,
+    },
+    /// The binary name of the class of the throwable.
+    Throws(String),
+}
+
+fn parse_table(path: &Path) -> Result<Vec<Case>, String> {
+    let file = path.file_name().unwrap().to_string_lossy();
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut cases = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let location = format!("{}:{}", file, i + 1);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let case =
+            parse_case(line, location.clone()).map_err(|e| format!("{}: {}", location, e))?;
+        cases.push(case);
+    }
+    Ok(cases)
+}
+
+fn parse_case(line: &str, location: String) -> Result<Case, String> {
+    let columns = line.split('|').map(str::trim).collect::<Vec<_>>();
+    if !(4..=5).contains(&columns.len()) {
+        return Err(format!("expected 4 or 5 columns, got {}", columns.len()));
+    }
+    let expected = match columns[3].strip_prefix("throws ") {
+        Some(class) => Expected::Throws(class.trim().to_string()),
+        None => Expected::Values {
+            stack: parse_values(columns[3])?,
+            locals: parse_locals(columns.get(4).copied().unwrap_or_default())?,
+        },
+    };
+    Ok(Case {
+        location,
+        op: parse_op(columns[0])?,
+        locals: parse_locals(columns[1])?,
+        stack: parse_values(columns[2])?,
+        expected,
+    })
+}
+
+/// Parses an instruction like `iinc 1 -1`. The opcode is looked up by the
+/// mnemonic, and the operands are encoded in the widths that the opcode has
+/// in the class file, so that the op is parsed like one in a method.
+fn parse_op(s: &str) -> Result<Op, String> {
+    let mut parts = s.split_whitespace();
+    let mnemonic = parts.next().ok_or("missing instruction")?;
+    let operands = parts
+        .map(|p| {
+            p.parse::<i64>()
+                .map_err(|_| format!("invalid operand {}", p))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for opcode in 0..=u8::MAX {
+        // enough zeros for the operands of any instruction but the switches
+        let template = [opcode, 0, 0, 0, 0];
+        let mut source = &template[..];
+        let Ok(op) = Op::parse(&mut source) else {
+            continue;
+        };
+        if op.mnemonic() != mnemonic {
+            continue;
+        }
+        let width = template.len() - 1 - source.len();
+        let widths: &[usize] = match op {
+            Op::IInc(..) => &[1, 1],
+            Op::InvokeInterface(..) => &[2, 1, 1],
+            Op::MultiANewArray(..) => &[2, 1],
+            _ if width == 0 => &[],
+            _ => &[width],
+        };
+        if widths.len() != operands.len() {
+            return Err(format!("{} takes {} operands", mnemonic, widths.len()));
+        }
+        let mut bytes = vec![opcode];
+        for (operand, width) in operands.iter().zip(widths) {
+            let bits = 8 * *width as u32;
+            if *operand < -(1 << (bits - 1)) || *operand >= 1 << bits {
+                return Err(format!(
+                    "operand {} doesn't fit into {} bytes",
+                    operand, width
+                ));
+            }
+            bytes.extend_from_slice(&operand.to_be_bytes()[8 - width..]);
+        }
+        return Op::parse(&mut bytes.as_slice()).map_err(|e| format!("{:?}", e));
+    }
+    Err(format!("unknown instruction {}", mnemonic))
+}
+
+fn parse_values(s: &str) -> Result<Vec<NativeValue>, String> {
+    s.split_whitespace().map(parse_value).collect()
+}
+
+fn parse_locals(s: &str) -> Result<Vec<Option<NativeValue>>, String> {
+    s.split_whitespace()
+        .map(|v| match v {
+            "_" => Ok(None),
+            v => parse_value(v).map(Some),
+        })
+        .collect()
+}
+
+fn parse_value(s: &str) -> Result<NativeValue, String> {
+    let invalid = || format!("invalid value {}", s);
+    let (ty, value) = s.split_once(':').ok_or_else(invalid)?;
+    Ok(match ty {
+        "I" => NativeValue::Integer(value.parse().map_err(|_| invalid())?),
+        "J" => NativeValue::Long(value.parse().map_err(|_| invalid())?),
+        "F" => NativeValue::Float(value.parse().map_err(|_| invalid())?),
+        "D" => NativeValue::Double(value.parse().map_err(|_| invalid())?),
+        "A" if value == "null" => NativeValue::Reference(0),
+        "A" => NativeValue::Reference(value.parse().map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    })
+}
+
+/// Whether the values are the same, comparing floats by their bits except for
+/// NaNs.
+fn same(expected: &NativeValue, actual: &NativeValue) -> bool {
+    match (expected, actual) {
+        (NativeValue::Float(a), NativeValue::Float(b)) => {
+            (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+        }
+        (NativeValue::Double(a), NativeValue::Double(b)) => {
+            (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Executes the instruction of the case in a fresh frame, and returns what
+/// differs from the expectation.
+fn run(case: &Case) -> Result<(), String> {
+    let mut t = Thread::new(Arc::default());
+    let mut frame = Frame::allocate(
+        case.locals.len().max(expected_locals(case).len()),
+        case.stack.len().max(expected_stack(case).len()),
+        Arc::default(),
+    );
+    for (i, value) in case.locals.iter().enumerate() {
+        if let Some(value) = value {
+            frame.locals.set(i, value.clone());
+        }
+    }
+    for value in &case.stack {
+        frame.operand_stack.push(value.clone());
+    }
+    t.stack.push_frame(frame);
+
+    let result = t.evaluate(case.op.clone());
+    let (stack, locals) = match (&case.expected, result) {
+        (Expected::Throws(class), Ok(())) => return Err(format!("completed, expected {}", class)),
+        (Expected::Throws(class), Err(e)) if e.class_name() == class => return Ok(()),
+        (Expected::Throws(class), Err(e)) => {
+            return Err(format!("threw {:?}, expected {}", e, class))
+        }
+        (Expected::Values { .. }, Err(e)) => return Err(format!("threw {:?}", e)),
+        (Expected::Values { stack, locals }, Ok(())) => (stack, locals),
+    };
+
+    let frame = t.stack.current_frame_mut();
+    if frame.operand_stack.len() != stack.len() {
+        return Err(format!(
+            "the stack has {} values, expected {}",
+            frame.operand_stack.len(),
+            stack.len()
+        ));
+    }
+    for (i, expected) in stack.iter().enumerate().rev() {
+        let actual = match expected {
+            NativeValue::Integer(_) => NativeValue::Integer(frame.operand_stack.pop_int()),
+            NativeValue::Long(_) => NativeValue::Long(frame.operand_stack.pop_long()),
+            NativeValue::Float(_) => NativeValue::Float(frame.operand_stack.pop_float()),
+            NativeValue::Double(_) => NativeValue::Double(frame.operand_stack.pop_double()),
+            _ => NativeValue::Reference(frame.operand_stack.pop_reference()),
+        };
+        if !same(expected, &actual) {
+            return Err(format!(
+                "stack[{}] is {:?}, expected {:?}",
+                i, actual, expected
+            ));
+        }
+    }
+    for (i, expected) in locals.iter().enumerate() {
+        let Some(expected) = expected else {
+            continue;
+        };
+        let actual = match expected {
+            NativeValue::Integer(_) => NativeValue::Integer(frame.locals.get_int(i)),
+            NativeValue::Long(_) => NativeValue::Long(frame.locals.get_long(i)),
+            NativeValue::Float(_) => NativeValue::Float(frame.locals.get_float(i)),
+            NativeValue::Double(_) => NativeValue::Double(frame.locals.get_double(i)),
+            _ => NativeValue::Reference(frame.locals.get_reference(i)),
+        };
+        if !same(expected, &actual) {
+            return Err(format!(
+                "local {} is {:?}, expected {:?}",
+                i, actual, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn expected_locals(case: &Case) -> &[Option<NativeValue>] {
+    match &case.expected {
+        Expected::Values { locals, .. } => locals,
+        Expected::Throws(_) => &[],
+    }
+}
+
+fn expected_stack(case: &Case) -> &[NativeValue] {
+    match &case.expected {
+        Expected::Values { stack, .. } => stack,
+        Expected::Throws(_) => &[],
+    }
+}
+
+fn tables() -> Vec<PathBuf> {
+    let mut tables = std::fs::read_dir(TABLES)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect::<Vec<_>>();
+    tables.sort();
+    tables
+}
+
+#[test]
+fn test_conformance() {
+    let mut cases = vec![];
+    for table in tables() {
+        cases.extend(parse_table(&table).unwrap());
+    }
+    assert!(!cases.is_empty(), "no cases in {}", TABLES);
+
+    let mut failures = vec![];
+    for case in &cases {
+        // a type mismatch on the stack panics in debug builds
+        let result = catch_unwind(AssertUnwindSafe(|| run(case)))
+            .unwrap_or_else(|_| Err("panicked".to_string()));
+        if let Err(e) = result {
+            failures.push(format!("{} ({}): {}", case.location, case.op.mnemonic(), e));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_parse_case() {
+    let case = parse_case("iinc 1 -1 | I:0 I:5 | | | _ I:4", "test.txt:1".to_string()).unwrap();
+    assert_eq!(Op::IInc(1, -1), case.op);
+    assert_eq!(
+        vec![Some(NativeValue::Integer(0)), Some(NativeValue::Integer(5))],
+        case.locals
+    );
+    assert!(case.stack.is_empty());
+    assert!(matches!(
+        case.expected,
+        Expected::Values { ref stack, ref locals }
+            if stack.is_empty() && *locals == vec![None, Some(NativeValue::Integer(4))]
+    ));
+
+    let case = parse_case("sipush -1 | | | I:-1", "test.txt:2".to_string()).unwrap();
+    assert_eq!(Op::SIPush(0xFFFF), case.op);
+
+    let case = parse_case(
+        "idiv | | I:1 I:0 | throws java/lang/ArithmeticException",
+        "test.txt:3".to_string(),
+    )
+    .unwrap();
+    assert!(
+        matches!(case.expected, Expected::Throws(ref c) if c == "java/lang/ArithmeticException")
+    );
+
+    for (line, error) in [
+        ("iadd | | I:1", "expected 4 or 5 columns, got 3"),
+        ("ifoo | | | ", "unknown instruction ifoo"),
+        ("bipush | | | ", "bipush takes 1 operands"),
+        ("bipush 256 | | | ", "operand 256 doesn't fit into 1 bytes"),
+        ("iadd | | I:x | ", "invalid value I:x"),
+        ("iadd | | Z:1 | ", "invalid value Z:1"),
+    ] {
+        assert_eq!(
+            error,
+            parse_case(line, "test.txt:1".to_string()).unwrap_err()
+        );
+    }
+}
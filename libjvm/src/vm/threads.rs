@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The live threads of a VM and their statistics, like the thread management
+/// of `ThreadMXBean`.
+///
+/// Every [`Thread`](crate::vm::thread::Thread) registers itself on creation
+/// and is removed when it is dropped. The counts of blocking on a monitor and
+/// of waiting (`Object.wait`, `Thread.join` and `Thread.sleep`) are always
+/// recorded, their durations only while contention monitoring is enabled.
+/// The CPU time of a thread is the time it spent running, i.e. neither
+/// blocked nor waiting, as measured by the clock of the runtime.
+pub struct ThreadRegistry {
+    next_id: AtomicI64,
+    cpu_time_enabled: AtomicBool,
+    contention_monitoring_enabled: AtomicBool,
+    threads: Mutex<BTreeMap<i64, (String, Arc<ThreadStats>)>>,
+}
+
+/// The statistics of a single thread, see [`ThreadRegistry`]. Durations are
+/// in nanoseconds.
+#[derive(Default)]
+pub struct ThreadStats {
+    cpu_time: AtomicU64,
+    blocked_count: AtomicU64,
+    blocked_time: AtomicU64,
+    waited_count: AtomicU64,
+    waited_time: AtomicU64,
+}
+
+/// A snapshot of the statistics of a thread, see [`ThreadRegistry::metrics`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ThreadMetrics {
+    pub id: i64,
+    pub name: String,
+    /// [`None`] if CPU time measurement is disabled.
+    pub cpu_time: Option<Duration>,
+    pub blocked_count: u64,
+    /// [`None`] if contention monitoring is disabled.
+    pub blocked_time: Option<Duration>,
+    pub waited_count: u64,
+    /// [`None`] if contention monitoring is disabled.
+    pub waited_time: Option<Duration>,
+}
+
+impl Default for ThreadRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThreadRegistry {
+    /// Creates an empty registry, with CPU time measurement enabled and
+    /// contention monitoring disabled, like the defaults of HotSpot.
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicI64::new(1),
+            cpu_time_enabled: AtomicBool::new(true),
+            contention_monitoring_enabled: AtomicBool::new(false),
+            threads: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Registers a new thread with the given name, and returns its id and its
+    /// statistics. Ids start at 1 and are never reused.
+    pub fn register(&self, name: &str) -> (i64, Arc<ThreadStats>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let stats = Arc::new(ThreadStats::default());
+        self.threads
+            .lock()
+            .unwrap()
+            .insert(id, (name.to_string(), stats.clone()));
+        (id, stats)
+    }
+
+    pub fn unregister(&self, id: i64) {
+        self.threads.lock().unwrap().remove(&id);
+    }
+
+    /// The ids of the live threads, in the order they were created.
+    pub fn ids(&self) -> Vec<i64> {
+        self.threads.lock().unwrap().keys().copied().collect()
+    }
+
+    pub fn stats(&self, id: i64) -> Option<Arc<ThreadStats>> {
+        self.threads
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|(_, stats)| stats.clone())
+    }
+
+    pub fn is_cpu_time_enabled(&self) -> bool {
+        self.cpu_time_enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_cpu_time_enabled(&self, enabled: bool) {
+        self.cpu_time_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_contention_monitoring_enabled(&self) -> bool {
+        self.contention_monitoring_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables contention monitoring. Enabling it resets the
+    /// blocked and waited times of all threads, like
+    /// `ThreadMXBean.setThreadContentionMonitoringEnabled`.
+    pub fn set_contention_monitoring_enabled(&self, enabled: bool) {
+        let was_enabled = self
+            .contention_monitoring_enabled
+            .swap(enabled, Ordering::Relaxed);
+        if enabled && !was_enabled {
+            for (_, stats) in self.threads.lock().unwrap().values() {
+                stats.reset_contention_times();
+            }
+        }
+    }
+
+    /// Returns the CPU time of the given thread, or [`None`] if there is no
+    /// such thread or CPU time measurement is disabled.
+    pub fn cpu_time(&self, id: i64) -> Option<Duration> {
+        if !self.is_cpu_time_enabled() {
+            return None;
+        }
+        self.stats(id).map(|stats| stats.cpu_time())
+    }
+
+    /// Returns a snapshot of the statistics of all live threads, in the
+    /// order they were created.
+    pub fn metrics(&self) -> Vec<ThreadMetrics> {
+        let cpu_time = self.is_cpu_time_enabled();
+        let contention = self.is_contention_monitoring_enabled();
+        self.threads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, (name, stats))| ThreadMetrics {
+                id: *id,
+                name: name.clone(),
+                cpu_time: cpu_time.then(|| stats.cpu_time()),
+                blocked_count: stats.blocked_count(),
+                blocked_time: contention.then(|| stats.blocked_time()),
+                waited_count: stats.waited_count(),
+                waited_time: contention.then(|| stats.waited_time()),
+            })
+            .collect()
+    }
+}
+
+impl ThreadStats {
+    pub fn cpu_time(&self) -> Duration {
+        Duration::from_nanos(self.cpu_time.load(Ordering::Relaxed))
+    }
+
+    pub fn blocked_count(&self) -> u64 {
+        self.blocked_count.load(Ordering::Relaxed)
+    }
+
+    pub fn blocked_time(&self) -> Duration {
+        Duration::from_nanos(self.blocked_time.load(Ordering::Relaxed))
+    }
+
+    pub fn waited_count(&self) -> u64 {
+        self.waited_count.load(Ordering::Relaxed)
+    }
+
+    pub fn waited_time(&self) -> Duration {
+        Duration::from_nanos(self.waited_time.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn add_cpu_time(&self, nanos: u64) {
+        self.cpu_time.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Records that the thread was blocked on a monitor for the given time,
+    /// which is only added if contention monitoring is enabled.
+    pub(crate) fn add_blocked(&self, nanos: Option<u64>) {
+        self.blocked_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(nanos) = nanos {
+            self.blocked_time.fetch_add(nanos, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that the thread waited for the given time, see
+    /// [`ThreadStats::add_blocked`].
+    pub(crate) fn add_waited(&self, nanos: Option<u64>) {
+        self.waited_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(nanos) = nanos {
+            self.waited_time.fetch_add(nanos, Ordering::Relaxed);
+        }
+    }
+
+    pub fn reset_contention_times(&self) {
+        self.blocked_time.store(0, Ordering::Relaxed);
+        self.waited_time.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register() {
+        let registry = ThreadRegistry::new();
+        let (main, stats) = registry.register("main");
+        let (worker, _) = registry.register("worker");
+        assert_eq!((1, 2), (main, worker));
+        assert_eq!(vec![1, 2], registry.ids());
+
+        stats.add_cpu_time(5);
+        assert_eq!(Some(Duration::from_nanos(5)), registry.cpu_time(main));
+        registry.set_cpu_time_enabled(false);
+        assert_eq!(None, registry.cpu_time(main));
+        assert_eq!(None, registry.metrics()[0].cpu_time);
+
+        registry.unregister(main);
+        assert_eq!(vec![2], registry.ids());
+        assert!(registry.stats(main).is_none());
+        // ids are not reused
+        assert_eq!(3, registry.register("other").0);
+    }
+
+    #[test]
+    fn test_contention_monitoring() {
+        let registry = ThreadRegistry::new();
+        let (id, stats) = registry.register("main");
+        stats.add_blocked(Some(10));
+        stats.add_waited(None);
+
+        let metrics = &registry.metrics()[0];
+        assert_eq!((id, "main"), (metrics.id, metrics.name.as_str()));
+        assert_eq!((1, None), (metrics.blocked_count, metrics.blocked_time));
+        assert_eq!((1, None), (metrics.waited_count, metrics.waited_time));
+
+        // enabling resets the times, but not the counts
+        registry.set_contention_monitoring_enabled(true);
+        stats.add_waited(Some(7));
+        let metrics = &registry.metrics()[0];
+        assert_eq!(Some(Duration::ZERO), metrics.blocked_time);
+        assert_eq!(2, metrics.waited_count);
+        assert_eq!(Some(Duration::from_nanos(7)), metrics.waited_time);
+    }
+}
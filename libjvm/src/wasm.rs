@@ -0,0 +1,190 @@
+//! An API for JavaScript hosts, e.g. browsers, when libjvm is built for
+//! wasm32-unknown-unknown with the `wasm` feature as part of a `cdylib`.
+//!
+//! Strings and byte arrays are passed as a pointer and a length into the
+//! memory of the module. The host allocates them with `rjvm_alloc`, and frees
+//! them with `rjvm_free` when the call returns.
+//!
+//! The class path is a file system whose files are either added by the host
+//! with `rjvm_add_file`, or fetched with the `fetch` import on first access.
+//! The module imports the following functions from the `rjvm` module:
+//!
+//! * `write(fd, ptr, len)`: writes the output of Java code to its standard
+//!   output (`fd` 1) or standard error (`fd` 2).
+//! * `fetch(path_ptr, path_len)`: fetches the file at the given path, and
+//!   returns its length, or -1 if there's no such file. This has to be
+//!   synchronous, e.g. with a synchronous `XMLHttpRequest` in a web worker.
+//! * `fetch_read(ptr)`: copies the file of the last `fetch` to the given
+//!   pointer, which points to as many bytes as `fetch` returned.
+//!
+//! ```js
+//! const decoder = new TextDecoder();
+//! let memory, fetched;
+//! const bytes = (ptr, len) => new Uint8Array(memory.buffer, ptr, len);
+//! const { instance } = await WebAssembly.instantiateStreaming(fetch("rjvm.wasm"), {
+//!   rjvm: {
+//!     write: (fd, ptr, len) => console.log(decoder.decode(bytes(ptr, len))),
+//!     fetch: (ptr, len) => {
+//!       const request = new XMLHttpRequest();
+//!       request.open("GET", decoder.decode(bytes(ptr, len)), false);
+//!       request.overrideMimeType("text/plain; charset=x-user-defined");
+//!       request.send();
+//!       if (request.status !== 200) return -1;
+//!       fetched = Uint8Array.from(request.response, (c) => c.charCodeAt(0));
+//!       return fetched.length;
+//!     },
+//!     fetch_read: (ptr) => bytes(ptr, fetched.length).set(fetched),
+//!   },
+//! });
+//! memory = instance.exports.memory;
+//! ```
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::Path;
+
+use libvfs::FileSystem;
+
+use crate::vm::classloader::classpath::ClassPathEntry;
+use crate::vm::VM;
+
+#[link(wasm_import_module = "rjvm")]
+extern "C" {
+    fn write(fd: i32, ptr: *const u8, len: usize);
+    fn fetch(path_ptr: *const u8, path_len: usize) -> isize;
+    fn fetch_read(ptr: *mut u8);
+}
+
+thread_local! {
+    /// The class path of all VMs. wasm32 has no threads, so this is global.
+    static FILE_SYSTEM: RefCell<Option<FileSystem>> = const { RefCell::new(None) };
+}
+
+fn file_system() -> FileSystem {
+    FILE_SYSTEM.with(|fs| {
+        fs.borrow_mut()
+            .get_or_insert_with(|| {
+                FileSystem::new_copy_on_write_fs(FileSystem::new_fetch_fs(fetch_from_host))
+            })
+            .clone()
+    })
+}
+
+fn fetch_from_host(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    let path = path.to_string_lossy();
+    // SAFETY: the host only reads the path during the call
+    let len = unsafe { fetch(path.as_ptr(), path.len()) };
+    if len < 0 {
+        return Ok(None);
+    }
+    let mut bytes = vec![0; len as usize];
+    // SAFETY: the buffer has the length of the fetched file
+    unsafe { fetch_read(bytes.as_mut_ptr()) };
+    Ok(Some(bytes))
+}
+
+/// The standard output or error of Java code, written with the `write` import.
+struct HostOutput(i32);
+
+impl Write for HostOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // SAFETY: the host only reads the buffer during the call
+        unsafe { write(self.0, buf.as_ptr(), buf.len()) };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// # Safety
+///
+/// The pointer and length must be those of a string that the host wrote to
+/// the memory of the module.
+unsafe fn host_str<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).ok()
+}
+
+/// Allocates the given number of bytes for the host, e.g. for the arguments
+/// of other functions.
+#[no_mangle]
+pub extern "C" fn rjvm_alloc(len: usize) -> *mut u8 {
+    let mut bytes = Vec::<u8>::with_capacity(len);
+    let ptr = bytes.as_mut_ptr();
+    std::mem::forget(bytes);
+    ptr
+}
+
+/// Frees bytes that were allocated with [`rjvm_alloc`].
+///
+/// # Safety
+///
+/// The pointer and length must be those of an earlier call to
+/// [`rjvm_alloc`], and the bytes must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn rjvm_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// Adds a file to the class path, e.g. a class file that the host already
+/// has, so that it isn't fetched. Returns 0, or -1 if the file can't be
+/// added, e.g. because it already exists.
+///
+/// # Safety
+///
+/// The pointers and lengths must be those of bytes that the host wrote to the
+/// memory of the module.
+#[no_mangle]
+pub unsafe extern "C" fn rjvm_add_file(
+    path_ptr: *const u8,
+    path_len: usize,
+    bytes_ptr: *const u8,
+    bytes_len: usize,
+) -> i32 {
+    let path = match host_str(path_ptr, path_len) {
+        Some(path) => path,
+        None => return -1,
+    };
+    let bytes = std::slice::from_raw_parts(bytes_ptr, bytes_len);
+    match file_system()
+        .create(path)
+        .and_then(|mut f| f.write_all(bytes))
+    {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Runs the main method of the given class, e.g. `com/example/Main`, with the
+/// given directory of the file system as the class path, on the calling
+/// thread. Returns the exit status of the VM.
+///
+/// # Safety
+///
+/// The pointers and lengths must be those of strings that the host wrote to
+/// the memory of the module.
+#[no_mangle]
+pub unsafe extern "C" fn rjvm_run_main(
+    class_path_ptr: *const u8,
+    class_path_len: usize,
+    class_name_ptr: *const u8,
+    class_name_len: usize,
+) -> i32 {
+    let (class_path, class_name) = match (
+        host_str(class_path_ptr, class_path_len),
+        host_str(class_name_ptr, class_name_len),
+    ) {
+        (Some(class_path), Some(class_name)) => (class_path, class_name),
+        _ => return 1,
+    };
+    let vm = VM::new(
+        file_system(),
+        vec![ClassPathEntry::Dir(class_path.to_string())].into(),
+    );
+    vm.runtime().files().set_stdout(Box::new(HostOutput(1)));
+    vm.runtime().files().set_stderr(Box::new(HostOutput(2)));
+    // the VM keeps the name of the main class for as long as it runs, and
+    // hosts run few programs per instance of the module
+    vm.run_main_class_on_current_thread(Box::leak(class_name.into()))
+}
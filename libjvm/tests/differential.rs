@@ -0,0 +1,142 @@
+// Differential tests, which run the programs in `tests/differential` on rjvm
+// and on the `java` of the host, and compare their output and exit status.
+//
+// Every program is a directory with a `Main` class. If `javac` is available,
+// the sources are compiled before running, otherwise the pre-built class files
+// in the directory are used. Without a reference JVM, the tests are skipped.
+
+use libjvm::vm::classloader::classpath::ClassPathEntry;
+use libjvm::vm::VM;
+use libvfs::FileSystem;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+const CORPUS: &str = "tests/differential";
+
+#[derive(Debug, Eq, PartialEq)]
+struct Outcome {
+    stdout: String,
+    exit_status: i32,
+}
+
+/// A [`Write`] that can be handed to the VM while the test keeps a handle to
+/// the written bytes.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn has_command(name: &str) -> bool {
+    Command::new(name).arg("-version").output().is_ok()
+}
+
+/// Returns the directory with the class files of the given program, compiling
+/// the sources if possible.
+fn class_dir(program: &Path) -> PathBuf {
+    if !has_command("javac") {
+        return program.to_path_buf();
+    }
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("differential")
+        .join(program.file_name().unwrap());
+    std::fs::create_dir_all(&out).unwrap();
+    let sources: Vec<PathBuf> = std::fs::read_dir(program)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "java"))
+        .collect();
+    let status = Command::new("javac")
+        .arg("-d")
+        .arg(&out)
+        .args(&sources)
+        .status()
+        .unwrap();
+    assert!(status.success(), "unable to compile {}", program.display());
+    out
+}
+
+fn run_reference(classes: &Path) -> Outcome {
+    let output = Command::new("java")
+        .arg("-cp")
+        .arg(classes)
+        .arg("Main")
+        .output()
+        .unwrap();
+    Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        exit_status: output.status.code().unwrap_or(-1),
+    }
+}
+
+fn run_rjvm(classes: &Path) -> Outcome {
+    let vm = VM::new(
+        FileSystem::new_os_fs(),
+        vec![ClassPathEntry::Dir(classes.to_str().unwrap().into())].into(),
+    );
+    let stdout = Capture::default();
+    vm.runtime().files().set_stdout(Box::new(stdout.clone()));
+    let exit_status = vm.run_main_class("Main");
+    let stdout = String::from_utf8_lossy(&stdout.0.lock().unwrap()).into_owned();
+    Outcome {
+        stdout,
+        exit_status,
+    }
+}
+
+fn known_failures() -> BTreeSet<String> {
+    std::fs::read_to_string(Path::new(CORPUS).join("known_failures.txt"))
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_differential_corpus() {
+    if !has_command("java") {
+        eprintln!("skipping differential tests, no reference JVM found");
+        return;
+    }
+
+    let known_failures = known_failures();
+    let mut programs: Vec<PathBuf> = std::fs::read_dir(CORPUS)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.is_dir())
+        .collect();
+    programs.sort();
+
+    let mut errors = vec![];
+    for program in programs {
+        let name = program.file_name().unwrap().to_str().unwrap().to_owned();
+        let classes = class_dir(&program);
+        let expected = run_reference(&classes);
+        let actual = run_rjvm(&classes);
+
+        match (expected == actual, known_failures.contains(&name)) {
+            (true, true) => errors.push(format!(
+                "{}: passes now, remove it from the known failures",
+                name
+            )),
+            (false, false) => errors.push(format!(
+                "{}: expected {:?}, but got {:?}",
+                name, expected, actual
+            )),
+            _ => {}
+        }
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
@@ -0,0 +1,11 @@
+public class Main {
+    public static void main(String[] args) {
+        int a = Integer.MAX_VALUE;
+        System.out.println(a + 1);
+        System.out.println((byte) 250);
+        System.out.println((char) 65);
+        System.out.println(7 / 2);
+        System.out.println(-7 % 3);
+        System.out.println(1L << 40);
+    }
+}
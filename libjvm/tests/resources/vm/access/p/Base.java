@@ -0,0 +1,15 @@
+package p;
+
+public class Base {
+    public int publicField;
+    protected int protectedField;
+    int packageField;
+    private int privateField;
+    protected static int protectedStatic;
+
+    public int publicMethod() { return 0; }
+    protected int protectedMethod() { return 0; }
+    int packageMethod() { return 0; }
+    private int privateMethod() { return 0; }
+    protected static int protectedStaticMethod() { return 0; }
+}
@@ -0,0 +1,9 @@
+package p;
+
+public class Outer {
+    private int secret;
+
+    class Inner {
+        private int inner;
+    }
+}
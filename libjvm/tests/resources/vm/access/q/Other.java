@@ -0,0 +1,4 @@
+package q;
+
+public class Other {
+}
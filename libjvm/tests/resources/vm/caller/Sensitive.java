@@ -0,0 +1,10 @@
+import jdk.internal.reflect.CallerSensitive;
+
+public class Sensitive {
+    @CallerSensitive
+    public static void lookup() {
+    }
+
+    public static void plain() {
+    }
+}
@@ -0,0 +1,8 @@
+import jdk.internal.vm.annotation.Hidden;
+
+public class Trampoline {
+    @Hidden
+    static void bounce() {
+        Sensitive.lookup();
+    }
+}
@@ -0,0 +1,3 @@
+// compiled against a version of Parent that is a class
+public class Child extends Parent {
+}
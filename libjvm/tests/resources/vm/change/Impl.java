@@ -0,0 +1,3 @@
+// compiled against a version of Contract that is an interface
+public class Impl implements Contract {
+}
@@ -0,0 +1,3 @@
+public interface Service {
+    int work();
+}
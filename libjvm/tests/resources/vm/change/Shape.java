@@ -0,0 +1,8 @@
+public class Shape {
+    public int count;
+    public static int size;
+
+    public double area() {
+        return 0;
+    }
+}
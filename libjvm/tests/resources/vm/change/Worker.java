@@ -0,0 +1,3 @@
+// compiled against a version of Service without work()
+public class Worker implements Service {
+}
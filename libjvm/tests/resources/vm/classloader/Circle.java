@@ -0,0 +1,7 @@
+public class Circle extends Shape {
+    static double scale;
+    double radius;
+    boolean filled;
+    Object label;
+    char symbol;
+}
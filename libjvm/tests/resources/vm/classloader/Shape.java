@@ -0,0 +1,5 @@
+public class Shape {
+    static long count;
+    int id;
+    byte kind;
+}
@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Boolean extends Object {
+    private final boolean value;
+
+    public Boolean(boolean value) {
+        this.value = value;
+    }
+}
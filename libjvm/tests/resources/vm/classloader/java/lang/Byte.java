@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Byte extends Number {
+    private final byte value;
+
+    public Byte(byte value) {
+        this.value = value;
+    }
+}
@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Character extends Object {
+    private final char value;
+
+    public Character(char value) {
+        this.value = value;
+    }
+}
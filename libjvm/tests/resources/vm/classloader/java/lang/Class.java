@@ -0,0 +1,8 @@
+package java.lang;
+
+public final class Class<T> {
+    private transient String name;
+
+    private Class() {
+    }
+}
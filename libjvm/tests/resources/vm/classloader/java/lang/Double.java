@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Double extends Number {
+    private final double value;
+
+    public Double(double value) {
+        this.value = value;
+    }
+}
@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Float extends Number {
+    private final float value;
+
+    public Float(float value) {
+        this.value = value;
+    }
+}
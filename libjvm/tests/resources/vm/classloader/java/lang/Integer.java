@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Integer extends Number {
+    private final int value;
+
+    public Integer(int value) {
+        this.value = value;
+    }
+}
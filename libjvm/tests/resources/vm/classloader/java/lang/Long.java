@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Long extends Number {
+    private final long value;
+
+    public Long(long value) {
+        this.value = value;
+    }
+}
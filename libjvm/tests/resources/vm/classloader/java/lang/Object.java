@@ -0,0 +1,6 @@
+package java.lang;
+
+public class Object {
+    public Object() {
+    }
+}
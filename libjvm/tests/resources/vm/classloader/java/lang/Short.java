@@ -0,0 +1,9 @@
+package java.lang;
+
+public final class Short extends Number {
+    private final short value;
+
+    public Short(short value) {
+        this.value = value;
+    }
+}
@@ -0,0 +1,11 @@
+package java.lang.invoke;
+
+public abstract class MethodHandle {
+    public final native Object invokeExact(Object... args) throws Throwable;
+
+    public final native Object invoke(Object... args) throws Throwable;
+
+    public MethodHandle bindTo(Object x) {
+        return this;
+    }
+}
@@ -0,0 +1,11 @@
+package java.lang.invoke;
+
+public final class MethodType {
+    private final Class<?> rtype;
+    private final Class<?>[] ptypes;
+
+    private MethodType(Class<?> rtype, Class<?>[] ptypes) {
+        this.rtype = rtype;
+        this.ptypes = ptypes;
+    }
+}
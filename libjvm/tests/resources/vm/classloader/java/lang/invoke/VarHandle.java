@@ -0,0 +1,7 @@
+package java.lang.invoke;
+
+public abstract class VarHandle {
+    public final native Object get(Object... args);
+
+    public final native void set(Object... args);
+}
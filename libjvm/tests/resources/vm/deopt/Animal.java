@@ -0,0 +1,9 @@
+public class Animal {
+    String sound() {
+        return "...";
+    }
+
+    String name() {
+        return "animal";
+    }
+}
@@ -0,0 +1,5 @@
+public class Cat extends Animal {
+    String name() {
+        return "cat";
+    }
+}
@@ -0,0 +1,2 @@
+public class Puppy extends Dog {
+}
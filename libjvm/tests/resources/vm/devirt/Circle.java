@@ -0,0 +1,11 @@
+public class Circle implements Shape {
+    double radius = 1;
+
+    public double area() {
+        return Math.PI * radius * radius;
+    }
+
+    public String label() {
+        return "circle";
+    }
+}
@@ -0,0 +1,5 @@
+public class Rotated extends Square {
+    public double area() {
+        return 2 * side * side;
+    }
+}
@@ -0,0 +1,7 @@
+public interface Shape {
+    double area();
+
+    default String label() {
+        return "shape";
+    }
+}
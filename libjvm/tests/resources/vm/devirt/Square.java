@@ -0,0 +1,11 @@
+public class Square implements Shape {
+    double side = 1;
+
+    public double area() {
+        return side * side;
+    }
+
+    final double side() {
+        return side;
+    }
+}
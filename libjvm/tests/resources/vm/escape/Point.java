@@ -0,0 +1,64 @@
+public class Point {
+    static Object global;
+
+    int x;
+    Point next;
+
+    Point(int x) {
+        this.x = x;
+    }
+
+    static int local(int x) {
+        Point p = new Point(x);
+        return p.x;
+    }
+
+    static Object returned() {
+        return new Point(1);
+    }
+
+    static void stored() {
+        global = new Point(2);
+    }
+
+    static int merged(boolean b) {
+        Point p = b ? new Point(1) : new Point(2);
+        return p.x;
+    }
+
+    static int loop(int n) {
+        Point previous = null;
+        Point current = new Point(0);
+        for (int i = 0; i < n; i++) {
+            previous = current;
+            current = new Point(i);
+        }
+        return previous.x + current.x;
+    }
+
+    static int passed() {
+        Point p = new Point(3);
+        return x(p);
+    }
+
+    static int x(Point p) {
+        return p.x;
+    }
+
+    static int thrown() {
+        try {
+            Point p = new Point(4);
+            return p.x / 0;
+        } catch (ArithmeticException e) {
+            return -1;
+        }
+    }
+
+    void link(Point other) {
+        next = other;
+    }
+
+    static void linked() {
+        new Point(5).link(new Point(6));
+    }
+}
@@ -0,0 +1,3 @@
+// the class file of Gone is deleted after compiling, so that it is missing
+public class Gone extends Exception {
+}
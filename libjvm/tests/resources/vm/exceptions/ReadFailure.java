@@ -0,0 +1,2 @@
+public class ReadFailure extends Exception {
+}
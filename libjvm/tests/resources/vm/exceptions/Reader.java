@@ -0,0 +1,11 @@
+public class Reader {
+    public int read() throws ReadFailure, Closed {
+        return 0;
+    }
+
+    public void close() {
+    }
+
+    public void reset() throws Gone {
+    }
+}
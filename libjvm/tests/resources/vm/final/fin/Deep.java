@@ -0,0 +1,9 @@
+// compiled against a version of Sealed where value() is not final
+package fin;
+
+public class Deep extends Middle {
+    @Override
+    public int value() {
+        return 4;
+    }
+}
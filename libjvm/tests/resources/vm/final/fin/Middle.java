@@ -0,0 +1,4 @@
+package fin;
+
+public class Middle extends Sealed {
+}
@@ -0,0 +1,21 @@
+package fin;
+
+public class Sealed {
+    public static final long CREATED = System.nanoTime();
+    public final int id;
+
+    public Sealed() {
+        id = 1;
+    }
+
+    public final int value() {
+        return id;
+    }
+
+    final int local() {
+        return 2;
+    }
+
+    public void reset() {
+    }
+}
@@ -0,0 +1,8 @@
+package other;
+
+// Sealed.local is package-private, so this doesn't override it
+public class Unrelated extends fin.Sealed {
+    final int local() {
+        return 5;
+    }
+}
@@ -0,0 +1,4 @@
+// compiled as extending another class, and patched afterwards, since javac
+// rejects cyclic inheritance
+public class CycleB extends CycleA {
+}
@@ -0,0 +1,3 @@
+// compiled as extending another class, and patched afterwards
+public class CycleC extends CycleC {
+}
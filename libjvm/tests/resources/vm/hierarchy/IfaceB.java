@@ -0,0 +1,3 @@
+// compiled as extending another interface, and patched afterwards
+public interface IfaceB extends IfaceA {
+}
@@ -0,0 +1,3 @@
+// patched after compilation to have no superclass, i.e. super_class 0
+public class NoSuper {
+}
@@ -0,0 +1,3 @@
+// patched after compilation to have java/lang/Number as its superclass
+public interface Odd {
+}
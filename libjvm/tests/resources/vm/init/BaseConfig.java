@@ -0,0 +1,3 @@
+public class BaseConfig {
+    static int base = 1;
+}
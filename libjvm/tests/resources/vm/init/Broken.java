@@ -0,0 +1,3 @@
+public class Broken {
+    static int value = 1 / Integer.parseInt("0");
+}
@@ -0,0 +1,3 @@
+public class BrokenChild extends Broken {
+    static int child = 1;
+}
@@ -0,0 +1,3 @@
+public class Config extends BaseConfig implements Marker, Defaults {
+    static int config = base + 1;
+}
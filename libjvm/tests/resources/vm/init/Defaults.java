@@ -0,0 +1,7 @@
+public interface Defaults extends Named {
+    int VALUE = Integer.parseInt("2");
+
+    default int value() {
+        return VALUE;
+    }
+}
@@ -0,0 +1,3 @@
+public interface Marker {
+    Object MARKER = new Object();
+}
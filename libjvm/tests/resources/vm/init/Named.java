@@ -0,0 +1,7 @@
+public interface Named {
+    String NAME = String.valueOf("named");
+
+    default String name() {
+        return NAME;
+    }
+}
@@ -0,0 +1,9 @@
+package java.io;
+
+public class File {
+    private final String path;
+
+    public File(String path) {
+        this.path = path;
+    }
+}
@@ -0,0 +1,11 @@
+package java.io;
+
+public final class FileDescriptor {
+    private int fd;
+
+    public FileDescriptor() {
+        fd = -1;
+    }
+
+    private native void close0() throws IOException;
+}
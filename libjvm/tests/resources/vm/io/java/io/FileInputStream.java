@@ -0,0 +1,13 @@
+package java.io;
+
+public class FileInputStream {
+    private final FileDescriptor fd = new FileDescriptor();
+
+    private native void open0(String name) throws FileNotFoundException;
+
+    private native int read0() throws IOException;
+
+    private native int readBytes(byte[] b, int off, int len) throws IOException;
+
+    private native int available0() throws IOException;
+}
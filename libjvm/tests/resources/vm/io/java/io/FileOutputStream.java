@@ -0,0 +1,11 @@
+package java.io;
+
+public class FileOutputStream {
+    private final FileDescriptor fd = new FileDescriptor();
+
+    private native void open0(String name, boolean append) throws FileNotFoundException;
+
+    private native void write(int b, boolean append) throws IOException;
+
+    private native void writeBytes(byte[] b, int off, int len, boolean append) throws IOException;
+}
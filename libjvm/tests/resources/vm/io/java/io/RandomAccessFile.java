@@ -0,0 +1,21 @@
+package java.io;
+
+public class RandomAccessFile {
+    private final FileDescriptor fd = new FileDescriptor();
+
+    private native void open0(String name, int mode) throws FileNotFoundException;
+
+    private native int read0() throws IOException;
+
+    private native int readBytes(byte[] b, int off, int len) throws IOException;
+
+    private native void write0(int b) throws IOException;
+
+    private native void writeBytes(byte[] b, int off, int len) throws IOException;
+
+    public native long getFilePointer() throws IOException;
+
+    private native void seek0(long pos) throws IOException;
+
+    public native long length() throws IOException;
+}
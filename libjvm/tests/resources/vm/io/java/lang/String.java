@@ -0,0 +1,11 @@
+package java.lang;
+
+public final class String {
+    private final byte[] value;
+    private final byte coder;
+
+    private String(byte[] value, byte coder) {
+        this.value = value;
+        this.coder = coder;
+    }
+}
@@ -0,0 +1,43 @@
+public class Locks {
+    private final Object lock = new Object();
+    private int count;
+
+    public void increment() {
+        synchronized (lock) {
+            count++;
+        }
+    }
+
+    public int nested(Object other) {
+        synchronized (lock) {
+            synchronized (other) {
+                return count;
+            }
+        }
+    }
+
+    public void loop(int n) {
+        for (int i = 0; i < n; i++) {
+            synchronized (this) {
+                if (count > 10) {
+                    break;
+                }
+                count += i;
+            }
+        }
+    }
+
+    public int caught() {
+        try {
+            synchronized (lock) {
+                return Integer.parseInt("" + count);
+            }
+        } catch (NumberFormatException e) {
+            return -1;
+        }
+    }
+
+    public synchronized int method() {
+        return count;
+    }
+}
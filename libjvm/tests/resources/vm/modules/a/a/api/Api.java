@@ -0,0 +1,7 @@
+package a.api;
+
+public class Api {
+    public int value;
+
+    public int method() { return 0; }
+}
@@ -0,0 +1,5 @@
+package a.friend;
+
+public class Friend {
+    public int value;
+}
@@ -0,0 +1,5 @@
+package a.internal;
+
+public class Secret {
+    public int value;
+}
@@ -0,0 +1,5 @@
+module a {
+    exports a.api;
+    exports a.friend to b;
+    opens a.internal to c;
+}
@@ -0,0 +1,3 @@
+module b {
+    requires transitive a;
+}
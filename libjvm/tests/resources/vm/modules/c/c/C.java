@@ -0,0 +1,5 @@
+package c;
+
+public class C {
+    public int value;
+}
@@ -0,0 +1,3 @@
+module c {
+    requires b;
+}
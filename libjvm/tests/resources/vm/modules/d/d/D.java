@@ -0,0 +1,5 @@
+package d;
+
+public class D {
+    public int value;
+}
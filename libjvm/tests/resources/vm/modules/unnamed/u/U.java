@@ -0,0 +1,5 @@
+package u;
+
+public class U {
+    public int value;
+}
@@ -0,0 +1,7 @@
+package java.net;
+
+public final class Inet4Address extends InetAddress {
+    Inet4Address(int address) {
+        super(address);
+    }
+}
@@ -0,0 +1,21 @@
+package java.net;
+
+public class InetAddress {
+    static final int IPv4 = 1;
+
+    static class InetAddressHolder {
+        int address;
+        int family;
+
+        InetAddressHolder(int address, int family) {
+            this.address = address;
+            this.family = family;
+        }
+    }
+
+    final transient InetAddressHolder holder;
+
+    InetAddress(int address) {
+        holder = new InetAddressHolder(address, IPv4);
+    }
+}
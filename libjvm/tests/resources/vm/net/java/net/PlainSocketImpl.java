@@ -0,0 +1,19 @@
+package java.net;
+
+import java.io.IOException;
+
+class PlainSocketImpl extends SocketImpl {
+    native void socketCreate(boolean stream, boolean isServer) throws IOException;
+
+    native void socketConnect(InetAddress address, int port, int timeout) throws IOException;
+
+    native void socketBind(InetAddress address, int port) throws IOException;
+
+    native void socketListen(int count) throws IOException;
+
+    native void socketAccept(SocketImpl s) throws IOException;
+
+    native void socketClose0(boolean useDeferredClose) throws IOException;
+
+    native void socketShutdown(int howto) throws IOException;
+}
@@ -0,0 +1,10 @@
+package java.net;
+
+import java.io.FileDescriptor;
+
+public abstract class SocketImpl {
+    protected FileDescriptor fd = new FileDescriptor();
+    protected InetAddress address;
+    protected int port;
+    protected int localport;
+}
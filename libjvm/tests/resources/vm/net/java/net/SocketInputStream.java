@@ -0,0 +1,9 @@
+package java.net;
+
+import java.io.FileDescriptor;
+import java.io.IOException;
+
+class SocketInputStream {
+    private native int socketRead0(FileDescriptor fd, byte[] b, int off, int len, int timeout)
+            throws IOException;
+}
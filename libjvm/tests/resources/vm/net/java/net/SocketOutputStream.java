@@ -0,0 +1,9 @@
+package java.net;
+
+import java.io.FileDescriptor;
+import java.io.IOException;
+
+class SocketOutputStream {
+    private native void socketWrite0(FileDescriptor fd, byte[] b, int off, int len)
+            throws IOException;
+}
@@ -0,0 +1,9 @@
+public interface A {
+    default int value() {
+        return 1;
+    }
+
+    static int twice(int x) {
+        return 2 * x;
+    }
+}
@@ -0,0 +1,5 @@
+public interface B extends A {
+    default int value() {
+        return 2;
+    }
+}
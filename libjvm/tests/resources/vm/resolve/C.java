@@ -0,0 +1,7 @@
+// Diamond.class was compiled against a version of this interface without
+// value(), so that it conflicts with B.value() at run time.
+public interface C {
+    default int value() {
+        return 3;
+    }
+}
@@ -0,0 +1,3 @@
+public interface D {
+    int value();
+}
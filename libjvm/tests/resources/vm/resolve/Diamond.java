@@ -0,0 +1,2 @@
+public class Diamond implements B, C {
+}
@@ -0,0 +1,5 @@
+public class Overrides implements B {
+    public int value() {
+        return 4;
+    }
+}
@@ -0,0 +1,9 @@
+public class Sub extends UsesB {
+    private int hidden() {
+        return 5;
+    }
+
+    static int util() {
+        return 6;
+    }
+}
@@ -0,0 +1,2 @@
+public class UsesB implements A, B {
+}
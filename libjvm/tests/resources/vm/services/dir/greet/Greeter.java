@@ -0,0 +1,5 @@
+package greet;
+
+public interface Greeter {
+    String greet();
+}
@@ -0,0 +1,5 @@
+package greet;
+
+public class Hello implements Greeter {
+    public String greet() { return "hello"; }
+}
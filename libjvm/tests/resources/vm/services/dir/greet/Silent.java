@@ -0,0 +1,4 @@
+package greet;
+
+public class Silent {
+}
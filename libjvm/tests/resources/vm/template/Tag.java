@@ -0,0 +1,3 @@
+public interface Tag {
+    Object TAG = new Object();
+}
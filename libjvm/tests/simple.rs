@@ -10,5 +10,14 @@ pub fn test_simple_vm() {
     let cp = vec![ClassPathEntry::from("test/simple")];
 
     let vm = VM::new(fs, cp.into());
-    vm.run_main_class("Main");
+    assert_eq!(0, vm.run_main_class("Main"));
+}
+
+#[test]
+pub fn test_shutdown_hooks_run_on_exit() {
+    let vm = VM::new(FileSystem::new_os_fs(), vec![].into());
+    let (tx, rx) = std::sync::mpsc::channel();
+    vm.add_shutdown_hook(Box::new(move || tx.send(()).unwrap()));
+    assert_eq!(0, vm.run_main_class("Main"));
+    assert!(rx.try_recv().is_ok());
 }
@@ -1,7 +1,6 @@
 use crate::file::File;
 use crate::{FileBackend, FileSystem};
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
 
 pub(crate) struct BasePathBackend {
     base_path: PathBuf,
@@ -34,6 +33,10 @@ impl FileBackend for BasePathBackend {
         self.underlying.open(self.relativize(path))
     }
 
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File> {
+        self.underlying.open_read_write(self.relativize(path))
+    }
+
     fn exists(&self, path: &Path) -> std::io::Result<bool> {
         self.underlying.exists(self.relativize(path))
     }
@@ -46,6 +49,21 @@ impl FileBackend for BasePathBackend {
         self.underlying.create_dir(self.relativize(path))
     }
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool> {
+        self.underlying.is_dir(self.relativize(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let entries = self.underlying.read_dir(self.relativize(path))?;
+        Ok(entries
+            .into_iter()
+            .map(|p| match p.strip_prefix(&self.base_path) {
+                Ok(stripped) => PathBuf::from(stripped),
+                Err(_) => p,
+            })
+            .collect())
+    }
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()> {
         self.underlying.r#move(from, to)
     }
@@ -4,12 +4,11 @@ use crate::{FileBackend, FileSystem};
 use prefix_tree::PrefixSet;
 use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
-use std::sync::{Mutex, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 pub struct CopyOnWriteBackend {
     deleted_paths: RwLock<PrefixSet<u8>>,
-    layer: Rc<Mutex<InMemoryBackend>>,
+    layer: Arc<Mutex<InMemoryBackend>>,
     fallback: FileSystem,
 }
 
@@ -17,10 +16,17 @@ impl CopyOnWriteBackend {
     pub fn new(underlying: FileSystem) -> Self {
         Self {
             deleted_paths: RwLock::new(PrefixSet::new()),
-            layer: Rc::new(Mutex::new(InMemoryBackend::new())),
+            layer: Arc::new(Mutex::new(InMemoryBackend::new())),
             fallback: underlying,
         }
     }
+
+    fn is_deleted(&self, path: &Path) -> bool {
+        self.deleted_paths
+            .read()
+            .unwrap()
+            .contains(path.to_str().unwrap())
+    }
 }
 
 impl FileBackend for CopyOnWriteBackend {
@@ -51,6 +57,11 @@ impl FileBackend for CopyOnWriteBackend {
         ))))
     }
 
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File> {
+        // files from the fallback are copied into the layer on the first write
+        self.open(path)
+    }
+
     fn exists(&self, path: &Path) -> std::io::Result<bool> {
         let p = path.to_str().unwrap();
         let is_deleted = self.deleted_paths.read().unwrap().contains(p);
@@ -84,6 +95,35 @@ impl FileBackend for CopyOnWriteBackend {
         self.layer.lock().unwrap().create_dir(path)
     }
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool> {
+        if self.is_deleted(path) {
+            return Ok(false);
+        }
+        Ok(self.layer.lock().unwrap().is_dir(path)? || self.fallback.is_dir(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        if self.is_deleted(path) {
+            return Err(Error::new(ErrorKind::NotFound, "directory was not found"));
+        }
+
+        let mut entries = self
+            .layer
+            .lock()
+            .unwrap()
+            .read_dir(path)
+            .unwrap_or_default();
+        if self.fallback.is_dir(path).unwrap_or(false) {
+            for entry in self.fallback.read_dir(path)? {
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries.retain(|e| !self.is_deleted(e));
+        Ok(entries)
+    }
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()> {
         todo!("move")
     }
@@ -110,14 +150,14 @@ impl FileBackend for CopyOnWriteBackend {
 }
 
 struct CopyOnWriteFile {
-    layer: Rc<Mutex<InMemoryBackend>>,
+    layer: Arc<Mutex<InMemoryBackend>>,
     underlying: File,
     path: PathBuf,
     writable: bool,
 }
 
 impl CopyOnWriteFile {
-    fn new_from_underlying(layer: Rc<Mutex<InMemoryBackend>>, file: File, path: &Path) -> Self {
+    fn new_from_underlying(layer: Arc<Mutex<InMemoryBackend>>, file: File, path: &Path) -> Self {
         Self {
             layer,
             underlying: file,
@@ -126,7 +166,7 @@ impl CopyOnWriteFile {
         }
     }
 
-    fn new_from_layer(layer: Rc<Mutex<InMemoryBackend>>, file: File, path: &Path) -> Self {
+    fn new_from_layer(layer: Arc<Mutex<InMemoryBackend>>, file: File, path: &Path) -> Self {
         Self {
             layer,
             underlying: file,
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::file::File;
+use crate::FileBackend;
+
+/// The source of the files of a [`FetchBackend`], e.g. HTTP requests to the
+/// server that a web page was loaded from.
+pub trait Fetcher: Send + Sync {
+    /// Returns the contents of the file at the given path, or [`None`] if
+    /// there's no such file.
+    fn fetch(&self, path: &Path) -> std::io::Result<Option<Vec<u8>>>;
+}
+
+impl<F> Fetcher for F
+where
+    F: Fn(&Path) -> std::io::Result<Option<Vec<u8>>> + Send + Sync,
+{
+    fn fetch(&self, path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+        self(path)
+    }
+}
+
+/// A read-only backend whose files are fetched on first access and then kept
+/// in memory, including which paths don't exist, so that every path is
+/// fetched at most once. Remote sources usually can't list directories, so
+/// this backend has none. For a writable file system, wrap it with
+/// [`FileSystem::new_copy_on_write_fs`](crate::FileSystem::new_copy_on_write_fs).
+pub struct FetchBackend {
+    fetcher: Box<dyn Fetcher>,
+    cache: Mutex<HashMap<PathBuf, Option<Arc<Vec<u8>>>>>,
+}
+
+impl FetchBackend {
+    pub fn new(fetcher: impl Fetcher + 'static) -> Self {
+        Self {
+            fetcher: Box::new(fetcher),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, path: &Path) -> std::io::Result<Option<Arc<Vec<u8>>>> {
+        if let Some(entry) = self.cache.lock().unwrap().get(path) {
+            return Ok(entry.clone());
+        }
+        // the lock isn't held while fetching, since that may take long. Two
+        // threads may fetch the same path, but the result is the same.
+        let entry = self.fetcher.fetch(path)?.map(Arc::new);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), entry.clone());
+        Ok(entry)
+    }
+}
+
+fn read_only() -> Error {
+    Error::new(ErrorKind::PermissionDenied, "the file system is read-only")
+}
+
+impl FileBackend for FetchBackend {
+    fn open(&self, path: &Path) -> std::io::Result<File> {
+        match self.get(path)? {
+            // every handle reads its own copy, so writes to it are discarded
+            Some(bytes) => Ok(File::new(Box::new(Cursor::new(bytes.to_vec())))),
+            None => Err(Error::new(ErrorKind::NotFound, "file not found")),
+        }
+    }
+
+    fn open_read_write(&self, _path: &Path) -> std::io::Result<File> {
+        Err(read_only())
+    }
+
+    fn exists(&self, path: &Path) -> std::io::Result<bool> {
+        Ok(self.get(path)?.is_some())
+    }
+
+    fn create(&self, _path: &Path) -> std::io::Result<File> {
+        Err(read_only())
+    }
+
+    fn create_dir(&self, _path: &Path) -> std::io::Result<()> {
+        Err(read_only())
+    }
+
+    fn is_dir(&self, _path: &Path) -> std::io::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_dir(&self, _path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "fetched directories can't be listed",
+        ))
+    }
+
+    fn r#move(&self, _from: &Path, _to: &Path) -> std::io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_file(&self, _path: &Path) -> std::io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_dir(&self, _path: &Path) -> std::io::Result<()> {
+        Err(read_only())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_fetch_once() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let backend = FetchBackend::new(move |path: &Path| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok((path == Path::new("classes/Main.class")).then(|| vec![0xCA, 0xFE]))
+        });
+
+        for _ in 0..2 {
+            let mut bytes = vec![];
+            backend
+                .open(Path::new("classes/Main.class"))
+                .unwrap()
+                .read_to_end(&mut bytes)
+                .unwrap();
+            assert_eq!(vec![0xCA, 0xFE], bytes);
+            assert!(!backend.exists(Path::new("classes/Missing.class")).unwrap());
+        }
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+
+        assert_eq!(
+            ErrorKind::NotFound,
+            backend
+                .open(Path::new("classes/Missing.class"))
+                .err()
+                .unwrap()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            backend
+                .create(Path::new("classes/New.class"))
+                .err()
+                .unwrap()
+                .kind()
+        );
+    }
+}
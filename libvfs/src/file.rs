@@ -1,8 +1,8 @@
 use std::io::{Read, Seek, SeekFrom, Write};
 
-pub trait ReadWriteSeek: Read + Write + Seek {}
+pub trait ReadWriteSeek: Read + Write + Seek + Send {}
 
-impl<U> ReadWriteSeek for U where U: Read + Write + Seek {}
+impl<U> ReadWriteSeek for U where U: Read + Write + Seek + Send {}
 
 pub struct File {
     inner: Box<dyn ReadWriteSeek>,
@@ -1,30 +1,48 @@
-use std::path::Path;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use mockall::automock;
 
 use crate::basepath::BasePathBackend;
 use crate::copy_on_write::CopyOnWriteBackend;
+use crate::fetch::FetchBackend;
 use crate::file::File;
 use crate::mem::InMemoryBackend;
 use crate::os::OsFileBackend;
+use crate::path::VfsPath;
 
 mod basepath;
 mod copy_on_write;
+mod fetch;
 pub mod file;
 mod mem;
 mod os;
+pub mod path;
+pub mod snapshot;
+pub mod sync;
+
+pub use fetch::Fetcher;
 
 #[automock]
-pub trait FileBackend {
+pub trait FileBackend: Send + Sync {
     fn open(&self, path: &Path) -> std::io::Result<File>;
 
+    /// Opens an existing file for reading and writing. Unlike [`FileBackend::create`],
+    /// this doesn't truncate the file.
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File>;
+
     fn exists(&self, path: &Path) -> std::io::Result<bool>;
 
     fn create(&self, path: &Path) -> std::io::Result<File>;
 
     fn create_dir(&self, path: &Path) -> std::io::Result<()>;
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool>;
+
+    /// Lists the direct children of the directory at the given path. The returned
+    /// paths are the given path joined with the name of each child.
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>>;
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()>;
 
     fn remove_file(&self, path: &Path) -> std::io::Result<()>;
@@ -32,21 +50,24 @@ pub trait FileBackend {
     fn remove_dir(&self, path: &Path) -> std::io::Result<()>;
 }
 
+/// A handle to a file backend. Cloning a [`FileSystem`] is cheap, and all
+/// clones share the same underlying backend, so it can be handed to multiple
+/// components (and threads) of the VM.
 #[derive(Clone)]
 pub struct FileSystem {
-    inner: Rc<dyn FileBackend>,
+    inner: Arc<dyn FileBackend>,
 }
 
 impl FileSystem {
     pub fn new_os_fs() -> Self {
         Self {
-            inner: Rc::new(OsFileBackend::new()),
+            inner: Arc::new(OsFileBackend::new()),
         }
     }
 
     pub fn new_copy_on_write_fs(underlying: FileSystem) -> Self {
         Self {
-            inner: Rc::new(CopyOnWriteBackend::new(underlying)),
+            inner: Arc::new(CopyOnWriteBackend::new(underlying)),
         }
     }
 
@@ -55,16 +76,39 @@ impl FileSystem {
         P: AsRef<Path>,
     {
         Self {
-            inner: Rc::new(BasePathBackend::new(underlying, path)),
+            inner: Arc::new(BasePathBackend::new(underlying, path)),
         }
     }
 
     pub fn new_in_memory_fs() -> Self {
         Self {
-            inner: Rc::new(InMemoryBackend::new()),
+            inner: Arc::new(InMemoryBackend::new()),
+        }
+    }
+
+    /// Creates a read-only file system whose files are fetched from the given
+    /// source on first access, e.g. over HTTP in a browser, where there is no
+    /// file system of the host.
+    pub fn new_fetch_fs(fetcher: impl Fetcher + 'static) -> Self {
+        Self {
+            inner: Arc::new(FetchBackend::new(fetcher)),
         }
     }
 
+    /// Creates a [`VfsPath`] handle for the given path within this file system.
+    pub fn path<P>(&self, path: P) -> VfsPath
+    where
+        P: AsRef<Path>,
+    {
+        VfsPath::new(self.clone(), path)
+    }
+
+    /// Whether this and the other file system share the same backend, i.e. one
+    /// is a clone of the other.
+    pub fn same_backend(&self, other: &FileSystem) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub fn open<P>(&self, path: P) -> std::io::Result<File>
     where
         P: AsRef<Path>,
@@ -72,6 +116,13 @@ impl FileSystem {
         self.inner.open(path.as_ref())
     }
 
+    pub fn open_read_write<P>(&self, path: P) -> std::io::Result<File>
+    where
+        P: AsRef<Path>,
+    {
+        self.inner.open_read_write(path.as_ref())
+    }
+
     pub fn exists<P>(&self, path: P) -> std::io::Result<bool>
     where
         P: AsRef<Path>,
@@ -93,6 +144,20 @@ impl FileSystem {
         self.inner.create_dir(path.as_ref())
     }
 
+    pub fn is_dir<P>(&self, path: P) -> std::io::Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        self.inner.is_dir(path.as_ref())
+    }
+
+    pub fn read_dir<P>(&self, path: P) -> std::io::Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        self.inner.read_dir(path.as_ref())
+    }
+
     pub fn r#move<P>(&self, from: P, to: P) -> std::io::Result<()>
     where
         P: AsRef<Path>,
@@ -120,6 +185,10 @@ impl FileBackend for FileSystem {
         self.open(path)
     }
 
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File> {
+        self.open_read_write(path)
+    }
+
     fn exists(&self, path: &Path) -> std::io::Result<bool> {
         self.exists(path)
     }
@@ -132,6 +201,14 @@ impl FileBackend for FileSystem {
         self.create_dir(path)
     }
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool> {
+        self.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.read_dir(path)
+    }
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()> {
         self.r#move(from, to)
     }
@@ -161,11 +238,26 @@ mod tests {
             .returning(|x| Err(Error::new(ErrorKind::Unsupported, "test-error")));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.open(path);
     }
 
+    #[test]
+    fn test_open_read_write() {
+        let path = Path::new("test.txt");
+        let mut mock = MockFileBackend::new();
+        mock.expect_open_read_write()
+            .with(eq(path))
+            .times(1)
+            .returning(|x| Err(Error::new(ErrorKind::Unsupported, "test-error")));
+
+        let fs = FileSystem {
+            inner: Arc::new(mock),
+        };
+        let _ = fs.open_read_write(path);
+    }
+
     #[test]
     fn test_create() {
         let path = Path::new("test.txt");
@@ -176,7 +268,7 @@ mod tests {
             .returning(|x| Err(Error::new(ErrorKind::Unsupported, "test-error")));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.create(path);
     }
@@ -191,7 +283,7 @@ mod tests {
             .returning(|x| Ok(()));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.create_dir(path);
     }
@@ -206,7 +298,7 @@ mod tests {
             .returning(|x| Ok(false));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.exists(path);
     }
@@ -222,7 +314,7 @@ mod tests {
             .returning(|x, y| Ok(()));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.r#move(old, new);
     }
@@ -237,7 +329,7 @@ mod tests {
             .returning(|x| Err(Error::new(ErrorKind::Unsupported, "test-error")));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.remove_file(path);
     }
@@ -252,7 +344,7 @@ mod tests {
             .returning(|x| Ok(()));
 
         let fs = FileSystem {
-            inner: Rc::new(mock),
+            inner: Arc::new(mock),
         };
         let _ = fs.remove_dir(path);
     }
@@ -1,6 +1,6 @@
 use std::cmp::min;
 use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use prefix_tree::PrefixMap;
@@ -26,16 +26,24 @@ impl InMemoryBackend {
 
     fn find_data(&self, path: &Path) -> Option<InMemFileData> {
         let guard = self.files.lock().unwrap();
-        let entry = match guard.get(path.to_str().unwrap()) {
-            None => return None,
-            Some(e) => e,
-        };
+        let entry = guard.get(path.to_str().unwrap())?;
         let file_entry = match entry {
             InMemFsEntry::File(e) => e,
             InMemFsEntry::Dir => return None,
         };
         Some(file_entry.clone())
     }
+
+    /// Returns the prefix that all keys of children of the given directory
+    /// path start with.
+    fn child_prefix(path: &Path) -> String {
+        let p = path.to_str().unwrap();
+        if p.is_empty() || p.ends_with('/') {
+            p.to_owned()
+        } else {
+            format!("{}/", p)
+        }
+    }
 }
 
 impl FileBackend for InMemoryBackend {
@@ -47,6 +55,11 @@ impl FileBackend for InMemoryBackend {
         Ok(File::new(Box::new(InMemFile::from(data))))
     }
 
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File> {
+        // in-memory files are always writable
+        self.open(path)
+    }
+
     fn exists(&self, path: &Path) -> std::io::Result<bool> {
         Ok(self
             .files
@@ -87,6 +100,46 @@ impl FileBackend for InMemoryBackend {
         Ok(())
     }
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool> {
+        let files = self.files.lock().unwrap();
+        match files.get(path.to_str().unwrap()) {
+            Some(InMemFsEntry::Dir) => Ok(true),
+            Some(InMemFsEntry::File(_)) => Ok(false),
+            None => {
+                // directories may exist implicitly if a file was created below them
+                let prefix = Self::child_prefix(path);
+                Ok(files
+                    .keys()
+                    .any(|k| k.starts_with(prefix.as_bytes()) && k.len() > prefix.len()))
+            }
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path)? {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                "the directory does not exist",
+            ));
+        }
+
+        let prefix = Self::child_prefix(path);
+        let files = self.files.lock().unwrap();
+        let mut entries: Vec<PathBuf> = vec![];
+        for key in files.keys() {
+            let key = String::from_utf8(key).unwrap();
+            let name = match key.strip_prefix(prefix.as_str()) {
+                Some(rest) if !rest.is_empty() => rest.split('/').next().unwrap(),
+                _ => continue,
+            };
+            let entry = path.join(name);
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()> {
         let mut files = self.files.lock().unwrap();
         let f = from.to_str().unwrap();
@@ -166,7 +219,7 @@ impl Read for InMemFile {
         let data = self.data.lock().unwrap();
         let ptr = self.pointer as usize;
         let buf_len = buf.len();
-        let to_read = min(buf_len, data.len() - ptr);
+        let to_read = min(buf_len, data.len().saturating_sub(ptr));
         buf[0..to_read].copy_from_slice(&data[ptr..ptr + to_read]);
         self.pointer += to_read as u64;
         Ok(to_read)
@@ -176,12 +229,14 @@ impl Read for InMemFile {
 impl Write for InMemFile {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let mut data = self.data.lock().unwrap();
-        let required_length = self.pointer as usize + buf.len();
-        let data_len = data.len();
-        if data_len < required_length {
-            data.reserve(required_length - data_len);
+        let ptr = self.pointer as usize;
+        let required_length = ptr + buf.len();
+        if data.len() < required_length {
+            // like a real file, seeking past the end and writing leaves a gap of zeros
+            data.resize(required_length, 0);
         }
-        buf.iter().for_each(|&b| data.push(b));
+        data[ptr..required_length].copy_from_slice(buf);
+        self.pointer = required_length as u64;
         Ok(buf.len())
     }
 
@@ -219,7 +274,7 @@ mod tests {
         let path = Path::new("test.txt");
         {
             let mut f = fs.create(path).unwrap();
-            writeln!(&mut f, "some number: {}", 7);
+            writeln!(&mut f, "some number: {}", 7).unwrap();
         }
         let mut f = fs.open(path).unwrap();
         let mut actual = String::new();
@@ -254,4 +309,24 @@ mod tests {
         assert!(!fs.exists(old_path).unwrap());
         assert!(fs.exists(new_path).unwrap());
     }
+
+    #[test]
+    fn test_write_at_position() {
+        let fs = InMemoryBackend::new();
+        let path = Path::new("test.txt");
+        {
+            let mut f = fs.create(path).unwrap();
+            f.write_all(b"hello world").unwrap();
+        }
+        {
+            let mut f = fs.open_read_write(path).unwrap();
+            f.seek(SeekFrom::Start(6)).unwrap();
+            f.write_all(b"there!").unwrap();
+            f.seek(SeekFrom::Start(14)).unwrap();
+            f.write_all(b"?").unwrap();
+        }
+        let mut actual = vec![];
+        fs.open(path).unwrap().read_to_end(&mut actual).unwrap();
+        assert_eq!(b"hello there!\0\0?".to_vec(), actual);
+    }
 }
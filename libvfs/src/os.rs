@@ -1,6 +1,6 @@
 use crate::file::File;
 use crate::FileBackend;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub(crate) struct OsFileBackend {}
 
@@ -16,6 +16,14 @@ impl FileBackend for OsFileBackend {
         Ok(File::new(Box::new(f)))
     }
 
+    fn open_read_write(&self, path: &Path) -> std::io::Result<File> {
+        let f = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        Ok(File::new(Box::new(f)))
+    }
+
     fn exists(&self, path: &Path) -> std::io::Result<bool> {
         let metadata = std::fs::metadata(path)?;
         Ok(metadata.is_dir() || metadata.is_file())
@@ -30,6 +38,16 @@ impl FileBackend for OsFileBackend {
         std::fs::create_dir(path)
     }
 
+    fn is_dir(&self, path: &Path) -> std::io::Result<bool> {
+        Ok(std::fs::metadata(path)?.is_dir())
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
     fn r#move(&self, from: &Path, to: &Path) -> std::io::Result<()> {
         std::fs::rename(from, to)
     }
@@ -0,0 +1,182 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::file::File;
+use crate::FileSystem;
+
+/// A path within a specific [`FileSystem`]. This allows to navigate the file system
+/// fluently, without having to pass the file system around next to the path.
+///
+/// ```
+/// # use libvfs::FileSystem;
+/// let fs = FileSystem::new_in_memory_fs();
+/// let classes = fs.path("classes");
+/// let object = classes.join("java/lang/Object.class");
+/// assert_eq!(classes, object.parent().unwrap().parent().unwrap().parent().unwrap());
+/// ```
+#[derive(Clone)]
+pub struct VfsPath {
+    fs: FileSystem,
+    path: PathBuf,
+}
+
+impl VfsPath {
+    pub fn new<P>(fs: FileSystem, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            fs,
+            path: PathBuf::from(path.as_ref()),
+        }
+    }
+
+    pub fn file_system(&self) -> &FileSystem {
+        &self.fs
+    }
+
+    pub fn as_path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.path.file_name().and_then(|n| n.to_str())
+    }
+
+    /// Creates a new path in the same file system, with the given path
+    /// appended to this one.
+    pub fn join<P>(&self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            fs: self.fs.clone(),
+            path: self.path.join(path),
+        }
+    }
+
+    /// Returns the parent of this path, or [`None`] if this path is a root
+    /// or empty.
+    pub fn parent(&self) -> Option<Self> {
+        self.path.parent().map(|p| Self {
+            fs: self.fs.clone(),
+            path: PathBuf::from(p),
+        })
+    }
+
+    pub fn exists(&self) -> std::io::Result<bool> {
+        self.fs.exists(&self.path)
+    }
+
+    pub fn is_dir(&self) -> std::io::Result<bool> {
+        self.fs.is_dir(&self.path)
+    }
+
+    pub fn open(&self) -> std::io::Result<File> {
+        self.fs.open(&self.path)
+    }
+
+    pub fn create(&self) -> std::io::Result<File> {
+        self.fs.create(&self.path)
+    }
+
+    pub fn create_dir(&self) -> std::io::Result<()> {
+        self.fs.create_dir(&self.path)
+    }
+
+    pub fn read_dir(&self) -> std::io::Result<Vec<Self>> {
+        Ok(self
+            .fs
+            .read_dir(&self.path)?
+            .into_iter()
+            .map(|p| Self {
+                fs: self.fs.clone(),
+                path: p,
+            })
+            .collect())
+    }
+
+    pub fn remove_file(&self) -> std::io::Result<()> {
+        self.fs.remove_file(&self.path)
+    }
+
+    pub fn remove_dir(&self) -> std::io::Result<()> {
+        self.fs.remove_dir(&self.path)
+    }
+}
+
+impl AsRef<Path> for VfsPath {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+/// Two paths are equal if they have the same path and point into the same
+/// file system instance (or a clone of it).
+impl PartialEq for VfsPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.fs.same_backend(&other.fs)
+    }
+}
+
+impl Eq for VfsPath {}
+
+impl std::fmt::Debug for VfsPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("VfsPath").field(&self.path).finish()
+    }
+}
+
+impl Display for VfsPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_join_parent() {
+        let fs = FileSystem::new_in_memory_fs();
+        let root = fs.path("root");
+        let file = root.join("sub").join("file.txt");
+        assert_eq!(Path::new("root/sub/file.txt"), file.as_path());
+        assert_eq!(Some("file.txt"), file.file_name());
+        assert_eq!(root.join("sub"), file.parent().unwrap());
+    }
+
+    #[test]
+    fn test_different_file_systems_not_equal() {
+        let a = FileSystem::new_in_memory_fs();
+        let b = FileSystem::new_in_memory_fs();
+        assert_eq!(a.path("x"), a.clone().path("x"));
+        assert_ne!(a.path("x"), b.path("x"));
+    }
+
+    #[test]
+    fn test_create_open_read_dir() {
+        let fs = FileSystem::new_in_memory_fs();
+        let root = fs.path("root");
+        root.create_dir().unwrap();
+        {
+            let mut f = root.join("file.txt").create().unwrap();
+            write!(&mut f, "content").unwrap();
+        }
+
+        let entries = root.read_dir().unwrap();
+        assert_eq!(vec![root.join("file.txt")], entries);
+        assert!(entries[0].exists().unwrap());
+        assert!(!entries[0].is_dir().unwrap());
+
+        let mut content = String::new();
+        entries[0]
+            .open()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!("content", content);
+    }
+}
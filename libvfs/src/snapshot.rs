@@ -0,0 +1,180 @@
+use std::io::{copy, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::FileSystem;
+
+const MAGIC: &[u8; 4] = b"RVFS";
+const VERSION: u8 = 1;
+
+const TAG_DIR: u8 = 0;
+const TAG_FILE: u8 = 1;
+const TAG_END: u8 = 0xFF;
+
+/// Writes the directory tree at `root` of the given file system into a compact
+/// archive. The archive can be restored with [`read_snapshot`], which is much faster
+/// than re-populating an in-memory file system (e.g. with extracted JDK classes) from
+/// scratch in every run or test.
+///
+/// The archive consists of a header (`RVFS` and a version byte), followed by one
+/// record per directory or file, and an end marker. All paths in the archive are
+/// relative to `root`. Numbers are stored big endian.
+pub fn write_snapshot<P>(fs: &FileSystem, root: P, sink: &mut impl Write) -> std::io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let root = root.as_ref();
+    sink.write_all(MAGIC)?;
+    sink.write_all(&[VERSION])?;
+    for child in fs.read_dir(root)? {
+        write_entry(fs, root, &child, sink)?;
+    }
+    sink.write_all(&[TAG_END])
+}
+
+/// Restores an archive written by [`write_snapshot`] into a new in-memory file
+/// system.
+pub fn read_snapshot(source: &mut impl Read) -> std::io::Result<FileSystem> {
+    let mut magic = [0_u8; 4];
+    source.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not a snapshot archive"));
+    }
+    let version = read_u8(source)?;
+    if version != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "unsupported snapshot version",
+        ));
+    }
+
+    let fs = FileSystem::new_in_memory_fs();
+    loop {
+        match read_u8(source)? {
+            TAG_DIR => {
+                let path = read_path(source)?;
+                fs.create_dir(path)?;
+            }
+            TAG_FILE => {
+                let path = read_path(source)?;
+                let len = u64::from_be_bytes(read_array(source)?);
+                let mut f = fs.create(path)?;
+                let copied = copy(&mut source.take(len), &mut f)?;
+                if copied != len {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "snapshot file content is truncated",
+                    ));
+                }
+            }
+            TAG_END => return Ok(fs),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "invalid entry tag")),
+        }
+    }
+}
+
+fn write_entry(
+    fs: &FileSystem,
+    root: &Path,
+    path: &Path,
+    sink: &mut impl Write,
+) -> std::io::Result<()> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    if fs.is_dir(path)? {
+        sink.write_all(&[TAG_DIR])?;
+        write_path(relative, sink)?;
+        for child in fs.read_dir(path)? {
+            write_entry(fs, root, &child, sink)?;
+        }
+    } else {
+        let mut content = vec![];
+        fs.open(path)?.read_to_end(&mut content)?;
+
+        sink.write_all(&[TAG_FILE])?;
+        write_path(relative, sink)?;
+        sink.write_all(&(content.len() as u64).to_be_bytes())?;
+        sink.write_all(&content)?;
+    }
+    Ok(())
+}
+
+fn write_path(path: &Path, sink: &mut impl Write) -> std::io::Result<()> {
+    let p = path
+        .to_str()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path is not valid utf8"))?;
+    if p.len() > u16::MAX as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "path is too long"));
+    }
+    sink.write_all(&(p.len() as u16).to_be_bytes())?;
+    sink.write_all(p.as_bytes())
+}
+
+fn read_path(source: &mut impl Read) -> std::io::Result<PathBuf> {
+    let len = u16::from_be_bytes(read_array(source)?);
+    let mut bytes = vec![0_u8; len as usize];
+    source.read_exact(&mut bytes)?;
+    String::from_utf8(bytes)
+        .map(PathBuf::from)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "path is not valid utf8"))
+}
+
+fn read_u8(source: &mut impl Read) -> std::io::Result<u8> {
+    Ok(read_array::<1>(source)?[0])
+}
+
+fn read_array<const N: usize>(source: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0_u8; N];
+    source.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let fs = FileSystem::new_in_memory_fs();
+        fs.create_dir("java").unwrap();
+        fs.create_dir("java/lang").unwrap();
+        fs.create_dir("empty").unwrap();
+        {
+            let mut f = fs.create("java/lang/Object.class").unwrap();
+            f.write_all(&[0xCA, 0xFE, 0xBA, 0xBE]).unwrap();
+        }
+
+        let mut archive = vec![];
+        write_snapshot(&fs, "", &mut archive).unwrap();
+        let restored = read_snapshot(&mut archive.as_slice()).unwrap();
+
+        assert!(restored.is_dir("empty").unwrap());
+        assert!(restored.is_dir("java/lang").unwrap());
+        let mut content = vec![];
+        restored
+            .open("java/lang/Object.class")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(vec![0xCA, 0xFE, 0xBA, 0xBE], content);
+    }
+
+    #[test]
+    fn test_read_snapshot_invalid_magic() {
+        let archive = b"NOPE\x01\xFF";
+        let err = read_snapshot(&mut archive.as_slice()).err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn test_read_snapshot_truncated() {
+        let fs = FileSystem::new_in_memory_fs();
+        {
+            let mut f = fs.create("file").unwrap();
+            f.write_all(b"content").unwrap();
+        }
+        let mut archive = vec![];
+        write_snapshot(&fs, "", &mut archive).unwrap();
+        archive.truncate(archive.len() - 4);
+
+        assert!(read_snapshot(&mut archive.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::{copy, Read};
+use std::path::Path;
+
+use crate::FileSystem;
+
+/// Summary of the work done by a [`sync`].
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+    /// Files that were written to the destination, because they were missing
+    /// or their content differed.
+    pub copied: usize,
+    /// Files that already existed in the destination with identical content.
+    pub unchanged: usize,
+    /// Files and directories that were removed from the destination, because
+    /// they don't exist in the source.
+    pub removed: usize,
+}
+
+/// Mirrors the directory tree (or single file) at `path` from one file system into
+/// another. Afterwards, the tree in `to_fs` has the same directories and files with
+/// the same content as the one in `from_fs`.
+///
+/// Files are streamed rather than read into memory, and files that already exist in
+/// the destination are only rewritten if their length or content hash differs.
+///
+/// This can be used to snapshot an in-memory layer to disk, or to seed an in-memory
+/// file system from an OS directory.
+pub fn sync<P>(from_fs: &FileSystem, to_fs: &FileSystem, path: P) -> std::io::Result<SyncReport>
+where
+    P: AsRef<Path>,
+{
+    let mut report = SyncReport::default();
+    sync_entry(from_fs, to_fs, path.as_ref(), &mut report)?;
+    Ok(report)
+}
+
+fn sync_entry(
+    from_fs: &FileSystem,
+    to_fs: &FileSystem,
+    path: &Path,
+    report: &mut SyncReport,
+) -> std::io::Result<()> {
+    if from_fs.is_dir(path)? {
+        sync_dir(from_fs, to_fs, path, report)
+    } else {
+        sync_file(from_fs, to_fs, path, report)
+    }
+}
+
+fn sync_dir(
+    from_fs: &FileSystem,
+    to_fs: &FileSystem,
+    path: &Path,
+    report: &mut SyncReport,
+) -> std::io::Result<()> {
+    if exists(to_fs, path)? && !to_fs.is_dir(path)? {
+        to_fs.remove_file(path)?;
+        report.removed += 1;
+    }
+    if !exists(to_fs, path)? {
+        to_fs.create_dir(path)?;
+    }
+
+    let children = from_fs.read_dir(path)?;
+    for stale in to_fs.read_dir(path)? {
+        if !children.contains(&stale) {
+            remove_all(to_fs, &stale)?;
+            report.removed += 1;
+        }
+    }
+    for child in children {
+        sync_entry(from_fs, to_fs, &child, report)?;
+    }
+    Ok(())
+}
+
+fn sync_file(
+    from_fs: &FileSystem,
+    to_fs: &FileSystem,
+    path: &Path,
+    report: &mut SyncReport,
+) -> std::io::Result<()> {
+    if exists(to_fs, path)? {
+        if to_fs.is_dir(path)? {
+            remove_all(to_fs, path)?;
+            report.removed += 1;
+        } else if fingerprint(from_fs, path)? == fingerprint(to_fs, path)? {
+            report.unchanged += 1;
+            return Ok(());
+        } else {
+            to_fs.remove_file(path)?;
+        }
+    }
+
+    let mut source = from_fs.open(path)?;
+    let mut destination = to_fs.create(path)?;
+    copy(&mut source, &mut destination)?;
+    report.copied += 1;
+    Ok(())
+}
+
+/// Removes the file or directory tree at the given path.
+fn remove_all(fs: &FileSystem, path: &Path) -> std::io::Result<()> {
+    if fs.is_dir(path)? {
+        for child in fs.read_dir(path)? {
+            remove_all(fs, &child)?;
+        }
+        // implicit directories of the in-memory backend vanish with their last child
+        if exists(fs, path)? {
+            fs.remove_dir(path)?;
+        }
+        Ok(())
+    } else {
+        fs.remove_file(path)
+    }
+}
+
+/// Returns the length and a hash of the content of the file at the given path.
+fn fingerprint(fs: &FileSystem, path: &Path) -> std::io::Result<(u64, u64)> {
+    let mut f = fs.open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut len = 0_u64;
+    let mut buf = [0_u8; 8192];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.write(&buf[..n]);
+        len += n as u64;
+    }
+    Ok((len, hasher.finish()))
+}
+
+/// Like [`FileSystem::exists`], but treats a missing path as `false` instead of an
+/// error, which is what the OS backend reports.
+fn exists(fs: &FileSystem, path: &Path) -> std::io::Result<bool> {
+    match fs.exists(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        r => r,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_file(fs: &FileSystem, path: &str, content: &str) {
+        let mut f = fs.create(path).unwrap();
+        write!(&mut f, "{}", content).unwrap();
+    }
+
+    fn read_file(fs: &FileSystem, path: &str) -> String {
+        let mut content = String::new();
+        fs.open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_sync_tree() {
+        let from = FileSystem::new_in_memory_fs();
+        from.create_dir("root").unwrap();
+        from.create_dir("root/sub").unwrap();
+        write_file(&from, "root/a.txt", "a");
+        write_file(&from, "root/sub/b.txt", "b");
+
+        let to = FileSystem::new_in_memory_fs();
+        let report = sync(&from, &to, "root").unwrap();
+        assert_eq!(2, report.copied);
+        assert!(to.is_dir("root/sub").unwrap());
+        assert_eq!("a", read_file(&to, "root/a.txt"));
+        assert_eq!("b", read_file(&to, "root/sub/b.txt"));
+    }
+
+    #[test]
+    fn test_sync_detects_changes() {
+        let from = FileSystem::new_in_memory_fs();
+        from.create_dir("root").unwrap();
+        write_file(&from, "root/a.txt", "a");
+        write_file(&from, "root/b.txt", "b");
+
+        let to = FileSystem::new_in_memory_fs();
+        sync(&from, &to, "root").unwrap();
+
+        from.remove_file("root/b.txt").unwrap();
+        write_file(&from, "root/b.txt", "changed");
+        write_file(&to, "root/stale.txt", "stale");
+
+        let report = sync(&from, &to, "root").unwrap();
+        assert_eq!(
+            SyncReport {
+                copied: 1,
+                unchanged: 1,
+                removed: 1,
+            },
+            report
+        );
+        assert_eq!("changed", read_file(&to, "root/b.txt"));
+        assert!(!to.exists("root/stale.txt").unwrap());
+    }
+}
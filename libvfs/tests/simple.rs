@@ -1,5 +1,6 @@
+use libvfs::sync::sync;
 use libvfs::FileSystem;
-use std::io::Read;
+use std::io::{Read, Write};
 
 #[test]
 fn test_os_fs() {
@@ -18,3 +19,34 @@ fn test_base_path_fs() {
     f.read_to_string(&mut content).unwrap();
     assert_eq!("hello world", content);
 }
+
+#[test]
+fn test_cloned_fs_shares_backend() {
+    let fs = FileSystem::new_in_memory_fs();
+    let clone = fs.clone();
+    {
+        let mut f = clone.create("file1").unwrap();
+        write!(&mut f, "hello world").unwrap();
+    }
+
+    let handle = std::thread::spawn(move || {
+        let mut f = fs.open("file1").unwrap();
+        let mut content = String::new();
+        f.read_to_string(&mut content).unwrap();
+        content
+    });
+    assert_eq!("hello world", handle.join().unwrap());
+}
+
+#[test]
+fn test_sync_os_fs_into_memory() {
+    let os = FileSystem::new_base_path_fs(FileSystem::new_os_fs(), "tests");
+    let mem = FileSystem::new_in_memory_fs();
+    let report = sync(&os, &mem, "resources").unwrap();
+    assert_eq!(1, report.copied);
+
+    let mut f = mem.open("resources/file1").unwrap();
+    let mut content = String::new();
+    f.read_to_string(&mut content).unwrap();
+    assert_eq!("hello world", content);
+}
@@ -0,0 +1,64 @@
+//! A JVM that can be embedded into Rust programs.
+//!
+//! This crate is the public API of the workspace. It re-exports the parts of
+//! `libjava`, `libjvm` and `libvfs` that programs embedding the VM need:
+//! creating and configuring a [`VM`], running Java code in it, reading and
+//! writing class files with [`classfile`] and the file system that the VM
+//! sees with [`fs`].
+//!
+//! Everything that is reachable from this crate follows semantic versioning.
+//! The `lib*` crates don't, their APIs change whenever the implementation
+//! does, so programs should depend on this crate instead of them. Items that
+//! aren't re-exported here aren't part of the API, even if they are public in
+//! one of the `lib*` crates.
+//!
+//! ```no_run
+//! use rjvm::{ClassPath, VM};
+//!
+//! let vm = VM::builder()
+//!     .class_path(ClassPath::from(vec!["classes".into()]))
+//!     .build()
+//!     .unwrap();
+//! std::process::exit(vm.run_main_class("com/example/Main"));
+//! ```
+//!
+//! # Features
+//!
+//! - `net` and `process` (default): socket and process natives, see
+//!   [`Runtime`]. Without them, Java code can't open sockets or start
+//!   programs at all.
+//! - `serde`: `Serialize` and `Deserialize` for [`classfile::ClassFile`].
+//! - `stack-guards`: the checks of the operand stack and the local variables
+//!   in release builds, see [`VmBuilder::stack_guards`]. Debug builds always
+//!   have them.
+
+pub use libjvm::vm::builder::VmBuilder;
+pub use libjvm::vm::classloader::classpath::{ClassPath, ClassPathEntry};
+pub use libjvm::vm::error::{Exception, LinkageError, VmError};
+pub use libjvm::vm::module::ModuleMode;
+pub use libjvm::vm::runtime::Runtime;
+pub use libjvm::vm::scheduler::SchedulerMode;
+pub use libjvm::vm::template::VmTemplate;
+pub use libjvm::vm::types::NativeValue;
+pub use libjvm::vm::VM;
+
+/// Reading, writing and inspecting class files ([`$4`]) without a VM.
+///
+/// [`$4`]: https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html
+pub mod classfile {
+    pub use libjava::classfile::flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFlags};
+    pub use libjava::classfile::visitor::{ClassVisitor, ClassWriter, FieldVisitor, MethodVisitor};
+    pub use libjava::classfile::{
+        AttributeDiff, AttributeInfo, ClassDiff, ClassFile, ClassFileError, ConstantPool,
+        ConstantPoolError, ConstantPoolInfo, FieldInfo, FormatViolation, MemberDiff, MethodInfo,
+        ModuleDescriptor, ParseLimit, ParseMode, ParseOptions, Version,
+    };
+}
+
+/// The file system that the VM loads classes from and that Java code reads
+/// and writes files in, which can be the one of the host, one in memory or
+/// one layered on top of another.
+pub mod fs {
+    pub use libvfs::file::File;
+    pub use libvfs::FileSystem;
+}